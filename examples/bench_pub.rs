@@ -0,0 +1,213 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Throughput and round-trip latency benchmark.
+//!
+//! Start the echo side first, then sweep payload sizes, send priorities
+//! and queue depths; one CSV row is written per combination:
+//!
+//! ```bash
+//! cargo run --release --example bench_sub
+//! cargo run --release --example bench_pub -- --count 20000 --out bench.csv
+//! ```
+//!
+//! Options (comma separated lists):
+//!
+//! * `--sizes 0,16,64,128,240` payload bytes per message
+//! * `--prios 0,10` `mq_send` priority of the ping leg
+//! * `--depths 1,4,10` queue `maxmsg`, must match `bench_sub`
+//! * `--count 10000` messages per throughput run
+//! * `--samples 1000` probes per latency run
+//! * `--out FILE` write the CSV there instead of stdout
+//!
+//! Throughput counts full round trips, so it measures both legs plus the
+//! echo's dispatch; latency probes are sent one at a time.
+
+use mq_ipc::{MqTopic, Msg, MSG_PAYLOAD_SIZE};
+use std::{
+    env,
+    fs::File,
+    io::{self, Write},
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+const MSG_TYPE_BULK: u16 = 0x8000;
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Config {
+    sizes: Vec<usize>,
+    prios: Vec<u32>,
+    depths: Vec<i64>,
+    count: usize,
+    samples: usize,
+    out: Option<String>,
+}
+
+struct Row {
+    size: usize,
+    prio: u32,
+    depth: i64,
+    msgs_per_sec: f64,
+    rtt_us: [f64; 4],
+}
+
+fn parse_list<T: FromStr>(s: &str) -> Result<Vec<T>, Box<dyn std::error::Error>>
+where
+    T::Err: std::error::Error + 'static,
+{
+    Ok(s.split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()?)
+}
+
+fn parse_args() -> Result<Config, Box<dyn std::error::Error>> {
+    let mut cfg = Config {
+        sizes: vec![0, 16, 64, 128, MSG_PAYLOAD_SIZE],
+        prios: vec![0, 10],
+        depths: vec![1, 4, 10],
+        count: 10_000,
+        samples: 1_000,
+        out: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--sizes" => cfg.sizes = parse_list(&value)?,
+            "--prios" => cfg.prios = parse_list(&value)?,
+            "--depths" => cfg.depths = parse_list(&value)?,
+            "--count" => cfg.count = value.parse()?,
+            "--samples" => cfg.samples = value.parse()?,
+            "--out" => cfg.out = Some(value),
+            other => return Err(format!("unknown argument {other}").into()),
+        }
+    }
+
+    if let Some(size) = cfg.sizes.iter().find(|s| **s > MSG_PAYLOAD_SIZE) {
+        return Err(format!("payload {size} exceeds {MSG_PAYLOAD_SIZE} bytes").into());
+    }
+    Ok(cfg)
+}
+
+fn reply(rx: &Receiver<Msg>) -> io::Result<Msg> {
+    rx.recv_timeout(REPLY_TIMEOUT).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            "no echo received; is bench_sub running with the same --depths?",
+        )
+    })
+}
+
+fn throughput(
+    ping: &MqTopic,
+    rx: &Receiver<Msg>,
+    msg: &Msg,
+    prio: u32,
+    count: usize,
+) -> io::Result<f64> {
+    let start = Instant::now();
+    for _ in 0..count {
+        ping.publish(msg, prio)?;
+    }
+    for _ in 0..count {
+        reply(rx)?;
+    }
+    Ok(count as f64 / start.elapsed().as_secs_f64())
+}
+
+fn latency(
+    ping: &MqTopic,
+    rx: &Receiver<Msg>,
+    payload: &[u8],
+    prio: u32,
+    samples: usize,
+) -> io::Result<[f64; 4]> {
+    let mut rtts = Vec::with_capacity(samples);
+    for i in 0..samples {
+        // The probe number rides in msg_type so stale replies are skipped.
+        let seq = (i % MSG_TYPE_BULK as usize) as u16;
+        let probe = Msg::new(seq, payload);
+
+        let start = Instant::now();
+        ping.publish(&probe, prio)?;
+        while reply(rx)?.hdr.msg_type != seq {}
+        rtts.push(start.elapsed().as_secs_f64() * 1e6);
+    }
+
+    if rtts.is_empty() {
+        return Ok([0.0; 4]);
+    }
+    rtts.sort_by(f64::total_cmp);
+    let pct = |p: f64| rtts[((rtts.len() - 1) as f64 * p).round() as usize];
+    Ok([rtts[0], pct(0.50), pct(0.99), rtts[rtts.len() - 1]])
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cfg = parse_args()?;
+    let mut out: Box<dyn Write> = match &cfg.out {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    writeln!(
+        out,
+        "payload,prio,maxmsg,messages,msgs_per_sec,rtt_min_us,rtt_p50_us,rtt_p99_us,rtt_max_us"
+    )?;
+
+    let payload = [0xA5u8; MSG_PAYLOAD_SIZE];
+    for &depth in &cfg.depths {
+        let ping = MqTopic::new(&format!("/mq_ipc_bench.ping.d{depth}"), depth)?;
+        let pong = MqTopic::new(&format!("/mq_ipc_bench.pong.d{depth}"), depth)?;
+        let (tx, rx) = mpsc::channel();
+        pong.subscribe(move |msg| {
+            let _ = tx.send(msg);
+        });
+
+        for &size in &cfg.sizes {
+            for &prio in &cfg.prios {
+                eprintln!("bench_pub: payload={size} prio={prio} maxmsg={depth}");
+                let bulk = Msg::new(MSG_TYPE_BULK, &payload[..size]);
+                let row = Row {
+                    size,
+                    prio,
+                    depth,
+                    msgs_per_sec: throughput(&ping, &rx, &bulk, prio, cfg.count)?,
+                    rtt_us: latency(&ping, &rx, &payload[..size], prio, cfg.samples)?,
+                };
+
+                let [min, p50, p99, max] = row.rtt_us;
+                writeln!(
+                    out,
+                    "{},{},{},{},{:.0},{min:.1},{p50:.1},{p99:.1},{max:.1}",
+                    row.size, row.prio, row.depth, cfg.count, row.msgs_per_sec
+                )?;
+                out.flush()?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Echo side of the benchmark: every message received on a ping queue is
+//! published back, unchanged, on the matching pong queue.
+//!
+//! ```bash
+//! cargo run --release --example bench_sub -- --depths 1,4,10
+//! ```
+//!
+//! The depth list must match the one given to `bench_pub`; each depth
+//! gets its own pair of queues because `maxmsg` is fixed at creation.
+
+use mq_ipc::{shutdown, MqTopic};
+use std::{env, sync::Arc};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut depths: Vec<i64> = vec![1, 4, 10];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depths" => {
+                let list = args.next().ok_or("--depths needs a value")?;
+                depths = list
+                    .split(',')
+                    .map(|d| d.trim().parse())
+                    .collect::<Result<_, _>>()?;
+            }
+            other => return Err(format!("unknown argument {other}").into()),
+        }
+    }
+
+    // Keep the topics alive for as long as the process runs.
+    let mut topics = Vec::new();
+    for depth in depths {
+        let ping = MqTopic::new(&format!("/mq_ipc_bench.ping.d{depth}"), depth)?;
+        let pong = Arc::new(MqTopic::new(
+            &format!("/mq_ipc_bench.pong.d{depth}"),
+            depth,
+        )?);
+
+        let echo = Arc::clone(&pong);
+        ping.subscribe(move |msg| {
+            if let Err(err) = echo.publish(&msg, 0) {
+                eprintln!("bench_sub: echo failed: {err}");
+            }
+        });
+        topics.push((ping, pong));
+    }
+
+    eprintln!("bench_sub: echoing {} queue pair(s)", topics.len());
+    shutdown::spin()?;
+    eprintln!("bench_sub: shutting down");
+    Ok(())
+}
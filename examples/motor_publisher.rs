@@ -22,6 +22,7 @@ THE SOFTWARE.
 */
 
 use bytemuck::{Pod, Zeroable};
+use mq_ipc::shutdown;
 use mq_ipc::wire::WireTx;
 use std::{io, thread, time::Duration};
 
@@ -40,12 +41,13 @@ fn main() -> io::Result<()> {
     // - internal TX topic: "/ipc_tx" (inside the lib)
     let motor = WireTx::<MotorState>::new("/example_motor_state", 4)?;
 
+    shutdown::install()?;
     println!("motor_publisher started (local + mirrored to /ipc_tx).");
 
     let mut angle: f32 = 0.0;
     let mut vel: f32 = 1.0;
 
-    loop {
+    while !shutdown::requested() {
         angle += 0.1;
         vel += 0.05;
 
@@ -63,4 +65,7 @@ fn main() -> io::Result<()> {
 
         thread::sleep(Duration::from_millis(10));
     }
+
+    println!("motor_publisher: shutting down");
+    Ok(())
 }
@@ -21,7 +21,7 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 THE SOFTWARE.
 */
 
-use mq_ipc::Topic;
+use mq_ipc::{shutdown, Topic};
 use bytemuck::{Pod, Zeroable};
 
 #[repr(C)]
@@ -45,7 +45,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     });
 
-    loop {
-        std::thread::sleep(std::time::Duration::from_secs(1));
-    }
+    shutdown::spin()?;
+    println!("motor_subscriber: shutting down");
+    Ok(())
 }
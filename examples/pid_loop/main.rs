@@ -0,0 +1,131 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! A PID position loop split over four nodes: setpoint publisher,
+//! controller, plant simulator and monitor. See `nodes.rs` for the
+//! topics between them.
+//!
+//! Run everything in one process:
+//!
+//! ```bash
+//! cargo run --example pid_loop
+//! ```
+//!
+//! or one node per terminal, in any order, to watch the deadline and
+//! watchdog react when one of them is stopped and restarted:
+//!
+//! ```bash
+//! cargo run --example pid_loop -- plant
+//! cargo run --example pid_loop -- controller
+//! cargo run --example pid_loop -- setpoint
+//! cargo run --example pid_loop -- monitor
+//! cargo run --example pid_loop -- tune 8 0.5 4
+//! ```
+
+mod nodes;
+
+use mq_ipc::shutdown;
+use nodes::{Gains, Names};
+use std::{
+    io,
+    sync::{atomic::AtomicBool, Arc},
+    thread,
+    time::Duration,
+};
+
+const PREFIX: &str = "/pid_loop";
+
+fn usage() -> ! {
+    eprintln!("usage: pid_loop [all|setpoint|controller|plant|monitor|tune KP KI KD]");
+    std::process::exit(2);
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let role = args.first().map_or("all", String::as_str);
+    let names = Names::new(PREFIX);
+
+    if role == "tune" {
+        let gain = |i: usize| -> f32 {
+            args.get(i)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_else(|| usage())
+        };
+        let gains = Gains {
+            kp: gain(1),
+            ki: gain(2),
+            kd: gain(3),
+        };
+        println!("{:?}", nodes::tune(&names, gains)?);
+        return Ok(());
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let run = |role: &'static str| {
+        let names = names.clone();
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || -> io::Result<()> {
+            match role {
+                "setpoint" => {
+                    nodes::run_setpoint(&names, &[1.0, -0.5], Duration::from_secs(4), &stop)
+                }
+                "controller" => nodes::run_controller(&names, Gains::DEFAULT, &stop),
+                "plant" => {
+                    let report = nodes::run_plant(&names, &stop)?;
+                    println!(
+                        "plant: stopped at position {:.3}, effort {:.3}, {} e-stop(s)",
+                        report.state.position, report.effort, report.estops
+                    );
+                    Ok(())
+                }
+                _ => nodes::run_monitor(&names, Duration::from_millis(250), &stop).map(drop),
+            }
+        })
+    };
+
+    let handles: Vec<_> = match role {
+        "all" => ["plant", "controller", "setpoint", "monitor"]
+            .into_iter()
+            .map(run)
+            .collect(),
+        "setpoint" => vec![run("setpoint")],
+        "controller" => vec![run("controller")],
+        "plant" => vec![run("plant")],
+        "monitor" => vec![run("monitor")],
+        _ => usage(),
+    };
+
+    shutdown::spin()?;
+    stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    for handle in handles {
+        if let Err(err) = handle.join().expect("node panicked") {
+            eprintln!("pid_loop: {err}");
+        }
+    }
+    if role == "all" {
+        for name in nodes::queue_names(&names) {
+            mq_ipc::cleanup::unlink(&name)?;
+        }
+    }
+    Ok(())
+}
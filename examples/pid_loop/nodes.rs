@@ -0,0 +1,413 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Nodes of the `pid_loop` example, shared with `tests/pid_loop.rs`.
+//!
+//! ```text
+//!  setpoint ──{p}_setpoint──▶ controller ──{p}_command──▶ plant
+//!                              ▲    │                      │ ▲
+//!                              │    └──{p}_heartbeat───────┘ │ (watchdog)
+//!                              └─────────{p}_state───────────┘
+//!  monitor ──{p}_tune (request/reply)──▶ controller
+//! ```
+//!
+//! * The plant integrates a damped mass under the commanded effort and
+//!   publishes its state as a keyed, timestamped sample.
+//! * The controller runs one PID step per state sample. A state older
+//!   than [`STATE_DEADLINE`] is a missed deadline: the effort drops to
+//!   zero until the plant is heard from again.
+//! * The plant supervises the controller's heartbeat with a watchdog that
+//!   publishes a zero effort at urgent priority when it stops.
+//! * The monitor polls, and can retune, the controller over a service.
+
+use bytemuck::{Pod, Zeroable};
+use mq_ipc::{
+    clock,
+    keyed::KeyedTopic,
+    rpc::{Client, Server},
+    shutdown,
+    stamped::Stamped,
+    watchdog::{Watchdog, WatchdogOptions},
+    MqTopic, Msg, Qos, Topic, TopicOptions,
+};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const MSG_TYPE_SETPOINT: u16 = 1;
+pub const MSG_TYPE_COMMAND: u16 = 2;
+pub const MSG_TYPE_STATE: u16 = 3;
+pub const MSG_TYPE_HEARTBEAT: u16 = 4;
+
+/// Instance key of the single simulated axis on the state topic.
+pub const AXIS: u64 = 0;
+
+/// Plant integration and publish period.
+pub const PLANT_PERIOD: Duration = Duration::from_millis(10);
+/// How long the controller acts on a plant state before calling it stale.
+pub const STATE_DEADLINE: Duration = Duration::from_millis(50);
+/// Longest gap between controller heartbeats the plant tolerates.
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Largest effort the controller commands, either way.
+const EFFORT_LIMIT: f32 = 20.0;
+/// Viscous damping of the simulated plant.
+const DAMPING: f32 = 1.0;
+
+/// Topic names of one loop instance.
+#[derive(Clone, Debug)]
+pub struct Names {
+    prefix: String,
+}
+
+impl Names {
+    /// Names below `prefix`, e.g. `"/pid"` for `/pid_state`.
+    pub fn new(prefix: &str) -> Self {
+        Names {
+            prefix: prefix.to_string(),
+        }
+    }
+
+    pub fn setpoint(&self) -> String {
+        format!("{}_setpoint", self.prefix)
+    }
+
+    pub fn state(&self) -> String {
+        format!("{}_state", self.prefix)
+    }
+
+    pub fn command(&self) -> String {
+        format!("{}_command", self.prefix)
+    }
+
+    pub fn heartbeat(&self) -> String {
+        format!("{}_heartbeat", self.prefix)
+    }
+
+    pub fn tune(&self) -> String {
+        format!("{}_tune", self.prefix)
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PlantState {
+    pub position: f32,
+    pub velocity: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct Gains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+impl Gains {
+    /// Close to critically damped for the simulated plant.
+    pub const DEFAULT: Gains = Gains {
+        kp: 16.0,
+        ki: 1.0,
+        kd: 7.0,
+    };
+}
+
+/// Request to the tune service: read the status, or set new gains first.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct TuneRequest {
+    pub gains: Gains,
+    /// Non-zero to apply `gains`.
+    pub set: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ControllerStatus {
+    pub gains: Gains,
+    pub setpoint: f32,
+    pub error: f32,
+    pub effort: f32,
+    /// Control steps run so far.
+    pub cycles: u32,
+    /// Times the plant state went stale.
+    pub deadline_misses: u32,
+}
+
+/// Publish each of `targets` for `hold`, cycling until `stop` is set.
+pub fn run_setpoint(
+    names: &Names,
+    targets: &[f32],
+    hold: Duration,
+    stop: &AtomicBool,
+) -> io::Result<()> {
+    // Keep-latest: a controller started later still finds the current
+    // target queued.
+    let topic = Topic::<f32>::with_options(&names.setpoint(), &latest())?;
+    for target in targets.iter().cycle() {
+        topic.publish(target, MSG_TYPE_SETPOINT, 0)?;
+        println!("setpoint: {target:.2}");
+        if sleep_unless(stop, hold) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Pid {
+    status: ControllerStatus,
+    integral: f32,
+    last: Option<Stamped<PlantState>>,
+}
+
+impl Pid {
+    /// One step on a new state sample; returns the effort to command.
+    fn step(&mut self, sample: Stamped<PlantState>) -> f32 {
+        let state = sample.value();
+        let s = &mut self.status;
+        let error = s.setpoint - state.position;
+        // Derivative on the measurement, so setpoint steps do not kick.
+        let (dt, rate) = match self.last {
+            Some(last) if sample.stamp_ns > last.stamp_ns => {
+                let dt = sample.dt(&last).as_secs_f32();
+                (dt, (state.position - last.value().position) / dt)
+            }
+            _ => (0.0, state.velocity),
+        };
+        self.integral += error * dt;
+        let g = s.gains;
+        let effort =
+            (g.kp * error + g.ki * self.integral - g.kd * rate).clamp(-EFFORT_LIMIT, EFFORT_LIMIT);
+
+        self.last = Some(sample);
+        s.error = error;
+        s.effort = effort;
+        s.cycles += 1;
+        effort
+    }
+
+    fn miss(&mut self) {
+        self.status.deadline_misses += 1;
+        self.status.effort = 0.0;
+        self.integral = 0.0;
+        self.last = None;
+    }
+}
+
+/// Run the controller until `stop` is set.
+pub fn run_controller(names: &Names, gains: Gains, stop: &AtomicBool) -> io::Result<()> {
+    let pid = Arc::new(Mutex::new(Pid {
+        status: ControllerStatus {
+            gains,
+            ..Default::default()
+        },
+        integral: 0.0,
+        last: None,
+    }));
+    let command = Arc::new(Topic::<f32>::with_options(&names.command(), &latest())?);
+    // Best effort: a missing watchdog must not stall the control step.
+    let heartbeat = MqTopic::with_options(
+        &names.heartbeat(),
+        &TopicOptions::new(8).qos(Qos::BestEffort),
+    )?;
+
+    let setpoint = Topic::<f32>::with_options(&names.setpoint(), &latest())?;
+    {
+        let pid = Arc::clone(&pid);
+        setpoint.subscribe(move |target| lock(&pid).status.setpoint = target);
+    }
+
+    let state = KeyedTopic::<Stamped<PlantState>>::with_options(&names.state(), &latest())?;
+    {
+        let pid = Arc::clone(&pid);
+        let command = Arc::clone(&command);
+        state.subscribe(move |_axis, sample| {
+            let effort = lock(&pid).step(sample);
+            if let Err(err) = command.publish(&effort, MSG_TYPE_COMMAND, 0) {
+                eprintln!("controller: command failed: {err}");
+            }
+            let _ = heartbeat.publish(&Msg::new(MSG_TYPE_HEARTBEAT, &[]), 0);
+        });
+    }
+    {
+        let pid = Arc::clone(&pid);
+        let command = Arc::clone(&command);
+        state.on_deadline(STATE_DEADLINE, move |axis| {
+            eprintln!("controller: state of axis {axis} is stale, holding zero effort");
+            lock(&pid).miss();
+            let _ = command.publish(&0.0, MSG_TYPE_COMMAND, 0);
+        });
+    }
+
+    let _tune = {
+        let pid = Arc::clone(&pid);
+        Server::<TuneRequest, ControllerStatus>::new(&names.tune(), 4, move |req| {
+            let mut pid = lock(&pid);
+            if req.set != 0 {
+                pid.status.gains = req.gains;
+                pid.integral = 0.0;
+            }
+            pid.status
+        })?
+    };
+
+    println!("controller: running with {gains:?}");
+    sleep_unless(stop, Duration::MAX);
+    Ok(())
+}
+
+/// How the plant ended up when it stopped.
+#[derive(Copy, Clone, Debug)]
+pub struct PlantReport {
+    pub state: PlantState,
+    /// Effort applied in the last step.
+    pub effort: f32,
+    /// Times the heartbeat watchdog tripped.
+    pub estops: u64,
+}
+
+/// Simulate the plant until `stop` is set.
+pub fn run_plant(names: &Names, stop: &AtomicBool) -> io::Result<PlantReport> {
+    let command = Topic::<f32>::with_options(&names.command(), &latest())?;
+    let state = KeyedTopic::<Stamped<PlantState>>::with_options(&names.state(), &latest())?;
+    let zero_effort = Msg::new(MSG_TYPE_COMMAND, bytemuck::bytes_of(&0.0f32));
+    let watchdog = Watchdog::new(
+        &names.heartbeat(),
+        &names.command(),
+        zero_effort,
+        &WatchdogOptions::new(HEARTBEAT_TIMEOUT).repeat(HEARTBEAT_TIMEOUT),
+        &TopicOptions::new(8),
+    )?;
+
+    let clock = clock::default_clock();
+    let mut now = clock.now();
+    let mut plant = PlantState::default();
+    let mut effort = 0.0;
+    while !stop.load(Ordering::Relaxed) {
+        // The e-stop is urgent, so it comes out first; whatever the
+        // controller sent after it still wins.
+        loop {
+            match command.try_recv() {
+                Ok(Some(cmd)) => effort = cmd,
+                Ok(None) => break,
+                // `stop` follows right after; report where we got to.
+                Err(err) if shutdown::is_shutdown(&err) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        state.publish(AXIS, &Stamped::new(now, plant), MSG_TYPE_STATE, 0)?;
+
+        thread::sleep(PLANT_PERIOD);
+        let next = clock.now();
+        let dt = (next - now).as_secs_f32();
+        now = next;
+        plant.velocity += (effort - DAMPING * plant.velocity) * dt;
+        plant.position += plant.velocity * dt;
+    }
+
+    Ok(PlantReport {
+        state: plant,
+        effort,
+        estops: watchdog.trips(),
+    })
+}
+
+/// Query the controller every `every` until `stop` is set and return
+/// what it answered.
+pub fn run_monitor(
+    names: &Names,
+    every: Duration,
+    stop: &AtomicBool,
+) -> io::Result<Vec<ControllerStatus>> {
+    let client = Client::<TuneRequest, ControllerStatus>::new(&names.tune())?;
+    let mut history = Vec::new();
+    let started = Instant::now();
+    loop {
+        match client.call(&TuneRequest::default(), every) {
+            Ok(s) => {
+                println!(
+                    "monitor: t={:5.2}s setpoint={:6.3} error={:7.4} effort={:7.3} cycles={} misses={}",
+                    started.elapsed().as_secs_f32(),
+                    s.setpoint,
+                    s.error,
+                    s.effort,
+                    s.cycles,
+                    s.deadline_misses,
+                );
+                history.push(s);
+            }
+            Err(err) => eprintln!("monitor: controller not answering: {err}"),
+        }
+        if sleep_unless(stop, every) {
+            return Ok(history);
+        }
+    }
+}
+
+/// Set new gains on a running controller.
+pub fn tune(names: &Names, gains: Gains) -> io::Result<ControllerStatus> {
+    let client = Client::<TuneRequest, ControllerStatus>::new(&names.tune())?;
+    client.call(&TuneRequest { gains, set: 1 }, Duration::from_secs(1))
+}
+
+/// Every queue a loop instance uses, for cleanup.
+pub fn queue_names(names: &Names) -> Vec<String> {
+    vec![
+        names.setpoint(),
+        names.state(),
+        names.command(),
+        names.heartbeat(),
+        names.tune(),
+    ]
+}
+
+fn latest() -> TopicOptions {
+    TopicOptions::new(8).qos(Qos::KeepLatest)
+}
+
+fn lock<T>(m: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sleep up to `dur`, waking early once `stop` is set; returns whether
+/// it was.
+fn sleep_unless(stop: &AtomicBool, dur: Duration) -> bool {
+    let until = Instant::now().checked_add(dur);
+    while !stop.load(Ordering::Relaxed) {
+        let left = until.map_or(Duration::MAX, |t| {
+            t.saturating_duration_since(Instant::now())
+        });
+        if left.is_zero() {
+            return false;
+        }
+        thread::sleep(left.min(Duration::from_millis(10)));
+    }
+    true
+}
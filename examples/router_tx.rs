@@ -21,8 +21,9 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 THE SOFTWARE.
 */
 
+use mq_ipc::shutdown;
 use mq_ipc::wire::{open_ipc_tx, WirePacket};
-use std::{io, thread, time::Duration};
+use std::io;
 
 fn send_over_wire(pkt: &WirePacket) {
     let topic = pkt.topic_name();
@@ -58,7 +59,7 @@ fn main() -> io::Result<()> {
         send_over_wire(&pkt);
     });
 
-    loop {
-        thread::sleep(Duration::from_secs(1));
-    }
+    shutdown::spin()?;
+    println!("router_tx: shutting down");
+    Ok(())
 }
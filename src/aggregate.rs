@@ -0,0 +1,219 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Windowed summaries (count/mean/min/max) of a typed topic.
+//!
+//! Dashboards and loggers rarely want every sample of a 1 kHz topic.
+//! A [`WindowedAggregator`] subscribes to the topic, reduces each message
+//! to `N` numbers with a caller-supplied extractor, and once per window
+//! hands a [`Summary`] of those numbers to a callback:
+//!
+//! ```no_run
+//! # use bytemuck::{Pod, Zeroable};
+//! # use mq_ipc::{aggregate::WindowedAggregator, Topic};
+//! # use std::time::Duration;
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! struct MotorState { rpm: f32, torque: f32 }
+//!
+//! let topic = Topic::<MotorState>::new("/motor/state", 8)?;
+//! let _agg = WindowedAggregator::new(
+//!     &topic,
+//!     Duration::from_secs(1),
+//!     |s: &MotorState| [s.rpm as f64, s.torque as f64],
+//!     |summary| println!("rpm mean {:.1} max {:.1}", summary.mean[0], summary.max[0]),
+//! );
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Windows without samples produce no summary.
+
+use super::Topic;
+use bytemuck::{Pod, Zeroable};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Statistics of one window, per extracted field.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Summary<const N: usize> {
+    /// Wall-clock time the window started.
+    pub start: SystemTime,
+    /// Window length.
+    pub window: Duration,
+    /// Samples in the window.
+    pub count: u64,
+    pub mean: [f64; N],
+    pub min: [f64; N],
+    pub max: [f64; N],
+}
+
+#[derive(Copy, Clone)]
+struct Accum<const N: usize> {
+    count: u64,
+    sum: [f64; N],
+    min: [f64; N],
+    max: [f64; N],
+}
+
+impl<const N: usize> Accum<N> {
+    const EMPTY: Self = Accum {
+        count: 0,
+        sum: [0.0; N],
+        min: [f64::INFINITY; N],
+        max: [f64::NEG_INFINITY; N],
+    };
+
+    fn add(&mut self, values: &[f64; N]) {
+        self.count += 1;
+        for (i, v) in values.iter().enumerate() {
+            self.sum[i] += v;
+            self.min[i] = self.min[i].min(*v);
+            self.max[i] = self.max[i].max(*v);
+        }
+    }
+
+    fn summary(&self, start: SystemTime, window: Duration) -> Summary<N> {
+        Summary {
+            start,
+            window,
+            count: self.count,
+            mean: self.sum.map(|s| s / self.count as f64),
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+/// Aggregating subscription; stops summarising when dropped.
+pub struct WindowedAggregator {
+    running: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl WindowedAggregator {
+    /// Summarise `topic` every `window`, reducing each message with
+    /// `extract` and passing the result to `on_summary` from a helper
+    /// thread.
+    pub fn new<T, X, F, const N: usize>(
+        topic: &Topic<T>,
+        window: Duration,
+        extract: X,
+        on_summary: F,
+    ) -> Self
+    where
+        T: Pod + Zeroable + Send + Sync + 'static,
+        X: Fn(&T) -> [f64; N] + Send + Sync + 'static,
+        F: Fn(&Summary<N>) + Send + 'static,
+    {
+        let accum = Arc::new(Mutex::new(Accum::<N>::EMPTY));
+        let running = Arc::new(AtomicBool::new(true));
+
+        {
+            let accum = Arc::clone(&accum);
+            let running = Arc::clone(&running);
+            // Subscriptions outlive us; a stopped aggregator just ignores
+            // further messages.
+            topic.subscribe(move |value: T| {
+                if running.load(Ordering::Relaxed) {
+                    let values = extract(&value);
+                    accum.lock().unwrap_or_else(|e| e.into_inner()).add(&values);
+                }
+            });
+        }
+
+        let running_clone = Arc::clone(&running);
+        let handle = thread::spawn(move || {
+            let mut start = SystemTime::now();
+            let mut next = Instant::now() + window;
+            while running_clone.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                if now < next {
+                    thread::sleep((next - now).min(Duration::from_millis(100)));
+                    continue;
+                }
+                next += window;
+
+                let done = std::mem::replace(
+                    &mut *accum.lock().unwrap_or_else(|e| e.into_inner()),
+                    Accum::EMPTY,
+                );
+                if done.count > 0 {
+                    on_summary(&done.summary(start, window));
+                }
+                start += window;
+            }
+        });
+
+        WindowedAggregator {
+            running,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for WindowedAggregator {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::sync::mpsc;
+
+    #[test]
+    fn summarises_each_window() {
+        let tmp = TempTopic::new("/mq_ipc_test_aggregate_");
+        let topic: Topic<f32> = Topic::new(tmp.name(), 8).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let _agg = WindowedAggregator::new(
+            &topic,
+            Duration::from_millis(100),
+            |v: &f32| [*v as f64, -*v as f64],
+            move |s| tx.send(*s).unwrap(),
+        );
+        for v in [1.0f32, 2.0, 3.0, 4.0] {
+            topic.publish(&v, 1, 0).unwrap();
+        }
+
+        let s = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(s.count, 4);
+        assert_eq!(s.mean, [2.5, -2.5]);
+        assert_eq!(s.min, [1.0, -4.0]);
+        assert_eq!(s.max, [4.0, -1.0]);
+        // Nothing was published since, so no further summaries.
+        assert!(rx.recv_timeout(Duration::from_millis(250)).is_err());
+    }
+}
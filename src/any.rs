@@ -0,0 +1,168 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Type-erased topic handles.
+//!
+//! Managers, recorders and supervisors often hold topics of many message
+//! types at once. [`AnyTopic`] is the object-safe part of a topic, so
+//! they can keep a `Vec<Box<dyn AnyTopic>>`:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::{any::AnyTopic, Topic};
+//!
+//! let topics: Vec<Box<dyn AnyTopic>> = vec![
+//!     Box::new(Topic::<u32>::new("/mode", 4)?),
+//!     Box::new(Topic::<[f32; 3]>::new("/accel", 8)?),
+//! ];
+//! for topic in &topics {
+//!     println!("{} ({}): {:?}", topic.name(), topic.type_name(), topic.stats().depth);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`AnyTopic::as_any`] gets the concrete handle back.
+
+use super::{stats, MqTopic, Msg, Topic};
+use bytemuck::{Pod, Zeroable};
+use std::{any::Any, io};
+
+/// Object-safe view of a topic handle; see the module docs.
+pub trait AnyTopic: Send + Sync {
+    /// Queue name, namespace included.
+    fn name(&self) -> &str;
+
+    /// Name of the message type, for humans.
+    fn type_name(&self) -> &'static str;
+
+    fn stats(&self) -> stats::TopicStats;
+
+    /// Publish an already encoded message.
+    fn publish_raw(&self, msg: &Msg, prio: u32) -> io::Result<()>;
+
+    /// Subscribe to messages as they arrive, undecoded.
+    #[cfg(feature = "callbacks")]
+    fn subscribe_raw(&self, f: Box<dyn Fn(&Msg) + Send + Sync + 'static>);
+
+    /// Drop the handle, stopping its worker.
+    fn close(self: Box<Self>);
+
+    /// The concrete handle, for downcasting.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl AnyTopic for MqTopic {
+    fn name(&self) -> &str {
+        MqTopic::name(self)
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Msg>()
+    }
+
+    fn stats(&self) -> stats::TopicStats {
+        MqTopic::stats(self)
+    }
+
+    fn publish_raw(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        self.publish(msg, prio)
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn subscribe_raw(&self, f: Box<dyn Fn(&Msg) + Send + Sync + 'static>) {
+        self.subscribe(move |msg| f(&msg));
+    }
+
+    fn close(self: Box<Self>) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<T> AnyTopic for Topic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    fn name(&self) -> &str {
+        self.raw().name()
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn stats(&self) -> stats::TopicStats {
+        Topic::stats(self)
+    }
+
+    fn publish_raw(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        self.raw().publish(msg, prio)
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn subscribe_raw(&self, f: Box<dyn Fn(&Msg) + Send + Sync + 'static>) {
+        Topic::subscribe_raw(self, f);
+    }
+
+    fn close(self: Box<Self>) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::mpsc, time::Duration};
+
+    #[test]
+    fn heterogeneous_topics_behind_one_trait() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_any_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_any_b_");
+        let topics: Vec<Box<dyn AnyTopic>> = vec![
+            Box::new(Topic::<u32>::new(tmp_a.name(), 4).unwrap()),
+            Box::new(Topic::<[f32; 2]>::new(tmp_b.name(), 4).unwrap()),
+        ];
+        assert_eq!(topics[0].type_name(), "u32");
+        assert_eq!(topics[1].name(), tmp_b.name());
+
+        let (tx, rx) = mpsc::channel();
+        topics[0].subscribe_raw(Box::new(move |msg| tx.send(msg.data().to_vec()).unwrap()));
+        topics[0]
+            .publish_raw(&Msg::new(1, &7u32.to_ne_bytes()), 0)
+            .unwrap();
+        let got = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(got, 7u32.to_ne_bytes());
+        assert_eq!(topics[0].stats().published, 1);
+
+        let accel = topics[1].as_any().downcast_ref::<Topic<[f32; 2]>>();
+        assert!(accel.is_some());
+        for topic in topics {
+            topic.close();
+        }
+    }
+}
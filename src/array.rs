@@ -0,0 +1,361 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Variable-length arrays of a [`Pod`] element.
+//!
+//! Point clouds, joint vectors and waypoint lists vary in length from one
+//! message to the next. Instead of a fixed-maximum struct with a separate
+//! length field, an [`ArrayTopic<T>`] publishes a `&[T]` and hands
+//! subscribers a `Vec<T>` of the same length:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::array::ArrayTopic;
+//!
+//! let joints = ArrayTopic::<f32>::new("/joint_positions", 8)?;
+//! joints.publish(&[0.0, 0.5, 1.2, -0.3, 0.0, 0.9], 1, 0)?;
+//! if let Some(positions) = joints.try_recv()? {
+//!     println!("{} joints", positions.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each message's data is an [`ArrayHeader`] followed by packed elements
+//! and is flagged with [`FLAG_ARRAY`]. Arrays larger than one message are
+//! split into fragments sent back to back at the same priority, and
+//! reassembled on the receiving side; an array that lost a fragment (a
+//! full non-blocking queue, a competing reader) is dropped whole rather
+//! than delivered with a hole.
+
+use super::{dedup, MqTopic, Msg, TopicOptions};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    collections::VecDeque,
+    io,
+    marker::PhantomData,
+    os::raw::c_long,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// `hdr.flags` bit: the data is an [`ArrayHeader`] plus packed elements.
+pub const FLAG_ARRAY: u16 = 0x0020;
+
+/// Size of the [`ArrayHeader`] at the start of every fragment.
+pub const ARRAY_HEADER_SIZE: usize = std::mem::size_of::<ArrayHeader>();
+
+/// Arrays a receiver reassembles at the same time, e.g. from several
+/// publishers; the oldest incomplete one is dropped beyond that.
+pub const MAX_PARTIAL: usize = 4;
+
+/// Leading bytes of an array fragment.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct ArrayHeader {
+    /// Identifies the array among the publisher's others.
+    pub array_id: u32,
+    /// Element count of the whole array.
+    pub total: u32,
+    /// Index of the first element in this fragment.
+    pub first: u32,
+    /// Elements in this fragment.
+    pub count: u16,
+    /// Size of each element in bytes.
+    pub size: u16,
+}
+
+/// How many `T`s fit in one fragment of at most `max_payload` data bytes.
+pub fn capacity<T>(max_payload: usize) -> usize {
+    let size = std::mem::size_of::<T>().max(1);
+    max_payload.saturating_sub(ARRAY_HEADER_SIZE) / size
+}
+
+/// Header and elements of `msg`, or `None` if it is not an array
+/// fragment of `T`s.
+pub fn fragment<T: Pod>(msg: &Msg) -> Option<(ArrayHeader, Vec<T>)> {
+    if msg.hdr.flags & FLAG_ARRAY == 0 {
+        return None;
+    }
+    let data = msg.data();
+    let hdr: ArrayHeader = bytemuck::pod_read_unaligned(data.get(..ARRAY_HEADER_SIZE)?);
+    let size = std::mem::size_of::<T>();
+    if hdr.size as usize != size {
+        return None;
+    }
+    let body = data.get(ARRAY_HEADER_SIZE..ARRAY_HEADER_SIZE + hdr.count as usize * size)?;
+    let elems = body
+        .chunks_exact(size.max(1))
+        .map(bytemuck::pod_read_unaligned)
+        .collect();
+    Some((hdr, elems))
+}
+
+struct Partial<T> {
+    id: u32,
+    total: usize,
+    elems: Vec<T>,
+}
+
+/// Reassembles fragments into whole arrays.
+struct Assembler<T> {
+    partial: VecDeque<Partial<T>>,
+}
+
+impl<T: Pod> Assembler<T> {
+    fn new() -> Self {
+        Assembler {
+            partial: VecDeque::new(),
+        }
+    }
+
+    /// Add a fragment; returns the array it completes.
+    fn push(&mut self, hdr: ArrayHeader, elems: Vec<T>) -> Option<Vec<T>> {
+        let total = hdr.total as usize;
+        let pos = self.partial.iter().position(|p| p.id == hdr.array_id);
+        let mut partial = match pos {
+            Some(i) => self.partial.remove(i)?,
+            None => Partial {
+                id: hdr.array_id,
+                total,
+                elems: Vec::with_capacity(total),
+            },
+        };
+        // A gap or a restart means a fragment went missing.
+        if hdr.first as usize != partial.elems.len() || partial.total != total {
+            if hdr.first != 0 {
+                return None;
+            }
+            partial.elems.clear();
+            partial.total = total;
+        }
+        partial.elems.extend(elems);
+        if partial.elems.len() >= partial.total {
+            partial.elems.truncate(partial.total);
+            return Some(partial.elems);
+        }
+        if self.partial.len() == MAX_PARTIAL {
+            self.partial.pop_front();
+        }
+        self.partial.push_back(partial);
+        None
+    }
+}
+
+/// Topic carrying variable-length arrays of `T`; see the
+/// [module docs](self).
+pub struct ArrayTopic<T> {
+    inner: MqTopic,
+    id_base: u32,
+    next_id: AtomicU32,
+    limit: usize,
+    assembler: Mutex<Assembler<T>>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> ArrayTopic<T>
+where
+    T: Pod + Send + Sync + 'static,
+{
+    /// Create or open `name` with default options.
+    pub fn new(name: &str, maxmsg: c_long) -> io::Result<Self> {
+        Self::with_options(name, &TopicOptions::new(maxmsg))
+    }
+
+    /// Create or open `name` with explicit [`TopicOptions`]. Fails with
+    /// `InvalidInput` if not even one `T` fits in a message.
+    pub fn with_options(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        let inner = MqTopic::with_options(name, opts)?;
+        let limit = capacity::<T>(inner.max_payload).min(u16::MAX as usize);
+        if limit == 0 || std::mem::size_of::<T>() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name}: a message cannot hold any elements of this type"),
+            ));
+        }
+        Ok(ArrayTopic {
+            inner,
+            id_base: dedup::unique_seed() as u32,
+            next_id: AtomicU32::new(0),
+            limit,
+            assembler: Mutex::new(Assembler::new()),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Publish `values` as one array, in as many fragments as needed.
+    pub fn publish(&self, values: &[T], msg_type: u16, prio: u32) -> io::Result<()> {
+        let total = u32::try_from(values.len()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "array longer than u32::MAX")
+        })?;
+        let id = self
+            .id_base
+            .wrapping_add(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut first = 0;
+        loop {
+            let chunk = &values[first..(first + self.limit).min(values.len())];
+            let hdr = ArrayHeader {
+                array_id: id,
+                total,
+                first: first as u32,
+                count: chunk.len() as u16,
+                size: std::mem::size_of::<T>() as u16,
+            };
+            let mut data = Vec::with_capacity(ARRAY_HEADER_SIZE + std::mem::size_of_val(chunk));
+            data.extend_from_slice(bytemuck::bytes_of(&hdr));
+            data.extend_from_slice(bytemuck::cast_slice(chunk));
+            let mut msg = Msg::new(msg_type, &data);
+            msg.hdr.flags |= FLAG_ARRAY;
+            self.inner.publish(&msg, prio)?;
+
+            first += chunk.len();
+            if first >= values.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Call `f` with every complete array received. Other messages on the
+    /// topic are ignored.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe<F>(&self, f: F)
+    where
+        F: Fn(Vec<T>) + Send + Sync + 'static,
+    {
+        let assembler = Mutex::new(Assembler::new());
+        self.inner.subscribe(move |msg: Msg| {
+            let Some((hdr, elems)) = fragment::<T>(&msg) else {
+                return;
+            };
+            let done = assembler
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(hdr, elems);
+            if let Some(values) = done {
+                f(values);
+            }
+        });
+    }
+
+    /// The next complete array, if its fragments are already queued.
+    /// Fragments of an array still in flight are kept for the next call.
+    pub fn try_recv(&self) -> io::Result<Option<Vec<T>>> {
+        self.recv_until(None)
+    }
+
+    /// Wait up to `timeout` for the next complete array.
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<Vec<T>>> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> io::Result<Option<Vec<T>>> {
+        loop {
+            let msg = match deadline {
+                Some(at) => self
+                    .inner
+                    .recv_timeout(at.saturating_duration_since(Instant::now()))?,
+                None => self.inner.try_recv()?,
+            };
+            let Some(msg) = msg else {
+                return Ok(None);
+            };
+            let Some((hdr, elems)) = fragment::<T>(&msg) else {
+                continue;
+            };
+            let done = self
+                .assembler
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(hdr, elems);
+            if done.is_some() {
+                return Ok(done);
+            }
+        }
+    }
+
+    /// Elements carried per message; longer arrays are fragmented.
+    pub fn fragment_limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The underlying topic.
+    pub fn raw(&self) -> &MqTopic {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+    struct Point {
+        x: f32,
+        y: f32,
+        z: f32,
+    }
+
+    #[test]
+    fn fragments_and_reassembles_arrays() {
+        let tmp = TempTopic::new("/mq_ipc_test_array_");
+        let topic = ArrayTopic::<Point>::new(tmp.name(), 8).unwrap();
+        let limit = topic.fragment_limit();
+        assert_eq!(limit, (crate::MSG_PAYLOAD_SIZE - ARRAY_HEADER_SIZE) / 12);
+
+        let points: Vec<Point> = (0..limit * 2 + 3)
+            .map(|i| Point {
+                x: i as f32,
+                y: 0.0,
+                z: -(i as f32),
+            })
+            .collect();
+        topic.publish(&points, 1, 0).unwrap();
+        topic.publish(&[], 1, 0).unwrap();
+
+        assert_eq!(topic.try_recv().unwrap(), Some(points));
+        assert_eq!(topic.try_recv().unwrap(), Some(Vec::new()));
+        assert_eq!(topic.try_recv().unwrap(), None);
+    }
+
+    #[test]
+    fn drops_arrays_with_a_missing_fragment() {
+        let mut asm = Assembler::<u8>::new();
+        let hdr = |id, first, count| ArrayHeader {
+            array_id: id,
+            total: 4,
+            first,
+            count,
+            size: 1,
+        };
+        assert_eq!(asm.push(hdr(1, 0, 2), vec![1, 2]), None);
+        // Second half of array 2 without its first half.
+        assert_eq!(asm.push(hdr(2, 2, 2), vec![7, 8]), None);
+        assert_eq!(asm.push(hdr(1, 2, 2), vec![3, 4]), Some(vec![1, 2, 3, 4]));
+        assert!(asm.partial.is_empty());
+    }
+}
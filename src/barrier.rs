@@ -0,0 +1,219 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Startup barrier shared by several processes.
+//!
+//! Each participant drops one token into a dedicated queue; once the
+//! queue holds `n` tokens everyone waiting is released. Nobody reads the
+//! tokens, so late arrivals see the barrier as already open.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use std::time::Duration;
+//! // in each of sensor, filter and controller:
+//! let barrier = mq_ipc::barrier::Barrier::new("/pipeline.start", 3)?;
+//! barrier.wait_timeout(Duration::from_secs(5))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A barrier is single use; [`Barrier::remove`] it before the next run.
+//! The token queue is `n` deep, so `n` is bounded by the system's
+//! `msg_max` (10 by default for unprivileged users).
+
+use super::{cleanup, defaults, open_queue, queue_attr, Msg};
+use libc::{self, mqd_t};
+use std::{
+    io,
+    os::raw::{c_char, c_long},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+const MSG_TYPE_ARRIVE: u16 = 1;
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Handle of one participant.
+pub struct Barrier {
+    name: String,
+    mqd: mqd_t,
+    parties: usize,
+    arrived: AtomicBool,
+}
+
+impl Barrier {
+    /// Open (creating if needed) the barrier `name` for `parties`
+    /// participants.
+    pub fn new(name: &str, parties: usize) -> io::Result<Self> {
+        if parties == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "barrier needs at least one party",
+            ));
+        }
+
+        let name = defaults::topic_name(name).into_owned();
+        let mqd = open_queue(
+            &name,
+            libc::O_CREAT | libc::O_RDWR | libc::O_NONBLOCK,
+            Some(parties as c_long),
+        )?;
+        let barrier = Barrier {
+            name,
+            mqd,
+            parties,
+            arrived: AtomicBool::new(false),
+        };
+
+        // Someone else created it for fewer parties: it could never open.
+        let capacity = queue_attr(mqd)?.mq_maxmsg as usize;
+        if capacity < parties {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} holds {capacity} tokens, {parties} needed", barrier.name),
+            ));
+        }
+        Ok(barrier)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parties(&self) -> usize {
+        self.parties
+    }
+
+    /// Number of participants that have arrived so far.
+    pub fn arrived(&self) -> io::Result<usize> {
+        Ok(queue_attr(self.mqd)?.mq_curmsgs as usize)
+    }
+
+    /// Whether every participant has arrived.
+    pub fn is_open(&self) -> io::Result<bool> {
+        Ok(self.arrived()? >= self.parties)
+    }
+
+    /// Register this participant without waiting. Calling it again is a
+    /// no-op.
+    pub fn arrive(&self) -> io::Result<()> {
+        if self.arrived.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let token = Msg::new(MSG_TYPE_ARRIVE, &std::process::id().to_le_bytes());
+        let rc = unsafe {
+            libc::mq_send(
+                self.mqd,
+                &token as *const Msg as *const c_char,
+                std::mem::size_of::<Msg>(),
+                0,
+            )
+        };
+        if rc == -1 {
+            self.arrived.store(false, Ordering::SeqCst);
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::EAGAIN) {
+                return Err(io::Error::other(format!(
+                    "{}: more than {} parties arrived",
+                    self.name, self.parties
+                )));
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Arrive and block until every participant has.
+    pub fn wait(&self) -> io::Result<()> {
+        self.arrive()?;
+        while !self.is_open()? {
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Like [`Barrier::wait`], but fails with `TimedOut` after `timeout`.
+    /// The arrival is not withdrawn.
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<()> {
+        self.arrive()?;
+        let deadline = Instant::now() + timeout;
+        while !self.is_open()? {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "{}: {} of {} parties arrived",
+                        self.name,
+                        self.arrived()?,
+                        self.parties
+                    ),
+                ));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Delete the barrier `name`, so the next run starts from zero.
+    pub fn remove(name: &str) -> io::Result<()> {
+        cleanup::unlink(name)
+    }
+}
+
+impl Drop for Barrier {
+    fn drop(&mut self) {
+        unsafe {
+            libc::mq_close(self.mqd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::sync::Arc;
+
+    #[test]
+    fn releases_when_all_parties_arrive() {
+        let tmp = TempTopic::new("/mq_ipc_test_barrier_");
+        let a = Arc::new(Barrier::new(tmp.name(), 2).unwrap());
+        let b = Barrier::new(tmp.name(), 2).unwrap();
+
+        let err = a.wait_timeout(Duration::from_millis(20)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(a.arrived().unwrap(), 1);
+
+        let waiter = {
+            let a = Arc::clone(&a);
+            thread::spawn(move || a.wait())
+        };
+        b.wait_timeout(Duration::from_secs(2)).unwrap();
+        waiter.join().unwrap().unwrap();
+        assert!(b.is_open().unwrap());
+
+        assert!(Barrier::new(tmp.name(), 3).is_err());
+    }
+}
@@ -0,0 +1,339 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Coalescing many small samples into one message.
+//!
+//! High-rate, low-importance telemetry spends most of its time in
+//! `mq_send`/`mq_receive`. A [`BatchingPublisher`] packs up to
+//! [`BatchPolicy::max_samples`] values (or whatever arrived within
+//! [`BatchPolicy::max_delay`]) into a single message flagged with
+//! [`FLAG_BATCH`]. [`Topic::subscribe`](crate::Topic::subscribe) unpacks
+//! such messages transparently, so subscribers do not change.
+//!
+//! The data of a batch message is a [`BatchHeader`] followed by `count`
+//! packed values.
+
+use super::{MqTopic, Msg, TopicOptions, URGENT_PRIORITY};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    marker::PhantomData,
+    os::raw::c_long,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// `hdr.flags` bit: the data is a [`BatchHeader`] plus packed values.
+pub const FLAG_BATCH: u16 = 0x0004;
+
+/// Size of the [`BatchHeader`] at the start of a batch.
+pub const BATCH_HEADER_SIZE: usize = std::mem::size_of::<BatchHeader>();
+
+/// Leading bytes of a batch message.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct BatchHeader {
+    /// Number of values that follow.
+    pub count: u16,
+    /// Size of each value in bytes.
+    pub size: u16,
+}
+
+/// When a [`BatchingPublisher`] sends what it has collected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BatchPolicy {
+    /// Flush once this many values are pending. Clamped to what fits in
+    /// one message.
+    pub max_samples: usize,
+    /// Flush this long after the first pending value at the latest.
+    pub max_delay: Duration,
+}
+
+impl BatchPolicy {
+    pub fn new(max_samples: usize, max_delay: Duration) -> Self {
+        BatchPolicy {
+            max_samples,
+            max_delay,
+        }
+    }
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        BatchPolicy::new(usize::MAX, Duration::from_millis(10))
+    }
+}
+
+/// How many `T`s fit in one batch of at most `max_payload` data bytes.
+pub fn capacity<T>(max_payload: usize) -> usize {
+    let size = std::mem::size_of::<T>().max(1);
+    max_payload.saturating_sub(BATCH_HEADER_SIZE) / size
+}
+
+/// The values packed into `msg`, or `None` if it is not a batch of `T`s.
+pub fn unbatch<T: Pod>(msg: &Msg) -> Option<Vec<T>> {
+    if msg.hdr.flags & FLAG_BATCH == 0 {
+        return None;
+    }
+    let data = msg.data();
+    let hdr: BatchHeader = bytemuck::pod_read_unaligned(data.get(..BATCH_HEADER_SIZE)?);
+    let size = std::mem::size_of::<T>();
+    if hdr.size as usize != size {
+        return None;
+    }
+    let body = data.get(BATCH_HEADER_SIZE..BATCH_HEADER_SIZE + hdr.count as usize * size)?;
+    Some(
+        body.chunks_exact(size.max(1))
+            .map(bytemuck::pod_read_unaligned)
+            .collect(),
+    )
+}
+
+struct Pending {
+    data: Vec<u8>,
+    count: usize,
+    key: (u16, u32),
+    since: Option<Instant>,
+    stop: bool,
+}
+
+struct Shared {
+    topic: MqTopic,
+    limit: usize,
+    max_delay: Duration,
+    pending: Mutex<Pending>,
+    cond: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Pending> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send whatever is pending. Called with the lock held so batches
+    /// leave in push order.
+    fn flush_locked(&self, pending: &mut Pending, size: usize) -> io::Result<()> {
+        if pending.count == 0 {
+            return Ok(());
+        }
+        let hdr = BatchHeader {
+            count: pending.count as u16,
+            size: size as u16,
+        };
+        pending.data[..BATCH_HEADER_SIZE].copy_from_slice(bytemuck::bytes_of(&hdr));
+        let mut msg = Msg::new(pending.key.0, &pending.data);
+        msg.hdr.flags |= FLAG_BATCH;
+
+        pending.data.truncate(BATCH_HEADER_SIZE);
+        pending.count = 0;
+        pending.since = None;
+        self.topic.publish(&msg, pending.key.1)
+    }
+}
+
+/// Publisher that sends `T`s in batches; see the [module docs](self).
+pub struct BatchingPublisher<T> {
+    shared: Arc<Shared>,
+    flusher: Option<thread::JoinHandle<()>>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> BatchingPublisher<T>
+where
+    T: Pod + Send + Sync + 'static,
+{
+    /// Create or open `name` with default options.
+    pub fn new(name: &str, maxmsg: c_long, policy: BatchPolicy) -> io::Result<Self> {
+        Self::with_options(name, &TopicOptions::new(maxmsg), policy)
+    }
+
+    /// Create or open `name` with explicit [`TopicOptions`]. Fails with
+    /// `InvalidInput` if not even one `T` fits in a message.
+    pub fn with_options(name: &str, opts: &TopicOptions, policy: BatchPolicy) -> io::Result<Self> {
+        let topic = MqTopic::with_options(name, opts)?;
+        let limit = capacity::<T>(topic.max_payload).min(policy.max_samples);
+        if limit == 0 || std::mem::size_of::<T>() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name}: a batch cannot hold any values of this type"),
+            ));
+        }
+
+        let shared = Arc::new(Shared {
+            topic,
+            limit,
+            max_delay: policy.max_delay,
+            pending: Mutex::new(Pending {
+                data: vec![0; BATCH_HEADER_SIZE],
+                count: 0,
+                key: (0, 0),
+                since: None,
+                stop: false,
+            }),
+            cond: Condvar::new(),
+        });
+
+        let flusher = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || Self::run_flusher(&shared))
+        };
+
+        Ok(BatchingPublisher {
+            shared,
+            flusher: Some(flusher),
+            _marker: PhantomData,
+        })
+    }
+
+    fn run_flusher(shared: &Shared) {
+        let mut pending = shared.lock();
+        while !pending.stop {
+            let Some(since) = pending.since else {
+                pending = shared.cond.wait(pending).unwrap_or_else(|e| e.into_inner());
+                continue;
+            };
+            let due = since + shared.max_delay;
+            let now = Instant::now();
+            if now < due {
+                pending = shared
+                    .cond
+                    .wait_timeout(pending, due - now)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+                continue;
+            }
+            if let Err(err) = shared.flush_locked(&mut pending, std::mem::size_of::<T>()) {
+                eprintln!("{}: batch flush failed: {err}", shared.topic.name());
+            }
+        }
+    }
+
+    /// Queue `value` for the next batch. A change of `msg_type` or `prio`
+    /// flushes the current batch first, as does reaching the size limit;
+    /// only those flushes can return an error.
+    pub fn publish(&self, value: &T, msg_type: u16, prio: u32) -> io::Result<()> {
+        let size = std::mem::size_of::<T>();
+        let mut pending = self.shared.lock();
+        if pending.count > 0 && pending.key != (msg_type, prio) {
+            self.shared.flush_locked(&mut pending, size)?;
+        }
+
+        pending.key = (msg_type, prio);
+        pending.data.extend_from_slice(bytemuck::bytes_of(value));
+        pending.count += 1;
+        if pending.since.is_none() {
+            pending.since = Some(Instant::now());
+            self.shared.cond.notify_one();
+        }
+
+        if pending.count >= self.shared.limit {
+            self.shared.flush_locked(&mut pending, size)?;
+        }
+        Ok(())
+    }
+
+    /// Send `value` right away at [`URGENT_PRIORITY`], on its own and
+    /// ahead of the pending batch, which is left untouched.
+    pub fn publish_urgent(&self, value: &T, msg_type: u16) -> io::Result<()> {
+        let msg = Msg::new(msg_type, bytemuck::bytes_of(value));
+        self.shared.topic.publish(&msg, URGENT_PRIORITY)
+    }
+
+    /// Send the pending values now.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut pending = self.shared.lock();
+        self.shared
+            .flush_locked(&mut pending, std::mem::size_of::<T>())
+    }
+
+    /// Values per batch after clamping [`BatchPolicy::max_samples`].
+    pub fn batch_limit(&self) -> usize {
+        self.shared.limit
+    }
+
+    /// The underlying topic.
+    pub fn raw(&self) -> &MqTopic {
+        &self.shared.topic
+    }
+}
+
+impl<T> Drop for BatchingPublisher<T> {
+    fn drop(&mut self) {
+        let size = std::mem::size_of::<T>();
+        {
+            let mut pending = self.shared.lock();
+            pending.stop = true;
+            if let Err(err) = self.shared.flush_locked(&mut pending, size) {
+                eprintln!("{}: batch flush failed: {err}", self.shared.topic.name());
+            }
+        }
+        self.shared.cond.notify_one();
+        if let Some(handle) = self.flusher.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, Topic};
+    use std::sync::mpsc;
+
+    #[test]
+    fn batches_by_count_and_by_delay() {
+        let tmp = TempTopic::new("/mq_ipc_test_batch_");
+        let policy = BatchPolicy::new(4, Duration::from_millis(20));
+        let publisher = BatchingPublisher::<u32>::new(tmp.name(), 8, policy).unwrap();
+        assert_eq!(publisher.batch_limit(), 4);
+
+        // No worker yet: the queue shows how many messages were sent.
+        for v in 0..6u32 {
+            publisher.publish(&v, 1, 0).unwrap();
+        }
+        assert_eq!(publisher.raw().stats().depth.current, 1);
+        thread::sleep(Duration::from_millis(60));
+        assert_eq!(publisher.raw().stats().depth.current, 2);
+
+        let topic = Topic::<u32>::new(tmp.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        topic.subscribe(move |v| tx.lock().unwrap().send(v).unwrap());
+
+        let got: Vec<u32> = (0..6)
+            .map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap())
+            .collect();
+        assert_eq!(got, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn unbatch_rejects_other_sizes() {
+        let mut msg = Msg::new(1, &[2, 0, 2, 0, 7, 0, 9, 0]);
+        msg.hdr.flags |= FLAG_BATCH;
+        assert_eq!(unbatch::<u16>(&msg), Some(vec![7, 9]));
+        assert_eq!(unbatch::<u32>(&msg), None);
+        assert_eq!(unbatch::<u16>(&Msg::new(1, &[0; 8])), None);
+        assert_eq!(capacity::<u32>(crate::MSG_PAYLOAD_SIZE), 59);
+    }
+}
@@ -0,0 +1,310 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Command-line companion to the mq-ipc library.
+//!
+//! ```text
+//! mq-ipc janitor [--grace SECS] [--dry-run] [--watch SECS]
+//! mq-ipc graph
+//! mq-ipc schema [TOPIC] [--format json|layout]
+//! mq-ipc compat OLD NEW
+//! mq-ipc send-file FILE --peer ADDR [--bind ADDR] [--name NAME] [--chunk BYTES] [--timeout SECS]
+//! mq-ipc wire-dump CAPTURE
+//! ```
+//!
+//! `janitor` unlinks queues whose registered endpoints all belong to dead
+//! processes. With `--watch` it keeps sweeping until SIGINT/SIGTERM.
+//!
+//! `graph` prints the live publisher/subscriber graph as Graphviz DOT.
+//!
+//! `schema` prints the payload layouts registered with `layout::register`,
+//! as JSON Schema (an object keyed by topic unless TOPIC is given) or as
+//! the plain `OFFSET TYPE NAME` listing.
+//!
+//! `compat` compares two files saved from `schema` and lists every layout
+//! change as compatible or breaking; it fails if any change is breaking.
+//!
+//! `send-file` pushes a file (typically a firmware image) over UDP to a
+//! wire peer that accepts transfers, resuming an interrupted one.
+//!
+//! `wire-dump` decodes a capture written by a router with
+//! `RouterOptions::capture`, one frame per line.
+
+use mq_ipc::{
+    compat::{self, Schemas},
+    graph,
+    janitor::{self, JanitorOptions},
+    layout, shutdown,
+    wire::{
+        capture::{self, Direction},
+        link::UdpLink,
+        router::{Router, RouterOptions},
+        transfer::TransferOptions,
+    },
+};
+use std::{
+    env,
+    path::Path,
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+const USAGE: &str = "usage: mq-ipc janitor [--grace SECS] [--dry-run] [--watch SECS]
+       mq-ipc graph
+       mq-ipc schema [TOPIC] [--format json|layout]
+       mq-ipc compat OLD NEW
+       mq-ipc send-file FILE --peer ADDR [--bind ADDR] [--name NAME] [--chunk BYTES] [--timeout SECS]
+       mq-ipc wire-dump CAPTURE";
+
+fn secs(arg: Option<String>, flag: &str) -> Result<Duration, String> {
+    let value = arg.ok_or_else(|| format!("{flag} needs a value"))?;
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|s| *s >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("{flag}: invalid number of seconds '{value}'"))
+}
+
+fn run_janitor(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut opts = JanitorOptions::default();
+    let mut watch = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--grace" => opts = opts.grace(secs(args.next(), "--grace")?),
+            "--dry-run" => opts = opts.dry_run(true),
+            "--watch" => watch = Some(secs(args.next(), "--watch")?),
+            other => return Err(format!("unknown option '{other}'\n{USAGE}")),
+        }
+    }
+
+    let verb = if opts.dry_run {
+        "would unlink"
+    } else {
+        "unlinked"
+    };
+    loop {
+        let report = janitor::sweep(&opts).map_err(|e| format!("sweep failed: {e}"))?;
+        for name in &report.unlinked {
+            println!("{verb} {name}");
+        }
+        if report.stale_entries > 0 {
+            println!("{} stale registry entries", report.stale_entries);
+        }
+
+        let Some(interval) = watch else {
+            return Ok(());
+        };
+        if shutdown::wait_timeout(interval)
+            .map_err(|e| e.to_string())?
+            .is_some()
+        {
+            return Ok(());
+        }
+    }
+}
+
+fn run_graph(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    if let Some(other) = args.next() {
+        return Err(format!("unknown option '{other}'\n{USAGE}"));
+    }
+    let graph = graph::snapshot().map_err(|e| format!("reading registry failed: {e}"))?;
+    print!("{}", graph.to_dot());
+    Ok(())
+}
+
+fn run_schema(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut topic = None;
+    let mut as_json = true;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().as_deref() {
+                Some("json") => as_json = true,
+                Some("layout") => as_json = false,
+                _ => return Err(format!("--format needs 'json' or 'layout'\n{USAGE}")),
+            },
+            other if topic.is_none() && !other.starts_with("--") => topic = Some(arg),
+            other => return Err(format!("unknown option '{other}'\n{USAGE}")),
+        }
+    }
+
+    let failed = |e| format!("reading registry failed: {e}");
+    let layouts = match &topic {
+        Some(topic) => {
+            let layout = layout::registered(topic)
+                .map_err(failed)?
+                .ok_or_else(|| format!("no layout registered for {topic}"))?;
+            vec![(topic.clone(), layout)]
+        }
+        None => layout::all_registered().map_err(failed)?,
+    };
+
+    if !as_json {
+        for (i, (topic, layout)) in layouts.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("# {topic}");
+            print!("{layout}");
+        }
+    } else if topic.is_some() {
+        println!("{}", layouts[0].1.to_json_schema());
+    } else {
+        let entries: Vec<_> = layouts
+            .iter()
+            .map(|(topic, layout)| format!("{topic:?}:{}", layout.to_json_schema()))
+            .collect();
+        println!("{{{}}}", entries.join(","));
+    }
+    Ok(())
+}
+
+fn run_compat(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let (Some(old), Some(new), None) = (args.next(), args.next(), args.next()) else {
+        return Err(USAGE.to_string());
+    };
+    let load = |path: &str| {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        Schemas::parse(&text).ok_or_else(|| format!("{path}: not a schema or layout file"))
+    };
+
+    let changes = match (load(&old)?, load(&new)?) {
+        (Schemas::One(a), Schemas::One(b)) => compat::compare(&a, &b)
+            .into_iter()
+            .map(|c| (a.type_name.clone(), c))
+            .collect(),
+        (Schemas::Topics(a), Schemas::Topics(b)) => compat::compare_topics(&a, &b),
+        _ => return Err("cannot compare a single layout with a set of topics".to_string()),
+    };
+
+    let breaking = changes.iter().filter(|(_, c)| c.is_breaking()).count();
+    for (what, change) in &changes {
+        let kind = if change.is_breaking() {
+            "breaking"
+        } else {
+            "compatible"
+        };
+        println!("{kind:<10} {what}: {change}");
+    }
+    println!("{} change(s), {breaking} breaking", changes.len());
+    if breaking > 0 {
+        return Err(format!("{new} is not wire-compatible with {old}"));
+    }
+    Ok(())
+}
+
+fn run_send_file(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let file = args.next().ok_or_else(|| USAGE.to_string())?;
+    let mut peer = None;
+    let mut bind = "0.0.0.0:0".to_string();
+    let mut name = None;
+    let mut opts = TransferOptions::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--peer" => peer = Some(value()?),
+            "--bind" => bind = value()?,
+            "--name" => name = Some(value()?),
+            "--chunk" => {
+                let v = value()?;
+                let bytes = v
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("--chunk: invalid size '{v}'"))?;
+                opts = opts.chunk_size(bytes);
+            }
+            "--timeout" => opts = opts.timeout(secs(args.next(), "--timeout")?),
+            other => return Err(format!("unknown option '{other}'\n{USAGE}")),
+        }
+    }
+
+    let peer = peer.ok_or_else(|| format!("--peer is required\n{USAGE}"))?;
+    let name = match name {
+        Some(name) => name,
+        None => Path::new(&file)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| format!("cannot derive a name from '{file}'; pass --name"))?
+            .to_string(),
+    };
+
+    let link = UdpLink::connect(bind.as_str(), peer.as_str())
+        .map_err(|e| format!("connecting to {peer} failed: {e}"))?;
+    let router = Router::new(Arc::new(link), &RouterOptions::new().forward(false))
+        .map_err(|e| format!("starting router failed: {e}"))?;
+    let report = router
+        .send_file(&file, &name, &opts)
+        .map_err(|e| format!("sending {file} failed: {e}"))?;
+    println!(
+        "sent {name}: {} bytes ({} resumed), {} chunks, {} retransmits",
+        report.bytes, report.resumed_from, report.chunks, report.retransmits
+    );
+    Ok(())
+}
+
+fn run_wire_dump(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let path = args.next().ok_or_else(|| USAGE.to_string())?;
+    if let Some(other) = args.next() {
+        return Err(format!("unknown option '{other}'\n{USAGE}"));
+    }
+    let frames = capture::read_capture(&path).map_err(|e| format!("reading {path} failed: {e}"))?;
+    for f in frames {
+        let ts = f.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let dir = match f.direction {
+            Direction::Tx => "TX",
+            Direction::Rx => "RX",
+        };
+        println!(
+            "{}.{:06} {dir} {}",
+            ts.as_secs(),
+            ts.subsec_micros(),
+            capture::describe(&f.frame)
+        );
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("janitor") => run_janitor(args),
+        Some("graph") => run_graph(args),
+        Some("schema") => run_schema(args),
+        Some("compat") => run_compat(args),
+        Some("send-file") => run_send_file(args),
+        Some("wire-dump") => run_wire_dump(args),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
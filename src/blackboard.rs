@@ -0,0 +1,477 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Shared key/value state with change notifications.
+//!
+//! A [`Blackboard`] keeps slowly-changing state, such as calibration
+//! values, where any process can [`set`](Blackboard::set) a key and the
+//! others [`get`](Blackboard::get) or [`watch`](Blackboard::watch) it:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::blackboard::Blackboard;
+//!
+//! let board = Blackboard::open("/calib")?;
+//! board.set("imu_bias", &[0.01f32, -0.02, 0.0])?;
+//! # #[cfg(feature = "callbacks")]
+//! board.watch("imu_bias", |bias: [f32; 3]| println!("new bias {bias:?}"))?;
+//! let _bias: Option<[f32; 3]> = board.get("imu_bias")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every key is a one-deep queue `<board>.<key>` that always holds the
+//! key's latest value, so the value outlives the process that set it.
+//! Reads and writes take the value out and put it (or its replacement)
+//! back, which keeps writers from racing each other. Watchers compare
+//! the value's version, which grows with every set, at a fixed interval;
+//! this suits state that changes now and then, not streams. Keys are
+//! recorded in the discovery [`registry`] for [`keys`](Blackboard::keys).
+//! Watching runs a thread per key and needs the `callbacks` feature.
+
+use super::{
+    cleanup,
+    clock::{Clock, MonotonicClock},
+    defaults, open_queue, realtime_after, realtime_now, registry, Msg, RecvBuf, MSG_PAYLOAD_SIZE,
+};
+use bytemuck::{Pod, Zeroable};
+use libc::{self, mqd_t};
+use std::{
+    collections::HashMap,
+    io,
+    os::raw::c_char,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+#[cfg(feature = "callbacks")]
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+/// Largest value a key can hold.
+pub const MAX_VALUE_SIZE: usize = MSG_PAYLOAD_SIZE - HEADER_LEN;
+
+const HEADER_LEN: usize = std::mem::size_of::<EntryHeader>();
+
+/// How often a [`Blackboard::watch`] looks for a new version.
+#[cfg(feature = "callbacks")]
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+const MSG_TYPE_ENTRY: u16 = 1;
+
+/// How long a receive waits for the value before looking again. The
+/// value is only ever out of its queue for a moment.
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long a value may be missing before it is given up as lost. It
+/// goes missing only if a process dies in the middle of an operation.
+const RECOVER_AFTER: Duration = Duration::from_secs(1);
+
+/// Leads the payload of a key's message.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct EntryHeader {
+    version: u64,
+    pid: u32,
+    size: u32,
+}
+
+/// Value of a key together with where it came from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Entry<T> {
+    pub value: T,
+    /// Grows with every set, across processes.
+    pub version: u64,
+    /// Process that set the value.
+    pub pid: u32,
+}
+
+/// One key's queue, shared with the key's watchers.
+struct Key {
+    name: String,
+    mqd: mqd_t,
+    _registration: registry::Registration,
+}
+
+impl Key {
+    /// Take the key's message, waiting up to [`RECOVER_AFTER`]; `None`
+    /// if it stayed missing.
+    fn take(&self) -> io::Result<Option<Msg>> {
+        let start = Instant::now();
+        let mut buf = RecvBuf::new();
+        loop {
+            let deadline = realtime_after(RETRY_INTERVAL);
+            let ret = unsafe {
+                libc::mq_timedreceive(
+                    self.mqd,
+                    buf.as_mut_ptr(),
+                    RecvBuf::LEN,
+                    std::ptr::null_mut(),
+                    &deadline,
+                )
+            };
+            if ret >= 0 {
+                return buf.msg(ret as usize).map(Some);
+            }
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::ETIMEDOUT) | Some(libc::EINTR) => {}
+                _ => return Err(err),
+            }
+            if start.elapsed() >= RECOVER_AFTER {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Put a message back without blocking. Returns false if the queue
+    /// already holds one, i.e. someone replaced a lost value meanwhile.
+    fn put(&self, msg: &Msg) -> io::Result<bool> {
+        let now = realtime_now();
+        let rc = unsafe {
+            libc::mq_timedsend(
+                self.mqd,
+                msg as *const Msg as *const c_char,
+                std::mem::size_of::<Msg>(),
+                0,
+                &now,
+            )
+        };
+        if rc == -1 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ETIMEDOUT) | Some(libc::EAGAIN) => Ok(false),
+                _ => Err(err),
+            };
+        }
+        Ok(true)
+    }
+
+    /// Current version and message, leaving the value in place.
+    fn read(&self) -> io::Result<Option<(EntryHeader, Msg)>> {
+        let Some(msg) = self.take()? else {
+            return Ok(None);
+        };
+        self.put(&msg)?;
+        Ok(header(&msg).map(|hdr| (hdr, msg)))
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        unsafe {
+            libc::mq_close(self.mqd);
+        }
+    }
+}
+
+fn header(msg: &Msg) -> Option<EntryHeader> {
+    if msg.hdr.msg_type != MSG_TYPE_ENTRY || (msg.hdr.len as usize) < HEADER_LEN {
+        return None;
+    }
+    let hdr: EntryHeader = bytemuck::pod_read_unaligned(&msg.payload[..HEADER_LEN]);
+    (hdr.version != 0).then_some(hdr)
+}
+
+fn decode<T: Pod>(name: &str, hdr: EntryHeader, msg: &Msg) -> io::Result<Entry<T>> {
+    let size = std::mem::size_of::<T>();
+    if hdr.size as usize != size || msg.hdr.len as usize != HEADER_LEN + size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{name} holds a {}-byte value, not {size}", hdr.size),
+        ));
+    }
+    Ok(Entry {
+        value: bytemuck::pod_read_unaligned(&msg.payload[HEADER_LEN..HEADER_LEN + size]),
+        version: hdr.version,
+        pid: hdr.pid,
+    })
+}
+
+/// Handle on a shared blackboard; see the module docs.
+pub struct Blackboard {
+    name: String,
+    keys: Mutex<HashMap<String, Arc<Key>>>,
+    #[cfg(feature = "callbacks")]
+    helpers: Mutex<Vec<(Arc<AtomicBool>, thread::JoinHandle<()>)>>,
+}
+
+impl Blackboard {
+    /// Open the blackboard `name`. Nothing is created until a key is set
+    /// or watched.
+    pub fn open(name: &str) -> io::Result<Self> {
+        let name = defaults::topic_name(name).into_owned();
+        if !name.starts_with('/') || name[1..].contains('/') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid blackboard name {name:?}"),
+            ));
+        }
+        Ok(Blackboard {
+            name,
+            keys: Mutex::new(HashMap::new()),
+            #[cfg(feature = "callbacks")]
+            helpers: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set `key` to `value` for every process. Returns the new version.
+    pub fn set<T: Pod>(&self, key: &str, value: &T) -> io::Result<u64> {
+        let bytes = bytemuck::bytes_of(value);
+        if bytes.len() > MAX_VALUE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("value of {} bytes exceeds {MAX_VALUE_SIZE}", bytes.len()),
+            ));
+        }
+        let key = self.key(key, true)?.expect("created");
+        loop {
+            // A lost value restarts from the clock, so versions still grow.
+            let prev = key.take()?.and_then(|msg| header(&msg));
+            let now = MonotonicClock.now().as_nanos() as u64;
+            let hdr = EntryHeader {
+                version: prev.map_or(now, |hdr| (hdr.version + 1).max(now)),
+                pid: std::process::id(),
+                size: bytes.len() as u32,
+            };
+            let mut data = [0u8; MSG_PAYLOAD_SIZE];
+            data[..HEADER_LEN].copy_from_slice(bytemuck::bytes_of(&hdr));
+            data[HEADER_LEN..HEADER_LEN + bytes.len()].copy_from_slice(bytes);
+            let msg = Msg::new(MSG_TYPE_ENTRY, &data[..HEADER_LEN + bytes.len()]);
+            if key.put(&msg)? {
+                return Ok(hdr.version);
+            }
+        }
+    }
+
+    /// Latest value of `key`, `None` if it was never set.
+    pub fn get<T: Pod>(&self, key: &str) -> io::Result<Option<T>> {
+        Ok(self.entry(key)?.map(|entry| entry.value))
+    }
+
+    /// Like [`get`](Self::get), with the value's version and setter.
+    pub fn entry<T: Pod>(&self, key: &str) -> io::Result<Option<Entry<T>>> {
+        let Some(key) = self.key(key, false)? else {
+            return Ok(None);
+        };
+        match key.read()? {
+            Some((hdr, msg)) => decode(&key.name, hdr, &msg).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Call `f` with the value of `key` now, if it is set, and after every
+    /// change from then on, for as long as this handle lives.
+    #[cfg(feature = "callbacks")]
+    pub fn watch<T, F>(&self, key: &str, f: F) -> io::Result<()>
+    where
+        T: Pod + Send,
+        F: Fn(T) + Send + 'static,
+    {
+        let key = self.key(key, true)?.expect("created");
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = Arc::clone(&running);
+
+        let handle = thread::spawn(move || {
+            let mut seen = 0;
+            while running_clone.load(Ordering::Relaxed) {
+                match key.read() {
+                    Ok(Some((hdr, msg))) if hdr.version != seen => {
+                        seen = hdr.version;
+                        match decode::<T>(&key.name, hdr, &msg) {
+                            Ok(entry) => f(entry.value),
+                            Err(err) => eprintln!("blackboard watch: {err}"),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => eprintln!("blackboard watch on {}: {err}", key.name),
+                }
+                thread::sleep(WATCH_INTERVAL);
+            }
+        });
+
+        self.helpers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((running, handle));
+        Ok(())
+    }
+
+    /// Every key set or watched on this blackboard so far, by any process.
+    pub fn keys(&self) -> io::Result<Vec<String>> {
+        let prefix = format!("{}.", self.name);
+        Ok(registry::topics()?
+            .into_iter()
+            .filter_map(|topic| topic.strip_prefix(&prefix).map(str::to_string))
+            .collect())
+    }
+
+    /// Delete `key` for every process. Watchers elsewhere keep their
+    /// last value; the key stays listed by [`keys`](Self::keys).
+    pub fn remove(&self, key: &str) -> io::Result<()> {
+        self.keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+        cleanup::unlink_resolved(&format!("{}.{key}", self.name))
+    }
+
+    /// Queue of `key`, opened on first use and created if `create`.
+    fn key(&self, key: &str, create: bool) -> io::Result<Option<Arc<Key>>> {
+        if key.is_empty() || key.contains('/') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid blackboard key {key:?}"),
+            ));
+        }
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(open) = keys.get(key) {
+            return Ok(Some(Arc::clone(open)));
+        }
+
+        let name = format!("{}.{key}", self.name);
+        let created = if create {
+            match open_queue(&name, libc::O_CREAT | libc::O_EXCL | libc::O_RDWR, Some(1)) {
+                Ok(mqd) => Some(mqd),
+                Err(err) if err.raw_os_error() == Some(libc::EEXIST) => None,
+                Err(err) => return Err(err),
+            }
+        } else {
+            None
+        };
+        let mqd = match created {
+            // Only the creator seeds the queue; anyone else could
+            // duplicate a value that is merely out of it right now.
+            Some(mqd) => {
+                let seed = Msg::new(MSG_TYPE_ENTRY, bytemuck::bytes_of(&EntryHeader::zeroed()));
+                let rc = unsafe {
+                    libc::mq_send(
+                        mqd,
+                        &seed as *const Msg as *const c_char,
+                        std::mem::size_of::<Msg>(),
+                        0,
+                    )
+                };
+                if rc == -1 {
+                    let err = io::Error::last_os_error();
+                    unsafe { libc::mq_close(mqd) };
+                    return Err(err);
+                }
+                mqd
+            }
+            None => match open_queue(&name, libc::O_RDWR, None) {
+                Ok(mqd) => mqd,
+                Err(err) if err.raw_os_error() == Some(libc::ENOENT) => {
+                    if !create {
+                        return Ok(None);
+                    }
+                    // Removed between the two opens; start over.
+                    drop(keys);
+                    return self.key(key, create);
+                }
+                Err(err) => return Err(err),
+            },
+        };
+        let registration = match registry::register(&name, registry::Role::Publisher) {
+            Ok(registration) => registration,
+            Err(err) => {
+                unsafe { libc::mq_close(mqd) };
+                return Err(err);
+            }
+        };
+        let open = Arc::new(Key {
+            name,
+            mqd,
+            _registration: registration,
+        });
+        keys.insert(key.to_string(), Arc::clone(&open));
+        Ok(Some(open))
+    }
+}
+
+#[cfg(feature = "callbacks")]
+impl Drop for Blackboard {
+    fn drop(&mut self) {
+        let helpers = std::mem::take(&mut *self.helpers.lock().unwrap_or_else(|e| e.into_inner()));
+        for (running, _) in &helpers {
+            running.store(false, Ordering::Relaxed);
+        }
+        for (_, handle) in helpers {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    #[cfg(feature = "callbacks")]
+    use std::sync::mpsc;
+
+    #[test]
+    fn values_are_shared_between_handles() {
+        let board = format!("/mq_ipc_test_board_{}", std::process::id());
+        let _tmp = TempTopic::with_name(&format!("{board}.gain"));
+        let a = Blackboard::open(&board).unwrap();
+        let b = Blackboard::open(&board).unwrap();
+
+        assert_eq!(b.get::<f64>("gain").unwrap(), None);
+        assert!(!a.keys().unwrap().contains(&"gain".to_string()));
+        let v1 = a.set("gain", &1.5f64).unwrap();
+        assert_eq!(b.get::<f64>("gain").unwrap(), Some(1.5));
+        let v2 = b.set("gain", &2.5f64).unwrap();
+        assert!(v2 > v1);
+
+        let entry = a.entry::<f64>("gain").unwrap().unwrap();
+        assert_eq!((entry.value, entry.version), (2.5, v2));
+        assert_eq!(entry.pid, std::process::id());
+        assert!(a.get::<u32>("gain").is_err());
+        assert!(a.keys().unwrap().contains(&"gain".to_string()));
+        assert!(a.set("bad/key", &0u8).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "callbacks")]
+    fn watch_sees_current_value_and_changes() {
+        let board = format!("/mq_ipc_test_board_watch_{}", std::process::id());
+        let _tmp = TempTopic::with_name(&format!("{board}.offset"));
+        let setter = Blackboard::open(&board).unwrap();
+        let watcher = Blackboard::open(&board).unwrap();
+        setter.set("offset", &7u32).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        watcher
+            .watch("offset", move |v: u32| tx.send(v).unwrap())
+            .unwrap();
+        let recv = || rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(recv(), 7);
+        setter.set("offset", &8u32).unwrap();
+        assert_eq!(recv(), 8);
+    }
+}
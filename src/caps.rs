@@ -0,0 +1,259 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Capability handshake between processes.
+//!
+//! Every process built with this crate can [`advertise`] what it
+//! understands: its [`PROTOCOL_VERSION`], the [extended header](crate::ext)
+//! fields, the `hdr.flags` layouts and the [`envelope`] records. The
+//! advertisements live on the [`CAPS_BOARD`] [`Blackboard`], one key per
+//! process, so late joiners find them too.
+//!
+//! Before enabling advanced features on a topic, a publisher asks what
+//! every live endpoint of it supports and lets [`Capabilities::restrict`]
+//! turn off what some peer could not decode, instead of corrupting its
+//! messages:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::{caps, MqTopic, TopicOptions};
+//!
+//! caps::advertise()?;
+//! let wanted = TopicOptions::new(8).sequenced(true).compress(true);
+//! let opts = caps::common_for("/lidar")?.restrict(&wanted);
+//! let lidar = MqTopic::with_options("/lidar", &opts)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Peers that never advertised, e.g. because they predate the handshake,
+//! are assumed to support [`Capabilities::BASELINE`]: plain messages only.
+
+use super::{
+    array, batch, blackboard::Blackboard, compress, envelope, ext, large, registry, text,
+    TopicOptions,
+};
+use bytemuck::{Pod, Zeroable};
+use std::io;
+
+/// Blackboard holding every process's [`Capabilities`].
+pub const CAPS_BOARD: &str = "/ipc_caps";
+
+/// Version of the wire protocol this crate speaks. Bumped when a change is
+/// not covered by a capability bit.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// What a process understands.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct Capabilities {
+    pub protocol: u32,
+    pub pid: u32,
+    /// Start time of `pid`, to tell a reused pid apart.
+    pub start_time: u64,
+    /// [`ExtHeader::present`](crate::ext::ExtHeader::present) bits.
+    pub ext: u32,
+    /// `hdr.flags` bits, e.g. [`ext::FLAG_EXT`].
+    pub flags: u16,
+    /// [`envelope`] `caps` bits.
+    pub envelope: u16,
+}
+
+impl Capabilities {
+    /// Assumed of peers that do not advertise.
+    pub const BASELINE: Capabilities = Capabilities {
+        protocol: 0,
+        pid: 0,
+        start_time: 0,
+        ext: 0,
+        flags: 0,
+        envelope: 0,
+    };
+
+    /// What this process understands.
+    pub fn local() -> Self {
+        let pid = std::process::id();
+        let mut flags = ext::FLAG_EXT | large::FLAG_LARGE | batch::FLAG_BATCH;
+        flags |= envelope::FLAG_ENVELOPE | array::FLAG_ARRAY | text::FLAG_BYTES;
+        if cfg!(feature = "lz4") {
+            flags |= compress::FLAG_COMPRESSED;
+        }
+        Capabilities {
+            protocol: PROTOCOL_VERSION,
+            pid,
+            start_time: registry::proc_start_time(pid).unwrap_or(0),
+            ext: ext::EXT_TRACE
+                | ext::EXT_SCHEMA
+                | ext::EXT_IDEMPOTENCY
+                | ext::EXT_SEQ
+                | ext::EXT_ORIGIN
+                | ext::EXT_GROUP
+                | ext::EXT_INSTANCE
+                | ext::EXT_TIMESTAMP
+                | ext::EXT_SESSION,
+            flags,
+            envelope: envelope::SUPPORTED_CAPS,
+        }
+    }
+
+    /// What both `self` and `other` understand. Keeps `self`'s identity.
+    pub fn intersect(&self, other: &Capabilities) -> Self {
+        Capabilities {
+            protocol: self.protocol.min(other.protocol),
+            ext: self.ext & other.ext,
+            flags: self.flags & other.flags,
+            envelope: self.envelope & other.envelope,
+            ..*self
+        }
+    }
+
+    /// Whether every one of the `ext` bits is understood.
+    pub fn has_ext(&self, bits: u32) -> bool {
+        self.ext & bits == bits
+    }
+
+    /// Whether every one of the `hdr.flags` bits is understood.
+    pub fn has_flags(&self, bits: u16) -> bool {
+        self.flags & bits == bits
+    }
+
+    /// `opts` with every feature these capabilities lack turned off.
+    pub fn restrict(&self, opts: &TopicOptions) -> TopicOptions {
+        let mut opts = opts.clone();
+        let ext = self.has_flags(ext::FLAG_EXT);
+        opts.propagate_trace &= ext && self.has_ext(ext::EXT_TRACE);
+        if !(ext && self.has_ext(ext::EXT_SCHEMA)) {
+            opts.schema_version = None;
+        }
+        opts.idempotency &= ext && self.has_ext(ext::EXT_IDEMPOTENCY);
+        opts.sequenced &= ext && self.has_ext(ext::EXT_SEQ);
+        opts.session &= ext && self.has_ext(ext::EXT_SESSION);
+        if !(ext && self.has_ext(ext::EXT_TIMESTAMP)) {
+            opts.timestamped = false;
+            opts.ttl = None;
+        }
+        opts.large &= self.has_flags(large::FLAG_LARGE);
+        opts.compress &= self.has_flags(compress::FLAG_COMPRESSED);
+        opts
+    }
+
+    fn is_alive(&self) -> bool {
+        registry::proc_start_time(self.pid).is_some_and(|t| t == self.start_time)
+    }
+}
+
+/// Publish this process's [`Capabilities`] on [`CAPS_BOARD`], and remove
+/// the advertisements of processes that are gone.
+pub fn advertise() -> io::Result<()> {
+    let board = Blackboard::open(CAPS_BOARD)?;
+    let local = Capabilities::local();
+    board.set(&local.pid.to_string(), &local)?;
+    for key in board.keys()? {
+        if let Some(caps) = board.get::<Capabilities>(&key)?
+            && !caps.is_alive()
+        {
+            board.remove(&key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Advertised capabilities of every live process, sorted by pid.
+pub fn peers() -> io::Result<Vec<Capabilities>> {
+    let board = Blackboard::open(CAPS_BOARD)?;
+    let mut peers = Vec::new();
+    for key in board.keys()? {
+        if let Some(caps) = board.get::<Capabilities>(&key)?
+            && caps.is_alive()
+        {
+            peers.push(caps);
+        }
+    }
+    peers.sort_by_key(|caps| caps.pid);
+    Ok(peers)
+}
+
+/// What this process and every live endpoint of `topic` in the
+/// [`registry`] understand.
+pub fn common_for(topic: &str) -> io::Result<Capabilities> {
+    let board = Blackboard::open(CAPS_BOARD)?;
+    let mut common = Capabilities::local();
+    let me = common.pid;
+    let mut pids: Vec<u32> = registry::endpoints(topic)?
+        .iter()
+        .map(|ep| ep.pid)
+        .collect();
+    pids.sort_unstable();
+    pids.dedup();
+    for pid in pids.into_iter().filter(|pid| *pid != me) {
+        let peer = board
+            .get::<Capabilities>(&pid.to_string())?
+            .filter(Capabilities::is_alive)
+            .filter(|caps| caps.pid == pid)
+            .unwrap_or(Capabilities::BASELINE);
+        common = common.intersect(&peer);
+    }
+    Ok(common)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::Role;
+
+    #[test]
+    fn advertised_capabilities_are_found() {
+        advertise().unwrap();
+        let me = std::process::id();
+        let found = peers().unwrap();
+        assert_eq!(
+            found.iter().find(|c| c.pid == me),
+            Some(&Capabilities::local())
+        );
+
+        let topic = format!("/mq_ipc_test_caps_{me}");
+        let _reg = registry::register(&topic, Role::Subscriber).unwrap();
+        assert_eq!(common_for(&topic).unwrap(), Capabilities::local());
+    }
+
+    #[test]
+    fn restrict_follows_the_weakest_peer() {
+        let wanted = TopicOptions::new(4)
+            .sequenced(true)
+            .schema_version(2)
+            .ttl(std::time::Duration::from_secs(1));
+        let full = Capabilities::local().restrict(&wanted);
+        assert!(full.sequenced && full.schema_version == Some(2) && full.ttl.is_some());
+
+        let mut older = Capabilities::local();
+        older.ext &= !ext::EXT_SEQ;
+        let common = Capabilities::local().intersect(&older);
+        let opts = common.restrict(&wanted);
+        assert!(!opts.sequenced && opts.schema_version == Some(2));
+
+        let none = Capabilities::local().intersect(&Capabilities::BASELINE);
+        let opts = none.restrict(&wanted);
+        assert!(opts.schema_version.is_none() && opts.ttl.is_none() && !opts.timestamped);
+        assert_eq!(none.protocol, 0);
+    }
+}
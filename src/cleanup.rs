@@ -0,0 +1,140 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Queue cleanup helpers for tests and development machines.
+//!
+//! Queues outlive the processes that create them, so crashed or
+//! interrupted runs leave entries behind in `/dev/mqueue`.
+
+use std::{
+    ffi::CString,
+    io,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::multi::list_topics;
+
+/// Unlink a queue by name. A queue that does not exist is not an error.
+pub fn unlink(name: &str) -> io::Result<()> {
+    unlink_resolved(&crate::defaults::topic_name(name))
+}
+
+/// [`unlink`] for a name that already carries the prefix.
+pub(crate) fn unlink_resolved(name: &str) -> io::Result<()> {
+    let cname = CString::new(name.as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid queue name"))?;
+
+    if unsafe { libc::mq_unlink(cname.as_ptr()) } == -1 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ENOENT) {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Unlink every queue whose name starts with `prefix` (e.g.
+/// `"/mq_ipc_test_"`) and return how many were removed.
+pub fn unlink_prefix(prefix: &str) -> io::Result<usize> {
+    let prefix = crate::defaults::topic_name(prefix);
+    let mut removed = 0;
+    for name in list_topics()? {
+        if name.starts_with(&*prefix) {
+            unlink_resolved(&name)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// A unique queue name that is unlinked when this guard is dropped,
+/// including during a panic unwind.
+///
+/// Open topics on [`TempTopic::name`] as usual; the queue itself stays
+/// usable by handles that are still open after the unlink.
+#[derive(Debug)]
+pub struct TempTopic {
+    name: String,
+}
+
+impl TempTopic {
+    /// Reserve a fresh name of the form `<prefix><pid>_<n>`.
+    pub fn new(prefix: &str) -> Self {
+        let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self::with_name(&format!("{prefix}{}_{n}", std::process::id()))
+    }
+
+    /// Guard an explicit queue name.
+    pub fn with_name(name: &str) -> Self {
+        TempTopic {
+            name: name.to_string(),
+        }
+    }
+
+    /// The guarded queue name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for TempTopic {
+    fn drop(&mut self) {
+        let _ = unlink(&self.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MqTopic;
+
+    #[test]
+    fn temp_topic_unlinks_on_drop() {
+        let name = {
+            let tmp = TempTopic::new("/mq_ipc_test_temp_");
+            let _topic = MqTopic::new(tmp.name(), 4).expect("failed to create topic");
+            tmp.name().to_string()
+        };
+
+        assert!(MqTopic::open_existing(&name).unwrap().is_none());
+    }
+
+    #[test]
+    fn temp_topic_unlinks_on_panic() {
+        let name = TempTopic::new("/mq_ipc_test_temp_panic_")
+            .name()
+            .to_string();
+        let inner = name.clone();
+
+        let result = std::panic::catch_unwind(move || {
+            let tmp = TempTopic::with_name(&inner);
+            let _topic = MqTopic::new(tmp.name(), 4).expect("failed to create topic");
+            panic!("boom");
+        });
+
+        assert!(result.is_err());
+        assert!(MqTopic::open_existing(&name).unwrap().is_none());
+    }
+}
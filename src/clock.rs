@@ -0,0 +1,202 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Time source abstraction for timestamps and application-level timing.
+//!
+//! Production code uses [`MonotonicClock`]; replay tools and deterministic
+//! tests install a [`SimClock`] with [`set_default_clock`] and move time
+//! by hand. The [`default_clock`] drives:
+//!
+//! - send stamps ([`TopicOptions::timestamped`](crate::TopicOptions::timestamped))
+//!   and [`ttl`](crate::TopicOptions::ttl) expiry,
+//! - [`Stamped::now`](crate::stamped::Stamped::now),
+//! - [`PeriodicPublisher`](crate::periodic::PeriodicPublisher),
+//!   [`Watchdog`](crate::watchdog::Watchdog) and [`Ping`](crate::ping::Ping),
+//! - [`ScriptedPublisher`](crate::testkit::ScriptedPublisher) playback.
+//!
+//! Everything else runs on real time: receive timeouts and `poll` waits
+//! are timed by the kernel, and throttling, [`shape`](crate::shape),
+//! reordering, link budgets and callback budgets measure with `Instant`.
+//! Lock and blackboard leases always use [`MonotonicClock`], whose epoch
+//! every process on the machine shares.
+
+use arc_swap::ArcSwap;
+use std::{
+    sync::{Arc, Condvar, LazyLock, Mutex},
+    time::Duration,
+};
+
+/// A source of monotonic time.
+pub trait Clock: Send + Sync {
+    /// Time elapsed since the clock's epoch.
+    fn now(&self) -> Duration;
+
+    /// Block the caller for `dur` of this clock's time.
+    fn sleep(&self, dur: Duration);
+
+    /// Block until `now() >= deadline`.
+    fn sleep_until(&self, deadline: Duration) {
+        let now = self.now();
+        if deadline > now {
+            self.sleep(deadline - now);
+        }
+    }
+}
+
+/// `CLOCK_MONOTONIC`. Its epoch is system-wide, so timestamps taken in
+/// different processes on the same machine are directly comparable.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        unsafe {
+            libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+        }
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+
+    fn sleep(&self, dur: Duration) {
+        std::thread::sleep(dur);
+    }
+
+    /// Absolute `clock_nanosleep`, so periodic callers do not accumulate
+    /// the time spent computing the remaining duration.
+    fn sleep_until(&self, deadline: Duration) {
+        let ts = libc::timespec {
+            tv_sec: deadline.as_secs() as libc::time_t,
+            tv_nsec: deadline.subsec_nanos() as libc::c_long,
+        };
+        while unsafe {
+            libc::clock_nanosleep(
+                libc::CLOCK_MONOTONIC,
+                libc::TIMER_ABSTIME,
+                &ts,
+                std::ptr::null_mut(),
+            )
+        } == libc::EINTR
+        {}
+    }
+}
+
+/// Manually driven clock for replay and tests.
+///
+/// Time only moves through [`SimClock::advance`] / [`SimClock::set`];
+/// sleepers wake once simulated time has passed their deadline.
+#[derive(Debug, Default)]
+pub struct SimClock {
+    now: Mutex<Duration>,
+    tick: Condvar,
+}
+
+impl SimClock {
+    pub fn new(start: Duration) -> Self {
+        SimClock {
+            now: Mutex::new(start),
+            tick: Condvar::new(),
+        }
+    }
+
+    /// Move time forward by `dur`.
+    pub fn advance(&self, dur: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now += dur;
+        self.tick.notify_all();
+    }
+
+    /// Jump to `t`. Going backwards is ignored: the clock stays monotonic.
+    pub fn set(&self, t: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        if t > *now {
+            *now = t;
+            self.tick.notify_all();
+        }
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn sleep(&self, dur: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        let deadline = *now + dur;
+        while *now < deadline {
+            now = self.tick.wait(now).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+static DEFAULT: LazyLock<ArcSwap<Arc<dyn Clock>>> =
+    LazyLock::new(|| ArcSwap::from_pointee(Arc::new(MonotonicClock)));
+
+/// The process-wide clock used when a component is not given one.
+pub fn default_clock() -> Arc<dyn Clock> {
+    Arc::clone(&DEFAULT.load())
+}
+
+/// Replace the process-wide clock, e.g. with a [`SimClock`] during replay.
+pub fn set_default_clock(clock: Arc<dyn Clock>) {
+    DEFAULT.store(Arc::new(clock));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn monotonic_moves_forward() {
+        let clock = MonotonicClock;
+        let a = clock.now();
+        clock.sleep(Duration::from_millis(2));
+        assert!(clock.now() >= a + Duration::from_millis(2));
+    }
+
+    #[test]
+    fn sim_clock_sleep_waits_for_advance() {
+        let clock = Arc::new(SimClock::new(Duration::from_secs(10)));
+        let sleeper = {
+            let clock = Arc::clone(&clock);
+            thread::spawn(move || {
+                clock.sleep(Duration::from_secs(5));
+                clock.now()
+            })
+        };
+
+        thread::sleep(Duration::from_millis(20));
+        assert!(!sleeper.is_finished());
+
+        clock.advance(Duration::from_secs(3));
+        thread::sleep(Duration::from_millis(20));
+        assert!(!sleeper.is_finished());
+
+        clock.set(Duration::from_secs(1));
+        assert_eq!(clock.now(), Duration::from_secs(13));
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(sleeper.join().unwrap(), Duration::from_secs(15));
+    }
+}
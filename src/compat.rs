@@ -0,0 +1,610 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Compatibility report between two versions of a payload layout.
+//!
+//! Before a changed message struct is deployed, [`compare`] its old and
+//! new [`Layout`] to see whether existing peers can still read it. Each
+//! [`Change`] is either wire-compatible (a renamed field or type, a field
+//! added in reserved space) or breaking (a different size, or a field that
+//! moved, changed type or disappeared):
+//!
+//! ```
+//! use mq_ipc::{compat, describe};
+//! # use bytemuck::{Pod, Zeroable};
+//! # #[repr(C)]
+//! # #[derive(Copy, Clone, Pod, Zeroable)]
+//! # struct V1 { rpm: f32, torque: f32 }
+//! # #[repr(C)]
+//! # #[derive(Copy, Clone, Pod, Zeroable)]
+//! # struct V2 { torque: f32, rpm: f32 }
+//! # describe!(V1 { rpm, torque });
+//! # describe!(V2 { torque, rpm });
+//! use mq_ipc::layout::Layout;
+//!
+//! let changes = compat::compare(&Layout::of::<V1>(), &Layout::of::<V2>());
+//! assert!(changes.iter().any(|c| c.is_breaking()));
+//! ```
+//!
+//! `mq-ipc compat OLD NEW` runs the same check on two files written by
+//! `mq-ipc schema`, in either format, and fails on breaking changes, so
+//! it can gate a CI job.
+
+use super::layout::{Field, Layout, Scalar};
+use std::fmt;
+
+/// One difference between two layouts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The type name changed; the bytes did not.
+    TypeRenamed { old: String, new: String },
+    /// The payload size changed.
+    SizeChanged { old: usize, new: usize },
+    /// The alignment changed; offsets are compared separately.
+    AlignChanged { old: usize, new: usize },
+    /// A field is new, in bytes that had no field before or after a size
+    /// change reported on its own.
+    FieldAdded { name: String, offset: usize },
+    /// A field is gone.
+    FieldRemoved { name: String },
+    /// A field has a new name but the same offset and type.
+    FieldRenamed { old: String, new: String },
+    /// A field is at a different offset.
+    FieldMoved {
+        name: String,
+        old: usize,
+        new: usize,
+    },
+    /// A field has a different primitive type or element count.
+    FieldRetyped {
+        name: String,
+        old: String,
+        new: String,
+    },
+    /// A topic in the old set is missing from the new one.
+    TopicRemoved,
+    /// A topic appears only in the new set.
+    TopicAdded,
+}
+
+impl Change {
+    /// Whether peers built against the old layout misread the new one.
+    pub fn is_breaking(&self) -> bool {
+        matches!(
+            self,
+            Change::SizeChanged { .. }
+                | Change::FieldRemoved { .. }
+                | Change::FieldMoved { .. }
+                | Change::FieldRetyped { .. }
+                | Change::TopicRemoved
+        )
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::TypeRenamed { old, new } => write!(f, "type renamed from {old} to {new}"),
+            Change::SizeChanged { old, new } => write!(f, "size changed from {old} to {new}"),
+            Change::AlignChanged { old, new } => {
+                write!(f, "alignment changed from {old} to {new}")
+            }
+            Change::FieldAdded { name, offset } => {
+                write!(f, "field {} added at offset {offset}", display_name(name))
+            }
+            Change::FieldRemoved { name } => write!(f, "field {} removed", display_name(name)),
+            Change::FieldRenamed { old, new } => write!(
+                f,
+                "field {} renamed to {}",
+                display_name(old),
+                display_name(new)
+            ),
+            Change::FieldMoved { name, old, new } => write!(
+                f,
+                "field {} moved from offset {old} to {new}",
+                display_name(name)
+            ),
+            Change::FieldRetyped { name, old, new } => write!(
+                f,
+                "field {} changed type from {old} to {new}",
+                display_name(name)
+            ),
+            Change::TopicRemoved => f.write_str("topic removed"),
+            Change::TopicAdded => f.write_str("topic added"),
+        }
+    }
+}
+
+fn display_name(name: &str) -> &str {
+    if name.is_empty() {
+        "(value)"
+    } else {
+        name
+    }
+}
+
+fn type_of(field: &Field) -> String {
+    match field.len {
+        Some(n) => format!("{}[{n}]", field.ty.name()),
+        None => field.ty.name().to_string(),
+    }
+}
+
+/// Every change from `old` to `new`, breaking ones first.
+pub fn compare(old: &Layout, new: &Layout) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old.size != new.size {
+        changes.push(Change::SizeChanged {
+            old: old.size,
+            new: new.size,
+        });
+    }
+
+    let find = |fields: &[Field], name: &str| fields.iter().position(|f| f.name == name);
+    let mut removed = Vec::new();
+    for f in &old.fields {
+        let Some(i) = find(&new.fields, &f.name) else {
+            removed.push(f);
+            continue;
+        };
+        let n = &new.fields[i];
+        if n.offset != f.offset {
+            changes.push(Change::FieldMoved {
+                name: f.name.clone(),
+                old: f.offset,
+                new: n.offset,
+            });
+        }
+        if (n.ty, n.len) != (f.ty, f.len) {
+            changes.push(Change::FieldRetyped {
+                name: f.name.clone(),
+                old: type_of(f),
+                new: type_of(n),
+            });
+        }
+    }
+
+    // A field that vanished while an identical one appeared in its place
+    // was renamed.
+    let mut added: Vec<&Field> = new
+        .fields
+        .iter()
+        .filter(|f| find(&old.fields, &f.name).is_none())
+        .collect();
+    let mut renamed = Vec::new();
+    for f in removed {
+        match added
+            .iter()
+            .position(|n| (n.offset, n.ty, n.len) == (f.offset, f.ty, f.len))
+        {
+            Some(i) => renamed.push(Change::FieldRenamed {
+                old: f.name.clone(),
+                new: added.remove(i).name.clone(),
+            }),
+            None => changes.push(Change::FieldRemoved {
+                name: f.name.clone(),
+            }),
+        }
+    }
+
+    changes.extend(renamed);
+    changes.extend(added.into_iter().map(|f| Change::FieldAdded {
+        name: f.name.clone(),
+        offset: f.offset,
+    }));
+    if old.align != new.align {
+        changes.push(Change::AlignChanged {
+            old: old.align,
+            new: new.align,
+        });
+    }
+    if old.type_name != new.type_name {
+        changes.push(Change::TypeRenamed {
+            old: old.type_name.clone(),
+            new: new.type_name.clone(),
+        });
+    }
+    changes.sort_by_key(|c| !c.is_breaking());
+    changes
+}
+
+/// [`compare`] every topic of two sets, e.g. two `mq-ipc schema` exports,
+/// matching them by name.
+pub fn compare_topics(old: &[(String, Layout)], new: &[(String, Layout)]) -> Vec<(String, Change)> {
+    let mut out = Vec::new();
+    for (topic, layout) in old {
+        match new.iter().find(|(t, _)| t == topic) {
+            Some((_, newer)) => out.extend(
+                compare(layout, newer)
+                    .into_iter()
+                    .map(|c| (topic.clone(), c)),
+            ),
+            None => out.push((topic.clone(), Change::TopicRemoved)),
+        }
+    }
+    for (topic, _) in new {
+        if !old.iter().any(|(t, _)| t == topic) {
+            out.push((topic.clone(), Change::TopicAdded));
+        }
+    }
+    out
+}
+
+/// Contents of a schema file: one layout, or layouts keyed by topic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schemas {
+    One(Layout),
+    Topics(Vec<(String, Layout)>),
+}
+
+impl Schemas {
+    /// Parse the output of `mq-ipc schema`: JSON Schema for one type or an
+    /// object of them keyed by topic, or the `--format layout` listing with
+    /// optional `# TOPIC` headers.
+    pub fn parse(text: &str) -> Option<Self> {
+        if text.trim_start().starts_with('{') {
+            let root = Json::parse(text)?;
+            if root.get("x-size").is_some() {
+                return from_json_schema(&root).map(Schemas::One);
+            }
+            let Json::Object(entries) = root else {
+                return None;
+            };
+            return entries
+                .iter()
+                .map(|(topic, schema)| Some((topic.clone(), from_json_schema(schema)?)))
+                .collect::<Option<_>>()
+                .map(Schemas::Topics);
+        }
+
+        if !text.lines().any(|l| l.starts_with("# ")) {
+            return Layout::parse(text).map(Schemas::One);
+        }
+        let mut topics = Vec::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("# ") {
+            let block = &rest[start + 2..];
+            let (topic, body) = block.split_once('\n')?;
+            let end = body.find("\n# ").map_or(body.len(), |i| i + 1);
+            topics.push((topic.trim().to_string(), Layout::parse(&body[..end])?));
+            rest = &body[end..];
+        }
+        Some(Schemas::Topics(topics))
+    }
+}
+
+/// Rebuild a layout from [`Layout::to_json_schema`] output.
+fn from_json_schema(root: &Json) -> Option<Layout> {
+    let mut fields = Vec::new();
+    if root.get("x-type").is_some() {
+        fields.push(json_field(root, String::new())?);
+    } else {
+        json_fields(root, "", &mut fields)?;
+    }
+    Some(Layout {
+        type_name: root.get("title")?.as_str()?.to_string(),
+        size: root.get("x-size")?.as_usize()?,
+        align: root.get("x-align")?.as_usize()?,
+        fields,
+    })
+}
+
+fn json_fields(object: &Json, prefix: &str, out: &mut Vec<Field>) -> Option<()> {
+    let Json::Object(props) = object.get("properties")? else {
+        return None;
+    };
+    for (key, value) in props {
+        let name = format!("{prefix}{key}");
+        if value.get("x-type").is_some() {
+            out.push(json_field(value, name)?);
+        } else {
+            json_fields(value, &format!("{name}."), out)?;
+        }
+    }
+    Some(())
+}
+
+fn json_field(value: &Json, name: String) -> Option<Field> {
+    let len = match value.get("type")?.as_str()? {
+        "array" => Some(value.get("maxItems")?.as_usize()?),
+        _ => None,
+    };
+    Some(Field {
+        name,
+        offset: value.get("x-offset")?.as_usize()?,
+        ty: Scalar::parse(value.get("x-type")?.as_str()?)?,
+        len,
+    })
+}
+
+/// Just enough JSON to read schema files back; objects keep key order,
+/// and arrays, booleans and null are checked but not kept.
+#[derive(Debug)]
+enum Json {
+    Other,
+    Number(f64),
+    String(String),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Option<Json> {
+        let mut p = Parser {
+            s: text.as_bytes(),
+            i: 0,
+        };
+        let value = p.value()?;
+        p.ws();
+        (p.i == p.s.len()).then_some(value)
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Json::Number(n) if n >= 0.0 && n.fract() == 0.0 => Some(n as usize),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn ws(&mut self) {
+        while self.s.get(self.i).is_some_and(u8::is_ascii_whitespace) {
+            self.i += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.ws();
+        let hit = self.s.get(self.i) == Some(&c);
+        self.i += hit as usize;
+        hit
+    }
+
+    fn literal(&mut self, word: &str) -> Option<Json> {
+        let end = self.i + word.len();
+        (self.s.get(self.i..end)? == word.as_bytes()).then(|| {
+            self.i = end;
+            Json::Other
+        })
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.ws();
+        match *self.s.get(self.i)? {
+            b'{' => {
+                self.i += 1;
+                let mut entries = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.ws();
+                        let key = self.string()?;
+                        if !self.eat(b':') {
+                            return None;
+                        }
+                        entries.push((key, self.value()?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Object(entries))
+            }
+            b'[' => {
+                self.i += 1;
+                if !self.eat(b']') {
+                    loop {
+                        self.value()?;
+                        if self.eat(b']') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Other)
+            }
+            b'"' => self.string().map(Json::String),
+            b't' => self.literal("true"),
+            b'f' => self.literal("false"),
+            b'n' => self.literal("null"),
+            _ => {
+                let start = self.i;
+                while self
+                    .s
+                    .get(self.i)
+                    .is_some_and(|c| c.is_ascii_digit() || b"+-.eE".contains(c))
+                {
+                    self.i += 1;
+                }
+                let text = std::str::from_utf8(&self.s[start..self.i]).ok()?;
+                text.parse().ok().map(Json::Number)
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.s.get(self.i) != Some(&b'"') {
+            return None;
+        }
+        self.i += 1;
+        let mut out = Vec::new();
+        loop {
+            match *self.s.get(self.i)? {
+                b'"' => {
+                    self.i += 1;
+                    return String::from_utf8(out).ok();
+                }
+                b'\\' => {
+                    let c = *self.s.get(self.i + 1)?;
+                    self.i += 2;
+                    let ch = match c {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let hex = std::str::from_utf8(self.s.get(self.i..self.i + 4)?).ok()?;
+                            self.i += 4;
+                            char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+                        }
+                        c => c as char,
+                    };
+                    out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                c => {
+                    out.push(c);
+                    self.i += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::describe;
+    use bytemuck::{Pod, Zeroable};
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable)]
+    struct Pose {
+        x: f32,
+        y: f32,
+        heading: f32,
+        reserved: u32,
+    }
+    describe!(Pose {
+        x,
+        y,
+        heading,
+        reserved
+    });
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable)]
+    struct PoseV2 {
+        x: f32,
+        y: f32,
+        yaw: f32,
+        quality: u32,
+    }
+    describe!(PoseV2 { x, y, yaw, quality });
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable)]
+    struct PoseV3 {
+        x: f64,
+        yaw: f32,
+        quality: u32,
+        y: [f32; 2],
+    }
+    describe!(PoseV3 { x, yaw, quality, y });
+
+    #[test]
+    fn classifies_changes() {
+        let (v1, v2, v3) = (
+            Layout::of::<Pose>(),
+            Layout::of::<PoseV2>(),
+            Layout::of::<PoseV3>(),
+        );
+        assert_eq!(
+            compare(&v1, &v2),
+            vec![
+                Change::FieldRenamed {
+                    old: "heading".into(),
+                    new: "yaw".into()
+                },
+                Change::FieldRenamed {
+                    old: "reserved".into(),
+                    new: "quality".into()
+                },
+                Change::TypeRenamed {
+                    old: "Pose".into(),
+                    new: "PoseV2".into()
+                },
+            ]
+        );
+
+        let breaking: Vec<_> = compare(&v2, &v3)
+            .into_iter()
+            .filter(Change::is_breaking)
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            breaking,
+            [
+                "size changed from 16 to 24",
+                "field x changed type from f32 to f64",
+                "field y moved from offset 4 to 16",
+                "field y changed type from f32 to f32[2]",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_both_export_formats() {
+        let layout = Layout::of::<PoseV3>();
+        assert_eq!(
+            Schemas::parse(&layout.to_json_schema()),
+            Some(Schemas::One(layout.clone()))
+        );
+
+        let json = format!(
+            "{{\"/pose\":{},\"/raw\":{}}}",
+            layout.to_json_schema(),
+            Layout::of::<[u16; 4]>().to_json_schema()
+        );
+        let listing = format!("# /pose\n{layout}\n# /raw\n{}", Layout::of::<[u16; 4]>());
+        let expected = Schemas::Topics(vec![
+            ("/pose".into(), layout),
+            ("/raw".into(), Layout::of::<[u16; 4]>()),
+        ]);
+        assert_eq!(Schemas::parse(&json), Some(expected.clone()));
+        assert_eq!(Schemas::parse(&listing), Some(expected));
+    }
+}
@@ -0,0 +1,130 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! LZ4 compression of typed values.
+//!
+//! With [`TopicOptions::compress`] (and the `lz4` feature),
+//! [`Topic::publish`](crate::Topic::publish) compresses values of at
+//! least [`MIN_COMPRESS_LEN`] bytes and sends the compressed form, marked
+//! with [`FLAG_COMPRESSED`], when it is smaller and fits in one message.
+//! A JSON-ish or sparse value larger than a message then travels inline
+//! instead of through a [`large`](crate::large) segment, and values that
+//! did fit take up less of the queue. Anything else is sent exactly as
+//! without the option.
+//!
+//! Typed subscribers decompress whether or not they set the option.
+//! Builds without the `lz4` feature cannot, and treat compressed
+//! messages as undecodable.
+
+use super::TopicOptions;
+use std::io;
+
+/// `hdr.flags` bit: the data is an LZ4 block prefixed with the original
+/// length as a little-endian `u32`.
+pub const FLAG_COMPRESSED: u16 = 0x0008;
+
+/// Values shorter than this are never compressed.
+pub const MIN_COMPRESS_LEN: usize = 64;
+
+/// Largest original length a subscriber will decompress to.
+pub const MAX_DECOMPRESSED_LEN: usize = 1 << 20;
+
+pub(crate) fn check_options(opts: &TopicOptions) -> io::Result<()> {
+    if !cfg!(feature = "lz4") {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "compression needs the `lz4` feature",
+        ));
+    }
+    if opts.schema_version.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "compression cannot be combined with a schema version",
+        ));
+    }
+    Ok(())
+}
+
+/// Compressed form of `bytes`, if it is worth sending.
+#[cfg(feature = "lz4")]
+pub(crate) fn compress(bytes: &[u8]) -> Option<Vec<u8>> {
+    if bytes.len() < MIN_COMPRESS_LEN || bytes.len() > MAX_DECOMPRESSED_LEN {
+        return None;
+    }
+    let packed = lz4_flex::block::compress_prepend_size(bytes);
+    (packed.len() < bytes.len() && packed.len() <= crate::MSG_PAYLOAD_SIZE).then_some(packed)
+}
+
+#[cfg(not(feature = "lz4"))]
+pub(crate) fn compress(_bytes: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+/// The original bytes of a [`FLAG_COMPRESSED`] payload.
+#[cfg(feature = "lz4")]
+pub(crate) fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    if len > MAX_DECOMPRESSED_LEN {
+        return None;
+    }
+    let mut out = vec![0u8; len];
+    match lz4_flex::block::decompress_into(&data[4..], &mut out) {
+        Ok(n) if n == len => Some(out),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "lz4"))]
+pub(crate) fn decompress(_data: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(all(test, feature = "lz4"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, Topic};
+    use std::{sync::mpsc, time::Duration};
+
+    #[test]
+    fn oversized_value_fits_once_compressed() {
+        let mut value = [0u32; 256];
+        value[0] = 7;
+        value[255] = 9;
+        let bytes = bytemuck::bytes_of(&value);
+        let packed = compress(bytes).unwrap();
+        assert!(packed.len() <= crate::MSG_PAYLOAD_SIZE);
+        assert_eq!(decompress(&packed).unwrap(), bytes);
+        assert!(compress(&[1u8; MIN_COMPRESS_LEN - 1]).is_none());
+
+        let tmp = TempTopic::new("/mq_ipc_test_compress_");
+        let opts = TopicOptions::new(4).compress(true).strict(true);
+        let topic: Topic<[u32; 256]> = Topic::with_options(tmp.name(), &opts).unwrap();
+        let (tx, rx) = mpsc::channel();
+        topic.subscribe(move |v| tx.send(v).unwrap());
+        topic.publish(&value, 1, 0).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), value);
+
+        let versioned = TopicOptions::new(4).compress(true).schema_version(2);
+        assert!(Topic::<u32>::with_options(tmp.name(), &versioned).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Topology files shared by every process of a system.
+//!
+//! Queue parameters live in one reviewed TOML file instead of constants
+//! scattered over publishers and subscribers:
+//!
+//! ```toml
+//! [[topic]]
+//! name = "/motor/state"
+//! size = 12          # payload bytes, checked against the Rust type
+//! depth = 4
+//! wire = true        # mirror publishes to /ipc_tx
+//! qos = { conflate = true, strict = true }
+//!
+//! [remap]
+//! "/motor/state" = "/robot1/motor/state"
+//! ```
+//!
+//! Code keeps using the logical name; [`Config::open`] applies the remap
+//! and the QoS. Requires the `config` feature.
+
+use super::{wire::WireTx, Topic, TopicOptions, MSG_PAYLOAD_SIZE};
+use bytemuck::{Pod, Zeroable};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    os::raw::c_long,
+    path::{Path, PathBuf},
+};
+
+/// Depth used for topics that do not set one.
+pub const DEFAULT_DEPTH: c_long = 10;
+
+/// Delivery options of a topic; maps onto [`TopicOptions`].
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Qos {
+    pub conflate: bool,
+    pub strict: bool,
+    pub dead_letter: bool,
+    pub nonblocking: bool,
+    pub propagate_trace: bool,
+    pub journal: Option<PathBuf>,
+    pub journal_sync: bool,
+}
+
+/// One `[[topic]]` entry.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TopicConfig {
+    pub name: String,
+    /// Expected payload size in bytes, if pinned.
+    #[serde(default)]
+    pub size: Option<usize>,
+    #[serde(default = "default_depth")]
+    pub depth: c_long,
+    #[serde(default)]
+    pub qos: Qos,
+    /// Mirror publishes to the wire TX topic.
+    #[serde(default)]
+    pub wire: bool,
+}
+
+fn default_depth() -> c_long {
+    DEFAULT_DEPTH
+}
+
+impl TopicConfig {
+    /// The [`TopicOptions`] this entry describes.
+    pub fn options(&self) -> TopicOptions {
+        let mut opts = TopicOptions::new(self.depth)
+            .conflate(self.qos.conflate)
+            .strict(self.qos.strict)
+            .dead_letter(self.qos.dead_letter)
+            .nonblocking(self.qos.nonblocking)
+            .propagate_trace(self.qos.propagate_trace)
+            .journal_sync(self.qos.journal_sync);
+        if let Some(path) = &self.qos.journal {
+            opts = opts.journal(path);
+        }
+        opts
+    }
+}
+
+/// A parsed and validated topology file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(rename = "topic")]
+    pub topics: Vec<TopicConfig>,
+    /// Logical name to actual queue name.
+    pub remap: HashMap<String, String>,
+}
+
+/// Read and validate a topology file.
+pub fn load(path: impl AsRef<Path>) -> io::Result<Config> {
+    let text = std::fs::read_to_string(path.as_ref())?;
+    Config::parse(&text)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.as_ref().display())))
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Config {
+    /// Parse and validate TOML text.
+    pub fn parse(text: &str) -> io::Result<Config> {
+        let config: Config = toml::from_str(text).map_err(|err| invalid(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        let mut seen = HashSet::new();
+        for topic in &self.topics {
+            if !topic.name.starts_with('/') {
+                return Err(invalid(format!("{}: name must start with '/'", topic.name)));
+            }
+            if !seen.insert(topic.name.as_str()) {
+                return Err(invalid(format!("{}: listed twice", topic.name)));
+            }
+            if topic.depth <= 0 {
+                return Err(invalid(format!("{}: depth must be positive", topic.name)));
+            }
+            if let Some(size) = topic.size
+                && size > MSG_PAYLOAD_SIZE
+            {
+                return Err(invalid(format!(
+                    "{}: size {size} exceeds {MSG_PAYLOAD_SIZE} bytes",
+                    topic.name
+                )));
+            }
+        }
+        for (from, to) in &self.remap {
+            if !to.starts_with('/') {
+                return Err(invalid(format!("remap {from}: {to} must start with '/'")));
+            }
+        }
+        Ok(())
+    }
+
+    /// The entry for logical name `name`.
+    pub fn topic(&self, name: &str) -> Option<&TopicConfig> {
+        self.topics.iter().find(|t| t.name == name)
+    }
+
+    /// Queue name that logical name `name` maps to.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.remap.get(name).map(String::as_str).unwrap_or(name)
+    }
+
+    fn checked<T>(&self, name: &str) -> io::Result<&TopicConfig> {
+        let topic = self.topic(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{name} is not configured"))
+        })?;
+        if let Some(size) = topic.size
+            && size != std::mem::size_of::<T>()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{name}: configured size {size}, type is {} bytes",
+                    std::mem::size_of::<T>()
+                ),
+            ));
+        }
+        Ok(topic)
+    }
+
+    /// Open the configured topic `name` with its remap and QoS applied.
+    pub fn open<T>(&self, name: &str) -> io::Result<Topic<T>>
+    where
+        T: Pod + Zeroable + Send + Sync + 'static,
+    {
+        let topic = self.checked::<T>(name)?;
+        Topic::with_options(self.resolve(name), &topic.options())
+    }
+
+    /// Open the configured topic `name` as a [`WireTx`]. Fails unless the
+    /// entry sets `wire = true`; the mirror uses the default QoS.
+    pub fn open_wire<T>(&self, name: &str) -> io::Result<WireTx<T>>
+    where
+        T: Pod + Zeroable + Send + Sync + 'static,
+    {
+        let topic = self.checked::<T>(name)?;
+        if !topic.wire {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name} is not wire mirrored"),
+            ));
+        }
+        WireTx::new(self.resolve(name), topic.depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        [[topic]]
+        name = "/motor/state"
+        size = 12
+        depth = 4
+        wire = true
+        qos = { conflate = true, strict = true }
+
+        [[topic]]
+        name = "/imu/raw"
+
+        [remap]
+        "/motor/state" = "/robot1/motor/state"
+    "#;
+
+    #[test]
+    fn parses_topics_qos_and_remaps() {
+        let config = Config::parse(SAMPLE).unwrap();
+        assert_eq!(config.topics.len(), 2);
+
+        let motor = config.topic("/motor/state").unwrap();
+        assert_eq!((motor.size, motor.depth, motor.wire), (Some(12), 4, true));
+        let opts = motor.options();
+        assert!(opts.conflate && opts.strict && !opts.dead_letter);
+
+        assert_eq!(config.topic("/imu/raw").unwrap().depth, DEFAULT_DEPTH);
+        assert_eq!(config.resolve("/motor/state"), "/robot1/motor/state");
+        assert_eq!(config.resolve("/imu/raw"), "/imu/raw");
+
+        let err = config.open::<u32>("/motor/state").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        for bad in [
+            "[[topic]]\nname = \"no_slash\"",
+            "[[topic]]\nname = \"/a\"\nsize = 4096",
+            "[[topic]]\nname = \"/a\"\n[[topic]]\nname = \"/a\"",
+            "[[topic]]\nname = \"/a\"\nqos = { bogus = true }",
+        ] {
+            let err = Config::parse(bad).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{bad}");
+        }
+    }
+}
@@ -0,0 +1,212 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Idempotency keys and subscriber-side duplicate suppression.
+//!
+//! Journal replay and at-least-once forwarding can deliver a message
+//! twice. Publishers on topics created with
+//! [`TopicOptions::idempotency`](crate::TopicOptions::idempotency) give
+//! every message a unique key in the extended header (or pass their own
+//! to [`MqTopic::publish_with_key`](crate::MqTopic::publish_with_key)).
+//! The key is stamped before the message is journaled, so a replayed
+//! message carries the same key as the original. Subscribing handles
+//! created with [`TopicOptions::dedup`](crate::TopicOptions::dedup)
+//! remember the most recent keys in a [`DedupCache`] and drop repeats
+//! before any callback runs.
+
+use super::{
+    ext::{ExtHeader, EXT_IDEMPOTENCY},
+    Msg,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The idempotency key of `msg`, if it has one.
+pub fn key_of(msg: &Msg) -> Option<u64> {
+    msg.ext()
+        .filter(|ext| ext.has(EXT_IDEMPOTENCY))
+        .map(|ext| ext.key)
+}
+
+pub(crate) fn write_key(ext: &mut ExtHeader, key: u64) {
+    ext.present |= EXT_IDEMPOTENCY;
+    ext.key = key;
+}
+
+/// Source of keys that are unique per handle with high probability.
+pub(crate) struct KeyGenerator {
+    base: u64,
+    next: AtomicU64,
+}
+
+impl KeyGenerator {
+    pub(crate) fn new() -> Self {
+        KeyGenerator {
+            base: unique_seed(),
+            next: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn next(&self) -> u64 {
+        self.base
+            .wrapping_add(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// A random-looking value that differs between calls and processes.
+pub(crate) fn unique_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    // Calls in the same nanosecond still differ through the counter.
+    static CALLS: AtomicU64 = AtomicU64::new(0);
+    splitmix64(
+        nanos
+            ^ ((std::process::id() as u64) << 40)
+            ^ CALLS.fetch_add(1, Ordering::Relaxed).rotate_right(8),
+    )
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Bounded least-recently-seen set of keys.
+pub struct DedupCache {
+    capacity: usize,
+    tick: u64,
+    // key -> tick it was last seen at, and the reverse for eviction.
+    seen: HashMap<u64, u64>,
+    by_tick: BTreeMap<u64, u64>,
+}
+
+impl DedupCache {
+    /// Remember up to `capacity` keys (at least one).
+    pub fn new(capacity: usize) -> Self {
+        DedupCache {
+            capacity: capacity.max(1),
+            tick: 0,
+            seen: HashMap::new(),
+            by_tick: BTreeMap::new(),
+        }
+    }
+
+    /// Record `key`. Returns `true` the first time it is seen, `false`
+    /// for a duplicate (which also refreshes it).
+    pub fn insert(&mut self, key: u64) -> bool {
+        self.tick += 1;
+        if let Some(old) = self.seen.insert(key, self.tick) {
+            self.by_tick.remove(&old);
+            self.by_tick.insert(self.tick, key);
+            return false;
+        }
+
+        self.by_tick.insert(self.tick, key);
+        if self.seen.len() > self.capacity
+            && let Some((_, oldest)) = self.by_tick.pop_first()
+        {
+            self.seen.remove(&oldest);
+        }
+        true
+    }
+
+    pub fn contains(&self, key: u64) -> bool {
+        self.seen.contains_key(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_seen() {
+        let mut cache = DedupCache::new(2);
+        assert!(cache.insert(1));
+        assert!(cache.insert(2));
+        assert!(!cache.insert(1)); // refreshes 1
+        assert!(cache.insert(3)); // evicts 2
+        assert!(cache.contains(1) && cache.contains(3) && !cache.contains(2));
+        assert_eq!(cache.len(), 2);
+
+        let keys = KeyGenerator::new();
+        assert_ne!(keys.next(), keys.next());
+    }
+
+    #[test]
+    fn replayed_and_repeated_messages_are_dropped() {
+        use crate::{cleanup::TempTopic, MqTopic, TopicOptions};
+        use std::{
+            sync::{Arc, Mutex},
+            thread,
+            time::Duration,
+        };
+
+        let tmp = TempTopic::new("/mq_ipc_test_dedup_");
+        let path =
+            std::env::temp_dir().join(format!("mq_ipc_test_dedup_{}.journal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let since = SystemTime::now();
+
+        let opts = TopicOptions::new(8)
+            .journal(&path)
+            .idempotency(true)
+            .dedup(16);
+        let topic = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_clone = Arc::clone(&got);
+        topic.subscribe(move |m| got_clone.lock().unwrap().push(m.hdr.msg_type));
+
+        topic.publish(&Msg::new(1, b"a"), 0).unwrap();
+        topic.publish(&Msg::new(2, b"b"), 0).unwrap();
+        topic.publish_with_key(&Msg::new(3, b"c"), 0, 42).unwrap();
+        topic.publish_with_key(&Msg::new(3, b"c"), 0, 42).unwrap();
+        // Journaled copies carry the original keys.
+        assert_eq!(topic.replay_journal(since).unwrap(), 4);
+        topic.publish(&Msg::new(4, b"d"), 0).unwrap();
+
+        for _ in 0..100 {
+            if got.lock().unwrap().contains(&4) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(*got.lock().unwrap(), vec![1, 2, 3, 4]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
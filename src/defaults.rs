@@ -0,0 +1,106 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Process-wide defaults taken from the environment.
+//!
+//! Read whenever a topic is created, so CI jobs, containers and
+//! multi-robot setups can repoint a whole graph without code changes:
+//!
+//! | Variable                | Effect                                          |
+//! | ----------------------- | ----------------------------------------------- |
+//! | `MQ_IPC_PREFIX`         | inserted after the leading `/` of every name    |
+//! | `MQ_IPC_DEFAULT_MAXMSG` | depth used by [`TopicOptions::default`]         |
+//! | `MQ_IPC_MAX_PAYLOAD`    | publishes above this many bytes are rejected    |
+//! | `MQ_IPC_REGISTRY_DIR`   | discovery [`registry`](crate::registry) root    |
+//!
+//! With `MQ_IPC_PREFIX=robot1.` the topic `/motor_state` opens the queue
+//! `/robot1.motor_state`. Unparsable values are ignored.
+//!
+//! [`TopicOptions::default`]: crate::TopicOptions
+
+use super::MSG_PAYLOAD_SIZE;
+use std::{borrow::Cow, env, os::raw::c_long};
+
+pub const PREFIX_VAR: &str = "MQ_IPC_PREFIX";
+pub const DEFAULT_MAXMSG_VAR: &str = "MQ_IPC_DEFAULT_MAXMSG";
+pub const MAX_PAYLOAD_VAR: &str = "MQ_IPC_MAX_PAYLOAD";
+
+/// Linux' default `msg_max` depth.
+pub const FALLBACK_MAXMSG: c_long = 10;
+
+/// The configured name prefix, without a leading `/`.
+pub fn prefix() -> Option<String> {
+    let prefix = env::var(PREFIX_VAR).ok()?;
+    let prefix = prefix.trim_start_matches('/');
+    (!prefix.is_empty()).then(|| prefix.to_string())
+}
+
+/// Queue name that `name` maps to under the current prefix.
+pub fn topic_name(name: &str) -> Cow<'_, str> {
+    match prefix() {
+        Some(prefix) => apply_prefix(&prefix, name),
+        None => Cow::Borrowed(name),
+    }
+}
+
+/// Insert `prefix` after the leading `/` of `name`.
+///
+/// Public constructors apply it exactly once; names read back from the
+/// registry or derived inside the crate are already resolved and must not
+/// go through here again.
+pub fn apply_prefix<'a>(prefix: &str, name: &'a str) -> Cow<'a, str> {
+    match name.strip_prefix('/') {
+        Some(rest) => Cow::Owned(format!("/{prefix}{rest}")),
+        None => Cow::Borrowed(name),
+    }
+}
+
+/// Default queue depth.
+pub fn maxmsg() -> c_long {
+    env::var(DEFAULT_MAXMSG_VAR)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(FALLBACK_MAXMSG)
+}
+
+/// Largest payload a topic accepts, at most [`MSG_PAYLOAD_SIZE`].
+pub fn max_payload() -> usize {
+    env::var(MAX_PAYLOAD_VAR)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .map_or(MSG_PAYLOAD_SIZE, |n: usize| n.min(MSG_PAYLOAD_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_is_inserted_once() {
+        assert_eq!(apply_prefix("robot1.", "/motor"), "/robot1.motor");
+        assert_eq!(apply_prefix("robot1.", "relative"), "relative");
+        // A name that merely starts with the prefix text is still prefixed.
+        assert_eq!(apply_prefix("ipc", "/ipc_tx"), "/ipcipc_tx");
+    }
+}
@@ -0,0 +1,260 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Split one topic into several by message type or predicate.
+//!
+//! The inverse of [`merge`](crate::merge): a [`Demux`] reads a single
+//! source, typically a legacy queue that carries everything, and
+//! republishes each message on the first output whose rule matches.
+//!
+//! ```no_run
+//! use mq_ipc::{demux::{Demux, Routes}, TopicOptions};
+//!
+//! let routes = Routes::new()
+//!     .msg_type(1, "/motor/state")
+//!     .msg_type(2, "/motor/cmd")
+//!     .when(|msg| msg.hdr.msg_type >= 0x100, "/diagnostics")
+//!     .fallback("/legacy/unrouted");
+//! let _demux = Demux::new("/legacy", routes, &TopicOptions::new(16))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Messages matching no rule go to the fallback if there is one and are
+//! otherwise counted in [`DemuxStats::unmatched`] and dropped.
+
+use super::{current_priority, MqTopic, Msg, TopicOptions};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+type Predicate = Box<dyn Fn(&Msg) -> bool + Send + Sync + 'static>;
+
+enum Rule {
+    MsgType(u16),
+    When(Predicate),
+}
+
+impl Rule {
+    fn matches(&self, msg: &Msg) -> bool {
+        match self {
+            Rule::MsgType(ty) => msg.hdr.msg_type == *ty,
+            Rule::When(f) => f(msg),
+        }
+    }
+}
+
+/// Ordered routing rules for a [`Demux`]; the first match wins.
+#[derive(Default)]
+pub struct Routes {
+    rules: Vec<(Rule, String)>,
+    fallback: Option<String>,
+}
+
+impl Routes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send messages of type `msg_type` to `output`.
+    pub fn msg_type(mut self, msg_type: u16, output: &str) -> Self {
+        self.rules
+            .push((Rule::MsgType(msg_type), output.to_string()));
+        self
+    }
+
+    /// Send messages for which `f` returns true to `output`.
+    pub fn when<F>(mut self, f: F, output: &str) -> Self
+    where
+        F: Fn(&Msg) -> bool + Send + Sync + 'static,
+    {
+        self.rules
+            .push((Rule::When(Box::new(f)), output.to_string()));
+        self
+    }
+
+    /// Send messages no rule matched to `output`.
+    pub fn fallback(mut self, output: &str) -> Self {
+        self.fallback = Some(output.to_string());
+        self
+    }
+}
+
+/// What a [`Demux`] has done so far.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DemuxStats {
+    /// Messages published on an output, fallback included.
+    pub routed: u64,
+    /// Messages dropped because nothing matched and there is no fallback.
+    pub unmatched: u64,
+    /// Messages lost because publishing on their output failed.
+    pub failed: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    routed: AtomicU64,
+    unmatched: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Routes messages from one source topic to several outputs until
+/// dropped.
+pub struct Demux {
+    source: MqTopic,
+    outputs: Vec<Arc<MqTopic>>,
+    counters: Arc<Counters>,
+}
+
+impl Demux {
+    /// Subscribe to `source` and start routing. The source and every
+    /// output are opened with `opts`; rules naming the same output share
+    /// one handle.
+    pub fn new(source: &str, routes: Routes, opts: &TopicOptions) -> io::Result<Self> {
+        let mut outputs: Vec<Arc<MqTopic>> = Vec::new();
+        let mut by_name = HashMap::new();
+        let mut open = |name: &str| -> io::Result<usize> {
+            if let Some(&i) = by_name.get(name) {
+                return Ok(i);
+            }
+            outputs.push(Arc::new(MqTopic::with_options(name, opts)?));
+            by_name.insert(name.to_string(), outputs.len() - 1);
+            Ok(outputs.len() - 1)
+        };
+
+        let mut rules = Vec::with_capacity(routes.rules.len());
+        for (rule, name) in routes.rules {
+            rules.push((rule, open(&name)?));
+        }
+        let fallback = routes.fallback.as_deref().map(&mut open).transpose()?;
+
+        let source = MqTopic::with_options(source, opts)?;
+        let counters = Arc::new(Counters::default());
+
+        let targets = outputs.clone();
+        let count = Arc::clone(&counters);
+        source.subscribe(move |msg| {
+            let out = rules
+                .iter()
+                .find(|(rule, _)| rule.matches(&msg))
+                .map(|(_, i)| *i)
+                .or(fallback);
+            let Some(out) = out.map(|i| &targets[i]) else {
+                count.unmatched.fetch_add(1, Ordering::Relaxed);
+                return;
+            };
+            match out.publish(&msg, current_priority().unwrap_or(0)) {
+                Ok(()) => count.routed.fetch_add(1, Ordering::Relaxed),
+                Err(err) => {
+                    eprintln!("demux to {} failed: {err}", out.name());
+                    count.failed.fetch_add(1, Ordering::Relaxed)
+                }
+            };
+        });
+
+        Ok(Demux {
+            source,
+            outputs,
+            counters,
+        })
+    }
+
+    /// Name of the topic being split.
+    pub fn source(&self) -> &str {
+        self.source.name()
+    }
+
+    /// Names of the distinct output topics, in first-mentioned order.
+    pub fn outputs(&self) -> impl Iterator<Item = &str> {
+        self.outputs.iter().map(|t| t.name())
+    }
+
+    /// Counters since the demux was created.
+    pub fn stats(&self) -> DemuxStats {
+        DemuxStats {
+            routed: self.counters.routed.load(Ordering::Relaxed),
+            unmatched: self.counters.unmatched.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::mpsc, thread, time::Duration};
+
+    #[test]
+    fn routes_by_type_then_predicate() {
+        let tmp_src = TempTopic::new("/mq_ipc_test_demux_src_");
+        let tmp_a = TempTopic::new("/mq_ipc_test_demux_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_demux_b_");
+        let opts = TopicOptions::new(8);
+
+        let routes = Routes::new()
+            .msg_type(1, tmp_a.name())
+            .when(|msg| msg.hdr.msg_type >= 10, tmp_b.name())
+            .msg_type(2, tmp_a.name());
+        let demux = Demux::new(tmp_src.name(), routes, &opts).unwrap();
+        assert_eq!(demux.outputs().count(), 2);
+
+        let (tx, rx) = mpsc::channel();
+        let mut sinks = Vec::new();
+        for (tag, name) in [('a', tmp_a.name()), ('b', tmp_b.name())] {
+            let sink = MqTopic::new(name, 8).unwrap();
+            let tx = tx.clone();
+            sink.subscribe(move |msg| tx.send((tag, msg.hdr.msg_type)).unwrap());
+            sinks.push(sink);
+        }
+
+        let source = MqTopic::new(tmp_src.name(), 8).unwrap();
+        for ty in [1, 11, 2, 5] {
+            source.publish(&Msg::new(ty, &[]), 0).unwrap();
+        }
+
+        let mut got: Vec<_> = (0..3)
+            .map(|_| rx.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        got.sort();
+        assert_eq!(got, vec![('a', 1), ('a', 2), ('b', 11)]);
+        for _ in 0..50 {
+            if demux.stats().unmatched == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            demux.stats(),
+            DemuxStats {
+                routed: 3,
+                unmatched: 1,
+                failed: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,212 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Dead-letter queues for messages that could not be delivered.
+//!
+//! With [`TopicOptions::dead_letter`](crate::TopicOptions::dead_letter)
+//! enabled, messages that fail strict decoding, carry an impossible length,
+//! make a callback panic, or arrive while nobody is subscribed are
+//! forwarded to `<topic>.dlq` as a [`DeadLetter`] instead of being dropped
+//! silently. Inspect them with a regular `Topic<DeadLetter>`.
+
+use super::{open_queue, Msg, MSG_PAYLOAD_SIZE};
+use bytemuck::{Pod, Zeroable};
+use libc::mqd_t;
+use std::{
+    io,
+    os::raw::{c_char, c_long},
+};
+
+/// Payload bytes of the original message kept in a [`DeadLetter`].
+pub const DEAD_LETTER_DATA: usize = MSG_PAYLOAD_SIZE - 8;
+
+/// Why a message ended up in the dead-letter queue.
+#[repr(u16)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    /// Payload length does not match the typed topic (strict mode).
+    Decode = 1,
+    /// Header claims more payload than a `Msg` can carry.
+    Oversize = 2,
+    /// A subscriber callback panicked while handling it.
+    Panic = 3,
+    /// No subscriber was registered when it arrived.
+    Unhandled = 4,
+}
+
+impl DeadLetterReason {
+    pub fn from_u16(v: u16) -> Option<Self> {
+        match v {
+            1 => Some(Self::Decode),
+            2 => Some(Self::Oversize),
+            3 => Some(Self::Panic),
+            4 => Some(Self::Unhandled),
+            _ => None,
+        }
+    }
+}
+
+/// Message published on a dead-letter queue.
+///
+/// `len` is the original `hdr.len`; only the first [`DEAD_LETTER_DATA`]
+/// bytes of the original payload are kept.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct DeadLetter {
+    pub reason: u16,
+    pub msg_type: u16,
+    pub len: u16,
+    pub reserved: u16,
+    pub data: [u8; DEAD_LETTER_DATA],
+}
+
+impl DeadLetter {
+    pub fn reason(&self) -> Option<DeadLetterReason> {
+        DeadLetterReason::from_u16(self.reason)
+    }
+
+    /// The preserved part of the original payload.
+    pub fn data(&self) -> &[u8] {
+        let n = (self.len as usize).min(DEAD_LETTER_DATA);
+        &self.data[..n]
+    }
+}
+
+/// Name of the dead-letter queue paired with `topic`.
+pub fn dlq_name(topic: &str) -> String {
+    format!("{topic}.dlq")
+}
+
+/// Write side of a dead-letter queue, owned by a topic's worker.
+pub(crate) struct DeadLetterQueue {
+    mqd: mqd_t,
+}
+
+impl DeadLetterQueue {
+    pub(crate) fn open(topic: &str, maxmsg: c_long) -> io::Result<Self> {
+        let mqd = open_queue(
+            &dlq_name(topic),
+            libc::O_CREAT | libc::O_WRONLY | libc::O_NONBLOCK,
+            Some(maxmsg),
+        )?;
+        Ok(DeadLetterQueue { mqd })
+    }
+
+    /// Forward `msg`. Never blocks: if the DLQ itself is full the letter
+    /// is dropped.
+    pub(crate) fn send(&self, reason: DeadLetterReason, msg: &Msg) {
+        let n = (msg.hdr.len as usize).min(DEAD_LETTER_DATA);
+        let mut letter = DeadLetter {
+            reason: reason as u16,
+            msg_type: msg.hdr.msg_type,
+            len: msg.hdr.len,
+            reserved: 0,
+            data: [0u8; DEAD_LETTER_DATA],
+        };
+        letter.data[..n].copy_from_slice(&msg.payload[..n]);
+
+        let wrapped = Msg::new(0, bytemuck::bytes_of(&letter));
+        let rc = unsafe {
+            libc::mq_send(
+                self.mqd,
+                &wrapped as *const Msg as *const c_char,
+                std::mem::size_of::<Msg>(),
+                0,
+            )
+        };
+        if rc == -1 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EAGAIN) {
+                eprintln!("dead-letter send failed: {err}");
+            }
+        }
+    }
+}
+
+impl Drop for DeadLetterQueue {
+    fn drop(&mut self) {
+        unsafe {
+            libc::mq_close(self.mqd);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, Topic, TopicOptions};
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, Pod, Zeroable, PartialEq)]
+    struct TestMsg {
+        a: u32,
+        b: u32,
+    }
+
+    fn wait_for(letters: &Arc<Mutex<Vec<DeadLetter>>>, n: usize) {
+        for _ in 0..100 {
+            if letters.lock().unwrap().len() >= n {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn strict_decode_failure_and_panic_are_dead_lettered() {
+        let tmp = TempTopic::new("/mq_ipc_test_dlq_");
+        let _tmp_dlq = TempTopic::with_name(&dlq_name(tmp.name()));
+
+        let letters: Arc<Mutex<Vec<DeadLetter>>> = Arc::new(Mutex::new(Vec::new()));
+        let letters_clone = Arc::clone(&letters);
+        let dlq: Topic<DeadLetter> = Topic::new(&dlq_name(tmp.name()), 4).unwrap();
+        dlq.subscribe(move |l: DeadLetter| letters_clone.lock().unwrap().push(l));
+
+        let opts = TopicOptions::new(4).dead_letter(true).strict(true);
+        let topic: Topic<TestMsg> = Topic::with_options(tmp.name(), &opts).unwrap();
+        topic.subscribe(|m: TestMsg| {
+            if m.a == 13 {
+                panic!("unlucky");
+            }
+        });
+
+        // Too short for TestMsg.
+        topic.raw().publish(&Msg::new(5, &[1, 2, 3]), 0).unwrap();
+        wait_for(&letters, 1);
+        topic.publish(&TestMsg { a: 13, b: 0 }, 6, 0).unwrap();
+        wait_for(&letters, 2);
+
+        let got = letters.lock().unwrap();
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].reason(), Some(DeadLetterReason::Decode));
+        assert_eq!(got[0].msg_type, 5);
+        assert_eq!(got[0].data(), &[1, 2, 3]);
+        assert_eq!(got[1].reason(), Some(DeadLetterReason::Panic));
+        assert_eq!(got[1].msg_type, 6);
+    }
+}
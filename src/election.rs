@@ -0,0 +1,456 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Lease-based leader election between redundant processes.
+//!
+//! Every candidate of an election `name` registers in the discovery
+//! [`registry`] and owns an inbox queue `<name>.<key>`. Each heartbeat
+//! period it sends its state to every other live candidate. A candidate
+//! that has heard no leader for a whole lease takes over if it has the
+//! best rank — lowest [`ElectionOptions::priority`], then registry key —
+//! among the candidates it heard during that lease. Leadership is
+//! sticky: a better candidate that joins later waits for the current
+//! leader to go away.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::{election::{ElectionOptions, LeaderElection}, Msg, MqTopic};
+//! let election = LeaderElection::new("/ctl.election", &ElectionOptions::default())?;
+//! let cmd = MqTopic::new("/ctl.cmd", 4)?;
+//! if election.is_leader() {
+//!     cmd.publish(&Msg::new(1, b"go"), 0)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{cleanup, defaults, open_queue, registry, Msg, RecvBuf};
+use arc_swap::ArcSwapOption;
+use bytemuck::{Pod, Zeroable};
+use libc::{self, mqd_t};
+use std::{
+    collections::HashMap,
+    io,
+    os::raw::{c_char, c_long},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const MSG_TYPE_HEARTBEAT: u16 = 1;
+const INBOX_DEPTH: c_long = 8;
+const KEY_LEN: usize = 48;
+
+const STATE_FOLLOWER: u32 = 0;
+const STATE_LEADER: u32 = 1;
+const STATE_RESIGNED: u32 = 2;
+
+/// Timing and rank of a candidate.
+#[derive(Copy, Clone, Debug)]
+pub struct ElectionOptions {
+    /// How often candidates announce themselves.
+    pub heartbeat: Duration,
+    /// Silence after which a leader is considered gone. Should span
+    /// several heartbeats.
+    pub lease: Duration,
+    /// Lower wins.
+    pub priority: u32,
+}
+
+impl ElectionOptions {
+    pub fn heartbeat(mut self, period: Duration) -> Self {
+        self.heartbeat = period;
+        self
+    }
+
+    pub fn lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    pub fn priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+impl Default for ElectionOptions {
+    fn default() -> Self {
+        ElectionOptions {
+            heartbeat: Duration::from_millis(100),
+            lease: Duration::from_millis(500),
+            priority: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Heartbeat {
+    pid: u32,
+    priority: u32,
+    state: u32,
+    key_len: u32,
+    key: [u8; KEY_LEN],
+}
+
+impl Heartbeat {
+    fn new(pid: u32, priority: u32, state: u32, key: &str) -> Self {
+        let mut hb = Heartbeat::zeroed();
+        let len = key.len().min(KEY_LEN);
+        hb.key[..len].copy_from_slice(&key.as_bytes()[..len]);
+        hb.key_len = len as u32;
+        hb.pid = pid;
+        hb.priority = priority;
+        hb.state = state;
+        hb
+    }
+
+    fn key(&self) -> &str {
+        let len = (self.key_len as usize).min(KEY_LEN);
+        std::str::from_utf8(&self.key[..len]).unwrap_or("")
+    }
+}
+
+struct Peer {
+    pid: u32,
+    priority: u32,
+    leader: bool,
+    seen: Instant,
+}
+
+type ChangeCallback = Box<dyn Fn(bool) + Send + Sync + 'static>;
+
+/// This process's candidacy; resigns when dropped.
+pub struct LeaderElection {
+    name: String,
+    leader: Arc<AtomicBool>,
+    leader_pid: Arc<AtomicU32>,
+    on_change: Arc<ArcSwapOption<ChangeCallback>>,
+    running: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+fn inbox_name(election: &str, key: &str) -> String {
+    format!("{election}.{key}")
+}
+
+impl LeaderElection {
+    /// Join the election `name`. The first decision is made one lease
+    /// after joining, so an existing leader is heard first.
+    pub fn new(name: &str, opts: &ElectionOptions) -> io::Result<Self> {
+        let name = defaults::topic_name(name).into_owned();
+        let reg = registry::register(&name, registry::Role::Subscriber)?;
+        let inbox_name = inbox_name(&name, reg.key());
+        let inbox = open_queue(
+            &inbox_name,
+            libc::O_CREAT | libc::O_RDONLY | libc::O_NONBLOCK,
+            Some(INBOX_DEPTH),
+        )?;
+
+        let leader = Arc::new(AtomicBool::new(false));
+        let leader_pid = Arc::new(AtomicU32::new(0));
+        let on_change = Arc::new(ArcSwapOption::empty());
+        let running = Arc::new(AtomicBool::new(true));
+
+        let mut candidate = Candidate {
+            name: name.clone(),
+            key: reg.key().to_string(),
+            opts: *opts,
+            inbox,
+            inbox_name,
+            _reg: reg,
+            peers: HashMap::new(),
+            outboxes: HashMap::new(),
+            leader: Arc::clone(&leader),
+            leader_pid: Arc::clone(&leader_pid),
+            on_change: Arc::clone(&on_change),
+        };
+        let running_clone = Arc::clone(&running);
+        let worker = thread::spawn(move || candidate.run(&running_clone));
+
+        Ok(LeaderElection {
+            name,
+            leader,
+            leader_pid,
+            on_change,
+            running,
+            worker: Some(worker),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether this process currently holds the lease.
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::SeqCst)
+    }
+
+    /// PID of the current leader, if one is known.
+    pub fn leader(&self) -> Option<u32> {
+        let pid = self.leader_pid.load(Ordering::SeqCst);
+        (pid != 0).then_some(pid)
+    }
+
+    /// Block until some leader is known or `timeout` elapses.
+    pub fn wait_for_leader(&self, timeout: Duration) -> Option<u32> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(pid) = self.leader() {
+                return Some(pid);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Invoke `f(is_leader)` on every leadership change of this process.
+    pub fn on_change<F>(&self, f: F)
+    where
+        F: Fn(bool) + Send + Sync + 'static,
+    {
+        self.on_change.store(Some(Arc::new(Box::new(f))));
+    }
+}
+
+impl Drop for LeaderElection {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.worker.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// State owned by the election thread.
+struct Candidate {
+    name: String,
+    key: String,
+    opts: ElectionOptions,
+    inbox: mqd_t,
+    inbox_name: String,
+    _reg: registry::Registration,
+    peers: HashMap<String, Peer>,
+    outboxes: HashMap<String, mqd_t>,
+    leader: Arc<AtomicBool>,
+    leader_pid: Arc<AtomicU32>,
+    on_change: Arc<ArcSwapOption<ChangeCallback>>,
+}
+
+impl Candidate {
+    fn run(&mut self, running: &AtomicBool) {
+        let joined = Instant::now();
+        while running.load(Ordering::SeqCst) {
+            self.receive();
+            let now = Instant::now();
+            let lease = self.opts.lease;
+            self.peers.retain(|_, p| now.duration_since(p.seen) < lease);
+
+            let me = (self.opts.priority, self.key.as_str());
+            let leader = self
+                .peers
+                .iter()
+                .filter(|(_, p)| p.leader)
+                .min_by_key(|(k, p)| (p.priority, k.as_str()));
+            let am_leader = self.leader.load(Ordering::SeqCst);
+
+            let (next, leader_pid) = match leader {
+                // Two leaders after a partition: the worse one yields.
+                Some((k, p)) if (p.priority, k.as_str()) < me || !am_leader => (false, p.pid),
+                _ if am_leader => (true, std::process::id()),
+                _ => {
+                    let best = self
+                        .peers
+                        .iter()
+                        .all(|(k, p)| me < (p.priority, k.as_str()));
+                    let take = best && now.duration_since(joined) >= lease;
+                    (take, if take { std::process::id() } else { 0 })
+                }
+            };
+
+            self.leader_pid.store(leader_pid, Ordering::SeqCst);
+            if next != am_leader {
+                self.leader.store(next, Ordering::SeqCst);
+                if let Some(cb) = self.on_change.load_full() {
+                    cb(next);
+                }
+            }
+
+            self.broadcast(if next { STATE_LEADER } else { STATE_FOLLOWER });
+            thread::sleep(self.opts.heartbeat);
+        }
+
+        // Let followers take over now instead of after a lease.
+        self.broadcast(STATE_RESIGNED);
+        self.leader.store(false, Ordering::SeqCst);
+    }
+
+    fn receive(&mut self) {
+        let mut buf = RecvBuf::new();
+        loop {
+            let ret = unsafe {
+                libc::mq_receive(
+                    self.inbox,
+                    buf.as_mut_ptr(),
+                    RecvBuf::LEN,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ret < 0 {
+                return;
+            }
+
+            let Ok(msg) = buf.msg(ret as usize) else {
+                continue;
+            };
+            let data = msg.data();
+            if msg.hdr.msg_type != MSG_TYPE_HEARTBEAT
+                || data.len() != std::mem::size_of::<Heartbeat>()
+            {
+                continue;
+            }
+            let hb: Heartbeat = bytemuck::pod_read_unaligned(data);
+            let key = hb.key().to_string();
+            if hb.state == STATE_RESIGNED {
+                self.peers.remove(&key);
+                continue;
+            }
+            self.peers.insert(
+                key,
+                Peer {
+                    pid: hb.pid,
+                    priority: hb.priority,
+                    leader: hb.state == STATE_LEADER,
+                    seen: Instant::now(),
+                },
+            );
+        }
+    }
+
+    fn broadcast(&mut self, state: u32) {
+        let hb = Heartbeat::new(std::process::id(), self.opts.priority, state, &self.key);
+        let msg = Msg::new(MSG_TYPE_HEARTBEAT, bytemuck::bytes_of(&hb));
+
+        let Ok(endpoints) = registry::endpoints(&self.name) else {
+            return;
+        };
+        let mut live = Vec::with_capacity(endpoints.len());
+        for ep in endpoints {
+            let key = ep.key();
+            if key == self.key {
+                continue;
+            }
+            live.push(key.to_string());
+
+            let mqd = match self.outboxes.get(key) {
+                Some(mqd) => *mqd,
+                None => match open_queue(
+                    &inbox_name(&self.name, key),
+                    libc::O_WRONLY | libc::O_NONBLOCK,
+                    None,
+                ) {
+                    Ok(mqd) => *self.outboxes.entry(key.to_string()).or_insert(mqd),
+                    // Registered but its inbox is not up yet.
+                    Err(_) => continue,
+                },
+            };
+            // A full inbox belongs to a stuck peer; skipping it is fine.
+            unsafe {
+                libc::mq_send(
+                    mqd,
+                    &msg as *const Msg as *const c_char,
+                    std::mem::size_of::<Msg>(),
+                    0,
+                );
+            }
+        }
+
+        self.outboxes.retain(|key, mqd| {
+            let keep = live.contains(key);
+            if !keep {
+                unsafe { libc::mq_close(*mqd) };
+            }
+            keep
+        });
+    }
+}
+
+impl Drop for Candidate {
+    fn drop(&mut self) {
+        for (_, mqd) in self.outboxes.drain() {
+            unsafe { libc::mq_close(mqd) };
+        }
+        unsafe { libc::mq_close(self.inbox) };
+        let _ = cleanup::unlink_resolved(&self.inbox_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    fn wait_until(f: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline {
+            if f() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        false
+    }
+
+    #[test]
+    fn best_candidate_leads_and_follower_takes_over() {
+        let tmp = TempTopic::new("/mq_ipc_test_election_");
+        let opts = ElectionOptions::default()
+            .heartbeat(Duration::from_millis(10))
+            .lease(Duration::from_millis(80));
+
+        let a = LeaderElection::new(tmp.name(), &opts.priority(0)).unwrap();
+        let b = LeaderElection::new(tmp.name(), &opts.priority(1)).unwrap();
+        let changes = Arc::new(AtomicU32::new(0));
+        let changes_clone = Arc::clone(&changes);
+        b.on_change(move |leader| {
+            assert!(leader);
+            changes_clone.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert!(wait_until(|| a.is_leader()));
+        thread::sleep(Duration::from_millis(100));
+        assert!(!b.is_leader());
+        assert_eq!(b.leader(), Some(std::process::id()));
+
+        drop(a);
+        assert!(wait_until(|| b.is_leader()));
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+    }
+}
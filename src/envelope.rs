@@ -0,0 +1,300 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Messages with open-ended, self-describing metadata.
+//!
+//! The [extended header](crate::ext) is a fixed struct: every new field
+//! grows it for every message and changes its layout for every peer. An
+//! [`Envelope`] instead carries its metadata as a short list of
+//! tag-length-value records in front of the value, so fields can be added
+//! later without touching [`MsgHeader`](crate::MsgHeader) or
+//! [`ExtHeader`](crate::ext::ExtHeader) again:
+//!
+//! ```text
+//! | caps: u16 | tlv_len: u16 | tag: u8 | len: u8 | value ... | T |
+//! ```
+//!
+//! `caps` has one [`CAP_*`](CAP_TRACE) bit per kind of record present;
+//! compare it with a peer's [`SUPPORTED_CAPS`] to tell whether it will
+//! understand everything. Readers skip records they do not know by their
+//! length. Tags from [`TAG_USER_MIN`] up are the application's own and
+//! come back verbatim in [`Envelope::tags`].
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::{envelope::Envelope, MqTopic};
+//!
+//! let topic = MqTopic::new("/cmd", 4)?;
+//! let cmd = Envelope::new(42u32).key(7).tag(0x80, b"operator")?;
+//! topic.publish(&cmd.encode(1)?, 0)?;
+//! if let Some(msg) = topic.try_recv()?
+//!     && let Ok(env) = Envelope::<u32>::decode(&msg)
+//! {
+//!     println!("{} key={:?} tags={:?}", env.value, env.key, env.tags);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{trace::TraceContext, Msg, MSG_PAYLOAD_SIZE};
+use bytemuck::{Pod, Zeroable};
+use std::io;
+
+/// `hdr.flags` bit: the payload is an [`Envelope`].
+pub const FLAG_ENVELOPE: u16 = 0x0010;
+
+/// Record tag: [`TraceContext`], trace ID then span ID.
+pub const TAG_TRACE: u8 = 1;
+/// Record tag: idempotency or routing key, a `u64`.
+pub const TAG_KEY: u8 = 2;
+/// Record tag: [`schema`](crate::schema) version, a `u32`.
+pub const TAG_SCHEMA: u8 = 3;
+/// Lowest tag left to applications.
+pub const TAG_USER_MIN: u8 = 0x80;
+
+/// `caps` bit: a [`TAG_TRACE`] record is present.
+pub const CAP_TRACE: u16 = 1 << 0;
+/// `caps` bit: a [`TAG_KEY`] record is present.
+pub const CAP_KEY: u16 = 1 << 1;
+/// `caps` bit: a [`TAG_SCHEMA`] record is present.
+pub const CAP_SCHEMA: u16 = 1 << 2;
+/// `caps` bit: application records are present.
+pub const CAP_USER_TAGS: u16 = 1 << 3;
+
+/// Every `caps` bit this version of the crate understands.
+pub const SUPPORTED_CAPS: u16 = CAP_TRACE | CAP_KEY | CAP_SCHEMA | CAP_USER_TAGS;
+
+/// Leads the payload of an envelope message.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct EnvelopeHeader {
+    caps: u16,
+    tlv_len: u16,
+}
+
+const HEADER_LEN: usize = std::mem::size_of::<EnvelopeHeader>();
+
+/// A value with optional metadata records; see the module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope<T> {
+    pub value: T,
+    pub trace: Option<TraceContext>,
+    pub key: Option<u64>,
+    pub schema: Option<u32>,
+    /// Application records, in order.
+    pub tags: Vec<(u8, Vec<u8>)>,
+}
+
+impl<T: Pod> Envelope<T> {
+    /// An envelope with no metadata.
+    pub fn new(value: T) -> Self {
+        Envelope {
+            value,
+            trace: None,
+            key: None,
+            schema: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn trace(mut self, ctx: TraceContext) -> Self {
+        self.trace = Some(ctx);
+        self
+    }
+
+    pub fn key(mut self, key: u64) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn schema(mut self, version: u32) -> Self {
+        self.schema = Some(version);
+        self
+    }
+
+    /// Add an application record. `tag` must be at least
+    /// [`TAG_USER_MIN`] and `data` at most 255 bytes.
+    pub fn tag(mut self, tag: u8, data: &[u8]) -> io::Result<Self> {
+        if tag < TAG_USER_MIN || data.len() > u8::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid user tag {tag:#x} of {} bytes", data.len()),
+            ));
+        }
+        self.tags.push((tag, data.to_vec()));
+        Ok(self)
+    }
+
+    /// `caps` bits of the records this envelope carries.
+    pub fn caps(&self) -> u16 {
+        let mut caps = 0;
+        if self.trace.is_some() {
+            caps |= CAP_TRACE;
+        }
+        if self.key.is_some() {
+            caps |= CAP_KEY;
+        }
+        if self.schema.is_some() {
+            caps |= CAP_SCHEMA;
+        }
+        if !self.tags.is_empty() {
+            caps |= CAP_USER_TAGS;
+        }
+        caps
+    }
+
+    /// Lay the envelope out as a message of `msg_type`. Fails with
+    /// `InvalidInput` if records and value do not fit one message.
+    pub fn encode(&self, msg_type: u16) -> io::Result<Msg> {
+        let mut tlv = Vec::new();
+        let mut push = |tag: u8, data: &[u8]| {
+            tlv.push(tag);
+            tlv.push(data.len() as u8);
+            tlv.extend_from_slice(data);
+        };
+        if let Some(ctx) = &self.trace {
+            let mut ids = [0u8; 24];
+            ids[..16].copy_from_slice(&ctx.trace_id);
+            ids[16..].copy_from_slice(&ctx.span_id);
+            push(TAG_TRACE, &ids);
+        }
+        if let Some(key) = self.key {
+            push(TAG_KEY, &key.to_le_bytes());
+        }
+        if let Some(version) = self.schema {
+            push(TAG_SCHEMA, &version.to_le_bytes());
+        }
+        for (tag, data) in &self.tags {
+            push(*tag, data);
+        }
+
+        let value = bytemuck::bytes_of(&self.value);
+        let len = HEADER_LEN + tlv.len() + value.len();
+        if len > MSG_PAYLOAD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("envelope of {len} bytes exceeds {MSG_PAYLOAD_SIZE}"),
+            ));
+        }
+        let header = EnvelopeHeader {
+            caps: self.caps(),
+            tlv_len: tlv.len() as u16,
+        };
+        let mut data = Vec::with_capacity(len);
+        data.extend_from_slice(bytemuck::bytes_of(&header));
+        data.extend_from_slice(&tlv);
+        data.extend_from_slice(value);
+
+        let mut msg = Msg::new(msg_type, &data);
+        msg.hdr.flags |= FLAG_ENVELOPE;
+        Ok(msg)
+    }
+
+    /// Parse an envelope message. Unknown records below
+    /// [`TAG_USER_MIN`] are skipped; a malformed layout or a value of the
+    /// wrong size is `InvalidData`.
+    pub fn decode(msg: &Msg) -> io::Result<Self> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+        if msg.hdr.flags & FLAG_ENVELOPE == 0 {
+            return Err(invalid("not an envelope"));
+        }
+        let data = &msg.payload[..(msg.hdr.len as usize).min(MSG_PAYLOAD_SIZE)];
+        if data.len() < HEADER_LEN {
+            return Err(invalid("truncated envelope header"));
+        }
+        let header: EnvelopeHeader = bytemuck::pod_read_unaligned(&data[..HEADER_LEN]);
+        let tlv_end = HEADER_LEN + header.tlv_len as usize;
+        if data.len() != tlv_end + std::mem::size_of::<T>() {
+            return Err(invalid("envelope value has the wrong size"));
+        }
+
+        let mut env = Envelope::new(bytemuck::pod_read_unaligned(&data[tlv_end..]));
+        let mut tlv = &data[HEADER_LEN..tlv_end];
+        while !tlv.is_empty() {
+            let (tag, len) = match tlv {
+                [tag, len, ..] => (*tag, *len as usize),
+                _ => return Err(invalid("truncated envelope record")),
+            };
+            let Some(value) = tlv.get(2..2 + len) else {
+                return Err(invalid("truncated envelope record"));
+            };
+            match (tag, len) {
+                (TAG_TRACE, 24) => {
+                    let mut ctx = TraceContext::default();
+                    ctx.trace_id.copy_from_slice(&value[..16]);
+                    ctx.span_id.copy_from_slice(&value[16..]);
+                    env.trace = Some(ctx);
+                }
+                (TAG_KEY, 8) => env.key = Some(u64::from_le_bytes(value.try_into().unwrap())),
+                (TAG_SCHEMA, 4) => env.schema = Some(u32::from_le_bytes(value.try_into().unwrap())),
+                (TAG_TRACE | TAG_KEY | TAG_SCHEMA, _) => {
+                    return Err(invalid("envelope record has the wrong size"));
+                }
+                (tag, _) if tag >= TAG_USER_MIN => env.tags.push((tag, value.to_vec())),
+                // From a newer peer; its length lets us step over it.
+                _ => {}
+            }
+            tlv = &tlv[2 + len..];
+        }
+        Ok(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_records_and_skips_unknown_ones() {
+        let ctx = TraceContext {
+            trace_id: [1; 16],
+            span_id: [2; 8],
+        };
+        let env = Envelope::new([1.5f32, -2.0])
+            .trace(ctx)
+            .key(9)
+            .schema(3)
+            .tag(0x81, b"left arm")
+            .unwrap();
+        assert_eq!(env.caps(), SUPPORTED_CAPS);
+        let msg = env.encode(4).unwrap();
+        assert_eq!(Envelope::<[f32; 2]>::decode(&msg).unwrap(), env);
+        assert!(Envelope::<u8>::decode(&msg).is_err());
+        assert!(Envelope::new(0u8).tag(0x10, b"").is_err());
+
+        // A record from a newer peer in front of a key record.
+        let mut data = vec![0u8; HEADER_LEN];
+        data.extend_from_slice(&[0x20, 2, 0xaa, 0xbb, TAG_KEY, 8]);
+        data.extend_from_slice(&5u64.to_le_bytes());
+        let header = EnvelopeHeader {
+            caps: CAP_KEY | 1 << 15,
+            tlv_len: (data.len() - HEADER_LEN) as u16,
+        };
+        data[..HEADER_LEN].copy_from_slice(bytemuck::bytes_of(&header));
+        data.push(7);
+        let mut newer = Msg::new(1, &data);
+        newer.hdr.flags |= FLAG_ENVELOPE;
+        let env = Envelope::<u8>::decode(&newer).unwrap();
+        assert_eq!((env.value, env.key), (7, Some(5)));
+    }
+}
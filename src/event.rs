@@ -0,0 +1,191 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Payloadless notifications.
+//!
+//! An [`Event`] is a one-deep queue of one-byte messages: [`Event::notify`]
+//! sets it, a waiter clears it. Notifying an event that is already set
+//! is a no-op, so a burst of notifications before the waiter runs wakes
+//! it once. This is the cheap way to say "new data in the shm segment"
+//! when the data itself travels elsewhere.
+//!
+//! Like any queue, a notification wakes a single waiter; use one event
+//! per consumer when several processes must be told.
+
+use super::{defaults, open_queue_sized, queue_attr, realtime_after, realtime_now};
+use libc::{self, mqd_t};
+use std::{io, os::raw::c_char, time::Duration};
+
+/// A set/clear notification shared between processes.
+pub struct Event {
+    name: String,
+    mqd: mqd_t,
+    msgsize: usize,
+}
+
+impl Event {
+    /// Open (creating if needed) the event `name`.
+    pub fn new(name: &str) -> io::Result<Self> {
+        Self::open_resolved(defaults::topic_name(name).into_owned())
+    }
+
+    pub(crate) fn open_resolved(name: String) -> io::Result<Self> {
+        let mqd = open_queue_sized(&name, libc::O_CREAT | libc::O_RDWR, Some(1), 1)?;
+
+        // Receives need a buffer as large as the queue's message size,
+        // which differs if someone else created it.
+        let msgsize = match queue_attr(mqd) {
+            Ok(attr) => attr.mq_msgsize as usize,
+            Err(err) => {
+                unsafe { libc::mq_close(mqd) };
+                return Err(err);
+            }
+        };
+        Ok(Event { name, mqd, msgsize })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set the event. Never blocks; an already set event stays set.
+    pub fn notify(&self) -> io::Result<()> {
+        let byte = 1u8;
+        let now = realtime_now();
+        let rc = unsafe {
+            libc::mq_timedsend(self.mqd, &byte as *const u8 as *const c_char, 1, 0, &now)
+        };
+        if rc == -1 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                // Full: a notification is already pending.
+                Some(libc::ETIMEDOUT) | Some(libc::EAGAIN) => {}
+                _ => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the event is set, without clearing it.
+    pub fn is_set(&self) -> io::Result<bool> {
+        Ok(queue_attr(self.mqd)?.mq_curmsgs > 0)
+    }
+
+    /// Block until the event is set, then clear it.
+    pub fn wait(&self) -> io::Result<()> {
+        let mut buf = vec![0u8; self.msgsize];
+        loop {
+            let ret = unsafe {
+                libc::mq_receive(
+                    self.mqd,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len(),
+                    std::ptr::null_mut(),
+                )
+            };
+            if ret >= 0 {
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EINTR) {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the event. Returns whether it was set
+    /// (and is now cleared).
+    pub fn wait_timeout(&self, timeout: Duration) -> io::Result<bool> {
+        self.timed_receive(&realtime_after(timeout))
+    }
+
+    /// Clear the event if it is set. Returns whether it was.
+    pub fn try_wait(&self) -> io::Result<bool> {
+        self.timed_receive(&realtime_now())
+    }
+
+    fn timed_receive(&self, deadline: &libc::timespec) -> io::Result<bool> {
+        let mut buf = vec![0u8; self.msgsize];
+        let ret = unsafe {
+            libc::mq_timedreceive(
+                self.mqd,
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len(),
+                std::ptr::null_mut(),
+                deadline,
+            )
+        };
+        if ret >= 0 {
+            return Ok(true);
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::ETIMEDOUT) | Some(libc::EINTR) => Ok(false),
+            _ => Err(err),
+        }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            libc::mq_close(self.mqd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn bursts_coalesce_into_one_wakeup() {
+        let tmp = TempTopic::new("/mq_ipc_test_event_");
+        let event = Event::new(tmp.name()).unwrap();
+
+        for _ in 0..5 {
+            event.notify().unwrap();
+        }
+        assert!(event.is_set().unwrap());
+        assert!(event.try_wait().unwrap());
+        assert!(!event.try_wait().unwrap());
+        assert!(!event.wait_timeout(Duration::from_millis(10)).unwrap());
+    }
+
+    #[test]
+    fn wakes_waiter_in_other_handle() {
+        let tmp = TempTopic::new("/mq_ipc_test_event_wake_");
+        let waiter = Arc::new(Event::new(tmp.name()).unwrap());
+        let notifier = Event::new(tmp.name()).unwrap();
+
+        let handle = {
+            let waiter = Arc::clone(&waiter);
+            thread::spawn(move || waiter.wait_timeout(Duration::from_secs(2)))
+        };
+        thread::sleep(Duration::from_millis(10));
+        notifier.notify().unwrap();
+        assert!(handle.join().unwrap().unwrap());
+    }
+}
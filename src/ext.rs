@@ -0,0 +1,197 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Optional extended header carried at the front of the payload.
+//!
+//! When `hdr.flags` has [`FLAG_EXT`] set, the first
+//! `size_of::<ExtHeader>()` payload bytes hold an [`ExtHeader`] and the
+//! application data follows it; `hdr.len` covers both. Messages without
+//! the flag are laid out exactly as before, so plain publishers pay
+//! nothing. Use [`Msg::ext`] / [`Msg::data`] instead of slicing
+//! `payload` by hand.
+
+use super::{Msg, MsgHeader, MSG_PAYLOAD_SIZE};
+use bytemuck::{Pod, Zeroable};
+use std::time::Duration;
+
+/// `hdr.flags` bit: payload starts with an [`ExtHeader`].
+pub const FLAG_EXT: u16 = 0x0001;
+
+/// `ExtHeader::present` bit: `trace_id`/`span_id` are valid.
+pub const EXT_TRACE: u32 = 1 << 0;
+
+/// `ExtHeader::present` bit: `schema` is valid.
+pub const EXT_SCHEMA: u32 = 1 << 1;
+
+/// `ExtHeader::present` bit: `key` is valid.
+pub const EXT_IDEMPOTENCY: u32 = 1 << 2;
+
+/// `ExtHeader::present` bit: `source`/`seq` are valid.
+pub const EXT_SEQ: u32 = 1 << 3;
+
+/// `ExtHeader::present` bit: `origin` is valid.
+pub const EXT_ORIGIN: u32 = 1 << 4;
+
+/// `ExtHeader::present` bit: `group`/`group_index`/`group_size` are valid.
+pub const EXT_GROUP: u32 = 1 << 5;
+
+/// `ExtHeader::present` bit: `instance` is valid.
+pub const EXT_INSTANCE: u32 = 1 << 6;
+
+/// `ExtHeader::present` bit: `sent_ns` is valid.
+pub const EXT_TIMESTAMP: u32 = 1 << 7;
+
+/// `ExtHeader::present` bit: `session` is valid.
+pub const EXT_SESSION: u32 = 1 << 8;
+
+/// Size of the extended header inside the payload.
+pub const EXT_HEADER_SIZE: usize = std::mem::size_of::<ExtHeader>();
+
+/// Application bytes left in a message that carries an extended header.
+pub const EXT_PAYLOAD_SIZE: usize = MSG_PAYLOAD_SIZE - EXT_HEADER_SIZE;
+
+/// Extended per-message metadata. `present` says which fields are set.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct ExtHeader {
+    pub present: u32,
+    /// Layout version of the application data; see [`crate::schema`].
+    pub schema: u32,
+    /// W3C-style trace ID of the publishing context.
+    pub trace_id: [u8; 16],
+    /// Span ID within that trace.
+    pub span_id: [u8; 8],
+    /// Idempotency key; see [`crate::dedup`].
+    pub key: u64,
+    /// Publishing handle that numbered this message; see [`crate::reorder`].
+    pub source: u32,
+    /// Index of the input a [`Merge`](crate::merge::Merge) took this
+    /// message from.
+    pub origin: u32,
+    /// Per-source sequence number.
+    pub seq: u64,
+    /// Transaction this message is one part of; see [`crate::group`].
+    pub group: u64,
+    pub group_index: u16,
+    /// Number of parts in the transaction.
+    pub group_size: u16,
+    /// Random ID of the publishing process; see [`crate::session`].
+    pub session: u32,
+    /// Instance key on a keyed topic; see [`crate::keyed`].
+    pub instance: u64,
+    /// Send time on the system-wide `CLOCK_MONOTONIC`, in ns; see
+    /// [`TopicOptions::ttl`](crate::TopicOptions::ttl).
+    pub sent_ns: u64,
+}
+
+impl ExtHeader {
+    pub fn has(&self, bit: u32) -> bool {
+        self.present & bit != 0
+    }
+}
+
+impl Msg {
+    /// Create a message carrying `ext` in front of `data`.
+    ///
+    /// `data` is truncated to [`EXT_PAYLOAD_SIZE`].
+    pub fn with_ext(msg_type: u16, ext: &ExtHeader, data: &[u8]) -> Self {
+        let n = data.len().min(EXT_PAYLOAD_SIZE);
+        let mut msg = Msg {
+            hdr: MsgHeader {
+                msg_type,
+                len: (EXT_HEADER_SIZE + n) as u16,
+                flags: FLAG_EXT,
+                reserved: 0,
+            },
+            payload: [0u8; MSG_PAYLOAD_SIZE],
+        };
+        msg.payload[..EXT_HEADER_SIZE].copy_from_slice(bytemuck::bytes_of(ext));
+        msg.payload[EXT_HEADER_SIZE..EXT_HEADER_SIZE + n].copy_from_slice(&data[..n]);
+        msg
+    }
+
+    /// The extended header, if this message carries one.
+    pub fn ext(&self) -> Option<ExtHeader> {
+        if self.hdr.flags & FLAG_EXT == 0 || (self.hdr.len as usize) < EXT_HEADER_SIZE {
+            return None;
+        }
+        Some(bytemuck::pod_read_unaligned(
+            &self.payload[..EXT_HEADER_SIZE],
+        ))
+    }
+
+    /// When the message was sent, on the system-wide `CLOCK_MONOTONIC`, if
+    /// its publisher stamped it.
+    pub fn sent_at(&self) -> Option<Duration> {
+        self.ext()
+            .filter(|ext| ext.has(EXT_TIMESTAMP))
+            .map(|ext| Duration::from_nanos(ext.sent_ns))
+    }
+
+    /// Application bytes, i.e. the payload without any extended header.
+    pub fn data(&self) -> &[u8] {
+        let len = (self.hdr.len as usize).min(MSG_PAYLOAD_SIZE);
+        if self.ext().is_some() {
+            &self.payload[EXT_HEADER_SIZE..len]
+        } else {
+            &self.payload[..len]
+        }
+    }
+
+    /// Copy of this message with `ext` attached (replacing any existing
+    /// one). Returns `None` when the data would not fit next to it.
+    pub fn attach_ext(&self, ext: &ExtHeader) -> Option<Msg> {
+        let data = self.data();
+        if data.len() > EXT_PAYLOAD_SIZE {
+            return None;
+        }
+        let mut out = Msg::with_ext(self.hdr.msg_type, ext, data);
+        out.hdr.flags |= self.hdr.flags;
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ext_roundtrip_and_data_split() {
+        let ext = ExtHeader {
+            present: EXT_TRACE,
+            trace_id: [7; 16],
+            ..Default::default()
+        };
+        let msg = Msg::with_ext(3, &ext, b"hello");
+        assert_eq!(msg.ext(), Some(ext));
+        assert_eq!(msg.data(), b"hello");
+
+        let plain = Msg::new(3, b"hello");
+        assert_eq!(plain.ext(), None);
+        assert_eq!(plain.data(), b"hello");
+        assert_eq!(plain.attach_ext(&ext).unwrap().data(), b"hello");
+
+        let full = Msg::new(3, &[0u8; MSG_PAYLOAD_SIZE]);
+        assert!(full.attach_ext(&ext).is_none());
+    }
+}
@@ -0,0 +1,472 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Bridge to [Foxglove Studio] over the Foxglove WebSocket protocol
+//! (`foxglove.websocket.v1`). Enabled by the `foxglove` feature.
+//!
+//! Every [`Channel`] is advertised to connected clients together with
+//! its schema; clients subscribe to the channels they want to plot and
+//! receive each message as a binary `MessageData` frame. Live topics
+//! are attached with [`FoxgloveBridge::add_topic`]; anything else,
+//! e.g. journal entries being replayed, can be pushed through
+//! [`Channel::send`]:
+//!
+//! ```no_run
+//! use mq_ipc::{foxglove::{ChannelInfo, FoxgloveBridge}, journal};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let bridge = FoxgloveBridge::bind("0.0.0.0:8765", "robot")?;
+//! let channel = bridge.channel(ChannelInfo::json("/motor_state", "MotorState", "{}"));
+//! for entry in journal::read_entries("motor.journal")? {
+//!     let len = entry.msg.hdr.len as usize;
+//!     channel.send(entry.timestamp, &entry.msg.payload[..len]);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The bridge subscribes like any other endpoint, so on a shared queue
+//! it takes its turn at the messages rather than seeing all of them;
+//! point it at a dedicated topic or a mirror when that matters.
+//!
+//! [Foxglove Studio]: https://foxglove.dev
+
+use super::Topic;
+use bytemuck::{Pod, Zeroable};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+use tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::HeaderValue,
+    Message, WebSocket,
+};
+
+/// WebSocket subprotocol spoken by the bridge.
+pub const SUBPROTOCOL: &str = "foxglove.websocket.v1";
+
+const OP_MESSAGE_DATA: u8 = 0x01;
+// How long a client thread blocks on reads before flushing queued frames.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What clients are told about a channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelInfo {
+    pub topic: String,
+    /// Payload encoding, e.g. `"json"` or `"protobuf"`.
+    pub encoding: String,
+    pub schema_name: String,
+    /// Schema text in the format implied by `encoding`.
+    pub schema: String,
+}
+
+impl ChannelInfo {
+    /// A channel carrying JSON payloads described by a JSON Schema.
+    pub fn json(topic: &str, schema_name: &str, schema: &str) -> Self {
+        ChannelInfo {
+            topic: topic.to_string(),
+            encoding: "json".to_string(),
+            schema_name: schema_name.to_string(),
+            schema: schema.to_string(),
+        }
+    }
+
+    fn advertisement(&self, id: u32) -> Value {
+        json!({
+            "id": id,
+            "topic": self.topic,
+            "encoding": self.encoding,
+            "schemaName": self.schema_name,
+            "schema": self.schema,
+        })
+    }
+}
+
+struct Client {
+    // channel id -> subscription id chosen by the client
+    subscriptions: Mutex<HashMap<u32, u32>>,
+    tx: mpsc::Sender<Message>,
+}
+
+struct Shared {
+    name: String,
+    channels: Mutex<BTreeMap<u32, ChannelInfo>>,
+    clients: Mutex<Vec<Arc<Client>>>,
+    next_channel: AtomicU32,
+    running: AtomicBool,
+}
+
+impl Shared {
+    fn advertise(&self, id: u32, info: &ChannelInfo) {
+        let text = json!({"op": "advertise", "channels": [info.advertisement(id)]}).to_string();
+        for client in self.clients.lock().unwrap().iter() {
+            let _ = client.tx.send(Message::text(text.clone()));
+        }
+    }
+}
+
+/// Handle for pushing messages on one advertised channel.
+#[derive(Clone)]
+pub struct Channel {
+    id: u32,
+    shared: Arc<Shared>,
+}
+
+impl Channel {
+    /// Channel id as advertised to clients.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Whether any connected client is subscribed, i.e. whether encoding
+    /// a payload for [`send`](Self::send) is worth it.
+    pub fn has_subscribers(&self) -> bool {
+        self.shared
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|c| c.subscriptions.lock().unwrap().contains_key(&self.id))
+    }
+
+    /// Deliver `payload`, stamped with `timestamp`, to every subscribed
+    /// client. Clients that are not subscribed get nothing.
+    pub fn send(&self, timestamp: SystemTime, payload: &[u8]) {
+        let ns = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        for client in self.shared.clients.lock().unwrap().iter() {
+            let Some(&sub) = client.subscriptions.lock().unwrap().get(&self.id) else {
+                continue;
+            };
+            let mut frame = Vec::with_capacity(13 + payload.len());
+            frame.push(OP_MESSAGE_DATA);
+            frame.extend_from_slice(&sub.to_le_bytes());
+            frame.extend_from_slice(&ns.to_le_bytes());
+            frame.extend_from_slice(payload);
+            let _ = client.tx.send(Message::binary(frame));
+        }
+    }
+}
+
+/// Foxglove WebSocket server; stops accepting and disconnects clients
+/// on drop.
+pub struct FoxgloveBridge {
+    shared: Arc<Shared>,
+    addr: SocketAddr,
+    acceptor: Option<thread::JoinHandle<()>>,
+}
+
+impl FoxgloveBridge {
+    /// Listen on `addr`; `name` is shown to clients as the server name.
+    pub fn bind(addr: impl ToSocketAddrs, name: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            name: name.to_string(),
+            channels: Mutex::new(BTreeMap::new()),
+            clients: Mutex::new(Vec::new()),
+            next_channel: AtomicU32::new(1),
+            running: AtomicBool::new(true),
+        });
+
+        let accept_shared = Arc::clone(&shared);
+        let acceptor = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !accept_shared.running.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let shared = Arc::clone(&accept_shared);
+                thread::spawn(move || {
+                    if let Err(err) = serve_client(stream, &shared) {
+                        eprintln!("foxglove client error: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(FoxgloveBridge {
+            shared,
+            addr,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Advertise a new channel to current and future clients.
+    pub fn channel(&self, info: ChannelInfo) -> Channel {
+        let id = self.shared.next_channel.fetch_add(1, Ordering::Relaxed);
+        self.shared.advertise(id, &info);
+        self.shared.channels.lock().unwrap().insert(id, info);
+        Channel {
+            id,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Subscribe to `topic` and forward every value as JSON, described by
+    /// the JSON Schema `schema`.
+    pub fn add_topic<T>(&self, topic: &Topic<T>, schema_name: &str, schema: &str) -> Channel
+    where
+        T: Pod + Zeroable + Serialize + Send + Sync + 'static,
+    {
+        let channel = self.channel(ChannelInfo::json(topic.raw().name(), schema_name, schema));
+        let tx = channel.clone();
+        topic.subscribe(move |value: T| {
+            if !tx.has_subscribers() {
+                return;
+            }
+            match serde_json::to_vec(&value) {
+                Ok(payload) => tx.send(SystemTime::now(), &payload),
+                Err(err) => eprintln!("foxglove encode error: {err}"),
+            }
+        });
+        channel
+    }
+
+    /// Number of connected clients.
+    pub fn client_count(&self) -> usize {
+        self.shared.clients.lock().unwrap().len()
+    }
+}
+
+impl Drop for FoxgloveBridge {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+        // The acceptor is parked in `accept`; a throwaway connection
+        // brings it back to check the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.acceptor.take() {
+            let _ = handle.join();
+        }
+        for client in self.shared.clients.lock().unwrap().drain(..) {
+            let _ = client.tx.send(Message::Close(None));
+        }
+    }
+}
+
+// The error type is dictated by tungstenite's handshake callback.
+#[allow(clippy::result_large_err)]
+fn negotiate(req: &Request, mut resp: Response) -> Result<Response, ErrorResponse> {
+    let offered = req
+        .headers()
+        .get_all("Sec-WebSocket-Protocol")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|p| p.trim() == SUBPROTOCOL);
+    if !offered {
+        let mut err = ErrorResponse::new(Some(format!("subprotocol {SUBPROTOCOL} required")));
+        *err.status_mut() = tungstenite::http::StatusCode::BAD_REQUEST;
+        return Err(err);
+    }
+    resp.headers_mut().insert(
+        "Sec-WebSocket-Protocol",
+        HeaderValue::from_static(SUBPROTOCOL),
+    );
+    Ok(resp)
+}
+
+fn serve_client(stream: TcpStream, shared: &Arc<Shared>) -> io::Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let ws = tungstenite::accept_hdr(stream, negotiate)
+        .map_err(|e| io::Error::new(io::ErrorKind::ConnectionAborted, e.to_string()))?;
+    ws.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (tx, rx) = mpsc::channel();
+    let client = Arc::new(Client {
+        subscriptions: Mutex::new(HashMap::new()),
+        tx,
+    });
+
+    let info = json!({
+        "op": "serverInfo",
+        "name": shared.name,
+        "capabilities": [],
+        "supportedEncodings": [],
+        "metadata": {},
+    });
+    let _ = client.tx.send(Message::text(info.to_string()));
+    {
+        // Advertise under the clients lock so `channel()` cannot slip a
+        // channel in between the snapshot and the registration.
+        let mut clients = shared.clients.lock().unwrap();
+        let channels = shared.channels.lock().unwrap();
+        if !channels.is_empty() {
+            let list: Vec<_> = channels
+                .iter()
+                .map(|(id, c)| c.advertisement(*id))
+                .collect();
+            let text = json!({"op": "advertise", "channels": list}).to_string();
+            let _ = client.tx.send(Message::text(text));
+        }
+        clients.push(Arc::clone(&client));
+    }
+
+    let result = client_loop(ws, &client, &rx);
+    shared
+        .clients
+        .lock()
+        .unwrap()
+        .retain(|c| !Arc::ptr_eq(c, &client));
+    result
+}
+
+fn client_loop(
+    mut ws: WebSocket<TcpStream>,
+    client: &Client,
+    rx: &mpsc::Receiver<Message>,
+) -> io::Result<()> {
+    let ws_err = |e: tungstenite::Error| io::Error::other(e.to_string());
+    loop {
+        while let Ok(msg) = rx.try_recv() {
+            let close = matches!(msg, Message::Close(_));
+            ws.write(msg).map_err(ws_err)?;
+            if close {
+                let _ = ws.flush();
+                return Ok(());
+            }
+        }
+        ws.flush().map_err(ws_err)?;
+
+        match ws.read() {
+            Ok(Message::Text(text)) => handle_request(client, &text),
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                return Ok(());
+            }
+            Err(err) => return Err(ws_err(err)),
+        }
+    }
+}
+
+fn handle_request(client: &Client, text: &str) {
+    let Ok(req) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    let mut subs = client.subscriptions.lock().unwrap();
+    match req["op"].as_str() {
+        Some("subscribe") => {
+            for sub in req["subscriptions"].as_array().into_iter().flatten() {
+                if let (Some(id), Some(channel)) = (sub["id"].as_u64(), sub["channelId"].as_u64()) {
+                    subs.insert(channel as u32, id as u32);
+                }
+            }
+        }
+        Some("unsubscribe") => {
+            for id in req["subscriptionIds"].as_array().into_iter().flatten() {
+                if let Some(id) = id.as_u64() {
+                    subs.retain(|_, sub| *sub as u64 != id);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use tungstenite::client::IntoClientRequest;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable, Serialize)]
+    struct Sample {
+        speed: f32,
+    }
+
+    fn read_json(ws: &mut WebSocket<TcpStream>) -> Value {
+        match ws.read().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected text, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn forwards_subscribed_topic_as_message_data() {
+        let tmp = TempTopic::new("/mq_ipc_test_foxglove_");
+        let topic = Topic::<Sample>::new(tmp.name(), 8).unwrap();
+        let bridge = FoxgloveBridge::bind("127.0.0.1:0", "test").unwrap();
+        let channel = bridge.add_topic(&topic, "Sample", "{}");
+
+        let mut req = format!("ws://{}", bridge.local_addr())
+            .into_client_request()
+            .unwrap();
+        req.headers_mut().insert(
+            "Sec-WebSocket-Protocol",
+            HeaderValue::from_static(SUBPROTOCOL),
+        );
+        let stream = TcpStream::connect(bridge.local_addr()).unwrap();
+        let (mut ws, _) = tungstenite::client(req, stream).unwrap();
+
+        assert_eq!(read_json(&mut ws)["op"], "serverInfo");
+        let adv = read_json(&mut ws);
+        assert_eq!(adv["op"], "advertise");
+        assert_eq!(adv["channels"][0]["topic"], tmp.name());
+        assert_eq!(adv["channels"][0]["id"], channel.id());
+
+        let sub =
+            json!({"op": "subscribe", "subscriptions": [{"id": 7, "channelId": channel.id()}]});
+        ws.send(Message::text(sub.to_string())).unwrap();
+        for _ in 0..100 {
+            if channel.has_subscribers() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(channel.has_subscribers());
+
+        topic.publish(&Sample { speed: 1.5 }, 1, 0).unwrap();
+        let frame = match ws.read().unwrap() {
+            Message::Binary(frame) => frame,
+            other => panic!("expected binary, got {other:?}"),
+        };
+        assert_eq!(frame[0], OP_MESSAGE_DATA);
+        assert_eq!(u32::from_le_bytes(frame[1..5].try_into().unwrap()), 7);
+        let payload: Value = serde_json::from_slice(&frame[13..]).unwrap();
+        assert_eq!(payload["speed"], 1.5);
+    }
+}
@@ -0,0 +1,226 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Mode management driven by topics.
+//!
+//! A [`StateMachine`] holds one state value, moves it along when typed
+//! messages arrive on the topics it listens to, and publishes every new
+//! state on a status topic so other nodes can follow the mode without
+//! polling:
+//!
+//! ```no_run
+//! # use bytemuck::{Pod, Zeroable};
+//! # use mq_ipc::{fsm::StateMachine, TopicOptions};
+//! const IDLE: u32 = 0;
+//! const ENABLED: u32 = 1;
+//! const FAULT: u32 = 2;
+//!
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! struct Command { enable: u32 }
+//!
+//! let opts = TopicOptions::new(8);
+//! let mode = StateMachine::new("/motor/mode", IDLE, &opts)?;
+//! mode.on("/motor/cmd", &opts, |state, cmd: &Command| match (*state, cmd.enable) {
+//!     (IDLE, 1) => Some(ENABLED),
+//!     (ENABLED, 0) => Some(IDLE),
+//!     _ => None,
+//! })?;
+//! mode.on("/motor/fault", &opts, |_, _: &u32| Some(FAULT))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Transitions are serialised across all input topics. One that leaves
+//! the state unchanged publishes nothing.
+
+use super::{Topic, TopicOptions};
+use arc_swap::{ArcSwap, ArcSwapOption};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+type TransitionCallback<S> = Box<dyn Fn(S, S) + Send + Sync + 'static>;
+
+struct Core<S>
+where
+    S: Pod + Zeroable + PartialEq + Send + Sync + 'static,
+{
+    state: Mutex<S>,
+    current: ArcSwap<S>,
+    status: Topic<S>,
+    on_transition: ArcSwapOption<TransitionCallback<S>>,
+}
+
+impl<S> Core<S>
+where
+    S: Pod + Zeroable + PartialEq + Send + Sync + 'static,
+{
+    fn step(&self, next: impl FnOnce(&S) -> Option<S>) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(to) = next(&state).filter(|to| *to != *state) else {
+            return false;
+        };
+        let from = std::mem::replace(&mut *state, to);
+        self.current.store(Arc::new(to));
+
+        if let Err(err) = self.status.publish(&to, 0, 0) {
+            eprintln!(
+                "state publish on {} failed: {err}",
+                self.status.raw().name()
+            );
+        }
+        if let Some(cb) = self.on_transition.load().as_ref() {
+            cb(from, to);
+        }
+        true
+    }
+}
+
+/// State value moved by messages and mirrored on a status topic.
+pub struct StateMachine<S>
+where
+    S: Pod + Zeroable + PartialEq + Send + Sync + 'static,
+{
+    core: Arc<Core<S>>,
+    inputs: Mutex<Vec<Box<dyn Send + Sync>>>,
+}
+
+impl<S> StateMachine<S>
+where
+    S: Pod + Zeroable + PartialEq + Send + Sync + 'static,
+{
+    /// Start in `initial` and publish it on `status` right away.
+    pub fn new(status: &str, initial: S, opts: &TopicOptions) -> io::Result<Self> {
+        let status = Topic::with_options(status, opts)?;
+        status.publish(&initial, 0, 0)?;
+        Ok(StateMachine {
+            core: Arc::new(Core {
+                state: Mutex::new(initial),
+                current: ArcSwap::from_pointee(initial),
+                status,
+                on_transition: ArcSwapOption::empty(),
+            }),
+            inputs: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Call `f(state, message)` for every `M` received on `topic` and move
+    /// to the state it returns, if any. The topic stays subscribed for the
+    /// lifetime of the machine.
+    pub fn on<M, F>(&self, topic: &str, opts: &TopicOptions, f: F) -> io::Result<()>
+    where
+        M: Pod + Zeroable + Send + Sync + 'static,
+        F: Fn(&S, &M) -> Option<S> + Send + Sync + 'static,
+    {
+        let input = Topic::<M>::with_options(topic, opts)?;
+        let core = Arc::clone(&self.core);
+        input.subscribe(move |msg: M| {
+            core.step(|state| f(state, &msg));
+        });
+        self.inputs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(input));
+        Ok(())
+    }
+
+    /// Move to `state` from code, e.g. when a watchdog expires. Returns
+    /// whether the state changed.
+    pub fn set(&self, state: S) -> bool {
+        self.core.step(|_| Some(state))
+    }
+
+    /// Current state.
+    pub fn state(&self) -> S {
+        **self.core.current.load()
+    }
+
+    /// Call `f(from, to)` after every transition, once the new state has
+    /// been published. `f` runs with transitions locked out, so it must
+    /// not call [`set`](Self::set).
+    pub fn on_transition<F>(&self, f: F)
+    where
+        F: Fn(S, S) + Send + Sync + 'static,
+    {
+        self.core.on_transition.store(Some(Arc::new(Box::new(f))));
+    }
+
+    /// Name of the status topic.
+    pub fn status(&self) -> &str {
+        self.core.status.raw().name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::mpsc, time::Duration};
+
+    const IDLE: u32 = 0;
+    const ENABLED: u32 = 1;
+    const FAULT: u32 = 2;
+
+    #[test]
+    fn transitions_are_published_on_change() {
+        let tmp_status = TempTopic::new("/mq_ipc_test_fsm_status_");
+        let tmp_cmd = TempTopic::new("/mq_ipc_test_fsm_cmd_");
+        let opts = TopicOptions::new(8);
+
+        let fsm = StateMachine::new(tmp_status.name(), IDLE, &opts).unwrap();
+        fsm.on(tmp_cmd.name(), &opts, |state, enable: &u32| {
+            match (*state, *enable) {
+                (IDLE, 1) => Some(ENABLED),
+                (ENABLED, 0) => Some(IDLE),
+                _ => None,
+            }
+        })
+        .unwrap();
+        let (trans_tx, trans_rx) = mpsc::channel();
+        fsm.on_transition(move |from, to| trans_tx.send((from, to)).unwrap());
+
+        let status = Topic::<u32>::new(tmp_status.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        status.subscribe(move |state| tx.send(state).unwrap());
+
+        let cmd = Topic::<u32>::new(tmp_cmd.name(), 8).unwrap();
+        // The second enable is a no-op and must not be published.
+        for enable in [1, 1, 0] {
+            cmd.publish(&enable, 1, 0).unwrap();
+        }
+
+        let timeout = Duration::from_secs(2);
+        let got: Vec<u32> = (0..3).map(|_| rx.recv_timeout(timeout).unwrap()).collect();
+        assert_eq!(got, vec![IDLE, ENABLED, IDLE]);
+        assert_eq!(trans_rx.recv_timeout(timeout).unwrap(), (IDLE, ENABLED));
+        assert_eq!(trans_rx.recv_timeout(timeout).unwrap(), (ENABLED, IDLE));
+
+        assert!(fsm.set(FAULT));
+        assert!(!fsm.set(FAULT));
+        assert_eq!(fsm.state(), FAULT);
+        assert_eq!(rx.recv_timeout(timeout).unwrap(), FAULT);
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Snapshot of the IPC graph: which processes publish and subscribe on
+//! which topics, built from the discovery [`registry`].
+//!
+//! Only live endpoints are included. Several handles of one process on
+//! the same topic collapse into a single edge with a `count`.
+
+use super::registry::{self, Role};
+use std::{collections::BTreeMap, fmt::Write, io};
+
+/// A process with at least one live endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    pub pid: u32,
+    /// Process name, as recorded in the registry.
+    pub name: String,
+}
+
+/// A process publishing to or subscribing from a topic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub pid: u32,
+    pub topic: String,
+    pub role: Role,
+    /// Number of handles behind this edge.
+    pub count: usize,
+}
+
+/// Nodes, topics and pub/sub edges at one point in time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Graph {
+    /// Sorted by PID.
+    pub nodes: Vec<Node>,
+    /// Sorted by name.
+    pub topics: Vec<String>,
+    pub edges: Vec<Edge>,
+}
+
+/// Read the current graph from the registry.
+pub fn snapshot() -> io::Result<Graph> {
+    let mut nodes = BTreeMap::new();
+    let mut topics = Vec::new();
+    let mut edges: BTreeMap<(String, u32, bool), Edge> = BTreeMap::new();
+
+    for topic in registry::topics()? {
+        let endpoints = registry::endpoints(&topic)?;
+        if endpoints.is_empty() {
+            continue;
+        }
+        for ep in endpoints {
+            nodes.entry(ep.pid).or_insert_with(|| Node {
+                pid: ep.pid,
+                name: ep.node.clone(),
+            });
+            let key = (topic.clone(), ep.pid, ep.role == Role::Subscriber);
+            edges
+                .entry(key)
+                .or_insert_with(|| Edge {
+                    pid: ep.pid,
+                    topic: topic.clone(),
+                    role: ep.role,
+                    count: 0,
+                })
+                .count += 1;
+        }
+        topics.push(topic);
+    }
+
+    Ok(Graph {
+        nodes: nodes.into_values().collect(),
+        topics,
+        edges: edges.into_values().collect(),
+    })
+}
+
+impl Graph {
+    /// Edges touching `topic`.
+    pub fn edges_of<'a>(&'a self, topic: &'a str) -> impl Iterator<Item = &'a Edge> {
+        self.edges.iter().filter(move |e| e.topic == topic)
+    }
+
+    /// Render as Graphviz DOT: processes are boxes, topics ellipses, and
+    /// edges run publisher → topic → subscriber.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph mq_ipc {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let label = format!("{}\\n(pid {})", escape(&node.name), node.pid);
+            let _ = writeln!(
+                out,
+                "    \"pid:{}\" [shape=box, label=\"{label}\"];",
+                node.pid
+            );
+        }
+        for topic in &self.topics {
+            let _ = writeln!(out, "    \"{}\" [shape=ellipse];", escape(topic));
+        }
+        for edge in &self.edges {
+            let (from, to) = match edge.role {
+                Role::Publisher => (format!("pid:{}", edge.pid), escape(&edge.topic)),
+                Role::Subscriber => (escape(&edge.topic), format!("pid:{}", edge.pid)),
+            };
+            let _ = write!(out, "    \"{from}\" -> \"{to}\"");
+            if edge.count > 1 {
+                let _ = write!(out, " [label=\"x{}\"]", edge.count);
+            }
+            out.push_str(";\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_includes_local_endpoints() {
+        let topic = format!("/mq_ipc_test_graph_{}", std::process::id());
+        let _pub = registry::register(&topic, Role::Publisher).unwrap();
+        let _sub_a = registry::register(&topic, Role::Subscriber).unwrap();
+        let _sub_b = registry::register(&topic, Role::Subscriber).unwrap();
+
+        let graph = snapshot().unwrap();
+        let pid = std::process::id();
+        assert!(graph.topics.contains(&topic));
+        assert!(graph.nodes.iter().any(|n| n.pid == pid));
+
+        let mut edges: Vec<_> = graph.edges_of(&topic).map(|e| (e.role, e.count)).collect();
+        edges.sort_by_key(|(role, _)| *role == Role::Subscriber);
+        assert_eq!(edges, vec![(Role::Publisher, 1), (Role::Subscriber, 2)]);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph mq_ipc {"));
+        assert!(dot.contains(&format!("\"pid:{pid}\" -> \"{topic}\";")));
+        assert!(dot.contains(&format!("\"{topic}\" -> \"pid:{pid}\" [label=\"x2\"];")));
+    }
+}
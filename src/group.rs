@@ -0,0 +1,316 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Transactions spanning several topics.
+//!
+//! A command for a multi-joint arm is one decision split over one topic
+//! per actuator. A [`TransactionalPublisher`] sends such a set as a group:
+//! every part carries the same group ID plus its index and the part
+//! count in the [extended header](crate::ext). A [`GroupSubscriber`] on
+//! the other side holds parts back until the whole group is in and then
+//! hands it over at once:
+//!
+//! ```no_run
+//! # use mq_ipc::{group::{GroupSubscriber, TransactionalPublisher}, Msg, TopicOptions};
+//! # use std::time::Duration;
+//! let joints = ["/arm/joint0/cmd", "/arm/joint1/cmd"];
+//! let opts = TopicOptions::new(8);
+//!
+//! let _rx = GroupSubscriber::new(&joints, Duration::from_millis(50), &opts, |group| {
+//!     for (topic, msg) in &group.parts {
+//!         println!("{topic}: {:?}", msg.data());
+//!     }
+//! })?;
+//!
+//! let tx = TransactionalPublisher::new(&joints, &opts)?;
+//! tx.publish(&[(0, &Msg::new(1, &[10])), (1, &Msg::new(1, &[20]))], 0)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! POSIX queues cannot send to several queues atomically, so atomicity
+//! is on the receiving side: a group whose parts do not all arrive
+//! within the subscriber's timeout, e.g. because the publisher failed
+//! half way, is discarded and counted in [`GroupStats::incomplete`].
+
+use super::{
+    ext::{EXT_GROUP, EXT_PAYLOAD_SIZE},
+    MqTopic, Msg, TopicOptions,
+};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Group IDs are the pid in the upper half and a per-process counter in
+/// the lower one.
+static NEXT_GROUP: AtomicU64 = AtomicU64::new(0);
+
+/// The group fields of `msg`'s extended header: `(group, index, size)`.
+pub fn group_of(msg: &Msg) -> Option<(u64, u16, u16)> {
+    msg.ext()
+        .filter(|ext| ext.has(EXT_GROUP))
+        .map(|ext| (ext.group, ext.group_index, ext.group_size))
+}
+
+/// Publisher of message groups over a fixed set of topics.
+pub struct TransactionalPublisher {
+    topics: Vec<MqTopic>,
+}
+
+impl TransactionalPublisher {
+    /// Open `topics`; parts refer to them by index.
+    pub fn new(topics: &[&str], opts: &TopicOptions) -> io::Result<Self> {
+        let topics = topics
+            .iter()
+            .map(|name| MqTopic::with_options(name, opts))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(TransactionalPublisher { topics })
+    }
+
+    /// Publish every `(topic_index, msg)` pair as one group and return its
+    /// ID. Nothing is sent unless every part is valid: an index within
+    /// range and data that fits next to the extended header.
+    pub fn publish(&self, parts: &[(usize, &Msg)], prio: u32) -> io::Result<u64> {
+        if parts.is_empty() || parts.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a group needs 1 to 65535 parts",
+            ));
+        }
+        let group = (std::process::id() as u64) << 32
+            | NEXT_GROUP.fetch_add(1, Ordering::Relaxed) & 0xFFFF_FFFF;
+
+        let msgs = parts
+            .iter()
+            .enumerate()
+            .map(|(i, (topic, msg))| {
+                if *topic >= self.topics.len() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("no topic #{topic}"),
+                    ));
+                }
+                let mut ext = msg.ext().unwrap_or_default();
+                ext.present |= EXT_GROUP;
+                ext.group = group;
+                ext.group_index = i as u16;
+                ext.group_size = parts.len() as u16;
+                msg.attach_ext(&ext).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("group part exceeds {EXT_PAYLOAD_SIZE} bytes"),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        for ((topic, _), msg) in parts.iter().zip(&msgs) {
+            self.topics[*topic].publish(msg, prio)?;
+        }
+        Ok(group)
+    }
+
+    /// Topic names, in the order part indices refer to.
+    pub fn topics(&self) -> impl Iterator<Item = &str> {
+        self.topics.iter().map(|t| t.name())
+    }
+}
+
+/// A complete group, parts in the order they were published.
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub id: u64,
+    /// `(topic_name, msg)` of every part.
+    pub parts: Vec<(String, Msg)>,
+}
+
+/// What a [`GroupSubscriber`] has done so far.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GroupStats {
+    /// Groups handed to the callback.
+    pub delivered: u64,
+    /// Groups discarded because a part was missing at the timeout.
+    pub incomplete: u64,
+    /// Messages received without group fields, ignored.
+    pub ungrouped: u64,
+}
+
+struct Partial {
+    parts: Vec<Option<(String, Msg)>>,
+    received: usize,
+    first_seen: Instant,
+}
+
+struct Assembler {
+    pending: HashMap<u64, Partial>,
+    stats: GroupStats,
+}
+
+impl Assembler {
+    /// Add one part; returns the group once it is complete.
+    fn push(&mut self, topic: &str, msg: Msg, timeout: Duration) -> Option<Group> {
+        let Some((id, index, size)) = group_of(&msg) else {
+            self.stats.ungrouped += 1;
+            return None;
+        };
+
+        let now = Instant::now();
+        let before = self.pending.len();
+        self.pending
+            .retain(|_, p| now.duration_since(p.first_seen) < timeout);
+        self.stats.incomplete += (before - self.pending.len()) as u64;
+
+        let partial = self.pending.entry(id).or_insert_with(|| Partial {
+            parts: vec![None; size as usize],
+            received: 0,
+            first_seen: now,
+        });
+        let slot = partial.parts.get_mut(index as usize)?;
+        if slot.is_none() {
+            *slot = Some((topic.to_string(), msg));
+            partial.received += 1;
+        }
+        if partial.received < partial.parts.len() {
+            return None;
+        }
+
+        let done = self.pending.remove(&id)?;
+        self.stats.delivered += 1;
+        Some(Group {
+            id,
+            parts: done.parts.into_iter().flatten().collect(),
+        })
+    }
+}
+
+/// Subscription to a set of topics that delivers whole groups.
+pub struct GroupSubscriber {
+    _topics: Vec<MqTopic>,
+    state: Arc<Mutex<Assembler>>,
+}
+
+impl GroupSubscriber {
+    /// Call `f` with every group that completes on `topics` within
+    /// `timeout` of its first part arriving.
+    ///
+    /// Expired groups are swept when the next part arrives, so their
+    /// parts are held until then.
+    pub fn new<F>(topics: &[&str], timeout: Duration, opts: &TopicOptions, f: F) -> io::Result<Self>
+    where
+        F: FnMut(Group) + Send + 'static,
+    {
+        let handles = topics
+            .iter()
+            .map(|name| MqTopic::with_options(name, opts))
+            .collect::<io::Result<Vec<_>>>()?;
+        let state = Arc::new(Mutex::new(Assembler {
+            pending: HashMap::new(),
+            stats: GroupStats::default(),
+        }));
+        let f = Arc::new(Mutex::new(f));
+
+        for topic in &handles {
+            let state = Arc::clone(&state);
+            let f = Arc::clone(&f);
+            let name = topic.name().to_string();
+            topic.subscribe(move |msg| {
+                let group = state
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(&name, msg, timeout);
+                if let Some(group) = group {
+                    let mut f = f.lock().unwrap_or_else(|e| e.into_inner());
+                    f(group);
+                }
+            });
+        }
+
+        Ok(GroupSubscriber {
+            _topics: handles,
+            state,
+        })
+    }
+
+    pub fn stats(&self) -> GroupStats {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, ext::ExtHeader};
+    use std::{sync::mpsc, thread};
+
+    #[test]
+    fn delivers_complete_groups_only() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_group_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_group_b_");
+        let names = [tmp_a.name(), tmp_b.name()];
+        let opts = TopicOptions::new(8);
+
+        let (tx, rx) = mpsc::channel();
+        let sub = GroupSubscriber::new(&names, Duration::from_millis(30), &opts, move |g| {
+            tx.send(g).unwrap()
+        })
+        .unwrap();
+        let publisher = TransactionalPublisher::new(&names, &opts).unwrap();
+
+        let a = Msg::new(1, &[1]);
+        let b = Msg::new(2, &[2]);
+        let id = publisher.publish(&[(1, &b), (0, &a)], 0).unwrap();
+        let group = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(group.id, id);
+        let parts: Vec<_> = group
+            .parts
+            .iter()
+            .map(|(topic, msg)| (topic.as_str(), msg.hdr.msg_type, msg.data()[0]))
+            .collect();
+        assert_eq!(parts, vec![(tmp_b.name(), 2, 2), (tmp_a.name(), 1, 1)]);
+
+        // Half a group: send only the first part by hand.
+        let half = publisher.publish(&[(0, &a), (5, &b)], 0);
+        assert_eq!(half.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let ext = ExtHeader {
+            present: EXT_GROUP,
+            group: 99,
+            group_index: 0,
+            group_size: 2,
+            ..Default::default()
+        };
+        let raw = MqTopic::new(tmp_a.name(), 8).unwrap();
+        raw.publish(&Msg::with_ext(1, &ext, &[3]), 0).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        publisher.publish(&[(0, &a), (1, &b)], 0).unwrap();
+        rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(rx.try_recv().is_err());
+        let stats = sub.stats();
+        assert_eq!((stats.delivered, stats.incomplete), (2, 1));
+    }
+}
@@ -0,0 +1,447 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! gRPC gateway exposing selected topics to off-board services.
+//! Enabled by the `grpc` feature.
+//!
+//! The service is described in `proto/gateway.proto`: `Subscribe` is a
+//! server-streaming call that forwards every message received on an
+//! exposed topic, and `Publish` is a unary call that injects a message
+//! into a topic the gateway accepts publishes for. Only topics named in
+//! [`GatewayOptions`] are reachable; anything else is `NOT_FOUND`.
+//!
+//! ```no_run
+//! use mq_ipc::grpc::{Gateway, GatewayOptions};
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let opts = GatewayOptions::new()
+//!     .stream("/motor_state")
+//!     .accept_publish("/motor_cmd");
+//! let _gateway = Gateway::serve("0.0.0.0:50051", &opts)?;
+//! mq_ipc::shutdown::spin()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The gateway runs its own Tokio runtime on a background thread, so the
+//! caller does not need to be async. It holds one subscription per
+//! streamed topic and fans it out to all connected streams; a stream
+//! that falls behind by more than [`STREAM_BUFFER`] messages skips ahead.
+
+use super::{current_priority, MqTopic, Msg, TopicOptions, MSG_PAYLOAD_SIZE};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    io,
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    thread,
+    time::SystemTime,
+};
+use tokio::sync::{broadcast, oneshot};
+use tokio_stream::{
+    wrappers::{BroadcastStream, TcpListenerStream},
+    Stream, StreamExt,
+};
+use tonic::{
+    body::Body,
+    codegen::{http, BoxFuture, Service, StdError},
+    server::{Grpc, NamedService, ServerStreamingService, UnaryService},
+    Request, Response, Status,
+};
+use tonic_prost::ProstCodec;
+
+/// Messages buffered per streaming client before it starts losing them.
+pub const STREAM_BUFFER: usize = 256;
+
+const SERVICE_NAME: &str = "mq_ipc.gateway.Gateway";
+
+/// `SubscribeRequest` of `proto/gateway.proto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequest {
+    #[prost(string, tag = "1")]
+    pub topic: String,
+}
+
+/// `TopicMessage` of `proto/gateway.proto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TopicMessage {
+    #[prost(string, tag = "1")]
+    pub topic: String,
+    #[prost(uint32, tag = "2")]
+    pub msg_type: u32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub payload: Vec<u8>,
+    #[prost(uint64, tag = "4")]
+    pub timestamp_ns: u64,
+    #[prost(uint32, tag = "5")]
+    pub prio: u32,
+}
+
+/// `PublishRequest` of `proto/gateway.proto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PublishRequest {
+    #[prost(string, tag = "1")]
+    pub topic: String,
+    #[prost(uint32, tag = "2")]
+    pub msg_type: u32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub payload: Vec<u8>,
+    #[prost(uint32, tag = "4")]
+    pub prio: u32,
+}
+
+/// `PublishReply` of `proto/gateway.proto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PublishReply {}
+
+/// Which topics a [`Gateway`] exposes, and how.
+#[derive(Clone, Debug, Default)]
+pub struct GatewayOptions {
+    streams: Vec<String>,
+    publishes: Vec<String>,
+    topic: Option<TopicOptions>,
+}
+
+impl GatewayOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow clients to `Subscribe` to `topic`.
+    pub fn stream(mut self, topic: &str) -> Self {
+        self.streams.push(topic.to_string());
+        self
+    }
+
+    /// Allow clients to `Publish` on `topic`.
+    pub fn accept_publish(mut self, topic: &str) -> Self {
+        self.publishes.push(topic.to_string());
+        self
+    }
+
+    /// Options used to open the exposed topics; [`TopicOptions::default`]
+    /// when unset.
+    pub fn topic_options(mut self, opts: TopicOptions) -> Self {
+        self.topic = Some(opts);
+        self
+    }
+}
+
+struct Streamed {
+    // Keeps the subscription alive.
+    _topic: MqTopic,
+    tx: broadcast::Sender<TopicMessage>,
+}
+
+struct State {
+    streams: HashMap<String, Streamed>,
+    publishes: HashMap<String, Arc<MqTopic>>,
+}
+
+/// Running gateway; shuts the server down on drop.
+pub struct Gateway {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<thread::JoinHandle<()>>,
+}
+
+impl Gateway {
+    /// Open the topics named in `opts` and serve them on `addr`.
+    pub fn serve(addr: impl ToSocketAddrs, opts: &GatewayOptions) -> io::Result<Self> {
+        let topic_opts = opts.topic.clone().unwrap_or_default();
+
+        let mut streams = HashMap::new();
+        for name in &opts.streams {
+            let topic = MqTopic::with_options(name, &topic_opts)?;
+            let (tx, _) = broadcast::channel(STREAM_BUFFER);
+            let sender = tx.clone();
+            let label = name.clone();
+            topic.subscribe(move |msg: Msg| {
+                // No receivers just means nobody is streaming right now.
+                let _ = sender.send(to_proto(&label, &msg));
+            });
+            streams.insert(name.clone(), Streamed { _topic: topic, tx });
+        }
+
+        let mut publishes = HashMap::new();
+        for name in &opts.publishes {
+            let topic = MqTopic::with_options(name, &topic_opts)?;
+            publishes.insert(name.clone(), Arc::new(topic));
+        }
+
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()?;
+        let service = GatewayService {
+            state: Arc::new(State { streams, publishes }),
+        };
+        let (shutdown, stop) = oneshot::channel::<()>();
+
+        let server = thread::spawn(move || {
+            runtime.block_on(async move {
+                let listener = match tokio::net::TcpListener::from_std(listener) {
+                    Ok(listener) => listener,
+                    Err(err) => {
+                        eprintln!("grpc gateway listener error: {err}");
+                        return;
+                    }
+                };
+                let result = tonic::transport::Server::builder()
+                    .add_service(service)
+                    .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
+                        let _ = stop.await;
+                    })
+                    .await;
+                if let Err(err) = result {
+                    eprintln!("grpc gateway error: {err}");
+                }
+            });
+        });
+
+        Ok(Gateway {
+            addr,
+            shutdown: Some(shutdown),
+            server: Some(server),
+        })
+    }
+
+    /// Address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for Gateway {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        if let Some(handle) = self.server.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn to_proto(topic: &str, msg: &Msg) -> TopicMessage {
+    let len = (msg.hdr.len as usize).min(MSG_PAYLOAD_SIZE);
+    TopicMessage {
+        topic: topic.to_string(),
+        msg_type: msg.hdr.msg_type as u32,
+        payload: msg.payload[..len].to_vec(),
+        timestamp_ns: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64),
+        prio: current_priority().unwrap_or(0),
+    }
+}
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<TopicMessage, Status>> + Send + 'static>>;
+
+#[derive(Clone)]
+struct GatewayService {
+    state: Arc<State>,
+}
+
+impl NamedService for GatewayService {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+struct SubscribeSvc(Arc<State>);
+
+impl ServerStreamingService<SubscribeRequest> for SubscribeSvc {
+    type Response = TopicMessage;
+    type ResponseStream = MessageStream;
+    type Future = BoxFuture<Response<MessageStream>, Status>;
+
+    fn call(&mut self, request: Request<SubscribeRequest>) -> Self::Future {
+        let state = Arc::clone(&self.0);
+        Box::pin(async move {
+            let topic = request.into_inner().topic;
+            let Some(streamed) = state.streams.get(&topic) else {
+                return Err(Status::not_found(format!("{topic} is not streamed")));
+            };
+            // Lagged receivers yield an error item; skipping it resumes
+            // at the oldest message still buffered.
+            let stream =
+                BroadcastStream::new(streamed.tx.subscribe()).filter_map(|item| item.ok().map(Ok));
+            Ok(Response::new(Box::pin(stream) as MessageStream))
+        })
+    }
+}
+
+struct PublishSvc(Arc<State>);
+
+impl UnaryService<PublishRequest> for PublishSvc {
+    type Response = PublishReply;
+    type Future = BoxFuture<Response<PublishReply>, Status>;
+
+    fn call(&mut self, request: Request<PublishRequest>) -> Self::Future {
+        let state = Arc::clone(&self.0);
+        Box::pin(async move {
+            let req = request.into_inner();
+            let Some(topic) = state.publishes.get(&req.topic) else {
+                return Err(Status::not_found(format!(
+                    "{} does not accept publishes",
+                    req.topic
+                )));
+            };
+            let msg_type = u16::try_from(req.msg_type)
+                .map_err(|_| Status::invalid_argument("msg_type does not fit in 16 bits"))?;
+            if req.payload.len() > MSG_PAYLOAD_SIZE {
+                return Err(Status::invalid_argument(format!(
+                    "payload of {} bytes exceeds {MSG_PAYLOAD_SIZE}",
+                    req.payload.len()
+                )));
+            }
+            let msg = Msg::new(msg_type, &req.payload);
+            let topic = Arc::clone(topic);
+            // `mq_send` may block on a full queue.
+            tokio::task::spawn_blocking(move || topic.publish(&msg, req.prio))
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+            Ok(Response::new(PublishReply {}))
+        })
+    }
+}
+
+impl<B> Service<http::Request<B>> for GatewayService
+where
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let state = Arc::clone(&self.state);
+        let method = req
+            .uri()
+            .path()
+            .strip_prefix('/')
+            .and_then(|p| p.strip_prefix(SERVICE_NAME))
+            .and_then(|p| p.strip_prefix('/'));
+        match method {
+            Some("Subscribe") => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.server_streaming(SubscribeSvc(state), req).await)
+            }),
+            Some("Publish") => Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(PublishSvc(state), req).await)
+            }),
+            _ => Box::pin(async move { Ok(Status::unimplemented("").into_http()) }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::mpsc, time::Duration};
+    use tonic::{codegen::http::uri::PathAndQuery, transport::Channel};
+
+    #[test]
+    fn streams_and_accepts_publishes() {
+        let tmp_out = TempTopic::new("/mq_ipc_test_grpc_out_");
+        let tmp_in = TempTopic::new("/mq_ipc_test_grpc_in_");
+        let opts = GatewayOptions::new()
+            .stream(tmp_out.name())
+            .accept_publish(tmp_in.name())
+            .topic_options(TopicOptions::new(8));
+        let gateway = Gateway::serve("127.0.0.1:0", &opts).unwrap();
+
+        let inbound = MqTopic::new(tmp_in.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        inbound.subscribe(move |msg| tx.send(msg.hdr.msg_type).unwrap());
+        let outbound = MqTopic::new(tmp_out.name(), 8).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let channel = Channel::from_shared(format!("http://{}", gateway.local_addr()))
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut grpc = tonic::client::Grpc::new(channel);
+
+            grpc.ready().await.unwrap();
+            let path = PathAndQuery::from_static("/mq_ipc.gateway.Gateway/Subscribe");
+            let req = SubscribeRequest {
+                topic: tmp_out.name().to_string(),
+            };
+            let mut stream = grpc
+                .server_streaming(Request::new(req), path, ProstCodec::default())
+                .await
+                .unwrap()
+                .into_inner();
+
+            outbound.publish(&Msg::new(5, b"hello"), 0).unwrap();
+            let got: TopicMessage = stream.message().await.unwrap().unwrap();
+            assert_eq!((got.msg_type, &got.payload[..]), (5, &b"hello"[..]));
+
+            grpc.ready().await.unwrap();
+            let path = PathAndQuery::from_static("/mq_ipc.gateway.Gateway/Publish");
+            let req = PublishRequest {
+                topic: tmp_in.name().to_string(),
+                msg_type: 9,
+                payload: vec![1, 2, 3],
+                prio: 0,
+            };
+            let _: Response<PublishReply> = grpc
+                .unary(Request::new(req), path, ProstCodec::default())
+                .await
+                .unwrap();
+
+            grpc.ready().await.unwrap();
+            let path = PathAndQuery::from_static("/mq_ipc.gateway.Gateway/Publish");
+            let req = PublishRequest {
+                topic: tmp_out.name().to_string(),
+                ..Default::default()
+            };
+            let err = grpc
+                .unary::<_, PublishReply, _>(Request::new(req), path, ProstCodec::default())
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), tonic::Code::NotFound);
+        });
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), 9);
+    }
+}
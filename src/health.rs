@@ -0,0 +1,687 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Uniform health protocol between processes.
+//!
+//! A [`Node`] publishes a [`NodeStatus`] on `/ipc_health.<node>` at a
+//! fixed period: its [`NodeState`], uptime and error counters. A
+//! [`HealthMonitor`] finds the health topics through the discovery
+//! [`registry`], including those of nodes that start after it, and keeps
+//! the latest status of each. A node that stops
+//! reporting shows up as not [`alive`](NodeHealth::alive) once the
+//! monitor's timeout has passed.
+//!
+//! The kernel does not allow a `/` inside a queue name, so the node name
+//! is joined to [`HEALTH_TOPIC_PREFIX`] with a dot.
+//!
+//! A process that calls [`install_crash_hook`] also publishes a
+//! [`NodeCrashed`] on [`CRASH_TOPIC`] when it panics, so the monitor marks
+//! the node dead right away instead of waiting out the timeout.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::health::{HealthMonitor, Node, NodeOptions, NodeState};
+//! use std::time::Duration;
+//!
+//! let node = Node::new("planner", &NodeOptions::default())?;
+//! node.set_state(NodeState::Ok);
+//!
+//! let monitor = HealthMonitor::new(Duration::from_secs(3))?;
+//! for n in monitor.nodes() {
+//!     println!("{}: {:?} alive={}", n.name, n.state(), n.alive);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{defaults, registry, Topic, TopicOptions};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    backtrace::Backtrace,
+    collections::HashMap,
+    hash::{DefaultHasher, Hasher},
+    io,
+    panic::{self, PanicHookInfo},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Health topics are named `<prefix><node>`.
+pub const HEALTH_TOPIC_PREFIX: &str = "/ipc_health.";
+
+/// Longest node name carried by a [`NodeStatus`].
+pub const NODE_NAME_LEN: usize = 64;
+
+/// Well-known topic carrying [`NodeCrashed`] reports.
+pub const CRASH_TOPIC: &str = "/ipc_crash";
+
+/// Longest panic message carried by a [`NodeCrashed`]; longer ones are
+/// truncated.
+pub const CRASH_MESSAGE_LEN: usize = 152;
+
+/// Depth of a health topic; it conflates, so only the latest matters.
+const HEALTH_DEPTH: libc::c_long = 2;
+
+/// How often a [`HealthMonitor`] looks for new health topics.
+const SCAN_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Depth of [`CRASH_TOPIC`]; crashes are rare but must not conflate.
+const CRASH_DEPTH: libc::c_long = 8;
+
+/// Polling period of the background threads.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Coarse state a node reports about itself.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum NodeState {
+    /// Initializing; not serving yet.
+    Starting = 0,
+    Ok = 1,
+    /// Serving with reduced capability.
+    Degraded = 2,
+    /// Not serving.
+    Error = 3,
+    /// Shutting down; the last status a node sends.
+    Stopping = 4,
+}
+
+impl NodeState {
+    /// Decode the wire value, `None` for unknown states.
+    pub fn from_u32(v: u32) -> Option<Self> {
+        Some(match v {
+            0 => NodeState::Starting,
+            1 => NodeState::Ok,
+            2 => NodeState::Degraded,
+            3 => NodeState::Error,
+            4 => NodeState::Stopping,
+            _ => return None,
+        })
+    }
+}
+
+/// Periodic status of one node, published on its health topic.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct NodeStatus {
+    /// Wall-clock time of the report, in ns since the Unix epoch.
+    pub timestamp_ns: u64,
+    pub uptime_ms: u64,
+    pub errors: u64,
+    pub warnings: u64,
+    pub pid: u32,
+    /// A [`NodeState`]; see [`NodeStatus::state`].
+    pub state: u32,
+    pub name_len: u32,
+    pub reserved: u32,
+    pub name: [u8; NODE_NAME_LEN],
+}
+
+impl NodeStatus {
+    /// Reported state, `None` if the value is not a known [`NodeState`].
+    pub fn state(&self) -> Option<NodeState> {
+        NodeState::from_u32(self.state)
+    }
+
+    /// Name of the reporting node.
+    pub fn name(&self) -> &str {
+        let len = (self.name_len as usize).min(NODE_NAME_LEN);
+        std::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+/// Health topic of node `name`.
+pub fn health_topic(name: &str) -> String {
+    format!("{HEALTH_TOPIC_PREFIX}{name}")
+}
+
+fn check_node_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name.len() > NODE_NAME_LEN || name.contains('/') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid node name {name:?}"),
+        ));
+    }
+    Ok(())
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Last words of a node that panicked, published on [`CRASH_TOPIC`] by the
+/// hook of [`install_crash_hook`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct NodeCrashed {
+    /// Wall-clock time of the panic, in ns since the Unix epoch.
+    pub timestamp_ns: u64,
+    /// Hash of the panicking thread's backtrace; equal hashes from the same
+    /// binary are the same crash site.
+    pub backtrace_hash: u64,
+    pub pid: u32,
+    pub name_len: u16,
+    pub message_len: u16,
+    pub name: [u8; NODE_NAME_LEN],
+    pub message: [u8; CRASH_MESSAGE_LEN],
+}
+
+impl NodeCrashed {
+    fn new(name: &str, message: &str, backtrace_hash: u64) -> Self {
+        let mut end = message.len().min(CRASH_MESSAGE_LEN);
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut crash = NodeCrashed::zeroed();
+        crash.timestamp_ns = now_ns();
+        crash.backtrace_hash = backtrace_hash;
+        crash.pid = std::process::id();
+        crash.name_len = name.len() as u16;
+        crash.name[..name.len()].copy_from_slice(name.as_bytes());
+        crash.message_len = end as u16;
+        crash.message[..end].copy_from_slice(&message.as_bytes()[..end]);
+        crash
+    }
+
+    /// Name of the crashed node.
+    pub fn name(&self) -> &str {
+        let len = (self.name_len as usize).min(NODE_NAME_LEN);
+        std::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+
+    /// Panic message with its source location, possibly truncated.
+    pub fn message(&self) -> &str {
+        let len = (self.message_len as usize).min(CRASH_MESSAGE_LEN);
+        std::str::from_utf8(&self.message[..len]).unwrap_or("")
+    }
+}
+
+struct CrashHook {
+    name: String,
+    topic: Topic<NodeCrashed>,
+}
+
+static CRASH_HOOK: Mutex<Option<CrashHook>> = Mutex::new(None);
+
+/// Publish a [`NodeCrashed`] for `name` on [`CRASH_TOPIC`] whenever this
+/// process panics, then run the previously installed panic hook.
+///
+/// The topic is opened here rather than in the hook, and the report is sent
+/// without blocking, so a panic never waits on a full queue. Every panic is
+/// reported, including ones later caught with `catch_unwind`. Calling it
+/// again only changes the reported name.
+pub fn install_crash_hook(name: &str) -> io::Result<()> {
+    check_node_name(name)?;
+    let topic = Topic::with_options(
+        CRASH_TOPIC,
+        &TopicOptions::new(CRASH_DEPTH).nonblocking(true),
+    )?;
+
+    let mut hook = CRASH_HOOK.lock().unwrap_or_else(|e| e.into_inner());
+    let first = hook.is_none();
+    *hook = Some(CrashHook {
+        name: name.to_string(),
+        topic,
+    });
+    drop(hook);
+
+    if first {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            report_crash(info);
+            previous(info);
+        }));
+    }
+    Ok(())
+}
+
+fn report_crash(info: &PanicHookInfo<'_>) {
+    let hook = CRASH_HOOK.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(hook) = hook.as_ref() else {
+        return;
+    };
+
+    let payload = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    let message = match info.location() {
+        Some(loc) => format!("{loc}: {payload}"),
+        None => payload.to_string(),
+    };
+    let mut hasher = DefaultHasher::new();
+    hasher.write(Backtrace::force_capture().to_string().as_bytes());
+
+    let crash = NodeCrashed::new(&hook.name, &message, hasher.finish());
+    let _ = hook.topic.publish(&crash, 0, 0);
+}
+
+/// Reporting period of a [`Node`].
+#[derive(Copy, Clone, Debug)]
+pub struct NodeOptions {
+    pub period: Duration,
+}
+
+impl NodeOptions {
+    pub fn period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+}
+
+impl Default for NodeOptions {
+    fn default() -> Self {
+        NodeOptions {
+            period: Duration::from_secs(1),
+        }
+    }
+}
+
+struct NodeShared {
+    name: String,
+    started: Instant,
+    state: AtomicU32,
+    errors: AtomicU64,
+    warnings: AtomicU64,
+    running: AtomicBool,
+    topic: Topic<NodeStatus>,
+}
+
+impl NodeShared {
+    fn status(&self) -> NodeStatus {
+        let mut status = NodeStatus::zeroed();
+        status.timestamp_ns = now_ns();
+        status.uptime_ms = self.started.elapsed().as_millis() as u64;
+        status.errors = self.errors.load(Ordering::Relaxed);
+        status.warnings = self.warnings.load(Ordering::Relaxed);
+        status.pid = std::process::id();
+        status.state = self.state.load(Ordering::Relaxed);
+        status.name_len = self.name.len() as u32;
+        status.name[..self.name.len()].copy_from_slice(self.name.as_bytes());
+        status
+    }
+
+    fn report(&self) {
+        let _ = self.topic.publish(&self.status(), 0, 0);
+    }
+}
+
+/// A process (or component) that reports its health; see the module docs.
+///
+/// Starts in [`NodeState::Starting`] and sends a final
+/// [`NodeState::Stopping`] status when dropped.
+pub struct Node {
+    shared: Arc<NodeShared>,
+    reporter: Option<thread::JoinHandle<()>>,
+}
+
+impl Node {
+    /// Start reporting as `name`, which must be a plain queue-name
+    /// component of at most [`NODE_NAME_LEN`] bytes.
+    pub fn new(name: &str, opts: &NodeOptions) -> io::Result<Self> {
+        check_node_name(name)?;
+        let topic = Topic::with_options(
+            &health_topic(name),
+            &TopicOptions::new(HEALTH_DEPTH)
+                .conflate(true)
+                .nonblocking(true),
+        )?;
+        let shared = Arc::new(NodeShared {
+            name: name.to_string(),
+            started: Instant::now(),
+            state: AtomicU32::new(NodeState::Starting as u32),
+            errors: AtomicU64::new(0),
+            warnings: AtomicU64::new(0),
+            running: AtomicBool::new(true),
+            topic,
+        });
+        shared.report();
+
+        let reporter = Arc::clone(&shared);
+        let period = opts.period;
+        let handle = thread::spawn(move || {
+            let mut next = Instant::now() + period;
+            while reporter.running.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                if now < next {
+                    thread::sleep((next - now).min(POLL_INTERVAL));
+                    continue;
+                }
+                next += period;
+                reporter.report();
+            }
+        });
+
+        Ok(Node {
+            shared,
+            reporter: Some(handle),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.shared.name
+    }
+
+    /// Change the reported state. The new state goes out immediately.
+    pub fn set_state(&self, state: NodeState) {
+        let old = self.shared.state.swap(state as u32, Ordering::Relaxed);
+        if old != state as u32 {
+            self.shared.report();
+        }
+    }
+
+    pub fn state(&self) -> NodeState {
+        NodeState::from_u32(self.shared.state.load(Ordering::Relaxed)).unwrap_or(NodeState::Error)
+    }
+
+    /// Count an error in the next status.
+    pub fn record_error(&self) {
+        self.shared.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a warning in the next status.
+    pub fn record_warning(&self) {
+        self.shared.warnings.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The status this node would publish now.
+    pub fn status(&self) -> NodeStatus {
+        self.shared.status()
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.reporter.take() {
+            let _ = handle.join();
+        }
+        self.set_state(NodeState::Stopping);
+    }
+}
+
+/// Latest health of one node, as seen by a [`HealthMonitor`].
+#[derive(Clone, Debug)]
+pub struct NodeHealth {
+    pub name: String,
+    pub status: NodeStatus,
+    /// When the last status arrived.
+    pub last_seen: Instant,
+    /// Crash reported by the node since its last status, if any.
+    pub crash: Option<NodeCrashed>,
+    /// Whether that status was within the monitor's timeout and no crash
+    /// followed it.
+    pub alive: bool,
+}
+
+impl NodeHealth {
+    /// Reported state, `None` if the node sent an unknown value.
+    pub fn state(&self) -> Option<NodeState> {
+        self.status.state()
+    }
+}
+
+type Statuses = Arc<Mutex<HashMap<String, (NodeStatus, Instant)>>>;
+type Crashes = Arc<Mutex<HashMap<String, (NodeCrashed, Instant)>>>;
+
+/// Aggregates the health topics of all nodes; see the module docs.
+pub struct HealthMonitor {
+    statuses: Statuses,
+    crashes: Crashes,
+    _crash_topic: Topic<NodeCrashed>,
+    timeout: Duration,
+    running: Arc<AtomicBool>,
+    scanner: Option<thread::JoinHandle<()>>,
+}
+
+impl HealthMonitor {
+    /// Watch every health topic. Nodes silent for longer than `timeout`
+    /// are reported as not alive.
+    pub fn new(timeout: Duration) -> io::Result<Self> {
+        let statuses: Statuses = Arc::new(Mutex::new(HashMap::new()));
+        let mut topics = HashMap::new();
+        scan(&mut topics, &statuses)?;
+
+        let crashes: Crashes = Arc::new(Mutex::new(HashMap::new()));
+        let crash_topic =
+            Topic::<NodeCrashed>::with_options(CRASH_TOPIC, &TopicOptions::new(CRASH_DEPTH))?;
+        {
+            let crashes = Arc::clone(&crashes);
+            crash_topic.subscribe(move |crash: NodeCrashed| {
+                crashes
+                    .lock()
+                    .unwrap()
+                    .insert(crash.name().to_string(), (crash, Instant::now()));
+            });
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let scanner = {
+            let statuses = Arc::clone(&statuses);
+            let running = Arc::clone(&running);
+            thread::spawn(move || {
+                let mut next = Instant::now() + SCAN_INTERVAL;
+                while running.load(Ordering::Relaxed) {
+                    let now = Instant::now();
+                    if now < next {
+                        thread::sleep((next - now).min(POLL_INTERVAL));
+                        continue;
+                    }
+                    next += SCAN_INTERVAL;
+                    if let Err(err) = scan(&mut topics, &statuses) {
+                        eprintln!("health scan: {err}");
+                    }
+                }
+            })
+        };
+
+        Ok(HealthMonitor {
+            statuses,
+            crashes,
+            _crash_topic: crash_topic,
+            timeout,
+            running,
+            scanner: Some(scanner),
+        })
+    }
+
+    /// Every node heard from so far, sorted by name.
+    pub fn nodes(&self) -> Vec<NodeHealth> {
+        let now = Instant::now();
+        let statuses = self.statuses.lock().unwrap();
+        let mut nodes: Vec<_> = statuses
+            .iter()
+            .map(|(name, (status, seen))| self.health(name, status, *seen, now))
+            .collect();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        nodes
+    }
+
+    /// Latest health of node `name`, if it has reported.
+    pub fn node(&self, name: &str) -> Option<NodeHealth> {
+        let statuses = self.statuses.lock().unwrap();
+        statuses
+            .get(name)
+            .map(|(status, seen)| self.health(name, status, *seen, Instant::now()))
+    }
+
+    /// Every crash reported so far, the latest per node, sorted by name.
+    pub fn crashes(&self) -> Vec<NodeCrashed> {
+        let crashes = self.crashes.lock().unwrap();
+        let mut all: Vec<_> = crashes.values().map(|(crash, _)| *crash).collect();
+        all.sort_by(|a, b| a.name().cmp(b.name()));
+        all
+    }
+
+    /// Whether every node heard from is alive and [`NodeState::Ok`].
+    pub fn all_ok(&self) -> bool {
+        self.nodes()
+            .iter()
+            .all(|n| n.alive && n.state() == Some(NodeState::Ok))
+    }
+
+    fn health(&self, name: &str, status: &NodeStatus, seen: Instant, now: Instant) -> NodeHealth {
+        let crash = self
+            .crashes
+            .lock()
+            .unwrap()
+            .get(name)
+            .filter(|(_, at)| *at >= seen)
+            .map(|(crash, _)| *crash);
+        NodeHealth {
+            name: name.to_string(),
+            status: *status,
+            last_seen: seen,
+            crash,
+            alive: crash.is_none() && now.duration_since(seen) <= self.timeout,
+        }
+    }
+}
+
+impl Drop for HealthMonitor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.scanner.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Subscribe to health topics that appeared since the last scan.
+fn scan(topics: &mut HashMap<String, Topic<NodeStatus>>, statuses: &Statuses) -> io::Result<()> {
+    let prefix = defaults::topic_name(HEALTH_TOPIC_PREFIX);
+    for queue in registry::topics()? {
+        let Some(node) = queue.strip_prefix(&*prefix) else {
+            continue;
+        };
+        if topics.contains_key(node) {
+            continue;
+        }
+        let topic = match Topic::<NodeStatus>::open_resolved(
+            &queue,
+            &TopicOptions::new(HEALTH_DEPTH).conflate(true),
+        ) {
+            Ok(topic) => topic,
+            // Unlinked between listing and opening.
+            Err(err) if err.raw_os_error() == Some(libc::ENOENT) => continue,
+            Err(err) => return Err(err),
+        };
+        let statuses = Arc::clone(statuses);
+        let name = node.to_string();
+        topic.subscribe(move |status: NodeStatus| {
+            statuses
+                .lock()
+                .unwrap()
+                .insert(name.clone(), (status, Instant::now()));
+        });
+        topics.insert(node.to_string(), topic);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    fn wait_for(monitor: &HealthMonitor, name: &str, state: NodeState) -> NodeHealth {
+        for _ in 0..100 {
+            if let Some(n) = monitor.node(name)
+                && n.state() == Some(state)
+            {
+                return n;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("{name} never reported {state:?}");
+    }
+
+    #[test]
+    fn monitor_tracks_node_state() {
+        let name = format!("mq_ipc_test_health_{}", std::process::id());
+        let _tmp = TempTopic::with_name(&health_topic(&name));
+
+        let monitor = HealthMonitor::new(Duration::from_secs(5)).unwrap();
+        let node = Node::new(
+            &name,
+            &NodeOptions::default().period(Duration::from_millis(50)),
+        )
+        .unwrap();
+        wait_for(&monitor, &name, NodeState::Starting);
+
+        node.record_error();
+        node.set_state(NodeState::Degraded);
+        let seen = wait_for(&monitor, &name, NodeState::Degraded);
+        assert!(seen.alive);
+        assert_eq!(seen.status.name(), name);
+        assert_eq!(seen.status.pid, std::process::id());
+        assert!(!monitor.all_ok());
+
+        drop(node);
+        wait_for(&monitor, &name, NodeState::Stopping);
+        assert!(Node::new("bad/name", &NodeOptions::default()).is_err());
+    }
+
+    #[test]
+    fn crash_hook_reports_panics() {
+        let name = format!("mq_ipc_test_crash_{}", std::process::id());
+        let _tmp = TempTopic::with_name(&health_topic(&name));
+        let _crash = TempTopic::with_name(CRASH_TOPIC);
+
+        let monitor = HealthMonitor::new(Duration::from_secs(5)).unwrap();
+        // No periodic status may land between the crash and the check.
+        let opts = NodeOptions::default().period(Duration::from_secs(60));
+        let node = Node::new(&name, &opts).unwrap();
+        node.set_state(NodeState::Ok);
+        wait_for(&monitor, &name, NodeState::Ok);
+        install_crash_hook(&name).unwrap();
+
+        let line = line!() + 1;
+        let _ = panic::catch_unwind(|| panic!("sensor on fire"));
+        for _ in 0..100 {
+            if monitor.node(&name).is_some_and(|n| n.crash.is_some()) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let seen = monitor.node(&name).unwrap();
+        assert!(!seen.alive);
+        let crash = seen.crash.unwrap();
+        assert_eq!(crash.name(), name);
+        assert_eq!(crash.pid, std::process::id());
+        assert!(crash.message().ends_with("sensor on fire"));
+        assert!(crash.message().contains(&format!("health.rs:{line}:")));
+        assert_eq!(monitor.crashes().len(), 1);
+        assert!(install_crash_hook("bad/name").is_err());
+    }
+}
@@ -0,0 +1,174 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Short look-back over a subscription.
+//!
+//! [`MqTopic::lookback`](crate::MqTopic::lookback) and its
+//! typed counterpart subscribe a bounded ring that keeps the last few
+//! messages, so an algorithm that starts late can look back over a short
+//! window without a full recorder:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::Topic;
+//! use std::time::Duration;
+//!
+//! let imu = Topic::<[f32; 6]>::new("/imu", 8)?;
+//! let lookback = imu.lookback(200);
+//! // ... later ...
+//! let last_second = lookback.history_within(Duration::from_secs(1));
+//! let mut seen = last_second.last().map_or(0, |r| r.seq);
+//! for r in lookback.history_since(seen) {
+//!     seen = r.seq;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Entries are numbered from 1 in the order they were received, so a
+//! reader can poll [`Lookback::history_since`] with the last number it saw.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// One message or value kept by a [`Lookback`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Recorded<M> {
+    /// Position in the history, from 1.
+    pub seq: u64,
+    pub received: Instant,
+    pub value: M,
+}
+
+struct Ring<M> {
+    entries: VecDeque<Recorded<M>>,
+    capacity: usize,
+    last_seq: u64,
+}
+
+/// The last messages of a subscription; see the module docs. Clones share
+/// the same ring.
+pub struct Lookback<M> {
+    ring: Arc<Mutex<Ring<M>>>,
+}
+
+impl<M> Clone for Lookback<M> {
+    fn clone(&self) -> Self {
+        Lookback {
+            ring: Arc::clone(&self.ring),
+        }
+    }
+}
+
+impl<M: Clone> Lookback<M> {
+    /// An empty history of the last `capacity` entries, at least one.
+    pub(crate) fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Lookback {
+            ring: Arc::new(Mutex::new(Ring {
+                entries: VecDeque::with_capacity(capacity),
+                capacity,
+                last_seq: 0,
+            })),
+        }
+    }
+
+    pub(crate) fn record(&self, value: M) {
+        let mut ring = self.ring.lock().unwrap_or_else(|e| e.into_inner());
+        if ring.entries.len() == ring.capacity {
+            ring.entries.pop_front();
+        }
+        ring.last_seq += 1;
+        let seq = ring.last_seq;
+        ring.entries.push_back(Recorded {
+            seq,
+            received: Instant::now(),
+            value,
+        });
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.lock().unwrap_or_else(|e| e.into_inner()).capacity
+    }
+
+    /// Number of the latest entry, 0 before the first.
+    pub fn last_seq(&self) -> u64 {
+        self.ring.lock().unwrap_or_else(|e| e.into_inner()).last_seq
+    }
+
+    /// Every entry kept, oldest first.
+    pub fn history(&self) -> Vec<Recorded<M>> {
+        self.collect(|_| true)
+    }
+
+    /// Entries numbered after `seq`, oldest first. Entries that already
+    /// left the ring are gone; compare the first `seq` to tell.
+    pub fn history_since(&self, seq: u64) -> Vec<Recorded<M>> {
+        self.collect(|r| r.seq > seq)
+    }
+
+    /// Entries received within the last `window`, oldest first.
+    pub fn history_within(&self, window: Duration) -> Vec<Recorded<M>> {
+        let now = Instant::now();
+        self.collect(|r| now.duration_since(r.received) <= window)
+    }
+
+    fn collect(&self, keep: impl Fn(&Recorded<M>) -> bool) -> Vec<Recorded<M>> {
+        let ring = self.ring.lock().unwrap_or_else(|e| e.into_inner());
+        ring.entries.iter().filter(|r| keep(r)).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, Topic};
+    use std::thread;
+
+    #[test]
+    fn keeps_the_last_entries() {
+        let tmp = TempTopic::new("/mq_ipc_test_history_");
+        let topic = Topic::<u32>::new(tmp.name(), 8).unwrap();
+        let lookback = topic.lookback(3);
+        for v in 1..=5u32 {
+            topic.publish(&v, 0, 0).unwrap();
+        }
+        for _ in 0..100 {
+            if lookback.last_seq() == 5 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let values = |rs: Vec<Recorded<u32>>| rs.into_iter().map(|r| r.value).collect::<Vec<_>>();
+        assert_eq!(values(lookback.history()), [3, 4, 5]);
+        assert_eq!(values(lookback.history_since(4)), [5]);
+        assert_eq!(values(lookback.history_since(0)), [3, 4, 5]);
+        assert_eq!(lookback.history()[0].seq, 3);
+        assert_eq!(lookback.history_within(Duration::from_secs(60)).len(), 3);
+        assert_eq!(lookback.capacity(), 3);
+    }
+}
@@ -0,0 +1,410 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Tiny HTTP/JSON endpoint for debugging and shallow integrations.
+//! Enabled by the `http` feature.
+//!
+//! | request                        | response                              |
+//! |--------------------------------|---------------------------------------|
+//! | `GET /topics`                  | every known topic, with endpoint counts |
+//! | `GET /topics/{name}/latest`    | last value seen on a registered topic |
+//! | `POST /topics/{name}`          | publish the JSON body on it           |
+//!
+//! `{name}` is the topic name without its leading slash, so
+//! `/lidar/front` is reached at `/topics/lidar/front/latest`. Only topics
+//! registered with [`HttpServer::register`] can be read or written; that
+//! is where the server learns the Rust type to convert JSON from and to.
+//! Every other topic in the discovery registry is still listed.
+//!
+//! ```no_run
+//! # #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, serde::Serialize, serde::Deserialize)]
+//! # #[repr(C)]
+//! # struct MotorState { speed: f32 }
+//! # fn main() -> std::io::Result<()> {
+//! let server = mq_ipc::http::HttpServer::bind("127.0.0.1:8080")?;
+//! server.register::<MotorState>("/motor_state", 1, 8)?;
+//! // curl localhost:8080/topics/motor_state/latest
+//! mq_ipc::shutdown::spin()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `latest` comes from an ordinary subscription, so on a queue shared
+//! with other readers the server only sees its share of the messages.
+//! Connections are served one request at a time and then closed.
+
+use super::{registry, Topic};
+use bytemuck::{Pod, Zeroable};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    os::raw::c_long,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Largest request body accepted by `POST`.
+pub const MAX_BODY: usize = 64 * 1024;
+
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Latest = Arc<Mutex<Option<(SystemTime, Value)>>>;
+type PublishFn = Box<dyn Fn(Value) -> Result<(), String> + Send + Sync>;
+
+struct Registered {
+    type_name: &'static str,
+    latest: Latest,
+    publish: PublishFn,
+}
+
+struct Shared {
+    topics: Mutex<BTreeMap<String, Arc<Registered>>>,
+    running: AtomicBool,
+}
+
+/// HTTP server; stops accepting connections on drop.
+pub struct HttpServer {
+    shared: Arc<Shared>,
+    addr: SocketAddr,
+    acceptor: Option<thread::JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// Listen on `addr`.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            topics: Mutex::new(BTreeMap::new()),
+            running: AtomicBool::new(true),
+        });
+
+        let accept_shared = Arc::clone(&shared);
+        let acceptor = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !accept_shared.running.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let shared = Arc::clone(&accept_shared);
+                thread::spawn(move || {
+                    let _ = serve(stream, &shared);
+                });
+            }
+        });
+
+        Ok(HttpServer {
+            shared,
+            addr,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Open `name` as a `Topic<T>` and make it readable and writable over
+    /// HTTP. `POST`ed values are published with `msg_type`.
+    pub fn register<T>(&self, name: &str, msg_type: u16, maxmsg: c_long) -> io::Result<()>
+    where
+        T: Pod + Zeroable + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let topic = Arc::new(Topic::<T>::new(name, maxmsg)?);
+        let latest: Latest = Arc::new(Mutex::new(None));
+
+        let sink = Arc::clone(&latest);
+        topic.subscribe(move |value: T| {
+            if let Ok(json) = serde_json::to_value(value) {
+                *sink.lock().unwrap() = Some((SystemTime::now(), json));
+            }
+        });
+
+        let publish: PublishFn = Box::new(move |body| {
+            let value: T = serde_json::from_value(body).map_err(|e| e.to_string())?;
+            topic
+                .publish(&value, msg_type, 0)
+                .map_err(|e| e.to_string())
+        });
+
+        let entry = Registered {
+            type_name: std::any::type_name::<T>(),
+            latest,
+            publish,
+        };
+        self.shared
+            .topics
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), Arc::new(entry));
+        Ok(())
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+        // Unblock `accept` so the loop sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.acceptor.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+struct Reply {
+    status: u16,
+    body: Option<Value>,
+}
+
+impl Reply {
+    fn json(status: u16, body: Value) -> Self {
+        Reply {
+            status,
+            body: Some(body),
+        }
+    }
+
+    fn error(status: u16, msg: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": msg.into() }))
+    }
+}
+
+fn serve(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return respond(stream, Reply::error(400, "malformed request line")),
+    };
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':')
+            && key.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(usize::MAX);
+        }
+    }
+
+    let reply = if content_length > MAX_BODY {
+        Reply::error(413, format!("body larger than {MAX_BODY} bytes"))
+    } else {
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body)?;
+        route(shared, &method, &path, &body)
+    };
+    respond(stream, reply)
+}
+
+fn route(shared: &Shared, method: &str, path: &str, body: &[u8]) -> Reply {
+    let path = path.split('?').next().unwrap_or("");
+    if path == "/topics" || path == "/topics/" {
+        return match method {
+            "GET" => list_topics(shared),
+            _ => Reply::error(405, "method not allowed"),
+        };
+    }
+    let Some(rest) = path.strip_prefix("/topics/") else {
+        return Reply::error(404, "not found");
+    };
+
+    let (name, latest) = match rest.strip_suffix("/latest") {
+        Some(name) => (format!("/{name}"), true),
+        None => (format!("/{rest}"), false),
+    };
+    let Some(entry) = shared.topics.lock().unwrap().get(&name).cloned() else {
+        return Reply::error(404, format!("{name} is not registered"));
+    };
+
+    match (method, latest) {
+        ("GET", true) => match entry.latest.lock().unwrap().clone() {
+            Some((at, value)) => Reply::json(
+                200,
+                json!({
+                    "topic": name,
+                    "type": entry.type_name,
+                    "timestamp_ns": at
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .map_or(0, |d| d.as_nanos() as u64),
+                    "value": value,
+                }),
+            ),
+            None => Reply {
+                status: 204,
+                body: None,
+            },
+        },
+        ("POST", false) => {
+            let value = match serde_json::from_slice(body) {
+                Ok(value) => value,
+                Err(err) => return Reply::error(400, err.to_string()),
+            };
+            match (entry.publish)(value) {
+                Ok(()) => Reply {
+                    status: 204,
+                    body: None,
+                },
+                Err(err) => Reply::error(400, err),
+            }
+        }
+        _ => Reply::error(405, "method not allowed"),
+    }
+}
+
+fn list_topics(shared: &Shared) -> Reply {
+    let registered = shared.topics.lock().unwrap();
+    let mut names: Vec<String> = registry::topics().unwrap_or_default();
+    names.extend(registered.keys().cloned());
+    names.sort();
+    names.dedup();
+
+    let topics: Vec<Value> = names
+        .into_iter()
+        .map(|name| {
+            let endpoints = registry::endpoints(&name).unwrap_or_default();
+            let count = |role| endpoints.iter().filter(|ep| ep.role == role).count();
+            json!({
+                "name": name,
+                "type": registered.get(&name).map(|e| e.type_name),
+                "publishers": count(registry::Role::Publisher),
+                "subscribers": count(registry::Role::Subscriber),
+            })
+        })
+        .collect();
+    Reply::json(200, Value::Array(topics))
+}
+
+fn respond(mut stream: TcpStream, reply: Reply) -> io::Result<()> {
+    let reason = match reply.status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "",
+    };
+    let body = reply.body.map(|b| b.to_string()).unwrap_or_default();
+    let mut head = format!(
+        "HTTP/1.1 {} {reason}\r\nConnection: close\r\n",
+        reply.status
+    );
+    if reply.status != 204 {
+        head.push_str(&format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n",
+            body.len()
+        ));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use serde::Deserialize;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable, Serialize, Deserialize)]
+    struct Sample {
+        speed: f32,
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn post_then_read_latest() {
+        let tmp = TempTopic::new("/mq_ipc_test_http_");
+        let name = tmp.name().to_string();
+        let server = HttpServer::bind("127.0.0.1:0").unwrap();
+        server.register::<Sample>(&name, 1, 8).unwrap();
+        let addr = server.local_addr();
+        let latest = format!("/topics{name}/latest");
+
+        assert_eq!(request(addr, "GET", &latest, "").0, 204);
+        assert_eq!(
+            request(addr, "POST", &format!("/topics{name}"), "{\"speed\": 2.5}").0,
+            204
+        );
+        assert_eq!(
+            request(addr, "POST", &format!("/topics{name}"), "{}").0,
+            400
+        );
+
+        let mut body = String::new();
+        for _ in 0..100 {
+            let (status, b) = request(addr, "GET", &latest, "");
+            if status == 200 {
+                body = b;
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let value: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value["value"]["speed"], 2.5);
+
+        let (status, body) = request(addr, "GET", "/topics", "");
+        assert_eq!(status, 200);
+        let topics: Value = serde_json::from_str(&body).unwrap();
+        assert!(topics
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|t| t["name"] == name.as_str() && t["type"].is_string()));
+        assert_eq!(request(addr, "GET", "/topics/nope/latest", "").0, 404);
+    }
+}
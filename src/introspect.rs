@@ -0,0 +1,324 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Point-in-time dump of every topic handle in this process.
+//!
+//! [`dump`] gathers, for each live [`MqTopic`](crate::MqTopic), the
+//! queue attributes, the handle's [`TopicStats`] and worker health, plus
+//! the latest values of [`KeyedTopic`](crate::keyed::KeyedTopic)s that
+//! cache them, and renders it all as one JSON document. It is meant for
+//! crash reports and support bundles:
+//!
+//! ```no_run
+//! let _motor = mq_ipc::MqTopic::new("/motor/state", 8)?;
+//! std::fs::write("/tmp/ipc-dump.json", mq_ipc::introspect::dump())?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Values are written as hex, since the dump does not know their Rust
+//! types. Handles are listed in creation order; several handles on one
+//! topic each get an entry.
+
+use super::stats::TopicStats;
+use std::{
+    fmt::Write as _,
+    os::raw::c_long,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+/// Queue attributes as reported by `mq_getattr`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QueueAttr {
+    pub maxmsg: c_long,
+    pub msgsize: c_long,
+    pub curmsgs: c_long,
+    pub flags: c_long,
+}
+
+impl From<libc::mq_attr> for QueueAttr {
+    fn from(attr: libc::mq_attr) -> Self {
+        QueueAttr {
+            maxmsg: attr.mq_maxmsg,
+            msgsize: attr.mq_msgsize,
+            curmsgs: attr.mq_curmsgs,
+            flags: attr.mq_flags,
+        }
+    }
+}
+
+/// Latest value of a topic or of one key on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatestValue {
+    /// Instance key on keyed topics.
+    pub key: Option<u64>,
+    /// Time since the value was received.
+    pub age: Duration,
+    pub data: Vec<u8>,
+}
+
+/// State of one topic handle.
+#[derive(Clone, Debug)]
+pub struct TopicDump {
+    /// `None` if the queue could not be queried.
+    pub queue: Option<QueueAttr>,
+    pub stats: TopicStats,
+    pub healthy: bool,
+    pub restarts: u32,
+    pub latest: Vec<LatestValue>,
+}
+
+/// Everything [`snapshot`] found.
+#[derive(Clone, Debug)]
+pub struct Dump {
+    pub pid: u32,
+    pub timestamp: SystemTime,
+    pub topics: Vec<TopicDump>,
+}
+
+type TopicFn = Box<dyn Fn() -> TopicDump + Send + Sync + 'static>;
+type LatestFn = Box<dyn Fn() -> Vec<LatestValue> + Send + Sync + 'static>;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static TOPICS: Mutex<Vec<(u64, TopicFn)>> = Mutex::new(Vec::new());
+static LATEST: Mutex<Vec<(u64, String, LatestFn)>> = Mutex::new(Vec::new());
+
+/// Include a topic handle in dumps until [`unregister`] is called with the
+/// returned ID, which must happen before the resources `f` reads go away.
+pub(crate) fn register_topic<F>(f: F) -> u64
+where
+    F: Fn() -> TopicDump + Send + Sync + 'static,
+{
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    TOPICS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, Box::new(f)));
+    id
+}
+
+/// Add the values returned by `f` to the entries of topic `name`.
+#[cfg(feature = "callbacks")]
+pub(crate) fn register_latest<F>(name: &str, f: F) -> u64
+where
+    F: Fn() -> Vec<LatestValue> + Send + Sync + 'static,
+{
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    LATEST
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, name.to_string(), Box::new(f)));
+    id
+}
+
+pub(crate) fn unregister(id: u64) {
+    TOPICS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(i, _)| *i != id);
+    LATEST
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(i, _, _)| *i != id);
+}
+
+/// Collect the state of every registered topic handle.
+pub fn snapshot() -> Dump {
+    let mut topics: Vec<TopicDump> = TOPICS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(_, f)| f())
+        .collect();
+
+    for (_, name, f) in LATEST.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        if let Some(topic) = topics.iter_mut().find(|t| t.stats.name == *name) {
+            topic.latest.extend(f());
+        }
+    }
+
+    Dump {
+        pid: std::process::id(),
+        timestamp: SystemTime::now(),
+        topics,
+    }
+}
+
+/// [`snapshot`] rendered as JSON.
+pub fn dump() -> String {
+    snapshot().to_json()
+}
+
+impl Dump {
+    /// Render as a single JSON object. Durations are in nanoseconds,
+    /// the timestamp in nanoseconds since the Unix epoch.
+    pub fn to_json(&self) -> String {
+        let ts = self
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            r#"{{"pid":{},"timestamp_ns":{},"topics":["#,
+            self.pid,
+            ts.as_nanos()
+        );
+        for (i, t) in self.topics.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            t.write_json(&mut out);
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+impl TopicDump {
+    fn write_json(&self, out: &mut String) {
+        let s = &self.stats;
+        out.push_str(r#"{"name":"#);
+        push_str(out, &s.name);
+        out.push_str(r#","queue":"#);
+        match &self.queue {
+            Some(q) => {
+                let _ = write!(
+                    out,
+                    r#"{{"maxmsg":{},"msgsize":{},"curmsgs":{},"flags":{}}}"#,
+                    q.maxmsg, q.msgsize, q.curmsgs, q.flags
+                );
+            }
+            None => out.push_str("null"),
+        }
+        let _ = write!(
+            out,
+            r#","healthy":{},"restarts":{},"published":{},"dropped":{},"rejected":{}"#,
+            self.healthy, self.restarts, s.published, s.dropped, s.rejected
+        );
+        let _ = write!(
+            out,
+            r#","expired":{},"worker_cpu_ns":{}"#,
+            s.expired,
+            s.worker_cpu.as_nanos()
+        );
+        let _ = write!(
+            out,
+            r#","depth":{{"high_watermark":{},"avg":{:.3}}},"callbacks":["#,
+            s.depth.high_watermark,
+            s.depth.avg()
+        );
+        for (i, cb) in s.callbacks.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(
+                out,
+                r#"{{"index":{},"invocations":{},"total_ns":{},"max_ns":{},"over_budget":{}}}"#,
+                cb.index,
+                cb.invocations,
+                cb.total.as_nanos(),
+                cb.max.as_nanos(),
+                cb.over_budget
+            );
+        }
+        out.push_str(r#"],"latest":["#);
+        for (i, v) in self.latest.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(r#"{"key":"#);
+            match v.key {
+                Some(key) => {
+                    let _ = write!(out, "{key}");
+                }
+                None => out.push_str("null"),
+            }
+            let _ = write!(out, r#","age_ns":{},"data":""#, v.age.as_nanos());
+            for b in &v.data {
+                let _ = write!(out, "{b:02x}");
+            }
+            out.push_str("\"}");
+        }
+        out.push_str("]}");
+    }
+}
+
+/// Append `s` as a JSON string literal.
+fn push_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, keyed::KeyedTopic, MqTopic};
+    use std::thread;
+
+    #[test]
+    fn dump_lists_live_topics_and_latest_values() {
+        let tmp_raw = TempTopic::new("/mq_ipc_test_introspect_raw_");
+        let tmp_keyed = TempTopic::new("/mq_ipc_test_introspect_keyed_");
+
+        let raw = MqTopic::new(tmp_raw.name(), 4).unwrap();
+        let keyed = KeyedTopic::<u16>::new(tmp_keyed.name(), 4).unwrap();
+        keyed.track();
+        keyed.publish(7, &0xBEEF, 1, 0).unwrap();
+        for _ in 0..100 {
+            if keyed.latest(7).is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let json = dump();
+        assert!(json.starts_with(&format!(r#"{{"pid":{},"#, std::process::id())));
+        assert!(json.contains(&format!(
+            r#""name":"{}","queue":{{"maxmsg":4,"#,
+            tmp_raw.name()
+        )));
+        assert!(json.contains(r#"{"key":7,"age_ns":"#));
+        assert!(json.contains(r#""data":"efbe"}"#));
+
+        drop(raw);
+        assert!(!dump().contains(tmp_raw.name()));
+
+        let mut escaped = String::new();
+        push_str(&mut escaped, "a\"b\\\n");
+        assert_eq!(escaped, r#""a\"b\\\u000a""#);
+    }
+}
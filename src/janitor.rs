@@ -0,0 +1,221 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Garbage collection of queues left behind by crashed processes.
+//!
+//! Queues live until reboot unless someone unlinks them. The janitor
+//! walks the discovery [`registry`]: entries whose process is gone are
+//! deleted, and a topic whose every endpoint is dead has its queue
+//! unlinked. Run it from a supervisor via [`Janitor::start`] or as
+//! `mq-ipc janitor`.
+
+use super::{cleanup, registry};
+use std::{
+    fs, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// What a sweep may touch.
+#[derive(Copy, Clone, Debug)]
+pub struct JanitorOptions {
+    /// Dead entries younger than this are left alone, so a process being
+    /// restarted by its supervisor keeps its queued messages.
+    pub grace: Duration,
+    /// Only report what would be removed.
+    pub dry_run: bool,
+}
+
+impl JanitorOptions {
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    pub fn dry_run(mut self, on: bool) -> Self {
+        self.dry_run = on;
+        self
+    }
+}
+
+impl Default for JanitorOptions {
+    fn default() -> Self {
+        JanitorOptions {
+            grace: Duration::from_secs(30),
+            dry_run: false,
+        }
+    }
+}
+
+/// Outcome of a sweep.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SweepReport {
+    /// Registry entries of dead processes that were removed.
+    pub stale_entries: usize,
+    /// Queues that were unlinked.
+    pub unlinked: Vec<String>,
+}
+
+impl SweepReport {
+    fn merge(&mut self, other: SweepReport) {
+        self.stale_entries += other.stale_entries;
+        self.unlinked.extend(other.unlinked);
+    }
+}
+
+fn older_than(path: &std::path::Path, grace: Duration) -> bool {
+    let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) else {
+        return false;
+    };
+    SystemTime::now()
+        .duration_since(modified)
+        .is_ok_and(|age| age >= grace)
+}
+
+/// Sweep every topic in the registry.
+pub fn sweep(opts: &JanitorOptions) -> io::Result<SweepReport> {
+    let mut report = SweepReport::default();
+    for topic in registry::topics()? {
+        report.merge(sweep_topic(&topic, opts)?);
+    }
+    Ok(report)
+}
+
+/// Sweep a single topic, named as the registry lists it (with any
+/// `MQ_IPC_PREFIX` already applied).
+pub fn sweep_topic(topic: &str, opts: &JanitorOptions) -> io::Result<SweepReport> {
+    let mut report = SweepReport::default();
+    let endpoints = registry::all_endpoints(topic)?;
+    let (live, dead): (Vec<_>, Vec<_>) = endpoints.into_iter().partition(|ep| ep.is_alive());
+
+    let mut expired = 0;
+    for ep in &dead {
+        if !older_than(&ep.path, opts.grace) {
+            continue;
+        }
+        expired += 1;
+        if !opts.dry_run {
+            match fs::remove_file(&ep.path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    report.stale_entries = expired;
+
+    // Only queues known to have been used, and abandoned by everyone.
+    if live.is_empty() && expired > 0 && expired == dead.len() {
+        if !opts.dry_run {
+            cleanup::unlink_resolved(topic)?;
+            let dir = registry::topic_dir(topic);
+            let _ = fs::remove_file(dir.join(".owner"));
+            let _ = fs::remove_file(dir.join(".layout"));
+            let _ = fs::remove_dir(dir);
+        }
+        report.unlinked.push(topic.to_string());
+    }
+    Ok(report)
+}
+
+/// Background janitor sweeping at a fixed interval.
+pub struct Janitor {
+    running: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl Janitor {
+    /// Sweep now and then every `interval` until dropped.
+    pub fn start(interval: Duration, opts: JanitorOptions) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = Arc::clone(&running);
+
+        let worker = thread::spawn(move || {
+            let mut next = Instant::now();
+            while running_clone.load(Ordering::Relaxed) {
+                if Instant::now() >= next {
+                    if let Err(err) = sweep(&opts) {
+                        eprintln!("janitor: {err}");
+                    }
+                    next += interval;
+                }
+                thread::sleep(Duration::from_millis(100).min(interval));
+            }
+        });
+
+        Janitor {
+            running,
+            worker: Some(worker),
+        }
+    }
+}
+
+impl Drop for Janitor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.worker.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, MqTopic};
+
+    #[test]
+    fn unlinks_queue_of_dead_endpoints_after_grace() {
+        let tmp = TempTopic::new("/mq_ipc_test_janitor_");
+        let topic = MqTopic::new(tmp.name(), 4).unwrap();
+        drop(topic);
+
+        let dir = registry::topic_dir(tmp.name());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("sub-999999999-0"),
+            "pid=999999999\nstart=1\nrole=sub\nnode=ghost\n",
+        )
+        .unwrap();
+
+        let strict = JanitorOptions::default().grace(Duration::from_secs(3600));
+        assert_eq!(
+            sweep_topic(tmp.name(), &strict).unwrap(),
+            SweepReport::default()
+        );
+
+        let now = JanitorOptions::default().grace(Duration::ZERO);
+        let dry = sweep_topic(tmp.name(), &now.dry_run(true)).unwrap();
+        assert_eq!(dry.unlinked, vec![tmp.name().to_string()]);
+        assert!(MqTopic::open_existing(tmp.name()).unwrap().is_some());
+
+        let report = sweep_topic(tmp.name(), &now).unwrap();
+        assert_eq!(report.stale_entries, 1);
+        assert!(MqTopic::open_existing(tmp.name()).unwrap().is_none());
+        assert!(!dir.exists());
+    }
+}
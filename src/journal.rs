@@ -0,0 +1,229 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Write-ahead journal of published messages.
+//!
+//! A journaled topic appends every message to a file *before* handing it
+//! to `mq_send`. After a crash or restart the journal can be replayed so
+//! consumers do not miss commands published while they were down.
+//!
+//! Each record is fixed-size: `timestamp_ns: u64`, `prio: u32`,
+//! `len: u32` (always `size_of::<Msg>()`), then the raw `Msg` — all
+//! little-endian. A torn record at the end of the file is ignored.
+
+use super::Msg;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const RECORD_HEADER: usize = 8 + 4 + 4;
+const MSG_SIZE: usize = std::mem::size_of::<Msg>();
+
+/// One journaled publish.
+#[derive(Copy, Clone, Debug)]
+pub struct JournalEntry {
+    pub timestamp: SystemTime,
+    pub prio: u32,
+    pub msg: Msg,
+}
+
+/// Append-only journal file.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+    sync: bool,
+}
+
+impl Journal {
+    /// Open (or create) a journal for appending.
+    ///
+    /// With `sync` set every append is followed by `fdatasync`, trading
+    /// publish latency for durability across power loss.
+    pub fn open(path: impl AsRef<Path>, sync: bool) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Journal {
+            path,
+            file: Mutex::new(file),
+            sync,
+        })
+    }
+
+    /// Path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one message.
+    pub fn append(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+
+        let mut rec = [0u8; RECORD_HEADER + MSG_SIZE];
+        rec[0..8].copy_from_slice(&ts.to_le_bytes());
+        rec[8..12].copy_from_slice(&prio.to_le_bytes());
+        rec[12..16].copy_from_slice(&(MSG_SIZE as u32).to_le_bytes());
+        rec[RECORD_HEADER..].copy_from_slice(msg_bytes(msg));
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(&rec)?;
+        if self.sync {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Drop every record, e.g. once consumers have caught up.
+    pub fn clear(&self) -> io::Result<()> {
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.set_len(0)
+    }
+
+    /// Read all complete records of this journal.
+    pub fn entries(&self) -> io::Result<Vec<JournalEntry>> {
+        read_entries(&self.path)
+    }
+}
+
+/// Read all complete records from the journal at `path`.
+pub fn read_entries(path: impl AsRef<Path>) -> io::Result<Vec<JournalEntry>> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+
+    let mut out = Vec::new();
+    let mut rest = &data[..];
+    while rest.len() >= RECORD_HEADER {
+        let ts = u64::from_le_bytes(rest[0..8].try_into().unwrap());
+        let prio = u32::from_le_bytes(rest[8..12].try_into().unwrap());
+        let len = u32::from_le_bytes(rest[12..16].try_into().unwrap()) as usize;
+
+        if len != MSG_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "journal record size does not match Msg",
+            ));
+        }
+        if rest.len() < RECORD_HEADER + len {
+            // Torn write at the tail.
+            break;
+        }
+
+        out.push(JournalEntry {
+            timestamp: UNIX_EPOCH + Duration::from_nanos(ts),
+            prio,
+            msg: msg_from_bytes(&rest[RECORD_HEADER..RECORD_HEADER + len]),
+        });
+        rest = &rest[RECORD_HEADER + len..];
+    }
+    Ok(out)
+}
+
+fn msg_bytes(msg: &Msg) -> &[u8] {
+    bytemuck::bytes_of(msg)
+}
+
+fn msg_from_bytes(bytes: &[u8]) -> Msg {
+    debug_assert_eq!(bytes.len(), MSG_SIZE);
+    bytemuck::pod_read_unaligned(bytes)
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, MqTopic, TopicOptions};
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    fn journal_path(tag: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mq_ipc_test_journal_{tag}_{}", std::process::id()))
+    }
+
+    #[test]
+    fn append_and_read_back() {
+        let path = journal_path("rw");
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let journal = Journal::open(&path, false).unwrap();
+            journal.append(&Msg::new(1, b"one"), 3).unwrap();
+            journal.append(&Msg::new(2, b"two"), 0).unwrap();
+        }
+        // Simulate a torn trailing record.
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[1, 2, 3])
+            .unwrap();
+
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].prio, 3);
+        assert_eq!(&entries[0].msg.payload[..3], b"one");
+        assert_eq!(entries[1].msg.hdr.msg_type, 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn journaled_topic_replays() {
+        let tmp = TempTopic::new("/mq_ipc_test_journal_");
+        let path = journal_path("replay");
+        let _ = std::fs::remove_file(&path);
+        let before = SystemTime::now();
+
+        {
+            let opts = TopicOptions::new(4).journal(&path);
+            let topic = MqTopic::with_options(tmp.name(), &opts).unwrap();
+            topic.publish(&Msg::new(7, b"cmd"), 0).unwrap();
+        }
+
+        let received: Arc<Mutex<Vec<u16>>> = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = Arc::clone(&received);
+        let opts = TopicOptions::new(4).journal(&path);
+        let topic = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        topic.subscribe(move |m| received_clone.lock().unwrap().push(m.hdr.msg_type));
+
+        assert_eq!(topic.replay_journal(before).unwrap(), 1);
+        for _ in 0..50 {
+            if received.lock().unwrap().contains(&7) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(received.lock().unwrap().contains(&7));
+        // Replayed messages are not journaled a second time.
+        assert_eq!(read_entries(&path).unwrap().len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
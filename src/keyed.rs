@@ -0,0 +1,520 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Topics carrying many instances told apart by a key.
+//!
+//! One `/joints/state` topic can carry the state of every joint, or one
+//! `/fleet/pose` the pose of every robot, when each message names the
+//! instance it is about. A [`KeyedTopic`] stamps that key into the
+//! [extended header](crate::ext) and, on the receiving side, keeps the
+//! latest value per key:
+//!
+//! ```no_run
+//! # use mq_ipc::keyed::KeyedTopic;
+//! # use std::time::Duration;
+//! let joints = KeyedTopic::<f32>::new("/joints/angle", 16)?;
+//! joints.publish(3, &0.25, 1, 0)?;
+//!
+//! joints.subscribe(|joint, angle| println!("joint {joint}: {angle}"));
+//! joints.on_deadline(Duration::from_millis(50), |joint| eprintln!("joint {joint} is stale"));
+//! let _j3 = joints.latest(3);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! A handle only takes messages out of the queue once it
+//! [`track`](KeyedTopic::track)s the topic, which [`subscribe`] and
+//! [`on_deadline`] do for it, so publish-only handles stay out of the way
+//! of subscribers elsewhere.
+//!
+//! Keys are latched per handle: a new [`subscribe`] callback first gets
+//! the latest value of every key seen so far. With
+//! [`latch`](KeyedTopic::latch), a publisher also republishes its latest
+//! value per key whenever another subscriber joins, so late joiners in
+//! other processes see every instance without waiting for its next
+//! update.
+//!
+//! [`subscribe_pool`](KeyedTopic::subscribe_pool) spreads the keys over
+//! several threads, always routing a key to the same one, so busy
+//! topics are handled in parallel without reordering any one instance.
+//!
+//! [`subscribe`]: KeyedTopic::subscribe
+//! [`on_deadline`]: KeyedTopic::on_deadline
+
+use super::{
+    ext::{EXT_INSTANCE, EXT_PAYLOAD_SIZE},
+    introspect, MqTopic, Msg, Topic, TopicOptions,
+};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    os::raw::c_long,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Longest pause between two deadline checks.
+const DEADLINE_POLL_MAX: Duration = Duration::from_millis(100);
+
+/// Values a pool thread may have queued before the topic's worker waits
+/// for it.
+pub const POOL_QUEUE_DEPTH: usize = 64;
+
+/// How often an idle pool thread checks whether its topic is gone.
+const POOL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Pool thread, out of `n`, that [`KeyedTopic::subscribe_pool`] hands
+/// `key` to.
+pub fn shard(key: u64, n: usize) -> usize {
+    // Fibonacci hashing, so sequential keys spread across the pool.
+    let mixed = key.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    ((mixed >> 32) % n.max(1) as u64) as usize
+}
+
+/// The instance key stamped into `msg`, if any.
+pub fn instance_of(msg: &Msg) -> Option<u64> {
+    msg.ext()
+        .filter(|ext| ext.has(EXT_INSTANCE))
+        .map(|ext| ext.instance)
+}
+
+/// Latest value of one key, as seen by this handle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sample<T> {
+    pub key: u64,
+    pub value: T,
+    /// Time since the value was received.
+    pub age: Duration,
+    /// Values received for this key so far.
+    pub count: u64,
+}
+
+type KeyedCallback<T> = Arc<dyn Fn(u64, T) + Send + Sync + 'static>;
+
+struct Instance<T> {
+    value: T,
+    received: Instant,
+    count: u64,
+}
+
+struct Cache<T> {
+    instances: BTreeMap<u64, Instance<T>>,
+    callbacks: Vec<KeyedCallback<T>>,
+}
+
+impl<T: Copy> Cache<T> {
+    fn sample(&self, key: u64, now: Instant) -> Option<Sample<T>> {
+        self.instances.get(&key).map(|inst| Sample {
+            key,
+            value: inst.value,
+            age: now.saturating_duration_since(inst.received),
+            count: inst.count,
+        })
+    }
+}
+
+/// Typed topic whose messages each belong to one keyed instance.
+pub struct KeyedTopic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    topic: Topic<T>,
+    cache: Arc<Mutex<Cache<T>>>,
+    tracking: OnceLock<()>,
+    /// Last message published per key, for [`latch`](Self::latch).
+    retained: Arc<Mutex<HashMap<u64, (Msg, u32)>>>,
+    latched: AtomicBool,
+    helpers: Mutex<Vec<(Arc<AtomicBool>, thread::JoinHandle<()>)>>,
+    introspect_id: u64,
+}
+
+impl<T> KeyedTopic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    pub fn new(name: &str, maxmsg: c_long) -> io::Result<Self> {
+        Self::with_options(name, &TopicOptions::new(maxmsg))
+    }
+
+    pub fn with_options(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        let topic = Topic::with_options(name, opts)?;
+        let cache = Arc::new(Mutex::new(Cache {
+            instances: BTreeMap::new(),
+            callbacks: Vec::new(),
+        }));
+        let introspect_id = {
+            let cache = Arc::clone(&cache);
+            introspect::register_latest(topic.raw().name(), move || {
+                let now = Instant::now();
+                let cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+                cache
+                    .instances
+                    .iter()
+                    .map(|(key, inst)| introspect::LatestValue {
+                        key: Some(*key),
+                        age: now.saturating_duration_since(inst.received),
+                        data: bytemuck::bytes_of(&inst.value).to_vec(),
+                    })
+                    .collect()
+            })
+        };
+        Ok(KeyedTopic {
+            topic,
+            cache,
+            tracking: OnceLock::new(),
+            retained: Arc::new(Mutex::new(HashMap::new())),
+            latched: AtomicBool::new(false),
+            helpers: Mutex::new(Vec::new()),
+            introspect_id,
+        })
+    }
+
+    /// Publish `value` as the new state of instance `key`. The value must
+    /// fit next to the extended header, i.e. in [`EXT_PAYLOAD_SIZE`]
+    /// bytes unless it is sent by reference.
+    pub fn publish(&self, key: u64, value: &T, msg_type: u16, prio: u32) -> io::Result<()> {
+        let msg = self.topic.encode(value, msg_type)?;
+        let mut ext = msg.ext().unwrap_or_default();
+        ext.present |= EXT_INSTANCE;
+        ext.instance = key;
+        let keyed = msg.attach_ext(&ext).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("keyed payload exceeds {EXT_PAYLOAD_SIZE} bytes"),
+            )
+        })?;
+
+        self.topic.raw().publish(&keyed, prio)?;
+        self.retained
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, (keyed, prio));
+        Ok(())
+    }
+
+    /// Start receiving into the per-key cache read by
+    /// [`latest`](Self::latest) and friends. Idempotent.
+    ///
+    /// Messages without a key count as key 0.
+    pub fn track(&self) {
+        self.tracking.get_or_init(|| {
+            let cache = Arc::clone(&self.cache);
+            self.topic
+                .raw()
+                .subscribe(self.topic.decoder_msg(move |msg, value: T| {
+                    let key = instance_of(msg).unwrap_or(0);
+                    let callbacks = {
+                        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+                        let count = cache.instances.get(&key).map_or(0, |i| i.count);
+                        cache.instances.insert(
+                            key,
+                            Instance {
+                                value,
+                                received: Instant::now(),
+                                count: count + 1,
+                            },
+                        );
+                        cache.callbacks.clone()
+                    };
+                    for cb in &callbacks {
+                        cb(key, value);
+                    }
+                }));
+        });
+    }
+
+    /// Call `f(key, value)` for every value received. Before this returns,
+    /// `f` is called once with the latest value of every key already
+    /// known, in key order; it must not call back into this handle
+    /// during that replay.
+    pub fn subscribe<F>(&self, f: F)
+    where
+        F: Fn(u64, T) + Send + Sync + 'static,
+    {
+        self.track();
+        let f: KeyedCallback<T> = Arc::new(f);
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, inst) in &cache.instances {
+            f(*key, inst.value);
+        }
+        cache.callbacks.push(f);
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but `f` runs on a pool of
+    /// `threads` threads, each key always on the same one (see [`shard`]).
+    /// Values of one key keep their order while different keys are
+    /// handled in parallel. A full pool queue holds up the topic's worker.
+    pub fn subscribe_pool<F>(&self, threads: usize, f: F)
+    where
+        F: Fn(u64, T) + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        let mut senders = Vec::new();
+        let mut helpers = self.helpers.lock().unwrap_or_else(|e| e.into_inner());
+        for _ in 0..threads.max(1) {
+            let (tx, rx) = mpsc::sync_channel::<(u64, T)>(POOL_QUEUE_DEPTH);
+            let f = Arc::clone(&f);
+            let running = Arc::new(AtomicBool::new(true));
+            let running_clone = Arc::clone(&running);
+            let handle = thread::spawn(move || {
+                while running_clone.load(Ordering::Relaxed) {
+                    match rx.recv_timeout(POOL_POLL_INTERVAL) {
+                        Ok((key, value)) => f(key, value),
+                        Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            });
+            senders.push(tx);
+            helpers.push((running, handle));
+        }
+        drop(helpers);
+
+        self.subscribe(move |key, value| {
+            let _ = senders[shard(key, senders.len())].send((key, value));
+        });
+    }
+
+    /// Latest value received for `key`.
+    pub fn latest(&self, key: u64) -> Option<T> {
+        self.sample(key).map(|s| s.value)
+    }
+
+    /// Latest value received for `key`, with its age and count.
+    pub fn sample(&self, key: u64) -> Option<Sample<T>> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.sample(key, Instant::now())
+    }
+
+    /// Every key received so far, in ascending order.
+    pub fn keys(&self) -> Vec<u64> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.instances.keys().copied().collect()
+    }
+
+    /// Known keys whose latest value is older than `deadline`.
+    pub fn overdue(&self, deadline: Duration) -> Vec<u64> {
+        overdue(&self.cache, deadline)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Call `f(key)` from a helper thread whenever a known key goes longer
+    /// than `deadline` without a new value; once per miss, until the key
+    /// is updated again. Starts [`track`](Self::track)ing.
+    pub fn on_deadline<F>(&self, deadline: Duration, f: F)
+    where
+        F: Fn(u64) + Send + 'static,
+    {
+        self.track();
+        let cache = Arc::clone(&self.cache);
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = Arc::clone(&running);
+        let poll = (deadline / 4).clamp(Duration::from_millis(1), DEADLINE_POLL_MAX);
+
+        let handle = thread::spawn(move || {
+            // Keys reported, with their count at the time.
+            let mut reported: HashMap<u64, u64> = HashMap::new();
+            while running_clone.load(Ordering::Relaxed) {
+                let late = overdue(&cache, deadline);
+                reported.retain(|key, count| late.contains(&(*key, *count)));
+                for (key, count) in late {
+                    if reported.insert(key, count).is_none() {
+                        f(key);
+                    }
+                }
+                thread::sleep(poll);
+            }
+        });
+
+        self.helpers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((running, handle));
+    }
+
+    /// Republish the latest value of every key this handle published
+    /// whenever the number of matched subscribers grows.
+    pub fn latch(&self) -> io::Result<()> {
+        if self.latched.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let out = MqTopic::open_existing_resolved(self.topic.raw().name())?.ok_or_else(|| {
+            self.latched.store(false, Ordering::Relaxed);
+            io::Error::from(io::ErrorKind::NotFound)
+        })?;
+        let retained = Arc::clone(&self.retained);
+        let last = AtomicUsize::new(usize::MAX);
+
+        self.topic.raw().on_matched(move |count| {
+            let prev = last.swap(count, Ordering::Relaxed);
+            if prev == usize::MAX || count <= prev {
+                return;
+            }
+            let retained = retained.lock().unwrap_or_else(|e| e.into_inner());
+            for (msg, prio) in retained.values() {
+                if let Err(err) = out.publish(msg, *prio) {
+                    eprintln!("latch republish on {} failed: {err}", out.name());
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// The underlying typed topic.
+    pub fn topic(&self) -> &Topic<T> {
+        &self.topic
+    }
+}
+
+impl<T> Drop for KeyedTopic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        introspect::unregister(self.introspect_id);
+        let helpers = std::mem::take(&mut *self.helpers.lock().unwrap_or_else(|e| e.into_inner()));
+        for (running, handle) in helpers {
+            running.store(false, Ordering::Relaxed);
+            let _ = handle.join();
+        }
+    }
+}
+
+/// `(key, count)` of the keys whose latest value is older than `deadline`.
+fn overdue<T: Copy>(cache: &Mutex<Cache<T>>, deadline: Duration) -> Vec<(u64, u64)> {
+    let now = Instant::now();
+    let cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .instances
+        .iter()
+        .filter(|(_, inst)| now.saturating_duration_since(inst.received) > deadline)
+        .map(|(key, inst)| (*key, inst.count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    fn wait_until(f: impl Fn() -> bool) -> bool {
+        for _ in 0..200 {
+            if f() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn caches_latest_per_key_and_replays_it() {
+        let tmp = TempTopic::new("/mq_ipc_test_keyed_");
+        let publisher = KeyedTopic::<u32>::new(tmp.name(), 8).unwrap();
+        let reader = KeyedTopic::<u32>::new(tmp.name(), 8).unwrap();
+        reader.track();
+
+        for (key, value) in [(1, 10), (2, 20), (1, 11)] {
+            publisher.publish(key, &value, 1, 0).unwrap();
+        }
+        assert!(wait_until(|| reader.latest(1) == Some(11)));
+        assert_eq!(reader.keys(), vec![1, 2]);
+        assert_eq!(reader.sample(1).unwrap().count, 2);
+        assert_eq!(reader.latest(3), None);
+
+        let (tx, rx) = mpsc::channel();
+        reader.on_deadline(Duration::from_millis(30), move |key| tx.send(key).unwrap());
+
+        let replayed = Arc::new(Mutex::new(Vec::new()));
+        let replayed_clone = Arc::clone(&replayed);
+        reader.subscribe(move |key, value| replayed_clone.lock().unwrap().push((key, value)));
+        assert_eq!(*replayed.lock().unwrap(), vec![(1, 11), (2, 20)]);
+
+        let mut late: Vec<u64> = (0..2)
+            .map(|_| rx.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        late.sort();
+        assert_eq!(late, vec![1, 2]);
+        assert_eq!(reader.overdue(Duration::from_millis(30)), vec![1, 2]);
+        assert!(rx.recv_timeout(Duration::from_millis(60)).is_err());
+    }
+
+    #[test]
+    fn latched_values_reach_late_subscribers() {
+        let tmp = TempTopic::new("/mq_ipc_test_keyed_latch_");
+        let publisher = KeyedTopic::<u32>::new(tmp.name(), 8).unwrap();
+        publisher.latch().unwrap();
+        publisher.publish(5, &50, 1, 0).unwrap();
+
+        {
+            let first = KeyedTopic::<u32>::new(tmp.name(), 8).unwrap();
+            first.track();
+            assert!(wait_until(|| first.latest(5) == Some(50)));
+            // Also drain the republish triggered by this subscriber.
+            thread::sleep(Duration::from_millis(250));
+        }
+        // Let the publisher see the subscriber leave before one joins.
+        thread::sleep(Duration::from_millis(250));
+
+        let second = KeyedTopic::<u32>::new(tmp.name(), 8).unwrap();
+        second.track();
+        assert!(wait_until(|| second.latest(5) == Some(50)));
+    }
+
+    #[test]
+    fn pool_keeps_each_key_on_one_thread() {
+        let tmp = TempTopic::new("/mq_ipc_test_keyed_pool_");
+        let publisher = KeyedTopic::<u32>::new(tmp.name(), 8).unwrap();
+        let reader = KeyedTopic::<u32>::new(tmp.name(), 8).unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        reader.subscribe_pool(3, move |key, value| {
+            let me = thread::current().id();
+            seen_clone.lock().unwrap().push((key, value, me));
+        });
+
+        for value in 0..5 {
+            for key in 0..6 {
+                publisher.publish(key, &value, 1, 0).unwrap();
+            }
+        }
+        assert!(wait_until(|| seen.lock().unwrap().len() == 30));
+
+        let seen = seen.lock().unwrap();
+        for key in 0..6 {
+            let mine: Vec<_> = seen.iter().filter(|(k, _, _)| *k == key).collect();
+            assert!(mine.iter().all(|(_, _, t)| *t == mine[0].2));
+            let values: Vec<u32> = mine.iter().map(|(_, v, _)| *v).collect();
+            assert_eq!(values, vec![0, 1, 2, 3, 4]);
+        }
+        let shards: std::collections::HashSet<_> = (0..6).map(|k| shard(k, 3)).collect();
+        assert!(shards.len() > 1);
+    }
+}
@@ -0,0 +1,471 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Payload-by-reference for buffers that do not fit in a message.
+//!
+//! An mqueue message holds at most
+//! [`MSG_PAYLOAD_SIZE`](crate::MSG_PAYLOAD_SIZE) bytes and cannot carry
+//! file descriptors. For topics created with
+//! [`TopicOptions::large`](crate::TopicOptions::large), the publisher
+//! copies each value into a sealed `memfd` segment and only a small
+//! [`LargeRef`] goes through the queue. The subscriber that receives it
+//! asks the publishing process for the segment over a Unix socket
+//! (abstract name `mq_ipc.large.<pid>`), gets the fd back via
+//! `SCM_RIGHTS` and reads the value from it.
+//!
+//! The publisher keeps the last [`RETAINED_SEGMENTS`] unfetched
+//! segments. A reference whose segment was evicted, or whose publisher
+//! exited, fails to resolve; typed subscribers treat that like a decode
+//! error. A worker fetches each segment once and shares the bytes with
+//! all of its callbacks. The [`journal`](crate::journal) records
+//! references, not data.
+
+use super::Msg;
+use bytemuck::{Pod, Zeroable};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, Read, Write},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        linux::net::SocketAddrExt,
+        unix::{
+            fs::FileExt,
+            net::{SocketAddr, UnixListener, UnixStream},
+        },
+    },
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+
+/// `hdr.flags` bit: the data is a [`LargeRef`], not the value itself.
+pub const FLAG_LARGE: u16 = 0x0002;
+
+/// Unfetched segments a process keeps before evicting the oldest.
+pub const RETAINED_SEGMENTS: usize = 64;
+
+/// How long the segment server waits on one client, so a peer that
+/// connects and goes quiet cannot hold up everyone else's fetches.
+const SERVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where a large value lives: segment `segment` held by process `pid`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct LargeRef {
+    pub pid: u32,
+    pub reserved: u32,
+    pub segment: u64,
+    /// Size of the value in bytes.
+    pub len: u64,
+}
+
+impl Msg {
+    /// Create a message that refers to a stored segment.
+    pub fn by_ref(msg_type: u16, r: &LargeRef) -> Self {
+        let mut msg = Msg::new(msg_type, bytemuck::bytes_of(r));
+        msg.hdr.flags |= FLAG_LARGE;
+        msg
+    }
+
+    /// The segment this message refers to, if it is a by-reference one.
+    pub fn large_ref(&self) -> Option<LargeRef> {
+        let data = self.data();
+        if self.hdr.flags & FLAG_LARGE == 0 || data.len() < std::mem::size_of::<LargeRef>() {
+            return None;
+        }
+        Some(bytemuck::pod_read_unaligned(
+            &data[..std::mem::size_of::<LargeRef>()],
+        ))
+    }
+}
+
+#[derive(Default)]
+struct Segments {
+    next: u64,
+    files: HashMap<u64, File>,
+    order: VecDeque<u64>,
+}
+
+static SEGMENTS: OnceLock<Mutex<Segments>> = OnceLock::new();
+static SERVER: Mutex<Option<u32>> = Mutex::new(None);
+
+fn socket_addr(pid: u32) -> io::Result<SocketAddr> {
+    SocketAddr::from_abstract_name(format!("mq_ipc.large.{pid}"))
+}
+
+fn segments() -> &'static Mutex<Segments> {
+    SEGMENTS.get_or_init(Mutex::default)
+}
+
+/// Start this process' segment server unless it is already running.
+fn ensure_server() -> io::Result<()> {
+    let pid = std::process::id();
+    let mut running = SERVER.lock().unwrap_or_else(|e| e.into_inner());
+    // A forked child inherits the flag but not the thread.
+    if *running == Some(pid) {
+        return Ok(());
+    }
+
+    let listener = UnixListener::bind_addr(&socket_addr(pid)?)?;
+    thread::spawn(move || serve(listener));
+    *running = Some(pid);
+    Ok(())
+}
+
+fn serve(listener: UnixListener) {
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        if stream.set_read_timeout(Some(SERVE_TIMEOUT)).is_err()
+            || stream.set_write_timeout(Some(SERVE_TIMEOUT)).is_err()
+        {
+            continue;
+        }
+        let mut id = [0u8; 8];
+        if stream.read_exact(&mut id).is_err() {
+            continue;
+        }
+
+        let file = {
+            let mut segs = segments().lock().unwrap_or_else(|e| e.into_inner());
+            let id = u64::from_le_bytes(id);
+            segs.order.retain(|s| *s != id);
+            segs.files.remove(&id)
+        };
+        let _ = send_fd(&stream, file.as_ref().map(|f| f.as_raw_fd()));
+    }
+}
+
+/// Copy `data` into a new sealed segment and return its reference.
+pub fn store(data: &[u8]) -> io::Result<LargeRef> {
+    ensure_server()?;
+
+    let fd = unsafe {
+        libc::memfd_create(
+            c"mq_ipc.large".as_ptr(),
+            libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(data)?;
+    let seals = libc::F_SEAL_WRITE | libc::F_SEAL_GROW | libc::F_SEAL_SHRINK | libc::F_SEAL_SEAL;
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut segs = segments().lock().unwrap_or_else(|e| e.into_inner());
+    let segment = segs.next;
+    segs.next += 1;
+    segs.files.insert(segment, file);
+    segs.order.push_back(segment);
+    while segs.order.len() > RETAINED_SEGMENTS {
+        if let Some(old) = segs.order.pop_front() {
+            segs.files.remove(&old);
+        }
+    }
+
+    Ok(LargeRef {
+        pid: std::process::id(),
+        reserved: 0,
+        segment,
+        len: data.len() as u64,
+    })
+}
+
+/// Take the segment behind `r` from its publisher.
+///
+/// Each segment can be fetched once; `NotFound` means it was already
+/// taken or has been evicted.
+pub fn fetch(r: &LargeRef) -> io::Result<File> {
+    let mut stream = UnixStream::connect_addr(&socket_addr(r.pid)?)?;
+    stream.write_all(&r.segment.to_le_bytes())?;
+    match recv_fd(&stream)? {
+        Some(fd) => Ok(File::from(fd)),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("segment {} of pid {} is gone", r.segment, r.pid),
+        )),
+    }
+}
+
+type Fetched = Result<Arc<[u8]>, io::ErrorKind>;
+
+thread_local! {
+    // Segment of the message a worker is dispatching on this thread.
+    static DISPATCHED: RefCell<Option<(LargeRef, Fetched)>> = const { RefCell::new(None) };
+}
+
+/// Fetches the segment behind a message once, before a worker fans it
+/// out; [`load`] on the same thread reads from it instead of asking the
+/// publisher again.
+#[cfg(feature = "callbacks")]
+pub(crate) struct DispatchGuard {
+    active: bool,
+}
+
+#[cfg(feature = "callbacks")]
+impl DispatchGuard {
+    pub(crate) fn enter(msg: &Msg) -> Self {
+        let Some(r) = msg.large_ref() else {
+            return DispatchGuard { active: false };
+        };
+        let fetched = fetch_bytes(&r).map_err(|err| err.kind());
+        DISPATCHED.with(|d| *d.borrow_mut() = Some((r, fetched)));
+        DispatchGuard { active: true }
+    }
+}
+
+#[cfg(feature = "callbacks")]
+impl Drop for DispatchGuard {
+    fn drop(&mut self) {
+        if self.active {
+            DISPATCHED.with(|d| d.borrow_mut().take());
+        }
+    }
+}
+
+fn fetch_bytes(r: &LargeRef) -> io::Result<Arc<[u8]>> {
+    let file = fetch(r)?;
+    // `len` comes from the queue; only trust it as far as the segment goes.
+    let size = file.metadata()?.len();
+    if r.len > size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "reference claims {} bytes, segment {} of pid {} holds {size}",
+                r.len, r.segment, r.pid
+            ),
+        ));
+    }
+    let mut bytes = vec![0u8; r.len as usize];
+    // The fd shares its file offset with the publisher's, so read at 0.
+    file.read_exact_at(&mut bytes, 0)?;
+    Ok(bytes.into())
+}
+
+/// Fetch `r` and read it as `T`.
+///
+/// With `strict`, a segment whose size differs from `size_of::<T>()` is
+/// rejected with `InvalidData`; otherwise it is zero-padded or truncated.
+pub fn load<T: Pod>(r: &LargeRef, strict: bool) -> io::Result<T> {
+    let size = std::mem::size_of::<T>();
+    if strict && r.len != size as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("segment holds {} bytes, expected {size}", r.len),
+        ));
+    }
+
+    let dispatched = DISPATCHED.with(|d| match &*d.borrow() {
+        Some((cur, fetched)) if cur == r => Some(fetched.clone()),
+        _ => None,
+    });
+    let bytes = match dispatched {
+        Some(fetched) => fetched.map_err(|kind| {
+            io::Error::new(
+                kind,
+                format!(
+                    "segment {} of pid {} could not be fetched",
+                    r.segment, r.pid
+                ),
+            )
+        })?,
+        None => fetch_bytes(r)?,
+    };
+    let mut value = T::zeroed();
+    let n = bytes.len().min(size);
+    bytemuck::bytes_of_mut(&mut value)[..n].copy_from_slice(&bytes[..n]);
+    Ok(value)
+}
+
+// Control buffer big enough for one fd, aligned for `cmsghdr`.
+type CmsgBuf = [u64; 4];
+
+fn send_fd(stream: &UnixStream, fd: Option<RawFd>) -> io::Result<()> {
+    let mut status = [u8::from(fd.is_none())];
+    let mut iov = libc::iovec {
+        iov_base: status.as_mut_ptr().cast(),
+        iov_len: status.len(),
+    };
+    let mut cmsg_buf: CmsgBuf = [0; 4];
+    let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+
+    if let Some(fd) = fd {
+        let fd_len = std::mem::size_of::<RawFd>() as u32;
+        unsafe {
+            hdr.msg_control = cmsg_buf.as_mut_ptr().cast();
+            hdr.msg_controllen = libc::CMSG_SPACE(fd_len) as _;
+            let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(fd_len) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>(), fd);
+        }
+    }
+
+    if unsafe { libc::sendmsg(stream.as_raw_fd(), &hdr, libc::MSG_NOSIGNAL) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn recv_fd(stream: &UnixStream) -> io::Result<Option<OwnedFd>> {
+    let mut status = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: status.as_mut_ptr().cast(),
+        iov_len: status.len(),
+    };
+    let mut cmsg_buf: CmsgBuf = [0; 4];
+    let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+    hdr.msg_control = cmsg_buf.as_mut_ptr().cast();
+    hdr.msg_controllen = std::mem::size_of::<CmsgBuf>() as _;
+
+    let n = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut hdr, libc::MSG_CMSG_CLOEXEC) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if n == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let mut received = None;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+        if !cmsg.is_null()
+            && (*cmsg).cmsg_level == libc::SOL_SOCKET
+            && (*cmsg).cmsg_type == libc::SCM_RIGHTS
+        {
+            let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<RawFd>());
+            received = Some(OwnedFd::from_raw_fd(fd));
+        }
+    }
+    Ok(if status[0] == 0 { received } else { None })
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, Topic, TopicOptions};
+    use std::{sync::mpsc, time::Duration};
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable)]
+    struct Frame {
+        seq: u32,
+        pixels: [u8; 4092],
+    }
+
+    #[test]
+    fn segments_are_fetched_once() {
+        let r = store(b"0123456789").unwrap();
+        assert_eq!(r.len, 10);
+
+        let msg = Msg::by_ref(7, &r);
+        assert_eq!(msg.large_ref(), Some(r));
+        assert_eq!(Msg::new(7, bytemuck::bytes_of(&r)).large_ref(), None);
+
+        let mut buf = [0u8; 10];
+        fetch(&r).unwrap().read_exact_at(&mut buf, 0).unwrap();
+        assert_eq!(&buf, b"0123456789");
+        assert_eq!(fetch(&r).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn oversized_references_are_rejected() {
+        let mut r = store(&[7u8; 16]).unwrap();
+        r.len = u64::MAX;
+        let err = load::<[u8; 16]>(&r, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn a_silent_client_does_not_stall_the_server() {
+        let r = store(b"abc").unwrap();
+        let _idle = UnixStream::connect_addr(&socket_addr(r.pid).unwrap()).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || tx.send(load::<[u8; 3]>(&r, true).map_err(|e| e.kind())));
+        let got = rx.recv_timeout(SERVE_TIMEOUT * 3).unwrap();
+        assert_eq!(got, Ok(*b"abc"));
+    }
+
+    #[test]
+    fn large_topic_round_trips_values() {
+        let tmp = TempTopic::new("/mq_ipc_test_large_");
+        let opts = TopicOptions::new(4).large(true).strict(true);
+        let topic = Topic::<Frame>::with_options(tmp.name(), &opts).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        topic.subscribe(move |frame| {
+            let sum: u32 = frame.pixels.iter().map(|p| *p as u32).sum();
+            tx.lock().unwrap().send((frame.seq, sum)).unwrap();
+        });
+
+        let frame = Frame {
+            seq: 42,
+            pixels: [3; 4092],
+        };
+        topic.publish(&frame, 1, 0).unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(1)).unwrap(),
+            (42, 3 * 4092)
+        );
+    }
+
+    #[test]
+    fn every_subscriber_sees_a_large_value() {
+        let tmp = TempTopic::new("/mq_ipc_test_large_fanout_");
+        let opts = TopicOptions::new(4).large(true).dead_letter(true);
+        let _tmp_dlq = TempTopic::with_name(&crate::dlq::dlq_name(tmp.name()));
+        let topic = Topic::<Frame>::with_options(tmp.name(), &opts).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        for id in ['a', 'b'] {
+            let tx = Mutex::new(tx.clone());
+            topic.subscribe(move |frame: Frame| tx.lock().unwrap().send((id, frame.seq)).unwrap());
+        }
+
+        let frame = Frame {
+            seq: 7,
+            pixels: [0; 4092],
+        };
+        topic.publish(&frame, 1, 0).unwrap();
+        let mut got = [
+            rx.recv_timeout(Duration::from_secs(1)).unwrap(),
+            rx.recv_timeout(Duration::from_secs(1)).unwrap(),
+        ];
+        got.sort();
+        assert_eq!(got, [('a', 7), ('b', 7)]);
+    }
+}
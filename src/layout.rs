@@ -0,0 +1,712 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Field-level layout descriptions of payload types.
+//!
+//! Typed topics carry raw `#[repr(C)]` structs, so a C peer, a Foxglove
+//! panel or a documentation generator needs the field names, offsets and
+//! primitive types to make sense of the bytes. Payload types opt in with
+//! [`describe!`](crate::describe!), which derives a [`Describe`] impl from
+//! the field list:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use bytemuck::{Pod, Zeroable};
+//!
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! pub struct MotorState { rpm: f32, torque: f32, pos: [i16; 2] }
+//!
+//! mq_ipc::describe!(MotorState { rpm, torque, pos });
+//!
+//! let layout = mq_ipc::layout::Layout::of::<MotorState>();
+//! println!("{}", layout.to_json_schema());
+//! mq_ipc::layout::register::<MotorState>("/motor_state")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`register`] stores the layout next to the topic's [`registry`]
+//! entries, where `mq-ipc schema` (and [`registered`]) pick it up from any
+//! process. Nested described structs are flattened into dotted field
+//! names (`imu.x`); the JSON Schema export nests them again.
+
+use super::registry;
+use bytemuck::Pod;
+use std::{fmt, fmt::Write as _, fs, io, path::Path};
+
+/// File holding the layout inside a topic's registry directory.
+const LAYOUT_FILE: &str = ".layout";
+
+/// Primitive type of a described field.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Scalar {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    const ALL: [Scalar; 10] = [
+        Scalar::U8,
+        Scalar::I8,
+        Scalar::U16,
+        Scalar::I16,
+        Scalar::U32,
+        Scalar::I32,
+        Scalar::U64,
+        Scalar::I64,
+        Scalar::F32,
+        Scalar::F64,
+    ];
+
+    /// Rust spelling of the type, e.g. `"u16"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Scalar::U8 => "u8",
+            Scalar::I8 => "i8",
+            Scalar::U16 => "u16",
+            Scalar::I16 => "i16",
+            Scalar::U32 => "u32",
+            Scalar::I32 => "i32",
+            Scalar::U64 => "u64",
+            Scalar::I64 => "i64",
+            Scalar::F32 => "f32",
+            Scalar::F64 => "f64",
+        }
+    }
+
+    /// `<stdint.h>` spelling of the type, e.g. `"uint16_t"`.
+    pub fn c_name(self) -> &'static str {
+        match self {
+            Scalar::U8 => "uint8_t",
+            Scalar::I8 => "int8_t",
+            Scalar::U16 => "uint16_t",
+            Scalar::I16 => "int16_t",
+            Scalar::U32 => "uint32_t",
+            Scalar::I32 => "int32_t",
+            Scalar::U64 => "uint64_t",
+            Scalar::I64 => "int64_t",
+            Scalar::F32 => "float",
+            Scalar::F64 => "double",
+        }
+    }
+
+    /// Size in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            Scalar::U8 | Scalar::I8 => 1,
+            Scalar::U16 | Scalar::I16 => 2,
+            Scalar::U32 | Scalar::I32 | Scalar::F32 => 4,
+            Scalar::U64 | Scalar::I64 | Scalar::F64 => 8,
+        }
+    }
+
+    /// Inverse of [`Scalar::name`].
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == s)
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Scalar::F32 | Scalar::F64)
+    }
+}
+
+/// One primitive (or fixed-size array of primitives) inside a payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// Dotted path from the payload root; empty for a bare primitive payload.
+    pub name: String,
+    /// Byte offset from the start of the payload.
+    pub offset: usize,
+    pub ty: Scalar,
+    /// Element count for `[T; N]` fields, `None` for a single value.
+    pub len: Option<usize>,
+}
+
+impl Field {
+    /// Size in bytes, including every array element.
+    pub fn size(&self) -> usize {
+        self.ty.size() * self.len.unwrap_or(1)
+    }
+}
+
+/// Payload types whose fields can be described.
+///
+/// Implemented for the primitive integer and float types and arrays of
+/// them; structs get it from [`describe!`](crate::describe!).
+pub trait Describe: Pod {
+    /// Short type name used as the schema title.
+    fn type_name() -> &'static str;
+
+    /// Append the fields of a `Self` stored at `offset`, naming them
+    /// below `path`.
+    fn describe(path: &str, offset: usize, out: &mut Vec<Field>);
+}
+
+macro_rules! describe_scalar {
+    ($($ty:ty => $scalar:ident),* $(,)?) => {$(
+        impl Describe for $ty {
+            fn type_name() -> &'static str {
+                stringify!($ty)
+            }
+
+            fn describe(path: &str, offset: usize, out: &mut Vec<Field>) {
+                out.push(Field {
+                    name: path.to_string(),
+                    offset,
+                    ty: Scalar::$scalar,
+                    len: None,
+                });
+            }
+        }
+
+        impl<const N: usize> Describe for [$ty; N] {
+            fn type_name() -> &'static str {
+                std::any::type_name::<Self>()
+            }
+
+            fn describe(path: &str, offset: usize, out: &mut Vec<Field>) {
+                out.push(Field {
+                    name: path.to_string(),
+                    offset,
+                    ty: Scalar::$scalar,
+                    len: Some(N),
+                });
+            }
+        }
+    )*};
+}
+
+describe_scalar! {
+    u8 => U8, i8 => I8, u16 => U16, i16 => I16, u32 => U32,
+    i32 => I32, u64 => U64, i64 => I64, f32 => F32, f64 => F64,
+}
+
+/// Used by [`describe!`](crate::describe!): describe the field that
+/// `_get` projects to, letting the compiler infer its type.
+#[doc(hidden)]
+pub fn describe_field<S, T: Describe>(
+    path: &str,
+    name: &str,
+    offset: usize,
+    _get: fn(&S) -> &T,
+    out: &mut Vec<Field>,
+) {
+    let path = if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    };
+    T::describe(&path, offset, out);
+}
+
+/// Implement [`Describe`](crate::layout::Describe) for a `#[repr(C)]`
+/// struct by listing its fields.
+///
+/// Field types come from the struct definition and offsets from
+/// [`core::mem::offset_of!`]; every field type must itself implement
+/// `Describe`, so unlisted padding is the only thing left out.
+///
+/// ```
+/// use bytemuck::{Pod, Zeroable};
+///
+/// #[repr(C)]
+/// #[derive(Copy, Clone, Pod, Zeroable)]
+/// struct Imu { x: f32, y: f32 }
+///
+/// #[repr(C)]
+/// #[derive(Copy, Clone, Pod, Zeroable)]
+/// struct Sample { stamp: u64, imu: Imu }
+///
+/// mq_ipc::describe!(Imu { x, y });
+/// mq_ipc::describe!(Sample { stamp, imu });
+///
+/// let layout = mq_ipc::layout::Layout::of::<Sample>();
+/// assert_eq!(layout.fields[2].name, "imu.y");
+/// assert_eq!(layout.fields[2].offset, 12);
+/// ```
+#[macro_export]
+macro_rules! describe {
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::layout::Describe for $ty {
+            fn type_name() -> &'static str {
+                stringify!($ty)
+            }
+
+            fn describe(
+                path: &str,
+                offset: usize,
+                out: &mut ::std::vec::Vec<$crate::layout::Field>,
+            ) {
+                $(
+                    $crate::layout::describe_field(
+                        path,
+                        stringify!($field),
+                        offset + ::core::mem::offset_of!($ty, $field),
+                        |v: &$ty| &v.$field,
+                        out,
+                    );
+                )*
+            }
+        }
+    };
+}
+
+/// Size, alignment and flattened fields of a payload type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub type_name: String,
+    pub size: usize,
+    pub align: usize,
+    /// Fields in declaration order.
+    pub fields: Vec<Field>,
+}
+
+impl Layout {
+    /// Describe `T`.
+    pub fn of<T: Describe>() -> Self {
+        let mut fields = Vec::new();
+        T::describe("", 0, &mut fields);
+        Layout {
+            type_name: T::type_name().to_string(),
+            size: std::mem::size_of::<T>(),
+            align: std::mem::align_of::<T>(),
+            fields,
+        }
+    }
+
+    /// Parse the text form produced by the [`Display`](fmt::Display) impl.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        // Type names such as `[f32; 3]` contain spaces; the sizes do not.
+        let head = lines.next()?.trim().strip_prefix("type ")?;
+        let (rest, align) = head.rsplit_once(' ')?;
+        let (type_name, size) = rest.rsplit_once(' ')?;
+        let type_name = type_name.trim().to_string();
+        let size = size.strip_prefix("size=")?.parse().ok()?;
+        let align = align.strip_prefix("align=")?.parse().ok()?;
+
+        let mut fields = Vec::new();
+        for line in lines {
+            let mut parts = line.split_whitespace();
+            let offset = parts.next()?.parse().ok()?;
+            let ty = parts.next()?;
+            let (ty, len) = match ty.split_once('[') {
+                Some((ty, n)) => (ty, Some(n.strip_suffix(']')?.parse().ok()?)),
+                None => (ty, None),
+            };
+            fields.push(Field {
+                name: parts.next().unwrap_or("").to_string(),
+                offset,
+                ty: Scalar::parse(ty)?,
+                len,
+            });
+        }
+        Some(Layout {
+            type_name,
+            size,
+            align,
+            fields,
+        })
+    }
+
+    /// Render as a JSON Schema (draft 2020-12) document.
+    ///
+    /// Besides the standard keywords every field carries `x-offset` and
+    /// `x-type` (the primitive type), and the root `x-size` and
+    /// `x-align`, which is what codegen needs to reproduce the struct.
+    pub fn to_json_schema(&self) -> String {
+        let mut out = String::new();
+        out.push_str(r#"{"$schema":"https://json-schema.org/draft/2020-12/schema","title":"#);
+        push_str(&mut out, &self.type_name);
+        let _ = write!(out, r#","x-size":{},"x-align":{},"#, self.size, self.align);
+        match self.fields.as_slice() {
+            [field] if field.name.is_empty() => write_field_body(&mut out, field),
+            fields => write_object_body(&mut out, fields, ""),
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// One line per field: `OFFSET TYPE[LEN] NAME`, below a
+/// `type NAME size=N align=N` header.
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "type {} size={} align={}",
+            self.type_name, self.size, self.align
+        )?;
+        for field in &self.fields {
+            write!(f, "{:>6} {}", field.offset, field.ty.name())?;
+            if let Some(n) = field.len {
+                write!(f, "[{n}]")?;
+            }
+            if field.name.is_empty() {
+                writeln!(f)?;
+            } else {
+                writeln!(f, " {}", field.name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write `"type":"object","properties":{...},"required":[...]` for the
+/// fields below `prefix` (which `fields` all share).
+fn write_object_body(out: &mut String, fields: &[Field], prefix: &str) {
+    out.push_str(r#""type":"object","properties":{"#);
+    let mut required = Vec::new();
+    let mut rest = fields;
+    while let Some(first) = rest.first() {
+        let local = &first.name[prefix.len()..];
+        let head = local.split('.').next().unwrap_or(local);
+        if !required.is_empty() {
+            out.push(',');
+        }
+        push_str(out, head);
+        out.push(':');
+
+        if head.len() == local.len() {
+            out.push('{');
+            write_field_body(out, first);
+            out.push('}');
+            rest = &rest[1..];
+        } else {
+            let nested = format!("{prefix}{head}.");
+            let n = rest
+                .iter()
+                .take_while(|f| f.name.starts_with(&nested))
+                .count();
+            let _ = write!(out, r#"{{"x-offset":{},"#, first.offset);
+            write_object_body(out, &rest[..n], &nested);
+            out.push('}');
+            rest = &rest[n..];
+        }
+        required.push(head);
+    }
+    out.push_str(r#"},"required":["#);
+    for (i, name) in required.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_str(out, name);
+    }
+    out.push(']');
+}
+
+fn write_field_body(out: &mut String, field: &Field) {
+    let _ = write!(out, r#""x-offset":{},"x-type":"#, field.offset);
+    push_str(out, field.ty.name());
+    out.push(',');
+    let json_ty = if field.ty.is_float() {
+        "number"
+    } else {
+        "integer"
+    };
+    match field.len {
+        Some(n) => {
+            let _ = write!(
+                out,
+                r#""type":"array","minItems":{n},"maxItems":{n},"items":{{"type":"{json_ty}"}}"#
+            );
+        }
+        None => {
+            let _ = write!(out, r#""type":"{json_ty}""#);
+        }
+    }
+}
+
+fn push_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Publish the layout of `T` as the payload description of `topic`.
+///
+/// The entry outlives the process (like the topic itself) and is replaced
+/// by the next registration; the janitor removes it with the queue.
+pub fn register<T: Describe>(topic: &str) -> io::Result<()> {
+    register_layout(topic, &Layout::of::<T>())
+}
+
+/// [`register`] for an already built layout.
+pub fn register_layout(topic: &str, layout: &Layout) -> io::Result<()> {
+    let dir = registry::topic_dir(topic);
+    fs::create_dir_all(&dir)?;
+    let tmp = dir.join(format!(".layout-{}", std::process::id()));
+    fs::write(&tmp, layout.to_string())?;
+    fs::rename(&tmp, dir.join(LAYOUT_FILE))
+}
+
+/// Layout registered for `topic`, if any.
+pub fn registered(topic: &str) -> io::Result<Option<Layout>> {
+    let path = registry::topic_dir(topic).join(LAYOUT_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    Layout::parse(&text).map(Some).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed layout in {}", path.display()),
+        )
+    })
+}
+
+/// Every topic in the registry that has a layout, sorted by name.
+pub fn all_registered() -> io::Result<Vec<(String, Layout)>> {
+    let mut out = Vec::new();
+    for topic in registry::topics()? {
+        if let Some(layout) = registered(&topic)? {
+            out.push((topic, layout));
+        }
+    }
+    Ok(out)
+}
+
+/// Environment variable that makes [`assert_golden`] rewrite golden files
+/// instead of comparing against them.
+pub const BLESS_ENV: &str = "MQ_IPC_BLESS";
+
+/// Check the layout of `T` against the golden file at `path`.
+///
+/// The file holds the [`Display`](fmt::Display) form of the layout and is
+/// meant to be committed next to the type. A missing file, or a run with
+/// [`BLESS_ENV`] set, writes the current layout instead. Usually called
+/// through [`layout_check!`](crate::layout_check!).
+///
+/// # Panics
+///
+/// When the layout differs from the golden one, listing the first
+/// mismatching line.
+pub fn assert_golden<T: Describe>(path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let current = Layout::of::<T>().to_string();
+    let golden = match fs::read_to_string(path) {
+        Ok(golden) if std::env::var_os(BLESS_ENV).is_none() => golden,
+        Ok(_) | Err(_) => {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Err(err) = fs::write(path, &current) {
+                panic!("writing golden layout {} failed: {err}", path.display());
+            }
+            return;
+        }
+    };
+    if golden == current {
+        return;
+    }
+
+    let (want, got) = golden
+        .lines()
+        .map(Some)
+        .chain(std::iter::repeat(None))
+        .zip(current.lines().map(Some).chain(std::iter::repeat(None)))
+        .take_while(|pair| *pair != (None, None))
+        .find(|(want, got)| want.map(str::trim) != got.map(str::trim))
+        .unwrap_or((None, None));
+    panic!(
+        "layout of {} no longer matches {}\n  golden: {}\n  actual: {}\n\
+         rerun with {BLESS_ENV}=1 if the change is intended (it breaks peers built against the old layout)",
+        std::any::type_name::<T>(),
+        path.display(),
+        want.unwrap_or("<none>").trim(),
+        got.unwrap_or("<none>").trim(),
+    );
+}
+
+/// Pin the memory layout of a message struct.
+///
+/// With the values spelled out, the check runs while compiling: it fails
+/// the build when the size, alignment or any listed field offset changes.
+/// Fields need not be listed exhaustively.
+///
+/// ```
+/// #[repr(C)]
+/// struct MotorState { rpm: f32, torque: f32, pos: [i16; 2] }
+///
+/// mq_ipc::layout_check!(MotorState { size = 12, align = 4, rpm = 0, torque = 4, pos = 8 });
+/// ```
+///
+/// ```compile_fail
+/// #[repr(C)]
+/// struct MotorState { torque: f32, rpm: f32 }
+///
+/// mq_ipc::layout_check!(MotorState { size = 8, align = 4, rpm = 0, torque = 4 });
+/// ```
+///
+/// With `file = PATH` (relative to the calling crate's manifest) it is a
+/// statement for a `#[test]` that compares a [`Describe`](crate::layout::Describe)
+/// type against a golden file, see [`assert_golden`](crate::layout::assert_golden):
+///
+/// ```no_run
+/// # use bytemuck::{Pod, Zeroable};
+/// # #[repr(C)]
+/// # #[derive(Copy, Clone, Pod, Zeroable)]
+/// # struct MotorState { rpm: f32, torque: f32 }
+/// # mq_ipc::describe!(MotorState { rpm, torque });
+/// #[test]
+/// fn motor_state_layout() {
+///     mq_ipc::layout_check!(MotorState, file = "golden/motor_state.layout");
+/// }
+/// ```
+#[macro_export]
+macro_rules! layout_check {
+    ($ty:ty, file = $path:literal) => {
+        $crate::layout::assert_golden::<$ty>(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path))
+    };
+    ($ty:ident { size = $size:expr, align = $align:expr $(, $field:ident = $offset:expr)* $(,)? }) => {
+        const _: () = {
+            assert!(
+                ::core::mem::size_of::<$ty>() == $size,
+                concat!("size of ", stringify!($ty), " changed"),
+            );
+            assert!(
+                ::core::mem::align_of::<$ty>() == $align,
+                concat!("alignment of ", stringify!($ty), " changed"),
+            );
+            $(
+                assert!(
+                    ::core::mem::offset_of!($ty, $field) == $offset,
+                    concat!("field ", stringify!($ty), "::", stringify!($field), " moved"),
+                );
+            )*
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable)]
+    struct Imu {
+        x: f32,
+        y: f32,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Pod, Zeroable)]
+    struct Sample {
+        stamp: u64,
+        imu: Imu,
+        pos: [i16; 4],
+    }
+
+    crate::describe!(Imu { x, y });
+    crate::describe!(Sample { stamp, imu, pos });
+    crate::layout_check!(Sample { size = 24, align = 8, stamp = 0, imu = 8, pos = 16 });
+
+    #[test]
+    fn layout_round_trips_and_exports_schema() {
+        let layout = Layout::of::<Sample>();
+        assert_eq!((layout.size, layout.align), (24, 8));
+        let names: Vec<_> = layout
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.offset))
+            .collect();
+        assert_eq!(
+            names,
+            [("stamp", 0), ("imu.x", 8), ("imu.y", 12), ("pos", 16)]
+        );
+        assert_eq!(layout.fields[3].size(), 8);
+
+        assert_eq!(Layout::parse(&layout.to_string()), Some(layout.clone()));
+
+        let schema = layout.to_json_schema();
+        assert!(schema.contains(r#""title":"Sample","x-size":24,"x-align":8,"type":"object""#));
+        assert!(schema.contains(
+            r#""imu":{"x-offset":8,"type":"object","properties":{"x":{"x-offset":8,"x-type":"f32","type":"number"}"#
+        ));
+        assert!(schema.contains(r#""minItems":4,"maxItems":4,"items":{"type":"integer"}"#));
+        assert!(schema.ends_with(r#""required":["stamp","imu","pos"]}"#));
+
+        let bare = Layout::of::<u32>().to_json_schema();
+        assert!(bare.ends_with(r#""x-offset":0,"x-type":"u32","type":"integer"}"#));
+    }
+
+    #[test]
+    fn registered_layouts_are_visible_per_topic() {
+        let topic = format!("/mq_ipc_test_layout_{}", std::process::id());
+        assert_eq!(registered(&topic).unwrap(), None);
+
+        register::<Sample>(&topic).unwrap();
+        assert_eq!(registered(&topic).unwrap(), Some(Layout::of::<Sample>()));
+        assert!(all_registered()
+            .unwrap()
+            .iter()
+            .any(|(t, l)| *t == topic && l.type_name == "Sample"));
+
+        let dir = registry::topic_dir(&topic);
+        let _ = fs::remove_file(dir.join(LAYOUT_FILE));
+        let _ = fs::remove_dir(dir);
+    }
+
+    #[test]
+    fn golden_file_is_written_then_enforced() {
+        let path =
+            std::env::temp_dir().join(format!("mq_ipc_golden_{}.layout", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        assert_golden::<Sample>(&path);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            Layout::of::<Sample>().to_string()
+        );
+        assert_golden::<Sample>(&path);
+
+        let moved = fs::read_to_string(&path).unwrap().replace("imu.x", "imu.z");
+        fs::write(&path, moved).unwrap();
+        let err = std::panic::catch_unwind(|| assert_golden::<Sample>(&path)).unwrap_err();
+        let msg = err.downcast_ref::<String>().unwrap();
+        assert!(msg.contains("golden: 8 f32 imu.z"), "{msg}");
+        assert!(msg.contains("actual: 8 f32 imu.x"), "{msg}");
+
+        let _ = fs::remove_file(&path);
+    }
+}
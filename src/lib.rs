@@ -23,38 +23,182 @@ THE SOFTWARE.
 
 use libc::{self, mqd_t};
 use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::VecDeque,
     ffi::CString,
     io,
-    os::raw::{c_char, c_long},
+    os::raw::{c_char, c_int, c_long},
+    path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
     },
     thread,
+    time::{Duration, Instant, SystemTime},
+};
+#[cfg(feature = "callbacks")]
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{atomic::AtomicI32, mpsc, Condvar},
 };
 
 use arc_swap::ArcSwap;
+#[cfg(feature = "callbacks")]
+use arc_swap::ArcSwapOption;
 use bytemuck::{Pod, Zeroable};
 
+#[cfg(feature = "callbacks")]
+pub mod aggregate;
+pub mod any;
+pub mod array;
+pub mod barrier;
+pub mod batch;
+pub mod blackboard;
+pub mod caps;
+pub mod cleanup;
+pub mod clock;
+pub mod compat;
+pub mod compress;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod dedup;
+pub mod defaults;
+#[cfg(feature = "callbacks")]
+pub mod demux;
+pub mod dlq;
+#[cfg(feature = "callbacks")]
+pub mod election;
+pub mod envelope;
+pub mod event;
+pub mod ext;
+#[cfg(feature = "foxglove")]
+pub mod foxglove;
+#[cfg(feature = "callbacks")]
+pub mod fsm;
+pub mod graph;
+#[cfg(feature = "callbacks")]
+pub mod group;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "callbacks")]
+pub mod health;
+#[cfg(feature = "callbacks")]
+pub mod history;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod introspect;
+pub mod janitor;
+pub mod journal;
+#[cfg(feature = "callbacks")]
+pub mod keyed;
+pub mod large;
+pub mod layout;
+pub mod lock;
+#[cfg(feature = "callbacks")]
+pub mod merge;
+#[cfg(feature = "callbacks")]
+pub mod mirror;
+pub mod multi;
+#[cfg(feature = "callbacks")]
+pub mod periodic;
+#[cfg(feature = "callbacks")]
+pub mod ping;
+pub mod registry;
+#[cfg(feature = "callbacks")]
+pub mod relay;
+pub mod reorder;
+#[cfg(feature = "callbacks")]
+pub mod resample;
+pub mod retry;
+pub mod ring;
+#[cfg(feature = "callbacks")]
+pub mod rpc;
+pub mod schema;
+#[cfg(feature = "callbacks")]
+pub mod seq;
+pub mod session;
+pub mod set;
+pub mod shape;
+pub mod shutdown;
+pub mod stamped;
+pub mod static_topic;
+pub mod stats;
+pub mod testkit;
+pub mod text;
+pub mod trace;
+pub mod transport;
+pub mod validate;
+#[cfg(feature = "callbacks")]
+pub mod watchdog;
+
 pub const MSG_PAYLOAD_SIZE: usize = 240;
 
+/// Priority used by `publish_urgent`: the highest Linux accepts
+/// (`MQ_PRIO_MAX - 1`), so urgent messages leave every queue first.
+pub const URGENT_PRIORITY: u32 = 32767;
+
 const MSG_TYPE_SHUTDOWN: u16 = 0xFFFF;
 
+/// Header in front of every message.
+///
+/// The 8-byte layout (with `flags`) is queue format 2. Format 1 had no
+/// `flags` and 244-byte messages; opening such a queue, or receiving
+/// such a message, fails with `InvalidData` rather than misreading it.
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct MsgHeader {
     pub msg_type: u16,
     pub len: u16,
+    /// Layout flags, e.g. [`ext::FLAG_EXT`].
+    pub flags: u16,
+    pub reserved: u16,
 }
 
 /// Complete raw message sent over an mqueue.
-#[repr(C)]
-#[derive(Copy, Clone, Debug)]
+///
+/// Aligned to 8 bytes, so the payload can be borrowed in place as any
+/// [`Pod`] type of up to that alignment (see [`Topic::subscribe_ref`]).
+/// The size, and so the bytes on the queue, are the same as unaligned.
+#[repr(C, align(8))]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct Msg {
     pub hdr: MsgHeader,
     pub payload: [u8; MSG_PAYLOAD_SIZE],
 }
 
+/// Receive buffer that always holds a valid, aligned [`Msg`], whatever
+/// the kernel wrote into it.
+pub(crate) struct RecvBuf(Msg);
+
+impl RecvBuf {
+    pub(crate) const LEN: usize = std::mem::size_of::<Msg>();
+
+    pub(crate) fn new() -> Self {
+        RecvBuf(Msg::zeroed())
+    }
+
+    /// Where `mq_receive` should write, [`LEN`](Self::LEN) bytes long.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut c_char {
+        bytemuck::bytes_of_mut(&mut self.0).as_mut_ptr() as *mut c_char
+    }
+
+    /// The message of a receive that returned `len` bytes. Anything but
+    /// a whole [`Msg`] comes from a peer of another queue format.
+    pub(crate) fn msg(&mut self, len: usize) -> io::Result<Msg> {
+        if len != Self::LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{len}-byte message, expected {}: sent by an incompatible mq-ipc version",
+                    Self::LEN
+                ),
+            ));
+        }
+        Ok(self.0)
+    }
+}
+
 impl Msg {
     /// Create a new raw message from a type and arbitrary bytes.
     pub fn new(msg_type: u16, data: &[u8]) -> Self {
@@ -62,6 +206,8 @@ impl Msg {
             hdr: MsgHeader {
                 msg_type,
                 len: data.len().min(MSG_PAYLOAD_SIZE) as u16,
+                flags: 0,
+                reserved: 0,
             },
             payload: [0u8; MSG_PAYLOAD_SIZE],
         };
@@ -72,284 +218,2568 @@ impl Msg {
 }
 
 
+/// Open (and optionally create) the raw POSIX mqueue behind a topic.
+///
+/// When `maxmsg` is given the queue attributes are set so that one `Msg`
+/// fits in each slot; otherwise the queue must already exist. A queue
+/// whose slots have another size, e.g. one created by an mq-ipc of an
+/// older queue format, is refused with `InvalidData`.
+pub(crate) fn open_queue(name: &str, oflag: c_int, maxmsg: Option<c_long>) -> io::Result<mqd_t> {
+    open_queue_sized(name, oflag, maxmsg, std::mem::size_of::<Msg>())
+}
+
+/// [`open_queue`] for queues whose messages are not [`Msg`]s.
+pub(crate) fn open_queue_sized(
+    name: &str,
+    oflag: c_int,
+    maxmsg: Option<c_long>,
+    msgsize: usize,
+) -> io::Result<mqd_t> {
+    let cname = CString::new(name)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid queue name"))?;
+
+    let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
+    let attr_ptr = match maxmsg {
+        Some(maxmsg) => {
+            attr.mq_flags = 0;
+            attr.mq_maxmsg = maxmsg;
+            attr.mq_msgsize = msgsize as c_long;
+            attr.mq_curmsgs = 0;
+            &mut attr as *mut libc::mq_attr
+        }
+        None => std::ptr::null_mut::<libc::mq_attr>(),
+    };
+
+    let mqd = unsafe { libc::mq_open(cname.as_ptr(), oflag, 0o666, attr_ptr) };
+
+    if mqd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // An existing queue keeps its own attributes, whatever we asked for.
+    match queue_attr(mqd) {
+        Ok(attr) if attr.mq_msgsize == msgsize as c_long => Ok(mqd),
+        Ok(attr) => {
+            unsafe { libc::mq_close(mqd) };
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{name} holds {}-byte messages, expected {msgsize}: \
+                     created by an incompatible mq-ipc version",
+                    attr.mq_msgsize
+                ),
+            ))
+        }
+        Err(err) => {
+            unsafe { libc::mq_close(mqd) };
+            Err(err)
+        }
+    }
+}
+
+/// Per-topic settings used by [`MqTopic::with_options`].
+///
+/// Start from [`TopicOptions::new`] and chain the setters:
+///
+/// ```no_run
+/// # use mq_ipc::{MqTopic, TopicOptions};
+/// let setpoint = MqTopic::with_options("/setpoint", &TopicOptions::new(1).conflate(true))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct TopicOptions {
+    /// Queue depth used when the queue is created.
+    pub maxmsg: c_long,
+    /// "Latest value wins": publishing to a full queue discards the oldest
+    /// queued message instead of blocking.
+    pub conflate: bool,
+    /// Write-ahead [`journal`] file for published messages.
+    pub journal: Option<PathBuf>,
+    /// `fdatasync` the journal after every append.
+    pub journal_sync: bool,
+    /// Forward undeliverable messages to `<name>.dlq`; see [`dlq`].
+    pub dead_letter: bool,
+    /// Typed subscribers reject payloads whose length differs from
+    /// `size_of::<T>()` instead of zero-padding or truncating them.
+    pub strict: bool,
+    /// Stamp the caller's [`trace`] context into published messages.
+    pub propagate_trace: bool,
+    /// Report callbacks running longer than this via [`MqTopic::on_error`].
+    pub callback_budget: Option<Duration>,
+    /// Publishing to a full queue fails with `WouldBlock` instead of blocking.
+    pub nonblocking: bool,
+    /// Retry behaviour for `EINTR`/`EAGAIN` on publish.
+    pub retry: retry::RetryPolicy,
+    /// Space out publishes through a [`shape`] token bucket.
+    pub shape: Option<shape::TokenBucket>,
+    /// Publish a [`stats::DepthReport`] this often.
+    pub depth_report: Option<Duration>,
+    /// Publish a [`stats::StatsReport`] this often.
+    pub stats_report: Option<Duration>,
+    /// Claim the topic for this process; publishers elsewhere are refused
+    /// with [`registry::ExclusiveOwner`].
+    pub exclusive: bool,
+    /// Stamp this [`schema`] version into published messages.
+    pub schema_version: Option<u32>,
+    /// Typed publishers send values by reference through [`large`].
+    pub large: bool,
+    /// Typed publishers LZ4-compress values; see [`compress`].
+    pub compress: bool,
+    /// Give every published message an idempotency key; see [`dedup`].
+    pub idempotency: bool,
+    /// Drop messages whose idempotency key was among the last this many
+    /// seen by this handle's subscribers.
+    pub dedup: Option<usize>,
+    /// Number published messages per handle; see [`reorder`].
+    pub sequenced: bool,
+    /// Deliver each publisher's sequenced messages in order.
+    pub reorder: Option<reorder::ReorderOptions>,
+    /// Dispatch [`URGENT_PRIORITY`] messages on arrival, ahead of any
+    /// held for reordering.
+    pub urgent_first: bool,
+    /// Stamp the send time into published messages.
+    pub timestamped: bool,
+    /// Stamp this process's session ID into published messages; see
+    /// [`session`].
+    pub session: bool,
+    /// Discard received messages stamped longer ago than this.
+    pub ttl: Option<Duration>,
+    /// Restart the receive loop after it dies, reopening the queue if its
+    /// descriptor is gone; see [`MqTopic::is_healthy`].
+    pub supervise: bool,
+    /// Follow the queue by name when it is unlinked or recreated.
+    pub reopen: bool,
+    /// Whether dropping the handle removes the queue.
+    pub unlink: UnlinkPolicy,
+}
+
+/// When dropping a topic handle unlinks its queue.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum UnlinkPolicy {
+    /// Leave the queue for others; remove it with [`cleanup`] or the
+    /// janitor.
+    #[default]
+    Never,
+    /// Unlink if this handle created the queue (see [`MqTopic::created`]).
+    Creator,
+    /// Unlink if no live publisher or subscriber is left in the
+    /// [`registry`] once this handle's own entries are gone.
+    LastUser,
+}
+
+/// Named delivery profile, set with [`TopicOptions::qos`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Qos {
+    /// A publish to a full queue waits for room; nothing is dropped.
+    #[default]
+    Reliable,
+    /// A publish to a full queue fails with `WouldBlock` instead
+    /// ([`TopicOptions::nonblocking`]).
+    BestEffort,
+    /// A publish to a full queue drops the oldest message, so readers
+    /// find the newest ones ([`TopicOptions::conflate`]).
+    KeepLatest,
+}
+
+/// What became of a message, as reported by [`MqTopic::publish_confirmed`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    /// Accepted into the kernel queue.
+    Queued,
+    /// Accepted after dropping this many older messages to make room
+    /// ([`Qos::KeepLatest`]).
+    Evicted(usize),
+    /// Not sent: the queue was still full after any retries and the topic
+    /// does not wait for room ([`Qos::BestEffort`]).
+    Dropped,
+}
+
+impl Delivery {
+    /// Whether the message itself is in the queue.
+    pub fn is_queued(self) -> bool {
+        self != Delivery::Dropped
+    }
+}
+
+impl TopicOptions {
+    pub fn new(maxmsg: c_long) -> Self {
+        TopicOptions {
+            maxmsg,
+            conflate: false,
+            journal: None,
+            journal_sync: false,
+            dead_letter: false,
+            strict: false,
+            propagate_trace: false,
+            callback_budget: None,
+            nonblocking: false,
+            retry: retry::RetryPolicy::none(),
+            shape: None,
+            depth_report: None,
+            stats_report: None,
+            exclusive: false,
+            schema_version: None,
+            large: false,
+            compress: false,
+            idempotency: false,
+            dedup: None,
+            sequenced: false,
+            reorder: None,
+            urgent_first: false,
+            timestamped: false,
+            session: false,
+            ttl: None,
+            supervise: false,
+            reopen: false,
+            unlink: UnlinkPolicy::Never,
+        }
+    }
+
+    /// Enable conflation. Combine with `maxmsg = 1` to keep only the
+    /// single latest value in the queue.
+    pub fn conflate(mut self, on: bool) -> Self {
+        self.conflate = on;
+        self
+    }
+
+    /// Journal every publish to `path` before sending it; see
+    /// [`MqTopic::replay_journal`].
+    pub fn journal(mut self, path: impl AsRef<Path>) -> Self {
+        self.journal = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Make journal appends durable (`fdatasync`) at the cost of latency.
+    pub fn journal_sync(mut self, on: bool) -> Self {
+        self.journal_sync = on;
+        self
+    }
+
+    /// Enable the per-topic dead-letter queue.
+    pub fn dead_letter(mut self, on: bool) -> Self {
+        self.dead_letter = on;
+        self
+    }
+
+    /// Enable strict length checking in typed subscribers.
+    pub fn strict(mut self, on: bool) -> Self {
+        self.strict = on;
+        self
+    }
+
+    /// Carry [`trace::current`] in the extended header of every publish.
+    pub fn propagate_trace(mut self, on: bool) -> Self {
+        self.propagate_trace = on;
+        self
+    }
+
+    /// Flag callback invocations slower than `budget`.
+    pub fn callback_budget(mut self, budget: Duration) -> Self {
+        self.callback_budget = Some(budget);
+        self
+    }
+
+    /// Never block in `publish`; see also [`TopicOptions::retry`].
+    pub fn nonblocking(mut self, on: bool) -> Self {
+        self.nonblocking = on;
+        self
+    }
+
+    /// Retry transient send failures according to `policy`.
+    pub fn retry(mut self, policy: retry::RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Let `burst` publishes through back to back, then hold this handle
+    /// to `rate_hz` by waiting before each send; see [`shape`].
+    pub fn shape(mut self, rate_hz: f64, burst: u32) -> Self {
+        self.shape = Some(shape::TokenBucket::new(rate_hz, burst));
+        self
+    }
+
+    /// Periodically publish this topic's queue depth on
+    /// [`stats::DEPTH_REPORT_TOPIC`].
+    pub fn depth_report(mut self, interval: Duration) -> Self {
+        self.depth_report = Some(interval);
+        self
+    }
+
+    /// Periodically publish this topic's rates, drops, depth and callback
+    /// latency on [`stats::STATS_TOPIC`], for monitors that subscribe
+    /// rather than link a metrics library.
+    pub fn stats_report(mut self, interval: Duration) -> Self {
+        self.stats_report = Some(interval);
+        self
+    }
+
+    /// Make this handle the topic's only permitted publisher process.
+    pub fn exclusive(mut self, on: bool) -> Self {
+        self.exclusive = on;
+        self
+    }
+
+    /// Tag published messages with layout version `version`, letting
+    /// subscribers migrate older layouts via [`schema::Schema`].
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Publish typed values through `memfd` segments instead of inline,
+    /// for types larger than a message; see [`large`].
+    pub fn large(mut self, on: bool) -> Self {
+        self.large = on;
+        self
+    }
+
+    /// Compress typed values when that makes them smaller. Needs the `lz4`
+    /// feature and cannot be combined with [`schema_version`](Self::schema_version).
+    pub fn compress(mut self, on: bool) -> Self {
+        self.compress = on;
+        self
+    }
+
+    /// Stamp a unique idempotency key into every published message.
+    pub fn idempotency(mut self, on: bool) -> Self {
+        self.idempotency = on;
+        self
+    }
+
+    /// Suppress duplicates among the last `capacity` keyed messages.
+    pub fn dedup(mut self, capacity: usize) -> Self {
+        self.dedup = Some(capacity);
+        self
+    }
+
+    /// Stamp a per-handle sequence number into every published message.
+    pub fn sequenced(mut self, on: bool) -> Self {
+        self.sequenced = on;
+        self
+    }
+
+    /// Hold back out-of-order sequenced messages before dispatch.
+    pub fn reorder(mut self, opts: reorder::ReorderOptions) -> Self {
+        self.reorder = Some(opts);
+        self
+    }
+
+    /// Let urgent messages skip the reorder buffer instead of waiting
+    /// behind a gap.
+    pub fn urgent_first(mut self, on: bool) -> Self {
+        self.urgent_first = on;
+        self
+    }
+
+    /// Stamp the send time, read from [`clock::default_clock`], into every
+    /// published message; see [`Msg::sent_at`].
+    pub fn timestamped(mut self, on: bool) -> Self {
+        self.timestamped = on;
+        self
+    }
+
+    /// Stamp this process's session ID into every published message, so
+    /// subscribers notice when the publisher restarts; see
+    /// [`MqTopic::on_publisher_restart`].
+    pub fn session(mut self, on: bool) -> Self {
+        self.session = on;
+        self
+    }
+
+    /// Have the worker discard, and count in
+    /// [`TopicStats::expired`](stats::TopicStats::expired), messages sent
+    /// more than `ttl` ago, so a subscriber that fell behind skips a
+    /// stale backlog instead of working through it. Messages without a
+    /// send time are always delivered. Implies
+    /// [`timestamped`](Self::timestamped) for this handle's publishes.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Restart the worker when a callback panics (without a dead-letter
+    /// queue) or the descriptor fails. Each death and restart is reported
+    /// through [`MqTopic::on_error`].
+    pub fn supervise(mut self, on: bool) -> Self {
+        self.supervise = on;
+        self
+    }
+
+    /// Keep receiving when another process unlinks and recreates the
+    /// queue: the worker notices its descriptor now points at an orphaned
+    /// (or closed) queue, opens the name again and reports
+    /// [`TopicError::QueueReopened`]. Publishing through this handle
+    /// still goes to the queue it was opened on.
+    pub fn reopen(mut self, on: bool) -> Self {
+        self.reopen = on;
+        self
+    }
+
+    /// Unlink the queue when the handle drops, according to `policy`, so
+    /// one process does not pull a queue out from under the others.
+    pub fn unlink_on_drop(mut self, policy: UnlinkPolicy) -> Self {
+        self.unlink = policy;
+        self
+    }
+
+    /// Set `nonblocking` and `conflate` as `qos` describes.
+    pub fn qos(mut self, qos: Qos) -> Self {
+        self.nonblocking = qos == Qos::BestEffort;
+        self.conflate = qos == Qos::KeepLatest;
+        self
+    }
+}
+
+impl Default for TopicOptions {
+    /// `MQ_IPC_DEFAULT_MAXMSG` deep, else Linux' default `msg_max`; see
+    /// [`defaults`].
+    fn default() -> Self {
+        TopicOptions::new(defaults::maxmsg())
+    }
+}
+
+#[cfg(feature = "callbacks")]
 type Callback = Arc<dyn Fn(Msg) + Send + Sync + 'static>;
+#[cfg(feature = "callbacks")]
+type ErrorCallback = Box<dyn Fn(&TopicError) + Send + Sync + 'static>;
+
+/// Runtime problems reported through [`MqTopic::on_error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TopicError {
+    /// A callback exceeded [`TopicOptions::callback_budget`].
+    CallbackOverBudget { index: usize, elapsed: Duration },
+    /// The worker stopped receiving while the topic was still open.
+    WorkerDied { cause: WorkerFailure },
+    /// A supervised worker was started again after dying.
+    WorkerRestarted { restarts: u32 },
+    /// The queue was unlinked and has not been recreated yet; the worker
+    /// waits for it. Only with [`TopicOptions::reopen`].
+    QueueLost,
+    /// The worker now receives from a new queue of the same name.
+    QueueReopened,
+}
+
+/// Why a topic's worker died.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WorkerFailure {
+    /// A subscriber callback panicked.
+    Panic,
+    /// Receiving failed with this `errno`.
+    Errno(i32),
+}
+
+impl std::fmt::Display for TopicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TopicError::CallbackOverBudget { index, elapsed } => {
+                write!(f, "callback #{index} took {elapsed:?}, over budget")
+            }
+            TopicError::WorkerDied {
+                cause: WorkerFailure::Panic,
+            } => write!(f, "worker died: callback panicked"),
+            TopicError::WorkerDied {
+                cause: WorkerFailure::Errno(code),
+            } => write!(f, "worker died: {}", io::Error::from_raw_os_error(*code)),
+            TopicError::WorkerRestarted { restarts } => {
+                write!(f, "worker restarted ({restarts} so far)")
+            }
+            TopicError::QueueLost => write!(f, "queue unlinked, waiting for it to reappear"),
+            TopicError::QueueReopened => write!(f, "queue recreated, reopened by name"),
+        }
+    }
+}
+
+/// Returned by [`MqTopic::wait_for`] when no matching message arrived in
+/// time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timeout {
+    pub waited: Duration,
+}
+
+impl std::fmt::Display for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no matching message within {:?}", self.waited)
+    }
+}
+
+impl std::error::Error for Timeout {}
+
+impl From<Timeout> for io::Error {
+    fn from(t: Timeout) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, t)
+    }
+}
+
+#[cfg(feature = "callbacks")]
+#[derive(Clone)]
+struct Subscription {
+    cb: Callback,
+    priority: i32,
+    counters: Arc<stats::CallbackCounters>,
+}
+
+#[cfg(feature = "callbacks")]
+struct SubscriberList {
+    cbs: Vec<Subscription>,
+}
+
+/// State handed to a topic's worker thread.
+#[cfg(feature = "callbacks")]
+struct WorkerCtx {
+    mqd: mqd_t,
+    subs: Arc<ArcSwap<SubscriberList>>,
+    depth: Arc<stats::DepthCounters>,
+    running: Arc<AtomicBool>,
+    dlq: Option<Arc<dlq::DeadLetterQueue>>,
+    budget: Option<Duration>,
+    on_error: Arc<ArcSwapOption<ErrorCallback>>,
+    dedup: Option<Mutex<dedup::DedupCache>>,
+    reorder: Option<reorder::ReorderOptions>,
+    urgent_first: bool,
+    ttl: Option<Duration>,
+    traffic: Arc<stats::TrafficCounters>,
+    suspension: Arc<Suspension>,
+    sessions: Arc<session::SessionWatch>,
+    name: String,
+    supervise: bool,
+    reopen: bool,
+    health: Arc<WorkerHealth>,
+}
+
+/// How one run of the receive loop ended.
+#[cfg(feature = "callbacks")]
+enum WorkerExit {
+    /// The topic is closing or the process shutting down.
+    Stopped,
+    Failed(io::Error),
+    /// The name now refers to another queue, or to none.
+    Moved,
+}
+
+/// Worker state shared with its topic.
+struct WorkerHealth {
+    healthy: AtomicBool,
+    restarts: AtomicU32,
+    /// Descriptor the worker receives on: the topic's own, or one it
+    /// reopened after that one failed.
+    #[cfg(feature = "callbacks")]
+    rx_mqd: AtomicI32,
+    /// CPU time the worker thread has used, in ns.
+    cpu_ns: AtomicU64,
+}
+
+/// [`MqTopic::suspend`] state shared with the worker.
+#[cfg(feature = "callbacks")]
+#[derive(Default)]
+struct Suspension {
+    suspended: AtomicBool,
+    /// The worker is waiting in [`Suspension::wait`] rather than receiving.
+    parked: Mutex<bool>,
+    resumed: Condvar,
+}
+
+#[cfg(feature = "callbacks")]
+impl Suspension {
+    /// Block while suspended. Returns false if `running` was cleared
+    /// meanwhile.
+    fn wait(&self, running: &AtomicBool) -> bool {
+        if !self.suspended.load(Ordering::Acquire) {
+            return true;
+        }
+        let mut parked = self.parked.lock().unwrap_or_else(|e| e.into_inner());
+        while self.suspended.load(Ordering::Acquire) {
+            if !running.load(Ordering::Relaxed) || shutdown::requested() {
+                *parked = false;
+                return false;
+            }
+            *parked = true;
+            parked = self
+                .resumed
+                .wait_timeout(parked, HELPER_POLL_INTERVAL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        *parked = false;
+        true
+    }
+
+    fn resume(&self) {
+        let _parked = self.parked.lock().unwrap_or_else(|e| e.into_inner());
+        self.suspended.store(false, Ordering::Release);
+        self.resumed.notify_all();
+    }
+
+    /// Wake a parked worker so it notices the topic closing. Returns
+    /// whether it was parked, i.e. needs no wake-up message.
+    fn release_parked(&self) -> bool {
+        let parked = self.parked.lock().unwrap_or_else(|e| e.into_inner());
+        self.resumed.notify_all();
+        *parked
+    }
+}
+
+/// Pause before a supervised worker starts again.
+#[cfg(feature = "callbacks")]
+const WORKER_RESTART_DELAY: Duration = Duration::from_millis(100);
+
+/// How often a worker with [`TopicOptions::reopen`] checks that its
+/// descriptor still refers to the named queue.
+#[cfg(feature = "callbacks")]
+const REOPEN_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+#[cfg(feature = "callbacks")]
+fn report(on_error: &ArcSwapOption<ErrorCallback>, err: TopicError) {
+    if let Some(cb) = on_error.load().as_ref() {
+        cb(&err);
+    }
+}
+
+/// Receive thread of a topic, started by the first `subscribe`.
+#[cfg(feature = "callbacks")]
+struct Worker {
+    handle: Option<thread::JoinHandle<()>>,
+    id: u64,
+}
+
 /// A system-wide topic backed by POSIX mqueue (`mqueue`).
 ///
 /// Multiple processes can open the same name (e.g. "/topic.motor_state")
 /// and publish to / subscribe from it. Inside this process, you can
 /// register multiple callbacks that are invoked by a background worker
 /// thread whenever a message arrives.
-struct SubscriberList {
-    cbs: Vec<Callback>,
-}
-
+///
+/// The worker is only started by the first `subscribe`: a handle that
+/// just publishes never takes messages out of the queue, so it cannot
+/// steal them from subscribers in other processes.
 pub struct MqTopic {
     name: String,
     mqd: mqd_t,
+    #[cfg(feature = "callbacks")]
     subs: Arc<ArcSwap<SubscriberList>>,
+    #[cfg(feature = "callbacks")]
     running: Arc<AtomicBool>,
-    worker: Option<thread::JoinHandle<()>>,
+    #[cfg(feature = "callbacks")]
+    worker: OnceLock<Worker>,
+    #[cfg(feature = "callbacks")]
+    budget: Option<Duration>,
+    max_payload: usize,
+    depth: Arc<stats::DepthCounters>,
+    conflate: bool,
+    journal: Option<journal::Journal>,
+    dlq: Option<Arc<dlq::DeadLetterQueue>>,
+    strict: bool,
+    propagate_trace: bool,
+    schema_version: Option<u32>,
+    large: bool,
+    compress: bool,
+    keys: Option<dedup::KeyGenerator>,
+    #[cfg(feature = "callbacks")]
+    dedup: Option<usize>,
+    sequencer: Option<reorder::Sequencer>,
+    #[cfg(feature = "callbacks")]
+    reorder: Option<reorder::ReorderOptions>,
+    #[cfg(feature = "callbacks")]
+    urgent_first: bool,
+    timestamped: bool,
+    ttl: Option<Duration>,
+    session: bool,
+    sessions: Arc<session::SessionWatch>,
+    #[cfg(feature = "callbacks")]
+    suspension: Arc<Suspension>,
+    nonblocking: bool,
+    retry: retry::RetryPolicy,
+    shaper: Option<shape::Shaper>,
+    sub_reg: OnceLock<Option<registry::Registration>>,
+    pub_reg: OnceLock<Option<registry::Registration>>,
+    helpers: Mutex<Vec<(Arc<AtomicBool>, thread::JoinHandle<()>)>>,
+    #[cfg(feature = "callbacks")]
+    on_error: Arc<ArcSwapOption<ErrorCallback>>,
+    owner: Option<registry::OwnerClaim>,
+    owner_check: Mutex<Option<(Instant, Result<(), registry::ExclusiveOwner>)>>,
+    traffic: Arc<stats::TrafficCounters>,
+    #[cfg(feature = "callbacks")]
+    supervise: bool,
+    #[cfg(feature = "callbacks")]
+    reopen: bool,
+    health: Arc<WorkerHealth>,
+    created: bool,
+    unlink: UnlinkPolicy,
+    introspect_id: u64,
+}
+
+/// What [`MqTopic::stats`] and the stats reporter read.
+struct StatsSource {
+    name: String,
+    mqd: mqd_t,
+    #[cfg(feature = "callbacks")]
+    subs: Arc<ArcSwap<SubscriberList>>,
+    depth: Arc<stats::DepthCounters>,
+    traffic: Arc<stats::TrafficCounters>,
+    health: Arc<WorkerHealth>,
+}
+
+impl StatsSource {
+    fn snapshot(&self) -> stats::TopicStats {
+        #[cfg(feature = "callbacks")]
+        let subs = self.subs.load();
+        let depth = match queue_attr(self.mqd) {
+            Ok(attr) => self
+                .depth
+                .snapshot(attr.mq_maxmsg as u64, attr.mq_curmsgs as u64),
+            Err(_) => self.depth.snapshot(0, 0),
+        };
+        stats::TopicStats {
+            name: self.name.clone(),
+            depth,
+            published: self.traffic.published.load(Ordering::Relaxed),
+            dropped: self.traffic.dropped.load(Ordering::Relaxed),
+            rejected: self.traffic.rejected.load(Ordering::Relaxed),
+            expired: self.traffic.expired.load(Ordering::Relaxed),
+            worker_cpu: Duration::from_nanos(self.health.cpu_ns.load(Ordering::Relaxed)),
+            #[cfg(feature = "callbacks")]
+            callbacks: subs
+                .cbs
+                .iter()
+                .enumerate()
+                .map(|(i, sub)| sub.counters.snapshot(i))
+                .collect(),
+            #[cfg(not(feature = "callbacks"))]
+            callbacks: Vec::new(),
+        }
+    }
 }
 
+/// How long an ownership check, passed or refused, is trusted before
+/// `publish` looks at the registry again.
+const OWNER_RECHECK: Duration = Duration::from_millis(100);
+
+/// Polling period of helper threads (`on_matched` watchers, depth reports).
+const HELPER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a blocked [`MqTopic::recv_timeout`] checks for [`shutdown`].
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 impl MqTopic {
     /// Create or open a topic backed by a POSIX mqueue.
     ///
-    /// - `name` must start with '/' (POSIX requirement).
+    /// - `name` must start with '/' (POSIX requirement); `MQ_IPC_PREFIX`
+    ///   is applied to it, see [`defaults`].
     /// - `maxmsg` is the maximum number of messages that can be queued.
     pub fn new(name: &str, maxmsg: c_long) -> io::Result<Self> {
-        let cname = CString::new(name)
-            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid queue name"))?;
-
-        let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
-        attr.mq_flags = 0;
-        attr.mq_maxmsg = maxmsg;
-        attr.mq_msgsize = std::mem::size_of::<Msg>() as c_long;
-        attr.mq_curmsgs = 0;
-
-        let mqd = unsafe {
-            libc::mq_open(
-                cname.as_ptr(),
-                libc::O_CREAT | libc::O_RDWR,
-                0o666,
-                &mut attr,
-            )
-        };
+        Self::with_options(name, &TopicOptions::new(maxmsg))
+    }
+
+    /// Create or open a topic with explicit [`TopicOptions`].
+    pub fn with_options(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        Self::open_resolved(&defaults::topic_name(name), opts)
+    }
 
-        if mqd == -1 {
-            return Err(io::Error::last_os_error());
+    /// [`with_options`](Self::with_options) for a queue name that already
+    /// carries the prefix, e.g. one read back from the [`registry`].
+    pub(crate) fn open_resolved(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        if opts.compress {
+            compress::check_options(opts)?;
         }
+        let shaper = opts.shape.map(shape::Shaper::new).transpose()?;
+        let journal = match &opts.journal {
+            Some(path) => Some(journal::Journal::open(path, opts.journal_sync)?),
+            None => None,
+        };
+        let dlq = if opts.dead_letter {
+            Some(Arc::new(dlq::DeadLetterQueue::open(name, opts.maxmsg)?))
+        } else {
+            None
+        };
+        let owner = if opts.exclusive {
+            Some(registry::claim_owner(name)?)
+        } else {
+            None
+        };
+        let (mqd, created) = create_queue(name, opts.maxmsg)?;
+        let mut topic = Self::from_mqd(name, mqd, opts, journal, dlq);
+        topic.owner = owner;
+        topic.created = created;
+        topic.shaper = shaper;
+        Ok(topic)
+    }
 
-        let subs = Arc::new(ArcSwap::from_pointee(SubscriberList { cbs: Vec::new() }));
-        let running = Arc::new(AtomicBool::new(true));
-        let worker = Self::spawn_worker(mqd, Arc::clone(&subs), Arc::clone(&running));
+    pub fn open_existing(name: &str) -> io::Result<Option<Self>> {
+        Self::open_existing_resolved(&defaults::topic_name(name))
+    }
 
-        Ok(MqTopic {
-            name: name.to_string(),
+    pub(crate) fn open_existing_resolved(name: &str) -> io::Result<Option<Self>> {
+        let mqd = match open_queue(name, libc::O_RDWR, None) {
+            Ok(mqd) => mqd,
+            Err(err) if err.raw_os_error() == Some(libc::ENOENT) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Ok(Some(Self::from_mqd(
+            name,
             mqd,
-            subs,
-            running,
-            worker: Some(worker),
-        })
+            &TopicOptions::default(),
+            None,
+            None,
+        )))
     }
 
-    pub fn open_existing(name: &str) -> io::Result<Option<Self>> {
-        let cname = CString::new(name)
-            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid queue name"))?;
-
-        let mqd = unsafe {
-            libc::mq_open(
-                cname.as_ptr(),
-                libc::O_RDWR,
-                0o660,
-                std::ptr::null_mut::<libc::mq_attr>(),
-            )
+    fn from_mqd(
+        name: &str,
+        mqd: mqd_t,
+        opts: &TopicOptions,
+        journal: Option<journal::Journal>,
+        dlq: Option<Arc<dlq::DeadLetterQueue>>,
+    ) -> Self {
+        let mut topic = MqTopic {
+            name: name.to_string(),
+            mqd,
+            #[cfg(feature = "callbacks")]
+            subs: Arc::new(ArcSwap::from_pointee(SubscriberList { cbs: Vec::new() })),
+            #[cfg(feature = "callbacks")]
+            running: Arc::new(AtomicBool::new(true)),
+            #[cfg(feature = "callbacks")]
+            worker: OnceLock::new(),
+            #[cfg(feature = "callbacks")]
+            budget: opts.callback_budget,
+            max_payload: defaults::max_payload(),
+            depth: Arc::new(stats::DepthCounters::default()),
+            conflate: opts.conflate,
+            journal,
+            dlq,
+            strict: opts.strict,
+            propagate_trace: opts.propagate_trace,
+            schema_version: opts.schema_version,
+            large: opts.large,
+            compress: opts.compress,
+            keys: opts.idempotency.then(dedup::KeyGenerator::new),
+            #[cfg(feature = "callbacks")]
+            dedup: opts.dedup,
+            sequencer: opts.sequenced.then(reorder::Sequencer::new),
+            #[cfg(feature = "callbacks")]
+            reorder: opts.reorder,
+            #[cfg(feature = "callbacks")]
+            urgent_first: opts.urgent_first,
+            timestamped: opts.timestamped || opts.ttl.is_some(),
+            ttl: opts.ttl,
+            session: opts.session,
+            sessions: Arc::default(),
+            #[cfg(feature = "callbacks")]
+            suspension: Arc::new(Suspension::default()),
+            nonblocking: opts.nonblocking,
+            retry: opts.retry,
+            shaper: None,
+            sub_reg: OnceLock::new(),
+            pub_reg: OnceLock::new(),
+            helpers: Mutex::new(Vec::new()),
+            #[cfg(feature = "callbacks")]
+            on_error: Arc::new(ArcSwapOption::empty()),
+            owner: None,
+            owner_check: Mutex::new(None),
+            traffic: Arc::new(stats::TrafficCounters::default()),
+            #[cfg(feature = "callbacks")]
+            supervise: opts.supervise,
+            #[cfg(feature = "callbacks")]
+            reopen: opts.reopen,
+            created: false,
+            unlink: opts.unlink,
+            health: Arc::new(WorkerHealth {
+                healthy: AtomicBool::new(true),
+                restarts: AtomicU32::new(0),
+                #[cfg(feature = "callbacks")]
+                rx_mqd: AtomicI32::new(mqd),
+                cpu_ns: AtomicU64::new(0),
+            }),
+            introspect_id: 0,
         };
+        topic.introspect_id = topic.register_introspection();
+        if let Some(interval) = opts.depth_report {
+            topic.spawn_depth_reporter(interval);
+        }
+        if let Some(interval) = opts.stats_report {
+            topic.spawn_stats_reporter(interval);
+        }
+        topic
+    }
 
-        if mqd == -1 {
-            let err = io::Error::last_os_error();
-            if let Some(code) = err.raw_os_error() {
-                if code == libc::ENOENT {
-                    return Ok(None)
+    #[cfg(feature = "callbacks")]
+    fn ensure_worker(&self) {
+        self.worker.get_or_init(|| {
+            let handle = Self::spawn_worker(WorkerCtx {
+                mqd: self.mqd,
+                subs: Arc::clone(&self.subs),
+                depth: Arc::clone(&self.depth),
+                running: Arc::clone(&self.running),
+                dlq: self.dlq.clone(),
+                budget: self.budget,
+                on_error: Arc::clone(&self.on_error),
+                dedup: self.dedup.map(|n| Mutex::new(dedup::DedupCache::new(n))),
+                reorder: self.reorder,
+                urgent_first: self.urgent_first,
+                ttl: self.ttl,
+                traffic: Arc::clone(&self.traffic),
+                suspension: Arc::clone(&self.suspension),
+                sessions: Arc::clone(&self.sessions),
+                name: self.name.clone(),
+                supervise: self.supervise,
+                reopen: self.reopen,
+                health: Arc::clone(&self.health),
+            });
+
+            let health = Arc::clone(&self.health);
+            let wake_running = Arc::clone(&self.running);
+            let id = register_worker(move || {
+                if wake_running.swap(false, Ordering::Relaxed) {
+                    send_shutdown(health.rx_mqd.load(Ordering::Relaxed));
                 }
-            }
-            return Err(err);
-        }
+            });
 
-        let subs = Arc::new(ArcSwap::from_pointee(SubscriberList { cbs: Vec::new() }));
-        let running = Arc::new(AtomicBool::new(true));
-        let worker = Self::spawn_worker(mqd, Arc::clone(&subs), Arc::clone(&running));
+            Worker {
+                handle: Some(handle),
+                id,
+            }
+        });
+    }
 
-        Ok(Some(MqTopic {
-            name: name.to_string(),
-            mqd,
-            subs,
-            running,
-            worker: Some(worker),
-        }))
+    #[cfg(feature = "callbacks")]
+    fn spawn_worker(ctx: WorkerCtx) -> thread::JoinHandle<()> {
+        thread::spawn(move || Self::run_worker(ctx))
     }
 
-    fn spawn_worker(
-        mqd: mqd_t,
-        subs: Arc<ArcSwap<SubscriberList>>,
-        running: Arc<AtomicBool>,
-    ) -> thread::JoinHandle<()> {
-        thread::spawn(move || {
-            let mut buf = [0u8; std::mem::size_of::<Msg>()];
-
-            loop {
-                let mut prio: u32 = 0;
-                let ret = unsafe {
-                    libc::mq_receive(
-                        mqd,
-                        buf.as_mut_ptr() as *mut c_char,
-                        buf.len(),
-                        &mut prio as *mut u32,
-                    )
+    /// Run the receive loop until the topic closes. A run that dies is
+    /// reported and, on a supervised topic, started again.
+    #[cfg(feature = "callbacks")]
+    fn run_worker(ctx: WorkerCtx) {
+        let mut mqd = ctx.mqd;
+        loop {
+            let exit = panic::catch_unwind(AssertUnwindSafe(|| Self::receive(&ctx, mqd)));
+            if !ctx.running.load(Ordering::Relaxed) || shutdown::requested() {
+                break;
+            }
+            let moved = match &exit {
+                Ok(WorkerExit::Moved) => true,
+                Ok(WorkerExit::Failed(err)) => {
+                    ctx.reopen && err.raw_os_error() == Some(libc::EBADF)
+                }
+                _ => false,
+            };
+            if moved {
+                let Some(fresh) = Self::reopen_queue(&ctx) else {
+                    break;
                 };
+                mqd = Self::swap_rx(&ctx, mqd, fresh);
+                report(&ctx.on_error, TopicError::QueueReopened);
+                continue;
+            }
 
-                if ret < 0 {
-                    let err = io::Error::last_os_error();
-                    if let Some(code) = err.raw_os_error() {
-                        match code {
-                            libc::EINTR => {
-                                // sinal interrompeu; se já mandaram parar, sai
-                                if !running.load(Ordering::Relaxed) {
-                                    break;
-                                }
-                                continue;
-                            }
-                            libc::EBADF => {
-                                // fila foi fechada: hora de sair
-                                break;
-                            }
-                            _ => {
-                                eprintln!("mq_receive error: {err}");
-                                if !running.load(Ordering::Relaxed) {
-                                    break;
-                                }
-                                continue;
-                            }
-                        }
-                    }
-                    break;
+            let cause = match exit {
+                Ok(WorkerExit::Stopped) | Ok(WorkerExit::Moved) => break,
+                Ok(WorkerExit::Failed(err)) => {
+                    WorkerFailure::Errno(err.raw_os_error().unwrap_or(0))
                 }
+                Err(_) => WorkerFailure::Panic,
+            };
+            ctx.health.healthy.store(false, Ordering::Relaxed);
+            report(&ctx.on_error, TopicError::WorkerDied { cause });
+            if !ctx.supervise {
+                break;
+            }
 
-                // SAFETY: buffer contém Msg válido
-                let msg: Msg = unsafe { std::ptr::read(buf.as_ptr() as *const Msg) };
+            thread::sleep(WORKER_RESTART_DELAY);
+            if queue_attr(mqd).is_err() {
+                match open_queue(&ctx.name, libc::O_RDWR, None) {
+                    Ok(fresh) => mqd = Self::swap_rx(&ctx, mqd, fresh),
+                    // Try again after the next delay.
+                    Err(_) => continue,
+                }
+            }
 
-                if msg.hdr.msg_type == MSG_TYPE_SHUTDOWN
-                    && !running.load(Ordering::Relaxed)
-                {
-                    break;
+            let restarts = ctx.health.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+            ctx.health.healthy.store(true, Ordering::Relaxed);
+            report(&ctx.on_error, TopicError::WorkerRestarted { restarts });
+        }
+        if mqd != ctx.mqd {
+            unsafe { libc::mq_close(mqd) };
+        }
+    }
+
+    /// Start receiving on `fresh` instead of `old`, closing `old` unless
+    /// it is the topic's own descriptor.
+    #[cfg(feature = "callbacks")]
+    fn swap_rx(ctx: &WorkerCtx, old: mqd_t, fresh: mqd_t) -> mqd_t {
+        ctx.health.rx_mqd.store(fresh, Ordering::Relaxed);
+        if old != ctx.mqd {
+            unsafe { libc::mq_close(old) };
+        }
+        fresh
+    }
+
+    /// Open the topic's queue by name again, waiting for it to be
+    /// recreated if needed. `None` once the topic stops.
+    #[cfg(feature = "callbacks")]
+    fn reopen_queue(ctx: &WorkerCtx) -> Option<mqd_t> {
+        let mut lost = false;
+        while ctx.running.load(Ordering::Relaxed) && !shutdown::requested() {
+            match open_queue(&ctx.name, libc::O_RDWR, None) {
+                Ok(mqd) => return Some(mqd),
+                Err(_) if !lost => {
+                    lost = true;
+                    report(&ctx.on_error, TopicError::QueueLost);
                 }
+                Err(_) => {}
+            }
+            thread::sleep(REOPEN_CHECK_INTERVAL);
+        }
+        None
+    }
+
+    /// One run of the receive loop on descriptor `mqd`.
+    #[cfg(feature = "callbacks")]
+    fn receive(ctx: &WorkerCtx, mqd: mqd_t) -> WorkerExit {
+        let WorkerCtx {
+            subs,
+            depth,
+            running,
+            dlq,
+            budget,
+            on_error,
+            dedup,
+            reorder,
+            urgent_first,
+            ttl,
+            traffic,
+            suspension,
+            sessions,
+            ..
+        } = ctx;
+        let (budget, urgent_first) = (*budget, *urgent_first);
+        let mut next_check = ctx.reopen.then(|| Instant::now() + REOPEN_CHECK_INTERVAL);
+        let mut buf = RecvBuf::new();
+        let mut reorder = reorder.map(reorder::Reorderer::new);
+        let mut ready = Vec::new();
+
+        let dispatch = |(msg, prio): reorder::Received| {
+            let current = subs.load();
+            let _trace = trace::ContextGuard::enter(
+                msg.ext()
+                    .and_then(|ext| trace::TraceContext::from_ext(&ext)),
+            );
+            let _prio = PriorityGuard::enter(prio);
+            sessions.observe(&msg);
 
-                let current = subs.load();
+            if let Some(dlq) = dlq {
+                if msg.hdr.len as usize > MSG_PAYLOAD_SIZE {
+                    dlq.send(dlq::DeadLetterReason::Oversize, &msg);
+                    return;
+                }
+                if current.cbs.is_empty() {
+                    dlq.send(dlq::DeadLetterReason::Unhandled, &msg);
+                    return;
+                }
+            }
+
+            // Fetched once here: a segment can only be taken once.
+            let _large = large::DispatchGuard::enter(&msg);
+            for (index, sub) in current.cbs.iter().enumerate() {
+                let started = Instant::now();
+                // Panics are only contained when there is a DLQ to
+                // report them to; otherwise they take the worker down.
+                let ok = match dlq {
+                    Some(_) => panic::catch_unwind(AssertUnwindSafe(|| (sub.cb)(msg))).is_ok(),
+                    None => {
+                        (sub.cb)(msg);
+                        true
+                    }
+                };
+                let elapsed = started.elapsed();
+
+                if sub.counters.record(elapsed, budget) {
+                    report(on_error, TopicError::CallbackOverBudget { index, elapsed });
+                }
+                if !ok && let Some(dlq) = dlq {
+                    dlq.send(dlq::DeadLetterReason::Panic, &msg);
+                }
+            }
+        };
+
+        loop {
+            if !suspension.wait(running) {
+                return WorkerExit::Stopped;
+            }
+            if let Some(at) = next_check
+                && Instant::now() >= at
+            {
+                if queue_moved(&ctx.name, mqd) {
+                    return WorkerExit::Moved;
+                }
+                next_check = Some(Instant::now() + REOPEN_CHECK_INTERVAL);
+            }
+
+            let mut prio: u32 = 0;
+            // Messages held for reordering, and the reopen check, bound
+            // how long we may block.
+            let deadline = match (reorder.as_ref().and_then(|r| r.deadline()), next_check) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            let ret = unsafe {
+                match deadline {
+                    None => {
+                        libc::mq_receive(mqd, buf.as_mut_ptr(), RecvBuf::LEN, &mut prio as *mut u32)
+                    }
+                    Some(at) => libc::mq_timedreceive(
+                        mqd,
+                        buf.as_mut_ptr(),
+                        RecvBuf::LEN,
+                        &mut prio as *mut u32,
+                        &realtime_after(at.saturating_duration_since(Instant::now())),
+                    ),
+                }
+            };
+
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                if let Some(code) = err.raw_os_error() {
+                    match code {
+                        libc::EINTR => {
+                            // sinal interrompeu; se já mandaram parar, sai
+                            if !running.load(Ordering::Relaxed) || shutdown::requested() {
+                                return WorkerExit::Stopped;
+                            }
+                            continue;
+                        }
+                        libc::EBADF => {
+                            // fila foi fechada: hora de sair
+                            if running.load(Ordering::Relaxed) {
+                                return WorkerExit::Failed(err);
+                            }
+                            return WorkerExit::Stopped;
+                        }
+                        libc::ETIMEDOUT => {
+                            if let Some(r) = &mut reorder {
+                                r.expire(Instant::now(), &mut ready);
+                                ready.drain(..).for_each(dispatch);
+                            }
+                            continue;
+                        }
+                        _ => {
+                            eprintln!("mq_receive error: {err}");
+                            if !running.load(Ordering::Relaxed) {
+                                return WorkerExit::Stopped;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                return WorkerExit::Stopped;
+            }
+
+            let msg = match buf.msg(ret as usize) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    eprintln!("mq_receive on {}: {err}", ctx.name);
+                    continue;
+                }
+            };
+
+            if msg.hdr.msg_type == MSG_TYPE_SHUTDOWN && !running.load(Ordering::Relaxed) {
+                return WorkerExit::Stopped;
+            }
+            // Suspended while blocked in the receive: hold this one back.
+            if !suspension.wait(running) {
+                return WorkerExit::Stopped;
+            }
+
+            // Depth as it was before this receive took its message.
+            if let Ok(attr) = queue_attr(mqd) {
+                depth.record(attr.mq_curmsgs as u64 + 1);
+            }
+
+            if let Some(cache) = dedup
+                && let Some(key) = dedup::key_of(&msg)
+                && !cache.lock().unwrap_or_else(|e| e.into_inner()).insert(key)
+            {
+                continue;
+            }
+
+            if expired(*ttl, &msg) {
+                traffic.expired.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            match &mut reorder {
+                Some(r) if urgent_first && prio >= URGENT_PRIORITY => {
+                    dispatch((msg, prio));
+                    r.push_dispatched(&msg, Instant::now(), &mut ready);
+                    ready.drain(..).for_each(dispatch);
+                }
+                Some(r) => {
+                    r.push((msg, prio), Instant::now(), &mut ready);
+                    ready.drain(..).for_each(dispatch);
+                }
+                None => dispatch((msg, prio)),
+            }
+            ctx.health
+                .cpu_ns
+                .store(thread_cpu_time().as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Register a callback to be invoked whenever a message arrives.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe<F>(&self, f: F)
+    where
+        F: Fn(Msg) + Send + Sync + 'static,
+    {
+        self.subscribe_with_priority(0, f);
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but `f` runs before every
+    /// callback of lower `priority` on each message, e.g. a safety monitor
+    /// ahead of a logger. Callbacks of equal priority run in subscription
+    /// order; [`subscribe`](Self::subscribe) uses priority 0.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_with_priority<F>(&self, priority: i32, f: F)
+    where
+        F: Fn(Msg) + Send + Sync + 'static,
+    {
+        self.add_subscription(priority, Arc::new(f));
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn add_subscription(&self, priority: i32, cb: Callback) {
+        let sub = Subscription {
+            cb,
+            priority,
+            counters: Arc::new(stats::CallbackCounters::default()),
+        };
+        self.sub_reg
+            .get_or_init(|| registry::register(&self.name, registry::Role::Subscriber).ok());
+
+        loop {
+            let current = self.subs.load_full();
+
+            let mut new_vec = current.cbs.clone();
+            let at = new_vec.partition_point(|s| s.priority >= priority);
+            new_vec.insert(at, sub.clone());
+
+            let new_list = Arc::new(SubscriberList { cbs: new_vec });
+
+            match self.subs.compare_and_swap(&current, new_list) {
+                old if Arc::ptr_eq(&old, &current) => {
+                    break;
+                }
+                _ => {
+                    continue;
+                }
+            }
+        }
+        self.ensure_worker();
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn remove_subscription(&self, cb: &Callback) {
+        loop {
+            let current = self.subs.load_full();
+
+            let mut new_vec = current.cbs.clone();
+            new_vec.retain(|s| !Arc::ptr_eq(&s.cb, cb));
+
+            let new_list = Arc::new(SubscriberList { cbs: new_vec });
+            let old = self.subs.compare_and_swap(&current, new_list);
+            if Arc::ptr_eq(&old, &current) {
+                break;
+            }
+        }
+    }
+
+    /// Block until a message for which `pred` returns true is received,
+    /// e.g. a status reporting "enabled" during a startup handshake, and
+    /// return it. Messages already queued count, as for any new
+    /// subscriber. Other callbacks still see every message, the matching
+    /// one included.
+    ///
+    /// The predicate runs on the worker thread as a temporary subscriber
+    /// that is removed again before this returns.
+    #[cfg(feature = "callbacks")]
+    pub fn wait_for<F>(&self, pred: F, timeout: Duration) -> Result<Msg, Timeout>
+    where
+        F: Fn(&Msg) -> bool + Send + Sync + 'static,
+    {
+        self.wait_with(timeout, move |tx| {
+            Arc::new(move |msg: Msg| {
+                if pred(&msg) {
+                    let _ = tx.try_send(msg);
+                }
+            })
+        })
+    }
+
+    /// Subscribe the callback built by `watch` until it hands a value to
+    /// its sender or `timeout` expires.
+    #[cfg(feature = "callbacks")]
+    fn wait_with<R, W>(&self, timeout: Duration, watch: W) -> Result<R, Timeout>
+    where
+        R: Send + 'static,
+        W: FnOnce(mpsc::SyncSender<R>) -> Callback,
+    {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let cb = watch(tx);
+        self.add_subscription(0, Arc::clone(&cb));
+        let res = rx
+            .recv_timeout(timeout)
+            .map_err(|_| Timeout { waited: timeout });
+        self.remove_subscription(&cb);
+        res
+    }
+
+    /// Keep the last `capacity` messages in a [`history::Lookback`] that
+    /// can be queried at any time, for code that joins late and needs a
+    /// short window of the past.
+    #[cfg(feature = "callbacks")]
+    pub fn lookback(&self, capacity: usize) -> history::Lookback<Msg> {
+        let lookback = history::Lookback::new(capacity);
+        let ring = lookback.clone();
+        self.subscribe(move |msg| ring.record(msg));
+        lookback
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but only every `n`th message
+    /// reaches `f`; the others are dropped in the worker. `n = 0` counts
+    /// as 1.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_decimated<F>(&self, n: u64, f: F)
+    where
+        F: Fn(Msg) + Send + Sync + 'static,
+    {
+        let n = n.max(1);
+        let seen = AtomicU64::new(0);
+        self.subscribe(move |msg: Msg| {
+            if seen.fetch_add(1, Ordering::Relaxed).is_multiple_of(n) {
+                f(msg);
+            }
+        });
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but at most `max_hz` messages
+    /// per second reach `f`: a message arriving less than `1 / max_hz`
+    /// after the last delivered one is dropped in the worker.
+    ///
+    /// # Panics
+    ///
+    /// If `max_hz` is not positive.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_throttled<F>(&self, max_hz: f64, f: F)
+    where
+        F: Fn(Msg) + Send + Sync + 'static,
+    {
+        assert!(max_hz > 0.0, "max_hz must be positive");
+        let period = Duration::from_secs_f64(1.0 / max_hz);
+        let last: Mutex<Option<Instant>> = Mutex::new(None);
+        self.subscribe(move |msg: Msg| {
+            let now = Instant::now();
+            {
+                let mut last = last.lock().unwrap_or_else(|e| e.into_inner());
+                if last.is_some_and(|at| now - at < period) {
+                    return;
+                }
+                *last = Some(now);
+            }
+            f(msg);
+        });
+    }
+
+    /// Take the next message off the queue without waiting, the polling
+    /// alternative to [`subscribe`](Self::subscribe). Mixing both on one
+    /// handle splits the messages between the worker and the caller.
+    ///
+    /// Messages older than [`TopicOptions::ttl`] are skipped; `dedup` and
+    /// `reorder` only apply to callbacks. After a [`shutdown`] request the
+    /// queue is still drained, but an empty one fails with the shutdown
+    /// error ([`shutdown::is_shutdown`]) rather than returning `None`.
+    pub fn try_recv(&self) -> io::Result<Option<Msg>> {
+        self.poll(None)
+    }
+
+    /// Like [`try_recv`](Self::try_recv), but wait up to `timeout` for a
+    /// message to arrive.
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<Msg>> {
+        self.poll(Some(Instant::now() + timeout))
+    }
+
+    fn poll(&self, deadline: Option<Instant>) -> io::Result<Option<Msg>> {
+        self.sub_reg
+            .get_or_init(|| registry::register(&self.name, registry::Role::Subscriber).ok());
+
+        let mut buf = RecvBuf::new();
+        loop {
+            // Wait in slices so a signal to another thread is noticed.
+            let at = match deadline {
+                Some(at) => realtime_after(
+                    at.saturating_duration_since(Instant::now())
+                        .min(SHUTDOWN_CHECK_INTERVAL),
+                ),
+                None => realtime_now(),
+            };
+            let ret = unsafe {
+                libc::mq_timedreceive(
+                    self.mqd,
+                    buf.as_mut_ptr(),
+                    RecvBuf::LEN,
+                    std::ptr::null_mut(),
+                    &at,
+                )
+            };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::ETIMEDOUT | libc::EAGAIN | libc::EINTR) if shutdown::requested() => {
+                        return Err(shutdown::error());
+                    }
+                    Some(libc::EINTR) => continue,
+                    Some(libc::ETIMEDOUT | libc::EAGAIN)
+                        if deadline.is_some_and(|at| Instant::now() < at) =>
+                    {
+                        continue;
+                    }
+                    Some(libc::ETIMEDOUT | libc::EAGAIN) => return Ok(None),
+                    _ => return Err(err),
+                }
+            }
+
+            let msg = buf.msg(ret as usize)?;
+            // Wake-ups left behind by dropped handles.
+            if msg.hdr.msg_type == MSG_TYPE_SHUTDOWN {
+                continue;
+            }
+            if let Ok(attr) = queue_attr(self.mqd) {
+                self.depth.record(attr.mq_curmsgs as u64 + 1);
+            }
+            if expired(self.ttl, &msg) {
+                self.traffic.expired.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            self.sessions.observe(&msg);
+            return Ok(Some(msg));
+        }
+    }
+
+    /// Publish a raw message to this topic with a given priority.
+    pub fn publish(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        match self.publish_confirmed(msg, prio)? {
+            Delivery::Dropped => Err(io::Error::from_raw_os_error(libc::EAGAIN)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like [`publish`](Self::publish), but report what the topic's
+    /// [`Qos`] did with the message instead of failing when it was dropped
+    /// by policy. Errors are left for real failures.
+    ///
+    /// For application-level flow control on a best-effort topic, pair it
+    /// with [`wait_writable`](Self::wait_writable).
+    pub fn publish_confirmed(&self, msg: &Msg, prio: u32) -> io::Result<Delivery> {
+        self.pub_reg
+            .get_or_init(|| registry::register(&self.name, registry::Role::Publisher).ok());
+
+        if self.owner.is_none() {
+            self.check_owner()?;
+        }
+        if msg.data().len() > self.max_payload {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("payload exceeds {} bytes", self.max_payload),
+            ));
+        }
+        if self.schema_version.is_some() && msg.data().len() > ext::EXT_PAYLOAD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("versioned payload exceeds {} bytes", ext::EXT_PAYLOAD_SIZE),
+            ));
+        }
+
+        let msg = self.decorate(msg);
+        if let Some(journal) = &self.journal {
+            journal.append(&msg, prio)?;
+        }
+        let res = self.deliver(&msg, prio);
+        self.traffic
+            .record(matches!(res, Ok(delivery) if delivery.is_queued()));
+        res
+    }
+
+    /// Publish `msg` at [`URGENT_PRIORITY`], e.g. an e-stop that must
+    /// overtake whatever telemetry is already queued.
+    pub fn publish_urgent(&self, msg: &Msg) -> io::Result<()> {
+        self.publish(msg, URGENT_PRIORITY)
+    }
+
+    /// Publish `msg` with an explicit idempotency key, e.g. one derived
+    /// from a command ID so retries by the application are suppressed too.
+    pub fn publish_with_key(&self, msg: &Msg, prio: u32, key: u64) -> io::Result<()> {
+        let mut ext = msg.ext().unwrap_or_default();
+        dedup::write_key(&mut ext, key);
+        let keyed = msg.attach_ext(&ext).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("keyed payload exceeds {} bytes", ext::EXT_PAYLOAD_SIZE),
+            )
+        })?;
+        self.publish(&keyed, prio)
+    }
+
+    fn check_owner(&self) -> io::Result<()> {
+        let mut cached = self.owner_check.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((at, verdict)) = &*cached
+            && at.elapsed() < OWNER_RECHECK
+        {
+            return verdict.clone().map_err(registry::ExclusiveOwner::into_io);
+        }
+        let res = registry::check_owner(&self.name);
+        // A refused publisher that keeps retrying must not read the
+        // registry each time either; I/O errors are not remembered.
+        let verdict = match &res {
+            Ok(()) => Ok(()),
+            Err(err) => match err
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<registry::ExclusiveOwner>())
+            {
+                Some(owner) => Err(owner.clone()),
+                None => return res,
+            },
+        };
+        *cached = Some((Instant::now(), verdict));
+        res
+    }
+
+    /// Fill in the extended-header fields this topic is configured to add.
+    /// Messages that have no room for the header go out unchanged.
+    fn decorate<'a>(&self, msg: &'a Msg) -> Cow<'a, Msg> {
+        let trace = if self.propagate_trace {
+            trace::current()
+        } else {
+            None
+        };
+        if trace.is_none()
+            && self.schema_version.is_none()
+            && self.keys.is_none()
+            && self.sequencer.is_none()
+            && !self.timestamped
+            && !self.session
+        {
+            return Cow::Borrowed(msg);
+        }
+
+        let mut ext = msg.ext().unwrap_or_default();
+        if let Some(trace) = trace {
+            trace.write_ext(&mut ext);
+        }
+        if let Some(version) = self.schema_version {
+            ext.present |= ext::EXT_SCHEMA;
+            ext.schema = version;
+        }
+        if let Some(keys) = &self.keys
+            && !ext.has(ext::EXT_IDEMPOTENCY)
+        {
+            dedup::write_key(&mut ext, keys.next());
+        }
+        if let Some(sequencer) = &self.sequencer {
+            sequencer.stamp(&mut ext);
+        }
+        if self.timestamped {
+            ext.present |= ext::EXT_TIMESTAMP;
+            ext.sent_ns = clock::default_clock().now().as_nanos() as u64;
+        }
+        // Relayed messages keep the session of their original publisher.
+        if self.session && !ext.has(ext::EXT_SESSION) {
+            session::stamp(&mut ext);
+        }
+        match msg.attach_ext(&ext) {
+            Some(out) => Cow::Owned(out),
+            None => Cow::Borrowed(msg),
+        }
+    }
+
+    /// Re-publish journaled messages stamped at or after `since`, without
+    /// journaling them again. Returns how many were sent.
+    ///
+    /// Typically called once at startup by a restarted publisher.
+    pub fn replay_journal(&self, since: SystemTime) -> io::Result<usize> {
+        let Some(journal) = &self.journal else {
+            return Ok(0);
+        };
+
+        let mut sent = 0;
+        for entry in journal.entries()? {
+            if entry.timestamp >= since {
+                self.send(&entry.msg, entry.prio)?;
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// The token bucket set via [`TopicOptions::shape`], if any.
+    pub fn shaper(&self) -> Option<&shape::Shaper> {
+        self.shaper.as_ref()
+    }
+
+    /// The journal attached via [`TopicOptions::journal`], if any.
+    pub fn journal(&self) -> Option<&journal::Journal> {
+        self.journal.as_ref()
+    }
+
+    fn send(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        match self.deliver(msg, prio)? {
+            Delivery::Dropped => Err(io::Error::from_raw_os_error(libc::EAGAIN)),
+            _ => Ok(()),
+        }
+    }
+
+    fn deliver(&self, msg: &Msg, prio: u32) -> io::Result<Delivery> {
+        if let Some(shaper) = &self.shaper {
+            shaper.acquire();
+        }
+        if self.conflate {
+            let evicted = self.publish_conflated(msg, prio)?;
+            return Ok(match evicted {
+                0 => Delivery::Queued,
+                n => Delivery::Evicted(n),
+            });
+        }
+        match self.retry.run(|| self.send_once(msg, prio)) {
+            Ok(()) => Ok(Delivery::Queued),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(Delivery::Dropped),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn send_once(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        let data_ptr = msg as *const Msg as *const c_char;
+        let len = std::mem::size_of::<Msg>();
+        let rc = if self.nonblocking {
+            // The descriptor is shared with the (blocking) worker, so use
+            // an already-expired deadline rather than O_NONBLOCK.
+            let now = realtime_now();
+            unsafe { libc::mq_timedsend(self.mqd, data_ptr, len, prio, &now) }
+        } else {
+            unsafe { libc::mq_send(self.mqd, data_ptr, len, prio) }
+        };
+        if rc == -1 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ETIMEDOUT) {
+                return Err(io::Error::from_raw_os_error(libc::EAGAIN));
+            }
+            Err(err)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Drain-one-then-send: never blocks on a full queue, the oldest
+    /// queued message is dropped to make room instead. Returns how many
+    /// were dropped.
+    fn publish_conflated(&self, msg: &Msg, prio: u32) -> io::Result<usize> {
+        let data_ptr = msg as *const Msg as *const c_char;
+        let len = std::mem::size_of::<Msg>();
+        let mut scratch = [0u8; std::mem::size_of::<Msg>()];
+        let mut evicted = 0;
+
+        loop {
+            // An absolute timeout of "now" turns both calls non-blocking.
+            let now = realtime_now();
+            let rc = unsafe { libc::mq_timedsend(self.mqd, data_ptr, len, prio, &now) };
+            if rc == 0 {
+                return Ok(evicted);
+            }
+
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::ETIMEDOUT) | Some(libc::EAGAIN) => {}
+                Some(libc::EINTR) => continue,
+                _ => return Err(err),
+            }
+
+            let now = realtime_now();
+            let rc = unsafe {
+                libc::mq_timedreceive(
+                    self.mqd,
+                    scratch.as_mut_ptr() as *mut c_char,
+                    scratch.len(),
+                    std::ptr::null_mut(),
+                    &now,
+                )
+            };
+            if rc != -1 {
+                self.traffic.dropped.fetch_add(1, Ordering::Relaxed);
+                evicted += 1;
+            } else {
+                let err = io::Error::last_os_error();
+                // Someone else drained it first (ETIMEDOUT): just retry.
+                if !matches!(
+                    err.raw_os_error(),
+                    Some(libc::ETIMEDOUT) | Some(libc::EAGAIN) | Some(libc::EINTR)
+                ) {
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Wait until the queue has room for a message, or until `timeout`
+    /// elapses (`None` waits indefinitely). Returns whether there is room.
+    ///
+    /// Room can be taken by another publisher before the next publish;
+    /// this is for pacing, not a reservation. Async code can instead wait
+    /// for the [raw descriptor](std::os::fd::AsRawFd) to become writable
+    /// with its runtime's reactor, e.g. tokio's `AsyncFd`.
+    pub fn wait_writable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let mut pfd = libc::pollfd {
+                fd: self.mqd,
+                events: libc::POLLOUT,
+                revents: 0,
+            };
+            let ms = deadline.map_or(-1, |at| {
+                let left = at.saturating_duration_since(Instant::now());
+                left.as_millis().min(c_int::MAX as u128) as c_int
+            });
+            match unsafe { libc::poll(&mut pfd, 1, ms) } {
+                -1 => {
+                    let err = io::Error::last_os_error();
+                    if err.raw_os_error() != Some(libc::EINTR) {
+                        return Err(err);
+                    }
+                }
+                0 => return Ok(false),
+                _ => return Ok(pfd.revents & libc::POLLOUT != 0),
+            }
+        }
+    }
+
+    /// Number of live subscribers on this topic across all processes,
+    /// as recorded in the discovery [`registry`].
+    pub fn matched_subscribers(&self) -> io::Result<usize> {
+        registry::subscriber_count(&self.name)
+    }
+
+    /// Block until at least `n` subscribers are matched or `timeout` elapses.
+    /// Returns whether the count was reached.
+    pub fn wait_for_subscribers(&self, n: usize, timeout: Duration) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.matched_subscribers()? >= n {
+                return Ok(true);
+            }
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Invoke `f` with the matched subscriber count now and whenever it
+    /// changes afterwards. The registry is polled from a helper thread that
+    /// lives as long as this topic.
+    #[cfg(feature = "callbacks")]
+    pub fn on_matched<F>(&self, f: F)
+    where
+        F: Fn(usize) + Send + 'static,
+    {
+        let name = self.name.clone();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = Arc::clone(&running);
+
+        let handle = thread::spawn(move || {
+            let mut last = None;
+            while running_clone.load(Ordering::Relaxed) {
+                if let Ok(count) = registry::subscriber_count(&name)
+                    && last != Some(count)
+                {
+                    last = Some(count);
+                    f(count);
+                }
+                thread::sleep(HELPER_POLL_INTERVAL);
+            }
+        });
+
+        self.helpers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((running, handle));
+    }
+
+    fn spawn_depth_reporter(&self, interval: Duration) {
+        let mqd = self.mqd;
+        let name = self.name.clone();
+        let depth = Arc::clone(&self.depth);
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = Arc::clone(&running);
+
+        let handle = thread::spawn(move || {
+            // Reports are best effort: nobody listening must not stall us.
+            let opts = TopicOptions::default().nonblocking(true);
+            let out: Topic<stats::DepthReport> =
+                match Topic::with_options(stats::DEPTH_REPORT_TOPIC, &opts) {
+                    Ok(out) => out,
+                    Err(err) => {
+                        eprintln!("depth report topic: {err}");
+                        return;
+                    }
+                };
+
+            let mut next = Instant::now() + interval;
+            while running_clone.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                if now < next {
+                    thread::sleep((next - now).min(HELPER_POLL_INTERVAL));
+                    continue;
+                }
+                next += interval;
+
+                if let Ok(attr) = queue_attr(mqd) {
+                    let snap = depth.snapshot(attr.mq_maxmsg as u64, attr.mq_curmsgs as u64);
+                    let _ = out.publish(&stats::DepthReport::new(&name, &snap), 0, 0);
+                }
+            }
+        });
+
+        self.helpers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((running, handle));
+    }
+
+    fn spawn_stats_reporter(&self, interval: Duration) {
+        let source = self.stats_source();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = Arc::clone(&running);
+
+        let handle = thread::spawn(move || {
+            let opts = TopicOptions::default().nonblocking(true);
+            let out: Topic<stats::StatsReport> =
+                match Topic::with_options(stats::STATS_TOPIC, &opts) {
+                    Ok(out) => out,
+                    Err(err) => {
+                        eprintln!("stats topic: {err}");
+                        return;
+                    }
+                };
+
+            let mut prev = source.snapshot();
+            let mut prev_at = Instant::now();
+            let mut next = prev_at + interval;
+            while running_clone.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                if now < next {
+                    thread::sleep((next - now).min(HELPER_POLL_INTERVAL));
+                    continue;
+                }
+                next += interval;
+
+                let snap = source.snapshot();
+                let report = stats::StatsReport::new(&snap, &prev, now - prev_at);
+                let _ = out.publish(&report, 0, 0);
+                (prev, prev_at) = (snap, now);
+            }
+        });
+
+        self.helpers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((running, handle));
+    }
+
+    /// Register a callback for runtime problems such as slow subscribers.
+    /// Replaces any previously registered one.
+    #[cfg(feature = "callbacks")]
+    pub fn on_error<F>(&self, f: F)
+    where
+        F: Fn(&TopicError) + Send + Sync + 'static,
+    {
+        self.on_error.store(Some(Arc::new(Box::new(f))));
+    }
+
+    /// Invoke `f` when a message arrives from a publisher session this
+    /// handle has not seen before, i.e. the publisher restarted. Runs
+    /// before the message is handed to the subscribers (or returned by
+    /// [`MqTopic::try_recv`]). Only publishers with
+    /// [`TopicOptions::session`] are tracked; replaces any previous callback.
+    pub fn on_publisher_restart<F>(&self, f: F)
+    where
+        F: Fn(session::PublisherRestart) + Send + Sync + 'static,
+    {
+        self.sessions.set_callback(Box::new(f));
+    }
+
+    /// Whether this handle created the queue, as opposed to opening one
+    /// that already existed.
+    pub fn created(&self) -> bool {
+        self.created
+    }
+
+    /// Whether this handle's worker (if any) is receiving. Turns false
+    /// when it dies and, with [`TopicOptions::supervise`], back to true
+    /// once it has been restarted.
+    pub fn is_healthy(&self) -> bool {
+        self.health.healthy.load(Ordering::Relaxed)
+    }
+
+    /// How often a supervised worker has been restarted.
+    pub fn worker_restarts(&self) -> u32 {
+        self.health.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Stop this handle's worker from taking messages off the queue, so
+    /// the kernel queue absorbs what arrives, e.g. while a pipeline is
+    /// reconfigured. A message the worker was already waiting for when
+    /// the call came is held back and delivered first after
+    /// [`resume`](Self::resume). Publishing is not affected.
+    #[cfg(feature = "callbacks")]
+    pub fn suspend(&self) {
+        self.suspension.suspended.store(true, Ordering::Release);
+    }
+
+    /// Let a [`suspend`](Self::suspend)ed worker drain the queue again.
+    #[cfg(feature = "callbacks")]
+    pub fn resume(&self) {
+        self.suspension.resume();
+    }
+
+    #[cfg(feature = "callbacks")]
+    pub fn is_suspended(&self) -> bool {
+        self.suspension.suspended.load(Ordering::Acquire)
+    }
+
+    /// Snapshot of this topic's runtime statistics.
+    pub fn stats(&self) -> stats::TopicStats {
+        self.stats_source().snapshot()
+    }
+
+    fn register_introspection(&self) -> u64 {
+        let source = self.stats_source();
+        let health = Arc::clone(&self.health);
+        introspect::register_topic(move || introspect::TopicDump {
+            queue: queue_attr(source.mqd).ok().map(Into::into),
+            stats: source.snapshot(),
+            healthy: health.healthy.load(Ordering::Relaxed),
+            restarts: health.restarts.load(Ordering::Relaxed),
+            latest: Vec::new(),
+        })
+    }
+
+    fn stats_source(&self) -> StatsSource {
+        StatsSource {
+            name: self.name.clone(),
+            mqd: self.mqd,
+            #[cfg(feature = "callbacks")]
+            subs: Arc::clone(&self.subs),
+            depth: Arc::clone(&self.depth),
+            traffic: Arc::clone(&self.traffic),
+            health: Arc::clone(&self.health),
+        }
+    }
+
+    /// Get the POSIX mqueue name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the raw mqd_t for advanced usage.
+    pub fn raw_mqd(&self) -> mqd_t {
+        self.mqd
+    }
+}
+
+/// The queue descriptor, for waiting on it with `poll(2)` or an async
+/// reactor. Reading or writing through it bypasses the topic.
+impl std::os::fd::AsRawFd for MqTopic {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.mqd
+    }
+}
+
+impl Drop for MqTopic {
+    fn drop(&mut self) {
+        introspect::unregister(self.introspect_id);
+        #[cfg(feature = "callbacks")]
+        let mut worker = self.worker.take();
+        #[cfg(feature = "callbacks")]
+        if let Some(w) = &worker {
+            unregister_worker(w.id);
+        }
+        #[cfg(feature = "callbacks")]
+        self.running.store(false, Ordering::Relaxed);
+
+        let helpers = std::mem::take(self.helpers.get_mut().unwrap_or_else(|e| e.into_inner()));
+        for (running, handle) in helpers {
+            running.store(false, Ordering::Relaxed);
+            let _ = handle.join();
+        }
+
+        // A worker that already left (e.g. after a process-wide shutdown)
+        // must not leave a stray wake-up message behind in the queue.
+        #[cfg(feature = "callbacks")]
+        let handle = worker.as_mut().and_then(|w| w.handle.take());
+        #[cfg(feature = "callbacks")]
+        if handle.as_ref().is_some_and(|h| !h.is_finished()) && !self.suspension.release_parked() {
+            send_shutdown(self.health.rx_mqd.load(Ordering::Relaxed));
+        }
+
+        unsafe {
+            libc::mq_close(self.mqd);
+        }
+
+        #[cfg(feature = "callbacks")]
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+
+        let unlink = match self.unlink {
+            UnlinkPolicy::Never => false,
+            UnlinkPolicy::Creator => self.created,
+            UnlinkPolicy::LastUser => {
+                self.sub_reg.take();
+                self.pub_reg.take();
+                registry::endpoints(&self.name).is_ok_and(|eps| eps.is_empty())
+            }
+        };
+        if unlink {
+            let _ = cleanup::unlink_resolved(&self.name);
+        }
+    }
+}
+
+/// Whether `msg` was sent longer than `ttl` ago.
+fn expired(ttl: Option<Duration>, msg: &Msg) -> bool {
+    ttl.is_some_and(|ttl| {
+        msg.sent_at()
+            .is_some_and(|sent| clock::default_clock().now().saturating_sub(sent) > ttl)
+    })
+}
+
+/// Open `name`, creating it if needed. Also reports whether this call
+/// created it.
+fn create_queue(name: &str, maxmsg: c_long) -> io::Result<(mqd_t, bool)> {
+    let exclusive = libc::O_CREAT | libc::O_EXCL | libc::O_RDWR;
+    match open_queue(name, exclusive, Some(maxmsg)) {
+        Ok(mqd) => Ok((mqd, true)),
+        // O_CREAT again in case it was unlinked in between.
+        Err(err) if err.raw_os_error() == Some(libc::EEXIST) => {
+            open_queue(name, libc::O_CREAT | libc::O_RDWR, Some(maxmsg)).map(|mqd| (mqd, false))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether `name` no longer refers to the queue open as `mqd`: it was
+/// unlinked, or unlinked and created again.
+#[cfg(feature = "callbacks")]
+fn queue_moved(name: &str, mqd: mqd_t) -> bool {
+    let current = match open_queue(name, libc::O_RDONLY | libc::O_NONBLOCK, None) {
+        Ok(current) => current,
+        Err(err) => return err.raw_os_error() == Some(libc::ENOENT),
+    };
+    let inode = |fd: mqd_t| {
+        let mut st: libc::stat = unsafe { std::mem::zeroed() };
+        (unsafe { libc::fstat(fd, &mut st) } == 0).then_some(st.st_ino)
+    };
+    let moved = inode(mqd) != inode(current);
+    unsafe { libc::mq_close(current) };
+    moved
+}
+
+pub(crate) fn queue_attr(mqd: mqd_t) -> io::Result<libc::mq_attr> {
+    let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
+    if unsafe { libc::mq_getattr(mqd, &mut attr) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(attr)
+}
+
+/// CPU time used by the calling thread.
+#[cfg(feature = "callbacks")]
+fn thread_cpu_time() -> Duration {
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts);
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+pub(crate) fn realtime_now() -> libc::timespec {
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_REALTIME, &mut ts);
+    }
+    ts
+}
+
+/// Absolute `CLOCK_REALTIME` deadline `d` from now, for `mq_timed*`.
+pub(crate) fn realtime_after(d: Duration) -> libc::timespec {
+    let at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        + d;
+    libc::timespec {
+        tv_sec: at.as_secs() as libc::time_t,
+        tv_nsec: at.subsec_nanos() as libc::c_long,
+    }
+}
+
+thread_local! {
+    static DISPATCH_PRIO: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Priority the message being handed to a subscriber callback on this
+/// thread was sent with; `None` outside callbacks.
+///
+/// Forwarders such as [`relay::Relay`] republish at this priority, so
+/// urgent messages keep overtaking telemetry after every hop.
+pub fn current_priority() -> Option<u32> {
+    DISPATCH_PRIO.with(|p| p.get())
+}
+
+#[cfg(feature = "callbacks")]
+struct PriorityGuard {
+    prev: Option<u32>,
+}
+
+#[cfg(feature = "callbacks")]
+impl PriorityGuard {
+    fn enter(prio: u32) -> Self {
+        PriorityGuard {
+            prev: DISPATCH_PRIO.with(|p| p.replace(Some(prio))),
+        }
+    }
+}
+
+#[cfg(feature = "callbacks")]
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        DISPATCH_PRIO.with(|p| p.set(self.prev));
+    }
+}
+
+/// Post the internal shutdown message that unblocks a worker sitting in
+/// `mq_receive`.
+#[cfg(feature = "callbacks")]
+fn send_shutdown(mqd: mqd_t) {
+    let shutdown = Msg::new(MSG_TYPE_SHUTDOWN, &[]);
+
+    unsafe {
+        let data_ptr = &shutdown as *const Msg as *const c_char;
+        let rc = libc::mq_send(mqd, data_ptr, std::mem::size_of::<Msg>(), 0);
+        if rc == -1 {
+            eprintln!("mq_send shutdown failed: {}", io::Error::last_os_error());
+        }
+    }
+}
+
+type WakeFn = Box<dyn Fn() + Send + 'static>;
+
+/// Every background worker alive in this process, so a process-wide
+/// shutdown can stop them all.
+static WORKERS: Mutex<Vec<(u64, WakeFn)>> = Mutex::new(Vec::new());
+#[cfg(feature = "callbacks")]
+static NEXT_WORKER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Register a worker; `wake` must make it leave its receive loop.
+#[cfg(feature = "callbacks")]
+pub(crate) fn register_worker<F>(wake: F) -> u64
+where
+    F: Fn() + Send + 'static,
+{
+    let id = NEXT_WORKER_ID.fetch_add(1, Ordering::Relaxed);
+    WORKERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, Box::new(wake)));
+    id
+}
+
+/// Forget a worker. Must be called before the resources used by its wake
+/// function (queue descriptors, pipes) are closed.
+#[cfg(feature = "callbacks")]
+pub(crate) fn unregister_worker(id: u64) {
+    WORKERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(wid, _)| *wid != id);
+}
+
+/// Ask every registered worker to stop.
+pub(crate) fn wake_all_workers() {
+    let workers = WORKERS.lock().unwrap_or_else(|e| e.into_inner());
+    for (_, wake) in workers.iter() {
+        wake();
+    }
+}
+
+/// Strongly-typed IPC topic built on top of `MqTopic`.
+///
+/// T must be Pod + Zeroable so it can be safely mapped to raw bytes.
+pub struct Topic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    inner: MqTopic,
+    validators: ArcSwap<Vec<Arc<Validator<T>>>>,
+    /// Values of a polled batch not handed out yet.
+    pending: Mutex<VecDeque<T>>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+type Validator<T> = dyn Fn(&T) -> Result<(), validate::ValidationError> + Send + Sync;
+
+impl<T> Topic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    /// Create or open a typed topic.
+    pub fn new(name: &str, maxmsg: c_long) -> io::Result<Self> {
+        Self::with_options(name, &TopicOptions::new(maxmsg))
+    }
+
+    /// Create or open a typed topic with explicit [`TopicOptions`].
+    pub fn with_options(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        Self::open_resolved(&defaults::topic_name(name), opts)
+    }
+
+    pub(crate) fn open_resolved(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        let inner = MqTopic::open_resolved(name, opts)?;
+        Ok(Self {
+            inner,
+            validators: ArcSwap::from_pointee(Vec::new()),
+            pending: Mutex::new(VecDeque::new()),
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Subscribe with a callback that receives `T` directly.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe<F>(&self, f: F)
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.inner.subscribe(self.decoder(f));
+    }
+
+    /// Typed [`MqTopic::subscribe_with_priority`].
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_with_priority<F>(&self, priority: i32, f: F)
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.inner
+            .subscribe_with_priority(priority, self.decoder(f));
+    }
+
+    /// Tap the undecoded messages next to any typed subscribers, e.g. to
+    /// log the exact wire bytes while chasing a layout mismatch. Sees
+    /// every message, including ones that would not decode as `T`.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_raw<F>(&self, f: F)
+    where
+        F: Fn(&Msg) + Send + Sync + 'static,
+    {
+        self.inner.subscribe(move |msg| f(&msg));
+    }
+
+    /// Typed [`MqTopic::lookback`]; a batch adds each of its values.
+    #[cfg(feature = "callbacks")]
+    pub fn lookback(&self, capacity: usize) -> history::Lookback<T> {
+        let lookback = history::Lookback::new(capacity);
+        let ring = lookback.clone();
+        self.subscribe(move |value| ring.record(value));
+        lookback
+    }
+
+    /// Typed [`MqTopic::subscribe_decimated`]. Messages are dropped before
+    /// they are decoded; a batch counts as one message.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_decimated<F>(&self, n: u64, f: F)
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.inner.subscribe_decimated(n, self.decoder(f));
+    }
+
+    /// Typed [`MqTopic::subscribe_throttled`]. Messages are dropped before
+    /// they are decoded; a batch counts as one message.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_throttled<F>(&self, max_hz: f64, f: F)
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.inner.subscribe_throttled(max_hz, self.decoder(f));
+    }
+
+    /// Wrap `f` into a raw callback that decodes `T`, resolving large and
+    /// batched messages and dead-lettering what does not decode.
+    #[cfg(feature = "callbacks")]
+    fn decoder<F>(&self, f: F) -> impl Fn(Msg) + Send + Sync + 'static
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.decoder_msg(move |_, value| f(value))
+    }
+
+    /// Like `decoder`, but `f` also sees the message each value came from.
+    #[cfg(feature = "callbacks")]
+    pub(crate) fn decoder_msg<F>(&self, f: F) -> impl Fn(Msg) + Send + Sync + 'static
+    where
+        F: Fn(&Msg, T) + Send + Sync + 'static,
+    {
+        let strict = self.inner.strict;
+        let dlq = self.inner.dlq.clone();
+        move |msg: Msg| Self::decode(&msg, strict, dlq.as_deref(), |value| f(&msg, value))
+    }
+
+    /// Hand every value `msg` carries to `f`, resolving large and batched
+    /// messages and dead-lettering what does not decode.
+    fn decode(msg: &Msg, strict: bool, dlq: Option<&dlq::DeadLetterQueue>, mut f: impl FnMut(T)) {
+        if let Some(r) = msg.large_ref() {
+            match large::load::<T>(&r, strict) {
+                Ok(value) => f(value),
+                Err(_) => {
+                    if let Some(dlq) = dlq {
+                        dlq.send(dlq::DeadLetterReason::Decode, msg);
+                    }
+                }
+            }
+            return;
+        }
+        if msg.hdr.flags & batch::FLAG_BATCH != 0 {
+            match batch::unbatch::<T>(msg) {
+                Some(values) => values.into_iter().for_each(&mut f),
+                None => {
+                    if let Some(dlq) = dlq {
+                        dlq.send(dlq::DeadLetterReason::Decode, msg);
+                    }
+                }
+            }
+            return;
+        }
+
+        let unpacked;
+        let data = if msg.hdr.flags & compress::FLAG_COMPRESSED != 0 {
+            match compress::decompress(msg.data()) {
+                Some(bytes) => {
+                    unpacked = bytes;
+                    &unpacked[..]
+                }
+                None => {
+                    if let Some(dlq) = dlq {
+                        dlq.send(dlq::DeadLetterReason::Decode, msg);
+                    }
+                    return;
+                }
+            }
+        } else {
+            msg.data()
+        };
+        if strict && data.len() != std::mem::size_of::<T>() {
+            if let Some(dlq) = dlq {
+                dlq.send(dlq::DeadLetterReason::Decode, msg);
+            }
+            return;
+        }
+
+        // Short payloads leave the rest of the value zeroed.
+        let mut value = T::zeroed();
+        let buf = bytemuck::bytes_of_mut(&mut value);
+        let n = std::cmp::min(data.len(), buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        f(value);
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but `f` borrows the value in
+    /// place from the received message instead of getting its own copy,
+    /// which saves a memcpy per message for large `T`. The reference is
+    /// only valid for the duration of the call.
+    ///
+    /// Messages that do not hold a whole, suitably aligned `T` (short
+    /// payloads, batches, [`large`] references, compressed values) are
+    /// decoded into a temporary first, exactly as [`subscribe`](Self::subscribe) would.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_ref<F>(&self, f: F)
+    where
+        F: Fn(&T) + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        let fallback = {
+            let f = Arc::clone(&f);
+            self.decoder(move |value: T| f(&value))
+        };
+
+        self.inner.subscribe(move |msg: Msg| {
+            let data = msg.data();
+            let size = std::mem::size_of::<T>();
+            let wrapped = batch::FLAG_BATCH | compress::FLAG_COMPRESSED;
+            let plain = msg.large_ref().is_none() && msg.hdr.flags & wrapped == 0;
+            if plain
+                && data.len() == size
+                && let Ok(value) = bytemuck::try_from_bytes::<T>(data)
+            {
+                f(value);
+            } else {
+                fallback(msg);
+            }
+        });
+    }
 
-                for cb in &current.cbs {
-                    (cb)(msg);
+    /// Typed [`MqTopic::wait_for`]: block until a value for which `pred`
+    /// returns true is received and return it.
+    #[cfg(feature = "callbacks")]
+    pub fn wait_for<F>(&self, pred: F, timeout: Duration) -> Result<T, Timeout>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.inner.wait_with(timeout, |tx| {
+            Arc::new(self.decoder(move |value: T| {
+                if pred(&value) {
+                    let _ = tx.try_send(value);
                 }
-            }
+            }))
         })
     }
 
-    /// Register a callback to be invoked whenever a message arrives.
-    pub fn subscribe<F>(&self, f: F)
+    /// Like [`subscribe`](Self::subscribe), but messages published with an
+    /// older [`TopicOptions::schema_version`] are migrated to `T` first.
+    /// Messages `schema` cannot decode go to the dead-letter queue.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe_with_schema<F>(&self, schema: schema::Schema<T>, f: F)
     where
-        F: Fn(Msg) + Send + Sync + 'static,
+        F: Fn(T) + Send + Sync + 'static,
     {
-        let cb: Callback = Arc::new(f);
-
-        loop {
-            let current = self.subs.load_full();
-
-            let mut new_vec = current.cbs.clone();
-            new_vec.push(cb.clone());
+        let strict = self.inner.strict;
+        let dlq = self.inner.dlq.clone();
+
+        self.inner
+            .subscribe(move |msg: Msg| match schema.decode(&msg, strict) {
+                Some(value) => f(value),
+                None => {
+                    if let Some(dlq) = &dlq {
+                        dlq.send(dlq::DeadLetterReason::Decode, &msg);
+                    }
+                }
+            });
+    }
 
-            let new_list = Arc::new(SubscriberList { cbs: new_vec });
+    /// Typed [`MqTopic::try_recv`]. A batch is handed out one value per
+    /// call; messages that do not decode are dead-lettered and skipped.
+    pub fn try_recv(&self) -> io::Result<Option<T>> {
+        self.poll(None)
+    }
 
-            match self.subs.compare_and_swap(&current, new_list) {
-                old if Arc::ptr_eq(&old, &current) => {
-                    break;
-                }
-                _ => {
-                    continue;
-                }
-            }
-        }
+    /// Typed [`MqTopic::recv_timeout`].
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<T>> {
+        self.poll(Some(Instant::now() + timeout))
     }
 
-    /// Publish a raw message to this topic with a given priority.
-    pub fn publish(&self, msg: &Msg, prio: u32) -> io::Result<()> {
-        let data_ptr = msg as *const Msg as *const c_char;
-        let len = std::mem::size_of::<Msg>();
-        let rc = unsafe { libc::mq_send(self.mqd, data_ptr, len, prio) };
-        if rc == -1 {
-            Err(io::Error::last_os_error())
-        } else {
-            Ok(())
+    fn poll(&self, deadline: Option<Instant>) -> io::Result<Option<T>> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(value) = pending.pop_front() {
+                return Ok(Some(value));
+            }
+            let Some(msg) = self.inner.poll(deadline)? else {
+                return Ok(None);
+            };
+            let (strict, dlq) = (self.inner.strict, self.inner.dlq.as_deref());
+            Self::decode(&msg, strict, dlq, |value| pending.push_back(value));
         }
     }
 
-    /// Get the POSIX mqueue name.
-    pub fn name(&self) -> &str {
-        &self.name
+    /// Publish a typed value as a message with the given `msg_type` and priority.
+    pub fn publish(&self, value: &T, msg_type: u16, prio: u32) -> io::Result<()> {
+        let msg = self.encode(value, msg_type)?;
+        self.inner.publish(&msg, prio)
     }
 
-    /// Get the raw mqd_t for advanced usage.
-    pub fn raw_mqd(&self) -> mqd_t {
-        self.mqd
+    /// Typed [`MqTopic::publish_confirmed`].
+    pub fn publish_confirmed(&self, value: &T, msg_type: u16, prio: u32) -> io::Result<Delivery> {
+        let msg = self.encode(value, msg_type)?;
+        self.inner.publish_confirmed(&msg, prio)
     }
-}
-
-impl Drop for MqTopic {
-    fn drop(&mut self) {
-        self.running.store(false, Ordering::Relaxed);
-        let shutdown = Msg::new(MSG_TYPE_SHUTDOWN, &[]);
 
-        unsafe {
-            let data_ptr = &shutdown as *const Msg as *const c_char;
-            let rc = libc::mq_send(
-                self.mqd,
-                data_ptr,
-                std::mem::size_of::<Msg>(),
-                0,
-            );
-            if rc == -1 {
-                eprintln!("mq_send shutdown failed: {}", io::Error::last_os_error());
+    /// Validate `value` and pack it the way this topic is configured to
+    /// (compressed, by reference or inline).
+    pub(crate) fn encode(&self, value: &T, msg_type: u16) -> io::Result<Msg> {
+        for check in self.validators.load().iter() {
+            if let Err(err) = check(value) {
+                self.inner.traffic.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(err.into_io());
             }
-
-            libc::mq_close(self.mqd);
         }
 
-        if let Some(handle) = self.worker.take() {
-            let _ = handle.join();
-        }
+        let bytes: &[u8] = bytemuck::bytes_of(value);
+        let packed = if self.inner.compress {
+            compress::compress(bytes)
+        } else {
+            None
+        };
+        Ok(if let Some(packed) = packed {
+            let mut msg = Msg::new(msg_type, &packed);
+            msg.hdr.flags |= compress::FLAG_COMPRESSED;
+            msg
+        } else if self.inner.large {
+            Msg::by_ref(msg_type, &large::store(bytes)?)
+        } else {
+            Msg::new(msg_type, bytes)
+        })
     }
-}
-
-/// Strongly-typed IPC topic built on top of `MqTopic`.
-///
-/// T must be Pod + Zeroable so it can be safely mapped to raw bytes.
-pub struct Topic<T>
-where
-    T: Pod + Zeroable + Send + Sync + 'static,
-{
-    inner: MqTopic,
-    _marker: std::marker::PhantomData<T>,
-}
 
-impl<T> Topic<T>
-where
-    T: Pod + Zeroable + Send + Sync + 'static,
-{
-    /// Create or open a typed topic.
-    pub fn new(name: &str, maxmsg: c_long) -> io::Result<Self> {
-        let inner = MqTopic::new(name, maxmsg)?;
-        Ok(Self {
-            inner,
-            _marker: std::marker::PhantomData,
-        })
+    /// Publish a typed value at [`URGENT_PRIORITY`].
+    pub fn publish_urgent(&self, value: &T, msg_type: u16) -> io::Result<()> {
+        self.publish(value, msg_type, URGENT_PRIORITY)
     }
 
-    /// Subscribe with a callback that receives `T` directly.
-    pub fn subscribe<F>(&self, f: F)
+    /// Register a check every published value must pass; see [`validate`].
+    /// Validators run in registration order before anything is queued.
+    pub fn add_validator<F>(&self, f: F)
     where
-        F: Fn(T) + Send + Sync + 'static,
+        F: Fn(&T) -> Result<(), validate::ValidationError> + Send + Sync + 'static,
     {
-        self.inner.subscribe(move |msg: Msg| {
-            let mut buf = vec![0u8; std::mem::size_of::<T>()];
-            let n = std::cmp::min(msg.hdr.len as usize, buf.len());
-            buf[..n].copy_from_slice(&msg.payload[..n]);
-            let value: T = *bytemuck::from_bytes::<T>(&buf[..]);
-            f(value);
+        let f: Arc<Validator<T>> = Arc::new(f);
+        self.validators.rcu(|cur| {
+            let mut next = (**cur).clone();
+            next.push(Arc::clone(&f));
+            next
         });
     }
 
-    /// Publish a typed value as a message with the given `msg_type` and priority.
-    pub fn publish(&self, value: &T, msg_type: u16, prio: u32) -> io::Result<()> {
-        let bytes: &[u8] = bytemuck::bytes_of(value);
-        let msg = Msg::new(msg_type, bytes);
-        self.inner.publish(&msg, prio)
+    /// See [`MqTopic::stats`].
+    pub fn stats(&self) -> stats::TopicStats {
+        self.inner.stats()
+    }
+
+    /// See [`MqTopic::is_healthy`].
+    pub fn is_healthy(&self) -> bool {
+        self.inner.is_healthy()
+    }
+
+    /// See [`MqTopic::suspend`].
+    #[cfg(feature = "callbacks")]
+    pub fn suspend(&self) {
+        self.inner.suspend();
+    }
+
+    /// See [`MqTopic::resume`].
+    #[cfg(feature = "callbacks")]
+    pub fn resume(&self) {
+        self.inner.resume();
+    }
+
+    #[cfg(feature = "callbacks")]
+    pub fn is_suspended(&self) -> bool {
+        self.inner.is_suspended()
+    }
+
+    /// See [`MqTopic::matched_subscribers`].
+    pub fn matched_subscribers(&self) -> io::Result<usize> {
+        self.inner.matched_subscribers()
     }
 
     /// Expose the underlying raw topic.
@@ -371,6 +2801,24 @@ pub mod wire {
     use std::marker::PhantomData;
     use std::os::raw::c_long;
 
+    pub mod capture;
+    #[cfg(feature = "callbacks")]
+    pub mod command;
+    pub mod conformance;
+    pub mod failover;
+    #[cfg(feature = "callbacks")]
+    pub mod federation;
+    pub mod frame;
+    pub mod link;
+    pub mod loopback;
+    pub mod mtu;
+    pub mod qos;
+    #[cfg(feature = "callbacks")]
+    pub mod router;
+    #[cfg(feature = "callbacks")]
+    pub mod store;
+    pub mod transfer;
+
     /// Internal, fixed name for the wire TX topic.
     pub const IPC_TX_TOPIC_NAME: &str = "/ipc_tx";
 
@@ -428,10 +2876,21 @@ pub mod wire {
     {
         /// Creates a wire-aware topic:
         /// - `local_topic_name`: application topic (e.g. "/motor/state")
+        ///
         /// The TX topic is always the internal "/ipc_tx".
         pub fn new(local_topic_name: &str, maxmsg: c_long) -> io::Result<Self> {
+            Self::with_tx_topic(local_topic_name, IPC_TX_TOPIC_NAME, maxmsg)
+        }
+
+        /// Like [`WireTx::new`], but mirror into `tx_topic_name` instead of
+        /// "/ipc_tx", e.g. to keep a test away from the host's router.
+        pub fn with_tx_topic(
+            local_topic_name: &str,
+            tx_topic_name: &str,
+            maxmsg: c_long,
+        ) -> io::Result<Self> {
             let local = Topic::<T>::new(local_topic_name, maxmsg)?;
-            let tx = Topic::<WirePacket>::new(IPC_TX_TOPIC_NAME, maxmsg)?;
+            let tx = Topic::<WirePacket>::new(tx_topic_name, maxmsg)?;
 
             Ok(Self {
                 local,
@@ -454,7 +2913,7 @@ pub mod wire {
 
             // 2) serialize T + topic name into WirePacket on "/ipc_tx"
             let topic_bytes = self.topic_name.as_bytes();
-            let tlen = topic_bytes.len().min(WIRE_MAX_TOPIC);
+            let tlen = frame::topic_len(&self.topic_name);
 
             let raw = bytemuck::bytes_of(value);
             let plen = raw.len().min(WIRE_MAX_PAYLOAD);
@@ -488,11 +2947,57 @@ pub mod wire {
     }
 }
 
+// Polling is all a build without `callbacks` can receive with.
 #[cfg(test)]
+mod poll_tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn polling_hands_out_values_without_a_worker() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_poll_");
+        let topic: Topic<u32> = Topic::new(tmp.name(), 4).unwrap();
+        assert_eq!(topic.try_recv().unwrap(), None);
+
+        topic.publish(&7, 1, 0).unwrap();
+        let policy = batch::BatchPolicy::new(3, Duration::from_secs(10));
+        let batcher = batch::BatchingPublisher::<u32>::new(tmp.name(), 4, policy).unwrap();
+        for v in 1..=3u32 {
+            batcher.publish(&v, 1, 0).unwrap();
+        }
+
+        let mut got = Vec::new();
+        while let Some(v) = topic.recv_timeout(Duration::from_millis(200)).unwrap() {
+            got.push(v);
+        }
+        assert_eq!(got, vec![7, 1, 2, 3]);
+        #[cfg(feature = "callbacks")]
+        assert!(topic.raw().worker.get().is_none());
+    }
+
+    #[test]
+    fn raw_polling_times_out_on_an_empty_queue() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_poll_raw_");
+        let topic = MqTopic::new(tmp.name(), 4).unwrap();
+        assert!(topic.try_recv().unwrap().is_none());
+
+        let start = Instant::now();
+        assert!(topic
+            .recv_timeout(Duration::from_millis(50))
+            .unwrap()
+            .is_none());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        topic.publish(&Msg::new(3, b"hi"), 0).unwrap();
+        let msg = topic.recv_timeout(Duration::from_secs(2)).unwrap().unwrap();
+        assert_eq!((msg.hdr.msg_type, msg.data()), (3, &b"hi"[..]));
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
 mod tests {
     use super::*;
     use bytemuck::{Pod, Zeroable};
-    use libc;
     use std::{
         ffi::CString,
         sync::{Arc, Mutex},
@@ -574,6 +3079,626 @@ mod tests {
         unlink_queue(&topic_name);
     }
 
+    #[test]
+    fn matched_subscribers_follow_subscriptions() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_matched_");
+        let topic: Topic<TestMsg> = Topic::new(tmp.name(), 4).expect("failed to create topic");
+        assert_eq!(topic.matched_subscribers().unwrap(), 0);
+
+        let seen: Arc<Mutex<Vec<usize>>> = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        topic
+            .raw()
+            .on_matched(move |n| seen_clone.lock().unwrap().push(n));
+
+        topic.subscribe(|_: TestMsg| {});
+        assert!(topic
+            .raw()
+            .wait_for_subscribers(1, Duration::from_secs(1))
+            .unwrap());
+        assert_eq!(topic.matched_subscribers().unwrap(), 1);
+
+        for _ in 0..50 {
+            if seen.lock().unwrap().last() == Some(&1) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(seen.lock().unwrap().last(), Some(&1));
+    }
+
+    #[test]
+    fn conflated_topic_keeps_latest() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_conflate_");
+        let opts = TopicOptions::new(1).conflate(true);
+        let topic: Topic<TestMsg> =
+            Topic::with_options(tmp.name(), &opts).expect("failed to create topic");
+
+        let received: Arc<Mutex<Vec<TestMsg>>> = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = Arc::clone(&received);
+        let gate = Arc::new(Mutex::new(()));
+        let gate_clone = Arc::clone(&gate);
+        topic.subscribe(move |m: TestMsg| {
+            received_clone.lock().unwrap().push(m);
+            drop(gate_clone.lock().unwrap());
+        });
+
+        // Hold the subscriber inside its first callback so the 1-deep
+        // queue stays full; without conflation the publishes would block.
+        let held = gate.lock().unwrap();
+        topic.publish(&TestMsg { a: 0, b: 0 }, 1, 0).unwrap();
+        for _ in 0..50 {
+            if !received.lock().unwrap().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        for i in 1..5 {
+            topic.publish(&TestMsg { a: i, b: 0 }, 1, 0).unwrap();
+        }
+        drop(held);
+
+        for _ in 0..50 {
+            if received.lock().unwrap().len() >= 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let got: Vec<u32> = received.lock().unwrap().iter().map(|m| m.a).collect();
+        assert_eq!(got, vec![0, 4]);
+    }
+
+    #[test]
+    fn callback_stats_and_budget_warning() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_cbstats_");
+        let opts = TopicOptions::new(4).callback_budget(Duration::from_millis(5));
+        let topic: Topic<TestMsg> = Topic::with_options(tmp.name(), &opts).unwrap();
+
+        let errors: Arc<Mutex<Vec<TopicError>>> = Arc::new(Mutex::new(Vec::new()));
+        let errors_clone = Arc::clone(&errors);
+        topic
+            .raw()
+            .on_error(move |e| errors_clone.lock().unwrap().push(e.clone()));
+
+        topic.subscribe(|_: TestMsg| {});
+        topic.subscribe(|m: TestMsg| {
+            if m.a == 1 {
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        topic.publish(&TestMsg { a: 0, b: 0 }, 1, 0).unwrap();
+        topic.publish(&TestMsg { a: 1, b: 0 }, 1, 0).unwrap();
+
+        for _ in 0..100 {
+            if topic.stats().callbacks[1].invocations == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let stats = topic.stats();
+        assert_eq!(stats.callbacks.len(), 2);
+        assert_eq!(stats.callbacks[0].invocations, 2);
+        assert_eq!(stats.callbacks[1].invocations, 2);
+        assert!(stats.callbacks[1].max >= Duration::from_millis(20));
+        assert_eq!(stats.callbacks[1].over_budget, 1);
+
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            TopicError::CallbackOverBudget { index: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn nonblocking_publish_reports_full_queue() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_nonblock_");
+        let opts = TopicOptions::new(1).nonblocking(true).retry(
+            retry::RetryPolicy::retries(2)
+                .with_backoff(Duration::from_millis(1), Duration::from_millis(4)),
+        );
+        let topic = MqTopic::with_options(tmp.name(), &opts).unwrap();
+
+        // Park the worker in a callback so nothing drains the queue.
+        let gate = Arc::new(Mutex::new(()));
+        let gate_clone = Arc::clone(&gate);
+        let entered = Arc::new(AtomicBool::new(false));
+        let entered_clone = Arc::clone(&entered);
+        topic.subscribe(move |_| {
+            entered_clone.store(true, Ordering::SeqCst);
+            drop(gate_clone.lock().unwrap());
+        });
+
+        let held = gate.lock().unwrap();
+        topic.publish(&Msg::new(1, &[]), 0).unwrap();
+        while !entered.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        topic.publish(&Msg::new(1, &[]), 0).unwrap();
+
+        let err = topic.publish(&Msg::new(1, &[]), 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        drop(held);
+    }
+
+    #[test]
+    fn depth_watermark_tracks_backlog() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_depth_");
+        let topic = MqTopic::new(tmp.name(), 4).unwrap();
+
+        // No subscriber yet, so no worker drains the queue.
+        for _ in 0..3 {
+            topic.publish(&Msg::new(1, &[]), 0).unwrap();
+        }
+        assert_eq!(topic.stats().depth.current, 3);
+
+        let seen = Arc::new(AtomicU64::new(0));
+        let seen_clone = Arc::clone(&seen);
+        topic.subscribe(move |_| {
+            seen_clone.fetch_add(1, Ordering::SeqCst);
+        });
+        for _ in 0..100 {
+            if seen.load(Ordering::SeqCst) == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let depth = topic.stats().depth;
+        assert_eq!(depth.maxmsg, 4);
+        assert_eq!(depth.samples, 3);
+        assert_eq!(depth.high_watermark, 3);
+    }
+
+    #[test]
+    fn decimated_and_throttled_subscribers_drop_messages() {
+        let tmp_dec = cleanup::TempTopic::new("/mq_ipc_test_decimate_");
+        let tmp_thr = cleanup::TempTopic::new("/mq_ipc_test_throttle_");
+        let decimated: Topic<u32> = Topic::new(tmp_dec.name(), 10).unwrap();
+        let throttled: Topic<u32> = Topic::new(tmp_thr.name(), 10).unwrap();
+
+        // Queue everything before the workers start.
+        for v in 0..10u32 {
+            decimated.publish(&v, 1, 0).unwrap();
+            throttled.publish(&v, 1, 0).unwrap();
+        }
+
+        let got_dec = Arc::new(Mutex::new(Vec::new()));
+        let got_thr = Arc::new(Mutex::new(Vec::new()));
+        let dec_clone = Arc::clone(&got_dec);
+        let thr_clone = Arc::clone(&got_thr);
+        decimated.subscribe_decimated(3, move |v| dec_clone.lock().unwrap().push(v));
+        throttled.subscribe_throttled(1.0, move |v| thr_clone.lock().unwrap().push(v));
+
+        for _ in 0..100 {
+            if decimated.stats().depth.current == 0 && throttled.stats().depth.current == 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(*got_dec.lock().unwrap(), vec![0, 3, 6, 9]);
+        assert_eq!(*got_thr.lock().unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn reopens_recreated_queue() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_reopen_");
+        let topic = MqTopic::with_options(tmp.name(), &TopicOptions::new(4).reopen(true)).unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = Arc::clone(&events);
+        topic.on_error(move |err| events_clone.lock().unwrap().push(err.clone()));
+        let seen = Arc::new(AtomicU64::new(0));
+        let seen_clone = Arc::clone(&seen);
+        topic.subscribe(move |msg| {
+            seen_clone.fetch_add(msg.hdr.msg_type as u64, Ordering::SeqCst);
+        });
+
+        cleanup::unlink(tmp.name()).unwrap();
+        let other = MqTopic::new(tmp.name(), 4).unwrap();
+        other.publish(&Msg::new(7, &[]), 0).unwrap();
+        for _ in 0..200 {
+            if seen.load(Ordering::SeqCst) == 7 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(seen.load(Ordering::SeqCst), 7);
+        assert_eq!(*events.lock().unwrap(), vec![TopicError::QueueReopened]);
+    }
+
+    #[test]
+    fn unlink_policies() {
+        let exists = |name: &str| MqTopic::open_existing(name).unwrap().is_some();
+
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_unlink_creator_");
+        let opts = TopicOptions::new(4).unlink_on_drop(UnlinkPolicy::Creator);
+        let creator = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        let other = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        assert!(creator.created() && !other.created());
+        drop(other);
+        assert!(exists(tmp.name()));
+        drop(creator);
+        assert!(!exists(tmp.name()));
+
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_unlink_last_");
+        let opts = TopicOptions::new(4).unlink_on_drop(UnlinkPolicy::LastUser);
+        let a = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        let b = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        a.publish(&Msg::new(1, &[]), 0).unwrap();
+        b.publish(&Msg::new(1, &[]), 0).unwrap();
+        drop(a);
+        assert!(exists(tmp.name()));
+        drop(b);
+        assert!(!exists(tmp.name()));
+    }
+
+    #[test]
+    fn queues_of_another_format_are_refused() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_format_");
+        // What a format-1 peer creates: 4-byte header, 244-byte slots.
+        let old = open_queue_sized(tmp.name(), libc::O_CREAT | libc::O_RDWR, Some(4), 244).unwrap();
+        unsafe { libc::mq_close(old) };
+
+        let err = MqTopic::new(tmp.name(), 4).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("244-byte"));
+        assert_eq!(
+            MqTopic::open_existing(tmp.name()).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // A format-1 peer that opened our queue sends 244-byte messages.
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_format_msg_");
+        let topic = MqTopic::new(tmp.name(), 4).unwrap();
+        let old = [0u8; 244];
+        let rc = unsafe { libc::mq_send(topic.mqd, old.as_ptr().cast(), old.len(), 0) };
+        assert_eq!(rc, 0);
+        assert_eq!(
+            topic.try_recv().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn recv_buf_is_aligned_and_rejects_short_messages() {
+        assert_eq!(std::mem::size_of::<Msg>(), 8 + MSG_PAYLOAD_SIZE);
+        let mut buf = RecvBuf::new();
+        assert_eq!(buf.as_mut_ptr() as usize % std::mem::align_of::<u64>(), 0);
+
+        let full = Msg::new(1, &[0xAA; MSG_PAYLOAD_SIZE]);
+        unsafe { std::ptr::copy_nonoverlapping(&full as *const Msg, buf.as_mut_ptr().cast(), 1) };
+        assert_eq!(
+            buf.msg(RecvBuf::LEN).unwrap().payload[MSG_PAYLOAD_SIZE - 1],
+            0xAA
+        );
+
+        // A short receive is a format-1 peer, not a truncated message.
+        let err = buf.msg(4 + MSG_PAYLOAD_SIZE).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn callbacks_run_in_priority_order() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_subprio_");
+        let topic = MqTopic::new(tmp.name(), 4).unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let subs = [
+            (0, "logger"),
+            (10, "safety"),
+            (-1, "archive"),
+            (0, "metrics"),
+        ];
+        for (prio, tag) in subs {
+            let order = Arc::clone(&order);
+            topic.subscribe_with_priority(prio, move |_| order.lock().unwrap().push(tag));
+        }
+
+        topic.publish(&Msg::new(1, &[]), 0).unwrap();
+        for _ in 0..100 {
+            if order.lock().unwrap().len() == 4 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["safety", "logger", "metrics", "archive"]
+        );
+    }
+
+    #[test]
+    fn subscribe_ref_borrows_values() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_subref_");
+        let topic: Topic<[u64; 16]> = Topic::new(tmp.name(), 4).unwrap();
+
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_clone = Arc::clone(&got);
+        topic.subscribe_ref(move |v: &[u64; 16]| got_clone.lock().unwrap().push(v[15]));
+
+        let mut value = [0u64; 16];
+        for i in 0..3u64 {
+            value[15] = i;
+            topic.publish(&value, 1, 0).unwrap();
+        }
+        for _ in 0..100 {
+            if got.lock().unwrap().len() == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(*got.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn wait_for_returns_first_match_or_times_out() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_waitfor_");
+        let topic: Topic<u32> = Topic::new(tmp.name(), 4).unwrap();
+
+        let publisher = Topic::<u32>::new(tmp.name(), 4).unwrap();
+        let handle = thread::spawn(move || {
+            for state in 1..=3u32 {
+                thread::sleep(Duration::from_millis(10));
+                publisher.publish(&state, 1, 0).unwrap();
+            }
+        });
+
+        let got = topic.wait_for(|state| *state == 3, Duration::from_secs(2));
+        handle.join().unwrap();
+        assert_eq!(got, Ok(3));
+        assert!(topic.stats().callbacks.is_empty());
+
+        let timeout = Duration::from_millis(50);
+        assert_eq!(
+            topic.wait_for(|state| *state == 99, timeout),
+            Err(Timeout { waited: timeout })
+        );
+    }
+
+    #[test]
+    fn ttl_skips_stale_backlog() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_ttl_");
+        let opts = TopicOptions::new(8).ttl(Duration::from_millis(50));
+        let topic: Topic<u32> = Topic::with_options(tmp.name(), &opts).unwrap();
+
+        for stale in 0..3u32 {
+            topic.publish(&stale, 1, 0).unwrap();
+        }
+        thread::sleep(Duration::from_millis(100));
+        topic.publish(&7, 1, 0).unwrap();
+        let (tx, rx) = mpsc::channel();
+        topic.subscribe(move |v| tx.send(v).unwrap());
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)), Ok(7));
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        assert_eq!(topic.stats().expired, 3);
+    }
+
+    #[test]
+    fn suspended_worker_leaves_messages_queued() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_suspend_");
+        let topic: Topic<u32> = Topic::new(tmp.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        topic.subscribe(move |v| tx.send(v).unwrap());
+        topic.publish(&1, 1, 0).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)), Ok(1));
+
+        topic.suspend();
+        assert!(topic.is_suspended());
+        for v in 2..=4u32 {
+            topic.publish(&v, 1, 0).unwrap();
+        }
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        // One message may be held by the worker; the rest stay queued.
+        assert!(topic.stats().depth.current >= 2);
+
+        topic.resume();
+        let got: Vec<u32> = (0..3)
+            .map(|_| rx.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        assert_eq!(got, [2, 3, 4]);
+
+        // Closing a suspended topic must not hang.
+        topic.suspend();
+        drop(topic);
+    }
+
+    #[test]
+    fn stats_attribute_worker_cpu_time() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_cpu_");
+        let topic = MqTopic::new(tmp.name(), 4).unwrap();
+        let (tx, rx) = mpsc::channel();
+        topic.subscribe(move |_| {
+            let start = thread_cpu_time();
+            while thread_cpu_time() - start < Duration::from_millis(20) {}
+            tx.send(()).unwrap();
+        });
+        assert_eq!(topic.stats().worker_cpu, Duration::ZERO);
+
+        topic.publish(&Msg::new(1, b"spin"), 0).unwrap();
+        rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        for _ in 0..100 {
+            if topic.stats().worker_cpu >= Duration::from_millis(20) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(topic.stats().worker_cpu >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn raw_subscriber_sees_wire_bytes_next_to_typed() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_raw_tap_");
+        let topic: Topic<u32> = Topic::new(tmp.name(), 4).unwrap();
+        let (typed_tx, typed_rx) = mpsc::channel();
+        let (raw_tx, raw_rx) = mpsc::channel();
+        topic.subscribe(move |v| typed_tx.send(v).unwrap());
+        topic.subscribe_raw(move |msg| {
+            let _ = raw_tx.send((msg.hdr.msg_type, msg.data().to_vec()));
+        });
+
+        topic.publish(&5, 3, 0).unwrap();
+        let timeout = Duration::from_secs(2);
+        assert_eq!(typed_rx.recv_timeout(timeout), Ok(5));
+        assert_eq!(
+            raw_rx.recv_timeout(timeout),
+            Ok((3, 5u32.to_ne_bytes().to_vec()))
+        );
+    }
+
+    #[test]
+    fn publisher_restart_is_reported_on_new_session() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_session_");
+        let publisher =
+            MqTopic::with_options(tmp.name(), &TopicOptions::new(4).session(true)).unwrap();
+        let subscriber = MqTopic::new(tmp.name(), 4).unwrap();
+        let restarts = Arc::new(Mutex::new(Vec::new()));
+        let restarts_clone = Arc::clone(&restarts);
+        subscriber.on_publisher_restart(move |r| restarts_clone.lock().unwrap().push(r));
+
+        publisher.publish(&Msg::new(1, b"a"), 0).unwrap();
+        let msg = subscriber.try_recv().unwrap().unwrap();
+        assert_eq!(session::session_of(&msg), Some(session::current()));
+        assert_eq!(msg.data(), b"a");
+
+        // What a restarted publisher process would send.
+        let ext = ext::ExtHeader {
+            present: ext::EXT_SESSION,
+            session: session::current().wrapping_add(1),
+            ..Default::default()
+        };
+        publisher.publish(&Msg::with_ext(1, &ext, b"b"), 0).unwrap();
+        subscriber.try_recv().unwrap().unwrap();
+
+        assert_eq!(
+            *restarts.lock().unwrap(),
+            [session::PublisherRestart {
+                previous: session::current(),
+                session: ext.session,
+            }]
+        );
+    }
+
+    #[test]
+    fn ownership_refusals_are_cached_too() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_owner_cache_");
+        let topic = MqTopic::new(tmp.name(), 4).unwrap();
+        // Some live process other than this one: the test runner's parent.
+        let pid = std::os::unix::process::parent_id();
+        let start = registry::proc_start_time(pid).unwrap();
+        let claim = registry::topic_dir(tmp.name()).join(".owner");
+        std::fs::create_dir_all(claim.parent().unwrap()).unwrap();
+        std::fs::write(
+            &claim,
+            format!("pid={pid}\nstart={start}\nrole=pub\nnode=x\n"),
+        )
+        .unwrap();
+
+        let msg = Msg::new(1, &[]);
+        let refused = |topic: &MqTopic| topic.publish(&msg, 0).unwrap_err().kind();
+        assert_eq!(refused(&topic), io::ErrorKind::AddrInUse);
+        std::fs::remove_file(&claim).unwrap();
+        assert_eq!(refused(&topic), io::ErrorKind::AddrInUse);
+        thread::sleep(OWNER_RECHECK);
+        topic.publish(&msg, 0).unwrap();
+
+        drop(topic);
+        let _ = std::fs::remove_dir(claim.parent().unwrap());
+    }
+
+    #[test]
+    fn publish_confirmed_reports_what_the_policy_did() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_delivery_");
+        let opts = TopicOptions::new(2);
+        let lossy = MqTopic::with_options(tmp.name(), &opts.clone().qos(Qos::BestEffort)).unwrap();
+        let latest = MqTopic::with_options(tmp.name(), &opts.qos(Qos::KeepLatest)).unwrap();
+        let msg = Msg::new(1, &[]);
+
+        assert_eq!(lossy.publish_confirmed(&msg, 0).unwrap(), Delivery::Queued);
+        assert_eq!(lossy.publish_confirmed(&msg, 0).unwrap(), Delivery::Queued);
+        assert!(!lossy
+            .wait_writable(Some(Duration::from_millis(10)))
+            .unwrap());
+        assert_eq!(lossy.publish_confirmed(&msg, 0).unwrap(), Delivery::Dropped);
+        let err = lossy.publish(&msg, 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(
+            latest.publish_confirmed(&msg, 0).unwrap(),
+            Delivery::Evicted(1)
+        );
+
+        lossy.try_recv().unwrap().unwrap();
+        assert!(lossy.wait_writable(None).unwrap());
+        assert_eq!((lossy.stats().dropped, latest.stats().dropped), (2, 1));
+    }
+
+    #[test]
+    fn shaped_topic_spaces_out_a_burst() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_shape_");
+        let opts = TopicOptions::new(8).shape(100.0, 2);
+        let rejected = opts.clone().dead_letter(true).shape(0.0, 2);
+        assert!(MqTopic::with_options(tmp.name(), &rejected).is_err());
+        let dlq = dlq::dlq_name(tmp.name());
+        assert!(MqTopic::open_existing(&dlq).unwrap().is_none());
+        let topic = MqTopic::with_options(tmp.name(), &opts).unwrap();
+
+        let start = Instant::now();
+        for i in 0..6u16 {
+            topic.publish(&Msg::new(i, &[]), 0).unwrap();
+        }
+        // Two go out at once, the other four wait ~10 ms each.
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert!(topic.shaper().unwrap().delayed() >= 1);
+        assert_eq!(topic.try_recv().unwrap().unwrap().hdr.msg_type, 0);
+    }
+
+    #[test]
+    fn supervised_worker_survives_panicking_callback() {
+        let tmp = cleanup::TempTopic::new("/mq_ipc_test_supervise_");
+        let topic =
+            MqTopic::with_options(tmp.name(), &TopicOptions::new(4).supervise(true)).unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = Arc::clone(&events);
+        topic.on_error(move |err| events_clone.lock().unwrap().push(err.clone()));
+
+        let seen = Arc::new(AtomicU64::new(0));
+        let seen_clone = Arc::clone(&seen);
+        topic.subscribe(move |msg| {
+            assert_ne!(msg.hdr.msg_type, 13, "poisoned message");
+            seen_clone.fetch_add(1, Ordering::SeqCst);
+        });
+
+        topic.publish(&Msg::new(13, &[]), 0).unwrap();
+        topic.publish(&Msg::new(1, &[]), 0).unwrap();
+        for _ in 0..100 {
+            if seen.load(Ordering::SeqCst) == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+        assert!(topic.is_healthy());
+        assert_eq!(topic.worker_restarts(), 1);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                TopicError::WorkerDied {
+                    cause: WorkerFailure::Panic
+                },
+                TopicError::WorkerRestarted { restarts: 1 },
+            ]
+        );
+    }
+
     // #[test]
     // fn wiretx_produces_expected_wirepacket() {
     //     let local_topic = format!("/mq_ipc_test_wiretx_{}", std::process::id());
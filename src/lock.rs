@@ -0,0 +1,420 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Lease-based exclusive locks shared between processes.
+//!
+//! An [`MqLock`] is a one-deep queue holding a single [`LockRecord`]: who
+//! owns the lock and until when. Whoever has received the record is the
+//! only one looking at it, so every operation is "take the record, check
+//! or change it, send it back". Acquiring writes the caller in as owner
+//! with a lease; the holder must [`renew`](LockGuard::renew) before the
+//! lease runs out. A lease that ran out, or whose owner process is gone,
+//! is free for the next caller, so a hung or crashed holder cannot keep
+//! a shared bus locked forever.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::lock::MqLock;
+//! use std::time::Duration;
+//!
+//! let i2c = MqLock::open("/i2c1_lock", Duration::from_millis(500))?;
+//! let guard = i2c.acquire(Duration::from_secs(1))?;
+//! // ... talk to the bus, renewing if it takes a while ...
+//! guard.renew()?;
+//! drop(guard);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! After a lease expires its former holder may still be running; check
+//! [`LockGuard::renew`] between steps, or hand [`LockGuard::token`] to the
+//! device as a fencing token, when that matters.
+
+use super::{
+    clock::{Clock, MonotonicClock},
+    defaults, open_queue_sized, queue_attr, realtime_after, realtime_now,
+};
+use bytemuck::{Pod, Zeroable};
+use libc::{self, mqd_t};
+use std::{
+    io,
+    os::raw::c_char,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// How long a receive waits for the record before looking again. The
+/// record is only ever out of the queue for a moment.
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long the record may be missing before it is recreated, free. It
+/// goes missing only if a process dies in the middle of an operation.
+const RECOVER_AFTER: Duration = Duration::from_secs(1);
+
+const RECORD_LEN: usize = std::mem::size_of::<LockRecord>();
+
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+/// State of a lock as stored in its queue.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct LockRecord {
+    /// Owner's pid, 0 while the lock is free.
+    pub owner_pid: u32,
+    pub reserved: u32,
+    /// Which [`MqLock`] handle of the owner process holds it.
+    pub owner_handle: u64,
+    /// Grows with every acquisition.
+    pub token: u64,
+    /// End of the lease on the system-wide `CLOCK_MONOTONIC`, in ns.
+    pub expires_ns: u64,
+}
+
+impl LockRecord {
+    /// Whether nobody holds the lock at monotonic time `now`.
+    pub fn is_free(&self, now: Duration) -> bool {
+        self.owner_pid == 0 || self.expires_ns <= now.as_nanos() as u64 || !alive(self.owner_pid)
+    }
+}
+
+fn alive(pid: u32) -> bool {
+    // EPERM: alive, just not ours to signal.
+    let rc = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    rc == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Handle on a lock shared between processes; see the module docs.
+///
+/// Handles are independent owners, even within one process, and a lock is
+/// not re-entrant: acquiring it again through the same handle waits like
+/// any other caller.
+pub struct MqLock {
+    name: String,
+    mqd: mqd_t,
+    lease: Duration,
+    handle: u64,
+}
+
+impl MqLock {
+    /// Open (creating if needed) the lock `name`, leasing it for `lease`
+    /// on every acquire or renew.
+    pub fn open(name: &str, lease: Duration) -> io::Result<Self> {
+        if lease.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "lock lease must be non-zero",
+            ));
+        }
+        let name = defaults::topic_name(name).into_owned();
+        let (mqd, created) = match open_queue_sized(
+            &name,
+            libc::O_CREAT | libc::O_EXCL | libc::O_RDWR,
+            Some(1),
+            RECORD_LEN,
+        ) {
+            Ok(mqd) => (mqd, true),
+            Err(err) if err.raw_os_error() == Some(libc::EEXIST) => (
+                open_queue_sized(&name, libc::O_RDWR, None, RECORD_LEN)?,
+                false,
+            ),
+            Err(err) => return Err(err),
+        };
+        let lock = MqLock {
+            name,
+            mqd,
+            lease,
+            handle: NEXT_HANDLE.fetch_add(1, Ordering::Relaxed),
+        };
+
+        let attr = queue_attr(mqd)?;
+        if attr.mq_msgsize as usize != RECORD_LEN || attr.mq_maxmsg != 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a lock queue", lock.name),
+            ));
+        }
+        // Only the creator seeds the record; anyone else could duplicate
+        // one that is merely out of the queue right now.
+        if created {
+            lock.put(&LockRecord::zeroed())?;
+        }
+        Ok(lock)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn lease(&self) -> Duration {
+        self.lease
+    }
+
+    /// Take the lock if it is free right now.
+    pub fn try_acquire(&self) -> io::Result<Option<LockGuard<'_>>> {
+        self.acquire_within(Duration::ZERO)
+    }
+
+    /// Wait up to `timeout` for the lock; fails with
+    /// [`io::ErrorKind::TimedOut`] if it stays held.
+    pub fn acquire(&self, timeout: Duration) -> io::Result<LockGuard<'_>> {
+        self.acquire_within(timeout)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} still held after {timeout:?}", self.name),
+            )
+        })
+    }
+
+    /// Current holder, `None` if the lock is free.
+    pub fn holder(&self) -> io::Result<Option<LockRecord>> {
+        let now = MonotonicClock.now();
+        Ok(self
+            .with_record(|_| false)?
+            .filter(|record| !record.is_free(now)))
+    }
+
+    fn acquire_within(&self, timeout: Duration) -> io::Result<Option<LockGuard<'_>>> {
+        let clock = MonotonicClock;
+        let deadline = clock.now() + timeout;
+        let mut missing_since = None;
+        loop {
+            let Some(mut record) = self.take()? else {
+                let now = clock.now();
+                let since = *missing_since.get_or_insert(now);
+                if now - since >= RECOVER_AFTER {
+                    self.put(&LockRecord::zeroed())?;
+                    missing_since = None;
+                } else if now >= deadline {
+                    return Ok(None);
+                }
+                continue;
+            };
+            missing_since = None;
+
+            let now = clock.now();
+            let won = record.is_free(now);
+            if won {
+                record.owner_pid = std::process::id();
+                record.owner_handle = self.handle;
+                record.token = (record.token + 1).max(now.as_nanos() as u64);
+                record.expires_ns = (now + self.lease).as_nanos() as u64;
+            }
+            // A failed put means the record was recreated meanwhile and
+            // this update is void.
+            if self.put(&record)? && won {
+                return Ok(Some(LockGuard {
+                    lock: self,
+                    token: record.token,
+                }));
+            }
+
+            let now = clock.now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            thread::sleep((deadline - now).min(RETRY_INTERVAL));
+        }
+    }
+
+    /// Take the record, let `f` update it while it is ours if this handle
+    /// holds the lock with `token`, and put it back. Returns whether `f`
+    /// ran and its change was stored.
+    fn update_held(&self, token: u64, f: impl FnOnce(&mut LockRecord)) -> io::Result<bool> {
+        let mut held = false;
+        let stored = self.with_record(|record| {
+            held = record.owner_pid == std::process::id()
+                && record.owner_handle == self.handle
+                && record.token == token;
+            if held {
+                f(record);
+            }
+            held
+        })?;
+        Ok(stored.is_some() && held)
+    }
+
+    /// Take the record, and put it back changed by `f` if it returns true
+    /// or unchanged otherwise. `None` if the record could not be had.
+    fn with_record(
+        &self,
+        f: impl FnOnce(&mut LockRecord) -> bool,
+    ) -> io::Result<Option<LockRecord>> {
+        let Some(original) = self.take()? else {
+            return Ok(None);
+        };
+        let mut record = original;
+        let record = if f(&mut record) { record } else { original };
+        Ok(self.put(&record)?.then_some(record))
+    }
+
+    /// Receive the record, waiting at most [`RETRY_INTERVAL`].
+    fn take(&self) -> io::Result<Option<LockRecord>> {
+        let mut record = LockRecord::zeroed();
+        let deadline = realtime_after(RETRY_INTERVAL);
+        let ret = unsafe {
+            libc::mq_timedreceive(
+                self.mqd,
+                &mut record as *mut LockRecord as *mut c_char,
+                RECORD_LEN,
+                std::ptr::null_mut(),
+                &deadline,
+            )
+        };
+        if ret >= 0 {
+            return Ok(Some(record));
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::ETIMEDOUT) | Some(libc::EINTR) => Ok(None),
+            _ => Err(err),
+        }
+    }
+
+    /// Send the record back without blocking. Returns false if the queue
+    /// already holds one.
+    fn put(&self, record: &LockRecord) -> io::Result<bool> {
+        let now = realtime_now();
+        let rc = unsafe {
+            libc::mq_timedsend(
+                self.mqd,
+                record as *const LockRecord as *const c_char,
+                RECORD_LEN,
+                0,
+                &now,
+            )
+        };
+        if rc == -1 {
+            let err = io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ETIMEDOUT) | Some(libc::EAGAIN) => Ok(false),
+                _ => Err(err),
+            };
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for MqLock {
+    fn drop(&mut self) {
+        unsafe {
+            libc::mq_close(self.mqd);
+        }
+    }
+}
+
+/// A held [`MqLock`]; releases it when dropped.
+pub struct LockGuard<'a> {
+    lock: &'a MqLock,
+    token: u64,
+}
+
+impl LockGuard<'_> {
+    /// Number of this acquisition; later holders always get a larger one.
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+
+    /// Extend the lease by the lock's lease from now. Fails if the lease
+    /// was lost, e.g. because it expired and someone else took the lock.
+    pub fn renew(&self) -> io::Result<()> {
+        let expires = (MonotonicClock.now() + self.lock.lease).as_nanos() as u64;
+        if self
+            .lock
+            .update_held(self.token, |record| record.expires_ns = expires)?
+        {
+            return Ok(());
+        }
+        Err(io::Error::other(format!(
+            "lease on {} was lost",
+            self.lock.name
+        )))
+    }
+
+    /// Release the lock now, reporting errors that dropping would ignore.
+    pub fn release(self) -> io::Result<()> {
+        let released = self.release_inner();
+        std::mem::forget(self);
+        released
+    }
+
+    fn release_inner(&self) -> io::Result<()> {
+        // Losing the lease already released it.
+        self.lock.update_held(self.token, |record| {
+            record.owner_pid = 0;
+            record.owner_handle = 0;
+            record.expires_ns = 0;
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.release_inner();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    #[test]
+    fn one_holder_at_a_time() {
+        let tmp = TempTopic::new("/mq_ipc_test_lock_");
+        let a = MqLock::open(tmp.name(), Duration::from_secs(5)).unwrap();
+        let b = MqLock::open(tmp.name(), Duration::from_secs(5)).unwrap();
+
+        let held = a.acquire(Duration::from_secs(1)).unwrap();
+        assert!(b.try_acquire().unwrap().is_none());
+        assert!(a.try_acquire().unwrap().is_none());
+        let err = b.acquire(Duration::from_millis(30)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(b.holder().unwrap().unwrap().owner_pid, std::process::id());
+        held.renew().unwrap();
+
+        let first = held.token();
+        held.release().unwrap();
+        assert!(b.holder().unwrap().is_none());
+        let next = b.try_acquire().unwrap().unwrap();
+        assert!(next.token() > first);
+        assert!(MqLock::open(tmp.name(), Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn expired_lease_passes_to_next_caller() {
+        let tmp = TempTopic::new("/mq_ipc_test_lock_lease_");
+        let a = MqLock::open(tmp.name(), Duration::from_millis(50)).unwrap();
+        let b = MqLock::open(tmp.name(), Duration::from_secs(5)).unwrap();
+
+        let stale = a.acquire(Duration::from_secs(1)).unwrap();
+        let taken = b.acquire(Duration::from_secs(2)).unwrap();
+        assert!(stale.renew().is_err());
+
+        // The old holder letting go must not free the new holder's lease.
+        drop(stale);
+        assert!(a.try_acquire().unwrap().is_none());
+        taken.renew().unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Fan-in of several topics carrying the same type.
+//!
+//! A [`Merge`] subscribes to every source and produces one sequence in
+//! arrival order, either handed to a callback together with the name of
+//! the source it came from, or republished on a single output topic
+//! where [`origin_of`] recovers the source:
+//!
+//! ```no_run
+//! # use bytemuck::{Pod, Zeroable};
+//! # use mq_ipc::merge::Merge;
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! struct Scan { ranges: [f32; 32] }
+//!
+//! let _lidar = Merge::<Scan>::new(&["/lidar/front", "/lidar/rear"], 8, |source, scan| {
+//!     println!("{source}: {}", scan.ranges[0]);
+//! })?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Callbacks are serialised, so the consumer never sees two messages at
+//! once even though each source has its own worker.
+
+use super::{
+    current_priority,
+    ext::{ExtHeader, EXT_ORIGIN},
+    MqTopic, Msg, Topic, TopicOptions,
+};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    os::raw::c_long,
+    sync::{Arc, Mutex},
+};
+
+/// Index of the [`Merge`] source `msg` was taken from, if it was
+/// republished by one.
+pub fn origin_of(msg: &Msg) -> Option<u32> {
+    msg.ext()
+        .filter(|ext| ext.has(EXT_ORIGIN))
+        .map(|ext| ext.origin)
+}
+
+/// Subscription to several `Topic<T>` sources delivered as one stream.
+pub struct Merge<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    sources: Vec<Topic<T>>,
+    names: Vec<String>,
+    output: Option<Arc<MqTopic>>,
+}
+
+impl<T> Merge<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    /// Call `f(source_name, value)` for every value on any of `sources`.
+    pub fn new<F>(sources: &[&str], maxmsg: c_long, f: F) -> io::Result<Self>
+    where
+        F: FnMut(&str, T) + Send + 'static,
+    {
+        let merge = Self::open(sources, maxmsg)?;
+        let f = Arc::new(Mutex::new(f));
+        for (topic, name) in merge.sources.iter().zip(&merge.names) {
+            let f = Arc::clone(&f);
+            let name = name.clone();
+            topic.subscribe(move |value| {
+                let mut f = f.lock().unwrap_or_else(|e| e.into_inner());
+                f(&name, value)
+            });
+        }
+        Ok(merge)
+    }
+
+    /// Republish every value on any of `sources` to `output` as
+    /// `msg_type`, tagged with the index of its source (see
+    /// [`origin_of`] and [`sources`](Self::sources)).
+    ///
+    /// Values too large to share a message with an extended header are
+    /// republished untagged.
+    pub fn republish(
+        sources: &[&str],
+        output: &str,
+        msg_type: u16,
+        opts: &TopicOptions,
+    ) -> io::Result<Self> {
+        let mut merge = Self::open(sources, opts.maxmsg)?;
+        let out = Arc::new(MqTopic::with_options(output, opts)?);
+        for (index, topic) in merge.sources.iter().enumerate() {
+            let out = Arc::clone(&out);
+            topic.subscribe(move |value: T| {
+                let raw = Msg::new(msg_type, bytemuck::bytes_of(&value));
+                let ext = ExtHeader {
+                    present: EXT_ORIGIN,
+                    origin: index as u32,
+                    ..Default::default()
+                };
+                let msg = raw.attach_ext(&ext).unwrap_or(raw);
+                if let Err(err) = out.publish(&msg, current_priority().unwrap_or(0)) {
+                    eprintln!("merge into {} failed: {err}", out.name());
+                }
+            });
+        }
+        merge.output = Some(out);
+        Ok(merge)
+    }
+
+    fn open(sources: &[&str], maxmsg: c_long) -> io::Result<Self> {
+        let topics = sources
+            .iter()
+            .map(|name| Topic::new(name, maxmsg))
+            .collect::<io::Result<Vec<_>>>()?;
+        let names = topics.iter().map(|t| t.raw().name().to_string()).collect();
+        Ok(Merge {
+            sources: topics,
+            names,
+            output: None,
+        })
+    }
+
+    /// Source names, in the order their indices refer to.
+    pub fn sources(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Name of the output topic, when republishing.
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_ref().map(|t| t.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::mpsc, time::Duration};
+
+    #[test]
+    fn republishes_tagged_with_origin() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_merge_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_merge_b_");
+        let tmp_out = TempTopic::new("/mq_ipc_test_merge_out_");
+
+        let merge = Merge::<u32>::republish(
+            &[tmp_a.name(), tmp_b.name()],
+            tmp_out.name(),
+            3,
+            &TopicOptions::new(8),
+        )
+        .unwrap();
+        assert_eq!(merge.sources(), [tmp_a.name(), tmp_b.name()]);
+
+        let out = MqTopic::new(tmp_out.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        out.subscribe(move |msg| {
+            let value: u32 = bytemuck::pod_read_unaligned(msg.data());
+            tx.send((origin_of(&msg), msg.hdr.msg_type, value)).unwrap();
+        });
+
+        Topic::<u32>::new(tmp_a.name(), 8)
+            .unwrap()
+            .publish(&10, 1, 0)
+            .unwrap();
+        Topic::<u32>::new(tmp_b.name(), 8)
+            .unwrap()
+            .publish(&20, 1, 0)
+            .unwrap();
+
+        let mut got: Vec<_> = (0..2)
+            .map(|_| rx.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        got.sort();
+        assert_eq!(got, vec![(Some(0), 3, 10), (Some(1), 3, 20)]);
+    }
+}
@@ -0,0 +1,422 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Warm-standby duplication for redundant pipelines.
+//!
+//! A [`MirroredPublisher`] sends every value to a primary and a standby
+//! topic, numbered identically in both copies. A [`StandbySelector`] on
+//! the consumer side listens to both, delivers the primary's stream and
+//! switches to the standby once the primary has been silent for the
+//! liveliness timeout:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::mirror::{MirroredPublisher, SelectorOptions, StandbySelector};
+//! use mq_ipc::TopicOptions;
+//! use std::time::Duration;
+//!
+//! // Producer side, e.g. one of two redundant sensor drivers...
+//! let imu = MirroredPublisher::<[f32; 3]>::new("/imu_a", "/imu_b", &TopicOptions::new(8))?;
+//! imu.publish(&[0.0, 0.0, 9.81], 1, 0)?;
+//!
+//! // ...and the consumer, which sees one stream.
+//! let opts = SelectorOptions::new(Duration::from_millis(50));
+//! let _sel = StandbySelector::<[f32; 3]>::new("/imu_a", "/imu_b", 8, &opts, |accel| {
+//!     println!("{accel:?}");
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The copies carry the same (`source`, `seq`) pair in the extended
+//! header, which is what lets the selector switch without repeating or
+//! skipping values: messages of the idle input are held back (up to
+//! [`SelectorOptions::backlog`]), and on a switch the ones the failed
+//! input never delivered are released first. Two independent producers
+//! publishing plain values work too; then the selector only switches.
+//!
+//! Switching is sticky: after a failover the standby stays active until
+//! it goes silent in turn, so a flapping primary does not bounce the
+//! consumer back and forth.
+
+use super::{
+    dedup,
+    ext::{ExtHeader, EXT_PAYLOAD_SIZE, EXT_SEQ},
+    reorder, MqTopic, Msg, TopicOptions,
+};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    collections::VecDeque,
+    io,
+    marker::PhantomData,
+    os::raw::c_long,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+/// Publishes each value to a primary and a standby topic.
+pub struct MirroredPublisher<T> {
+    primary: MqTopic,
+    standby: MqTopic,
+    source: u32,
+    next: AtomicU64,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: Pod> MirroredPublisher<T> {
+    /// Open both topics with `opts`. Its own sequencing is turned off;
+    /// the mirror numbers the copies itself.
+    pub fn new(primary: &str, standby: &str, opts: &TopicOptions) -> io::Result<Self> {
+        if std::mem::size_of::<T>() > EXT_PAYLOAD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} does not fit next to the extended header",
+                    std::any::type_name::<T>()
+                ),
+            ));
+        }
+        let opts = TopicOptions {
+            sequenced: false,
+            ..opts.clone()
+        };
+        Ok(MirroredPublisher {
+            primary: MqTopic::with_options(primary, &opts)?,
+            standby: MqTopic::with_options(standby, &opts)?,
+            source: dedup::unique_seed() as u32,
+            next: AtomicU64::new(0),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Publish `value` on both topics. Succeeds when at least one copy
+    /// went out; otherwise returns the primary's error.
+    pub fn publish(&self, value: &T, msg_type: u16, prio: u32) -> io::Result<()> {
+        let ext = ExtHeader {
+            present: EXT_SEQ,
+            source: self.source,
+            seq: self.next.fetch_add(1, Ordering::Relaxed),
+            ..Default::default()
+        };
+        let msg = Msg::with_ext(msg_type, &ext, bytemuck::bytes_of(value));
+        let primary = self.primary.publish(&msg, prio);
+        let standby = self.standby.publish(&msg, prio);
+        match (primary, standby) {
+            (Err(err), Err(_)) => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn primary(&self) -> &MqTopic {
+        &self.primary
+    }
+
+    pub fn standby(&self) -> &MqTopic {
+        &self.standby
+    }
+}
+
+/// Input of a [`StandbySelector`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Source {
+    Primary,
+    Standby,
+}
+
+impl Source {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Source::Primary => Source::Standby,
+            Source::Standby => Source::Primary,
+        }
+    }
+}
+
+/// When a [`StandbySelector`] fails over.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SelectorOptions {
+    /// Silence after which the active input counts as lost.
+    pub liveliness: Duration,
+    /// Messages of the idle input held back for a switch.
+    pub backlog: usize,
+}
+
+impl SelectorOptions {
+    pub fn new(liveliness: Duration) -> Self {
+        SelectorOptions {
+            liveliness,
+            backlog: 16,
+        }
+    }
+
+    pub fn backlog(mut self, backlog: usize) -> Self {
+        self.backlog = backlog;
+        self
+    }
+}
+
+type Deliver<T> = Box<dyn FnMut(T) + Send + 'static>;
+
+struct State<T> {
+    active: Source,
+    last_seen: [Instant; 2],
+    /// Last `(source, seq)` delivered.
+    delivered: Option<(u32, u64)>,
+    /// Idle input's messages the active one may not have delivered yet.
+    held: VecDeque<(Option<(u32, u64)>, T)>,
+    switches: u64,
+    f: Deliver<T>,
+}
+
+impl<T> State<T> {
+    fn is_new(&self, seq: Option<(u32, u64)>) -> bool {
+        match (self.delivered, seq) {
+            (Some((src, last)), Some((s, n))) => src != s || n > last,
+            _ => true,
+        }
+    }
+
+    fn deliver(&mut self, seq: Option<(u32, u64)>, value: T) {
+        if !self.is_new(seq) {
+            return;
+        }
+        if seq.is_some() {
+            self.delivered = seq;
+        }
+        (self.f)(value);
+    }
+
+    fn on_message(
+        &mut self,
+        from: Source,
+        seq: Option<(u32, u64)>,
+        value: T,
+        opts: &SelectorOptions,
+    ) {
+        let now = Instant::now();
+        self.last_seen[from.index()] = now;
+
+        if from != self.active {
+            let active_seen = self.last_seen[self.active.index()];
+            if now.duration_since(active_seen) < opts.liveliness {
+                if seq.is_some() && opts.backlog > 0 {
+                    if self.held.len() == opts.backlog {
+                        self.held.pop_front();
+                    }
+                    self.held.push_back((seq, value));
+                }
+                return;
+            }
+            self.active = from;
+            self.switches += 1;
+            for (seq, value) in std::mem::take(&mut self.held) {
+                self.deliver(seq, value);
+            }
+        }
+
+        self.deliver(seq, value);
+        // Whatever the idle input sent up to here is now stale.
+        while let Some((held, _)) = self.held.front()
+            && !self.is_new(*held)
+        {
+            self.held.pop_front();
+        }
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    opts: SelectorOptions,
+}
+
+/// Consumer of a primary/standby pair, delivering one input at a time.
+pub struct StandbySelector<T> {
+    primary: MqTopic,
+    standby: MqTopic,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> StandbySelector<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    /// Subscribe to `primary` and `standby` and call `f` with the values
+    /// of whichever is active. Calls are serialised.
+    pub fn new<F>(
+        primary: &str,
+        standby: &str,
+        maxmsg: c_long,
+        opts: &SelectorOptions,
+        f: F,
+    ) -> io::Result<Self>
+    where
+        F: FnMut(T) + Send + 'static,
+    {
+        let primary = MqTopic::new(primary, maxmsg)?;
+        let standby = MqTopic::new(standby, maxmsg)?;
+        let now = Instant::now();
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                active: Source::Primary,
+                last_seen: [now; 2],
+                delivered: None,
+                held: VecDeque::new(),
+                switches: 0,
+                f: Box::new(f),
+            }),
+            opts: *opts,
+        });
+
+        for (topic, from) in [(&primary, Source::Primary), (&standby, Source::Standby)] {
+            let shared = Arc::clone(&shared);
+            topic.subscribe(move |msg: Msg| {
+                let data = msg.data();
+                if data.len() != std::mem::size_of::<T>() {
+                    return;
+                }
+                let value: T = bytemuck::pod_read_unaligned(data);
+                let mut state = shared.state.lock().unwrap_or_else(|e| e.into_inner());
+                state.on_message(from, reorder::sequence_of(&msg), value, &shared.opts);
+            });
+        }
+
+        Ok(StandbySelector {
+            primary,
+            standby,
+            shared,
+        })
+    }
+
+    /// Input currently delivered.
+    pub fn active(&self) -> Source {
+        self.state().active
+    }
+
+    /// Number of failovers so far.
+    pub fn switches(&self) -> u64 {
+        self.state().switches
+    }
+
+    fn state(&self) -> MutexGuard<'_, State<T>> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Name of the topic `source` is read from.
+    pub fn topic(&self, source: Source) -> &str {
+        match source {
+            Source::Primary => self.primary.name(),
+            Source::Standby => self.standby.name(),
+        }
+    }
+
+    /// The input that is not active.
+    pub fn idle(&self) -> Source {
+        self.active().other()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::thread;
+
+    #[test]
+    fn mirrored_copies_share_sequence_numbers() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_mirror_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_mirror_b_");
+        let mirror =
+            MirroredPublisher::<u32>::new(tmp_a.name(), tmp_b.name(), &TopicOptions::new(4))
+                .unwrap();
+        mirror.publish(&5, 1, 0).unwrap();
+        mirror.publish(&6, 1, 0).unwrap();
+
+        for topic in [mirror.primary(), mirror.standby()] {
+            let first = topic.try_recv().unwrap().unwrap();
+            let second = topic.try_recv().unwrap().unwrap();
+            let (src, n) = reorder::sequence_of(&first).unwrap();
+            assert_eq!(reorder::sequence_of(&second), Some((src, n + 1)));
+            assert_eq!(first.data(), 5u32.to_ne_bytes());
+        }
+    }
+
+    #[test]
+    fn fails_over_without_gaps_or_repeats() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_select_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_select_b_");
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_cb = Arc::clone(&got);
+        let sel = StandbySelector::<u32>::new(
+            tmp_a.name(),
+            tmp_b.name(),
+            4,
+            &SelectorOptions::new(Duration::from_millis(100)),
+            move |v| got_cb.lock().unwrap().push(v),
+        )
+        .unwrap();
+
+        let primary = MqTopic::new(tmp_a.name(), 4).unwrap();
+        let standby = MqTopic::new(tmp_b.name(), 4).unwrap();
+        let send = |topic: &MqTopic, seq: u64| {
+            let ext = ExtHeader {
+                present: EXT_SEQ,
+                source: 9,
+                seq,
+                ..Default::default()
+            };
+            let msg = Msg::with_ext(1, &ext, &(seq as u32).to_ne_bytes());
+            topic.publish(&msg, 0).unwrap();
+        };
+        let wait_for = |n: usize| {
+            for _ in 0..200 {
+                if got.lock().unwrap().len() >= n {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        };
+
+        for seq in 1..=2 {
+            send(&primary, seq);
+            send(&standby, seq);
+        }
+        wait_for(2);
+        // The primary dies after 3 reached only the standby.
+        send(&standby, 3);
+        thread::sleep(Duration::from_millis(150));
+        send(&standby, 4);
+        wait_for(4);
+
+        assert_eq!(*got.lock().unwrap(), [1, 2, 3, 4]);
+        assert_eq!(sel.active(), Source::Standby);
+        assert_eq!(sel.switches(), 1);
+        assert_eq!(sel.topic(sel.idle()), tmp_a.name());
+    }
+}
@@ -0,0 +1,405 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Aggregate subscription over many topics served by a single worker.
+//!
+//! [`MultiSubscriber`] needs the `callbacks` feature; the topic listing
+//! and glob helpers do not.
+
+#[cfg(feature = "callbacks")]
+use super::{
+    defaults, open_queue, register_worker, unregister_worker, Msg, RecvBuf, MSG_TYPE_SHUTDOWN,
+};
+#[cfg(feature = "callbacks")]
+use libc::{self, mqd_t};
+use std::io;
+#[cfg(feature = "callbacks")]
+use std::{
+    os::raw::{c_int, c_long},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// Directory where Linux exposes the POSIX mqueue namespace.
+pub const MQUEUE_FS: &str = "/dev/mqueue";
+
+#[cfg(feature = "callbacks")]
+type MultiCallback = Arc<dyn Fn(&str, Msg) + Send + Sync + 'static>;
+
+/// Subscriber attached to several topics at once.
+///
+/// All queues are multiplexed with `poll(2)` on one background thread and
+/// every message is delivered to the same callback as `(topic_name, Msg)`.
+/// This is the building block for recorders, bridges and monitors that
+/// would otherwise need one `MqTopic` (and one thread) per topic.
+#[cfg(feature = "callbacks")]
+pub struct MultiSubscriber {
+    topics: Vec<(String, mqd_t)>,
+    running: Arc<AtomicBool>,
+    wake_fd: c_int,
+    worker: Option<thread::JoinHandle<()>>,
+    worker_id: u64,
+}
+
+#[cfg(feature = "callbacks")]
+impl MultiSubscriber {
+    /// Attach to a list of topics, creating the ones that do not exist yet.
+    pub fn new<F>(names: &[&str], maxmsg: c_long, f: F) -> io::Result<Self>
+    where
+        F: Fn(&str, Msg) + Send + Sync + 'static,
+    {
+        let names: Vec<_> = names
+            .iter()
+            .map(|name| defaults::topic_name(name))
+            .collect();
+        let names: Vec<&str> = names.iter().map(|name| &**name).collect();
+        Self::open_resolved(&names, maxmsg, f)
+    }
+
+    /// [`new`](Self::new) for queue names that already carry the prefix.
+    pub(crate) fn open_resolved<F>(names: &[&str], maxmsg: c_long, f: F) -> io::Result<Self>
+    where
+        F: Fn(&str, Msg) + Send + Sync + 'static,
+    {
+        let mut topics = Vec::with_capacity(names.len());
+        for name in names {
+            match open_queue(
+                name,
+                libc::O_CREAT | libc::O_RDONLY | libc::O_NONBLOCK,
+                Some(maxmsg),
+            ) {
+                Ok(mqd) => topics.push((name.to_string(), mqd)),
+                Err(err) => {
+                    close_all(&topics);
+                    return Err(err);
+                }
+            }
+        }
+
+        Self::start(topics, Arc::new(f))
+    }
+
+    /// Attach to every existing topic whose name matches `pattern`.
+    ///
+    /// The pattern is a shell-style glob over the full topic name
+    /// (`*` matches any run of characters, `?` a single one), e.g.
+    /// `"/motor_*"`. Like the names given to [`new`](Self::new), it is
+    /// written without `MQ_IPC_PREFIX`: under a prefix only that
+    /// namespace's queues are candidates. Only queues present at call
+    /// time are attached.
+    pub fn from_glob<F>(pattern: &str, f: F) -> io::Result<Self>
+    where
+        F: Fn(&str, Msg) + Send + Sync + 'static,
+    {
+        let root = defaults::topic_name("/");
+        let mut topics = Vec::new();
+        for name in list_topics()? {
+            let Some(rest) = name.strip_prefix(&*root) else {
+                continue;
+            };
+            if !glob_match(pattern, &format!("/{rest}")) {
+                continue;
+            }
+            match open_queue(&name, libc::O_RDONLY | libc::O_NONBLOCK, None) {
+                Ok(mqd) => topics.push((name, mqd)),
+                // Unlinked between listing and opening: just skip it.
+                Err(err) if err.raw_os_error() == Some(libc::ENOENT) => continue,
+                // Not a topic: a lock, an event or another format's queue.
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => continue,
+                Err(err) => {
+                    close_all(&topics);
+                    return Err(err);
+                }
+            }
+        }
+
+        Self::start(topics, Arc::new(f))
+    }
+
+    fn start(topics: Vec<(String, mqd_t)>, cb: MultiCallback) -> io::Result<Self> {
+        let mut pipe_fds = [0 as c_int; 2];
+        if unsafe { libc::pipe(pipe_fds.as_mut_ptr()) } == -1 {
+            let err = io::Error::last_os_error();
+            close_all(&topics);
+            return Err(err);
+        }
+        let [wake_rx, wake_tx] = pipe_fds;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let worker = Self::spawn_worker(topics.clone(), wake_rx, cb, Arc::clone(&running));
+
+        let wake_running = Arc::clone(&running);
+        let worker_id = register_worker(move || {
+            if wake_running.swap(false, Ordering::Relaxed) {
+                wake(wake_tx);
+            }
+        });
+
+        Ok(MultiSubscriber {
+            topics,
+            running,
+            wake_fd: wake_tx,
+            worker: Some(worker),
+            worker_id,
+        })
+    }
+
+    fn spawn_worker(
+        topics: Vec<(String, mqd_t)>,
+        wake_rx: c_int,
+        cb: MultiCallback,
+        running: Arc<AtomicBool>,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let mut fds: Vec<libc::pollfd> = std::iter::once(wake_rx)
+                .chain(topics.iter().map(|(_, mqd)| *mqd))
+                .map(|fd| libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+            let mut buf = RecvBuf::new();
+
+            'outer: loop {
+                let ret = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+                if ret < 0 {
+                    let err = io::Error::last_os_error();
+                    if err.raw_os_error() == Some(libc::EINTR) && running.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    if running.load(Ordering::Relaxed) {
+                        eprintln!("poll error: {err}");
+                    }
+                    break;
+                }
+
+                if !running.load(Ordering::Relaxed) || fds[0].revents != 0 {
+                    break;
+                }
+
+                for (i, (name, mqd)) in topics.iter().enumerate() {
+                    if fds[i + 1].revents & libc::POLLIN == 0 {
+                        continue;
+                    }
+
+                    // Drain everything queued; the queue is non-blocking so
+                    // losing a race with another reader just yields EAGAIN.
+                    loop {
+                        let ret = unsafe {
+                            libc::mq_receive(
+                                *mqd,
+                                buf.as_mut_ptr(),
+                                RecvBuf::LEN,
+                                std::ptr::null_mut(),
+                            )
+                        };
+                        if ret < 0 {
+                            let err = io::Error::last_os_error();
+                            match err.raw_os_error() {
+                                Some(libc::EAGAIN) | Some(libc::EINTR) => break,
+                                Some(libc::EBADF) => break 'outer,
+                                _ => {
+                                    eprintln!("mq_receive error on {name}: {err}");
+                                    break;
+                                }
+                            }
+                        }
+                        let msg = match buf.msg(ret as usize) {
+                            Ok(msg) => msg,
+                            Err(err) => {
+                                eprintln!("mq_receive on {name}: {err}");
+                                continue;
+                            }
+                        };
+
+                        // Wake-ups left behind by dropped `MqTopic` handles.
+                        if msg.hdr.msg_type == MSG_TYPE_SHUTDOWN {
+                            continue;
+                        }
+
+                        (cb)(name, msg);
+                    }
+                }
+            }
+
+            unsafe {
+                libc::close(wake_rx);
+            }
+        })
+    }
+
+    /// Names of the topics this subscriber is attached to.
+    pub fn topics(&self) -> impl Iterator<Item = &str> {
+        self.topics.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+#[cfg(feature = "callbacks")]
+impl Drop for MultiSubscriber {
+    fn drop(&mut self) {
+        unregister_worker(self.worker_id);
+        self.running.store(false, Ordering::Relaxed);
+        wake(self.wake_fd);
+
+        if let Some(handle) = self.worker.take() {
+            let _ = handle.join();
+        }
+
+        unsafe {
+            libc::close(self.wake_fd);
+        }
+        close_all(&self.topics);
+    }
+}
+
+#[cfg(feature = "callbacks")]
+fn wake(fd: c_int) {
+    let byte = 1u8;
+    unsafe {
+        libc::write(fd, &byte as *const u8 as *const libc::c_void, 1);
+    }
+}
+
+#[cfg(feature = "callbacks")]
+fn close_all(topics: &[(String, mqd_t)]) {
+    for (_, mqd) in topics {
+        unsafe {
+            libc::mq_close(*mqd);
+        }
+    }
+}
+
+/// List the names (with the leading '/') of all queues visible in
+/// [`MQUEUE_FS`].
+pub fn list_topics() -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(MQUEUE_FS)? {
+        let entry = entry?;
+        if let Some(name) = entry.file_name().to_str() {
+            names.push(format!("/{name}"));
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Minimal shell-style glob: `*` matches any run of characters and `?`
+/// matches exactly one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "callbacks")]
+    use crate::cleanup::TempTopic;
+    #[cfg(feature = "callbacks")]
+    use std::{sync::Mutex, time::Duration};
+
+    #[test]
+    fn glob_matching() {
+        assert!(glob_match("/motor_*", "/motor_state"));
+        assert!(glob_match("/*_state", "/motor_state"));
+        assert!(glob_match("/motor_?tate", "/motor_state"));
+        assert!(glob_match("*", "/anything"));
+        assert!(!glob_match("/motor_*", "/imu_raw"));
+        assert!(!glob_match("/motor", "/motor_state"));
+    }
+
+    #[test]
+    #[cfg(feature = "callbacks")]
+    fn receives_from_all_topics() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_multi_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_multi_b_");
+        let (a, b) = (tmp_a.name().to_string(), tmp_b.name().to_string());
+
+        {
+            let received: Arc<Mutex<Vec<(String, u16)>>> = Arc::new(Mutex::new(Vec::new()));
+            let received_clone = Arc::clone(&received);
+
+            let multi = MultiSubscriber::new(&[&a, &b], 4, move |name: &str, msg: Msg| {
+                received_clone
+                    .lock()
+                    .unwrap()
+                    .push((name.to_string(), msg.hdr.msg_type));
+            })
+            .expect("failed to create MultiSubscriber");
+            assert_eq!(multi.topics().count(), 2);
+
+            let raw_a = open_queue(&a, libc::O_WRONLY, None).unwrap();
+            let raw_b = open_queue(&b, libc::O_WRONLY, None).unwrap();
+            for (mqd, ty) in [(raw_a, 1u16), (raw_b, 2u16)] {
+                let msg = Msg::new(ty, &[]);
+                let rc = unsafe {
+                    libc::mq_send(
+                        mqd,
+                        &msg as *const Msg as *const std::os::raw::c_char,
+                        std::mem::size_of::<Msg>(),
+                        0,
+                    )
+                };
+                assert_eq!(rc, 0);
+                unsafe {
+                    libc::mq_close(mqd);
+                }
+            }
+
+            for _ in 0..50 {
+                if received.lock().unwrap().len() == 2 {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            let mut got = received.lock().unwrap().clone();
+            got.sort();
+            assert_eq!(got, vec![(a.clone(), 1), (b.clone(), 2)]);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Fixed-rate publishing from a dedicated timer thread.
+//!
+//! A [`PeriodicPublisher`] replaces the usual `loop { publish; sleep }`: it
+//! calls a closure once per period and publishes what it returns on a
+//! [`Topic`]. Deadlines are absolute, so the rate does not drift with the
+//! time spent in the closure, and how late each tick woke up is recorded
+//! in [`JitterStats`]:
+//!
+//! ```no_run
+//! # use mq_ipc::{periodic::{PeriodicOptions, PeriodicPublisher}, Topic};
+//! # use std::time::Duration;
+//! let topic = Topic::<f32>::new("/motor/position", 8)?;
+//! let mut angle = 0.0f32;
+//! let opts = PeriodicOptions::new(Duration::from_millis(10)).realtime(50);
+//! let publisher = PeriodicPublisher::new(topic, &opts, move || {
+//!     angle += 0.1;
+//!     angle
+//! })?;
+//! # std::thread::sleep(Duration::from_secs(1));
+//! println!("worst wake-up latency {:?}", publisher.stats().max);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! A tick that overruns whole periods does not try to catch up: the
+//! skipped deadlines are counted in [`JitterStats::missed`] and the next
+//! tick is the first deadline still in the future.
+
+use super::{clock, Topic};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Longest single sleep of the timer thread, so dropping a publisher with
+/// a long period does not wait for the next tick.
+const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+/// How a [`PeriodicPublisher`] ticks and publishes.
+#[derive(Copy, Clone, Debug)]
+pub struct PeriodicOptions {
+    pub period: Duration,
+    /// `msg_type` of the published messages.
+    pub msg_type: u16,
+    /// mqueue priority of the published messages.
+    pub prio: u32,
+    /// Run the timer thread under `SCHED_FIFO` at this priority.
+    pub realtime: Option<i32>,
+}
+
+impl PeriodicOptions {
+    pub fn new(period: Duration) -> Self {
+        PeriodicOptions {
+            period,
+            msg_type: 0,
+            prio: 0,
+            realtime: None,
+        }
+    }
+
+    pub fn msg_type(mut self, msg_type: u16) -> Self {
+        self.msg_type = msg_type;
+        self
+    }
+
+    pub fn prio(mut self, prio: u32) -> Self {
+        self.prio = prio;
+        self
+    }
+
+    /// Schedule the timer thread as `SCHED_FIFO` with `priority` (1-99).
+    /// [`PeriodicPublisher::new`] fails if the process may not do so,
+    /// typically for lack of `CAP_SYS_NICE` or an `RLIMIT_RTPRIO`.
+    pub fn realtime(mut self, priority: i32) -> Self {
+        self.realtime = Some(priority);
+        self
+    }
+}
+
+/// How punctually a [`PeriodicPublisher`] has been ticking.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct JitterStats {
+    pub ticks: u64,
+    /// Deadlines skipped because an earlier tick overran them.
+    pub missed: u64,
+    /// Ticks whose publish failed.
+    pub failed: u64,
+    /// Smallest and largest delay between a deadline and the wake-up.
+    pub min: Duration,
+    pub max: Duration,
+    /// Sum of all wake-up delays, for [`JitterStats::avg`].
+    pub total: Duration,
+}
+
+impl JitterStats {
+    /// Mean wake-up delay.
+    pub fn avg(&self) -> Duration {
+        if self.ticks == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.ticks as u32
+        }
+    }
+
+    fn record(&mut self, late: Duration) {
+        if self.ticks == 0 || late < self.min {
+            self.min = late;
+        }
+        self.max = self.max.max(late);
+        self.total += late;
+        self.ticks += 1;
+    }
+}
+
+/// Timer thread publishing a closure's result every period; stops when
+/// dropped.
+pub struct PeriodicPublisher<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    topic: Arc<Topic<T>>,
+    stats: Arc<Mutex<JitterStats>>,
+    running: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl<T> PeriodicPublisher<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    /// Publish `f()` on `topic` every `opts.period`, starting one period
+    /// from now.
+    pub fn new<F>(topic: Topic<T>, opts: &PeriodicOptions, mut f: F) -> io::Result<Self>
+    where
+        F: FnMut() -> T + Send + 'static,
+    {
+        Self::with_optional(topic, opts, move || Some(f()))
+    }
+
+    /// Like [`new`](Self::new), but ticks where `f` returns `None` publish
+    /// nothing. They still count in [`JitterStats::ticks`].
+    pub fn with_optional<F>(topic: Topic<T>, opts: &PeriodicOptions, mut f: F) -> io::Result<Self>
+    where
+        F: FnMut() -> Option<T> + Send + 'static,
+    {
+        if opts.period.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "period must not be zero",
+            ));
+        }
+
+        let topic = Arc::new(topic);
+        let stats = Arc::new(Mutex::new(JitterStats::default()));
+        let running = Arc::new(AtomicBool::new(true));
+        let clock = clock::default_clock();
+        let opts = *opts;
+        let (ready_tx, ready_rx) = mpsc::sync_channel(1);
+
+        let handle = {
+            let topic = Arc::clone(&topic);
+            let stats = Arc::clone(&stats);
+            let running = Arc::clone(&running);
+            thread::spawn(move || {
+                let ready = match opts.realtime {
+                    Some(priority) => set_fifo(priority),
+                    None => Ok(()),
+                };
+                let failed = ready.is_err();
+                let _ = ready_tx.send(ready);
+                if failed {
+                    return;
+                }
+
+                let mut deadline = clock.now() + opts.period;
+                while running.load(Ordering::Relaxed) {
+                    let now = clock.now();
+                    if now < deadline {
+                        clock.sleep_until(deadline.min(now + MAX_SLEEP));
+                        continue;
+                    }
+
+                    let ok = match f() {
+                        Some(value) => topic.publish(&value, opts.msg_type, opts.prio).is_ok(),
+                        None => true,
+                    };
+
+                    let mut st = stats.lock().unwrap_or_else(|e| e.into_inner());
+                    st.record(now - deadline);
+                    st.failed += u64::from(!ok);
+                    deadline += opts.period;
+                    if now >= deadline {
+                        let behind =
+                            ((now - deadline).as_nanos() / opts.period.as_nanos()) as u32 + 1;
+                        st.missed += u64::from(behind);
+                        deadline += opts.period * behind;
+                    }
+                }
+            })
+        };
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                let _ = handle.join();
+                return Err(err);
+            }
+            Err(_) => return Err(io::Error::other("timer thread exited")),
+        }
+
+        Ok(PeriodicPublisher {
+            topic,
+            stats,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// Jitter statistics so far.
+    pub fn stats(&self) -> JitterStats {
+        *self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The topic values are published on.
+    pub fn topic(&self) -> &Topic<T> {
+        &self.topic
+    }
+}
+
+impl<T> Drop for PeriodicPublisher<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn set_fifo(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    let rc = unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    if rc != 0 {
+        return Err(io::Error::from_raw_os_error(rc));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    #[test]
+    fn publishes_every_period() {
+        let tmp = TempTopic::new("/mq_ipc_test_periodic_");
+        let rx: Topic<u32> = Topic::new(tmp.name(), 8).unwrap();
+        let (tx, values) = mpsc::channel();
+        rx.subscribe(move |v| tx.send(v).unwrap());
+
+        let zero = PeriodicOptions::new(Duration::ZERO);
+        let err = PeriodicPublisher::new(Topic::<u32>::new(tmp.name(), 8).unwrap(), &zero, || 0);
+        assert_eq!(err.err().unwrap().kind(), io::ErrorKind::InvalidInput);
+
+        let mut n = 0u32;
+        let opts = PeriodicOptions::new(Duration::from_millis(5)).msg_type(7);
+        let publisher =
+            PeriodicPublisher::new(Topic::new(tmp.name(), 8).unwrap(), &opts, move || {
+                n += 1;
+                n
+            })
+            .unwrap();
+
+        let got: Vec<u32> = (0..5)
+            .map(|_| values.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        assert_eq!(got, vec![1, 2, 3, 4, 5]);
+
+        assert_eq!(publisher.topic().raw().name(), tmp.name());
+        let stats = publisher.stats();
+        assert!(stats.ticks >= 5);
+        assert_eq!(stats.failed, 0);
+        assert!(stats.min <= stats.avg() && stats.avg() <= stats.max);
+    }
+}
@@ -0,0 +1,281 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Round-trip latency probes between two processes.
+//!
+//! A [`Ping`] publishes timestamped [`Probe`]s on [`PING_TOPIC`] and an
+//! [`EchoResponder`] in the other process sends each one straight back
+//! on [`PONG_TOPIC`]. Only the pinger's clock is read, so the two sides
+//! need no time synchronisation.
+//!
+//! A queue hands every message to exactly one reader, so run one pinger
+//! per pong queue; use [`Ping::with_topics`] to measure several pairs.
+
+use super::{
+    clock::{self, Clock},
+    Topic,
+};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    os::raw::c_long,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const PING_TOPIC: &str = "/topic.ping";
+pub const PONG_TOPIC: &str = "/topic.pong";
+
+const MSG_TYPE_PROBE: u16 = 1;
+
+/// Wire format of a probe; the responder echoes it unchanged.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct Probe {
+    pub seq: u64,
+    /// Pinger clock reading at send time, in nanoseconds.
+    pub sent_ns: u64,
+    /// Identifies the pinger, so stray replies are not counted.
+    pub origin: u64,
+}
+
+/// Round-trip statistics collected by a [`Ping`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RttStats {
+    pub sent: u64,
+    pub received: u64,
+    pub min: Duration,
+    pub max: Duration,
+    /// Sum of all round trips, for [`RttStats::avg`].
+    pub total: Duration,
+}
+
+impl RttStats {
+    /// Mean round-trip time.
+    pub fn avg(&self) -> Duration {
+        if self.received == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total.as_nanos() / self.received as u128) as u64)
+        }
+    }
+
+    /// Probes that never came back (or have not yet).
+    pub fn lost(&self) -> u64 {
+        self.sent.saturating_sub(self.received)
+    }
+
+    fn record(&mut self, rtt: Duration) {
+        if self.received == 0 || rtt < self.min {
+            self.min = rtt;
+        }
+        self.max = self.max.max(rtt);
+        self.total += rtt;
+        self.received += 1;
+    }
+}
+
+#[derive(Default)]
+struct State {
+    stats: RttStats,
+    last: Option<(u64, Duration)>,
+}
+
+static NEXT_ORIGIN: AtomicU64 = AtomicU64::new(0);
+
+/// Latency probe sender.
+pub struct Ping {
+    ping: Topic<Probe>,
+    _pong: Topic<Probe>,
+    origin: u64,
+    seq: AtomicU64,
+    clock: Arc<dyn Clock>,
+    state: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl Ping {
+    /// Probe over [`PING_TOPIC`] / [`PONG_TOPIC`].
+    pub fn new(maxmsg: c_long) -> io::Result<Self> {
+        Self::with_topics(PING_TOPIC, PONG_TOPIC, maxmsg)
+    }
+
+    /// Probe over an explicit pair of topics.
+    pub fn with_topics(ping: &str, pong: &str, maxmsg: c_long) -> io::Result<Self> {
+        let ping = Topic::new(ping, maxmsg)?;
+        let pong: Topic<Probe> = Topic::new(pong, maxmsg)?;
+        let origin = (std::process::id() as u64) << 32
+            | NEXT_ORIGIN.fetch_add(1, Ordering::Relaxed) & 0xFFFF_FFFF;
+        let clock = clock::default_clock();
+        let state = Arc::new((Mutex::new(State::default()), Condvar::new()));
+
+        let cb_clock = Arc::clone(&clock);
+        let cb_state = Arc::clone(&state);
+        pong.subscribe(move |probe: Probe| {
+            if probe.origin != origin {
+                return;
+            }
+            let rtt = cb_clock
+                .now()
+                .saturating_sub(Duration::from_nanos(probe.sent_ns));
+
+            let (lock, cvar) = &*cb_state;
+            let mut st = lock.lock().unwrap();
+            st.stats.record(rtt);
+            st.last = Some((probe.seq, rtt));
+            cvar.notify_all();
+        });
+
+        Ok(Ping {
+            ping,
+            _pong: pong,
+            origin,
+            seq: AtomicU64::new(0),
+            clock,
+            state,
+        })
+    }
+
+    /// Send one probe without waiting for its reply. Returns its sequence
+    /// number.
+    pub fn probe(&self) -> io::Result<u64> {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let probe = Probe {
+            seq,
+            sent_ns: self.clock.now().as_nanos() as u64,
+            origin: self.origin,
+        };
+        self.ping.publish(&probe, MSG_TYPE_PROBE, 0)?;
+        self.state.0.lock().unwrap().stats.sent += 1;
+        Ok(seq)
+    }
+
+    /// Send one probe and wait for it to come back.
+    pub fn round_trip(&self, timeout: Duration) -> io::Result<Duration> {
+        let seq = self.probe()?;
+        let deadline = Instant::now() + timeout;
+
+        let (lock, cvar) = &*self.state;
+        let mut st = lock.lock().unwrap();
+        loop {
+            if let Some((last, rtt)) = st.last
+                && last == seq
+            {
+                return Ok(rtt);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("probe {seq} not echoed"),
+                ));
+            }
+            st = cvar.wait_timeout(st, left).unwrap().0;
+        }
+    }
+
+    /// Run `count` round trips spaced by `interval` and return the
+    /// accumulated statistics. Lost probes are counted, not fatal.
+    pub fn run(&self, count: usize, interval: Duration, timeout: Duration) -> io::Result<RttStats> {
+        for i in 0..count {
+            match self.round_trip(timeout) {
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {}
+                Err(err) => return Err(err),
+            }
+            if i + 1 < count {
+                thread::sleep(interval);
+            }
+        }
+        Ok(self.stats())
+    }
+
+    /// Statistics so far.
+    pub fn stats(&self) -> RttStats {
+        self.state.0.lock().unwrap().stats
+    }
+
+    /// Forget all collected statistics.
+    pub fn reset(&self) {
+        *self.state.0.lock().unwrap() = State::default();
+    }
+}
+
+/// Echoes every probe from the ping topic back on the pong topic.
+pub struct EchoResponder {
+    _ping: Topic<Probe>,
+    _pong: Arc<Topic<Probe>>,
+}
+
+impl EchoResponder {
+    /// Answer on [`PING_TOPIC`] / [`PONG_TOPIC`].
+    pub fn new(maxmsg: c_long) -> io::Result<Self> {
+        Self::with_topics(PING_TOPIC, PONG_TOPIC, maxmsg)
+    }
+
+    /// Answer on an explicit pair of topics.
+    pub fn with_topics(ping: &str, pong: &str, maxmsg: c_long) -> io::Result<Self> {
+        let ping: Topic<Probe> = Topic::new(ping, maxmsg)?;
+        let pong = Arc::new(Topic::new(pong, maxmsg)?);
+
+        let echo = Arc::clone(&pong);
+        ping.subscribe(move |probe: Probe| {
+            if let Err(err) = echo.publish(&probe, MSG_TYPE_PROBE, 0) {
+                eprintln!("ping echo failed: {err}");
+            }
+        });
+
+        Ok(EchoResponder {
+            _ping: ping,
+            _pong: pong,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    #[test]
+    fn round_trips_through_responder() {
+        let ping_name = TempTopic::new("/mq_ipc_test_ping_");
+        let pong_name = TempTopic::new("/mq_ipc_test_pong_");
+
+        let _echo = EchoResponder::with_topics(ping_name.name(), pong_name.name(), 4)
+            .expect("failed to create responder");
+        let ping = Ping::with_topics(ping_name.name(), pong_name.name(), 4)
+            .expect("failed to create pinger");
+
+        let stats = ping
+            .run(5, Duration::from_millis(1), Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(stats.sent, 5);
+        assert_eq!(stats.received, 5);
+        assert_eq!(stats.lost(), 0);
+        assert!(stats.min <= stats.avg() && stats.avg() <= stats.max);
+    }
+}
@@ -0,0 +1,487 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Best-effort discovery registry of who publishes and subscribes where.
+//!
+//! POSIX mqueues cannot report their readers, so every endpoint drops a
+//! small entry file under [`root`]: one directory per topic, one file per
+//! publisher/subscriber handle. Entries carry the owning PID and its start
+//! time, which lets readers ignore (and the janitor remove) entries left
+//! behind by processes that died without cleaning up.
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+/// Environment variable overriding the registry directory.
+pub const REGISTRY_DIR_ENV: &str = "MQ_IPC_REGISTRY_DIR";
+
+/// Which side of a topic an endpoint is on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    Publisher,
+    Subscriber,
+}
+
+impl Role {
+    fn as_str(self) -> &'static str {
+        match self {
+            Role::Publisher => "pub",
+            Role::Subscriber => "sub",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pub" => Some(Role::Publisher),
+            "sub" => Some(Role::Subscriber),
+            _ => None,
+        }
+    }
+}
+
+/// One registered publisher or subscriber.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    pub topic: String,
+    pub role: Role,
+    pub pid: u32,
+    /// Process start time (clock ticks since boot, from `/proc/<pid>/stat`).
+    pub start_time: u64,
+    /// Process name of the owner, for humans.
+    pub node: String,
+    /// Location of the entry file.
+    pub path: PathBuf,
+}
+
+impl Endpoint {
+    /// Name of the entry, unique among the topic's endpoints.
+    pub fn key(&self) -> &str {
+        entry_key(&self.path)
+    }
+
+    /// Whether the owning process is still the one that wrote the entry.
+    pub fn is_alive(&self) -> bool {
+        proc_start_time(self.pid) == Some(self.start_time)
+    }
+}
+
+/// Registry directory: `$MQ_IPC_REGISTRY_DIR`, else `/dev/shm/mq_ipc`
+/// (tmpfs, so it vanishes on reboot like the queues themselves).
+pub fn root() -> PathBuf {
+    if let Some(dir) = std::env::var_os(REGISTRY_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    let shm = Path::new("/dev/shm");
+    if shm.is_dir() {
+        shm.join("mq_ipc")
+    } else {
+        std::env::temp_dir().join("mq_ipc")
+    }
+}
+
+/// Directory holding the entries of `topic`.
+pub(crate) fn topic_dir(topic: &str) -> PathBuf {
+    root().join(encode_topic(topic))
+}
+
+fn encode_topic(topic: &str) -> String {
+    topic.replace('%', "%25").replace('/', "%2F")
+}
+
+fn decode_topic(dir: &str) -> String {
+    dir.replace("%2F", "/").replace("%25", "%")
+}
+
+/// Start time of `pid` in clock ticks since boot, if it is running.
+pub fn proc_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // `comm` may contain spaces; fields restart after the closing paren.
+    let rest = &stat[stat.rfind(')')? + 2..];
+    // starttime is field 22 overall, i.e. the 20th after `comm`.
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+fn proc_name() -> String {
+    fs::read_to_string("/proc/self/comm")
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+static NEXT_ENTRY: AtomicU32 = AtomicU32::new(0);
+
+/// RAII registry entry; removed again on drop.
+#[derive(Debug)]
+pub struct Registration {
+    path: PathBuf,
+}
+
+impl Registration {
+    /// Location of the entry file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Name of the entry, unique among the topic's endpoints.
+    pub fn key(&self) -> &str {
+        entry_key(&self.path)
+    }
+}
+
+fn entry_key(path: &Path) -> &str {
+    path.file_name().and_then(|n| n.to_str()).unwrap_or("")
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Record this process as a `role` endpoint of `topic`.
+pub fn register(topic: &str, role: Role) -> io::Result<Registration> {
+    let pid = std::process::id();
+    let start_time = proc_start_time(pid).unwrap_or(0);
+
+    let dir = topic_dir(topic);
+    fs::create_dir_all(&dir)?;
+
+    let n = NEXT_ENTRY.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!("{}-{pid}-{n}", role.as_str()));
+    let body = entry_body(pid, start_time, role);
+
+    // Write then rename so readers never see a half-written entry.
+    let tmp = dir.join(format!(".tmp-{pid}-{n}"));
+    fs::write(&tmp, body)?;
+    fs::rename(&tmp, &path)?;
+
+    Ok(Registration { path })
+}
+
+fn entry_body(pid: u32, start_time: u64, role: Role) -> String {
+    format!(
+        "pid={pid}\nstart={start_time}\nrole={}\nnode={}\n",
+        role.as_str(),
+        proc_name()
+    )
+}
+
+/// Error returned when a topic is owned by a publisher in another
+/// process. Carried inside an [`io::Error`] of kind `AddrInUse`; get it
+/// back with `err.get_ref().and_then(|e| e.downcast_ref::<ExclusiveOwner>())`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExclusiveOwner {
+    pub topic: String,
+    pub pid: u32,
+    pub node: String,
+}
+
+impl fmt::Display for ExclusiveOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is exclusively owned by {} (pid {})",
+            self.topic, self.node, self.pid
+        )
+    }
+}
+
+impl Error for ExclusiveOwner {}
+
+impl ExclusiveOwner {
+    fn from_endpoint(ep: Endpoint) -> Self {
+        ExclusiveOwner {
+            topic: ep.topic,
+            pid: ep.pid,
+            node: ep.node,
+        }
+    }
+
+    pub(crate) fn into_io(self) -> io::Error {
+        io::Error::new(io::ErrorKind::AddrInUse, self)
+    }
+}
+
+fn owner_path(topic: &str) -> PathBuf {
+    // Dot-prefixed so endpoint listings skip it.
+    topic_dir(topic).join(".owner")
+}
+
+/// Live exclusive owner of `topic`, if any.
+pub fn owner(topic: &str) -> io::Result<Option<Endpoint>> {
+    match parse_entry(topic, owner_path(topic)) {
+        Some(ep) if ep.is_alive() => Ok(Some(ep)),
+        _ => Ok(None),
+    }
+}
+
+/// RAII ownership of a topic; released when the last handle of this
+/// process that claimed it is dropped.
+#[derive(Debug)]
+pub struct OwnerClaim {
+    path: PathBuf,
+}
+
+/// Owner files this process holds, with the number of [`OwnerClaim`]s
+/// sharing each.
+struct Held {
+    path: PathBuf,
+    pid: u32,
+    handles: usize,
+}
+
+static HELD: Mutex<Vec<Held>> = Mutex::new(Vec::new());
+
+impl Drop for OwnerClaim {
+    fn drop(&mut self) {
+        let pid = std::process::id();
+        let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        // A forked child inherits the handle but not the claim.
+        let Some(i) = held
+            .iter()
+            .position(|h| h.path == self.path && h.pid == pid)
+        else {
+            return;
+        };
+        held[i].handles -= 1;
+        if held[i].handles == 0 {
+            held.swap_remove(i);
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Record this process as the exclusive publisher of `topic`.
+///
+/// Fails with [`ExclusiveOwner`] if a live process other than this one
+/// owns it; an owner that died is replaced. Claims of one process are
+/// shared, and the topic stays owned until all of them are dropped.
+pub fn claim_owner(topic: &str) -> io::Result<OwnerClaim> {
+    let pid = std::process::id();
+    let path = owner_path(topic);
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    held.retain(|h| h.pid == pid);
+    if let Some(h) = held.iter_mut().find(|h| h.path == path) {
+        h.handles += 1;
+        return Ok(OwnerClaim { path });
+    }
+
+    let start_time = proc_start_time(pid).unwrap_or(0);
+    let dir = path.parent().expect("owner file has a parent");
+    fs::create_dir_all(dir)?;
+
+    let n = NEXT_ENTRY.fetch_add(1, Ordering::Relaxed);
+    let tmp = dir.join(format!(".tmp-{pid}-{n}"));
+    fs::write(&tmp, entry_body(pid, start_time, Role::Publisher))?;
+
+    let result = loop {
+        // `link` refuses to replace an existing file, unlike `rename`.
+        match fs::hard_link(&tmp, &path) {
+            Ok(()) => break Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => break Err(err),
+        }
+        match owner(topic)? {
+            // Left behind by this process, e.g. by a leaked handle.
+            Some(ep) if ep.pid == pid => break Ok(()),
+            Some(ep) => break Err(ExclusiveOwner::from_endpoint(ep).into_io()),
+            // Stale claim of a dead process (or one vanishing right now).
+            None => match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => break Err(err),
+            },
+        }
+    };
+
+    let _ = fs::remove_file(&tmp);
+    result?;
+    held.push(Held {
+        path: path.clone(),
+        pid,
+        handles: 1,
+    });
+    Ok(OwnerClaim { path })
+}
+
+/// Fail with [`ExclusiveOwner`] if `topic` is owned by another process.
+pub fn check_owner(topic: &str) -> io::Result<()> {
+    match owner(topic)? {
+        Some(ep) if ep.pid != std::process::id() => {
+            Err(ExclusiveOwner::from_endpoint(ep).into_io())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parse_entry(topic: &str, path: PathBuf) -> Option<Endpoint> {
+    let body = fs::read_to_string(&path).ok()?;
+    let mut pid = None;
+    let mut start = None;
+    let mut role = None;
+    let mut node = String::new();
+
+    for line in body.lines() {
+        match line.split_once('=') {
+            Some(("pid", v)) => pid = v.parse().ok(),
+            Some(("start", v)) => start = v.parse().ok(),
+            Some(("role", v)) => role = Role::parse(v),
+            Some(("node", v)) => node = v.to_string(),
+            _ => {}
+        }
+    }
+
+    Some(Endpoint {
+        topic: topic.to_string(),
+        role: role?,
+        pid: pid?,
+        start_time: start?,
+        node,
+        path,
+    })
+}
+
+/// All entries for `topic`, including stale ones from dead processes.
+pub fn all_endpoints(topic: &str) -> io::Result<Vec<Endpoint>> {
+    let dir = topic_dir(topic);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut out = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if let Some(ep) = parse_entry(topic, entry.path()) {
+            out.push(ep);
+        }
+    }
+    Ok(out)
+}
+
+/// Live endpoints registered on `topic`.
+pub fn endpoints(topic: &str) -> io::Result<Vec<Endpoint>> {
+    let mut eps = all_endpoints(topic)?;
+    eps.retain(Endpoint::is_alive);
+    Ok(eps)
+}
+
+/// Number of live subscribers registered on `topic`, across all processes.
+pub fn subscriber_count(topic: &str) -> io::Result<usize> {
+    Ok(endpoints(topic)?
+        .iter()
+        .filter(|ep| ep.role == Role::Subscriber)
+        .count())
+}
+
+/// Every topic that has (or had) entries in the registry.
+pub fn topics() -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(root()) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut out = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let topic = decode_topic(&entry.file_name().to_string_lossy());
+        // Other state (e.g. `seq/`) shares the root; topics start with '/'.
+        if entry.file_type()?.is_dir() && topic.starts_with('/') {
+            out.push(topic);
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_and_count() {
+        let topic = format!("/mq_ipc_test_registry_{}", std::process::id());
+
+        assert_eq!(subscriber_count(&topic).unwrap(), 0);
+        {
+            let _pub = register(&topic, Role::Publisher).unwrap();
+            let _s1 = register(&topic, Role::Subscriber).unwrap();
+            let _s2 = register(&topic, Role::Subscriber).unwrap();
+
+            assert_eq!(subscriber_count(&topic).unwrap(), 2);
+            let eps = endpoints(&topic).unwrap();
+            assert_eq!(eps.len(), 3);
+            assert!(eps.iter().all(|ep| ep.pid == std::process::id()));
+            assert!(topics().unwrap().contains(&topic));
+        }
+        assert_eq!(subscriber_count(&topic).unwrap(), 0);
+
+        let _ = fs::remove_dir(root().join(encode_topic(&topic)));
+    }
+
+    #[test]
+    fn owner_claim_is_exclusive_and_released() {
+        let topic = format!("/mq_ipc_test_owner_{}", std::process::id());
+        {
+            let claim = claim_owner(&topic).unwrap();
+            assert_eq!(owner(&topic).unwrap().unwrap().pid, std::process::id());
+            // Same process: shared, and not counted as an endpoint.
+            let again = claim_owner(&topic).unwrap();
+            assert!(check_owner(&topic).is_ok());
+            assert!(endpoints(&topic).unwrap().is_empty());
+            drop(claim);
+            assert_eq!(owner(&topic).unwrap().unwrap().pid, std::process::id());
+            drop(again);
+        }
+        assert!(owner(&topic).unwrap().is_none());
+
+        // A claim left by a dead process is taken over.
+        let dir = root().join(encode_topic(&topic));
+        fs::write(
+            dir.join(".owner"),
+            "pid=999999999\nstart=1\nrole=pub\nnode=x\n",
+        )
+        .unwrap();
+        let _claim = claim_owner(&topic).unwrap();
+        drop(_claim);
+        let _ = fs::remove_dir(dir);
+    }
+
+    #[test]
+    fn topic_name_encoding_roundtrips() {
+        for name in ["/motor_state", "/a/b", "/odd%2Fname"] {
+            assert_eq!(decode_topic(&encode_topic(name)), name);
+        }
+    }
+}
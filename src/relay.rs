@@ -0,0 +1,214 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Republish one local topic onto another.
+//!
+//! A [`Relay`] is a subscriber on its source and a publisher on its sink,
+//! optionally passing every message through a transform on the way. It
+//! covers renaming a topic without touching either side, fanning several
+//! sources into one sink (one relay each), and adapting message layouts
+//! between producers and consumers that disagree.
+
+use super::{current_priority, defaults, MqTopic, Msg, TopicOptions};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// What a [`Relay`] has done so far.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    /// Messages published on the sink.
+    pub forwarded: u64,
+    /// Messages the transform chose to drop.
+    pub dropped: u64,
+    /// Messages lost because publishing on the sink failed.
+    pub failed: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    forwarded: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Forwards messages from a source topic to a sink topic until dropped.
+///
+/// Forwarding runs on the source topic's worker thread, so it stops with
+/// the process-wide [`shutdown`](crate::shutdown) like any subscriber. A
+/// panicking transform loses that message only. Failed publishes follow
+/// the sink's [`retry`](TopicOptions::retry) policy and are then counted
+/// in [`RelayStats::failed`].
+pub struct Relay {
+    source: MqTopic,
+    sink: Arc<MqTopic>,
+    counters: Arc<Counters>,
+}
+
+impl Relay {
+    /// Forward every message unchanged from `from` to `to`.
+    pub fn new(from: &str, to: &str, opts: &TopicOptions) -> io::Result<Self> {
+        Self::with_transform(from, to, opts, Some)
+    }
+
+    /// Forward `f(msg)` for every message, skipping those mapped to
+    /// `None`.
+    ///
+    /// Both topics are opened with `opts`. Relaying a topic onto itself
+    /// is rejected with `InvalidInput`.
+    pub fn with_transform<F>(from: &str, to: &str, opts: &TopicOptions, f: F) -> io::Result<Self>
+    where
+        F: Fn(Msg) -> Option<Msg> + Send + Sync + 'static,
+    {
+        if defaults::topic_name(from) == defaults::topic_name(to) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot relay {from} onto itself"),
+            ));
+        }
+
+        let sink = Arc::new(MqTopic::with_options(to, opts)?);
+        let source = MqTopic::with_options(from, opts)?;
+        let counters = Arc::new(Counters::default());
+
+        let out = Arc::clone(&sink);
+        let count = Arc::clone(&counters);
+        source.subscribe(move |msg| {
+            let Some(msg) = f(msg) else {
+                count.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            };
+            match out.publish(&msg, current_priority().unwrap_or(0)) {
+                Ok(()) => count.forwarded.fetch_add(1, Ordering::Relaxed),
+                Err(err) => {
+                    eprintln!("relay to {} failed: {err}", out.name());
+                    count.failed.fetch_add(1, Ordering::Relaxed)
+                }
+            };
+        });
+
+        Ok(Relay {
+            source,
+            sink,
+            counters,
+        })
+    }
+
+    /// Name of the topic being read.
+    pub fn source(&self) -> &str {
+        self.source.name()
+    }
+
+    /// Name of the topic being written.
+    pub fn sink(&self) -> &str {
+        self.sink.name()
+    }
+
+    /// Counters since the relay was created.
+    pub fn stats(&self) -> RelayStats {
+        RelayStats {
+            forwarded: self.counters.forwarded.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::mpsc, thread, time::Duration};
+
+    #[test]
+    fn relays_with_transform() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_relay_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_relay_b_");
+        let opts = TopicOptions::new(8);
+
+        assert!(Relay::new(tmp_a.name(), tmp_a.name(), &opts).is_err());
+
+        let relay = Relay::with_transform(tmp_a.name(), tmp_b.name(), &opts, |msg| {
+            (msg.hdr.msg_type != 0).then(|| Msg::new(msg.hdr.msg_type + 100, &[]))
+        })
+        .unwrap();
+
+        let sink = MqTopic::new(tmp_b.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        sink.subscribe(move |msg| tx.send(msg.hdr.msg_type).unwrap());
+
+        let source = MqTopic::new(tmp_a.name(), 8).unwrap();
+        for ty in [1, 0, 2] {
+            source.publish(&Msg::new(ty, &[]), 0).unwrap();
+        }
+
+        let got: Vec<u16> = (0..2)
+            .map(|_| rx.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        assert_eq!(got, vec![101, 102]);
+        for _ in 0..50 {
+            if relay.stats().forwarded == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            relay.stats(),
+            RelayStats {
+                forwarded: 2,
+                dropped: 1,
+                failed: 0
+            }
+        );
+    }
+
+    #[test]
+    fn inherits_priority() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_relay_prio_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_relay_prio_b_");
+        let relay = Relay::new(tmp_a.name(), tmp_b.name(), &TopicOptions::new(8)).unwrap();
+
+        let source = MqTopic::new(tmp_a.name(), 8).unwrap();
+        source.publish(&Msg::new(1, &[]), 0).unwrap();
+        source.publish(&Msg::new(2, &[]), 9).unwrap();
+        for _ in 0..50 {
+            if relay.stats().forwarded == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Both wait in the sink; the urgent one must come out first.
+        let sink = MqTopic::new(tmp_b.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        sink.subscribe(move |msg| tx.send((msg.hdr.msg_type, current_priority())).unwrap());
+        let got: Vec<_> = (0..2)
+            .map(|_| rx.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        assert_eq!(got, vec![(2, Some(9)), (1, Some(0))]);
+    }
+}
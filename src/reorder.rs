@@ -0,0 +1,329 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Per-publisher sequence numbers and in-order delivery.
+//!
+//! mqueues hand out higher priorities first, and links may reorder
+//! frames, so subscribers can see a publisher's messages out of order.
+//! Publishers on topics created with
+//! [`TopicOptions::sequenced`](crate::TopicOptions::sequenced) number
+//! their messages per handle (`source`, `seq`) in the extended header.
+//! A subscribing handle created with
+//! [`TopicOptions::reorder`](crate::TopicOptions::reorder) holds back
+//! messages that arrive ahead of a gap and releases them in sequence.
+//!
+//! A gap is given up on once [`ReorderOptions::window`] messages are
+//! waiting behind it or the oldest has waited
+//! [`ReorderOptions::max_delay`]; stragglers arriving after that are
+//! dropped, since delivering them would break the order. Unsequenced
+//! messages are passed through as they come.
+
+use super::{
+    dedup,
+    ext::{ExtHeader, EXT_SEQ},
+    Msg,
+};
+#[cfg(feature = "callbacks")]
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Instant,
+};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// How long a subscriber waits for missing sequence numbers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReorderOptions {
+    /// Messages held behind a gap before it is skipped.
+    pub window: usize,
+    /// Longest a message is held behind a gap.
+    pub max_delay: Duration,
+}
+
+impl ReorderOptions {
+    pub fn new(window: usize, max_delay: Duration) -> Self {
+        ReorderOptions { window, max_delay }
+    }
+}
+
+impl Default for ReorderOptions {
+    fn default() -> Self {
+        ReorderOptions::new(16, Duration::from_millis(50))
+    }
+}
+
+/// `(source, seq)` of `msg`, if its publisher numbered it.
+pub fn sequence_of(msg: &Msg) -> Option<(u32, u64)> {
+    msg.ext()
+        .filter(|ext| ext.has(EXT_SEQ))
+        .map(|ext| (ext.source, ext.seq))
+}
+
+/// Numbers the messages of one publishing handle.
+pub(crate) struct Sequencer {
+    source: u32,
+    next: AtomicU64,
+}
+
+impl Sequencer {
+    pub(crate) fn new() -> Self {
+        Sequencer {
+            source: dedup::unique_seed() as u32,
+            next: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stamp(&self, ext: &mut ExtHeader) {
+        ext.present |= EXT_SEQ;
+        ext.source = self.source;
+        ext.seq = self.next.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A received message and the priority it was sent with.
+#[cfg(feature = "callbacks")]
+pub(crate) type Received = (Msg, u32);
+
+#[cfg(feature = "callbacks")]
+struct Source {
+    next: u64,
+    // `None` marks a message that was dispatched out of band.
+    held: BTreeMap<u64, (Instant, Option<Received>)>,
+}
+
+#[cfg(feature = "callbacks")]
+impl Source {
+    fn release(&mut self, out: &mut Vec<Received>) {
+        while let Some(entry) = self.held.first_entry() {
+            if *entry.key() != self.next {
+                break;
+            }
+            out.extend(entry.remove().1);
+            self.next += 1;
+        }
+    }
+
+    /// Give up on the current gap.
+    fn skip_gap(&mut self, out: &mut Vec<Received>) {
+        if let Some(first) = self.held.keys().next() {
+            self.next = *first;
+            self.release(out);
+        }
+    }
+
+    fn oldest(&self) -> Option<Instant> {
+        self.held.values().map(|(at, _)| *at).min()
+    }
+}
+
+/// Reordering state of one subscribing handle.
+#[cfg(feature = "callbacks")]
+pub(crate) struct Reorderer {
+    opts: ReorderOptions,
+    sources: HashMap<u32, Source>,
+}
+
+#[cfg(feature = "callbacks")]
+impl Reorderer {
+    pub(crate) fn new(opts: ReorderOptions) -> Self {
+        Reorderer {
+            opts,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Accept `item`, appending whatever is now deliverable to `out`.
+    pub(crate) fn push(&mut self, item: Received, now: Instant, out: &mut Vec<Received>) {
+        match sequence_of(&item.0) {
+            Some((source, seq)) => self.hold(source, seq, Some(item), now, out),
+            None => out.push(item),
+        }
+    }
+
+    /// Account for `msg`, which the caller dispatched without waiting, so
+    /// the messages numbered after it are not held back by its absence.
+    pub(crate) fn push_dispatched(&mut self, msg: &Msg, now: Instant, out: &mut Vec<Received>) {
+        if let Some((source, seq)) = sequence_of(msg) {
+            self.hold(source, seq, None, now, out);
+        }
+    }
+
+    fn hold(
+        &mut self,
+        source: u32,
+        seq: u64,
+        item: Option<Received>,
+        now: Instant,
+        out: &mut Vec<Received>,
+    ) {
+        // Publishers count from 0. Joining later costs one skipped gap.
+        let src = self.sources.entry(source).or_insert(Source {
+            next: 0,
+            held: BTreeMap::new(),
+        });
+        if seq < src.next {
+            return;
+        }
+
+        src.held.insert(seq, (now, item));
+        src.release(out);
+        while src.held.len() > self.opts.window {
+            src.skip_gap(out);
+        }
+    }
+
+    /// Release messages that have waited `max_delay` behind a gap.
+    pub(crate) fn expire(&mut self, now: Instant, out: &mut Vec<Received>) {
+        for src in self.sources.values_mut() {
+            while src
+                .oldest()
+                .is_some_and(|at| now.duration_since(at) >= self.opts.max_delay)
+            {
+                src.skip_gap(out);
+            }
+        }
+    }
+
+    /// When [`expire`](Self::expire) next has something to do.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.sources
+            .values()
+            .filter_map(Source::oldest)
+            .min()
+            .map(|at| at + self.opts.max_delay)
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, MqTopic, TopicOptions};
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    fn numbered(seq: u64) -> Received {
+        let ext = ExtHeader {
+            present: EXT_SEQ,
+            source: 9,
+            seq,
+            ..Default::default()
+        };
+        (Msg::with_ext(seq as u16, &ext, &[]), 0)
+    }
+
+    fn types(out: &mut Vec<Received>) -> Vec<u16> {
+        out.drain(..).map(|(m, _)| m.hdr.msg_type).collect()
+    }
+
+    #[test]
+    fn holds_until_gap_fills_window_overflows_or_times_out() {
+        let opts = ReorderOptions::new(2, Duration::from_millis(10));
+        let mut r = Reorderer::new(opts);
+        let mut out = Vec::new();
+        let t0 = Instant::now();
+
+        r.push(numbered(0), t0, &mut out);
+        r.push(numbered(2), t0, &mut out);
+        assert_eq!(types(&mut out), vec![0]);
+        r.push(numbered(1), t0, &mut out);
+        assert_eq!(types(&mut out), vec![1, 2]);
+
+        // Window overflow skips the gap at 3.
+        r.push(numbered(4), t0, &mut out);
+        r.push(numbered(5), t0, &mut out);
+        r.push(numbered(6), t0, &mut out);
+        assert_eq!(types(&mut out), vec![4, 5, 6]);
+        r.push(numbered(3), t0, &mut out);
+        assert!(out.is_empty());
+
+        // Timeout skips the gap at 7.
+        r.push(numbered(8), t0, &mut out);
+        assert_eq!(r.deadline(), Some(t0 + opts.max_delay));
+        r.expire(t0 + Duration::from_millis(5), &mut out);
+        assert!(out.is_empty());
+        r.expire(t0 + Duration::from_millis(10), &mut out);
+        assert_eq!(types(&mut out), vec![8]);
+        assert_eq!(r.deadline(), None);
+
+        // An out-of-band dispatch fills its own gap.
+        r.push(numbered(10), t0, &mut out);
+        r.push_dispatched(&numbered(9).0, t0, &mut out);
+        assert_eq!(types(&mut out), vec![10]);
+    }
+
+    #[test]
+    fn reorders_across_priorities() {
+        let tmp = TempTopic::new("/mq_ipc_test_reorder_");
+        let publisher =
+            MqTopic::with_options(tmp.name(), &TopicOptions::new(8).sequenced(true)).unwrap();
+        // Higher priorities overtake: the queue now holds 1, 2, 0.
+        for (ty, prio) in [(0u16, 0u32), (1, 5), (2, 1)] {
+            publisher.publish(&Msg::new(ty, &[]), prio).unwrap();
+        }
+
+        let opts = TopicOptions::new(8).reorder(ReorderOptions::default());
+        let subscriber = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_clone = Arc::clone(&got);
+        subscriber.subscribe(move |m| got_clone.lock().unwrap().push(m.hdr.msg_type));
+
+        for _ in 0..100 {
+            if got.lock().unwrap().len() == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(*got.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn urgent_first_skips_the_buffer() {
+        let tmp = TempTopic::new("/mq_ipc_test_reorder_urgent_");
+        let publisher =
+            MqTopic::with_options(tmp.name(), &TopicOptions::new(8).sequenced(true)).unwrap();
+        publisher.publish(&Msg::new(0, &[]), 0).unwrap();
+        publisher.publish(&Msg::new(1, &[]), 0).unwrap();
+        publisher.publish_urgent(&Msg::new(2, &[])).unwrap();
+
+        let opts = TopicOptions::new(8)
+            .reorder(ReorderOptions::default())
+            .urgent_first(true);
+        let subscriber = MqTopic::with_options(tmp.name(), &opts).unwrap();
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_clone = Arc::clone(&got);
+        subscriber.subscribe(move |m| got_clone.lock().unwrap().push(m.hdr.msg_type));
+
+        for _ in 0..100 {
+            if got.lock().unwrap().len() == 3 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        // Without `urgent_first` the urgent message would wait for 0 and 1.
+        assert_eq!(*got.lock().unwrap(), vec![2, 0, 1]);
+    }
+}
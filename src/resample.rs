@@ -0,0 +1,172 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Republish a topic at a different, usually lower, rate.
+//!
+//! A [`Resampler`] subscribes to a topic at whatever rate it is published
+//! and republishes it on another from a [`PeriodicPublisher`], e.g. a
+//! 1 kHz controller state thinned out for a 50 Hz logger:
+//!
+//! ```no_run
+//! # use mq_ipc::{periodic::PeriodicOptions, resample::{Hold, Resampler}, TopicOptions};
+//! # use std::time::Duration;
+//! let _log = Resampler::<[f32; 6]>::new(
+//!     "/arm/joints",
+//!     "/arm/joints_50hz",
+//!     Hold::Latest,
+//!     &PeriodicOptions::new(Duration::from_millis(20)),
+//!     &TopicOptions::new(8),
+//! )?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Nothing is published before the first input sample arrives.
+
+use super::{
+    periodic::{JitterStats, PeriodicOptions, PeriodicPublisher},
+    Topic, TopicOptions,
+};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+/// What a [`Resampler`] publishes on a tick.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Hold {
+    /// The newest sample received since the previous tick; ticks without
+    /// a new sample publish nothing.
+    #[default]
+    Latest,
+    /// The newest sample received so far, repeated until a newer one
+    /// arrives (zero-order hold), so the output rate stays fixed.
+    ZeroOrder,
+}
+
+/// Newest input sample and whether it was already published.
+struct Slot<T> {
+    value: Option<T>,
+    fresh: bool,
+}
+
+/// Subscription republishing its input at a fixed rate; stops when dropped.
+pub struct Resampler<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    _input: Topic<T>,
+    output: PeriodicPublisher<T>,
+}
+
+impl<T> Resampler<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    /// Resample `input` onto `output` every `timing.period`. Both topics
+    /// are opened with `opts`.
+    pub fn new(
+        input: &str,
+        output: &str,
+        hold: Hold,
+        timing: &PeriodicOptions,
+        opts: &TopicOptions,
+    ) -> io::Result<Self> {
+        let slot = Arc::new(Mutex::new(Slot {
+            value: None,
+            fresh: false,
+        }));
+
+        let output = {
+            let slot = Arc::clone(&slot);
+            let topic = Topic::with_options(output, opts)?;
+            PeriodicPublisher::with_optional(topic, timing, move || {
+                let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+                let fresh = std::mem::replace(&mut slot.fresh, false);
+                match hold {
+                    Hold::Latest if !fresh => None,
+                    _ => slot.value,
+                }
+            })?
+        };
+
+        let input = Topic::with_options(input, opts)?;
+        input.subscribe(move |value: T| {
+            let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+            slot.value = Some(value);
+            slot.fresh = true;
+        });
+
+        Ok(Resampler {
+            _input: input,
+            output,
+        })
+    }
+
+    /// Timing of the output ticks.
+    pub fn stats(&self) -> JitterStats {
+        self.output.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{sync::mpsc, thread, time::Duration};
+
+    fn run(hold: Hold, samples: &[u32]) -> Vec<u32> {
+        let tmp_in = TempTopic::new("/mq_ipc_test_resample_in_");
+        let tmp_out = TempTopic::new("/mq_ipc_test_resample_out_");
+        let opts = TopicOptions::new(8);
+
+        let out = Topic::<u32>::new(tmp_out.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        out.subscribe(move |v| tx.send(v).unwrap());
+
+        let timing = PeriodicOptions::new(Duration::from_millis(40));
+        let resampler =
+            Resampler::<u32>::new(tmp_in.name(), tmp_out.name(), hold, &timing, &opts).unwrap();
+        let input = Topic::<u32>::new(tmp_in.name(), 8).unwrap();
+        for v in samples {
+            input.publish(v, 1, 0).unwrap();
+        }
+
+        // About three ticks; the burst lands before the first one.
+        thread::sleep(Duration::from_millis(140));
+        drop(resampler);
+        rx.try_iter().collect()
+    }
+
+    #[test]
+    fn latest_publishes_only_new_samples() {
+        assert_eq!(run(Hold::Latest, &[1, 2, 3]), vec![3]);
+    }
+
+    #[test]
+    fn zero_order_hold_repeats_last_sample() {
+        let got = run(Hold::ZeroOrder, &[1, 2, 3]);
+        assert!(got.len() >= 2, "{got:?}");
+        assert!(got.iter().all(|v| *v == 3), "{got:?}");
+    }
+}
@@ -0,0 +1,140 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Retry policy for transient `mq_send` failures.
+
+use std::{io, thread, time::Duration};
+
+/// How a topic retries a publish that failed with `EINTR` or, in
+/// non-blocking mode, `EAGAIN` (queue full).
+///
+/// Other errors are always returned immediately, as is any failure once
+/// shutdown has been requested.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 surfaces the error right away.
+    pub max_retries: u32,
+    /// Delay before the first `EAGAIN` retry; doubled on each attempt.
+    pub backoff: Duration,
+    /// Upper bound for the doubled delay.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Give up on the first failure (the default).
+    pub const fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Retry up to `n` times without waiting.
+    pub const fn retries(n: u32) -> Self {
+        RetryPolicy {
+            max_retries: n,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Wait `initial`, doubling up to `max`, between `EAGAIN` retries.
+    pub const fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Run `op` under this policy.
+    pub fn run<F>(&self, mut op: F) -> io::Result<()>
+    where
+        F: FnMut() -> io::Result<()>,
+    {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+
+        loop {
+            let err = match op() {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+
+            let interrupted = err.kind() == io::ErrorKind::Interrupted;
+            let full = err.kind() == io::ErrorKind::WouldBlock;
+            if !(interrupted || full) || attempt >= self.max_retries || crate::shutdown::requested()
+            {
+                return Err(err);
+            }
+            attempt += 1;
+
+            // EINTR is retried at once; only a full queue needs time.
+            if full && !delay.is_zero() {
+                thread::sleep(delay);
+                delay = (delay * 2).min(self.max_backoff.max(self.backoff));
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_transient_errors_only() {
+        let mut calls = 0;
+        let res = RetryPolicy::retries(3).run(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(res.is_ok());
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let res = RetryPolicy::retries(3).run(|| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        });
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        let res = RetryPolicy::none().run(|| {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::Interrupted))
+        });
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+}
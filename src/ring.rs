@@ -0,0 +1,561 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Lock-free single-producer/single-consumer ring in shared memory.
+//!
+//! For the hottest topics (kHz control loops) the two syscalls per
+//! message of an mqueue dominate latency. [`ShmRing`] keeps a fixed
+//! array of [`Msg`] slots in a POSIX shared-memory object
+//! (`/dev/shm/mq_ipc.ring.<name>`) with atomic head/tail indices, so a
+//! publish is a copy plus a release store. How the consumer learns about
+//! new data is chosen with [`Wakeup`].
+//!
+//! Exactly one thread may publish and one thread may consume; nothing
+//! enforces this. A full ring refuses new messages with `WouldBlock`,
+//! and message priorities are ignored. Use it through [`Transport`] to
+//! keep the option of falling back to an [`MqTopic`](crate::MqTopic).
+//! Like a topic's, the subscriber thread needs the `callbacks` feature;
+//! without it the consumer polls with [`ShmRing::recv_timeout`].
+
+#[cfg(feature = "callbacks")]
+use super::transport::MsgCallback;
+use super::{cleanup, defaults, event::Event, transport::Transport, Msg};
+#[cfg(feature = "callbacks")]
+use arc_swap::ArcSwap;
+#[cfg(feature = "callbacks")]
+use std::sync::{atomic::AtomicBool, Mutex, OnceLock};
+use std::{
+    ffi::CString,
+    io,
+    os::raw::c_int,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const MAGIC: u64 = u64::from_le_bytes(*b"mqipcrng");
+
+/// How long a futex-mode consumer spins before sleeping in the kernel.
+const SPIN_BEFORE_PARK: Duration = Duration::from_micros(50);
+
+/// Receive timeout of the subscriber thread, i.e. how quickly it notices
+/// that the ring was dropped.
+#[cfg(feature = "callbacks")]
+const WORKER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How the consumer waits for the producer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Wakeup {
+    /// Busy-poll the ring. Lowest latency, but the consumer keeps a core
+    /// at 100 %.
+    Spin,
+    /// Spin briefly, then sleep on a futex in the shared mapping.
+    #[default]
+    Futex,
+    /// Sleep on a 1-deep [`Event`] queue named `<name>.wake`.
+    Mqueue,
+}
+
+#[repr(C, align(64))]
+struct Padded<T>(T);
+
+#[repr(C)]
+struct Header {
+    magic: AtomicU64,
+    capacity: u64,
+    /// Next slot the producer writes.
+    head: Padded<AtomicU64>,
+    /// Next slot the consumer reads.
+    tail: Padded<AtomicU64>,
+    /// Futex word, bumped after every publish.
+    seq: Padded<AtomicU32>,
+    waiters: AtomicU32,
+}
+
+struct Mapping {
+    base: *mut u8,
+    len: usize,
+    capacity: u64,
+    wakeup: Wakeup,
+    event: Option<Event>,
+}
+
+// SAFETY: all access to the mapping goes through the header atomics, and
+// a slot is only touched by the side that currently owns it.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn header(&self) -> &Header {
+        unsafe { &*(self.base as *const Header) }
+    }
+
+    fn slot(&self, index: u64) -> *mut Msg {
+        let offset = std::mem::size_of::<Header>()
+            + (index % self.capacity) as usize * std::mem::size_of::<Msg>();
+        unsafe { self.base.add(offset) as *mut Msg }
+    }
+
+    fn try_push(&self, msg: &Msg) -> bool {
+        let hdr = self.header();
+        let head = hdr.head.0.load(Ordering::Relaxed);
+        if head - hdr.tail.0.load(Ordering::Acquire) >= self.capacity {
+            return false;
+        }
+        unsafe { std::ptr::write(self.slot(head), *msg) };
+        hdr.head.0.store(head + 1, Ordering::Release);
+        true
+    }
+
+    fn try_pop(&self) -> Option<Msg> {
+        let hdr = self.header();
+        let tail = hdr.tail.0.load(Ordering::Relaxed);
+        if tail == hdr.head.0.load(Ordering::Acquire) {
+            return None;
+        }
+        let msg = unsafe { std::ptr::read(self.slot(tail)) };
+        hdr.tail.0.store(tail + 1, Ordering::Release);
+        Some(msg)
+    }
+
+    fn notify(&self) -> io::Result<()> {
+        match self.wakeup {
+            Wakeup::Spin => Ok(()),
+            Wakeup::Futex => {
+                let hdr = self.header();
+                hdr.seq.0.fetch_add(1, Ordering::SeqCst);
+                if hdr.waiters.load(Ordering::SeqCst) > 0 {
+                    futex_wake(&hdr.seq.0);
+                }
+                Ok(())
+            }
+            Wakeup::Mqueue => match &self.event {
+                Some(event) => event.notify(),
+                None => Ok(()),
+            },
+        }
+    }
+
+    fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<Msg>> {
+        let start = Instant::now();
+        let deadline = start + timeout;
+        loop {
+            if let Some(msg) = self.try_pop() {
+                return Ok(Some(msg));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+
+            match self.wakeup {
+                Wakeup::Spin => std::hint::spin_loop(),
+                Wakeup::Futex if now - start < SPIN_BEFORE_PARK => std::hint::spin_loop(),
+                Wakeup::Futex => {
+                    let hdr = self.header();
+                    hdr.waiters.fetch_add(1, Ordering::SeqCst);
+                    let seen = hdr.seq.0.load(Ordering::SeqCst);
+                    if let Some(msg) = self.try_pop() {
+                        hdr.waiters.fetch_sub(1, Ordering::SeqCst);
+                        return Ok(Some(msg));
+                    }
+                    futex_wait(&hdr.seq.0, seen, deadline - now);
+                    hdr.waiters.fetch_sub(1, Ordering::SeqCst);
+                }
+                Wakeup::Mqueue => {
+                    if let Some(event) = &self.event {
+                        event.wait_timeout(deadline - now)?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.base as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+fn futex_wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+    let ts = libc::timespec {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    // Not FUTEX_PRIVATE: the word is shared with another process.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAIT,
+            expected,
+            &ts as *const libc::timespec,
+        );
+    }
+}
+
+fn futex_wake(word: &AtomicU32) {
+    unsafe {
+        libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, 1);
+    }
+}
+
+fn shm_name(name: &str) -> String {
+    format!("/mq_ipc.ring{}", name.replace('/', "."))
+}
+
+/// A shared-memory SPSC ring of [`Msg`]s.
+pub struct ShmRing {
+    name: String,
+    mapping: Arc<Mapping>,
+    #[cfg(feature = "callbacks")]
+    subs: Arc<ArcSwap<Vec<Arc<MsgCallback>>>>,
+    #[cfg(feature = "callbacks")]
+    running: Arc<AtomicBool>,
+    #[cfg(feature = "callbacks")]
+    worker: OnceLock<Mutex<Option<thread::JoinHandle<()>>>>,
+}
+
+impl ShmRing {
+    /// Open (creating if needed) the ring `name` with room for `capacity`
+    /// messages. Both ends must agree on `capacity` and `wakeup`.
+    pub fn open(name: &str, capacity: usize, wakeup: Wakeup) -> io::Result<Self> {
+        if capacity == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "ring capacity must be at least 1",
+            ));
+        }
+        let name = defaults::topic_name(name).into_owned();
+        let len = std::mem::size_of::<Header>() + capacity * std::mem::size_of::<Msg>();
+        let cname = CString::new(shm_name(&name))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid ring name"))?;
+
+        let (fd, created) = open_shm(&cname)?;
+        let base = match prepare(fd, len, created) {
+            Ok(()) => unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    fd,
+                    0,
+                )
+            },
+            Err(err) => {
+                unsafe { libc::close(fd) };
+                return Err(err);
+            }
+        };
+        unsafe { libc::close(fd) };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        let event = match wakeup {
+            Wakeup::Mqueue => Some(Event::open_resolved(format!("{name}.wake"))?),
+            _ => None,
+        };
+        let mapping = Mapping {
+            base: base as *mut u8,
+            len,
+            capacity: capacity as u64,
+            wakeup,
+            event,
+        };
+
+        if created {
+            // The fresh object is zero-filled, so only these need setting.
+            let raw = mapping.base as *mut Header;
+            unsafe { std::ptr::addr_of_mut!((*raw).capacity).write(capacity as u64) };
+        }
+        let hdr = mapping.header();
+        if created {
+            hdr.magic.store(MAGIC, Ordering::Release);
+        } else {
+            let deadline = Instant::now() + Duration::from_secs(1);
+            while hdr.magic.load(Ordering::Acquire) != MAGIC {
+                if Instant::now() >= deadline {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{name}: ring was never initialised"),
+                    ));
+                }
+                thread::yield_now();
+            }
+        }
+
+        Ok(ShmRing {
+            name,
+            mapping: Arc::new(mapping),
+            #[cfg(feature = "callbacks")]
+            subs: Arc::new(ArcSwap::from_pointee(Vec::new())),
+            #[cfg(feature = "callbacks")]
+            running: Arc::new(AtomicBool::new(true)),
+            #[cfg(feature = "callbacks")]
+            worker: OnceLock::new(),
+        })
+    }
+
+    /// Remove the shared-memory object behind `name`. Open handles keep
+    /// working until dropped.
+    pub fn remove(name: &str) -> io::Result<()> {
+        let name = defaults::topic_name(name);
+        let cname = CString::new(shm_name(&name))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid ring name"))?;
+        if unsafe { libc::shm_unlink(cname.as_ptr()) } == -1 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::ENOENT) {
+                return Err(err);
+            }
+        }
+        cleanup::unlink_resolved(&format!("{name}.wake"))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.mapping.capacity as usize
+    }
+
+    /// Messages currently queued.
+    pub fn len(&self) -> usize {
+        let hdr = self.mapping.header();
+        let tail = hdr.tail.0.load(Ordering::Acquire);
+        (hdr.head.0.load(Ordering::Acquire) - tail) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy `msg` into the ring and wake the consumer. Fails with
+    /// `WouldBlock` when the ring is full.
+    pub fn send(&self, msg: &Msg) -> io::Result<()> {
+        if !self.mapping.try_push(msg) {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{}: ring full", self.name),
+            ));
+        }
+        self.mapping.notify()
+    }
+
+    /// Take the next message, if any, without waiting.
+    pub fn try_recv(&self) -> Option<Msg> {
+        self.mapping.try_pop()
+    }
+
+    /// Wait up to `timeout` for the next message.
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<Msg>> {
+        self.mapping.recv_timeout(timeout)
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn ensure_worker(&self) {
+        self.worker.get_or_init(|| {
+            let mapping = Arc::clone(&self.mapping);
+            let subs = Arc::clone(&self.subs);
+            let running = Arc::clone(&self.running);
+            let name = self.name.clone();
+
+            let handle = thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    match mapping.recv_timeout(WORKER_POLL_INTERVAL) {
+                        Ok(Some(msg)) => {
+                            for cb in subs.load().iter() {
+                                (cb)(msg);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            eprintln!("{name}: ring receive error: {err}");
+                            thread::sleep(WORKER_POLL_INTERVAL);
+                        }
+                    }
+                }
+            });
+            Mutex::new(Some(handle))
+        });
+    }
+}
+
+fn open_shm(cname: &CString) -> io::Result<(c_int, bool)> {
+    let fd = unsafe {
+        libc::shm_open(
+            cname.as_ptr(),
+            libc::O_CREAT | libc::O_EXCL | libc::O_RDWR | libc::O_CLOEXEC,
+            0o666,
+        )
+    };
+    if fd >= 0 {
+        return Ok((fd, true));
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() != Some(libc::EEXIST) {
+        return Err(err);
+    }
+
+    let fd = unsafe { libc::shm_open(cname.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((fd, false))
+}
+
+/// Size a new object, or wait for its creator to have done so.
+fn prepare(fd: c_int, len: usize, created: bool) -> io::Result<()> {
+    if created {
+        if unsafe { libc::ftruncate(fd, len as libc::off_t) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        return Ok(());
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    loop {
+        let mut st: libc::stat = unsafe { std::mem::zeroed() };
+        if unsafe { libc::fstat(fd, &mut st) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        match st.st_size as usize {
+            0 if Instant::now() < deadline => thread::yield_now(),
+            size if size == len => return Ok(()),
+            size => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("ring exists with a different size ({size} bytes, expected {len})"),
+                ))
+            }
+        }
+    }
+}
+
+impl Transport for ShmRing {
+    fn publish(&self, msg: &Msg, _prio: u32) -> io::Result<()> {
+        self.send(msg)
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn subscribe(&self, f: MsgCallback) {
+        let f = Arc::new(f);
+        self.subs.rcu(|cur| {
+            let mut next = (**cur).clone();
+            next.push(Arc::clone(&f));
+            next
+        });
+        self.ensure_worker();
+    }
+}
+
+#[cfg(feature = "callbacks")]
+impl Drop for ShmRing {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(worker) = self.worker.get() {
+            let _ = self.mapping.notify();
+            if self.mapping.wakeup == Wakeup::Futex {
+                futex_wake(&self.mapping.header().seq.0);
+            }
+            let handle = worker.lock().unwrap_or_else(|e| e.into_inner()).take();
+            if let Some(handle) = handle {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    #[cfg(feature = "callbacks")]
+    use std::sync::mpsc;
+
+    #[test]
+    fn delivers_in_order_and_refuses_when_full() {
+        let tmp = TempTopic::new("/mq_ipc_test_ring_");
+        let ring = ShmRing::open(tmp.name(), 2, Wakeup::Spin).unwrap();
+        let peer = ShmRing::open(tmp.name(), 2, Wakeup::Spin).unwrap();
+        assert!(ShmRing::open(tmp.name(), 3, Wakeup::Spin).is_err());
+
+        ring.send(&Msg::new(1, b"a")).unwrap();
+        ring.send(&Msg::new(2, b"b")).unwrap();
+        let err = ring.send(&Msg::new(3, b"c")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(peer.len(), 2);
+
+        let first = peer
+            .recv_timeout(Duration::from_millis(10))
+            .unwrap()
+            .unwrap();
+        assert_eq!((first.hdr.msg_type, first.data()), (1, &b"a"[..]));
+        assert_eq!(peer.try_recv().unwrap().hdr.msg_type, 2);
+        assert!(peer
+            .recv_timeout(Duration::from_millis(1))
+            .unwrap()
+            .is_none());
+
+        drop((ring, peer));
+        ShmRing::remove(tmp.name()).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "callbacks")]
+    fn subscriber_is_woken_by_each_wakeup_mode() {
+        for wakeup in [Wakeup::Futex, Wakeup::Mqueue] {
+            let tmp = TempTopic::new("/mq_ipc_test_ring_");
+            let rx_ring = ShmRing::open(tmp.name(), 8, wakeup).unwrap();
+            let tx_ring = ShmRing::open(tmp.name(), 8, wakeup).unwrap();
+
+            let (tx, rx) = mpsc::channel();
+            let tx = Mutex::new(tx);
+            Transport::subscribe(
+                &rx_ring,
+                Box::new(move |msg: Msg| tx.lock().unwrap().send(msg.hdr.msg_type).unwrap()),
+            );
+
+            for ty in 1..=3u16 {
+                // Let the consumer fall asleep before each publish.
+                thread::sleep(Duration::from_millis(5));
+                tx_ring.publish_value(&ty, ty, 0).unwrap();
+            }
+            let got: Vec<u16> = (0..3)
+                .map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap())
+                .collect();
+            assert_eq!(got, vec![1, 2, 3], "{wakeup:?}");
+
+            drop((rx_ring, tx_ring));
+            ShmRing::remove(tmp.name()).unwrap();
+        }
+    }
+}
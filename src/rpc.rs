@@ -0,0 +1,353 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Request/reply over queues.
+//!
+//! A [`Server`] reads requests from the queue `name`; every [`Client`]
+//! owns a private reply queue `<name>.reply.<id>` whose id travels in
+//! the request, so replies reach exactly the caller that asked. Request
+//! and reply bodies are `Pod` values of at most [`RPC_BODY_SIZE`] bytes.
+
+use super::{defaults, open_queue, realtime_after, MqTopic, Msg, RecvBuf, MSG_PAYLOAD_SIZE};
+use bytemuck::{Pod, Zeroable};
+use libc::{self, mqd_t};
+use std::{
+    collections::HashMap,
+    io,
+    marker::PhantomData,
+    os::raw::{c_char, c_long},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+const MSG_TYPE_REQUEST: u16 = 1;
+const MSG_TYPE_REPLY: u16 = 2;
+
+/// Depth of every client's reply queue.
+const REPLY_DEPTH: c_long = 4;
+
+/// Bytes of a message payload left for the request or reply body.
+pub const RPC_BODY_SIZE: usize = MSG_PAYLOAD_SIZE - std::mem::size_of::<RpcHeader>();
+
+/// Routing prefix of every request and reply payload.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+struct RpcHeader {
+    client: u64,
+    seq: u64,
+}
+
+fn encode<T: Pod>(msg_type: u16, hdr: RpcHeader, body: &T) -> Msg {
+    let mut payload = [0u8; MSG_PAYLOAD_SIZE];
+    let hdr_len = std::mem::size_of::<RpcHeader>();
+    let body = bytemuck::bytes_of(body);
+    payload[..hdr_len].copy_from_slice(bytemuck::bytes_of(&hdr));
+    payload[hdr_len..hdr_len + body.len()].copy_from_slice(body);
+    Msg::new(msg_type, &payload[..hdr_len + body.len()])
+}
+
+fn decode<T: Pod>(msg: &Msg) -> Option<(RpcHeader, T)> {
+    let data = msg.data();
+    let hdr_len = std::mem::size_of::<RpcHeader>();
+    if data.len() != hdr_len + std::mem::size_of::<T>() {
+        return None;
+    }
+    let hdr = bytemuck::pod_read_unaligned(&data[..hdr_len]);
+    let body = bytemuck::pod_read_unaligned(&data[hdr_len..]);
+    Some((hdr, body))
+}
+
+fn check_size<T>() -> io::Result<()> {
+    if std::mem::size_of::<T>() > RPC_BODY_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is larger than {RPC_BODY_SIZE} bytes",
+                std::any::type_name::<T>()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn reply_queue(service: &str, client: u64) -> String {
+    format!("{service}.reply.{client:x}")
+}
+
+fn send_raw(mqd: mqd_t, msg: &Msg, deadline: &libc::timespec) -> io::Result<()> {
+    let rc = unsafe {
+        libc::mq_timedsend(
+            mqd,
+            msg as *const Msg as *const c_char,
+            std::mem::size_of::<Msg>(),
+            0,
+            deadline,
+        )
+    };
+    if rc == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Answers requests of type `Req` with replies of type `Rep`.
+///
+/// The handler runs on the topic's worker thread, one request at a time.
+pub struct Server<Req, Rep> {
+    _requests: MqTopic,
+    _marker: PhantomData<fn(Req) -> Rep>,
+}
+
+impl<Req, Rep> Server<Req, Rep>
+where
+    Req: Pod + Send + Sync + 'static,
+    Rep: Pod + Send + Sync + 'static,
+{
+    /// Serve `name` with `handler`.
+    pub fn new<F>(name: &str, maxmsg: c_long, handler: F) -> io::Result<Self>
+    where
+        F: Fn(Req) -> Rep + Send + Sync + 'static,
+    {
+        check_size::<Req>()?;
+        check_size::<Rep>()?;
+
+        let requests = MqTopic::new(name, maxmsg)?;
+        let service = requests.name().to_string();
+        let clients = ClientTable(Mutex::new(HashMap::new()));
+
+        requests.subscribe(move |msg: Msg| {
+            if msg.hdr.msg_type != MSG_TYPE_REQUEST {
+                return;
+            }
+            let Some((hdr, req)) = decode::<Req>(&msg) else {
+                eprintln!("{service}: malformed request");
+                return;
+            };
+            let reply = encode(MSG_TYPE_REPLY, hdr, &handler(req));
+            clients.send(&service, hdr.client, &reply);
+        });
+
+        Ok(Server {
+            _requests: requests,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Reply descriptors of the clients seen so far.
+struct ClientTable(Mutex<HashMap<u64, mqd_t>>);
+
+impl ClientTable {
+    fn send(&self, service: &str, client: u64, reply: &Msg) {
+        let mut table = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let mqd = match table.get(&client) {
+            Some(mqd) => *mqd,
+            None => match open_queue(
+                &reply_queue(service, client),
+                libc::O_WRONLY | libc::O_NONBLOCK,
+                None,
+            ) {
+                Ok(mqd) => *table.entry(client).or_insert(mqd),
+                // The client is gone; nobody to answer.
+                Err(_) => return,
+            },
+        };
+
+        // Never let a stuck client stall the service: a full reply queue
+        // means it stopped reading, so forget it.
+        if send_raw(mqd, reply, &realtime_after(Duration::ZERO)).is_err() {
+            unsafe { libc::mq_close(mqd) };
+            table.remove(&client);
+        }
+    }
+}
+
+impl Drop for ClientTable {
+    fn drop(&mut self) {
+        let table = self.0.get_mut().unwrap_or_else(|e| e.into_inner());
+        for (_, mqd) in table.drain() {
+            unsafe { libc::mq_close(mqd) };
+        }
+    }
+}
+
+static NEXT_CLIENT: AtomicU32 = AtomicU32::new(0);
+
+/// Caller side of a [`Server`].
+pub struct Client<Req, Rep> {
+    service: String,
+    id: u64,
+    requests: mqd_t,
+    replies: mqd_t,
+    reply_name: String,
+    seq: Mutex<u64>,
+    _marker: PhantomData<fn(Req) -> Rep>,
+}
+
+impl<Req, Rep> Client<Req, Rep>
+where
+    Req: Pod,
+    Rep: Pod,
+{
+    /// Connect to the service `name`. The server does not have to be up
+    /// yet; calls time out until it is.
+    pub fn new(name: &str) -> io::Result<Self> {
+        check_size::<Req>()?;
+        check_size::<Rep>()?;
+
+        let service = defaults::topic_name(name).into_owned();
+        let id =
+            (std::process::id() as u64) << 32 | NEXT_CLIENT.fetch_add(1, Ordering::Relaxed) as u64;
+        let reply_name = reply_queue(&service, id);
+
+        let requests = open_queue(
+            &service,
+            libc::O_CREAT | libc::O_WRONLY,
+            Some(defaults::maxmsg()),
+        )?;
+        let replies = match open_queue(
+            &reply_name,
+            libc::O_CREAT | libc::O_EXCL | libc::O_RDONLY,
+            Some(REPLY_DEPTH),
+        ) {
+            Ok(mqd) => mqd,
+            Err(err) => {
+                unsafe { libc::mq_close(requests) };
+                return Err(err);
+            }
+        };
+
+        Ok(Client {
+            service,
+            id,
+            requests,
+            replies,
+            reply_name,
+            seq: Mutex::new(0),
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Send `req` and wait up to `timeout` for its reply. Concurrent calls
+    /// on one client are serialized.
+    pub fn call(&self, req: &Req, timeout: Duration) -> io::Result<Rep> {
+        let mut seq = self.seq.lock().unwrap_or_else(|e| e.into_inner());
+        *seq += 1;
+        let hdr = RpcHeader {
+            client: self.id,
+            seq: *seq,
+        };
+
+        let deadline = Instant::now() + timeout;
+        send_raw(
+            self.requests,
+            &encode(MSG_TYPE_REQUEST, hdr, req),
+            &realtime_after(timeout),
+        )
+        .map_err(timed_out)?;
+
+        let mut buf = RecvBuf::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let ret = unsafe {
+                libc::mq_timedreceive(
+                    self.replies,
+                    buf.as_mut_ptr(),
+                    RecvBuf::LEN,
+                    std::ptr::null_mut(),
+                    &realtime_after(left),
+                )
+            };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                return Err(timed_out(err));
+            }
+
+            let Ok(msg) = buf.msg(ret as usize) else {
+                continue;
+            };
+            // Late replies to calls that already timed out are skipped.
+            if let Some((rhdr, rep)) = decode::<Rep>(&msg)
+                && msg.hdr.msg_type == MSG_TYPE_REPLY
+                && rhdr.seq == hdr.seq
+            {
+                return Ok(rep);
+            }
+        }
+    }
+}
+
+fn timed_out(err: io::Error) -> io::Error {
+    if err.raw_os_error() == Some(libc::ETIMEDOUT) {
+        io::Error::new(io::ErrorKind::TimedOut, "no reply from service")
+    } else {
+        err
+    }
+}
+
+impl<Req, Rep> Drop for Client<Req, Rep> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::mq_close(self.requests);
+            libc::mq_close(self.replies);
+        }
+        let _ = super::cleanup::unlink_resolved(&self.reply_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    #[test]
+    fn replies_reach_the_caller() {
+        let tmp = TempTopic::new("/mq_ipc_test_rpc_");
+        let _server = Server::new(tmp.name(), 4, |x: u32| x as u64 * 2).unwrap();
+
+        let a: Client<u32, u64> = Client::new(tmp.name()).unwrap();
+        let b: Client<u32, u64> = Client::new(tmp.name()).unwrap();
+        let timeout = Duration::from_secs(2);
+        assert_eq!(a.call(&21, timeout).unwrap(), 42);
+        assert_eq!(b.call(&5, timeout).unwrap(), 10);
+        assert_eq!(a.call(&1, timeout).unwrap(), 2);
+    }
+
+    #[test]
+    fn call_times_out_without_server() {
+        let tmp = TempTopic::new("/mq_ipc_test_rpc_none_");
+        let client: Client<u32, u32> = Client::new(tmp.name()).unwrap();
+        let err = client.call(&1, Duration::from_millis(20)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,286 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Versioned message layouts with subscriber-side migrations.
+//!
+//! A publisher created with
+//! [`TopicOptions::schema_version`](crate::TopicOptions::schema_version)
+//! stamps the layout version into the extended header. A subscriber
+//! describes the layout it understands as a [`Schema<T>`] and registers a
+//! migration for every older version it still has to accept, then
+//! subscribes with [`Topic::subscribe_with_schema`](crate::Topic::subscribe_with_schema):
+//!
+//! ```no_run
+//! # use bytemuck::{Pod, Zeroable};
+//! # use mq_ipc::{schema::Schema, Topic};
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! struct MotorStateV1 { rpm: f32 }
+//!
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! struct MotorStateV2 { rpm: f32, torque: f32 }
+//!
+//! let schema = Schema::<MotorStateV2>::new(2)
+//!     .migrate(1, |old: MotorStateV1| MotorStateV2 { rpm: old.rpm, torque: 0.0 });
+//! let topic = Topic::<MotorStateV2>::new("/motor/state", 8)?;
+//! # #[cfg(feature = "callbacks")]
+//! topic.subscribe_with_schema(schema, |state| println!("{}", state.rpm));
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Upgraded subscribers can be rolled out first; publishers switch to the
+//! new layout once every reader knows it. Messages without a version are
+//! taken to be version 1 (see [`Schema::unversioned`]). Messages that
+//! cannot be brought to the current version — newer than it, or with no
+//! migration path — are dropped, or sent to the dead-letter queue if the
+//! topic has one.
+
+use super::{ext::EXT_SCHEMA, Msg};
+use bytemuck::Pod;
+use std::{borrow::Cow, collections::HashMap, marker::PhantomData};
+
+type Step = Box<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync + 'static>;
+
+/// The layout version of `msg`, if its publisher stamped one.
+pub fn version_of(msg: &Msg) -> Option<u32> {
+    msg.ext()
+        .filter(|ext| ext.has(EXT_SCHEMA))
+        .map(|ext| ext.schema)
+}
+
+/// Current layout `T` plus migrations from older layouts.
+pub struct Schema<T> {
+    version: u32,
+    unversioned: u32,
+    // from-version -> (size of that layout, migration to from + 1)
+    steps: HashMap<u32, (usize, Step)>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Pod> Schema<T> {
+    /// `T` is layout `version`.
+    pub fn new(version: u32) -> Self {
+        Schema {
+            version,
+            unversioned: 1,
+            steps: HashMap::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Version assumed for messages that carry none, i.e. from publishers
+    /// that predate versioning. Defaults to 1.
+    pub fn unversioned(mut self, version: u32) -> Self {
+        self.unversioned = version;
+        self
+    }
+
+    /// Register the migration from layout `from` (`Old`) to `from + 1`
+    /// (`New`). Chains of migrations are applied one step at a time, so
+    /// `New` is `T` only for the last step.
+    ///
+    /// # Panics
+    ///
+    /// If `from` is not older than the current version.
+    pub fn migrate<Old, New, F>(mut self, from: u32, f: F) -> Self
+    where
+        Old: Pod,
+        New: Pod,
+        F: Fn(Old) -> New + Send + Sync + 'static,
+    {
+        assert!(
+            from < self.version,
+            "migration from v{from} is not older than v{}",
+            self.version
+        );
+        let step: Step = Box::new(move |data| {
+            let new = f(read_padded::<Old>(data));
+            bytemuck::bytes_of(&new).to_vec()
+        });
+        self.steps.insert(from, (std::mem::size_of::<Old>(), step));
+        self
+    }
+
+    /// Current layout version.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Decode `msg` as `T`, migrating it first if it is older.
+    ///
+    /// With `strict`, payloads whose length does not match the layout of
+    /// their version are rejected instead of zero-padded or truncated.
+    pub fn decode(&self, msg: &Msg, strict: bool) -> Option<T> {
+        let mut version = version_of(msg).unwrap_or(self.unversioned);
+        let mut data = Cow::Borrowed(msg.data());
+
+        while version != self.version {
+            let (size, step) = self.steps.get(&version)?;
+            if strict && data.len() != *size {
+                return None;
+            }
+            data = Cow::Owned(step(&data));
+            version += 1;
+        }
+
+        if strict && data.len() != std::mem::size_of::<T>() {
+            return None;
+        }
+        Some(read_padded(&data))
+    }
+}
+
+fn read_padded<T: Pod>(data: &[u8]) -> T {
+    let mut value = T::zeroed();
+    let buf = bytemuck::bytes_of_mut(&mut value);
+    let n = data.len().min(buf.len());
+    buf[..n].copy_from_slice(&data[..n]);
+    value
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::ext::ExtHeader;
+    use bytemuck::Zeroable;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+    struct V1 {
+        rpm: f32,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+    struct V2 {
+        rpm: f32,
+        torque: f32,
+    }
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+    struct V3 {
+        rpm: f32,
+        torque: f32,
+        temp: f32,
+    }
+
+    fn versioned(version: u32, data: &[u8]) -> Msg {
+        let ext = ExtHeader {
+            present: EXT_SCHEMA,
+            schema: version,
+            ..Default::default()
+        };
+        Msg::with_ext(1, &ext, data)
+    }
+
+    #[test]
+    fn migrates_through_every_older_version() {
+        let schema = Schema::<V3>::new(3)
+            .migrate(2, |v: V2| V3 {
+                rpm: v.rpm,
+                torque: v.torque,
+                temp: -1.0,
+            })
+            .migrate(1, |v: V1| V2 {
+                rpm: v.rpm,
+                torque: 0.5,
+            });
+
+        let v1 = Msg::new(1, bytemuck::bytes_of(&V1 { rpm: 10.0 }));
+        assert_eq!(version_of(&v1), None);
+        assert_eq!(
+            schema.decode(&v1, true),
+            Some(V3 {
+                rpm: 10.0,
+                torque: 0.5,
+                temp: -1.0
+            })
+        );
+
+        let current = V3 {
+            rpm: 1.0,
+            torque: 2.0,
+            temp: 3.0,
+        };
+        let v3 = versioned(3, bytemuck::bytes_of(&current));
+        assert_eq!(version_of(&v3), Some(3));
+        assert_eq!(schema.decode(&v3, true), Some(current));
+
+        // Newer than we understand, or a malformed older payload.
+        assert_eq!(schema.decode(&versioned(4, &[0; 12]), false), None);
+        assert_eq!(schema.decode(&versioned(2, &[0; 4]), true), None);
+    }
+
+    #[test]
+    fn topic_stamps_and_migrates_versions() {
+        use crate::{cleanup::TempTopic, Topic, TopicOptions};
+        use std::{sync::mpsc, time::Duration};
+
+        let tmp = TempTopic::new("/mq_ipc_test_schema_");
+        let old =
+            Topic::<V1>::with_options(tmp.name(), &TopicOptions::new(4).schema_version(1)).unwrap();
+        let new =
+            Topic::<V2>::with_options(tmp.name(), &TopicOptions::new(4).schema_version(2)).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let schema = Schema::<V2>::new(2).migrate(1, |v: V1| V2 {
+            rpm: v.rpm,
+            torque: 0.0,
+        });
+        let tx = std::sync::Mutex::new(tx);
+        new.subscribe_with_schema(schema, move |v| tx.lock().unwrap().send(v).unwrap());
+
+        old.publish(&V1 { rpm: 5.0 }, 1, 0).unwrap();
+        new.publish(
+            &V2 {
+                rpm: 6.0,
+                torque: 7.0,
+            },
+            1,
+            0,
+        )
+        .unwrap();
+
+        let timeout = Duration::from_secs(1);
+        let mut got = vec![
+            rx.recv_timeout(timeout).unwrap(),
+            rx.recv_timeout(timeout).unwrap(),
+        ];
+        got.sort_by(|a, b| a.rpm.total_cmp(&b.rpm));
+        assert_eq!(
+            got,
+            vec![
+                V2 {
+                    rpm: 5.0,
+                    torque: 0.0
+                },
+                V2 {
+                    rpm: 6.0,
+                    torque: 7.0
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Machine-wide unique, increasing IDs.
+//!
+//! One [`SeqServer`] per machine owns the counter and hands out blocks
+//! of IDs over [`rpc`]; [`SeqClient`]s anywhere ask for them.
+//! The counter is persisted next to the discovery [`registry`], so a
+//! restarted server continues where the previous one stopped (until
+//! reboot).
+
+use super::{defaults, registry, rpc};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    ops::Range,
+    os::{fd::AsRawFd, raw::c_long},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Default service name.
+pub const SEQ_SERVICE: &str = "/ipc_seq";
+
+const SEQ_DEPTH: c_long = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct SeqRequest {
+    pub count: u32,
+    pub reserved: u32,
+}
+
+/// `count == 0` reports a server-side failure.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct SeqReply {
+    pub first: u64,
+    pub count: u32,
+    pub reserved: u32,
+}
+
+/// Persistent counter behind the service.
+struct Counter {
+    next: u64,
+    path: PathBuf,
+    // Held for the server's lifetime so a second server cannot start.
+    _lock: File,
+}
+
+impl Counter {
+    fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension("lock"))?;
+        if unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == -1 {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is served by another process", path.display()),
+            ));
+        }
+
+        let next = match fs::read_to_string(path) {
+            Ok(text) => text.trim().parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: corrupt counter", path.display()),
+                )
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 1,
+            Err(err) => return Err(err),
+        };
+
+        Ok(Counter {
+            next,
+            path: path.to_path_buf(),
+            _lock: lock,
+        })
+    }
+
+    fn take(&mut self, count: u32) -> io::Result<u64> {
+        let first = self.next;
+        let next = first + count as u64;
+        // Persist before handing out, so a crash can skip IDs but
+        // never repeat them.
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, next.to_string())?;
+        fs::rename(&tmp, &self.path)?;
+        self.next = next;
+        Ok(first)
+    }
+}
+
+fn state_path(service: &str) -> PathBuf {
+    let file = service.trim_start_matches('/').replace('/', "_");
+    registry::root().join("seq").join(file)
+}
+
+/// Owner of the machine-wide counter.
+pub struct SeqServer {
+    _server: rpc::Server<SeqRequest, SeqReply>,
+}
+
+impl SeqServer {
+    /// Serve [`SEQ_SERVICE`].
+    pub fn new() -> io::Result<Self> {
+        Self::with_name(SEQ_SERVICE)
+    }
+
+    /// Serve the counter `name`. Fails with `AddrInUse` if another
+    /// process already serves it.
+    pub fn with_name(name: &str) -> io::Result<Self> {
+        let service = defaults::topic_name(name);
+        let counter = Mutex::new(Counter::open(&state_path(&service))?);
+
+        let server = rpc::Server::new(name, SEQ_DEPTH, move |req: SeqRequest| {
+            let count = req.count.max(1);
+            let mut counter = counter.lock().unwrap_or_else(|e| e.into_inner());
+            match counter.take(count) {
+                Ok(first) => SeqReply {
+                    first,
+                    count,
+                    reserved: 0,
+                },
+                Err(err) => {
+                    eprintln!("seq: cannot persist counter: {err}");
+                    SeqReply::default()
+                }
+            }
+        })?;
+
+        Ok(SeqServer { _server: server })
+    }
+}
+
+/// Asks a [`SeqServer`] for IDs.
+pub struct SeqClient {
+    client: rpc::Client<SeqRequest, SeqReply>,
+}
+
+impl SeqClient {
+    /// Use [`SEQ_SERVICE`].
+    pub fn new() -> io::Result<Self> {
+        Self::with_name(SEQ_SERVICE)
+    }
+
+    pub fn with_name(name: &str) -> io::Result<Self> {
+        Ok(SeqClient {
+            client: rpc::Client::new(name)?,
+        })
+    }
+
+    /// One fresh ID.
+    pub fn next(&self, timeout: Duration) -> io::Result<u64> {
+        Ok(self.reserve(1, timeout)?.start)
+    }
+
+    /// A block of `count` consecutive fresh IDs.
+    pub fn reserve(&self, count: u32, timeout: Duration) -> io::Result<Range<u64>> {
+        let req = SeqRequest { count, reserved: 0 };
+        let reply = self.client.call(&req, timeout)?;
+        if reply.count == 0 {
+            return Err(io::Error::other("sequence server failed"));
+        }
+        Ok(reply.first..reply.first + reply.count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    #[test]
+    fn ids_increase_across_server_restarts() {
+        let tmp = TempTopic::new("/mq_ipc_test_seq_");
+        let timeout = Duration::from_secs(2);
+        let path = state_path(tmp.name());
+
+        let first = {
+            let _server = SeqServer::with_name(tmp.name()).unwrap();
+            assert!(SeqServer::with_name(tmp.name()).is_err());
+
+            let client = SeqClient::with_name(tmp.name()).unwrap();
+            let a = client.next(timeout).unwrap();
+            let block = client.reserve(3, timeout).unwrap();
+            assert_eq!(block, a + 1..a + 4);
+            a
+        };
+
+        let _server = SeqServer::with_name(tmp.name()).unwrap();
+        let client = SeqClient::with_name(tmp.name()).unwrap();
+        assert_eq!(client.next(timeout).unwrap(), first + 4);
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("lock"));
+    }
+}
@@ -0,0 +1,184 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Publisher sessions, to tell a restarted source from a live one.
+//!
+//! Every process draws a random session ID when it starts. Topics opened
+//! with [`TopicOptions::session`](crate::TopicOptions::session) stamp it
+//! into the extended header of what they publish, and subscribers report
+//! a change through [`MqTopic::on_publisher_restart`](crate::MqTopic::on_publisher_restart),
+//! so a consumer holding state derived from the stream (a filter, an
+//! integrator, the last sequence number) can reset it when the source
+//! starts over:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::{MqTopic, TopicOptions};
+//!
+//! let imu = MqTopic::with_options("/imu", &TopicOptions::new(8).session(true))?;
+//! imu.on_publisher_restart(|restart| {
+//!     eprintln!("imu publisher restarted ({:08x} -> {:08x})", restart.previous, restart.session);
+//! });
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Sessions already seen are remembered, so a topic fed by several live
+//! publishers reports each one's first message once, not every switch
+//! between them.
+
+use super::{
+    dedup,
+    ext::{ExtHeader, EXT_SESSION},
+    Msg,
+};
+use arc_swap::ArcSwapOption;
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, OnceLock},
+};
+
+/// Sessions a subscriber remembers to ignore switches between them.
+const REMEMBERED: usize = 16;
+
+/// Session ID of this process; never 0.
+pub fn current() -> u32 {
+    static SESSION: OnceLock<u32> = OnceLock::new();
+    *SESSION.get_or_init(|| loop {
+        let id = dedup::unique_seed() as u32;
+        if id != 0 {
+            break id;
+        }
+    })
+}
+
+/// Session `msg` was published in, if its publisher stamped one.
+pub fn session_of(msg: &Msg) -> Option<u32> {
+    msg.ext()
+        .filter(|ext| ext.has(EXT_SESSION))
+        .map(|ext| ext.session)
+}
+
+pub(crate) fn stamp(ext: &mut ExtHeader) {
+    ext.present |= EXT_SESSION;
+    ext.session = current();
+}
+
+/// Reported by [`MqTopic::on_publisher_restart`](crate::MqTopic::on_publisher_restart).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PublisherRestart {
+    /// Session of the messages received before.
+    pub previous: u32,
+    /// Session of the message that revealed the restart.
+    pub session: u32,
+}
+
+type RestartCallback = Box<dyn Fn(PublisherRestart) + Send + Sync + 'static>;
+
+#[derive(Default)]
+struct Seen {
+    last: Option<u32>,
+    known: VecDeque<u32>,
+}
+
+/// Session tracking of one subscribing handle.
+#[derive(Default)]
+pub(crate) struct SessionWatch {
+    seen: Mutex<Seen>,
+    on_restart: ArcSwapOption<RestartCallback>,
+}
+
+impl SessionWatch {
+    pub(crate) fn set_callback(&self, f: RestartCallback) {
+        self.on_restart.store(Some(f.into()));
+    }
+
+    /// Note the session of a received message, reporting a new one.
+    pub(crate) fn observe(&self, msg: &Msg) {
+        let Some(session) = session_of(msg) else {
+            return;
+        };
+        let previous = {
+            let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+            if seen.last == Some(session) {
+                return;
+            }
+            let previous = seen.last.replace(session);
+            if seen.known.contains(&session) {
+                return;
+            }
+            if seen.known.len() == REMEMBERED {
+                seen.known.pop_front();
+            }
+            seen.known.push_back(session);
+            previous
+        };
+        if let (Some(previous), Some(cb)) = (previous, self.on_restart.load().as_ref()) {
+            cb(PublisherRestart { previous, session });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn stamped(session: u32) -> Msg {
+        let ext = ExtHeader {
+            present: EXT_SESSION,
+            session,
+            ..Default::default()
+        };
+        Msg::with_ext(1, &ext, b"x")
+    }
+
+    #[test]
+    fn reports_new_sessions_only() {
+        let watch = SessionWatch::default();
+        let restarts = Arc::new(Mutex::new(Vec::new()));
+        let restarts_cb = Arc::clone(&restarts);
+        watch.set_callback(Box::new(move |r| restarts_cb.lock().unwrap().push(r)));
+
+        for session in [7, 7, 9, 7, 9, 11] {
+            watch.observe(&stamped(session));
+        }
+        watch.observe(&Msg::new(1, b"unstamped"));
+
+        assert_eq!(
+            *restarts.lock().unwrap(),
+            [
+                PublisherRestart {
+                    previous: 7,
+                    session: 9
+                },
+                PublisherRestart {
+                    previous: 9,
+                    session: 11
+                },
+            ]
+        );
+        assert_ne!(current(), 0);
+        assert_eq!(current(), current());
+    }
+}
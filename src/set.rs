@@ -0,0 +1,401 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Many topics opened, served and torn down as one unit.
+//!
+//! A gateway that bridges dozens of topics describes them once with
+//! [`TopicDescriptor`]s and opens them together as a [`TopicSet`]. All
+//! subscribed topics share a single [`MultiSubscriber`] worker, and
+//! topics that fail to open are reported instead of aborting the rest.
+//! Subscribing needs the `callbacks` feature.
+
+use super::{
+    cleanup, defaults,
+    layout::{self, Describe, Layout},
+    open_queue, Msg,
+};
+#[cfg(feature = "callbacks")]
+use super::{multi::MultiSubscriber, registry};
+#[cfg(feature = "callbacks")]
+use arc_swap::ArcSwap;
+use bytemuck::Pod;
+use libc::{self, mqd_t};
+#[cfg(feature = "callbacks")]
+use std::sync::Mutex;
+use std::{
+    any::{self, TypeId},
+    collections::HashMap,
+    io,
+    os::raw::{c_char, c_long},
+    sync::Arc,
+};
+
+#[cfg(feature = "callbacks")]
+type SetCallback = Arc<dyn Fn(Msg) + Send + Sync + 'static>;
+
+/// Name, depth and payload type of one topic in a [`TopicSet`].
+#[derive(Clone, Debug)]
+pub struct TopicDescriptor {
+    name: String,
+    maxmsg: c_long,
+    type_name: &'static str,
+    type_id: TypeId,
+    size: usize,
+    layout: Option<Layout>,
+}
+
+impl TopicDescriptor {
+    /// Topic `name` carrying values of `T`.
+    pub fn new<T: Pod>(name: &str, maxmsg: c_long) -> Self {
+        TopicDescriptor {
+            name: name.to_string(),
+            maxmsg,
+            type_name: any::type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            size: std::mem::size_of::<T>(),
+            layout: None,
+        }
+    }
+
+    /// Like [`TopicDescriptor::new`], also recording the field layout of
+    /// `T`. [`TopicSet::open`] registers it for `mq-ipc schema`.
+    pub fn described<T: Describe>(name: &str, maxmsg: c_long) -> Self {
+        TopicDescriptor {
+            layout: Some(Layout::of::<T>()),
+            ..Self::new::<T>(name, maxmsg)
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn maxmsg(&self) -> c_long {
+        self.maxmsg
+    }
+
+    /// Rust type name of the payload, for diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Payload size in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Field layout, for descriptors built with [`TopicDescriptor::described`].
+    pub fn layout(&self) -> Option<&Layout> {
+        self.layout.as_ref()
+    }
+}
+
+struct Entry {
+    desc: TopicDescriptor,
+    mqd: mqd_t,
+    #[cfg(feature = "callbacks")]
+    subs: ArcSwap<Vec<SetCallback>>,
+}
+
+/// A group of topics sharing one receive worker.
+pub struct TopicSet {
+    order: Vec<String>,
+    entries: Arc<HashMap<String, Entry>>,
+    failures: Vec<(String, io::Error)>,
+    #[cfg(feature = "callbacks")]
+    worker: Mutex<Option<MultiSubscriber>>,
+    #[cfg(feature = "callbacks")]
+    sub_regs: Mutex<Vec<registry::Registration>>,
+}
+
+impl TopicSet {
+    /// Open (creating if needed) every described topic.
+    ///
+    /// This only fails as a whole on duplicate names; individual open
+    /// errors are collected in [`TopicSet::failures`].
+    pub fn open(descs: &[TopicDescriptor]) -> io::Result<Self> {
+        let mut order = Vec::with_capacity(descs.len());
+        let mut entries = HashMap::with_capacity(descs.len());
+        let mut failures = Vec::new();
+
+        for desc in descs {
+            let desc = &TopicDescriptor {
+                name: defaults::topic_name(&desc.name).into_owned(),
+                ..desc.clone()
+            };
+            if entries.contains_key(&desc.name) {
+                close_all(entries.values());
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} listed twice", desc.name),
+                ));
+            }
+            match open_queue(&desc.name, libc::O_CREAT | libc::O_RDWR, Some(desc.maxmsg)) {
+                Ok(mqd) => {
+                    if let Some(l) = &desc.layout {
+                        let _ = layout::register_layout(&desc.name, l);
+                    }
+                    order.push(desc.name.clone());
+                    entries.insert(
+                        desc.name.clone(),
+                        Entry {
+                            desc: desc.clone(),
+                            mqd,
+                            #[cfg(feature = "callbacks")]
+                            subs: ArcSwap::from_pointee(Vec::new()),
+                        },
+                    );
+                }
+                Err(err) => failures.push((desc.name.clone(), err)),
+            }
+        }
+
+        Ok(TopicSet {
+            order,
+            entries: Arc::new(entries),
+            failures,
+            #[cfg(feature = "callbacks")]
+            worker: Mutex::new(None),
+            #[cfg(feature = "callbacks")]
+            sub_regs: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Topics that could not be opened, with the reason.
+    pub fn failures(&self) -> &[(String, io::Error)] {
+        &self.failures
+    }
+
+    /// Whether every described topic was opened.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Descriptors of the opened topics, in declaration order.
+    pub fn descriptors(&self) -> impl Iterator<Item = &TopicDescriptor> {
+        self.order.iter().map(|name| &self.entries[name].desc)
+    }
+
+    fn entry<T: 'static>(&self, name: &str) -> io::Result<&Entry> {
+        let entry = self
+            .entries
+            .get(&*defaults::topic_name(name))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{name} is not in this set"),
+                )
+            })?;
+        if entry.desc.type_id != TypeId::of::<T>() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{name} carries {}, not {}",
+                    entry.desc.type_name,
+                    any::type_name::<T>()
+                ),
+            ));
+        }
+        Ok(entry)
+    }
+
+    /// Publish `value` on the member topic `name`.
+    pub fn publish<T: Pod>(
+        &self,
+        name: &str,
+        value: &T,
+        msg_type: u16,
+        prio: u32,
+    ) -> io::Result<()> {
+        let entry = self.entry::<T>(name)?;
+        let msg = Msg::new(msg_type, bytemuck::bytes_of(value));
+        let rc = unsafe {
+            libc::mq_send(
+                entry.mqd,
+                &msg as *const Msg as *const c_char,
+                std::mem::size_of::<Msg>(),
+                prio,
+            )
+        };
+        if rc == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Subscribe to the member topic `name`.
+    ///
+    /// The first subscription to a topic restarts the shared worker so it
+    /// also polls that queue; topics nobody subscribed to are never read.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe<T, F>(&self, name: &str, f: F) -> io::Result<()>
+    where
+        T: Pod + Send + Sync + 'static,
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        let entry = self.entry::<T>(name)?;
+        let cb: SetCallback = Arc::new(move |msg: Msg| {
+            let data = msg.data();
+            let mut value = T::zeroed();
+            let bytes = bytemuck::bytes_of_mut(&mut value);
+            let n = data.len().min(bytes.len());
+            bytes[..n].copy_from_slice(&data[..n]);
+            f(value);
+        });
+
+        let mut worker = self.worker.lock().unwrap_or_else(|e| e.into_inner());
+        let first = entry.subs.load().is_empty();
+        entry.subs.rcu(|cbs| {
+            let mut cbs = Vec::clone(cbs);
+            cbs.push(Arc::clone(&cb));
+            cbs
+        });
+
+        if first {
+            if let Ok(reg) = registry::register(&entry.desc.name, registry::Role::Subscriber) {
+                self.sub_regs
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(reg);
+            }
+            // Stop the old worker first; queued messages just wait.
+            drop(worker.take());
+            *worker = Some(self.start_worker()?);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn start_worker(&self) -> io::Result<MultiSubscriber> {
+        let names: Vec<&str> = self
+            .order
+            .iter()
+            .filter(|name| !self.entries[*name].subs.load().is_empty())
+            .map(String::as_str)
+            .collect();
+        let entries = Arc::clone(&self.entries);
+
+        // The queues exist already, so the depth argument is ignored.
+        MultiSubscriber::open_resolved(&names, 1, move |name: &str, msg: Msg| {
+            if let Some(entry) = entries.get(name) {
+                for cb in entry.subs.load().iter() {
+                    cb(msg);
+                }
+            }
+        })
+    }
+
+    /// Close every topic. Same as dropping the set.
+    pub fn close(self) {}
+
+    /// Close every topic and unlink all of their queues.
+    ///
+    /// Returns the first unlink error, after attempting all of them.
+    pub fn unlink(self) -> io::Result<()> {
+        let names = self.order.clone();
+        drop(self);
+
+        let mut result = Ok(());
+        for name in names {
+            if let Err(err) = cleanup::unlink_resolved(&name)
+                && result.is_ok()
+            {
+                result = Err(err);
+            }
+        }
+        result
+    }
+}
+
+impl Drop for TopicSet {
+    fn drop(&mut self) {
+        #[cfg(feature = "callbacks")]
+        drop(
+            self.worker
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+        );
+        close_all(self.entries.values());
+    }
+}
+
+fn close_all<'a>(entries: impl Iterator<Item = &'a Entry>) {
+    for entry in entries {
+        unsafe {
+            libc::mq_close(entry.mqd);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn shares_worker_and_reports_failures() {
+        let tmp_a = TempTopic::new("/mq_ipc_test_set_a_");
+        let tmp_b = TempTopic::new("/mq_ipc_test_set_b_");
+
+        let set = TopicSet::open(&[
+            TopicDescriptor::new::<u32>(tmp_a.name(), 4),
+            TopicDescriptor::new::<[f32; 2]>(tmp_b.name(), 4),
+            TopicDescriptor::new::<u32>("no_leading_slash", 4),
+        ])
+        .unwrap();
+        assert_eq!(set.failures().len(), 1);
+        assert_eq!(set.failures()[0].0, "no_leading_slash");
+        assert_eq!(set.descriptors().count(), 2);
+
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_a = Arc::clone(&got);
+        set.subscribe(tmp_a.name(), move |v: u32| {
+            got_a.lock().unwrap().push(v as f32)
+        })
+        .unwrap();
+        let got_b = Arc::clone(&got);
+        set.subscribe(tmp_b.name(), move |v: [f32; 2]| {
+            got_b.lock().unwrap().push(v[1])
+        })
+        .unwrap();
+
+        let err = set.publish(tmp_a.name(), &1.0f32, 1, 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        set.publish(tmp_a.name(), &7u32, 1, 0).unwrap();
+        set.publish(tmp_b.name(), &[0.0f32, 2.5], 1, 0).unwrap();
+        for _ in 0..100 {
+            if got.lock().unwrap().len() == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let mut got = got.lock().unwrap().clone();
+        got.sort_by(f32::total_cmp);
+        assert_eq!(got, vec![2.5, 7.0]);
+        set.unlink().unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Token-bucket shaping for bursty publishers.
+//!
+//! A [`TokenBucket`] lets `burst` messages through back to back and then
+//! one every `1 / rate` seconds, so a batch-processing node that wakes up
+//! with a hundred results does not overrun a shallow queue or a slow
+//! subscriber. Set it with [`TopicOptions::shape`](crate::TopicOptions::shape);
+//! every publish through that handle then waits for its token before it
+//! is sent:
+//!
+//! ```no_run
+//! # use mq_ipc::{MqTopic, TopicOptions};
+//! // At most 4 back to back, 50 per second sustained.
+//! let results = MqTopic::with_options("/results", &TopicOptions::new(4).shape(50.0, 4))?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! The wait happens before the send and regardless of
+//! [`nonblocking`](crate::TopicOptions::nonblocking): shaping decides
+//! *when* a message goes out, the queue's QoS what happens if it is still
+//! full. Callers that would rather skip than wait can hold a [`Shaper`]
+//! themselves and use [`Shaper::try_acquire`].
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Rate and burst size of a shaper.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TokenBucket {
+    /// Sustained messages per second.
+    pub rate_hz: f64,
+    /// Messages that may be sent back to back after an idle period.
+    pub burst: u32,
+}
+
+impl TokenBucket {
+    pub const fn new(rate_hz: f64, burst: u32) -> Self {
+        TokenBucket { rate_hz, burst }
+    }
+
+    /// Reject a rate that is not positive and finite, or a zero burst.
+    pub fn check(&self) -> io::Result<()> {
+        if !(self.rate_hz.is_finite() && self.rate_hz > 0.0) || self.burst == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "token bucket needs a positive rate and burst, got {} Hz x {}",
+                    self.rate_hz, self.burst
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time it takes to earn one token.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.rate_hz)
+    }
+}
+
+struct Bucket {
+    // May go negative: each caller reserves its token up front and sleeps
+    // off the debt outside the lock, so concurrent publishers queue fairly.
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Shared token-bucket state for one publisher.
+pub struct Shaper {
+    bucket: TokenBucket,
+    state: Mutex<Bucket>,
+    delayed: AtomicU64,
+}
+
+impl Shaper {
+    /// Start with a full bucket.
+    pub fn new(bucket: TokenBucket) -> io::Result<Self> {
+        bucket.check()?;
+        Ok(Shaper {
+            bucket,
+            state: Mutex::new(Bucket {
+                tokens: bucket.burst as f64,
+                refilled: Instant::now(),
+            }),
+            delayed: AtomicU64::new(0),
+        })
+    }
+
+    /// The configuration this shaper enforces.
+    pub fn bucket(&self) -> TokenBucket {
+        self.bucket
+    }
+
+    /// Take a token, or return how long until one is earned. Only a
+    /// successful call consumes a token.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.refill();
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.debt(1.0 - state.tokens))
+        }
+    }
+
+    /// Reserve a token and sleep until it is due. Returns how long the
+    /// caller waited; no wait is done once shutdown has been requested.
+    pub fn acquire(&self) -> Duration {
+        let wait = {
+            let mut state = self.refill();
+            state.tokens -= 1.0;
+            if state.tokens >= 0.0 {
+                return Duration::ZERO;
+            }
+            self.debt(-state.tokens)
+        };
+
+        self.delayed.fetch_add(1, Ordering::Relaxed);
+        if !crate::shutdown::requested() {
+            thread::sleep(wait);
+        }
+        wait
+    }
+
+    /// Publishes that had to wait for a token.
+    pub fn delayed(&self) -> u64 {
+        self.delayed.load(Ordering::Relaxed)
+    }
+
+    fn refill(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let earned = now.duration_since(state.refilled).as_secs_f64() * self.bucket.rate_hz;
+        state.tokens = (state.tokens + earned).min(self.bucket.burst as f64);
+        state.refilled = now;
+        state
+    }
+
+    fn debt(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64(tokens / self.bucket.rate_hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_passes_then_the_rate_applies() {
+        let shaper = Shaper::new(TokenBucket::new(100.0, 3)).unwrap();
+        for _ in 0..3 {
+            assert_eq!(shaper.try_acquire(), Ok(()));
+        }
+        let wait = shaper.try_acquire().unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(10));
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            shaper.acquire();
+        }
+        // Five tokens at 100 Hz take ~50 ms to earn.
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert!(shaper.delayed() >= 1);
+    }
+
+    #[test]
+    fn rejects_bad_buckets() {
+        assert!(Shaper::new(TokenBucket::new(0.0, 1)).is_err());
+        assert!(Shaper::new(TokenBucket::new(f64::INFINITY, 1)).is_err());
+        assert!(Shaper::new(TokenBucket::new(10.0, 0)).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Graceful process shutdown on SIGINT/SIGTERM.
+//!
+//! [`install`] hooks both signals to a crate-wide token. Once it flips, a
+//! watcher thread wakes every blocked worker out of its receive loop and
+//! [`spin`] returns, so a binary's `main` can simply be:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! let topic = mq_ipc::MqTopic::new("/example", 8)?;
+//! # #[cfg(feature = "callbacks")]
+//! topic.subscribe(|msg| println!("{:?}", msg.hdr));
+//! mq_ipc::shutdown::spin()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Polling receives keep handing out what is already queued; once a
+//! receive would come back empty it fails with an `Interrupted` error
+//! carrying a [`Shutdown`] instead (see [`is_shutdown`]).
+//!
+//! A second signal while shutdown is already in progress terminates the
+//! process immediately.
+
+#[cfg(feature = "callbacks")]
+use std::thread;
+use std::{
+    io,
+    os::raw::c_int,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+// Self-pipe used to wake `spin()` from the signal handler.
+static PIPE_RX: AtomicI32 = AtomicI32::new(-1);
+static PIPE_TX: AtomicI32 = AtomicI32::new(-1);
+static INSTALL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Why the process is shutting down.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Shutdown {
+    /// Signal number that triggered it, or `None` for [`request`].
+    pub signal: Option<c_int>,
+}
+
+impl std::fmt::Display for Shutdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.signal {
+            Some(sig) => write!(f, "shutting down on signal {sig}"),
+            None => write!(f, "shutting down on request"),
+        }
+    }
+}
+
+impl std::error::Error for Shutdown {}
+
+/// Whether `err` is the error receives return once shutdown is requested.
+pub fn is_shutdown(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<Shutdown>())
+}
+
+/// The error for receives made after shutdown was requested.
+pub(crate) fn error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, reason())
+}
+
+fn reason() -> Shutdown {
+    let sig = SIGNAL.load(Ordering::SeqCst);
+    Shutdown {
+        signal: (sig != 0).then_some(sig),
+    }
+}
+
+extern "C" fn on_signal(sig: c_int) {
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        // Second ctrl-c: the graceful path is wedged, bail out.
+        unsafe { libc::_exit(128 + sig) };
+    }
+    SIGNAL.store(sig, Ordering::SeqCst);
+    notify_pipe();
+}
+
+fn notify_pipe() {
+    let fd = PIPE_TX.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let byte = 1u8;
+        unsafe {
+            libc::write(fd, &byte as *const u8 as *const libc::c_void, 1);
+        }
+    }
+}
+
+/// Install the SIGINT/SIGTERM handlers. Calling it more than once is a no-op.
+///
+/// The handler itself only writes to a self-pipe; a `mq-ipc-shutdown`
+/// thread reads it and stops the workers, whether or not anything calls
+/// [`spin`]. Without the `callbacks` feature there are no workers and no
+/// such thread.
+pub fn install() -> io::Result<()> {
+    let _guard = INSTALL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if PIPE_RX.load(Ordering::SeqCst) >= 0 {
+        return Ok(());
+    }
+
+    let mut fds = [0 as c_int; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let rx = fds[0];
+    #[cfg(feature = "callbacks")]
+    thread::Builder::new()
+        .name("mq-ipc-shutdown".into())
+        .spawn(move || watch(rx))?;
+    PIPE_RX.store(rx, Ordering::SeqCst);
+    PIPE_TX.store(fds[1], Ordering::SeqCst);
+
+    for sig in [libc::SIGINT, libc::SIGTERM] {
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = on_signal as extern "C" fn(c_int) as libc::sighandler_t;
+        action.sa_flags = 0;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+
+        if unsafe { libc::sigaction(sig, &action, std::ptr::null_mut()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    // `request()` may have raced ahead of the pipe.
+    if REQUESTED.load(Ordering::SeqCst) {
+        notify_pipe();
+    }
+
+    Ok(())
+}
+
+// Waking workers takes a lock, which a signal handler must not do.
+#[cfg(feature = "callbacks")]
+fn watch(rx: c_int) {
+    let mut pfd = libc::pollfd {
+        fd: rx,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // The byte stays in the pipe for `wait_timeout` callers.
+    while unsafe { libc::poll(&mut pfd, 1, -1) } < 0 {
+        if io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            return;
+        }
+    }
+    if requested() {
+        crate::wake_all_workers();
+    }
+}
+
+/// Whether shutdown has been requested (by signal or [`request`]).
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Trigger shutdown programmatically, as if a signal had arrived.
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+    notify_pipe();
+    crate::wake_all_workers();
+}
+
+/// Block until shutdown is requested, then stop every worker in this
+/// process and report why.
+pub fn spin() -> io::Result<Shutdown> {
+    loop {
+        if let Some(reason) = wait_timeout(Duration::from_secs(3600))? {
+            return Ok(reason);
+        }
+    }
+}
+
+/// Like [`spin`], but gives up after `timeout` and returns `None`.
+pub fn wait_timeout(timeout: Duration) -> io::Result<Option<Shutdown>> {
+    install()?;
+
+    if !requested() {
+        let mut pfd = libc::pollfd {
+            fd: PIPE_RX.load(Ordering::SeqCst),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ms = timeout.as_millis().min(c_int::MAX as u128) as c_int;
+
+        // The pipe byte is left in place so every waiter wakes up.
+        let rc = unsafe { libc::poll(&mut pfd, 1, ms) };
+        if rc < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EINTR) {
+                return Err(err);
+            }
+        }
+
+        if !requested() {
+            return Ok(None);
+        }
+    }
+
+    crate::wake_all_workers();
+    Ok(Some(reason()))
+}
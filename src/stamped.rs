@@ -0,0 +1,359 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Timestamped samples and short time series.
+//!
+//! Nearly every sensor topic pairs a value with the time it was taken.
+//! [`Stamped<T>`] is that pair as a [`Pod`], so it goes over any
+//! [`Topic`](crate::Topic) as-is, and a [`Series`] keeps the latest few on
+//! the receiving side to answer the usual questions: how long between the
+//! last two samples, what the value was (or is estimated to be) at a given
+//! time, and what the data looks like as CSV for a plot.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::{stamped::{Series, Stamped}, Topic};
+//!
+//! let imu = Topic::<Stamped<[f32; 3]>>::new("/imu/accel", 16)?;
+//! imu.publish(&Stamped::now([0.0, 0.0, 9.81]), 1, 0)?;
+//!
+//! let mut series = Series::new(64);
+//! while let Some(sample) = imu.try_recv()? {
+//!     series.push(sample);
+//! }
+//! if let Some(accel) = series.at(mq_ipc::clock::default_clock().now()) {
+//!     println!("estimated now: {accel:?}");
+//! }
+//! series.write_csv(std::io::stdout())?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Timestamps come from the [default clock](crate::clock::default_clock),
+//! `CLOCK_MONOTONIC` unless replaced, so samples from different processes
+//! on one machine compare directly.
+
+use super::{
+    clock,
+    layout::{Describe, Field, Scalar},
+};
+use bytemuck::{Pod, Zeroable};
+use std::{collections::VecDeque, io, time::Duration};
+
+/// A value and the time it was sampled.
+///
+/// Packed so that it is [`Pod`] for any `T` without padding; copy fields
+/// out (`{ sample.value }`) rather than borrowing them.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Stamped<T> {
+    /// Sample time in nanoseconds on the sampling clock.
+    pub stamp_ns: u64,
+    pub value: T,
+}
+
+// SAFETY: packed, so there is no padding, and both fields are Pod.
+unsafe impl<T: Pod> Zeroable for Stamped<T> {}
+unsafe impl<T: Pod> Pod for Stamped<T> {}
+
+impl<T: Copy> Stamped<T> {
+    pub fn new(stamp: Duration, value: T) -> Self {
+        Stamped {
+            stamp_ns: stamp.as_nanos() as u64,
+            value,
+        }
+    }
+
+    /// `value` stamped with the default clock's current time.
+    pub fn now(value: T) -> Self {
+        Self::new(clock::default_clock().now(), value)
+    }
+
+    pub fn stamp(&self) -> Duration {
+        Duration::from_nanos(self.stamp_ns)
+    }
+
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    /// Time from `earlier` to this sample; zero if `earlier` is newer.
+    pub fn dt(&self, earlier: &Self) -> Duration {
+        Duration::from_nanos(self.stamp_ns.saturating_sub(earlier.stamp_ns))
+    }
+}
+
+/// Values that can be blended linearly, for [`interpolate`].
+pub trait Interpolate: Copy {
+    /// `self` at `t = 0`, `other` at `t = 1`; other `t` extrapolate.
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for f32 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        (*self as f64).lerp(&(*other as f64), t) as f32
+    }
+}
+
+impl<T: Interpolate, const N: usize> Interpolate for [T; N] {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&other[i], t))
+    }
+}
+
+/// Value at `at` on the line through `a` and `b`. Times outside the pair
+/// extrapolate; equal stamps give `b`.
+pub fn interpolate<T: Interpolate>(a: &Stamped<T>, b: &Stamped<T>, at: Duration) -> T {
+    let span = b.stamp_ns as f64 - a.stamp_ns as f64;
+    if span == 0.0 {
+        return b.value();
+    }
+    let t = (at.as_nanos() as f64 - a.stamp_ns as f64) / span;
+    a.value().lerp(&b.value(), t)
+}
+
+impl<T: Describe> Describe for Stamped<T> {
+    fn type_name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn describe(path: &str, offset: usize, out: &mut Vec<Field>) {
+        let join = |name: &str| match path {
+            "" => name.to_string(),
+            _ => format!("{path}.{name}"),
+        };
+        u64::describe(&join("stamp_ns"), offset, out);
+        T::describe(
+            &join("value"),
+            offset + std::mem::offset_of!(Self, value),
+            out,
+        );
+    }
+}
+
+/// The most recent samples of a stream, oldest first.
+#[derive(Clone, Debug)]
+pub struct Series<T: Pod> {
+    samples: VecDeque<Stamped<T>>,
+    capacity: usize,
+}
+
+impl<T: Pod> Series<T> {
+    /// Keep up to `capacity` samples (at least two).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(2);
+        Series {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Append `sample`, dropping the oldest when full. Samples not newer
+    /// than the latest are ignored and `false` is returned.
+    pub fn push(&mut self, sample: Stamped<T>) -> bool {
+        if let Some(last) = self.samples.back()
+            && sample.stamp_ns <= last.stamp_ns
+        {
+            return false;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        true
+    }
+
+    pub fn latest(&self) -> Option<Stamped<T>> {
+        self.samples.back().copied()
+    }
+
+    /// Time between the two latest samples.
+    pub fn last_dt(&self) -> Option<Duration> {
+        let n = self.samples.len();
+        (n >= 2).then(|| self.samples[n - 1].dt(&self.samples[n - 2]))
+    }
+
+    /// Mean sample rate over the series, in Hz.
+    pub fn rate_hz(&self) -> Option<f64> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let span = last.dt(first).as_secs_f64();
+        (span > 0.0).then(|| (self.samples.len() - 1) as f64 / span)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Stamped<T>> {
+        self.samples.iter()
+    }
+
+    /// Value at `at`, interpolated between the samples around it, or
+    /// extrapolated from the two latest (or earliest) when `at` is
+    /// outside the series. A single sample is returned as-is.
+    pub fn at(&self, at: Duration) -> Option<T>
+    where
+        T: Interpolate,
+    {
+        let ns = at.as_nanos() as u64;
+        let n = self.samples.len();
+        match n {
+            0 => None,
+            1 => Some(self.samples[0].value()),
+            _ => {
+                let i = self
+                    .samples
+                    .partition_point(|s| s.stamp_ns <= ns)
+                    .clamp(1, n - 1);
+                Some(interpolate(&self.samples[i - 1], &self.samples[i], at))
+            }
+        }
+    }
+
+    /// Write the series as CSV: a `t` column in seconds, then one column
+    /// per scalar of `T` as given by its [`Describe`] layout, with array
+    /// fields expanded to `name[i]`.
+    pub fn write_csv<W: io::Write>(&self, out: W) -> io::Result<()>
+    where
+        T: Describe,
+    {
+        write_csv(out, self.iter())
+    }
+}
+
+/// Write `samples` as CSV; see [`Series::write_csv`].
+pub fn write_csv<'a, T, W>(
+    mut out: W,
+    samples: impl IntoIterator<Item = &'a Stamped<T>>,
+) -> io::Result<()>
+where
+    T: Describe + 'a,
+    W: io::Write,
+{
+    let mut fields = Vec::new();
+    T::describe("", 0, &mut fields);
+
+    let mut header = vec!["t".to_string()];
+    for field in &fields {
+        let name = if field.name.is_empty() {
+            "value"
+        } else {
+            &field.name
+        };
+        match field.len {
+            Some(len) => header.extend((0..len).map(|i| format!("{name}[{i}]"))),
+            None => header.push(name.to_string()),
+        }
+    }
+    writeln!(out, "{}", header.join(","))?;
+
+    for sample in samples {
+        let value = sample.value();
+        let bytes = bytemuck::bytes_of(&value);
+        write!(out, "{}", sample.stamp().as_secs_f64())?;
+        for field in &fields {
+            let size = field.ty.size();
+            for i in 0..field.len.unwrap_or(1) {
+                let at = field.offset + i * size;
+                write!(out, ",{}", scalar_text(field.ty, &bytes[at..at + size]))?;
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn scalar_text(ty: Scalar, b: &[u8]) -> String {
+    macro_rules! read {
+        ($t:ty) => {
+            <$t>::from_ne_bytes(b.try_into().unwrap()).to_string()
+        };
+    }
+    match ty {
+        Scalar::U8 => read!(u8),
+        Scalar::I8 => read!(i8),
+        Scalar::U16 => read!(u16),
+        Scalar::I16 => read!(i16),
+        Scalar::U32 => read!(u32),
+        Scalar::I32 => read!(i32),
+        Scalar::U64 => read!(u64),
+        Scalar::I64 => read!(i64),
+        Scalar::F32 => read!(f32),
+        Scalar::F64 => read!(f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64, value: [f32; 2]) -> Stamped<[f32; 2]> {
+        Stamped::new(Duration::from_millis(ms), value)
+    }
+
+    #[test]
+    fn series_dt_and_interpolation() {
+        let mut series = Series::new(3);
+        assert!(series.push(at(0, [0.0, 10.0])));
+        assert!(series.push(at(10, [1.0, 10.0])));
+        assert!(!series.push(at(10, [9.0, 9.0])));
+        assert!(series.push(at(30, [3.0, 20.0])));
+        assert!(series.push(at(40, [4.0, 20.0])));
+
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.last_dt(), Some(Duration::from_millis(10)));
+        assert_eq!(series.rate_hz(), Some(2.0 / 0.030));
+        let ms = Duration::from_millis;
+        assert_eq!(series.at(ms(20)), Some([2.0, 15.0]));
+        assert_eq!(series.at(ms(30)), Some([3.0, 20.0]));
+        // Past the end: extrapolated from the two latest.
+        assert_eq!(series.at(ms(50)), Some([5.0, 20.0]));
+        assert_eq!(std::mem::size_of::<Stamped<[f32; 3]>>(), 20);
+    }
+
+    #[test]
+    fn csv_follows_the_layout() {
+        let samples = [at(1500, [0.5, -1.0]), at(2000, [0.25, 2.0])];
+        let mut out = Vec::new();
+        write_csv(&mut out, &samples).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "t,value[0],value[1]\n1.5,0.5,-1\n2,0.25,2\n"
+        );
+
+        let mut fields = Vec::new();
+        Stamped::<[f32; 2]>::describe("", 0, &mut fields);
+        assert_eq!(fields[1].name, "value");
+        assert_eq!(fields[1].offset, 8);
+    }
+}
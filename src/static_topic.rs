@@ -0,0 +1,286 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Topics declared once, as statics, and opened on first use.
+//!
+//! Large applications keep every topic in one table instead of spelling
+//! names and depths out wherever a handle is needed:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use bytemuck::{Pod, Zeroable};
+//!
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! pub struct MotorState { rpm: f32, torque: f32 }
+//!
+//! mq_ipc::topics! {
+//!     pub MOTOR_STATE: MotorState = "/motor_state", 8;
+//!     pub MOTOR_CMD: f32 = "/motor_cmd", 4;
+//! }
+//!
+//! MOTOR_STATE.get()?.publish(&MotorState { rpm: 1.0, torque: 0.5 }, 1, 0)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The names in one [`topics!`](crate::topics!) table are checked while compiling: two
+//! entries with the same name, or a name that is not a `/` followed by
+//! characters other than `/`, fail the build.
+//!
+//! [`topic!`](crate::topic!) declares a single topic and also takes its
+//! [`Qos`](crate::Qos) and any other [`TopicOptions`] setting:
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use std::time::Duration;
+//!
+//! mq_ipc::topic!(pub WHEEL_SPEED: f32 = "/wheel_speed", depth = 8, qos = KeepLatest);
+//! mq_ipc::topic!(ESTOP: u8 = "/estop", depth = 4, qos = Reliable, ttl = Duration::from_millis(50));
+//!
+//! WHEEL_SPEED.get()?.publish(&1.5, 1, 0)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Topic, TopicOptions};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    os::raw::c_long,
+    sync::{Mutex, OnceLock},
+};
+
+/// A topic declared as a `static`; the queue is opened by the first
+/// [`get`](Self::get) and shared by every later one.
+pub struct StaticTopic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    name: &'static str,
+    depth: c_long,
+    options: fn(c_long) -> TopicOptions,
+    topic: OnceLock<Topic<T>>,
+    opening: Mutex<()>,
+}
+
+impl<T> StaticTopic<T>
+where
+    T: Pod + Zeroable + Send + Sync + 'static,
+{
+    /// Declare topic `name` with room for `depth` messages.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is not a valid queue name; in a `static` this fails the
+    /// build.
+    pub const fn new(name: &'static str, depth: c_long) -> Self {
+        Self::with_options(name, depth, TopicOptions::new)
+    }
+
+    /// Like [`new`](Self::new), but the topic is opened with the options
+    /// `options` builds from `depth`.
+    pub const fn with_options(
+        name: &'static str,
+        depth: c_long,
+        options: fn(c_long) -> TopicOptions,
+    ) -> Self {
+        assert_valid(name);
+        StaticTopic {
+            name,
+            depth,
+            options,
+            topic: OnceLock::new(),
+            opening: Mutex::new(()),
+        }
+    }
+
+    /// The topic, opened now if this is the first use. A failed open is
+    /// not remembered, so the next call tries again.
+    pub fn get(&self) -> io::Result<&Topic<T>> {
+        if let Some(topic) = self.topic.get() {
+            return Ok(topic);
+        }
+        let _opening = self.opening.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(topic) = self.topic.get() {
+            return Ok(topic);
+        }
+        let topic = Topic::with_options(self.name, &self.options())?;
+        Ok(self.topic.get_or_init(|| topic))
+    }
+
+    /// Whether [`get`](Self::get) has opened the topic yet.
+    pub fn is_open(&self) -> bool {
+        self.topic.get().is_some()
+    }
+
+    /// The declared name, before any `MQ_IPC_PREFIX` is applied.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn depth(&self) -> c_long {
+        self.depth
+    }
+
+    /// The options the topic is opened with.
+    pub fn options(&self) -> TopicOptions {
+        (self.options)(self.depth)
+    }
+}
+
+/// Panic unless `name` is a `/` followed by one or more characters other
+/// than `/`, as POSIX requires of portable queue names.
+pub const fn assert_valid(name: &str) {
+    let bytes = name.as_bytes();
+    assert!(
+        bytes.len() > 1 && bytes[0] == b'/',
+        "topic names start with '/'"
+    );
+    let mut i = 1;
+    while i < bytes.len() {
+        assert!(
+            bytes[i] != b'/',
+            "topic names contain no '/' after the first"
+        );
+        i += 1;
+    }
+}
+
+/// Panic if any two of `names` are equal.
+pub const fn assert_unique(names: &[&str]) {
+    let mut i = 0;
+    while i < names.len() {
+        let mut j = i + 1;
+        while j < names.len() {
+            assert!(!str_eq(names[i], names[j]), "duplicate topic name");
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Declare a table of [`StaticTopic`]s, one `static` per line of
+/// `visibility NAME: Type = "/name", depth;`. See the
+/// [module docs](crate::static_topic).
+///
+/// ```compile_fail
+/// mq_ipc::topics! {
+///     LEFT: u32 = "/wheel", 4;
+///     RIGHT: u32 = "/wheel", 4;
+/// }
+/// ```
+#[macro_export]
+macro_rules! topics {
+    ($($vis:vis $id:ident : $ty:ty = $name:expr, $depth:expr;)*) => {
+        $(
+            $vis static $id: $crate::static_topic::StaticTopic<$ty> =
+                $crate::static_topic::StaticTopic::new($name, $depth);
+        )*
+        const _: () = $crate::static_topic::assert_unique(&[$($name),*]);
+    };
+}
+
+/// Declare one [`StaticTopic`]:
+/// `topic!(visibility NAME: Type = "/name", depth = N, qos = Profile, ...)`.
+///
+/// `qos` names a [`Qos`](crate::Qos) variant; every further `key = value`
+/// calls the [`TopicOptions`] setter `key` with `value`. Both are
+/// optional. See the [module docs](crate::static_topic).
+#[macro_export]
+macro_rules! topic {
+    (
+        $vis:vis $id:ident : $ty:ty = $name:expr, depth = $depth:expr,
+        qos = $qos:ident $(, $key:ident = $value:expr)* $(,)?
+    ) => {
+        $crate::topic!(
+            $vis $id: $ty = $name, depth = $depth,
+            qos = $crate::Qos::$qos $(, $key = $value)*
+        );
+    };
+    (
+        $vis:vis $id:ident : $ty:ty = $name:expr, depth = $depth:expr
+        $(, $key:ident = $value:expr)* $(,)?
+    ) => {
+        $vis static $id: $crate::static_topic::StaticTopic<$ty> =
+            $crate::static_topic::StaticTopic::with_options($name, $depth, |depth| {
+                $crate::TopicOptions::new(depth) $(.$key($value))*
+            });
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::topics! {
+        COUNTER: u32 = "/mq_ipc_test_static_counter", 4;
+        LEVEL: f32 = "/mq_ipc_test_static_level", 2;
+    }
+
+    #[test]
+    fn opens_on_first_use() {
+        let _cleanup = crate::cleanup::TempTopic::with_name(COUNTER.name());
+        assert!(!COUNTER.is_open());
+        let topic = COUNTER.get().unwrap();
+        assert!(COUNTER.is_open());
+        assert!(std::ptr::eq(topic, COUNTER.get().unwrap()));
+
+        topic.publish(&5, 1, 0).unwrap();
+        assert_eq!(COUNTER.get().unwrap().try_recv().unwrap(), Some(5));
+        assert_eq!((LEVEL.depth(), LEVEL.is_open()), (2, false));
+    }
+
+    #[test]
+    fn topic_macro_bakes_in_options() {
+        crate::topic!(LATEST: u32 = "/mq_ipc_test_static_latest", depth = 3, qos = KeepLatest);
+        crate::topic!(STRICT: u32 = "/mq_ipc_test_static_strict", depth = 5, strict = true);
+
+        let latest = LATEST.options();
+        assert!(latest.conflate && !latest.nonblocking && latest.maxmsg == 3);
+        let strict = STRICT.options();
+        assert!(strict.strict && !strict.conflate && strict.maxmsg == 5);
+        assert!(TopicOptions::new(1).qos(crate::Qos::BestEffort).nonblocking);
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate topic name")]
+    fn duplicate_names_are_rejected() {
+        assert_unique(&["/a", "/b", "/a"]);
+    }
+}
@@ -0,0 +1,385 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Runtime statistics of a topic and its subscribers.
+
+use bytemuck::{Pod, Zeroable};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Topic that periodic [`StatsReport`]s are published on; see
+/// [`TopicOptions::stats_report`](crate::TopicOptions::stats_report).
+pub const STATS_TOPIC: &str = "/ipc_stats";
+
+/// Longest topic name carried by a [`StatsReport`].
+pub const STATS_REPORT_NAME_LEN: usize = 152;
+
+/// Topic that periodic [`DepthReport`]s are published on; see
+/// [`TopicOptions::depth_report`](crate::TopicOptions::depth_report).
+pub const DEPTH_REPORT_TOPIC: &str = "/ipc_stats.depth";
+
+/// Longest topic name carried by a [`DepthReport`].
+pub const DEPTH_REPORT_NAME_LEN: usize = 208;
+
+/// Live counters of one subscription, updated by the worker.
+#[cfg(feature = "callbacks")]
+#[derive(Debug, Default)]
+pub(crate) struct CallbackCounters {
+    invocations: AtomicU64,
+    total_ns: AtomicU64,
+    max_ns: AtomicU64,
+    over_budget: AtomicU64,
+}
+
+#[cfg(feature = "callbacks")]
+impl CallbackCounters {
+    /// Account one invocation. Returns true if it exceeded `budget`.
+    pub(crate) fn record(&self, elapsed: Duration, budget: Option<Duration>) -> bool {
+        let ns = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+        self.total_ns.fetch_add(ns, Ordering::Relaxed);
+        self.max_ns.fetch_max(ns, Ordering::Relaxed);
+
+        let over = budget.is_some_and(|b| elapsed > b);
+        if over {
+            self.over_budget.fetch_add(1, Ordering::Relaxed);
+        }
+        over
+    }
+
+    pub(crate) fn snapshot(&self, index: usize) -> CallbackStats {
+        CallbackStats {
+            index,
+            invocations: self.invocations.load(Ordering::Relaxed),
+            total: Duration::from_nanos(self.total_ns.load(Ordering::Relaxed)),
+            max: Duration::from_nanos(self.max_ns.load(Ordering::Relaxed)),
+            over_budget: self.over_budget.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Publish-side counters of a topic handle.
+#[derive(Debug, Default)]
+pub(crate) struct TrafficCounters {
+    pub(crate) published: AtomicU64,
+    pub(crate) dropped: AtomicU64,
+    pub(crate) rejected: AtomicU64,
+    pub(crate) expired: AtomicU64,
+}
+
+impl TrafficCounters {
+    /// Account the outcome of one publish.
+    pub(crate) fn record(&self, published: bool) {
+        let counter = if published {
+            &self.published
+        } else {
+            &self.dropped
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Execution statistics of one subscriber callback.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallbackStats {
+    /// Position of the callback in dispatch order: by priority, then
+    /// subscription order.
+    pub index: usize,
+    pub invocations: u64,
+    /// Total time spent inside the callback.
+    pub total: Duration,
+    /// Longest single invocation.
+    pub max: Duration,
+    /// Invocations that exceeded the configured budget.
+    pub over_budget: u64,
+}
+
+impl CallbackStats {
+    /// Mean time per invocation.
+    pub fn avg(&self) -> Duration {
+        if self.invocations == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total.as_nanos() / self.invocations as u128) as u64)
+        }
+    }
+}
+
+/// Queue depth samples, taken by the worker on every receive.
+#[derive(Debug, Default)]
+pub(crate) struct DepthCounters {
+    high: AtomicU64,
+    samples: AtomicU64,
+    total: AtomicU64,
+}
+
+impl DepthCounters {
+    pub(crate) fn record(&self, depth: u64) {
+        self.high.fetch_max(depth, Ordering::Relaxed);
+        self.samples.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(depth, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, maxmsg: u64, current: u64) -> DepthStats {
+        DepthStats {
+            maxmsg,
+            current,
+            high_watermark: self.high.load(Ordering::Relaxed).max(current),
+            samples: self.samples.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Queue occupancy of a topic.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DepthStats {
+    /// Capacity the queue was created with.
+    pub maxmsg: u64,
+    /// Messages queued right now.
+    pub current: u64,
+    /// Deepest the queue has been seen by this process.
+    pub high_watermark: u64,
+    /// Receives sampled so far.
+    pub samples: u64,
+    /// Sum of the sampled depths, for [`DepthStats::avg`].
+    pub total: u64,
+}
+
+impl DepthStats {
+    /// Mean depth seen at receive time.
+    pub fn avg(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.total as f64 / self.samples as f64
+        }
+    }
+}
+
+/// Periodic depth sample of one topic, published on [`DEPTH_REPORT_TOPIC`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct DepthReport {
+    pub maxmsg: u32,
+    pub current: u32,
+    pub high_watermark: u32,
+    pub name_len: u32,
+    pub samples: u64,
+    pub name: [u8; DEPTH_REPORT_NAME_LEN],
+}
+
+impl DepthReport {
+    /// Build a report for `name`, truncating it if needed.
+    pub fn new(name: &str, depth: &DepthStats) -> Self {
+        let mut report = DepthReport::zeroed();
+        let len = name.len().min(DEPTH_REPORT_NAME_LEN);
+        report.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        report.name_len = len as u32;
+        report.maxmsg = depth.maxmsg as u32;
+        report.current = depth.current as u32;
+        report.high_watermark = depth.high_watermark as u32;
+        report.samples = depth.samples;
+        report
+    }
+
+    /// Name of the reported topic.
+    pub fn name(&self) -> &str {
+        let len = (self.name_len as usize).min(DEPTH_REPORT_NAME_LEN);
+        std::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+/// Snapshot returned by [`MqTopic::stats`](crate::MqTopic::stats).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TopicStats {
+    pub name: String,
+    pub callbacks: Vec<CallbackStats>,
+    pub depth: DepthStats,
+    /// Messages this handle published.
+    pub published: u64,
+    /// Publishes that failed, plus queued messages displaced by
+    /// conflation.
+    pub dropped: u64,
+    /// Values refused by [`validate`](crate::validate) hooks.
+    pub rejected: u64,
+    /// Received messages discarded for outliving the topic's
+    /// [`ttl`](crate::TopicOptions::ttl).
+    pub expired: u64,
+    /// CPU time used by this handle's worker thread, receiving and running
+    /// callbacks. Updated after each message.
+    pub worker_cpu: Duration,
+}
+
+/// Periodic statistics of one topic, published on [`STATS_TOPIC`].
+///
+/// Counters are totals over the life of the reporting handle; the rates
+/// cover the interval since its previous report.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct StatsReport {
+    /// Wall-clock time of the sample, in ns since the Unix epoch.
+    pub timestamp_ns: u64,
+    pub published: u64,
+    /// Messages taken off the queue by this handle's worker.
+    pub received: u64,
+    pub dropped: u64,
+    pub rejected: u64,
+    /// Mean callback run time across all subscribers.
+    pub latency_avg_ns: u64,
+    /// Longest single callback run.
+    pub latency_max_ns: u64,
+    /// Messages per second.
+    pub publish_rate: f32,
+    pub receive_rate: f32,
+    pub maxmsg: u32,
+    pub current: u32,
+    pub high_watermark: u32,
+    pub name_len: u32,
+    pub name: [u8; STATS_REPORT_NAME_LEN],
+}
+
+impl StatsReport {
+    /// Build a report from `now`, with rates taken against `prev`, the
+    /// snapshot from `elapsed` ago. The name is truncated if needed.
+    pub fn new(now: &TopicStats, prev: &TopicStats, elapsed: Duration) -> Self {
+        let mut report = StatsReport::zeroed();
+        let len = now.name.len().min(STATS_REPORT_NAME_LEN);
+        report.name[..len].copy_from_slice(&now.name.as_bytes()[..len]);
+        report.name_len = len as u32;
+        report.timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+
+        report.published = now.published;
+        report.received = now.depth.samples;
+        report.dropped = now.dropped;
+        report.rejected = now.rejected;
+
+        let invocations: u64 = now.callbacks.iter().map(|c| c.invocations).sum();
+        let total: Duration = now.callbacks.iter().map(|c| c.total).sum();
+        if invocations > 0 {
+            report.latency_avg_ns = (total.as_nanos() / invocations as u128) as u64;
+        }
+        report.latency_max_ns = now
+            .callbacks
+            .iter()
+            .map(|c| c.max.as_nanos() as u64)
+            .max()
+            .unwrap_or(0);
+
+        let secs = elapsed.as_secs_f32();
+        if secs > 0.0 {
+            report.publish_rate = now.published.saturating_sub(prev.published) as f32 / secs;
+            report.receive_rate =
+                now.depth.samples.saturating_sub(prev.depth.samples) as f32 / secs;
+        }
+
+        report.maxmsg = now.depth.maxmsg as u32;
+        report.current = now.depth.current as u32;
+        report.high_watermark = now.depth.high_watermark as u32;
+        report
+    }
+
+    /// Name of the reported topic.
+    pub fn name(&self) -> &str {
+        let len = (self.name_len as usize).min(STATS_REPORT_NAME_LEN);
+        std::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_track_max_avg_and_budget() {
+        let c = CallbackCounters::default();
+        let budget = Some(Duration::from_millis(5));
+        assert!(!c.record(Duration::from_millis(2), budget));
+        assert!(c.record(Duration::from_millis(10), budget));
+
+        let s = c.snapshot(3);
+        assert_eq!(s.index, 3);
+        assert_eq!(s.invocations, 2);
+        assert_eq!(s.max, Duration::from_millis(10));
+        assert_eq!(s.avg(), Duration::from_millis(6));
+        assert_eq!(s.over_budget, 1);
+
+        let many = CallbackStats {
+            invocations: 1 << 32,
+            total: Duration::from_secs(1 << 32),
+            ..s
+        };
+        assert_eq!(many.avg(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn depth_watermark_and_report() {
+        let d = DepthCounters::default();
+        for depth in [1, 4, 2, 1] {
+            d.record(depth);
+        }
+
+        let s = d.snapshot(4, 3);
+        assert_eq!(s.high_watermark, 4);
+        assert_eq!(s.samples, 4);
+        assert_eq!(s.avg(), 2.0);
+        assert_eq!(d.snapshot(10, 7).high_watermark, 7);
+
+        let report = DepthReport::new("/motor/state", &s);
+        assert_eq!(report.name(), "/motor/state");
+        assert_eq!((report.current, report.high_watermark), (3, 4));
+    }
+
+    #[test]
+    fn stats_report_rates_and_latency() {
+        let c = CallbackCounters::default();
+        c.record(Duration::from_micros(10), None);
+        c.record(Duration::from_micros(30), None);
+        let prev = TopicStats {
+            name: "/imu".to_string(),
+            published: 10,
+            ..Default::default()
+        };
+        let mut now = prev.clone();
+        now.published = 30;
+        now.dropped = 2;
+        now.callbacks = vec![c.snapshot(0)];
+        now.depth.samples = 5;
+
+        let report = StatsReport::new(&now, &prev, Duration::from_secs(2));
+        assert_eq!(report.name(), "/imu");
+        assert_eq!(
+            (report.published, report.dropped, report.received),
+            (30, 2, 5)
+        );
+        assert_eq!(report.publish_rate, 10.0);
+        assert_eq!(report.receive_rate, 2.5);
+        assert_eq!(report.latency_avg_ns, 20_000);
+        assert_eq!(report.latency_max_ns, 30_000);
+    }
+}
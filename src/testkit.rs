@@ -0,0 +1,510 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Harness for tests that span several processes.
+//!
+//! A test re-executes its own binary as helper children: the parent
+//! builds a [`Harness`], spawns roles with [`Harness::spawn`] and waits
+//! on the startup barrier, while the same test function run inside a
+//! child sees [`child`] return its role and does that part instead.
+//!
+//! ```no_run
+//! use mq_ipc::{testkit, MqTopic, Msg};
+//! use std::time::Duration;
+//!
+//! #[test]
+//! fn ping_across_processes() {
+//!     if let Some(env) = testkit::child() {
+//!         let topic = MqTopic::new(&env.topic_name("data"), 8).unwrap();
+//!         env.ready().unwrap();
+//!         topic.publish(&Msg::new(1, b"hi"), 0).unwrap();
+//!         return;
+//!     }
+//!
+//!     let mut harness = testkit::Harness::new("ping_across_processes").unwrap();
+//!     let topic = MqTopic::new(&harness.topic_name("data"), 8).unwrap();
+//!     topic.subscribe(|msg| assert_eq!(msg.hdr.msg_type, 1));
+//!     harness.spawn("publisher").unwrap();
+//!     harness.wait_ready(1, Duration::from_secs(5)).unwrap();
+//!     harness.wait_all(Duration::from_secs(5)).unwrap();
+//! }
+//! ```
+//!
+//! Every queue name comes from a per-harness prefix, so concurrent test
+//! runs never share queues, and all of them are unlinked when the
+//! harness is dropped.
+//!
+//! For single-process tests of subscriber logic, [`ScriptedPublisher`]
+//! plays a fixed, timed sequence of messages onto any [`Transport`],
+//! including the in-memory [`MockTransport`].
+
+#[cfg(feature = "callbacks")]
+use super::transport::MsgCallback;
+use super::{
+    cleanup, clock, journal::JournalEntry, open_queue, realtime_after, transport::Transport, Msg,
+    RecvBuf,
+};
+use bytemuck::Pod;
+use libc::{self, mqd_t};
+use std::{
+    env, io,
+    os::raw::c_char,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Environment variable carrying the role of a spawned child.
+pub const ROLE_ENV: &str = "MQ_IPC_TESTKIT_ROLE";
+/// Environment variable carrying the queue prefix of a spawned child.
+pub const PREFIX_ENV: &str = "MQ_IPC_TESTKIT_PREFIX";
+
+const BARRIER_SUFFIX: &str = "barrier";
+const MSG_TYPE_READY: u16 = 0x7E57;
+const BARRIER_DEPTH: libc::c_long = 10;
+
+static HARNESS_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// What a helper child was spawned to do.
+#[derive(Clone, Debug)]
+pub struct ChildEnv {
+    role: String,
+    prefix: String,
+}
+
+/// The child environment, or `None` when running as the parent test.
+pub fn child() -> Option<ChildEnv> {
+    let role = env::var(ROLE_ENV).ok()?;
+    let prefix = env::var(PREFIX_ENV).ok()?;
+    Some(ChildEnv { role, prefix })
+}
+
+impl ChildEnv {
+    /// Role name passed to [`Harness::spawn`].
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    /// Same name the parent gets from [`Harness::topic_name`].
+    pub fn topic_name(&self, suffix: &str) -> String {
+        format!("{}{suffix}", self.prefix)
+    }
+
+    /// Tell the parent this child is set up.
+    ///
+    /// The barrier is written through a bare descriptor, so no receive
+    /// worker competes with the parent for it.
+    pub fn ready(&self) -> io::Result<()> {
+        let mqd = open_queue(&self.topic_name(BARRIER_SUFFIX), libc::O_WRONLY, None)?;
+        let msg = Msg::new(MSG_TYPE_READY, &std::process::id().to_le_bytes());
+        let rc = unsafe {
+            libc::mq_send(
+                mqd,
+                &msg as *const Msg as *const c_char,
+                std::mem::size_of::<Msg>(),
+                0,
+            )
+        };
+        let res = if rc == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        unsafe {
+            libc::mq_close(mqd);
+        }
+        res
+    }
+}
+
+/// Parent side of a multi-process test.
+///
+/// Children are killed and every queue under the prefix is unlinked when
+/// the harness is dropped, including during a panic unwind.
+pub struct Harness {
+    test_name: String,
+    prefix: String,
+    barrier: mqd_t,
+    names: Vec<String>,
+    children: Vec<Child>,
+}
+
+impl Harness {
+    /// Create a harness for the test function `test_name`.
+    ///
+    /// Children re-run the current test binary filtered to exactly that
+    /// name, so it must be the full path libtest prints (e.g.
+    /// `module::tests::my_test` for unit tests).
+    pub fn new(test_name: &str) -> io::Result<Self> {
+        let n = HARNESS_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let prefix = format!("/mq_ipc_tk_{}_{n}_", std::process::id());
+        let barrier_name = format!("{prefix}{BARRIER_SUFFIX}");
+        let barrier = open_queue(
+            &barrier_name,
+            libc::O_CREAT | libc::O_RDONLY,
+            Some(BARRIER_DEPTH),
+        )?;
+
+        Ok(Harness {
+            test_name: test_name.to_string(),
+            prefix,
+            barrier,
+            names: vec![barrier_name],
+            children: Vec::new(),
+        })
+    }
+
+    /// Prefix shared by every queue of this harness.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Queue name for `suffix`, unlinked when the harness is dropped.
+    pub fn topic_name(&mut self, suffix: &str) -> String {
+        let name = format!("{}{suffix}", self.prefix);
+        if !self.names.contains(&name) {
+            self.names.push(name.clone());
+        }
+        name
+    }
+
+    /// Start a child running this test with `role`; returns its pid.
+    pub fn spawn(&mut self, role: &str) -> io::Result<u32> {
+        let child = Command::new(env::current_exe()?)
+            .args([self.test_name.as_str(), "--exact", "--nocapture"])
+            .env(ROLE_ENV, role)
+            .env(PREFIX_ENV, &self.prefix)
+            .stdin(Stdio::null())
+            .spawn()?;
+        let pid = child.id();
+        self.children.push(child);
+        Ok(pid)
+    }
+
+    /// Block until `n` children have called [`ChildEnv::ready`].
+    pub fn wait_ready(&mut self, n: usize, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut buf = RecvBuf::new();
+        let mut seen = 0;
+
+        while seen < n {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{seen} of {n} children ready"),
+                ));
+            }
+
+            let ts = realtime_after(left.min(Duration::from_millis(100)));
+            let ret = unsafe {
+                libc::mq_timedreceive(
+                    self.barrier,
+                    buf.as_mut_ptr(),
+                    RecvBuf::LEN,
+                    std::ptr::null_mut(),
+                    &ts,
+                )
+            };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::ETIMEDOUT) | Some(libc::EINTR) => {}
+                    _ => return Err(err),
+                }
+                self.check_children()?;
+                continue;
+            }
+
+            let msg = buf.msg(ret as usize)?;
+            if msg.hdr.msg_type == MSG_TYPE_READY {
+                seen += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for every child to exit and return their statuses in spawn
+    /// order. Children still running at `timeout` are killed.
+    pub fn wait_all(&mut self, timeout: Duration) -> io::Result<Vec<ExitStatus>> {
+        let deadline = Instant::now() + timeout;
+        let mut statuses = vec![None; self.children.len()];
+
+        loop {
+            for (child, status) in self.children.iter_mut().zip(statuses.iter_mut()) {
+                if status.is_none() {
+                    *status = child.try_wait()?;
+                }
+            }
+            if statuses.iter().all(Option::is_some) {
+                break;
+            }
+            if Instant::now() >= deadline {
+                self.kill_children();
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "children still running",
+                ));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        self.children.clear();
+        Ok(statuses.into_iter().flatten().collect())
+    }
+
+    /// Fail fast when a child died before reaching the barrier.
+    fn check_children(&mut self) -> io::Result<()> {
+        for child in &mut self.children {
+            if let Some(status) = child.try_wait()?
+                && !status.success()
+            {
+                return Err(io::Error::other(format!(
+                    "child {} exited early with {status}",
+                    child.id()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn kill_children(&mut self) {
+        for mut child in self.children.drain(..) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.kill_children();
+        unsafe {
+            libc::mq_close(self.barrier);
+        }
+        for name in &self.names {
+            let _ = cleanup::unlink(name);
+        }
+        // Catches queues children opened under names the parent never
+        // asked for; needs /dev/mqueue, so failures are ignored.
+        let _ = cleanup::unlink_prefix(&self.prefix);
+    }
+}
+
+/// One message of a [`ScriptedPublisher`] script.
+#[derive(Copy, Clone, Debug)]
+pub struct ScriptStep {
+    /// Offset from the start of playback.
+    pub at: Duration,
+    pub msg: Msg,
+    pub prio: u32,
+}
+
+/// Publishes a predefined, timed sequence of messages.
+///
+/// Steps are played in order of their offset (ties keep insertion
+/// order), each one sent no earlier than `start + offset / speed`, so a
+/// subscriber under test sees the same input trace on every run.
+///
+/// ```no_run
+/// use mq_ipc::testkit::{MockTransport, ScriptedPublisher};
+/// use std::time::Duration;
+///
+/// let mock = MockTransport::new();
+/// let sent = ScriptedPublisher::new()
+///     .at_value(Duration::ZERO, &1.0f32, 1, 0)
+///     .at_value(Duration::from_millis(20), &2.0f32, 1, 0)
+///     .play(&mock)
+///     .unwrap();
+/// assert_eq!(sent, 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScriptedPublisher {
+    steps: Vec<ScriptStep>,
+    speed: f64,
+}
+
+impl Default for ScriptedPublisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptedPublisher {
+    /// An empty script played at real-time speed.
+    pub fn new() -> Self {
+        ScriptedPublisher {
+            steps: Vec::new(),
+            speed: 1.0,
+        }
+    }
+
+    /// Rebuild the trace recorded in a journal, keeping the original
+    /// spacing relative to the first entry.
+    pub fn from_journal(entries: &[JournalEntry]) -> Self {
+        let mut script = Self::new();
+        let Some(first) = entries.iter().map(|e| e.timestamp).min() else {
+            return script;
+        };
+        for entry in entries {
+            let at = entry.timestamp.duration_since(first).unwrap_or_default();
+            script = script.at(at, entry.msg, entry.prio);
+        }
+        script
+    }
+
+    /// Send `msg` at `at` after the start of playback.
+    pub fn at(mut self, at: Duration, msg: Msg, prio: u32) -> Self {
+        let pos = self.steps.partition_point(|step| step.at <= at);
+        self.steps.insert(pos, ScriptStep { at, msg, prio });
+        self
+    }
+
+    /// Send a typed value at `at`, encoded like [`Topic::publish`](crate::Topic::publish).
+    pub fn at_value<T: Pod>(self, at: Duration, value: &T, msg_type: u16, prio: u32) -> Self {
+        self.at(at, Msg::new(msg_type, bytemuck::bytes_of(value)), prio)
+    }
+
+    /// Play the script `factor` times faster than written (e.g. `10.0`
+    /// to squeeze a one-second trace into 100 ms). Must be positive.
+    pub fn speed(mut self, factor: f64) -> Self {
+        assert!(factor > 0.0, "speed factor must be positive");
+        self.speed = factor;
+        self
+    }
+
+    /// Steps in playback order.
+    pub fn steps(&self) -> &[ScriptStep] {
+        &self.steps
+    }
+
+    /// Offset of the last step, as written.
+    pub fn duration(&self) -> Duration {
+        self.steps.last().map_or(Duration::ZERO, |step| step.at)
+    }
+
+    /// Publish every step onto `target`, blocking until the last one is
+    /// sent. Stops at the first publish error; otherwise returns the
+    /// number of messages sent.
+    ///
+    /// Steps are paced by [`clock::default_clock`], so under a
+    /// [`SimClock`](clock::SimClock) the script advances with simulated
+    /// time.
+    pub fn play(&self, target: &dyn Transport) -> io::Result<usize> {
+        let clock = clock::default_clock();
+        let start = clock.now();
+        for step in &self.steps {
+            clock.sleep_until(start + step.at.div_f64(self.speed));
+            target.publish(&step.msg, step.prio)?;
+        }
+        Ok(self.steps.len())
+    }
+
+    /// Like [`play`](Self::play), on a background thread.
+    pub fn spawn(self, target: Arc<dyn Transport>) -> thread::JoinHandle<io::Result<usize>> {
+        thread::spawn(move || self.play(&*target))
+    }
+}
+
+type MockCallback = dyn Fn(Msg) + Send + Sync + 'static;
+
+/// In-memory [`Transport`] that records what is published.
+///
+/// Subscribers run synchronously inside [`publish`](Transport::publish)
+/// on the publishing thread, so there is no delivery jitter to wait out
+/// in assertions.
+#[derive(Default)]
+pub struct MockTransport {
+    published: Mutex<Vec<(Msg, u32)>>,
+    subscribers: Mutex<Vec<Arc<MockCallback>>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every `(message, priority)` published so far, in order.
+    pub fn published(&self) -> Vec<(Msg, u32)> {
+        self.published.lock().unwrap().clone()
+    }
+
+    /// Forget the recorded messages; subscribers stay attached.
+    pub fn clear(&self) {
+        self.published.lock().unwrap().clear();
+    }
+}
+
+impl Transport for MockTransport {
+    fn publish(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        self.published.lock().unwrap().push((*msg, prio));
+        // Snapshot so callbacks may publish or subscribe themselves.
+        let subscribers = self.subscribers.lock().unwrap().clone();
+        for f in subscribers {
+            f(*msg);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn subscribe(&self, f: MsgCallback) {
+        self.subscribers.lock().unwrap().push(Arc::from(f));
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_publisher_plays_in_order_and_on_time() {
+        let mock = MockTransport::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        mock.subscribe(Box::new(move |msg: Msg| {
+            seen_clone.lock().unwrap().push(msg.hdr.msg_type);
+        }));
+
+        let script = ScriptedPublisher::new()
+            .at(Duration::from_millis(30), Msg::new(3, &[]), 0)
+            .at(Duration::ZERO, Msg::new(1, &[]), 0)
+            .at(Duration::from_millis(10), Msg::new(2, &[]), 5);
+        assert_eq!(script.duration(), Duration::from_millis(30));
+
+        let start = Instant::now();
+        assert_eq!(script.play(&mock).unwrap(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+        assert_eq!(mock.published()[1].1, 5);
+
+        let fast = Instant::now();
+        script.speed(100.0).play(&mock).unwrap();
+        assert!(fast.elapsed() < Duration::from_millis(30));
+        assert_eq!(mock.published().len(), 6);
+    }
+}
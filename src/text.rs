@@ -0,0 +1,521 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Short strings and byte blobs, as whole messages or as struct fields.
+//!
+//! Log lines, small JSON documents and opaque tokens vary in length and
+//! are not [`Pod`]. Instead of squeezing them into a fixed
+//! `[u8; N]` plus a length field, a [`BytesTopic`] carries a `&[u8]` and a
+//! [`StringTopic`] a `&str`, each as one message:
+//!
+//! ```text
+//! | len: u16 (little-endian) | bytes ... |
+//! ```
+//!
+//! flagged with [`FLAG_BYTES`]. Receivers check the prefix against the
+//! message length, and [`StringTopic`] also checks the bytes are UTF-8;
+//! anything else is dead-lettered (with [`TopicOptions::dead_letter`])
+//! and skipped, as [`Topic<T>`](crate::Topic) does with values that do
+//! not decode.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::text::StringTopic;
+//!
+//! let log = StringTopic::new("/log", 16)?;
+//! log.publish("motor: overcurrent on axis 2", 1, 0)?;
+//! if let Some(line) = log.try_recv()? {
+//!     println!("{line}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Payloads larger than [`BytesTopic::max_len`] are rejected; use
+//! [`large`](crate::large) for those.
+//!
+//! # Text inside Pod structs
+//!
+//! Names and labels that live inside a message struct use [`FixedStr<N>`]:
+//! `N` bytes of UTF-8, NUL-padded when shorter, so the struct stays
+//! [`Pod`] and the conversions to and from `&str` are
+//! checked instead of hand-rolled:
+//!
+//! ```
+//! use bytemuck::{Pod, Zeroable};
+//! use mq_ipc::text::FixedStr;
+//!
+//! #[repr(C)]
+//! #[derive(Copy, Clone, Pod, Zeroable)]
+//! struct JointState {
+//!     name: FixedStr<16>,
+//!     position: f32,
+//! }
+//!
+//! let state = JointState {
+//!     name: FixedStr::try_from("elbow").unwrap(),
+//!     position: 0.5,
+//! };
+//! assert_eq!(state.name.as_str(), Ok("elbow"));
+//! ```
+
+use super::{
+    dlq,
+    layout::{Describe, Field, Scalar},
+    MqTopic, Msg, TopicOptions,
+};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt, io,
+    os::raw::c_long,
+    str::Utf8Error,
+    time::{Duration, Instant},
+};
+
+/// `hdr.flags` bit: the data is a length-prefixed byte string.
+pub const FLAG_BYTES: u16 = 0x0040;
+
+/// Size of the length prefix.
+pub const LEN_PREFIX: usize = 2;
+
+/// Encode `bytes` as a [`FLAG_BYTES`] message.
+pub fn encode(msg_type: u16, bytes: &[u8]) -> io::Result<Msg> {
+    let len = u16::try_from(bytes.len())
+        .ok()
+        .filter(|_| LEN_PREFIX + bytes.len() <= super::MSG_PAYLOAD_SIZE)
+        .ok_or_else(|| too_long(bytes.len(), super::MSG_PAYLOAD_SIZE - LEN_PREFIX))?;
+    let mut data = Vec::with_capacity(LEN_PREFIX + bytes.len());
+    data.extend_from_slice(&len.to_le_bytes());
+    data.extend_from_slice(bytes);
+    let mut msg = Msg::new(msg_type, &data);
+    msg.hdr.flags |= FLAG_BYTES;
+    Ok(msg)
+}
+
+/// The byte string `msg` carries, or `None` if it is not a well-formed
+/// [`FLAG_BYTES`] message.
+pub fn decode(msg: &Msg) -> Option<&[u8]> {
+    if msg.hdr.flags & FLAG_BYTES == 0 {
+        return None;
+    }
+    let data = msg.data();
+    let len = u16::from_le_bytes(data.get(..LEN_PREFIX)?.try_into().ok()?) as usize;
+    data.get(LEN_PREFIX..LEN_PREFIX + len)
+}
+
+fn too_long(len: usize, max: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{len} bytes exceed the {max}-byte limit"),
+    )
+}
+
+/// Topic carrying byte strings; see the [module docs](self).
+pub struct BytesTopic {
+    inner: MqTopic,
+}
+
+impl BytesTopic {
+    /// Create or open `name` with default options.
+    pub fn new(name: &str, maxmsg: c_long) -> io::Result<Self> {
+        Self::with_options(name, &TopicOptions::new(maxmsg))
+    }
+
+    /// Create or open `name` with explicit [`TopicOptions`].
+    pub fn with_options(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        Ok(BytesTopic {
+            inner: MqTopic::with_options(name, opts)?,
+        })
+    }
+
+    /// Publish `bytes` as one message. Fails with `InvalidInput` beyond
+    /// [`max_len`](Self::max_len).
+    pub fn publish(&self, bytes: &[u8], msg_type: u16, prio: u32) -> io::Result<()> {
+        if bytes.len() > self.max_len() {
+            return Err(too_long(bytes.len(), self.max_len()));
+        }
+        self.inner.publish(&encode(msg_type, bytes)?, prio)
+    }
+
+    /// Longest byte string a message can carry on this topic.
+    pub fn max_len(&self) -> usize {
+        self.inner
+            .max_payload
+            .saturating_sub(LEN_PREFIX)
+            .min(u16::MAX as usize)
+    }
+
+    /// Call `f` with every byte string received.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe<F>(&self, f: F)
+    where
+        F: Fn(Vec<u8>) + Send + Sync + 'static,
+    {
+        self.subscribe_with(|bytes| Some(bytes.to_vec()), f);
+    }
+
+    /// The next byte string, if one is already queued.
+    pub fn try_recv(&self) -> io::Result<Option<Vec<u8>>> {
+        self.recv_with(None, |bytes| Some(bytes.to_vec()))
+    }
+
+    /// Wait up to `timeout` for the next byte string.
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        self.recv_with(Some(Instant::now() + timeout), |bytes| Some(bytes.to_vec()))
+    }
+
+    /// The underlying topic.
+    pub fn raw(&self) -> &MqTopic {
+        &self.inner
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn subscribe_with<V, P, F>(&self, parse: P, f: F)
+    where
+        P: Fn(&[u8]) -> Option<V> + Send + Sync + 'static,
+        F: Fn(V) + Send + Sync + 'static,
+    {
+        let dlq = self.inner.dlq.clone();
+        self.inner.subscribe(move |msg: Msg| {
+            if let Some(value) = parse_msg(&msg, dlq.as_deref(), &parse) {
+                f(value);
+            }
+        });
+    }
+
+    fn recv_with<V>(
+        &self,
+        deadline: Option<Instant>,
+        parse: impl Fn(&[u8]) -> Option<V>,
+    ) -> io::Result<Option<V>> {
+        loop {
+            let Some(msg) = self.inner.poll(deadline)? else {
+                return Ok(None);
+            };
+            if let Some(value) = parse_msg(&msg, self.inner.dlq.as_deref(), &parse) {
+                return Ok(Some(value));
+            }
+        }
+    }
+}
+
+fn parse_msg<V>(
+    msg: &Msg,
+    dlq: Option<&dlq::DeadLetterQueue>,
+    parse: impl Fn(&[u8]) -> Option<V>,
+) -> Option<V> {
+    let value = decode(msg).and_then(parse);
+    if value.is_none()
+        && let Some(dlq) = dlq
+    {
+        dlq.send(dlq::DeadLetterReason::Decode, msg);
+    }
+    value
+}
+
+/// Topic carrying UTF-8 strings; see the [module docs](self).
+pub struct StringTopic {
+    bytes: BytesTopic,
+}
+
+impl StringTopic {
+    /// Create or open `name` with default options.
+    pub fn new(name: &str, maxmsg: c_long) -> io::Result<Self> {
+        Self::with_options(name, &TopicOptions::new(maxmsg))
+    }
+
+    /// Create or open `name` with explicit [`TopicOptions`].
+    pub fn with_options(name: &str, opts: &TopicOptions) -> io::Result<Self> {
+        Ok(StringTopic {
+            bytes: BytesTopic::with_options(name, opts)?,
+        })
+    }
+
+    /// Publish `text` as one message. Fails with `InvalidInput` beyond
+    /// [`max_len`](Self::max_len) bytes.
+    pub fn publish(&self, text: &str, msg_type: u16, prio: u32) -> io::Result<()> {
+        self.bytes.publish(text.as_bytes(), msg_type, prio)
+    }
+
+    /// Longest string, in bytes, a message can carry on this topic.
+    pub fn max_len(&self) -> usize {
+        self.bytes.max_len()
+    }
+
+    /// Call `f` with every string received.
+    #[cfg(feature = "callbacks")]
+    pub fn subscribe<F>(&self, f: F)
+    where
+        F: Fn(String) + Send + Sync + 'static,
+    {
+        self.bytes.subscribe_with(utf8, f);
+    }
+
+    /// The next string, if one is already queued.
+    pub fn try_recv(&self) -> io::Result<Option<String>> {
+        self.bytes.recv_with(None, utf8)
+    }
+
+    /// Wait up to `timeout` for the next string.
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<String>> {
+        self.bytes.recv_with(Some(Instant::now() + timeout), utf8)
+    }
+
+    /// The underlying topic.
+    pub fn raw(&self) -> &MqTopic {
+        self.bytes.raw()
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Option<String> {
+    std::str::from_utf8(bytes).ok().map(str::to_owned)
+}
+
+/// Fixed-width, NUL-padded UTF-8 text for use inside [`Pod`] structs.
+///
+/// A string of exactly `N` bytes fills the field with no terminator.
+/// Bytes come straight off the queue, so reading one back is fallible:
+/// [`as_str`](Self::as_str) checks UTF-8, while
+/// [`to_string_lossy`](Self::to_string_lossy) and `Display` never fail.
+#[repr(transparent)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FixedStr<const N: usize>([u8; N]);
+
+// SAFETY: a transparent wrapper around `[u8; N]`, which is Pod for any N.
+unsafe impl<const N: usize> Zeroable for FixedStr<N> {}
+unsafe impl<const N: usize> Pod for FixedStr<N> {}
+
+impl<const N: usize> FixedStr<N> {
+    /// Width of the field in bytes.
+    pub const CAPACITY: usize = N;
+
+    /// The empty string.
+    pub const fn empty() -> Self {
+        FixedStr([0; N])
+    }
+
+    /// `s`, cut at the last character boundary that fits.
+    pub fn truncate(s: &str) -> Self {
+        let mut end = s.len().min(N);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut bytes = [0; N];
+        bytes[..end].copy_from_slice(&s.as_bytes()[..end]);
+        FixedStr(bytes)
+    }
+
+    /// Replace the contents with `s`; on error the field is unchanged.
+    pub fn set(&mut self, s: &str) -> Result<(), TooLong> {
+        *self = Self::try_from(s)?;
+        Ok(())
+    }
+
+    /// The bytes before the first NUL.
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(N);
+        &self.0[..len]
+    }
+
+    /// The text, if it is valid UTF-8.
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(self.as_bytes())
+    }
+
+    /// The text, with invalid sequences replaced by U+FFFD.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
+    }
+
+    /// Length in bytes, up to the first NUL.
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.first().is_none_or(|&b| b == 0)
+    }
+}
+
+impl<const N: usize> Default for FixedStr<N> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for FixedStr<N> {
+    type Error = TooLong;
+
+    /// `s`, or [`TooLong`] if it needs more than `N` bytes. An interior
+    /// NUL ends the string early when read back.
+    fn try_from(s: &str) -> Result<Self, TooLong> {
+        if s.len() > N {
+            return Err(TooLong {
+                len: s.len(),
+                capacity: N,
+            });
+        }
+        Ok(Self::truncate(s))
+    }
+}
+
+impl<const N: usize> PartialEq<str> for FixedStr<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for FixedStr<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<const N: usize> fmt::Display for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FixedStr<{N}>({:?})", self.to_string_lossy())
+    }
+}
+
+impl<const N: usize> Describe for FixedStr<N> {
+    fn type_name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn describe(path: &str, offset: usize, out: &mut Vec<Field>) {
+        out.push(Field {
+            name: path.to_string(),
+            offset,
+            ty: Scalar::U8,
+            len: Some(N),
+        });
+    }
+}
+
+/// A string did not fit in a [`FixedStr`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TooLong {
+    /// Bytes the string needs.
+    pub len: usize,
+    /// Bytes the field holds.
+    pub capacity: usize,
+}
+
+impl fmt::Display for TooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes do not fit in a {}-byte field",
+            self.len, self.capacity
+        )
+    }
+}
+
+impl Error for TooLong {}
+
+impl From<TooLong> for io::Error {
+    fn from(err: TooLong) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    #[test]
+    fn strings_round_trip_and_bad_payloads_are_skipped() {
+        let tmp = TempTopic::new("/mq_ipc_test_text_");
+        let topic = StringTopic::new(tmp.name(), 8).unwrap();
+
+        topic.publish("", 1, 0).unwrap();
+        topic
+            .publish("{\"axis\": 2, \"état\": \"ok\"}", 1, 0)
+            .unwrap();
+        // Invalid UTF-8, a prefix longer than the data and an unflagged message.
+        topic
+            .raw()
+            .publish(&encode(1, &[0xff, 0xfe]).unwrap(), 0)
+            .unwrap();
+        let mut short = encode(1, b"abc").unwrap();
+        short.hdr.len -= 1;
+        topic.raw().publish(&short, 0).unwrap();
+        topic.raw().publish(&Msg::new(1, b"\x01\x00x"), 0).unwrap();
+        topic.publish("last", 1, 0).unwrap();
+
+        assert_eq!(topic.try_recv().unwrap().as_deref(), Some(""));
+        assert_eq!(
+            topic.try_recv().unwrap().as_deref(),
+            Some("{\"axis\": 2, \"état\": \"ok\"}")
+        );
+        assert_eq!(topic.try_recv().unwrap().as_deref(), Some("last"));
+        assert_eq!(topic.try_recv().unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_oversized_payloads() {
+        let tmp = TempTopic::new("/mq_ipc_test_bytes_");
+        let topic = BytesTopic::new(tmp.name(), 4).unwrap();
+        let max = topic.max_len();
+        assert_eq!(max, crate::MSG_PAYLOAD_SIZE - LEN_PREFIX);
+
+        topic.publish(&vec![7; max], 1, 0).unwrap();
+        let err = topic.publish(&vec![7; max + 1], 1, 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(topic.try_recv().unwrap(), Some(vec![7; max]));
+    }
+
+    #[test]
+    fn fixed_str_conversions() {
+        let name = FixedStr::<8>::try_from("elbow").unwrap();
+        assert_eq!(name.as_str(), Ok("elbow"));
+        assert_eq!(name, "elbow");
+        assert_eq!(bytemuck::bytes_of(&name), b"elbow\0\0\0");
+
+        let full = FixedStr::<5>::try_from("wrist").unwrap();
+        assert_eq!((full.len(), full.as_str()), (5, Ok("wrist")));
+        assert_eq!(
+            FixedStr::<4>::try_from("shoulder"),
+            Err(TooLong {
+                len: 8,
+                capacity: 4
+            })
+        );
+        // "é" is two bytes and must not be split.
+        assert_eq!(FixedStr::<4>::truncate("abcé"), "abc");
+        assert!(FixedStr::<4>::default().is_empty());
+
+        let garbage: FixedStr<4> = bytemuck::cast([b'o', b'k', 0xff, 0]);
+        assert!(garbage.as_str().is_err());
+        assert_eq!(garbage.to_string(), "ok\u{fffd}");
+    }
+}
@@ -0,0 +1,148 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Trace-context propagation through the extended header.
+//!
+//! The crate does not depend on a tracing framework. Instead it keeps a
+//! thread-local "current" [`TraceContext`]: bridge it from your tracer
+//! (e.g. copy the OpenTelemetry span context into [`set_current`] or use
+//! [`in_context`]), and topics created with
+//! [`TopicOptions::propagate_trace`](crate::TopicOptions::propagate_trace)
+//! stamp it into every published message. Workers restore the publisher's
+//! context around subscriber callbacks, so [`current`] inside a callback
+//! returns the span that produced the message — across processes and,
+//! via the wire layer, across links.
+
+use super::ext::{ExtHeader, EXT_TRACE};
+use std::cell::Cell;
+
+/// A trace ID plus the ID of the span that published the message.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+}
+
+impl TraceContext {
+    #[cfg(feature = "callbacks")]
+    pub(crate) fn from_ext(ext: &ExtHeader) -> Option<Self> {
+        ext.has(EXT_TRACE).then_some(TraceContext {
+            trace_id: ext.trace_id,
+            span_id: ext.span_id,
+        })
+    }
+
+    pub(crate) fn write_ext(&self, ext: &mut ExtHeader) {
+        ext.present |= EXT_TRACE;
+        ext.trace_id = self.trace_id;
+        ext.span_id = self.span_id;
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<TraceContext>> = const { Cell::new(None) };
+}
+
+/// Context of the calling thread, if any.
+pub fn current() -> Option<TraceContext> {
+    CURRENT.with(|c| c.get())
+}
+
+/// Replace the calling thread's context, returning the previous one.
+pub fn set_current(ctx: Option<TraceContext>) -> Option<TraceContext> {
+    CURRENT.with(|c| c.replace(ctx))
+}
+
+/// Run `f` with `ctx` as the current context, restoring the previous one
+/// afterwards.
+pub fn in_context<R>(ctx: Option<TraceContext>, f: impl FnOnce() -> R) -> R {
+    let _guard = ContextGuard::enter(ctx);
+    f()
+}
+
+/// Restores the previous context on drop (also when unwinding).
+pub(crate) struct ContextGuard {
+    prev: Option<TraceContext>,
+}
+
+impl ContextGuard {
+    pub(crate) fn enter(ctx: Option<TraceContext>) -> Self {
+        ContextGuard {
+            prev: set_current(ctx),
+        }
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        set_current(self.prev);
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, MqTopic, Msg, TopicOptions};
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    type Seen = Arc<Mutex<Vec<(Option<TraceContext>, Vec<u8>)>>>;
+
+    #[test]
+    fn context_crosses_the_queue() {
+        let tmp = TempTopic::new("/mq_ipc_test_trace_");
+        let topic =
+            MqTopic::with_options(tmp.name(), &TopicOptions::new(4).propagate_trace(true)).unwrap();
+
+        let seen: Seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        topic.subscribe(move |msg: Msg| {
+            seen_clone
+                .lock()
+                .unwrap()
+                .push((current(), msg.data().to_vec()));
+        });
+
+        let ctx = TraceContext {
+            trace_id: [0xAB; 16],
+            span_id: [0xCD; 8],
+        };
+        in_context(Some(ctx), || topic.publish(&Msg::new(1, b"traced"), 0)).unwrap();
+        assert_eq!(current(), None);
+        topic.publish(&Msg::new(1, b"plain"), 0).unwrap();
+
+        for _ in 0..50 {
+            if seen.lock().unwrap().len() == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0], (Some(ctx), b"traced".to_vec()));
+        assert_eq!(seen[1], (None, b"plain".to_vec()));
+    }
+}
@@ -0,0 +1,67 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Common interface over the ways a topic can move messages.
+//!
+//! [`MqTopic`] (POSIX mqueues) is the default and works for any number of
+//! publishers and subscribers. [`ShmRing`](crate::ring::ShmRing) trades
+//! that flexibility for latency on single-producer/single-consumer hot
+//! paths. Code written against [`Transport`] can switch between them by
+//! changing one constructor. Without the `callbacks` feature the trait
+//! only publishes.
+
+use super::{MqTopic, Msg};
+use bytemuck::Pod;
+use std::io;
+
+/// Callback type taken by [`Transport::subscribe`].
+pub type MsgCallback = Box<dyn Fn(Msg) + Send + Sync + 'static>;
+
+/// Something messages can be published to and subscribed from.
+pub trait Transport: Send + Sync {
+    /// Send one message.
+    fn publish(&self, msg: &Msg, prio: u32) -> io::Result<()>;
+
+    /// Invoke `f` on a background thread for every received message.
+    #[cfg(feature = "callbacks")]
+    fn subscribe(&self, f: MsgCallback);
+
+    /// Publish a typed value, like [`Topic::publish`](crate::Topic::publish).
+    fn publish_value<T: Pod>(&self, value: &T, msg_type: u16, prio: u32) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        self.publish(&Msg::new(msg_type, bytemuck::bytes_of(value)), prio)
+    }
+}
+
+impl Transport for MqTopic {
+    fn publish(&self, msg: &Msg, prio: u32) -> io::Result<()> {
+        MqTopic::publish(self, msg, prio)
+    }
+
+    #[cfg(feature = "callbacks")]
+    fn subscribe(&self, f: MsgCallback) {
+        MqTopic::subscribe(self, f)
+    }
+}
@@ -0,0 +1,120 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Publisher-side checks on typed values.
+//!
+//! Validators registered with
+//! [`Topic::add_validator`](crate::Topic::add_validator) run on every
+//! [`Topic::publish`](crate::Topic::publish) before anything is queued,
+//! so a NaN torque or an out-of-range velocity never reaches a
+//! subscriber. Rejections are counted in
+//! [`TopicStats::rejected`](crate::stats::TopicStats::rejected).
+
+use std::{borrow::Cow, error::Error, fmt, io};
+
+/// Why a validator refused a value. Carried inside an [`io::Error`] of
+/// kind `InvalidInput`; get it back with
+/// `err.get_ref().and_then(|e| e.downcast_ref::<ValidationError>())`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub reason: Cow<'static, str>,
+}
+
+impl ValidationError {
+    pub fn new(reason: impl Into<Cow<'static, str>>) -> Self {
+        ValidationError {
+            reason: reason.into(),
+        }
+    }
+
+    pub(crate) fn into_io(self) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, self)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rejected by validator: {}", self.reason)
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Validator for finite values within `min..=max`, for the common case
+/// of a single float field. `field` picks it out of the message.
+pub fn finite_range<T, F>(
+    name: &'static str,
+    min: f64,
+    max: f64,
+    field: F,
+) -> impl Fn(&T) -> Result<(), ValidationError> + Send + Sync + 'static
+where
+    F: Fn(&T) -> f64 + Send + Sync + 'static,
+{
+    move |value: &T| {
+        let v = field(value);
+        if !v.is_finite() {
+            return Err(ValidationError::new(format!("{name} is not finite")));
+        }
+        if v < min || v > max {
+            return Err(ValidationError::new(format!(
+                "{name} = {v} outside {min}..={max}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, Topic};
+
+    #[test]
+    fn rejected_values_are_not_queued_and_are_counted() {
+        let tmp = TempTopic::new("/mq_ipc_test_validate_");
+        let topic: Topic<f32> = Topic::new(tmp.name(), 4).unwrap();
+        topic.add_validator(finite_range("torque", -10.0, 10.0, |v: &f32| *v as f64));
+        topic.add_validator(|v: &f32| {
+            if *v == 3.0 {
+                Err(ValidationError::new("three is unlucky"))
+            } else {
+                Ok(())
+            }
+        });
+
+        topic.publish(&1.5, 1, 0).unwrap();
+        for bad in [f32::NAN, 11.0, 3.0] {
+            let err = topic.publish(&bad, 1, 0).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(err
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<ValidationError>())
+                .is_some());
+        }
+
+        let stats = topic.stats();
+        assert_eq!(stats.rejected, 3);
+        assert_eq!(stats.depth.current, 1);
+    }
+}
@@ -0,0 +1,250 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Heartbeat supervision with an automatic emergency stop.
+//!
+//! A [`Watchdog`] expects a message on a heartbeat topic at least once
+//! per timeout. When one is late it publishes a fixed message, typically
+//! a zero-torque command, on a second topic at [`URGENT_PRIORITY`], so it
+//! overtakes anything already queued there:
+//!
+//! ```no_run
+//! # use mq_ipc::{watchdog::{Watchdog, WatchdogOptions}, Msg, TopicOptions};
+//! # use std::time::Duration;
+//! let zero_torque = Msg::new(2, bytemuck::bytes_of(&[0.0f32; 6]));
+//! let _dog = Watchdog::new(
+//!     "/arm/heartbeat",
+//!     "/arm/torque_cmd",
+//!     zero_torque,
+//!     &WatchdogOptions::new(Duration::from_millis(50)).repeat(Duration::from_millis(100)),
+//!     &TopicOptions::new(8),
+//! )?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! The timeout runs from creation, so a controller that never starts
+//! trips the watchdog too. The watchdog consumes heartbeats like any
+//! subscriber, so give it a heartbeat topic of its own.
+
+use super::{clock, MqTopic, Msg, TopicOptions, URGENT_PRIORITY};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Longest single sleep of the watchdog thread, bounding how long drop
+/// waits for it.
+const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+/// When a [`Watchdog`] trips and how it keeps the e-stop asserted.
+#[derive(Copy, Clone, Debug)]
+pub struct WatchdogOptions {
+    /// Longest allowed gap between heartbeats.
+    pub timeout: Duration,
+    /// Publish the e-stop message again this often while tripped.
+    pub repeat: Option<Duration>,
+}
+
+impl WatchdogOptions {
+    pub fn new(timeout: Duration) -> Self {
+        WatchdogOptions {
+            timeout,
+            repeat: None,
+        }
+    }
+
+    pub fn repeat(mut self, every: Duration) -> Self {
+        self.repeat = Some(every);
+        self
+    }
+}
+
+struct Shared {
+    /// Clock reading of the last heartbeat, in nanoseconds.
+    last_beat: AtomicU64,
+    tripped: AtomicBool,
+    trips: AtomicU64,
+    running: AtomicBool,
+}
+
+/// Heartbeat monitor; stops supervising when dropped.
+pub struct Watchdog {
+    _heartbeat: MqTopic,
+    estop: Arc<MqTopic>,
+    shared: Arc<Shared>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Watch `heartbeat` and publish `estop_msg` on `estop` whenever no
+    /// heartbeat arrived for `wd.timeout`. Both topics are opened with
+    /// `opts`.
+    pub fn new(
+        heartbeat: &str,
+        estop: &str,
+        estop_msg: Msg,
+        wd: &WatchdogOptions,
+        opts: &TopicOptions,
+    ) -> io::Result<Self> {
+        let clock = clock::default_clock();
+        let shared = Arc::new(Shared {
+            last_beat: AtomicU64::new(clock.now().as_nanos() as u64),
+            tripped: AtomicBool::new(false),
+            trips: AtomicU64::new(0),
+            running: AtomicBool::new(true),
+        });
+        let estop = Arc::new(MqTopic::with_options(estop, opts)?);
+
+        let hb = MqTopic::with_options(heartbeat, opts)?;
+        {
+            let shared = Arc::clone(&shared);
+            let clock = Arc::clone(&clock);
+            hb.subscribe(move |_| {
+                shared
+                    .last_beat
+                    .store(clock.now().as_nanos() as u64, Ordering::Relaxed);
+                shared.tripped.store(false, Ordering::Relaxed);
+            });
+        }
+
+        let handle = {
+            let shared = Arc::clone(&shared);
+            let estop = Arc::clone(&estop);
+            let wd = *wd;
+            thread::spawn(move || {
+                let mut next_repeat = Duration::ZERO;
+                while shared.running.load(Ordering::Relaxed) {
+                    let now = clock.now();
+                    let expires =
+                        Duration::from_nanos(shared.last_beat.load(Ordering::Relaxed)) + wd.timeout;
+                    if now < expires {
+                        clock.sleep_until(expires.min(now + MAX_SLEEP));
+                        continue;
+                    }
+
+                    let first = !shared.tripped.swap(true, Ordering::Relaxed);
+                    let again = wd.repeat.is_some() && now >= next_repeat;
+                    if first || again {
+                        if first {
+                            shared.trips.fetch_add(1, Ordering::Relaxed);
+                        }
+                        if let Err(err) = estop.publish(&estop_msg, URGENT_PRIORITY) {
+                            eprintln!("watchdog e-stop on {} failed: {err}", estop.name());
+                        }
+                        if let Some(every) = wd.repeat {
+                            next_repeat = now + every;
+                        }
+                    }
+
+                    let wake = match wd.repeat {
+                        Some(_) => next_repeat.min(now + MAX_SLEEP),
+                        None => now + MAX_SLEEP.min(wd.timeout),
+                    };
+                    clock.sleep_until(wake);
+                }
+            })
+        };
+
+        Ok(Watchdog {
+            _heartbeat: hb,
+            estop,
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    /// Whether the heartbeat is currently overdue. Cleared by the next
+    /// heartbeat.
+    pub fn is_tripped(&self) -> bool {
+        self.shared.tripped.load(Ordering::Relaxed)
+    }
+
+    /// How many times the watchdog has tripped.
+    pub fn trips(&self) -> u64 {
+        self.shared.trips.load(Ordering::Relaxed)
+    }
+
+    /// Name of the topic the e-stop message is published on.
+    pub fn estop_topic(&self) -> &str {
+        self.estop.name()
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+    use std::sync::mpsc;
+
+    #[test]
+    fn trips_when_heartbeat_stops() {
+        let tmp_hb = TempTopic::new("/mq_ipc_test_watchdog_hb_");
+        let tmp_stop = TempTopic::new("/mq_ipc_test_watchdog_stop_");
+        let opts = TopicOptions::new(8);
+
+        let stop = MqTopic::new(tmp_stop.name(), 8).unwrap();
+        let (tx, rx) = mpsc::channel();
+        stop.subscribe(move |msg| tx.send(msg.hdr.msg_type).unwrap());
+
+        let wd = WatchdogOptions::new(Duration::from_millis(100));
+        let dog =
+            Watchdog::new(tmp_hb.name(), tmp_stop.name(), Msg::new(9, &[]), &wd, &opts).unwrap();
+        let hb = MqTopic::new(tmp_hb.name(), 8).unwrap();
+
+        for _ in 0..8 {
+            hb.publish(&Msg::new(1, &[]), 0).unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!dog.is_tripped());
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap(), 9);
+        assert!(dog.is_tripped());
+        assert_eq!(dog.trips(), 1);
+
+        hb.publish(&Msg::new(1, &[]), 0).unwrap();
+        for _ in 0..50 {
+            if !dog.is_tripped() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        assert!(!dog.is_tripped());
+        // Without `repeat`, one e-stop per trip.
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,331 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Capture of wire traffic for offline debugging.
+//!
+//! [`CaptureLink`] wraps any [`Link`] and appends every frame it sends
+//! or receives to a capture file, also enabled per router with
+//! [`RouterOptions::capture`](super::router::RouterOptions::capture).
+//! `mq-ipc wire-dump FILE` decodes such a file, as does [`read_capture`]
+//! with [`describe`].
+//!
+//! Captures are pcap files (nanosecond timestamps, link type
+//! `LINKTYPE_USER0`), so Wireshark opens them too. Each packet is the
+//! frame prefixed with one byte of [`Direction`].
+
+use super::{
+    frame::{self, Control, FileFrame},
+    link::Link,
+};
+use std::{
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// pcap magic for nanosecond-resolution timestamps.
+pub const PCAP_MAGIC_NS: u32 = 0xa1b2_3c4d;
+
+/// `LINKTYPE_USER0`, reserved for private use.
+pub const LINKTYPE_USER0: u32 = 147;
+
+const SNAPLEN: u32 = 65535;
+
+/// Which way a captured frame went, seen from the capturing side.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Tx = 0,
+    Rx = 1,
+}
+
+/// One frame read back from a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedFrame {
+    pub timestamp: SystemTime,
+    pub direction: Direction,
+    pub frame: Vec<u8>,
+}
+
+/// Appends frames to a capture file.
+pub struct CaptureWriter {
+    file: Mutex<File>,
+}
+
+impl CaptureWriter {
+    /// Create (or truncate) the capture at `path`.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&PCAP_MAGIC_NS.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_USER0.to_le_bytes());
+        file.write_all(&header)?;
+        Ok(CaptureWriter {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Record `frame`, stamped with the current time.
+    pub fn record(&self, direction: Direction, frame: &[u8]) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let len = (frame.len() + 1).min(SNAPLEN as usize);
+        let mut rec = Vec::with_capacity(16 + len);
+        rec.extend_from_slice(&(now.as_secs() as u32).to_le_bytes());
+        rec.extend_from_slice(&now.subsec_nanos().to_le_bytes());
+        rec.extend_from_slice(&(len as u32).to_le_bytes());
+        rec.extend_from_slice(&((frame.len() + 1) as u32).to_le_bytes());
+        rec.push(direction as u8);
+        rec.extend_from_slice(&frame[..len - 1]);
+        // One write per record keeps concurrent TX and RX records whole.
+        self.file.lock().unwrap().write_all(&rec)
+    }
+}
+
+/// [`Link`] that records all traffic of the link it wraps.
+pub struct CaptureLink {
+    inner: Arc<dyn Link>,
+    writer: CaptureWriter,
+}
+
+impl CaptureLink {
+    /// Capture `inner`'s traffic into a new file at `path`.
+    pub fn new(inner: Arc<dyn Link>, path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(CaptureLink {
+            inner,
+            writer: CaptureWriter::create(path)?,
+        })
+    }
+}
+
+impl Link for CaptureLink {
+    fn send(&self, frame: &[u8]) -> io::Result<()> {
+        self.send_prio(frame, 0)
+    }
+
+    fn send_prio(&self, frame: &[u8], prio: u32) -> io::Result<()> {
+        self.inner.send_prio(frame, prio)?;
+        // A full disk must not take the link down with it.
+        let _ = self.writer.record(Direction::Tx, frame);
+        Ok(())
+    }
+
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        let frame = self.inner.recv(timeout)?;
+        if let Some(frame) = &frame {
+            let _ = self.writer.record(Direction::Rx, frame);
+        }
+        Ok(frame)
+    }
+
+    fn is_up(&self) -> bool {
+        self.inner.is_up()
+    }
+
+    fn mtu(&self) -> usize {
+        self.inner.mtu()
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+}
+
+/// Read every frame of the capture at `path`. A record torn by a crash
+/// at the end of the file is ignored.
+pub fn read_capture(path: impl AsRef<Path>) -> io::Result<Vec<CapturedFrame>> {
+    let mut raw = Vec::new();
+    File::open(path)?.read_to_end(&mut raw)?;
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+
+    let u32_at = |raw: &[u8], at: usize| u32::from_le_bytes(raw[at..at + 4].try_into().unwrap());
+    if raw.len() < 24 || u32_at(&raw, 0) != PCAP_MAGIC_NS {
+        return Err(invalid("not an mq-ipc wire capture"));
+    }
+    if u32_at(&raw, 20) != LINKTYPE_USER0 {
+        return Err(invalid("capture has an unexpected link type"));
+    }
+
+    let mut frames = Vec::new();
+    let mut at = 24;
+    while at + 16 <= raw.len() {
+        let secs = u32_at(&raw, at) as u64;
+        let nanos = u32_at(&raw, at + 4);
+        let len = u32_at(&raw, at + 8) as usize;
+        let Some(packet) = raw.get(at + 16..at + 16 + len) else {
+            break;
+        };
+        at += 16 + len;
+        let Some((&dir, frame)) = packet.split_first() else {
+            continue;
+        };
+        frames.push(CapturedFrame {
+            timestamp: UNIX_EPOCH + Duration::new(secs, nanos),
+            direction: if dir == Direction::Rx as u8 {
+                Direction::Rx
+            } else {
+                Direction::Tx
+            },
+            frame: frame.to_vec(),
+        });
+    }
+    Ok(frames)
+}
+
+/// One-line human-readable decoding of a wire frame.
+pub fn describe(bytes: &[u8]) -> String {
+    let hex = |data: &[u8]| {
+        let mut out = String::new();
+        for b in data {
+            let _ = write!(out, "{b:02x}");
+        }
+        out
+    };
+    let malformed = || format!("malformed {}", hex(bytes));
+
+    match frame::kind(bytes) {
+        Some(frame::KIND_DATA) => match frame::decode(bytes) {
+            Some(pkt) => {
+                let data = &pkt.data[..pkt.payload_len as usize];
+                format!("data {} [{}] {}", pkt.topic_name(), data.len(), hex(data))
+            }
+            None => malformed(),
+        },
+        Some(frame::KIND_HEARTBEAT) => "heartbeat".to_string(),
+        Some(frame::KIND_SUBSCRIBE | frame::KIND_UNSUBSCRIBE) => match frame::decode_control(bytes)
+        {
+            Some(Control::Subscribe { topic, period }) => match period {
+                Some(p) => format!("subscribe {topic} every {p:?}"),
+                None => format!("subscribe {topic}"),
+            },
+            Some(Control::Unsubscribe { topic }) => format!("unsubscribe {topic}"),
+            None => malformed(),
+        },
+        Some(frame::KIND_COMMAND) => match frame::decode_command(bytes) {
+            Some((id, topic, payload)) => format!("command #{id} {topic} {}", hex(payload)),
+            None => malformed(),
+        },
+        Some(frame::KIND_COMMAND_STATUS) => match frame::decode_command_status(bytes) {
+            Some((id, status, code)) => {
+                let status = match status {
+                    frame::STATUS_ACCEPTED => "accepted",
+                    frame::STATUS_REJECTED => "rejected",
+                    frame::STATUS_COMPLETED => "completed",
+                    _ => "unknown",
+                };
+                format!("command #{id} {status} code {code}")
+            }
+            None => malformed(),
+        },
+        Some(frame::KIND_FRAGMENT) => match frame::decode_fragment(bytes) {
+            Some(f) => format!(
+                "fragment #{} {}/{} [{}]",
+                f.msg_id,
+                f.index + 1,
+                f.count,
+                f.bytes.len()
+            ),
+            None => malformed(),
+        },
+        Some(frame::KIND_MTU) => match frame::decode_mtu(bytes) {
+            Some(mtu) => format!("mtu {mtu}"),
+            None => malformed(),
+        },
+        Some(_) => match frame::decode_file(bytes) {
+            Some(FileFrame::Offer {
+                id,
+                size,
+                crc,
+                restart,
+                name,
+            }) => {
+                let restart = if restart { " restart" } else { "" };
+                format!("file #{id} offer {name} {size} bytes crc {crc:08x}{restart}")
+            }
+            Some(FileFrame::Accept {
+                id, refused: true, ..
+            }) => format!("file #{id} refused"),
+            Some(FileFrame::Accept { id, offset, .. }) => {
+                format!("file #{id} accept from {offset}")
+            }
+            Some(FileFrame::Chunk {
+                id, offset, data, ..
+            }) => format!("file #{id} chunk {offset}+{}", data.len()),
+            Some(FileFrame::Ack { id, status, next }) => {
+                format!("file #{id} ack status {status} next {next}")
+            }
+            None => malformed(),
+        },
+        None => "empty".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::loopback::{Impairments, LoopbackLink};
+
+    #[test]
+    fn records_both_directions() {
+        let path =
+            std::env::temp_dir().join(format!("mq_ipc_test_capture_{}.pcap", std::process::id()));
+        let link =
+            CaptureLink::new(Arc::new(LoopbackLink::new(Impairments::new())), &path).unwrap();
+
+        let data = frame::encode(&frame::packet("/motor", &[0xab]));
+        link.send(&data).unwrap();
+        link.send(&frame::heartbeat()).unwrap();
+        assert_eq!(
+            link.recv(Duration::from_millis(50)).unwrap(),
+            Some(data.clone())
+        );
+
+        let frames = read_capture(&path).unwrap();
+        let summary: Vec<_> = frames
+            .iter()
+            .map(|f| (f.direction, describe(&f.frame)))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Direction::Tx, "data /motor [1] ab".to_string()),
+                (Direction::Tx, "heartbeat".to_string()),
+                (Direction::Rx, "data /motor [1] ab".to_string()),
+            ]
+        );
+        assert!(frames[0].timestamp <= frames[2].timestamp);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,346 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Remote commands with acknowledgment over a [`Router`].
+//!
+//! Topics mirrored by the router are fire-and-forget. A [`Command`]
+//! instead carries an ID, and the handler registered on the peer with
+//! [`Router::serve_commands`] answers it with [`CommandReply`]: usually
+//! `accept` followed later by `complete`, or `reject`. The sender's
+//! callback sees each of those as a [`CommandStatus`], or
+//! [`CommandStatus::TimedOut`] if no final answer arrives in time.
+//!
+//! ```no_run
+//! # use mq_ipc::wire::{command::{Command, CommandStatus}, router::Router};
+//! # use std::time::Duration;
+//! # fn demo(router: &Router) -> std::io::Result<()> {
+//! let arm = Command::<u32>::new(router, "/cmd/arm");
+//! arm.send(&1, Duration::from_secs(2), |status| match status {
+//!     CommandStatus::Completed(_) => println!("armed"),
+//!     other => println!("arm: {other:?}"),
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{frame, link::Link, router::Router, WIRE_MAX_PAYLOAD};
+use bytemuck::Pod;
+use std::{
+    collections::HashMap,
+    io,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Rejection code sent when no handler serves the command's topic.
+pub const REJECT_NO_HANDLER: u16 = 0xFFFF;
+
+/// Rejection code sent when the payload does not decode as the
+/// handler's type.
+pub const REJECT_MALFORMED: u16 = 0xFFFE;
+
+/// Progress of a sent command, as seen by its sender.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommandStatus {
+    /// The handler took the command on; more statuses follow.
+    Accepted,
+    /// Final: the handler refused, with an application code.
+    Rejected(u16),
+    /// Final: the command finished, with an application code.
+    Completed(u16),
+    /// Final: no rejection or completion arrived before the timeout.
+    TimedOut,
+}
+
+impl CommandStatus {
+    /// Whether no further status follows this one.
+    pub fn is_final(&self) -> bool {
+        !matches!(self, CommandStatus::Accepted)
+    }
+}
+
+type StatusCallback = Box<dyn FnMut(CommandStatus) + Send + 'static>;
+type Handler = Arc<dyn Fn(&[u8], CommandReply) + Send + Sync + 'static>;
+
+struct Pending {
+    deadline: Instant,
+    callback: StatusCallback,
+}
+
+/// Command bookkeeping of one router: our in-flight commands and the
+/// handlers serving the peer's.
+#[derive(Default)]
+pub(super) struct Commands {
+    next_id: AtomicU32,
+    pending: Mutex<HashMap<u32, Pending>>,
+    handlers: Mutex<HashMap<String, Handler>>,
+}
+
+impl Commands {
+    pub(super) fn serve<T, F>(&self, topic: &str, handler: F)
+    where
+        T: Pod,
+        F: Fn(T, CommandReply) + Send + Sync + 'static,
+    {
+        let handler: Handler = Arc::new(move |payload, reply| {
+            if payload.len() != std::mem::size_of::<T>() {
+                let _ = reply.reject(REJECT_MALFORMED);
+                return;
+            }
+            handler(bytemuck::pod_read_unaligned(payload), reply);
+        });
+        self.handlers
+            .lock()
+            .unwrap()
+            .insert(topic.to_string(), handler);
+    }
+
+    pub(super) fn on_command(&self, link: &Arc<dyn Link>, bytes: &[u8]) {
+        let Some((id, topic, payload)) = frame::decode_command(bytes) else {
+            return;
+        };
+        let reply = CommandReply {
+            id,
+            link: Arc::clone(link),
+        };
+        let handler = self.handlers.lock().unwrap().get(&topic).cloned();
+        match handler {
+            Some(handler) => handler(payload, reply),
+            None => {
+                let _ = reply.reject(REJECT_NO_HANDLER);
+            }
+        }
+    }
+
+    pub(super) fn on_status(&self, bytes: &[u8]) {
+        let Some((id, status, code)) = frame::decode_command_status(bytes) else {
+            return;
+        };
+        let status = match status {
+            frame::STATUS_ACCEPTED => CommandStatus::Accepted,
+            frame::STATUS_REJECTED => CommandStatus::Rejected(code),
+            frame::STATUS_COMPLETED => CommandStatus::Completed(code),
+            _ => return,
+        };
+        // Late replies to timed-out commands find nothing here.
+        let Some(mut pending) = self.pending.lock().unwrap().remove(&id) else {
+            return;
+        };
+        // Called unlocked so the callback may send further commands.
+        (pending.callback)(status);
+        if !status.is_final() {
+            self.pending.lock().unwrap().insert(id, pending);
+        }
+    }
+
+    /// Time out commands whose deadline passed.
+    pub(super) fn expire(&self, now: Instant) {
+        let expired: Vec<Pending> = {
+            let mut pending = self.pending.lock().unwrap();
+            let ids: Vec<u32> = pending
+                .iter()
+                .filter(|(_, p)| p.deadline <= now)
+                .map(|(id, _)| *id)
+                .collect();
+            ids.iter().filter_map(|id| pending.remove(id)).collect()
+        };
+        for mut p in expired {
+            (p.callback)(CommandStatus::TimedOut);
+        }
+    }
+}
+
+/// Handle for answering one received command. It may be moved to
+/// another thread to complete the command later.
+#[derive(Clone)]
+pub struct CommandReply {
+    id: u32,
+    link: Arc<dyn Link>,
+}
+
+impl CommandReply {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Acknowledge the command; a final status should follow.
+    pub fn accept(&self) -> io::Result<()> {
+        self.send(frame::STATUS_ACCEPTED, 0)
+    }
+
+    /// Refuse the command with an application `code`.
+    pub fn reject(&self, code: u16) -> io::Result<()> {
+        self.send(frame::STATUS_REJECTED, code)
+    }
+
+    /// Report the command finished with an application `code`.
+    pub fn complete(&self, code: u16) -> io::Result<()> {
+        self.send(frame::STATUS_COMPLETED, code)
+    }
+
+    fn send(&self, status: u8, code: u16) -> io::Result<()> {
+        self.link
+            .send(&frame::command_status(self.id, status, code))
+    }
+}
+
+/// Typed sender of commands to a topic served by the peer router.
+pub struct Command<T> {
+    topic: String,
+    link: Arc<dyn Link>,
+    commands: Arc<Commands>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: Pod> Command<T> {
+    pub fn new(router: &Router, topic: &str) -> Self {
+        let (link, commands) = router.command_channel();
+        Command {
+            topic: topic.to_string(),
+            link,
+            commands,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Send `value` and report its progress to `on_status`, ending with
+    /// a final status at the latest after `timeout`. Returns the
+    /// command ID.
+    pub fn send<F>(&self, value: &T, timeout: Duration, on_status: F) -> io::Result<u32>
+    where
+        F: FnMut(CommandStatus) + Send + 'static,
+    {
+        let payload = bytemuck::bytes_of(value);
+        if payload.len() > WIRE_MAX_PAYLOAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("command payload exceeds {WIRE_MAX_PAYLOAD} bytes"),
+            ));
+        }
+
+        let id = self.commands.next_id.fetch_add(1, Ordering::Relaxed);
+        self.commands.pending.lock().unwrap().insert(
+            id,
+            Pending {
+                deadline: Instant::now() + timeout,
+                callback: Box::new(on_status),
+            },
+        );
+        if let Err(err) = self.link.send(&frame::command(id, &self.topic, payload)) {
+            self.commands.pending.lock().unwrap().remove(&id);
+            return Err(err);
+        }
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cleanup::TempTopic, wire::router::RouterOptions};
+    use std::sync::mpsc;
+
+    struct ChanLink {
+        tx: mpsc::Sender<Vec<u8>>,
+        rx: Mutex<mpsc::Receiver<Vec<u8>>>,
+    }
+
+    impl Link for ChanLink {
+        fn send(&self, frame: &[u8]) -> io::Result<()> {
+            self.tx
+                .send(frame.to_vec())
+                .map_err(|_| io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+            Ok(self.rx.lock().unwrap().recv_timeout(timeout).ok())
+        }
+    }
+
+    #[test]
+    fn statuses_reach_the_sender() {
+        let (tmp_a, tmp_b) = (
+            TempTopic::new("/mq_ipc_test_cmd_a_"),
+            TempTopic::new("/mq_ipc_test_cmd_b_"),
+        );
+        let (a_tx, b_rx) = mpsc::channel();
+        let (b_tx, a_rx) = mpsc::channel();
+        let link_a = Arc::new(ChanLink {
+            tx: a_tx,
+            rx: Mutex::new(a_rx),
+        });
+        let link_b = Arc::new(ChanLink {
+            tx: b_tx,
+            rx: Mutex::new(b_rx),
+        });
+        let a = Router::new(link_a, &RouterOptions::new().tx_topic(tmp_a.name())).unwrap();
+        let b = Router::new(link_b, &RouterOptions::new().tx_topic(tmp_b.name())).unwrap();
+
+        b.serve_commands("/cmd/arm", |armed: u32, reply: CommandReply| {
+            if armed == 0 {
+                let _ = reply.reject(7);
+            } else {
+                let _ = reply.accept();
+                let _ = reply.complete(0);
+            }
+        });
+        b.serve_commands("/cmd/slow", |_: u32, _reply: CommandReply| {});
+
+        let (status_tx, status_rx) = mpsc::channel();
+        let run = |topic: &str, value: u32| -> Vec<CommandStatus> {
+            let tx = status_tx.clone();
+            Command::<u32>::new(&a, topic)
+                .send(&value, Duration::from_millis(300), move |s| {
+                    tx.send(s).unwrap()
+                })
+                .unwrap();
+            let mut got = Vec::new();
+            while let Ok(s) = status_rx.recv_timeout(Duration::from_secs(2)) {
+                got.push(s);
+                if s.is_final() {
+                    break;
+                }
+            }
+            got
+        };
+
+        assert_eq!(
+            run("/cmd/arm", 1),
+            vec![CommandStatus::Accepted, CommandStatus::Completed(0)]
+        );
+        assert_eq!(run("/cmd/arm", 0), vec![CommandStatus::Rejected(7)]);
+        assert_eq!(
+            run("/cmd/none", 1),
+            vec![CommandStatus::Rejected(REJECT_NO_HANDLER)]
+        );
+        assert_eq!(run("/cmd/slow", 1), vec![CommandStatus::TimedOut]);
+    }
+}
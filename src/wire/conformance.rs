@@ -0,0 +1,354 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Canonical wire frames for checking independent implementations.
+//!
+//! [`vectors`] builds one golden frame per frame kind and feature of the
+//! [`frame`] layout. [`fixture`] renders them as a text file, and the
+//! crate checks in a copy as `tests/vectors/wire.txt`. An embedded
+//! implementation can decode each line, re-encode it, and compare bytes.
+//! [`validate`] does the same check for a single frame on the host, e.g.
+//! on frames captured from a peer.
+//!
+//! Fixture lines are `name hex`. Lines starting with `#` describe the
+//! vector that follows them. Two name prefixes change what `hex` holds:
+//!
+//! - `reject/`: a malformed frame that a decoder must refuse, such as a
+//!   file chunk whose CRC-32 does not match its data.
+//! - `topic_hash/`: not a frame, but the 4-byte little-endian
+//!   [`frame::topic_hash`] of a topic name followed by that name, in
+//!   full and so possibly longer than a frame can carry.
+//!
+//! Compression is out of scope: the wire format has none, so there is
+//! nothing to vector.
+
+use super::{
+    frame::{self, Control, FileFrame, Fragment},
+    transfer,
+};
+use std::{fmt::Write, io, time::Duration};
+
+/// One golden frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vector {
+    /// Stable identifier, `<kind>/<case>`.
+    pub name: &'static str,
+    /// What the frame encodes, in words.
+    pub description: &'static str,
+    pub frame: Vec<u8>,
+    pub expect: Expect,
+}
+
+/// What a conforming implementation does with a [`Vector`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expect {
+    /// Decodes `frame` and re-encodes the same bytes.
+    Canonical,
+    /// Refuses `frame`.
+    Rejected,
+    /// Hashes the name in `frame` to the hash in front of it.
+    TopicHash,
+}
+
+/// Every golden frame, in fixture order.
+pub fn vectors() -> Vec<Vector> {
+    let v = |name, description, frame| Vector {
+        name,
+        description,
+        frame,
+        expect: Expect::Canonical,
+    };
+    let reject = |name, description, frame| Vector {
+        expect: Expect::Rejected,
+        ..v(name, description, frame)
+    };
+    let hash = |name, description, topic: &str| {
+        let mut bytes = frame::topic_hash(topic).to_le_bytes().to_vec();
+        bytes.extend_from_slice(topic.as_bytes());
+        Vector {
+            expect: Expect::TopicHash,
+            ..v(name, description, bytes)
+        }
+    };
+    vec![
+        v(
+            "data/basic",
+            "data frame: topic \"/motor/state\", payload 01 02 03 04",
+            frame::encode(&frame::packet("/motor/state", &[1, 2, 3, 4])),
+        ),
+        v(
+            "data/empty",
+            "data frame: topic \"/t\", empty payload",
+            frame::encode(&frame::packet("/t", &[])),
+        ),
+        v(
+            "data/max",
+            "data frame: 64-byte topic of 'a', 128-byte payload counting 0..=127",
+            frame::encode(&frame::packet(
+                &"a".repeat(super::WIRE_MAX_TOPIC),
+                &(0..super::WIRE_MAX_PAYLOAD as u8).collect::<Vec<_>>(),
+            )),
+        ),
+        v("heartbeat", "link keep-alive", frame::heartbeat()),
+        v(
+            "subscribe/every",
+            "subscribe to \"/imu\" at full rate",
+            frame::subscribe("/imu", None),
+        ),
+        v(
+            "subscribe/10hz",
+            "subscribe to \"/imu\" at most every 100 ms",
+            frame::subscribe("/imu", Some(Duration::from_millis(100))),
+        ),
+        v(
+            "unsubscribe",
+            "unsubscribe from \"/imu\"",
+            frame::unsubscribe("/imu"),
+        ),
+        v(
+            "command",
+            "command 0x01020304 to \"/cmd/arm\" with payload 01 00 00 00",
+            frame::command(0x0102_0304, "/cmd/arm", &1u32.to_le_bytes()),
+        ),
+        v(
+            "command_status/completed",
+            "command 0x01020304 completed with code 0x0102",
+            frame::command_status(0x0102_0304, frame::STATUS_COMPLETED, 0x0102),
+        ),
+        v(
+            "file/offer",
+            "offer transfer 7 of \"fw.bin\": 9 bytes \"123456789\", CRC-32 cbf43926",
+            frame::encode_file(&FileFrame::Offer {
+                id: 7,
+                size: 9,
+                crc: frame::crc32(b"123456789"),
+                restart: false,
+                name: "fw.bin".to_string(),
+            }),
+        ),
+        v(
+            "file/accept",
+            "accept transfer 7, 4 bytes \"1234\" already held",
+            frame::encode_file(&FileFrame::Accept {
+                id: 7,
+                refused: false,
+                offset: 4,
+                prefix_crc: frame::crc32(b"1234"),
+            }),
+        ),
+        v(
+            "file/chunk",
+            "chunk of transfer 7 at offset 4: \"56789\" with its CRC-32",
+            frame::encode_file(&FileFrame::Chunk {
+                id: 7,
+                offset: 4,
+                crc: frame::crc32(b"56789"),
+                data: b"56789".to_vec(),
+            }),
+        ),
+        v(
+            "file/ack",
+            "transfer 7 complete and verified",
+            frame::encode_file(&FileFrame::Ack {
+                id: 7,
+                status: transfer::ACK_DONE,
+                next: 9,
+            }),
+        ),
+        v(
+            "fragment/last",
+            "last of 3 fragments of message 0x0102, carrying 2f 6d",
+            frame::fragment(&Fragment {
+                msg_id: 0x0102,
+                index: 2,
+                count: 3,
+                bytes: b"/m",
+            }),
+        ),
+        v(
+            "mtu/can",
+            "receive MTU of a classic CAN link, 8 bytes",
+            frame::mtu(super::mtu::MTU_CAN_CLASSIC),
+        ),
+        v(
+            "data/cut_utf8",
+            "data frame: topic \"/\" + 62 'a' + \"é\" cut to its first 63 bytes, empty payload",
+            frame::encode(&frame::packet(&format!("/{}é", "a".repeat(62)), &[])),
+        ),
+        reject(
+            "reject/file_chunk_crc",
+            "chunk of transfer 7 at offset 4: \"56789\" with the CRC-32 of \"56788\"",
+            frame::encode_file(&FileFrame::Chunk {
+                id: 7,
+                offset: 4,
+                crc: frame::crc32(b"56788"),
+                data: b"56789".to_vec(),
+            }),
+        ),
+        hash(
+            "topic_hash/basic",
+            "topic_hash of \"/motor/state\"",
+            "/motor/state",
+        ),
+        hash(
+            "topic_hash/cut_utf8",
+            "topic_hash of \"/\" + 62 'a' + \"é\": only the first 63 bytes count",
+            &format!("/{}é", "a".repeat(62)),
+        ),
+    ]
+}
+
+/// Check `v` the way [`Vector::expect`] says a conforming implementation
+/// treats it.
+pub fn check(v: &Vector) -> io::Result<()> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+    match v.expect {
+        Expect::Canonical => validate(&v.frame),
+        Expect::Rejected => match validate(&v.frame) {
+            Ok(()) => Err(invalid("malformed frame was accepted")),
+            Err(_) => Ok(()),
+        },
+        Expect::TopicHash => {
+            let (hash, name) = v
+                .frame
+                .split_first_chunk::<4>()
+                .ok_or_else(|| invalid("topic hash vector too short"))?;
+            let name = std::str::from_utf8(name).map_err(|_| invalid("topic name is not UTF-8"))?;
+            if frame::topic_hash(name) != u32::from_le_bytes(*hash) {
+                return Err(invalid("topic hash mismatch"));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Check that `bytes` is a well-formed frame in canonical encoding:
+/// it decodes, and encoding the result reproduces it exactly.
+pub fn validate(bytes: &[u8]) -> io::Result<()> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+    let reencoded = match frame::kind(bytes) {
+        Some(frame::KIND_DATA) => {
+            frame::encode(&frame::decode(bytes).ok_or_else(|| invalid("malformed data frame"))?)
+        }
+        Some(frame::KIND_HEARTBEAT) => frame::heartbeat(),
+        Some(frame::KIND_SUBSCRIBE | frame::KIND_UNSUBSCRIBE) => {
+            match frame::decode_control(bytes).ok_or_else(|| invalid("malformed control frame"))? {
+                Control::Subscribe { topic, period } => frame::subscribe(&topic, period),
+                Control::Unsubscribe { topic } => frame::unsubscribe(&topic),
+            }
+        }
+        Some(frame::KIND_COMMAND) => {
+            let (id, topic, payload) =
+                frame::decode_command(bytes).ok_or_else(|| invalid("malformed command frame"))?;
+            frame::command(id, &topic, payload)
+        }
+        Some(frame::KIND_COMMAND_STATUS) => {
+            let (id, status, code) = frame::decode_command_status(bytes)
+                .ok_or_else(|| invalid("malformed command status frame"))?;
+            frame::command_status(id, status, code)
+        }
+        Some(
+            frame::KIND_FILE_OFFER
+            | frame::KIND_FILE_ACCEPT
+            | frame::KIND_FILE_CHUNK
+            | frame::KIND_FILE_ACK,
+        ) => {
+            let f = frame::decode_file(bytes).ok_or_else(|| invalid("malformed file frame"))?;
+            if let FileFrame::Chunk { crc, data, .. } = &f
+                && frame::crc32(data) != *crc
+            {
+                return Err(invalid("file chunk fails its CRC"));
+            }
+            frame::encode_file(&f)
+        }
+        Some(frame::KIND_FRAGMENT) => frame::fragment(
+            &frame::decode_fragment(bytes).ok_or_else(|| invalid("malformed fragment frame"))?,
+        ),
+        Some(frame::KIND_MTU) => {
+            frame::mtu(frame::decode_mtu(bytes).ok_or_else(|| invalid("malformed mtu frame"))?)
+        }
+        Some(kind) => return Err(invalid(&format!("unknown frame kind {kind:#04x}"))),
+        None => return Err(invalid("empty frame")),
+    };
+    if reencoded != bytes {
+        return Err(invalid("frame is not in canonical encoding"));
+    }
+    Ok(())
+}
+
+/// Render `vectors` in the fixture format.
+pub fn fixture(vectors: &[Vector]) -> String {
+    let mut out = String::from("# mq-ipc wire conformance vectors; name hex\n");
+    for v in vectors {
+        let _ = writeln!(out, "\n# {}", v.description);
+        let _ = write!(out, "{} ", v.name);
+        for b in &v.frame {
+            let _ = write!(out, "{b:02x}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Parse a fixture into `(name, frame)` pairs.
+pub fn parse_fixture(text: &str) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut out = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("fixture line {}: expected 'name hex'", lineno + 1),
+            )
+        };
+        let (name, hex) = line.split_once(' ').ok_or_else(bad)?;
+        let hex = hex.trim();
+        if hex.len() % 2 != 0 {
+            return Err(bad());
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| bad())?;
+        out.push((name.to_string(), bytes));
+    }
+    Ok(out)
+}
+
+/// Names of the vectors in `text` that are missing, differ from
+/// [`vectors`] or fail [`check`]. Empty means the fixture conforms.
+pub fn check_fixture(text: &str) -> io::Result<Vec<String>> {
+    let parsed = parse_fixture(text)?;
+    let mut bad = Vec::new();
+    for v in vectors() {
+        match parsed.iter().find(|(name, _)| name == v.name) {
+            Some((_, bytes)) if *bytes == v.frame && check(&v).is_ok() => {}
+            _ => bad.push(v.name.to_string()),
+        }
+    }
+    Ok(bad)
+}
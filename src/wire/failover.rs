@@ -0,0 +1,467 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Redundant links with automatic failover.
+//!
+//! A [`FailoverLink`] drives a primary and a backup [`Link`] (e.g. a
+//! serial line backed up by UDP) as one. Both are kept alive with
+//! heartbeat frames; traffic goes over the primary while it is healthy,
+//! moves to the backup when the primary stops answering or fails to
+//! send, and returns once the primary has been healthy again for a
+//! while. Frames arriving on either link are received.
+//!
+//! Both ends of the connection should use a `FailoverLink`, so that
+//! each side sees the other's heartbeats. Every change of state is
+//! published as a [`FailoverStatus`] on [`LINK_STATUS_TOPIC`].
+
+use super::{frame, link::Link, Topic};
+use crate::TopicOptions;
+use bytemuck::{Pod, Zeroable};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Topic that [`FailoverStatus`] events are published on.
+pub const LINK_STATUS_TOPIC: &str = "/ipc_link_status";
+
+/// [`FailoverStatus::active`] value while traffic uses the primary.
+pub const ACTIVE_PRIMARY: u8 = 0;
+/// [`FailoverStatus::active`] value while traffic uses the backup.
+pub const ACTIVE_BACKUP: u8 = 1;
+
+const RX_POLL: Duration = Duration::from_millis(20);
+
+/// State of a [`FailoverLink`], published whenever it changes.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct FailoverStatus {
+    /// Wall-clock time of the change, in nanoseconds since the epoch.
+    pub timestamp_ns: u64,
+    /// Switches between the links so far.
+    pub switches: u32,
+    /// [`ACTIVE_PRIMARY`] or [`ACTIVE_BACKUP`].
+    pub active: u8,
+    pub primary_up: u8,
+    pub backup_up: u8,
+    pub reserved: u8,
+}
+
+/// Timing and reporting for [`FailoverLink::new`].
+#[derive(Clone, Debug)]
+pub struct FailoverOptions {
+    heartbeat: Duration,
+    timeout: Duration,
+    fail_back: Duration,
+    status_topic: Option<String>,
+}
+
+impl Default for FailoverOptions {
+    fn default() -> Self {
+        FailoverOptions {
+            heartbeat: Duration::from_millis(200),
+            timeout: Duration::from_secs(1),
+            fail_back: Duration::from_secs(5),
+            status_topic: Some(LINK_STATUS_TOPIC.to_string()),
+        }
+    }
+}
+
+impl FailoverOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interval between heartbeats on each link.
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "heartbeat interval must be non-zero");
+        self.heartbeat = interval;
+        self
+    }
+
+    /// A link that received nothing for this long is down.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long the primary must stay healthy before traffic returns to it.
+    pub fn fail_back(mut self, hold: Duration) -> Self {
+        self.fail_back = hold;
+        self
+    }
+
+    /// Publish status events on `name` instead of [`LINK_STATUS_TOPIC`].
+    pub fn status_topic(mut self, name: &str) -> Self {
+        self.status_topic = Some(name.to_string());
+        self
+    }
+
+    /// Do not publish status events.
+    pub fn no_status(mut self) -> Self {
+        self.status_topic = None;
+        self
+    }
+}
+
+struct Path {
+    link: Arc<dyn Link>,
+    last_seen: Mutex<Option<Instant>>,
+    send_failed: AtomicBool,
+}
+
+impl Path {
+    fn up(&self, now: Instant, timeout: Duration) -> bool {
+        !self.send_failed.load(Ordering::Relaxed)
+            && self
+                .last_seen
+                .lock()
+                .unwrap()
+                .is_some_and(|t| now.duration_since(t) < timeout)
+    }
+
+    fn send(&self, frame: &[u8], prio: u32) -> io::Result<()> {
+        let res = self.link.send_prio(frame, prio);
+        self.send_failed.store(res.is_err(), Ordering::Relaxed);
+        res
+    }
+}
+
+struct Inner {
+    paths: [Path; 2],
+    active: AtomicU8,
+    switches: AtomicU32,
+    up: [AtomicBool; 2],
+    status: Option<Topic<FailoverStatus>>,
+    running: AtomicBool,
+}
+
+impl Inner {
+    fn snapshot(&self) -> FailoverStatus {
+        let timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        FailoverStatus {
+            timestamp_ns,
+            switches: self.switches.load(Ordering::Relaxed),
+            active: self.active.load(Ordering::Relaxed),
+            primary_up: self.up[0].load(Ordering::Relaxed) as u8,
+            backup_up: self.up[1].load(Ordering::Relaxed) as u8,
+            reserved: 0,
+        }
+    }
+
+    fn report(&self) {
+        if let Some(topic) = &self.status {
+            // Conflating, so a status nobody reads never blocks a send.
+            let _ = topic.publish(&self.snapshot(), 1, 0);
+        }
+    }
+
+    fn switch_to(&self, idx: u8) {
+        if self.active.swap(idx, Ordering::Relaxed) != idx {
+            self.switches.fetch_add(1, Ordering::Relaxed);
+            self.report();
+        }
+    }
+}
+
+/// [`Link`] over a primary and a backup link; see the module docs.
+pub struct FailoverLink {
+    inner: Arc<Inner>,
+    rx: Mutex<mpsc::Receiver<Vec<u8>>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl FailoverLink {
+    pub fn new(
+        primary: Arc<dyn Link>,
+        backup: Arc<dyn Link>,
+        opts: &FailoverOptions,
+    ) -> io::Result<Self> {
+        let status = match &opts.status_topic {
+            Some(name) => Some(Topic::<FailoverStatus>::with_options(
+                name,
+                &TopicOptions::new(8).conflate(true),
+            )?),
+            None => None,
+        };
+        let path = |link| Path {
+            link,
+            last_seen: Mutex::new(None),
+            send_failed: AtomicBool::new(false),
+        };
+        let inner = Arc::new(Inner {
+            paths: [path(primary), path(backup)],
+            active: AtomicU8::new(ACTIVE_PRIMARY),
+            switches: AtomicU32::new(0),
+            up: [AtomicBool::new(false), AtomicBool::new(false)],
+            status,
+            running: AtomicBool::new(true),
+        });
+
+        let (tx, rx) = mpsc::channel();
+        let mut threads = Vec::with_capacity(3);
+        for idx in 0..2 {
+            let inner = Arc::clone(&inner);
+            let tx = tx.clone();
+            threads.push(thread::spawn(move || read_loop(&inner, idx, &tx)));
+        }
+        let monitor = Arc::clone(&inner);
+        let opts = opts.clone();
+        threads.push(thread::spawn(move || monitor_loop(&monitor, &opts)));
+
+        Ok(FailoverLink {
+            inner,
+            rx: Mutex::new(rx),
+            threads,
+        })
+    }
+
+    /// Current state, as last published on the status topic.
+    pub fn status(&self) -> FailoverStatus {
+        self.inner.snapshot()
+    }
+}
+
+fn read_loop(inner: &Inner, idx: usize, tx: &mpsc::Sender<Vec<u8>>) {
+    let path = &inner.paths[idx];
+    while inner.running.load(Ordering::Relaxed) {
+        match path.link.recv(RX_POLL) {
+            Ok(Some(bytes)) => {
+                *path.last_seen.lock().unwrap() = Some(Instant::now());
+                if frame::kind(&bytes) == Some(frame::KIND_HEARTBEAT) {
+                    continue;
+                }
+                if tx.send(bytes).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => {}
+            Err(_) => thread::sleep(RX_POLL),
+        }
+    }
+}
+
+fn monitor_loop(inner: &Inner, opts: &FailoverOptions) {
+    let beat = frame::heartbeat();
+    let mut primary_since: Option<Instant> = None;
+
+    while inner.running.load(Ordering::Relaxed) {
+        for path in &inner.paths {
+            let _ = path.send(&beat, 0);
+        }
+
+        let now = Instant::now();
+        let up = [
+            inner.paths[0].up(now, opts.timeout),
+            inner.paths[1].up(now, opts.timeout),
+        ];
+        let mut changed = false;
+        for (flag, up) in inner.up.iter().zip(up) {
+            changed |= flag.swap(up, Ordering::Relaxed) != up;
+        }
+        primary_since = if up[0] {
+            primary_since.or(Some(now))
+        } else {
+            None
+        };
+
+        let active = inner.active.load(Ordering::Relaxed);
+        let target = match active {
+            ACTIVE_PRIMARY if !up[0] && up[1] => ACTIVE_BACKUP,
+            ACTIVE_BACKUP
+                if up[0]
+                    && (!up[1] || primary_since.is_some_and(|t| now - t >= opts.fail_back)) =>
+            {
+                ACTIVE_PRIMARY
+            }
+            _ => active,
+        };
+
+        if target != active {
+            inner.switch_to(target);
+        } else if changed {
+            inner.report();
+        }
+        thread::sleep(opts.heartbeat);
+    }
+}
+
+impl Link for FailoverLink {
+    fn send(&self, frame: &[u8]) -> io::Result<()> {
+        self.send_prio(frame, 0)
+    }
+
+    fn send_prio(&self, frame: &[u8], prio: u32) -> io::Result<()> {
+        let active = self.inner.active.load(Ordering::Relaxed);
+        match self.inner.paths[active as usize].send(frame, prio) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                // Do not wait for the monitor: retry on the other link now.
+                let other = active ^ 1;
+                self.inner.paths[other as usize]
+                    .send(frame, prio)
+                    .map_err(|_| err)?;
+                self.inner.switch_to(other);
+                Ok(())
+            }
+        }
+    }
+
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        match self.rx.lock().unwrap().recv_timeout(timeout) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    /// The smaller MTU of the two links, since traffic may use either.
+    fn mtu(&self) -> usize {
+        self.inner.paths[0]
+            .link
+            .mtu()
+            .min(self.inner.paths[1].link.mtu())
+    }
+
+    fn is_up(&self) -> bool {
+        self.inner.up.iter().any(|up| up.load(Ordering::Relaxed))
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "failover({}|{})",
+            self.inner.paths[0].link.name(),
+            self.inner.paths[1].link.name()
+        )
+    }
+}
+
+impl Drop for FailoverLink {
+    fn drop(&mut self) {
+        self.inner.running.store(false, Ordering::Relaxed);
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::cleanup::TempTopic;
+
+    struct ChanLink {
+        tx: mpsc::Sender<Vec<u8>>,
+        rx: Mutex<mpsc::Receiver<Vec<u8>>>,
+        down: Arc<AtomicBool>,
+    }
+
+    impl Link for ChanLink {
+        fn send(&self, frame: &[u8]) -> io::Result<()> {
+            if self.down.load(Ordering::Relaxed) {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            self.tx
+                .send(frame.to_vec())
+                .map_err(|_| io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+            match self.rx.lock().unwrap().recv_timeout(timeout) {
+                Ok(_) if self.down.load(Ordering::Relaxed) => Ok(None),
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(_) => Ok(None),
+            }
+        }
+    }
+
+    fn pair(down: &Arc<AtomicBool>) -> (Arc<dyn Link>, Arc<dyn Link>) {
+        let (a_tx, b_rx) = mpsc::channel();
+        let (b_tx, a_rx) = mpsc::channel();
+        let end = |tx, rx| -> Arc<dyn Link> {
+            Arc::new(ChanLink {
+                tx,
+                rx: Mutex::new(rx),
+                down: Arc::clone(down),
+            })
+        };
+        (end(a_tx, a_rx), end(b_tx, b_rx))
+    }
+
+    fn wait_until(f: impl Fn() -> bool) -> bool {
+        for _ in 0..200 {
+            if f() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn fails_over_and_back() {
+        let tmp = TempTopic::new("/mq_ipc_test_failover_");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let status = Topic::<FailoverStatus>::new(tmp.name(), 8).unwrap();
+        let events_cb = Arc::clone(&events);
+        status.subscribe(move |s: FailoverStatus| events_cb.lock().unwrap().push(s.active));
+
+        let primary_down = Arc::new(AtomicBool::new(false));
+        let (pa, pb) = pair(&primary_down);
+        let (ba, bb) = pair(&Arc::new(AtomicBool::new(false)));
+        let opts = FailoverOptions::new()
+            .heartbeat(Duration::from_millis(10))
+            .timeout(Duration::from_millis(80))
+            .fail_back(Duration::from_millis(100));
+        let a = FailoverLink::new(pa, ba, &opts.clone().status_topic(tmp.name())).unwrap();
+        let b = FailoverLink::new(pb, bb, &opts.no_status()).unwrap();
+
+        assert!(wait_until(|| {
+            let s = a.status();
+            s.primary_up == 1 && s.backup_up == 1
+        }));
+        a.send(b"one").unwrap();
+        assert_eq!(b.recv(Duration::from_secs(1)).unwrap().unwrap(), b"one");
+
+        primary_down.store(true, Ordering::Relaxed);
+        assert!(wait_until(|| a.status().active == ACTIVE_BACKUP));
+        a.send(b"two").unwrap();
+        assert_eq!(b.recv(Duration::from_secs(1)).unwrap().unwrap(), b"two");
+
+        primary_down.store(false, Ordering::Relaxed);
+        assert!(wait_until(|| a.status().active == ACTIVE_PRIMARY));
+        assert_eq!(a.status().switches, 2);
+        assert!(wait_until(|| events
+            .lock()
+            .unwrap()
+            .contains(&ACTIVE_BACKUP)));
+    }
+}
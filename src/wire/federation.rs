@@ -0,0 +1,575 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Zero-config topic sharing between machines on one network.
+//!
+//! Every machine in a small fleet runs a [`Federation`] under its own
+//! namespace (`robot1`, `robot2`, ...). It multicasts a [`Beacon`] with
+//! the namespace, the UDP port its router listens on and the topics it
+//! bridges, and learns the other machines from their beacons. One
+//! [`Router`] serves all of them: frames from `/ipc_tx` go to every live
+//! peer and arrive there under the sender's namespace.
+//!
+//! ```no_run
+//! # fn main() -> std::io::Result<()> {
+//! use mq_ipc::wire::federation::{Federation, FederationOptions};
+//!
+//! let fed = Federation::start(&FederationOptions::new("robot1").bridge("/motor_state"))?;
+//! // robot2 now receives `/motor_state` of this machine on its
+//! // `/robot1.motor_state` queue, and vice versa.
+//! for peer in fed.peers() {
+//!     println!("{} at {}: {:?}", peer.namespace, peer.addr, peer.topics);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! On the wire a topic carries its namespace as the first path segment
+//! ([`export_name`]); the receiving side maps it back to a single-level
+//! queue name the way `MQ_IPC_PREFIX` does ([`import_name`]), so a
+//! frame addressed to a machine's own namespace reaches its plain topic.
+
+use super::{
+    link::{Link, UDP_MAX_FRAME},
+    mtu::MTU_UDP,
+    router::{Router, RouterOptions},
+};
+use std::{
+    collections::HashMap,
+    io,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        raw::c_int,
+    },
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Default multicast group and port beacons are exchanged on.
+pub const BEACON_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 77, 77), 7447);
+
+/// Default time between two beacons of one machine.
+pub const BEACON_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default silence after which a peer is forgotten.
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest namespace accepted by [`Federation::start`].
+pub const MAX_NAMESPACE: usize = 32;
+
+const BEACON_MAGIC: &[u8; 4] = b"MQFB";
+const BEACON_VERSION: u8 = 1;
+const BEACON_POLL: Duration = Duration::from_millis(50);
+
+/// Wire name of `topic` published by `namespace`: `/motor_state` in
+/// `robot1` travels as `/robot1/motor_state`.
+pub fn export_name(namespace: &str, topic: &str) -> String {
+    format!("/{namespace}/{}", topic.trim_start_matches('/'))
+}
+
+/// Local queue a wire name lands on for a machine in `namespace`.
+///
+/// `/robot1/x` becomes `/x` on `robot1` itself and `/robot1.x` anywhere
+/// else; names without a namespace segment are kept.
+pub fn import_name(namespace: &str, wire: &str) -> String {
+    match wire.strip_prefix('/').and_then(|rest| rest.split_once('/')) {
+        Some((head, rest)) if head == namespace => format!("/{rest}"),
+        Some((head, rest)) => format!("/{head}.{rest}"),
+        None => wire.to_string(),
+    }
+}
+
+/// Announcement multicast by every [`Federation`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Beacon {
+    pub namespace: String,
+    /// UDP port of the sender's router; the address is the beacon's source.
+    pub wire_port: u16,
+    /// Topics the sender bridges, without its namespace.
+    pub topics: Vec<String>,
+}
+
+impl Beacon {
+    /// Encode into one datagram. Topics that would push it past
+    /// [`UDP_MAX_FRAME`] are left out.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64);
+        out.extend_from_slice(BEACON_MAGIC);
+        out.push(BEACON_VERSION);
+        out.extend_from_slice(&self.wire_port.to_le_bytes());
+        push_name(&mut out, &self.namespace);
+        let count_at = out.len();
+        out.push(0);
+        for topic in &self.topics {
+            let len = topic.len().min(u8::MAX as usize);
+            if out.len() + 1 + len > UDP_MAX_FRAME || out[count_at] == u8::MAX {
+                break;
+            }
+            push_name(&mut out, topic);
+            out[count_at] += 1;
+        }
+        out
+    }
+
+    /// Decode a datagram; `None` for anything that is not a beacon.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(BEACON_MAGIC)?;
+        let (&version, rest) = rest.split_first()?;
+        if version != BEACON_VERSION || rest.len() < 2 {
+            return None;
+        }
+        let wire_port = u16::from_le_bytes([rest[0], rest[1]]);
+        let (namespace, rest) = take_name(&rest[2..])?;
+        let (&count, mut rest) = rest.split_first()?;
+        let mut topics = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let (topic, tail) = take_name(rest)?;
+            topics.push(topic);
+            rest = tail;
+        }
+        Some(Beacon {
+            namespace,
+            wire_port,
+            topics,
+        })
+    }
+}
+
+fn push_name(out: &mut Vec<u8>, name: &str) {
+    let mut len = name.len().min(u8::MAX as usize);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    out.push(len as u8);
+    out.extend_from_slice(&name.as_bytes()[..len]);
+}
+
+fn take_name(bytes: &[u8]) -> Option<(String, &[u8])> {
+    let (&len, rest) = bytes.split_first()?;
+    let len = len as usize;
+    if rest.len() < len {
+        return None;
+    }
+    let name = std::str::from_utf8(&rest[..len]).ok()?.to_string();
+    Some((name, &rest[len..]))
+}
+
+/// A machine learned from its beacons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Peer {
+    pub namespace: String,
+    /// Where its router listens.
+    pub addr: SocketAddr,
+    pub topics: Vec<String>,
+    pub last_seen: Instant,
+}
+
+/// Configuration for [`Federation::start`].
+#[derive(Clone, Debug)]
+pub struct FederationOptions {
+    namespace: String,
+    group: SocketAddrV4,
+    bind: SocketAddr,
+    topics: Vec<String>,
+    interval: Duration,
+    timeout: Duration,
+    router: RouterOptions,
+}
+
+impl FederationOptions {
+    /// Federate this machine as `namespace`, e.g. `"robot1"`.
+    pub fn new(namespace: &str) -> Self {
+        FederationOptions {
+            namespace: namespace.trim_matches('/').to_string(),
+            group: BEACON_GROUP,
+            bind: SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            topics: Vec::new(),
+            interval: BEACON_INTERVAL,
+            timeout: PEER_TIMEOUT,
+            router: RouterOptions::new(),
+        }
+    }
+
+    /// Exchange beacons on `group` instead of [`BEACON_GROUP`]; fleets
+    /// sharing a network segment pick different groups or ports.
+    pub fn group(mut self, group: SocketAddrV4) -> Self {
+        self.group = group;
+        self
+    }
+
+    /// Address the router's socket binds to; any port by default.
+    pub fn bind(mut self, addr: SocketAddr) -> Self {
+        self.bind = addr;
+        self
+    }
+
+    /// Announce `topic` as bridged by this machine. The list is what
+    /// peers see in [`Peer::topics`]; what actually crosses is whatever
+    /// is mirrored into `/ipc_tx`.
+    pub fn bridge(mut self, topic: &str) -> Self {
+        self.topics.push(topic.to_string());
+        self
+    }
+
+    /// Time between beacons (default [`BEACON_INTERVAL`]).
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Forget peers silent for longer than `timeout` (default
+    /// [`PEER_TIMEOUT`]).
+    pub fn peer_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Options for the router; its namespace is set by the federation.
+    pub fn router(mut self, opts: RouterOptions) -> Self {
+        self.router = opts;
+        self
+    }
+}
+
+/// [`Link`] fanning frames out to every known peer over one socket.
+struct PeerLink {
+    namespace: String,
+    socket: UdpSocket,
+    peers: Mutex<HashMap<String, Peer>>,
+}
+
+impl PeerLink {
+    fn is_peer(&self, addr: SocketAddr) -> bool {
+        self.peers.lock().unwrap().values().any(|p| p.addr == addr)
+    }
+}
+
+impl Link for PeerLink {
+    fn send(&self, frame: &[u8]) -> io::Result<()> {
+        let addrs: Vec<SocketAddr> = self
+            .peers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|p| p.addr)
+            .collect();
+        let mut result = Ok(());
+        for addr in addrs {
+            if let Err(err) = self.socket.send_to(frame, addr) {
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        let deadline = Instant::now() + timeout;
+        let mut buf = vec![0u8; MTU_UDP.max(UDP_MAX_FRAME)];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            // A zero timeout would mean "block forever" to the socket.
+            self.socket
+                .set_read_timeout(Some(left.max(Duration::from_millis(1))))?;
+            match self.socket.recv_from(&mut buf) {
+                // Strays from machines not (or no longer) federated.
+                Ok((_, from)) if !self.is_peer(from) => {
+                    if left.is_zero() {
+                        return Ok(None);
+                    }
+                }
+                Ok((n, _)) => {
+                    buf.truncate(n);
+                    return Ok(Some(buf));
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::ConnectionRefused
+                    ) =>
+                {
+                    return Ok(None);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn is_up(&self) -> bool {
+        !self.peers.lock().unwrap().is_empty()
+    }
+
+    fn mtu(&self) -> usize {
+        MTU_UDP
+    }
+
+    fn name(&self) -> String {
+        format!("federation:{}", self.namespace)
+    }
+}
+
+/// Beacon exchange plus a [`Router`] to every discovered peer.
+///
+/// Dropping it stops announcing and routing; peers forget this machine
+/// after their peer timeout.
+pub struct Federation {
+    link: Arc<PeerLink>,
+    router: Router,
+    running: Arc<AtomicBool>,
+    beacon: Option<thread::JoinHandle<()>>,
+}
+
+impl Federation {
+    /// Join the fleet: start announcing and route to whoever answers.
+    pub fn start(opts: &FederationOptions) -> io::Result<Self> {
+        let ns = &opts.namespace;
+        if ns.is_empty() || ns.len() > MAX_NAMESPACE || ns.contains(['/', '.']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid namespace '{ns}'"),
+            ));
+        }
+
+        let beacon_socket = join_group(opts.group)?;
+        let link = Arc::new(PeerLink {
+            namespace: ns.clone(),
+            socket: UdpSocket::bind(opts.bind)?,
+            peers: Mutex::new(HashMap::new()),
+        });
+        let beacon = Beacon {
+            namespace: ns.clone(),
+            wire_port: link.socket.local_addr()?.port(),
+            topics: opts.topics.clone(),
+        }
+        .encode();
+        let router = Router::new(link.clone(), &opts.router.clone().namespace(ns))?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let worker = {
+            let link = Arc::clone(&link);
+            let running = Arc::clone(&running);
+            let (group, interval, timeout) = (opts.group, opts.interval, opts.timeout);
+            thread::spawn(move || {
+                let mut buf = vec![0u8; UDP_MAX_FRAME];
+                let mut last_sent: Option<Instant> = None;
+                while running.load(Ordering::Relaxed) {
+                    if last_sent.is_none_or(|t| t.elapsed() >= interval) {
+                        if let Err(err) = beacon_socket.send_to(&beacon, group) {
+                            eprintln!("federation beacon error: {err}");
+                        }
+                        last_sent = Some(Instant::now());
+                    }
+
+                    if let Ok((n, from)) = beacon_socket.recv_from(&mut buf)
+                        && let Some(b) = Beacon::decode(&buf[..n])
+                        && b.namespace != link.namespace
+                    {
+                        let peer = Peer {
+                            addr: SocketAddr::new(from.ip(), b.wire_port),
+                            namespace: b.namespace.clone(),
+                            topics: b.topics,
+                            last_seen: Instant::now(),
+                        };
+                        link.peers.lock().unwrap().insert(b.namespace, peer);
+                    }
+                    link.peers
+                        .lock()
+                        .unwrap()
+                        .retain(|_, p| p.last_seen.elapsed() <= timeout);
+                }
+            })
+        };
+
+        Ok(Federation {
+            link,
+            router,
+            running,
+            beacon: Some(worker),
+        })
+    }
+
+    /// This machine's namespace.
+    pub fn namespace(&self) -> &str {
+        &self.link.namespace
+    }
+
+    /// Live peers, sorted by namespace.
+    pub fn peers(&self) -> Vec<Peer> {
+        let mut peers: Vec<Peer> = self.link.peers.lock().unwrap().values().cloned().collect();
+        peers.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+        peers
+    }
+
+    /// Address the router's socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.link.socket.local_addr()
+    }
+
+    /// The router shared by all peers, e.g. for [`Router::stats`].
+    pub fn router(&self) -> &Router {
+        &self.router
+    }
+}
+
+impl Drop for Federation {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.beacon.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Socket bound to the group's port (shared with other federations on
+/// this host) and subscribed to the group.
+fn join_group(group: SocketAddrV4) -> io::Result<UdpSocket> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let socket = unsafe { UdpSocket::from_raw_fd(fd) };
+
+    let one: c_int = 1;
+    let rc = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_REUSEADDR,
+            &one as *const c_int as *const libc::c_void,
+            std::mem::size_of::<c_int>() as libc::socklen_t,
+        )
+    };
+    if rc == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: group.port().to_be(),
+        sin_addr: libc::in_addr { s_addr: 0 },
+        sin_zero: [0; 8],
+    };
+    let rc = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    };
+    if rc == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_read_timeout(Some(BEACON_POLL))?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cleanup::TempTopic,
+        wire::{frame, Topic, WirePacket},
+        MqTopic,
+    };
+
+    #[test]
+    fn names_and_beacons_round_trip() {
+        assert_eq!(export_name("robot1", "/motor_state"), "/robot1/motor_state");
+        assert_eq!(
+            import_name("robot2", "/robot1/motor_state"),
+            "/robot1.motor_state"
+        );
+        assert_eq!(import_name("robot1", "/robot1/motor_state"), "/motor_state");
+        assert_eq!(import_name("robot1", "/motor_state"), "/motor_state");
+
+        let beacon = Beacon {
+            namespace: "robot1".to_string(),
+            wire_port: 40123,
+            topics: vec!["/motor_state".to_string(), "/imu".to_string()],
+        };
+        assert_eq!(Beacon::decode(&beacon.encode()), Some(beacon.clone()));
+        assert_eq!(Beacon::decode(b"MQFB\x02"), None);
+
+        let many = Beacon {
+            topics: vec!["/t".repeat(100); 20],
+            ..beacon
+        };
+        let bytes = many.encode();
+        assert!(bytes.len() <= UDP_MAX_FRAME);
+        assert_eq!(Beacon::decode(&bytes).unwrap().topics.len(), 7);
+    }
+
+    #[test]
+    fn peers_discover_each_other_and_exchange_topics() {
+        let pid = std::process::id();
+        let group = SocketAddrV4::new(*BEACON_GROUP.ip(), 20000 + (pid % 20000) as u16);
+        let (ns_a, ns_b) = (format!("fa{pid}"), format!("fb{pid}"));
+        let tx_a = TempTopic::new("/mq_ipc_test_fed_tx_a_");
+        let tx_b = TempTopic::new("/mq_ipc_test_fed_tx_b_");
+        let dest = TempTopic::with_name(&format!("/{ns_a}.mq_ipc_test_fed"));
+
+        let start = |ns: &str, tx: &TempTopic| {
+            let opts = FederationOptions::new(ns)
+                .group(group)
+                .interval(Duration::from_millis(20))
+                .bridge("/mq_ipc_test_fed")
+                .router(RouterOptions::new().tx_topic(tx.name()).maxmsg(4));
+            Federation::start(&opts).unwrap()
+        };
+        let a = start(&ns_a, &tx_a);
+        let b = start(&ns_b, &tx_b);
+
+        for _ in 0..200 {
+            if !a.peers().is_empty() && !b.peers().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let peers = b.peers();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].namespace, ns_a);
+        assert_eq!(peers[0].addr.port(), a.local_addr().unwrap().port());
+        assert_eq!(peers[0].topics, ["/mq_ipc_test_fed"]);
+
+        let local = MqTopic::new(dest.name(), 4).unwrap();
+        let mirror = Topic::<WirePacket>::new(tx_a.name(), 4).unwrap();
+        mirror
+            .publish(&frame::packet("/mq_ipc_test_fed", &[4, 2]), 0, 0)
+            .unwrap();
+
+        let msg = local
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap()
+            .expect("frame did not cross the federation");
+        assert_eq!(&msg.payload[..msg.hdr.len as usize], &[4, 2]);
+    }
+}
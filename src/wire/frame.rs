@@ -0,0 +1,548 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Byte layout of [`WirePacket`]s on a [`Link`](super::link::Link).
+//!
+//! Every frame starts with a kind byte. Data frames then carry the
+//! packet with its fixed-size arrays trimmed to the used lengths:
+//!
+//! ```text
+//! 0      1          2             4           4+topic_len
+//! | kind | topic_len | payload_len | topic ... | payload ... |
+//!                      (u16 LE)
+//! ```
+//!
+//! Control frames let the peer shape what it is sent:
+//!
+//! ```text
+//! subscribe:   | 0x03 | period_us (u32 LE) | topic_len | topic ... |
+//! unsubscribe: | 0x04 | topic_len | topic ... |
+//! ```
+//!
+//! A `period_us` of zero asks for every sample.
+//!
+//! [`Command`](super::command::Command)s and their replies:
+//!
+//! ```text
+//! command: | 0x05 | id (u32 LE) | topic_len | topic ... | payload ... |
+//! status:  | 0x06 | id (u32 LE) | status | code (u16 LE) |
+//! ```
+//!
+//! File transfers (see [`transfer`](super::transfer)); all integers
+//! little-endian, CRCs are CRC-32/ISO-HDLC as computed by [`crc32`]:
+//!
+//! ```text
+//! offer:  | 0x07 | id u32 | size u64 | crc u32 | restart u8 | name_len | name ... |
+//! accept: | 0x08 | id u32 | refused u8 | offset u64 | prefix_crc u32 |
+//! chunk:  | 0x09 | id u32 | offset u64 | crc u32 | data ... |
+//! ack:    | 0x0A | id u32 | status u8 | next u64 |
+//! ```
+//!
+//! Frames larger than a link's MTU travel as fragments (see
+//! [`mtu`](super::mtu)). Each router also advertises the largest
+//! frame it can receive:
+//!
+//! ```text
+//! fragment: | 0x0B | msg_id (u16 LE) | index | count | bytes ... |
+//! mtu:      | 0x0C | mtu (u16 LE) |
+//! ```
+//!
+//! Frames carry topic names in full, never a hash of them. Peers that
+//! would rather not store names can still match frames by comparing
+//! [`packet_topic_hash`] against a [`topic_hash`] computed at compile
+//! time.
+//!
+//! Links are responsible for delimiting frames and for integrity
+//! checking where the medium does not already provide it.
+
+use super::{WirePacket, WIRE_MAX_PAYLOAD, WIRE_MAX_TOPIC};
+use std::time::Duration;
+
+/// Frame kind of a mirrored topic sample.
+pub const KIND_DATA: u8 = 0x01;
+
+/// Frame kind of a link keep-alive; carries no body.
+pub const KIND_HEARTBEAT: u8 = 0x02;
+
+/// Frame kind of a request to have a topic mirrored.
+pub const KIND_SUBSCRIBE: u8 = 0x03;
+
+/// Frame kind cancelling a [`KIND_SUBSCRIBE`] request.
+pub const KIND_UNSUBSCRIBE: u8 = 0x04;
+
+/// Frame kind of a remote command.
+pub const KIND_COMMAND: u8 = 0x05;
+
+/// Frame kind of a reply to a [`KIND_COMMAND`] frame.
+pub const KIND_COMMAND_STATUS: u8 = 0x06;
+
+/// Command status: the handler took the command on.
+pub const STATUS_ACCEPTED: u8 = 1;
+/// Command status: the handler refused the command.
+pub const STATUS_REJECTED: u8 = 2;
+/// Command status: the command finished.
+pub const STATUS_COMPLETED: u8 = 3;
+
+/// Frame kind announcing a file transfer.
+pub const KIND_FILE_OFFER: u8 = 0x07;
+/// Frame kind answering a [`KIND_FILE_OFFER`].
+pub const KIND_FILE_ACCEPT: u8 = 0x08;
+/// Frame kind carrying a piece of a file.
+pub const KIND_FILE_CHUNK: u8 = 0x09;
+/// Frame kind acknowledging a [`KIND_FILE_CHUNK`].
+pub const KIND_FILE_ACK: u8 = 0x0A;
+
+/// Frame kind carrying one piece of a larger frame.
+pub const KIND_FRAGMENT: u8 = 0x0B;
+/// Frame kind advertising the sender's receive MTU.
+pub const KIND_MTU: u8 = 0x0C;
+
+/// Bytes in front of the data of a fragment frame.
+pub const FRAGMENT_HEADER_SIZE: usize = 5;
+
+/// Bytes in front of the topic name of a data frame.
+pub const DATA_HEADER_SIZE: usize = 4;
+
+/// Encode `pkt` as a data frame.
+pub fn encode(pkt: &WirePacket) -> Vec<u8> {
+    let tlen = (pkt.topic_len as usize).min(WIRE_MAX_TOPIC);
+    let plen = (pkt.payload_len as usize).min(WIRE_MAX_PAYLOAD);
+    let mut frame = Vec::with_capacity(DATA_HEADER_SIZE + tlen + plen);
+    frame.push(KIND_DATA);
+    frame.push(tlen as u8);
+    frame.extend_from_slice(&(plen as u16).to_le_bytes());
+    frame.extend_from_slice(&pkt.topic[..tlen]);
+    frame.extend_from_slice(&pkt.data[..plen]);
+    frame
+}
+
+/// Kind byte of `frame`, or `None` if it is empty.
+pub fn kind(frame: &[u8]) -> Option<u8> {
+    frame.first().copied()
+}
+
+/// Encode a heartbeat frame.
+pub fn heartbeat() -> Vec<u8> {
+    vec![KIND_HEARTBEAT]
+}
+
+/// Decode a data frame; `None` for other kinds and malformed frames.
+pub fn decode(frame: &[u8]) -> Option<WirePacket> {
+    let (&kind, rest) = frame.split_first()?;
+    if kind != KIND_DATA || rest.len() < DATA_HEADER_SIZE - 1 {
+        return None;
+    }
+    let tlen = rest[0] as usize;
+    let plen = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+    let body = &rest[3..];
+    if tlen > WIRE_MAX_TOPIC || plen > WIRE_MAX_PAYLOAD || body.len() != tlen + plen {
+        return None;
+    }
+
+    let mut pkt = WirePacket {
+        payload_len: plen as u16,
+        topic_len: tlen as u8,
+        reserved: 0,
+        topic: [0u8; WIRE_MAX_TOPIC],
+        data: [0u8; WIRE_MAX_PAYLOAD],
+    };
+    pkt.topic[..tlen].copy_from_slice(&body[..tlen]);
+    pkt.data[..plen].copy_from_slice(&body[tlen..]);
+    Some(pkt)
+}
+
+/// Build a packet for `topic` carrying `data`, truncating both to the
+/// wire limits (the name per [`topic_len`]).
+pub fn packet(topic: &str, data: &[u8]) -> WirePacket {
+    let tlen = topic_len(topic);
+    let plen = data.len().min(WIRE_MAX_PAYLOAD);
+    let mut pkt = WirePacket {
+        payload_len: plen as u16,
+        topic_len: tlen as u8,
+        reserved: 0,
+        topic: [0u8; WIRE_MAX_TOPIC],
+        data: [0u8; WIRE_MAX_PAYLOAD],
+    };
+    pkt.topic[..tlen].copy_from_slice(&topic.as_bytes()[..tlen]);
+    pkt.data[..plen].copy_from_slice(&data[..plen]);
+    pkt
+}
+
+/// Decoded control frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// Mirror `topic`, at most once per `period` if set.
+    Subscribe {
+        topic: String,
+        period: Option<Duration>,
+    },
+    Unsubscribe {
+        topic: String,
+    },
+}
+
+/// Encode a subscribe request for `topic`.
+pub fn subscribe(topic: &str, period: Option<Duration>) -> Vec<u8> {
+    let us = period.map_or(0, |p| p.as_micros().clamp(1, u32::MAX as u128) as u32);
+    let mut frame = vec![KIND_SUBSCRIBE];
+    frame.extend_from_slice(&us.to_le_bytes());
+    push_topic(&mut frame, topic);
+    frame
+}
+
+/// Encode an unsubscribe request for `topic`.
+pub fn unsubscribe(topic: &str) -> Vec<u8> {
+    let mut frame = vec![KIND_UNSUBSCRIBE];
+    push_topic(&mut frame, topic);
+    frame
+}
+
+/// Decode a control frame; `None` for other kinds and malformed frames.
+pub fn decode_control(frame: &[u8]) -> Option<Control> {
+    let (&kind, rest) = frame.split_first()?;
+    match kind {
+        KIND_SUBSCRIBE => {
+            let us = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?);
+            Some(Control::Subscribe {
+                topic: read_topic(&rest[4..])?,
+                period: (us != 0).then(|| Duration::from_micros(us as u64)),
+            })
+        }
+        KIND_UNSUBSCRIBE => Some(Control::Unsubscribe {
+            topic: read_topic(rest)?,
+        }),
+        _ => None,
+    }
+}
+
+/// Encode command `id` for `topic` carrying `payload`.
+pub fn command(id: u32, topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![KIND_COMMAND];
+    frame.extend_from_slice(&id.to_le_bytes());
+    push_topic(&mut frame, topic);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Decode a command frame into `(id, topic, payload)`.
+pub fn decode_command(frame: &[u8]) -> Option<(u32, String, &[u8])> {
+    let (&kind, rest) = frame.split_first()?;
+    if kind != KIND_COMMAND {
+        return None;
+    }
+    let id = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?);
+    let tlen = *rest.get(4)? as usize;
+    let name = rest.get(5..5 + tlen)?;
+    let topic = String::from_utf8(name.to_vec()).ok()?;
+    Some((id, topic, &rest[5 + tlen..]))
+}
+
+/// Encode the reply `status` with application `code` to command `id`.
+pub fn command_status(id: u32, status: u8, code: u16) -> Vec<u8> {
+    let mut frame = vec![KIND_COMMAND_STATUS];
+    frame.extend_from_slice(&id.to_le_bytes());
+    frame.push(status);
+    frame.extend_from_slice(&code.to_le_bytes());
+    frame
+}
+
+/// Decode a command status frame into `(id, status, code)`.
+pub fn decode_command_status(frame: &[u8]) -> Option<(u32, u8, u16)> {
+    if frame.len() != 8 || frame[0] != KIND_COMMAND_STATUS {
+        return None;
+    }
+    let id = u32::from_le_bytes(frame[1..5].try_into().ok()?);
+    Some((id, frame[5], u16::from_le_bytes([frame[6], frame[7]])))
+}
+
+/// File transfer frame; see the module docs for the layout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileFrame {
+    Offer {
+        id: u32,
+        size: u64,
+        crc: u32,
+        /// Discard any partial copy instead of resuming it.
+        restart: bool,
+        name: String,
+    },
+    Accept {
+        id: u32,
+        refused: bool,
+        /// Bytes the receiver already holds.
+        offset: u64,
+        /// CRC of those bytes, so the sender can check they match.
+        prefix_crc: u32,
+    },
+    Chunk {
+        id: u32,
+        offset: u64,
+        crc: u32,
+        data: Vec<u8>,
+    },
+    Ack {
+        id: u32,
+        status: u8,
+        /// Offset the receiver expects next.
+        next: u64,
+    },
+}
+
+/// Encode a file transfer frame.
+pub fn encode_file(f: &FileFrame) -> Vec<u8> {
+    let mut out = Vec::new();
+    match f {
+        FileFrame::Offer {
+            id,
+            size,
+            crc,
+            restart,
+            name,
+        } => {
+            out.push(KIND_FILE_OFFER);
+            out.extend_from_slice(&id.to_le_bytes());
+            out.extend_from_slice(&size.to_le_bytes());
+            out.extend_from_slice(&crc.to_le_bytes());
+            out.push(*restart as u8);
+            let nlen = name.len().min(u8::MAX as usize);
+            out.push(nlen as u8);
+            out.extend_from_slice(&name.as_bytes()[..nlen]);
+        }
+        FileFrame::Accept {
+            id,
+            refused,
+            offset,
+            prefix_crc,
+        } => {
+            out.push(KIND_FILE_ACCEPT);
+            out.extend_from_slice(&id.to_le_bytes());
+            out.push(*refused as u8);
+            out.extend_from_slice(&offset.to_le_bytes());
+            out.extend_from_slice(&prefix_crc.to_le_bytes());
+        }
+        FileFrame::Chunk {
+            id,
+            offset,
+            crc,
+            data,
+        } => {
+            out.push(KIND_FILE_CHUNK);
+            out.extend_from_slice(&id.to_le_bytes());
+            out.extend_from_slice(&offset.to_le_bytes());
+            out.extend_from_slice(&crc.to_le_bytes());
+            out.extend_from_slice(data);
+        }
+        FileFrame::Ack { id, status, next } => {
+            out.push(KIND_FILE_ACK);
+            out.extend_from_slice(&id.to_le_bytes());
+            out.push(*status);
+            out.extend_from_slice(&next.to_le_bytes());
+        }
+    }
+    out
+}
+
+/// Decode a file transfer frame; `None` for other kinds and malformed
+/// frames.
+pub fn decode_file(frame: &[u8]) -> Option<FileFrame> {
+    let (&kind, rest) = frame.split_first()?;
+    let u32_at = |at: usize| Some(u32::from_le_bytes(rest.get(at..at + 4)?.try_into().ok()?));
+    let u64_at = |at: usize| Some(u64::from_le_bytes(rest.get(at..at + 8)?.try_into().ok()?));
+    let id = u32_at(0)?;
+    match kind {
+        KIND_FILE_OFFER => {
+            let nlen = *rest.get(17)? as usize;
+            let name = rest.get(18..)?;
+            if name.len() != nlen {
+                return None;
+            }
+            Some(FileFrame::Offer {
+                id,
+                size: u64_at(4)?,
+                crc: u32_at(12)?,
+                restart: *rest.get(16)? != 0,
+                name: String::from_utf8(name.to_vec()).ok()?,
+            })
+        }
+        KIND_FILE_ACCEPT if rest.len() == 17 => Some(FileFrame::Accept {
+            id,
+            refused: rest[4] != 0,
+            offset: u64_at(5)?,
+            prefix_crc: u32_at(13)?,
+        }),
+        KIND_FILE_CHUNK if rest.len() >= 16 => Some(FileFrame::Chunk {
+            id,
+            offset: u64_at(4)?,
+            crc: u32_at(12)?,
+            data: rest[16..].to_vec(),
+        }),
+        KIND_FILE_ACK if rest.len() == 13 => Some(FileFrame::Ack {
+            id,
+            status: rest[4],
+            next: u64_at(5)?,
+        }),
+        _ => None,
+    }
+}
+
+/// One fragment of a larger frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fragment<'a> {
+    pub msg_id: u16,
+    pub index: u8,
+    pub count: u8,
+    pub bytes: &'a [u8],
+}
+
+/// Encode a fragment frame.
+pub fn fragment(f: &Fragment<'_>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(FRAGMENT_HEADER_SIZE + f.bytes.len());
+    out.push(KIND_FRAGMENT);
+    out.extend_from_slice(&f.msg_id.to_le_bytes());
+    out.push(f.index);
+    out.push(f.count);
+    out.extend_from_slice(f.bytes);
+    out
+}
+
+/// Decode a fragment frame.
+pub fn decode_fragment(frame: &[u8]) -> Option<Fragment<'_>> {
+    if frame.len() < FRAGMENT_HEADER_SIZE || frame[0] != KIND_FRAGMENT {
+        return None;
+    }
+    let f = Fragment {
+        msg_id: u16::from_le_bytes([frame[1], frame[2]]),
+        index: frame[3],
+        count: frame[4],
+        bytes: &frame[FRAGMENT_HEADER_SIZE..],
+    };
+    (f.index < f.count).then_some(f)
+}
+
+/// Encode an MTU advertisement.
+pub fn mtu(mtu: usize) -> Vec<u8> {
+    let mut out = vec![KIND_MTU];
+    out.extend_from_slice(&(mtu.min(u16::MAX as usize) as u16).to_le_bytes());
+    out
+}
+
+/// Decode an MTU advertisement.
+pub fn decode_mtu(frame: &[u8]) -> Option<usize> {
+    match frame {
+        [KIND_MTU, lo, hi] => Some(u16::from_le_bytes([*lo, *hi]) as usize),
+        _ => None,
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (ISO-HDLC, as used by zlib and Ethernet) of `data`.
+pub const fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Extend `crc`, the [`crc32`] of some prefix, by `data`.
+pub const fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut c = !crc;
+    let mut i = 0;
+    while i < data.len() {
+        c = CRC_TABLE[((c ^ data[i] as u32) & 0xFF) as usize] ^ (c >> 8);
+        i += 1;
+    }
+    !c
+}
+
+/// Number of bytes of `name` a frame carries: all of it, or the longest
+/// prefix of at most [`WIRE_MAX_TOPIC`] bytes that ends on a `char`
+/// boundary, so the carried name stays valid UTF-8.
+pub const fn topic_len(name: &str) -> usize {
+    let bytes = name.as_bytes();
+    if bytes.len() <= WIRE_MAX_TOPIC {
+        return bytes.len();
+    }
+    let mut len = WIRE_MAX_TOPIC;
+    while len > 0 && bytes[len] & 0xC0 == 0x80 {
+        len -= 1;
+    }
+    len
+}
+
+/// [`crc32`] of topic `name` as data frames carry it: cut to
+/// [`topic_len`] bytes and without any `MQ_IPC_PREFIX`. No frame carries
+/// the hash itself; it equals [`packet_topic_hash`] of any packet built
+/// for `name`. Being a `const fn`, it lets firmware tables and Rust
+/// constants refer to topics without storing their names:
+///
+/// ```
+/// use mq_ipc::wire::frame::{crc32, packet, packet_topic_hash, topic_hash};
+///
+/// const MOTOR_STATE: u32 = topic_hash("/motor_state");
+/// assert_eq!(MOTOR_STATE, crc32(b"/motor_state"));
+/// assert_eq!(topic_hash("/"), 0x79D3_D2D4);
+///
+/// // A name cut at the wire limit keeps whole characters.
+/// let long = format!("/{}é", "a".repeat(62));
+/// let pkt = packet(&long, &[]);
+/// assert_eq!(pkt.topic_name(), &long[..63]);
+/// assert_eq!(topic_hash(&long), packet_topic_hash(&pkt));
+/// ```
+pub const fn topic_hash(name: &str) -> u32 {
+    crc32(name.as_bytes().split_at(topic_len(name)).0)
+}
+
+/// [`topic_hash`] of the topic name carried by `pkt`.
+pub fn packet_topic_hash(pkt: &WirePacket) -> u32 {
+    crc32(&pkt.topic[..(pkt.topic_len as usize).min(WIRE_MAX_TOPIC)])
+}
+
+fn push_topic(frame: &mut Vec<u8>, topic: &str) {
+    let tlen = topic_len(topic);
+    frame.push(tlen as u8);
+    frame.extend_from_slice(&topic.as_bytes()[..tlen]);
+}
+
+fn read_topic(body: &[u8]) -> Option<String> {
+    let (&tlen, name) = body.split_first()?;
+    if name.len() != tlen as usize {
+        return None;
+    }
+    String::from_utf8(name.to_vec()).ok()
+}
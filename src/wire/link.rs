@@ -0,0 +1,225 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Physical transports for wire frames.
+//!
+//! A [`Link`] moves whole frames (see [`frame`](super::frame)) between
+//! two routers. [`UdpLink`] is the stock implementation; serial ports,
+//! CAN buses and the like implement the same two methods.
+
+use super::{
+    mtu::{MTU_MAX, MTU_MIN, MTU_UDP},
+    qos::PriorityMap,
+};
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    os::{fd::AsRawFd, raw::c_int},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Smallest receive buffer of a [`UdpLink`].
+pub const UDP_MAX_FRAME: usize = 1500;
+
+/// Frame-oriented, bidirectional transport between two routers.
+pub trait Link: Send + Sync {
+    /// Send one frame.
+    fn send(&self, frame: &[u8]) -> io::Result<()>;
+
+    /// Send one frame carrying mq priority `prio`, so links that can
+    /// prioritize on the transport (DSCP, CAN arbitration IDs; see
+    /// [`qos`](super::qos)) do. Defaults to [`send`](Self::send).
+    fn send_prio(&self, frame: &[u8], prio: u32) -> io::Result<()> {
+        let _ = prio;
+        self.send(frame)
+    }
+
+    /// Wait up to `timeout` for the next frame; `Ok(None)` on timeout.
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>>;
+
+    /// Whether frames sent now are expected to reach the peer. Links
+    /// that cannot tell report `true` and rely on `send` failing.
+    fn is_up(&self) -> bool {
+        true
+    }
+
+    /// Largest frame this link carries in one `send`; larger frames are
+    /// fragmented by the router (see [`mtu`](super::mtu)).
+    fn mtu(&self) -> usize {
+        super::mtu::MTU_MAX
+    }
+
+    /// Human-readable name for stats and logs.
+    fn name(&self) -> String {
+        "link".to_string()
+    }
+}
+
+/// [`Link`] over a connected UDP socket: one datagram per frame.
+pub struct UdpLink {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    mtu: usize,
+    dscp: Option<PriorityMap<u8>>,
+    // Last TOS byte set on the socket; held across set + send.
+    tos: Mutex<Option<u8>>,
+}
+
+impl UdpLink {
+    /// Bind to `local` and exchange frames with `peer` only.
+    pub fn connect(local: impl ToSocketAddrs, peer: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(peer)?;
+        let peer = socket.peer_addr()?;
+        Ok(UdpLink {
+            socket,
+            peer,
+            mtu: MTU_UDP,
+            dscp: None,
+            tos: Mutex::new(None),
+        })
+    }
+
+    /// Advertise `mtu` instead of [`MTU_UDP`], e.g. on a path known to
+    /// carry jumbo frames or one with heavy encapsulation.
+    pub fn with_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu.clamp(MTU_MIN, MTU_MAX);
+        self
+    }
+
+    /// Mark each datagram with the DSCP code point `map` gives its
+    /// priority (IP TOS, or the traffic class on IPv6), so the network
+    /// queues frames the way the mq priorities do.
+    pub fn with_dscp(mut self, map: PriorityMap<u8>) -> Self {
+        self.dscp = Some(map);
+        self
+    }
+
+    /// TOS byte currently set on the socket (DSCP in the upper six bits).
+    pub fn tos(&self) -> io::Result<u8> {
+        let (level, name) = self.tos_option();
+        let mut value: c_int = 0;
+        let mut len = std::mem::size_of::<c_int>() as libc::socklen_t;
+        let rc = unsafe {
+            libc::getsockopt(
+                self.socket.as_raw_fd(),
+                level,
+                name,
+                &mut value as *mut c_int as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if rc == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(value as u8)
+    }
+
+    fn tos_option(&self) -> (c_int, c_int) {
+        if self.peer.is_ipv6() {
+            (libc::IPPROTO_IPV6, libc::IPV6_TCLASS)
+        } else {
+            (libc::IPPROTO_IP, libc::IP_TOS)
+        }
+    }
+
+    fn set_tos(&self, tos: u8) -> io::Result<()> {
+        let (level, name) = self.tos_option();
+        let value = tos as c_int;
+        let rc = unsafe {
+            libc::setsockopt(
+                self.socket.as_raw_fd(),
+                level,
+                name,
+                &value as *const c_int as *const libc::c_void,
+                std::mem::size_of::<c_int>() as libc::socklen_t,
+            )
+        };
+        if rc == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Local address, e.g. to learn the port picked for `:0`.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// The underlying socket, e.g. to set socket options.
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+}
+
+impl Link for UdpLink {
+    fn send(&self, frame: &[u8]) -> io::Result<()> {
+        self.socket.send(frame).map(|_| ())
+    }
+
+    fn send_prio(&self, frame: &[u8], prio: u32) -> io::Result<()> {
+        let Some(map) = &self.dscp else {
+            return self.send(frame);
+        };
+        let tos = map.get(prio) << 2;
+        let mut current = self.tos.lock().unwrap();
+        if *current != Some(tos) {
+            self.set_tos(tos)?;
+            *current = Some(tos);
+        }
+        self.send(frame)
+    }
+
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        // A zero timeout would mean "block forever" to the socket.
+        self.socket
+            .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+        let mut buf = vec![0u8; self.mtu.max(UDP_MAX_FRAME)];
+        match self.socket.recv(&mut buf) {
+            Ok(n) => {
+                buf.truncate(n);
+                Ok(Some(buf))
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(None)
+            }
+            // A peer that is not up yet answers with ICMP unreachable.
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    fn name(&self) -> String {
+        format!("udp:{}", self.peer)
+    }
+}
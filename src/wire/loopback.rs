@@ -0,0 +1,303 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! In-process [`Link`] for end-to-end tests of the wire layer.
+//!
+//! [`LoopbackLink::new`] feeds a router's TX straight back into its own
+//! RX, and [`LoopbackLink::pair`] connects two routers. Each end can
+//! impair what it sends with [`Impairments`]: random loss, a fixed
+//! latency, and single-bit corruption. The random number generator is
+//! seeded, so a failing test replays exactly.
+
+use super::{link::Link, mtu::MTU_MAX};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Faults a [`LoopbackLink`] injects into the frames it sends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Impairments {
+    loss: f64,
+    corrupt: f64,
+    latency: Duration,
+    seed: u64,
+}
+
+impl Default for Impairments {
+    fn default() -> Self {
+        Impairments {
+            loss: 0.0,
+            corrupt: 0.0,
+            latency: Duration::ZERO,
+            seed: 0x5eed,
+        }
+    }
+}
+
+impl Impairments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop each frame with probability `p`.
+    pub fn loss(mut self, p: f64) -> Self {
+        self.loss = p.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Flip one random bit of each frame with probability `p`.
+    pub fn corrupt(mut self, p: f64) -> Self {
+        self.corrupt = p.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Deliver every frame `latency` after it was sent.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Seed for the loss and corruption decisions.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Frames sent through one end, by fate.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoopbackStats {
+    pub sent: u64,
+    pub lost: u64,
+    pub corrupted: u64,
+}
+
+#[derive(Default)]
+struct Pipe {
+    queue: Mutex<VecDeque<(Instant, Vec<u8>)>>,
+    ready: Condvar,
+}
+
+/// One end of an in-process link; see the module docs.
+pub struct LoopbackLink {
+    tx: Arc<Pipe>,
+    rx: Arc<Pipe>,
+    impairments: Impairments,
+    mtu: usize,
+    rng: Mutex<u64>,
+    sent: AtomicU64,
+    lost: AtomicU64,
+    corrupted: AtomicU64,
+}
+
+impl LoopbackLink {
+    /// A link whose frames come back to itself.
+    pub fn new(impairments: Impairments) -> Self {
+        let pipe = Arc::new(Pipe::default());
+        Self::end(Arc::clone(&pipe), pipe, impairments)
+    }
+
+    /// Two connected ends; each end applies its own impairments to what
+    /// it sends.
+    pub fn pair(a: Impairments, b: Impairments) -> (Self, Self) {
+        let (ab, ba) = (Arc::new(Pipe::default()), Arc::new(Pipe::default()));
+        (
+            Self::end(Arc::clone(&ab), Arc::clone(&ba), a),
+            Self::end(ba, ab, b),
+        )
+    }
+
+    fn end(tx: Arc<Pipe>, rx: Arc<Pipe>, impairments: Impairments) -> Self {
+        LoopbackLink {
+            tx,
+            rx,
+            impairments,
+            mtu: MTU_MAX,
+            // xorshift must not start at zero.
+            rng: Mutex::new(impairments.seed | 1),
+            sent: AtomicU64::new(0),
+            lost: AtomicU64::new(0),
+            corrupted: AtomicU64::new(0),
+        }
+    }
+
+    /// Refuse to send frames longer than `mtu`, like a real link would.
+    pub fn with_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu;
+        self
+    }
+
+    pub fn stats(&self) -> LoopbackStats {
+        LoopbackStats {
+            sent: self.sent.load(Ordering::Relaxed),
+            lost: self.lost.load(Ordering::Relaxed),
+            corrupted: self.corrupted.load(Ordering::Relaxed),
+        }
+    }
+
+    fn next_random(&self) -> u64 {
+        let mut state = self.rng.lock().unwrap();
+        let mut x = *state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        *state = x;
+        x
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn roll(&self) -> f64 {
+        (self.next_random() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Link for LoopbackLink {
+    fn send(&self, frame: &[u8]) -> io::Result<()> {
+        if frame.len() > self.mtu {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} byte frame exceeds MTU {}", frame.len(), self.mtu),
+            ));
+        }
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        if self.roll() < self.impairments.loss {
+            self.lost.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        let mut frame = frame.to_vec();
+        if !frame.is_empty() && self.roll() < self.impairments.corrupt {
+            let bit = self.next_random() as usize % (frame.len() * 8);
+            frame[bit / 8] ^= 1 << (bit % 8);
+            self.corrupted.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let at = Instant::now() + self.impairments.latency;
+        self.tx.queue.lock().unwrap().push_back((at, frame));
+        self.tx.ready.notify_all();
+        Ok(())
+    }
+
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        let deadline = Instant::now() + timeout;
+        let mut queue = self.rx.queue.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            // Latency is the same for every frame, so the front is due first.
+            let wait_until = match queue.front() {
+                Some((at, _)) if *at <= now => return Ok(queue.pop_front().map(|(_, f)| f)),
+                Some((at, _)) => (*at).min(deadline),
+                None => deadline,
+            };
+            if now >= deadline {
+                return Ok(None);
+            }
+            queue = self
+                .rx
+                .ready
+                .wait_timeout(queue, wait_until - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    fn name(&self) -> String {
+        "loopback".to_string()
+    }
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+    use crate::{
+        cleanup::TempTopic,
+        wire::{
+            router::{Router, RouterOptions},
+            WireTx,
+        },
+    };
+    use std::thread;
+
+    #[test]
+    fn impairs_as_configured() {
+        let (a, b) = LoopbackLink::pair(
+            Impairments::new().corrupt(1.0),
+            Impairments::new()
+                .loss(1.0)
+                .latency(Duration::from_millis(30)),
+        );
+        a.send(&[0u8; 4]).unwrap();
+        let got = b.recv(Duration::from_millis(100)).unwrap().unwrap();
+        assert_eq!(got.iter().map(|b| b.count_ones()).sum::<u32>(), 1);
+        assert_eq!(a.stats().corrupted, 1);
+
+        b.send(&[1]).unwrap();
+        assert_eq!(a.recv(Duration::from_millis(50)).unwrap(), None);
+        assert_eq!(b.stats().lost, 1);
+
+        let slow = LoopbackLink::new(Impairments::new().latency(Duration::from_millis(30)));
+        slow.send(&[2]).unwrap();
+        assert_eq!(slow.recv(Duration::ZERO).unwrap(), None);
+        assert_eq!(
+            slow.recv(Duration::from_millis(200)).unwrap(),
+            Some(vec![2])
+        );
+    }
+
+    #[test]
+    fn wire_tx_round_trips_through_router() {
+        let tx = TempTopic::new("/mq_ipc_test_loopback_tx_");
+        let local = TempTopic::new("/mq_ipc_test_loopback_local_");
+
+        let wire = WireTx::<u32>::with_tx_topic(local.name(), tx.name(), 8).unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_cb = Arc::clone(&seen);
+        wire.local()
+            .subscribe(move |v: u32| seen_cb.lock().unwrap().push(v));
+
+        let _router = Router::new(
+            Arc::new(LoopbackLink::new(Impairments::new())),
+            &RouterOptions::new().tx_topic(tx.name()).maxmsg(8),
+        )
+        .unwrap();
+        wire.publish(&42).unwrap();
+
+        // Once from the local publish, once back through the link.
+        for _ in 0..100 {
+            if seen.lock().unwrap().len() == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![42, 42]);
+    }
+}
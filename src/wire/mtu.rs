@@ -0,0 +1,250 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Link MTUs and fragmentation.
+//!
+//! Every [`Link`] advertises the largest frame it carries in one send
+//! through [`Link::mtu`], e.g. [`MTU_CAN_CLASSIC`] for a classic CAN
+//! link, [`MTU_UDP`] for UDP, or whatever a serial link is configured
+//! with. A [`Router`](super::router::Router) wraps its link in an
+//! [`MtuLink`]. The wrapper tells the peer how large a frame this side
+//! can take, splits larger frames into fragments, and reassembles the
+//! fragments it receives. The fragment size is then set by the smaller
+//! of the two ends, so the same topics run unchanged over CAN, serial
+//! and UDP.
+
+use super::{
+    frame::{self, Fragment},
+    link::Link,
+};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicU16, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Classic CAN data field.
+pub const MTU_CAN_CLASSIC: usize = 8;
+/// CAN FD data field.
+pub const MTU_CAN_FD: usize = 64;
+/// Safe UDP payload on Ethernet paths with some encapsulation.
+pub const MTU_UDP: usize = 1400;
+/// Largest MTU that can be advertised; also the default of [`Link::mtu`].
+pub const MTU_MAX: usize = u16::MAX as usize;
+
+/// Smallest usable MTU: a fragment header plus one byte.
+pub const MTU_MIN: usize = frame::FRAGMENT_HEADER_SIZE + 1;
+
+/// Partially received frames are dropped after this long.
+pub const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+struct Partial {
+    parts: Vec<Option<Vec<u8>>>,
+    missing: usize,
+    started: Instant,
+}
+
+/// Link wrapper that fragments and reassembles against the negotiated
+/// MTU; see the module docs.
+pub struct MtuLink {
+    inner: Arc<dyn Link>,
+    peer_mtu: AtomicUsize,
+    next_id: AtomicU16,
+    partial: Mutex<HashMap<u16, Partial>>,
+}
+
+impl MtuLink {
+    pub fn new(inner: Arc<dyn Link>) -> Self {
+        MtuLink {
+            inner,
+            peer_mtu: AtomicUsize::new(0),
+            next_id: AtomicU16::new(0),
+            partial: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &Arc<dyn Link> {
+        &self.inner
+    }
+
+    /// Receive MTU the peer advertised, if it has.
+    pub fn peer_mtu(&self) -> Option<usize> {
+        match self.peer_mtu.load(Ordering::Relaxed) {
+            0 => None,
+            mtu => Some(mtu),
+        }
+    }
+
+    /// Tell the peer how large a frame this side receives.
+    pub fn advertise(&self) -> io::Result<()> {
+        self.inner.send(&frame::mtu(self.inner.mtu()))
+    }
+
+    fn reassemble(&self, f: &Fragment<'_>, now: Instant) -> Option<Vec<u8>> {
+        let mut partial = self.partial.lock().unwrap();
+        partial.retain(|_, p| now.duration_since(p.started) < REASSEMBLY_TIMEOUT);
+
+        let entry = partial.entry(f.msg_id).or_insert_with(|| Partial {
+            parts: vec![None; f.count as usize],
+            missing: f.count as usize,
+            started: now,
+        });
+        // A reused ID with a different shape starts over.
+        if entry.parts.len() != f.count as usize {
+            *entry = Partial {
+                parts: vec![None; f.count as usize],
+                missing: f.count as usize,
+                started: now,
+            };
+        }
+        let slot = &mut entry.parts[f.index as usize];
+        if slot.is_none() {
+            *slot = Some(f.bytes.to_vec());
+            entry.missing -= 1;
+        }
+        if entry.missing > 0 {
+            return None;
+        }
+        let done = partial.remove(&f.msg_id)?;
+        Some(done.parts.into_iter().flatten().flatten().collect())
+    }
+}
+
+impl Link for MtuLink {
+    fn send(&self, bytes: &[u8]) -> io::Result<()> {
+        self.send_prio(bytes, 0)
+    }
+
+    fn send_prio(&self, bytes: &[u8], prio: u32) -> io::Result<()> {
+        let mtu = self.mtu();
+        if bytes.len() <= mtu {
+            return self.inner.send_prio(bytes, prio);
+        }
+
+        let piece = mtu - frame::FRAGMENT_HEADER_SIZE;
+        let count = bytes.len().div_ceil(piece);
+        if count > u8::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} byte frame needs more than 255 fragments at MTU {mtu}",
+                    bytes.len()
+                ),
+            ));
+        }
+        let msg_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        for (index, chunk) in bytes.chunks(piece).enumerate() {
+            self.inner.send_prio(
+                &frame::fragment(&Fragment {
+                    msg_id,
+                    index: index as u8,
+                    count: count as u8,
+                    bytes: chunk,
+                }),
+                prio,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let now = Instant::now();
+            let Some(bytes) = self.inner.recv(deadline.saturating_duration_since(now))? else {
+                return Ok(None);
+            };
+            match frame::kind(&bytes) {
+                Some(frame::KIND_MTU) => {
+                    if let Some(mtu) = frame::decode_mtu(&bytes) {
+                        self.peer_mtu.store(mtu, Ordering::Relaxed);
+                    }
+                }
+                Some(frame::KIND_FRAGMENT) => {
+                    if let Some(whole) = frame::decode_fragment(&bytes)
+                        .and_then(|f| self.reassemble(&f, Instant::now()))
+                    {
+                        return Ok(Some(whole));
+                    }
+                }
+                _ => return Ok(Some(bytes)),
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn is_up(&self) -> bool {
+        self.inner.is_up()
+    }
+
+    /// The smaller of the wrapped link's MTU and the peer's advertised one.
+    fn mtu(&self) -> usize {
+        let own = self.inner.mtu();
+        self.peer_mtu()
+            .map_or(own, |peer| own.min(peer))
+            .max(MTU_MIN)
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::loopback::{Impairments, LoopbackLink};
+
+    #[test]
+    fn fragments_to_the_smaller_mtu() {
+        let (a, b) = LoopbackLink::pair(Impairments::new(), Impairments::new());
+        let a = MtuLink::new(Arc::new(a.with_mtu(MTU_UDP)));
+        let b = MtuLink::new(Arc::new(b.with_mtu(MTU_CAN_CLASSIC)));
+        assert_eq!(a.mtu(), MTU_UDP);
+
+        b.advertise().unwrap();
+        a.send(&[0x01; 4]).unwrap();
+        // B's recv consumes nothing but A's frame; A needs a recv to
+        // learn B's MTU.
+        assert_eq!(
+            b.recv(Duration::from_millis(50)).unwrap(),
+            Some(vec![0x01; 4])
+        );
+        assert_eq!(a.recv(Duration::from_millis(50)).unwrap(), None);
+        assert_eq!(a.peer_mtu(), Some(MTU_CAN_CLASSIC));
+        assert_eq!(a.mtu(), MTU_CAN_CLASSIC);
+
+        let big: Vec<u8> = (0..200u8).collect();
+        a.send(&big).unwrap();
+        assert_eq!(b.recv(Duration::from_millis(50)).unwrap(), Some(big));
+
+        let too_big = vec![0u8; 3 * 256];
+        assert!(a.send(&too_big).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Transport-level prioritization of wire frames.
+//!
+//! The router knows the mq priority of every frame it forwards (or the
+//! override from [`RouterOptions::topic_priority`]) and hands it to
+//! [`Link::send_prio`]. A [`PriorityMap`] turns that priority into what
+//! the transport understands: a DSCP code point for [`UdpLink`], or a CAN
+//! arbitration ID for a CAN link, where the lower ID wins the bus.
+//!
+//! ```
+//! use mq_ipc::wire::qos::{PriorityMap, DSCP_EF};
+//!
+//! // Everything at priority 10 and up is expedited, the rest best effort.
+//! let map = PriorityMap::new(0u8).level(10, DSCP_EF);
+//! assert_eq!(map.get(3), 0);
+//! assert_eq!(map.get(12), DSCP_EF);
+//! ```
+//!
+//! [`RouterOptions::topic_priority`]: super::router::RouterOptions::topic_priority
+//! [`Link::send_prio`]: super::link::Link::send_prio
+//! [`UdpLink`]: super::link::UdpLink
+
+use crate::URGENT_PRIORITY;
+
+/// Best effort (CS0).
+pub const DSCP_BEST_EFFORT: u8 = 0;
+/// Assured forwarding class 2, low drop (AF21).
+pub const DSCP_AF21: u8 = 18;
+/// Assured forwarding class 4, low drop (AF41).
+pub const DSCP_AF41: u8 = 34;
+/// Expedited forwarding (EF).
+pub const DSCP_EF: u8 = 46;
+
+/// Priority threshold of the middle class used by the stock maps.
+pub const PRIORITY_HIGH: u32 = 16;
+
+/// Step function from mq priority to a transport value.
+///
+/// Each [`level`](Self::level) applies from its priority up to the next
+/// level; priorities below every level get the default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PriorityMap<T> {
+    default: T,
+    // Sorted by threshold, ascending.
+    levels: Vec<(u32, T)>,
+}
+
+impl<T: Copy> PriorityMap<T> {
+    /// Map every priority to `default`.
+    pub fn new(default: T) -> Self {
+        PriorityMap {
+            default,
+            levels: Vec::new(),
+        }
+    }
+
+    /// Use `value` for priorities of `min_prio` and up (until the next
+    /// level). Setting the same threshold twice replaces it.
+    pub fn level(mut self, min_prio: u32, value: T) -> Self {
+        match self.levels.binary_search_by_key(&min_prio, |(p, _)| *p) {
+            Ok(i) => self.levels[i].1 = value,
+            Err(i) => self.levels.insert(i, (min_prio, value)),
+        }
+        self
+    }
+
+    /// Value for a frame sent at `prio`.
+    pub fn get(&self, prio: u32) -> T {
+        match self.levels.partition_point(|(p, _)| *p <= prio) {
+            0 => self.default,
+            i => self.levels[i - 1].1,
+        }
+    }
+}
+
+impl PriorityMap<u8> {
+    /// Stock DSCP classes: best effort for priority 0, AF21 from 1,
+    /// AF41 from [`PRIORITY_HIGH`] and EF for [`URGENT_PRIORITY`].
+    pub fn dscp() -> Self {
+        PriorityMap::new(DSCP_BEST_EFFORT)
+            .level(1, DSCP_AF21)
+            .level(PRIORITY_HIGH, DSCP_AF41)
+            .level(URGENT_PRIORITY, DSCP_EF)
+    }
+}
+
+impl PriorityMap<u32> {
+    /// Stock CAN arbitration IDs: `base` for [`URGENT_PRIORITY`],
+    /// `base + 1` from [`PRIORITY_HIGH`], `base + 2` from 1 and
+    /// `base + 3` for priority 0, so urgent frames win arbitration.
+    pub fn can_ids(base: u32) -> Self {
+        PriorityMap::new(base + 3)
+            .level(1, base + 2)
+            .level(PRIORITY_HIGH, base + 1)
+            .level(URGENT_PRIORITY, base)
+    }
+}
+
+impl Default for PriorityMap<u8> {
+    fn default() -> Self {
+        Self::dscp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::link::{Link, UdpLink};
+
+    #[test]
+    fn maps_priority_to_classes() {
+        let dscp = PriorityMap::dscp();
+        assert_eq!(dscp.get(0), DSCP_BEST_EFFORT);
+        assert_eq!(dscp.get(5), DSCP_AF21);
+        assert_eq!(dscp.get(PRIORITY_HIGH), DSCP_AF41);
+        assert_eq!(dscp.get(u32::MAX), DSCP_EF);
+
+        let can = PriorityMap::can_ids(0x100);
+        assert_eq!(can.get(URGENT_PRIORITY), 0x100);
+        assert_eq!(can.get(0), 0x103);
+        assert!(can.get(PRIORITY_HIGH) < can.get(1));
+
+        let custom = PriorityMap::new(1u8).level(4, 2).level(4, 3);
+        assert_eq!(custom.get(3), 1);
+        assert_eq!(custom.get(4), 3);
+    }
+
+    #[test]
+    fn udp_link_marks_tos() {
+        let rx = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tx = UdpLink::connect("127.0.0.1:0", rx.local_addr().unwrap())
+            .unwrap()
+            .with_dscp(PriorityMap::dscp());
+        tx.send_prio(b"x", URGENT_PRIORITY).unwrap();
+        assert_eq!(tx.tos().unwrap(), DSCP_EF << 2);
+        tx.send_prio(b"x", 0).unwrap();
+        assert_eq!(tx.tos().unwrap(), 0);
+    }
+}
@@ -0,0 +1,1059 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Bridge between the local `/ipc_tx` topic and a [`Link`].
+//!
+//! A [`Router`] forwards every [`WirePacket`] mirrored by
+//! [`WireTx`](super::WireTx) over its link, and republishes frames
+//! arriving from the peer onto the local topic they name (when that
+//! topic exists on this host).
+//!
+//! Each router accounts the bytes it sends per topic. With a
+//! [`LinkBudget`] it also caps the link's throughput: the budget is
+//! enforced per window, and when the previous window's demand exceeded
+//! it, topics are shed lowest-priority first so the important ones keep
+//! their full rate.
+//!
+//! With [`RouterOptions::store_and_forward`], frames that cannot go out
+//! because the link is down are buffered and flushed, in order, once it
+//! is back.
+//!
+//! In pull mode ([`RouterOptions::pull`]) a router forwards only the
+//! topics its peer asked for with [`Router::subscribe_remote`], each at
+//! most at the requested rate, so a small MCU on the other end receives
+//! what it consumes rather than everything in `/ipc_tx`.
+//!
+//! Data frames reach the link through [`Link::send_prio`] with the
+//! topic's priority, so transports that can prioritize (see
+//! [`qos`](super::qos)) agree with the mq queues. Frames flushed from the
+//! store go out at priority 0.
+//!
+//! With [`RouterOptions::namespace`] topic names are rewritten at the
+//! link: local `/motor_state` goes out as `/robot1/motor_state`, and a
+//! peer's `/robot2/motor_state` lands on the local queue
+//! `/robot2.motor_state` (see [`federation`]).
+
+use super::{
+    capture::CaptureLink,
+    command::{CommandReply, Commands},
+    failover::{FailoverLink, FailoverOptions},
+    federation, frame,
+    link::Link,
+    mtu::MtuLink,
+    store::{Store, StoreOptions},
+    transfer::{TransferOptions, TransferReport, Transfers},
+    Topic, WirePacket, IPC_TX_TOPIC_NAME, WIRE_MAX_PAYLOAD, WIRE_MAX_TOPIC,
+};
+use crate::{current_priority, MqTopic, Msg};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    os::raw::c_long,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Message type used when republishing received frames, matching what
+/// [`WireTx`](super::WireTx) publishes locally.
+pub const RX_MSG_TYPE: u16 = 1;
+
+/// Interval over which [`TopicBandwidth::bytes_per_sec`] is measured.
+pub const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// How often [`Router::subscribe_remote`] requests and the MTU
+/// advertisement are repeated, so a peer that restarted learns them again.
+pub const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+const RX_POLL: Duration = Duration::from_millis(50);
+
+/// Throughput cap for one link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinkBudget {
+    bytes_per_sec: u64,
+    window: Duration,
+}
+
+impl LinkBudget {
+    /// Allow `bytes_per_sec` of frame bytes, enforced over 100 ms windows.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        LinkBudget {
+            bytes_per_sec,
+            window: Duration::from_millis(100),
+        }
+    }
+
+    /// Enforcement window. Shorter windows smooth bursts at the cost of
+    /// reacting to every small spike.
+    pub fn window(mut self, window: Duration) -> Self {
+        assert!(!window.is_zero(), "budget window must be non-zero");
+        self.window = window;
+        self
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    fn per_window(&self) -> u64 {
+        (self.bytes_per_sec as f64 * self.window.as_secs_f64()) as u64
+    }
+}
+
+/// Configuration for [`Router::new`].
+#[derive(Clone, Debug)]
+pub struct RouterOptions {
+    tx_topic: String,
+    maxmsg: c_long,
+    budget: Option<LinkBudget>,
+    priorities: HashMap<String, u32>,
+    rx: bool,
+    store: Option<StoreOptions>,
+    pull: bool,
+    forward: bool,
+    capture: Option<PathBuf>,
+    namespace: Option<String>,
+}
+
+impl Default for RouterOptions {
+    fn default() -> Self {
+        RouterOptions {
+            tx_topic: IPC_TX_TOPIC_NAME.to_string(),
+            maxmsg: crate::defaults::maxmsg(),
+            budget: None,
+            priorities: HashMap::new(),
+            rx: true,
+            store: None,
+            pull: false,
+            forward: true,
+            capture: None,
+            namespace: None,
+        }
+    }
+}
+
+impl RouterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward packets from `name` instead of [`IPC_TX_TOPIC_NAME`].
+    pub fn tx_topic(mut self, name: &str) -> Self {
+        self.tx_topic = name.to_string();
+        self
+    }
+
+    /// Whether to forward `/ipc_tx` at all (default on). Tools that only
+    /// send commands or files turn it off so they do not take packets
+    /// from the host's real router.
+    pub fn forward(mut self, on: bool) -> Self {
+        self.forward = on;
+        self
+    }
+
+    /// Record all link traffic into a capture file at `path`; see
+    /// [`capture`](super::capture).
+    pub fn capture(mut self, path: impl AsRef<Path>) -> Self {
+        self.capture = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Depth of the `/ipc_tx` queue; defaults to
+    /// [`defaults::maxmsg`](crate::defaults::maxmsg).
+    pub fn maxmsg(mut self, maxmsg: c_long) -> Self {
+        self.maxmsg = maxmsg;
+        self
+    }
+
+    /// Cap the bytes sent over the link.
+    pub fn budget(mut self, budget: LinkBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Priority of `topic` on this link; higher survives a tight budget
+    /// longer and is what the link maps to DSCP or a CAN ID (see
+    /// [`qos`](super::qos)). Topics without an entry use the mq priority
+    /// they were mirrored with.
+    pub fn topic_priority(mut self, topic: &str, prio: u32) -> Self {
+        self.priorities.insert(topic.to_string(), prio);
+        self
+    }
+
+    /// Buffer frames while the link is down instead of dropping them.
+    /// Flushed frames are not throttled by the budget again.
+    pub fn store_and_forward(mut self, store: StoreOptions) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Forward only topics the peer subscribed to, instead of every
+    /// packet on `/ipc_tx`.
+    pub fn pull(mut self, on: bool) -> Self {
+        self.pull = on;
+        self
+    }
+
+    /// Whether to republish frames received from the peer (default on).
+    pub fn rx(mut self, on: bool) -> Self {
+        self.rx = on;
+        self
+    }
+
+    /// Prefix outgoing topics with `/namespace` and map prefixed incoming
+    /// ones to local queues; see [`federation::export_name`] and
+    /// [`federation::import_name`].
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.trim_matches('/').to_string());
+        self
+    }
+}
+
+/// Per-topic traffic on a link, as reported by [`Router::stats`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TopicBandwidth {
+    pub topic: String,
+    pub priority: u32,
+    /// Frames sent.
+    pub frames: u64,
+    /// Frame bytes sent.
+    pub bytes: u64,
+    /// Frames shed by the budget.
+    pub dropped: u64,
+    /// Frames not sent because the peer did not ask for them (pull mode).
+    pub filtered: u64,
+    /// Frames sent late from the store-and-forward buffer, also counted
+    /// in `frames`.
+    pub flushed: u64,
+    /// Send rate over the last complete [`RATE_WINDOW`].
+    pub bytes_per_sec: f64,
+}
+
+/// Snapshot of a router's link traffic.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkStats {
+    pub link: String,
+    /// MTU frames are fragmented to, as negotiated with the peer.
+    pub mtu: usize,
+    pub budget: Option<u64>,
+    pub tx_bytes: u64,
+    pub rx_frames: u64,
+    pub rx_bytes: u64,
+    /// Frames that could not be decoded or sent.
+    pub errors: u64,
+    /// Frames waiting in the store-and-forward buffer.
+    pub stored: usize,
+    /// Buffered frames discarded for exceeding the age limit.
+    pub expired: u64,
+    /// Buffered frames discarded to make room for newer ones.
+    pub overflowed: u64,
+    /// Sorted by topic name.
+    pub topics: Vec<TopicBandwidth>,
+}
+
+#[derive(Default)]
+struct Meter {
+    priority: u32,
+    frames: u64,
+    bytes: u64,
+    dropped: u64,
+    filtered: u64,
+    flushed: u64,
+    window_bytes: u64,
+    rate: f64,
+}
+
+struct Accounting {
+    topics: BTreeMap<String, Meter>,
+    window_start: Instant,
+}
+
+impl Accounting {
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < RATE_WINDOW {
+            return;
+        }
+        // An idle stretch longer than one window reads as zero.
+        let secs = elapsed.as_secs_f64();
+        let stale = elapsed >= RATE_WINDOW * 2;
+        for meter in self.topics.values_mut() {
+            meter.rate = if stale {
+                0.0
+            } else {
+                meter.window_bytes as f64 / secs
+            };
+            meter.window_bytes = 0;
+        }
+        self.window_start = now;
+    }
+}
+
+/// Windowed budget enforcement with priority-ordered shedding.
+struct Shaper {
+    budget: LinkBudget,
+    window_start: Instant,
+    sent: u64,
+    demand: BTreeMap<u32, u64>,
+    cutoff: u32,
+}
+
+impl Shaper {
+    fn new(budget: LinkBudget, now: Instant) -> Self {
+        Shaper {
+            budget,
+            window_start: now,
+            sent: 0,
+            demand: BTreeMap::new(),
+            cutoff: 0,
+        }
+    }
+
+    /// Whether a frame of `len` bytes at `prio` may go out at `now`.
+    fn admit(&mut self, prio: u32, len: u64, now: Instant) -> bool {
+        let per_window = self.budget.per_window();
+        if now.duration_since(self.window_start) >= self.budget.window {
+            self.cutoff = cutoff_for(&self.demand, per_window);
+            self.demand.clear();
+            self.sent = 0;
+            self.window_start = now;
+        }
+
+        *self.demand.entry(prio).or_default() += len;
+        if prio < self.cutoff || self.sent + len > per_window {
+            return false;
+        }
+        self.sent += len;
+        true
+    }
+}
+
+/// Lowest priority that still fits when `demand` is served from the
+/// highest priority down.
+fn cutoff_for(demand: &BTreeMap<u32, u64>, per_window: u64) -> u32 {
+    let mut total = 0u64;
+    for (&prio, &bytes) in demand.iter().rev() {
+        total += bytes;
+        if total >= per_window {
+            return prio;
+        }
+    }
+    0
+}
+
+/// A topic the peer asked for.
+struct Pull {
+    period: Option<Duration>,
+    last: Option<Instant>,
+}
+
+struct Shared {
+    link: Arc<dyn Link>,
+    mtu: Arc<MtuLink>,
+    priorities: HashMap<String, u32>,
+    namespace: Option<String>,
+    shaper: Option<Mutex<Shaper>>,
+    store: Option<Mutex<Store>>,
+    pull: Option<Mutex<HashMap<String, Pull>>>,
+    requested: Mutex<BTreeMap<String, Option<Duration>>>,
+    commands: Arc<Commands>,
+    transfers: Transfers,
+    accounting: Mutex<Accounting>,
+    rx_frames: AtomicU64,
+    rx_bytes: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Shared {
+    fn forward(&self, pkt: &WirePacket) {
+        let topic = pkt.topic_name();
+        let prio = self
+            .priorities
+            .get(&topic)
+            .copied()
+            .unwrap_or_else(|| current_priority().unwrap_or(0));
+        let now = Instant::now();
+        if let Some(pull) = &self.pull
+            && !wanted(&mut pull.lock().unwrap(), &topic, now)
+        {
+            let mut acc = self.accounting.lock().unwrap();
+            acc.roll(now);
+            let meter = acc.topics.entry(topic).or_default();
+            meter.priority = prio;
+            meter.filtered += 1;
+            return;
+        }
+        let bytes = match &self.namespace {
+            Some(ns) => {
+                let name = federation::export_name(ns, &topic);
+                if name.len() > WIRE_MAX_TOPIC {
+                    self.errors.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                let plen = (pkt.payload_len as usize).min(WIRE_MAX_PAYLOAD);
+                frame::encode(&frame::packet(&name, &pkt.data[..plen]))
+            }
+            None => frame::encode(pkt),
+        };
+        let len = bytes.len() as u64;
+
+        let admitted = match &self.shaper {
+            Some(shaper) => shaper.lock().unwrap().admit(prio, len, now),
+            None => true,
+        };
+        let sent = admitted && self.transmit(&bytes, prio);
+        if admitted && !sent && self.store.is_none() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut acc = self.accounting.lock().unwrap();
+        acc.roll(now);
+        let meter = acc.topics.entry(topic).or_default();
+        meter.priority = prio;
+        if sent {
+            meter.frames += 1;
+            meter.bytes += len;
+            meter.window_bytes += len;
+        } else if !admitted {
+            meter.dropped += 1;
+        }
+    }
+
+    /// Send `bytes` now, or buffer them behind anything already stored.
+    /// Returns whether they went out.
+    fn transmit(&self, bytes: &[u8], prio: u32) -> bool {
+        let Some(store) = &self.store else {
+            return self.link.send_prio(bytes, prio).is_ok();
+        };
+        let mut store = store.lock().unwrap();
+        if self.link.is_up() {
+            self.flush(&mut store);
+            if store.is_empty() && self.link.send_prio(bytes, prio).is_ok() {
+                return true;
+            }
+        }
+        if store.push(bytes).is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        false
+    }
+
+    fn flush(&self, store: &mut Store) {
+        let res = store.drain(|bytes| {
+            self.link.send(bytes)?;
+            let topic = frame::decode(bytes)
+                .map(|pkt| pkt.topic_name())
+                .unwrap_or_default();
+            let len = bytes.len() as u64;
+            let mut acc = self.accounting.lock().unwrap();
+            acc.roll(Instant::now());
+            let meter = acc.topics.entry(topic).or_default();
+            meter.frames += 1;
+            meter.flushed += 1;
+            meter.bytes += len;
+            meter.window_bytes += len;
+            Ok(())
+        });
+        if res.is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn control(&self, bytes: &[u8]) {
+        let (Some(pull), Some(control)) = (&self.pull, frame::decode_control(bytes)) else {
+            // Not in pull mode: everything is forwarded anyway.
+            return;
+        };
+        let mut pull = pull.lock().unwrap();
+        match control {
+            frame::Control::Subscribe { topic, period } => {
+                let topic = self.local_name(topic);
+                let entry = pull.entry(topic).or_insert(Pull { period, last: None });
+                entry.period = period;
+            }
+            frame::Control::Unsubscribe { topic } => {
+                pull.remove(&self.local_name(topic));
+            }
+        }
+    }
+
+    /// Local queue name for a topic named `wire` on the link.
+    fn local_name(&self, wire: String) -> String {
+        match &self.namespace {
+            Some(ns) => federation::import_name(ns, &wire),
+            None => wire,
+        }
+    }
+
+    fn resubscribe(&self) {
+        let _ = self.mtu.advertise();
+        let requested = self.requested.lock().unwrap().clone();
+        for (topic, period) in requested {
+            let _ = self.link.send(&frame::subscribe(&topic, period));
+        }
+    }
+
+    /// Flush the store if the link came back without new traffic.
+    fn poll_store(&self) {
+        if let Some(store) = &self.store
+            && self.link.is_up()
+        {
+            let mut store = store.lock().unwrap();
+            if !store.is_empty() {
+                self.flush(&mut store);
+            }
+        }
+    }
+
+    fn deliver(&self, bytes: &[u8], cache: &mut HashMap<String, MqTopic>) {
+        self.rx_frames.fetch_add(1, Ordering::Relaxed);
+        self.rx_bytes
+            .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        match frame::kind(bytes) {
+            Some(frame::KIND_DATA) => {}
+            Some(frame::KIND_HEARTBEAT) => return,
+            Some(frame::KIND_SUBSCRIBE | frame::KIND_UNSUBSCRIBE) => {
+                self.control(bytes);
+                return;
+            }
+            Some(frame::KIND_COMMAND) => {
+                self.commands.on_command(&self.link, bytes);
+                return;
+            }
+            Some(frame::KIND_COMMAND_STATUS) => {
+                self.commands.on_status(bytes);
+                return;
+            }
+            Some(
+                frame::KIND_FILE_OFFER
+                | frame::KIND_FILE_ACCEPT
+                | frame::KIND_FILE_CHUNK
+                | frame::KIND_FILE_ACK,
+            ) => {
+                self.transfers.on_frame(&*self.link, bytes);
+                return;
+            }
+            _ => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        let Some(pkt) = frame::decode(bytes) else {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        let name = self.local_name(pkt.topic_name());
+        if !cache.contains_key(&name) {
+            // Topics nobody serves here are skipped, not created.
+            match MqTopic::open_existing(&name) {
+                Ok(Some(topic)) => {
+                    cache.insert(name.clone(), topic);
+                }
+                _ => return,
+            }
+        }
+        let prio = self.priorities.get(&name).copied().unwrap_or(0);
+        let msg = Msg::new(RX_MSG_TYPE, &pkt.data[..pkt.payload_len as usize]);
+        if cache[&name].publish(&msg, prio).is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Whether a packet for `topic` should go out at `now` under the
+/// peer's subscriptions, and if so mark it sent.
+fn wanted(pull: &mut HashMap<String, Pull>, topic: &str, now: Instant) -> bool {
+    let Some(sub) = pull.get_mut(topic) else {
+        return false;
+    };
+    if let (Some(period), Some(last)) = (sub.period, sub.last)
+        && now.duration_since(last) < period
+    {
+        return false;
+    }
+    sub.last = Some(now);
+    true
+}
+
+/// Forwards `/ipc_tx` over a [`Link`] and republishes what the peer sends.
+///
+/// Dropping the router stops both directions.
+pub struct Router {
+    shared: Arc<Shared>,
+    _tx: Option<Topic<WirePacket>>,
+    running: Arc<AtomicBool>,
+    rx: Option<thread::JoinHandle<()>>,
+}
+
+impl Router {
+    pub fn new(link: Arc<dyn Link>, opts: &RouterOptions) -> io::Result<Self> {
+        let link: Arc<dyn Link> = match &opts.capture {
+            Some(path) => Arc::new(CaptureLink::new(link, path)?),
+            None => link,
+        };
+        let mtu = Arc::new(MtuLink::new(link));
+        let link: Arc<dyn Link> = mtu.clone();
+        let now = Instant::now();
+        let shared = Arc::new(Shared {
+            link,
+            mtu,
+            priorities: opts.priorities.clone(),
+            namespace: opts.namespace.clone(),
+            shaper: opts.budget.map(|b| Mutex::new(Shaper::new(b, now))),
+            store: match &opts.store {
+                Some(store) => Some(Mutex::new(Store::open(store)?)),
+                None => None,
+            },
+            pull: opts.pull.then(|| Mutex::new(HashMap::new())),
+            requested: Mutex::new(BTreeMap::new()),
+            commands: Arc::default(),
+            transfers: Transfers::default(),
+            accounting: Mutex::new(Accounting {
+                topics: BTreeMap::new(),
+                window_start: now,
+            }),
+            rx_frames: AtomicU64::new(0),
+            rx_bytes: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        });
+
+        let tx = if opts.forward {
+            let tx = Topic::<WirePacket>::new(&opts.tx_topic, opts.maxmsg)?;
+            let fwd = Arc::clone(&shared);
+            tx.subscribe(move |pkt: WirePacket| fwd.forward(&pkt));
+            Some(tx)
+        } else {
+            None
+        };
+
+        let _ = shared.mtu.advertise();
+        let running = Arc::new(AtomicBool::new(true));
+        let rx_enabled = opts.rx;
+        let rx = {
+            let shared = Arc::clone(&shared);
+            let running = Arc::clone(&running);
+            thread::spawn(move || {
+                let mut cache = HashMap::new();
+                let mut last_resubscribe = Instant::now();
+                while running.load(Ordering::Relaxed) {
+                    shared.poll_store();
+                    shared.commands.expire(Instant::now());
+                    if last_resubscribe.elapsed() >= RESUBSCRIBE_INTERVAL {
+                        shared.resubscribe();
+                        last_resubscribe = Instant::now();
+                    }
+                    if !rx_enabled {
+                        thread::sleep(RX_POLL);
+                        continue;
+                    }
+                    match shared.link.recv(RX_POLL) {
+                        Ok(Some(bytes)) => shared.deliver(&bytes, &mut cache),
+                        Ok(None) => {}
+                        Err(err) => {
+                            eprintln!("wire rx error on {}: {err}", shared.link.name());
+                            thread::sleep(RX_POLL);
+                        }
+                    }
+                }
+            })
+        };
+
+        Ok(Router {
+            shared,
+            _tx: tx,
+            running,
+            rx: Some(rx),
+        })
+    }
+
+    /// Route over `primary`, falling back to `backup` while it is down;
+    /// see [`FailoverLink`].
+    pub fn with_failover(
+        primary: Arc<dyn Link>,
+        backup: Arc<dyn Link>,
+        failover: &FailoverOptions,
+        opts: &RouterOptions,
+    ) -> io::Result<Self> {
+        let link = FailoverLink::new(primary, backup, failover)?;
+        Self::new(Arc::new(link), opts)
+    }
+
+    /// Ask the peer router to mirror `topic` to us, at most once per
+    /// `period` if given. Only honoured by peers in pull mode; the
+    /// request is repeated every [`RESUBSCRIBE_INTERVAL`].
+    pub fn subscribe_remote(&self, topic: &str, period: Option<Duration>) -> io::Result<()> {
+        self.shared
+            .requested
+            .lock()
+            .unwrap()
+            .insert(topic.to_string(), period);
+        self.shared.link.send(&frame::subscribe(topic, period))
+    }
+
+    /// Withdraw a [`Router::subscribe_remote`] request.
+    pub fn unsubscribe_remote(&self, topic: &str) -> io::Result<()> {
+        self.shared.requested.lock().unwrap().remove(topic);
+        self.shared.link.send(&frame::unsubscribe(topic))
+    }
+
+    /// Topics the peer currently asks this router for, with their
+    /// minimum periods. Empty unless in pull mode.
+    pub fn remote_subscriptions(&self) -> Vec<(String, Option<Duration>)> {
+        let Some(pull) = &self.shared.pull else {
+            return Vec::new();
+        };
+        let mut subs: Vec<_> = pull
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(topic, sub)| (topic.clone(), sub.period))
+            .collect();
+        subs.sort();
+        subs
+    }
+
+    /// Handle [`Command`](super::command::Command)s the peer sends to
+    /// `topic`, replacing any previous handler. Handlers run on the
+    /// router's receive thread.
+    pub fn serve_commands<T, F>(&self, topic: &str, handler: F)
+    where
+        T: bytemuck::Pod,
+        F: Fn(T, CommandReply) + Send + Sync + 'static,
+    {
+        self.shared.commands.serve(topic, handler);
+    }
+
+    pub(super) fn command_channel(&self) -> (Arc<dyn Link>, Arc<Commands>) {
+        (
+            Arc::clone(&self.shared.link),
+            Arc::clone(&self.shared.commands),
+        )
+    }
+
+    /// Send the file at `path` to the peer, which stores it as `name`.
+    /// Blocks until the peer confirmed the whole file; an interrupted
+    /// transfer resumes where it stopped when sent again.
+    pub fn send_file(
+        &self,
+        path: impl AsRef<Path>,
+        name: &str,
+        opts: &TransferOptions,
+    ) -> io::Result<TransferReport> {
+        let data = std::fs::read(path)?;
+        self.shared
+            .transfers
+            .send(&*self.shared.link, &data, name, opts)
+    }
+
+    /// Accept files the peer sends into `dir`, calling `on_received`
+    /// with the final path of each verified file.
+    pub fn receive_files<F>(&self, dir: impl Into<PathBuf>, on_received: F)
+    where
+        F: Fn(&Path) + Send + Sync + 'static,
+    {
+        self.shared
+            .transfers
+            .receive_into(dir.into(), Arc::new(on_received));
+    }
+
+    /// The link this router drives.
+    pub fn link(&self) -> &Arc<dyn Link> {
+        &self.shared.link
+    }
+
+    pub fn stats(&self) -> LinkStats {
+        // Taken before the accounting lock, which flushing nests inside.
+        let (stored, expired, overflowed) = match &self.shared.store {
+            Some(store) => {
+                let store = store.lock().unwrap();
+                (store.len(), store.expired, store.overflowed)
+            }
+            None => (0, 0, 0),
+        };
+
+        let mut acc = self.shared.accounting.lock().unwrap();
+        acc.roll(Instant::now());
+        let topics: Vec<TopicBandwidth> = acc
+            .topics
+            .iter()
+            .map(|(topic, m)| TopicBandwidth {
+                topic: topic.clone(),
+                priority: m.priority,
+                frames: m.frames,
+                bytes: m.bytes,
+                dropped: m.dropped,
+                filtered: m.filtered,
+                flushed: m.flushed,
+                bytes_per_sec: m.rate,
+            })
+            .collect();
+
+        LinkStats {
+            link: self.shared.link.name(),
+            mtu: self.shared.link.mtu(),
+            budget: self
+                .shared
+                .shaper
+                .as_ref()
+                .map(|s| s.lock().unwrap().budget.bytes_per_sec),
+            tx_bytes: topics.iter().map(|t| t.bytes).sum(),
+            rx_frames: self.shared.rx_frames.load(Ordering::Relaxed),
+            rx_bytes: self.shared.rx_bytes.load(Ordering::Relaxed),
+            errors: self.shared.errors.load(Ordering::Relaxed),
+            stored,
+            expired,
+            overflowed,
+            topics,
+        }
+    }
+}
+
+impl Drop for Router {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.rx.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheds_lowest_priority_first() {
+        let start = Instant::now();
+        let window = Duration::from_millis(100);
+        // 100 bytes per window.
+        let mut shaper = Shaper::new(LinkBudget::new(1000).window(window), start);
+
+        // First window: no history yet, so it is first come first served
+        // up to the cap.
+        let mut sent = [0u32; 3];
+        for i in 0..6 {
+            for prio in 0..3u32 {
+                let t = start + Duration::from_millis(i);
+                if shaper.admit(prio, 10, t) {
+                    sent[prio as usize] += 1;
+                }
+            }
+        }
+        assert_eq!(sent.iter().sum::<u32>(), 10);
+
+        // Demand was 180 bytes for a 100 byte budget: priority 0 is shed
+        // and the remaining two share the window.
+        let mut sent = [0u32; 3];
+        for i in 0..6 {
+            for prio in 0..3u32 {
+                let t = start + window + Duration::from_millis(i);
+                if shaper.admit(prio, 10, t) {
+                    sent[prio as usize] += 1;
+                }
+            }
+        }
+        assert_eq!(sent, [0, 5, 5]);
+    }
+
+    struct MockLink {
+        sent: Mutex<Vec<Vec<u8>>>,
+        inbox: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl MockLink {
+        /// Sent data frames, without the router's control traffic.
+        fn data(&self) -> Vec<Vec<u8>> {
+            self.sent
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|f| frame::kind(f) == Some(frame::KIND_DATA))
+                .cloned()
+                .collect()
+        }
+    }
+
+    impl Link for MockLink {
+        fn send(&self, frame: &[u8]) -> io::Result<()> {
+            self.sent.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }
+
+        fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+            let next = self.inbox.lock().unwrap().pop();
+            if next.is_none() {
+                thread::sleep(timeout.min(Duration::from_millis(5)));
+            }
+            Ok(next)
+        }
+    }
+
+    #[test]
+    fn forwards_and_accounts_both_directions() {
+        let tx = crate::cleanup::TempTopic::new("/mq_ipc_test_router_tx_");
+        let dest = crate::cleanup::TempTopic::new("/mq_ipc_test_router_rx_");
+        let link = Arc::new(MockLink {
+            sent: Mutex::new(Vec::new()),
+            inbox: Mutex::new(Vec::new()),
+        });
+
+        let local = MqTopic::new(dest.name(), 4).unwrap();
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_cb = Arc::clone(&got);
+        local.subscribe(move |msg: Msg| {
+            got_cb
+                .lock()
+                .unwrap()
+                .push(msg.payload[..msg.hdr.len as usize].to_vec())
+        });
+
+        let router = Router::new(
+            link.clone(),
+            &RouterOptions::new()
+                .tx_topic(tx.name())
+                .maxmsg(4)
+                .topic_priority("/motor", 5),
+        )
+        .unwrap();
+
+        let mirror = Topic::<WirePacket>::new(tx.name(), 4).unwrap();
+        mirror
+            .publish(&frame::packet("/motor", &[1, 2, 3, 4]), 0, 0)
+            .unwrap();
+        link.inbox
+            .lock()
+            .unwrap()
+            .push(frame::encode(&frame::packet(dest.name(), &[7, 8, 9])));
+
+        for _ in 0..100 {
+            if !link.data().is_empty() && !got.lock().unwrap().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let sent = link.data();
+        assert_eq!(sent.len(), 1);
+        let pkt = frame::decode(&sent[0]).unwrap();
+        assert_eq!(pkt.topic_name(), "/motor");
+        assert_eq!(&pkt.data[..pkt.payload_len as usize], &[1, 2, 3, 4]);
+        assert_eq!(*got.lock().unwrap(), vec![vec![7, 8, 9]]);
+
+        let stats = router.stats();
+        assert_eq!(stats.tx_bytes, sent[0].len() as u64);
+        assert_eq!(stats.rx_frames, 1);
+        assert_eq!(stats.topics.len(), 1);
+        assert_eq!(stats.topics[0].topic, "/motor");
+        assert_eq!(stats.topics[0].priority, 5);
+        assert_eq!(stats.topics[0].frames, 1);
+    }
+
+    #[test]
+    fn namespaced_forward_survives_a_bad_payload_length() {
+        let tx = crate::cleanup::TempTopic::new("/mq_ipc_test_router_ns_");
+        let link = Arc::new(MockLink {
+            sent: Mutex::new(Vec::new()),
+            inbox: Mutex::new(Vec::new()),
+        });
+        let _router = Router::new(
+            link.clone(),
+            &RouterOptions::new()
+                .tx_topic(tx.name())
+                .maxmsg(4)
+                .namespace("r1"),
+        )
+        .unwrap();
+
+        let mirror = Topic::<WirePacket>::new(tx.name(), 4).unwrap();
+        let mut bad = frame::packet("/motor", &[1]);
+        bad.payload_len = u16::MAX;
+        mirror.publish(&bad, 0, 0).unwrap();
+        mirror
+            .publish(&frame::packet("/motor", &[2]), 0, 0)
+            .unwrap();
+
+        for _ in 0..100 {
+            if link.data().len() == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let sent = link.data();
+        assert_eq!(sent.len(), 2);
+        let pkt = frame::decode(&sent[1]).unwrap();
+        assert_eq!(&pkt.data[..pkt.payload_len as usize], &[2]);
+    }
+
+    #[test]
+    fn pull_mode_sends_only_requested_topics() {
+        let tx = crate::cleanup::TempTopic::new("/mq_ipc_test_router_pull_");
+        let link = Arc::new(MockLink {
+            sent: Mutex::new(Vec::new()),
+            inbox: Mutex::new(vec![frame::subscribe(
+                "/motor",
+                Some(Duration::from_secs(3600)),
+            )]),
+        });
+        let router = Router::new(
+            link.clone(),
+            &RouterOptions::new()
+                .tx_topic(tx.name())
+                .maxmsg(4)
+                .pull(true),
+        )
+        .unwrap();
+        for _ in 0..100 {
+            if !router.remote_subscriptions().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            router.remote_subscriptions(),
+            vec![("/motor".to_string(), Some(Duration::from_secs(3600)))]
+        );
+
+        let mirror = Topic::<WirePacket>::new(tx.name(), 4).unwrap();
+        for topic in ["/motor", "/imu", "/motor"] {
+            mirror.publish(&frame::packet(topic, &[1]), 0, 0).unwrap();
+        }
+        let filtered = |stats: &LinkStats| stats.topics.iter().map(|t| t.filtered).sum::<u64>();
+        for _ in 0..100 {
+            if filtered(&router.stats()) == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let stats = router.stats();
+        assert_eq!(filtered(&stats), 2);
+        let sent = link.data();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(frame::decode(&sent[0]).unwrap().topic_name(), "/motor");
+    }
+}
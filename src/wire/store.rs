@@ -0,0 +1,280 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Store-and-forward buffer for a [`Router`](super::router::Router).
+//!
+//! While the link is down, frames are queued here instead of being lost
+//! and flushed in order once it is back. Frames older than the age limit
+//! are discarded at flush time, and when the buffer is full the oldest
+//! frame makes room for the newest. A disk-backed store also survives a
+//! restart of the router process.
+//!
+//! Each record of the backing file is
+//! `stamp_ns: u64 LE | len: u16 LE | frame`, where `stamp_ns` is the
+//! wall-clock time the frame was stored.
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const RECORD_HEADER: usize = 10;
+
+/// Buffering policy for [`RouterOptions::store_and_forward`](super::router::RouterOptions::store_and_forward).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreOptions {
+    capacity: usize,
+    max_age: Duration,
+    path: Option<PathBuf>,
+}
+
+impl StoreOptions {
+    /// Keep up to `capacity` frames in memory.
+    pub fn memory(capacity: usize) -> Self {
+        StoreOptions {
+            capacity,
+            max_age: Duration::from_secs(30),
+            path: None,
+        }
+    }
+
+    /// Keep up to `capacity` frames, mirrored to the file at `path`.
+    /// Frames already in the file are loaded when the router starts.
+    pub fn disk(path: impl AsRef<Path>, capacity: usize) -> Self {
+        StoreOptions {
+            path: Some(path.as_ref().to_path_buf()),
+            ..Self::memory(capacity)
+        }
+    }
+
+    /// Discard frames stored longer ago than `age` instead of sending
+    /// them late (default 30 s).
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = age;
+        self
+    }
+}
+
+pub(crate) struct Store {
+    opts: StoreOptions,
+    queue: VecDeque<(u64, Vec<u8>)>,
+    file: Option<File>,
+    dirty: bool,
+    pub(crate) expired: u64,
+    pub(crate) overflowed: u64,
+}
+
+impl Store {
+    pub(crate) fn open(opts: &StoreOptions) -> io::Result<Self> {
+        let mut store = Store {
+            opts: opts.clone(),
+            queue: VecDeque::new(),
+            file: None,
+            dirty: false,
+            expired: 0,
+            overflowed: 0,
+        };
+        if let Some(path) = &opts.path {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+            let mut raw = Vec::new();
+            file.read_to_end(&mut raw)?;
+            store.queue = parse(&raw);
+            while store.queue.len() > store.opts.capacity {
+                store.queue.pop_front();
+                store.overflowed += 1;
+            }
+            store.file = Some(file);
+            store.persist()?;
+        }
+        Ok(store)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, frame: &[u8]) -> io::Result<()> {
+        if self.opts.capacity == 0 {
+            self.overflowed += 1;
+            return Ok(());
+        }
+        if self.queue.len() == self.opts.capacity {
+            self.queue.pop_front();
+            self.overflowed += 1;
+            self.dirty = true;
+        }
+        let stamp = now_ns();
+        self.queue.push_back((stamp, frame.to_vec()));
+
+        if self.dirty {
+            return self.persist();
+        }
+        if let Some(file) = &mut self.file {
+            file.seek(SeekFrom::End(0))?;
+            file.write_all(&record(stamp, frame))?;
+        }
+        Ok(())
+    }
+
+    /// Hand stored frames to `send` oldest first, skipping expired ones,
+    /// until the store is empty or `send` fails. Returns how many were sent.
+    pub(crate) fn drain<F>(&mut self, mut send: F) -> io::Result<usize>
+    where
+        F: FnMut(&[u8]) -> io::Result<()>,
+    {
+        if self.queue.is_empty() {
+            return Ok(0);
+        }
+        let max_age = self.opts.max_age.as_nanos().min(u64::MAX as u128) as u64;
+        let now = now_ns();
+        let mut sent = 0;
+        while let Some((stamp, frame)) = self.queue.front() {
+            if now.saturating_sub(*stamp) > max_age {
+                self.queue.pop_front();
+                self.expired += 1;
+                continue;
+            }
+            if send(frame).is_err() {
+                break;
+            }
+            self.queue.pop_front();
+            sent += 1;
+        }
+        self.persist()?;
+        Ok(sent)
+    }
+
+    fn persist(&mut self) -> io::Result<()> {
+        self.dirty = false;
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+        let mut raw = Vec::new();
+        for (stamp, frame) in &self.queue {
+            raw.extend_from_slice(&record(*stamp, frame));
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&raw)
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+fn record(stamp: u64, frame: &[u8]) -> Vec<u8> {
+    let len = frame.len().min(u16::MAX as usize);
+    let mut out = Vec::with_capacity(RECORD_HEADER + len);
+    out.extend_from_slice(&stamp.to_le_bytes());
+    out.extend_from_slice(&(len as u16).to_le_bytes());
+    out.extend_from_slice(&frame[..len]);
+    out
+}
+
+fn parse(mut raw: &[u8]) -> VecDeque<(u64, Vec<u8>)> {
+    let mut queue = VecDeque::new();
+    // A torn record at the end (crash mid-append) is dropped.
+    while raw.len() >= RECORD_HEADER {
+        let stamp = u64::from_le_bytes(raw[..8].try_into().unwrap());
+        let len = u16::from_le_bytes([raw[8], raw[9]]) as usize;
+        let Some(frame) = raw.get(RECORD_HEADER..RECORD_HEADER + len) else {
+            break;
+        };
+        queue.push_back((stamp, frame.to_vec()));
+        raw = &raw[RECORD_HEADER + len..];
+    }
+    queue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survives_restart_and_keeps_order() {
+        let path = std::env::temp_dir().join(format!("mq_ipc_test_store_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let opts = StoreOptions::disk(&path, 3);
+
+        {
+            let mut store = Store::open(&opts).unwrap();
+            for frame in [b"a", b"b", b"c", b"d"] {
+                store.push(frame).unwrap();
+            }
+            assert_eq!(store.overflowed, 1);
+        }
+
+        let mut store = Store::open(&opts).unwrap();
+        assert_eq!(store.len(), 3);
+
+        // The link dies again after one frame: the rest stays queued.
+        let mut sent = Vec::new();
+        let n = store
+            .drain(|f| {
+                if sent.is_empty() {
+                    sent.push(f.to_vec());
+                    Ok(())
+                } else {
+                    Err(io::ErrorKind::BrokenPipe.into())
+                }
+            })
+            .unwrap();
+        assert_eq!(n, 1);
+        assert_eq!(sent, vec![b"b".to_vec()]);
+
+        let mut store = Store::open(&opts).unwrap();
+        let mut rest = Vec::new();
+        store
+            .drain(|f| {
+                rest.push(f.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(rest, vec![b"c".to_vec(), b"d".to_vec()]);
+        assert!(store.is_empty());
+
+        let mut aged = Store::open(&StoreOptions::memory(4).max_age(Duration::ZERO)).unwrap();
+        aged.push(b"old").unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(aged.drain(|_| Ok(())).unwrap(), 0);
+        assert_eq!(aged.expired, 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
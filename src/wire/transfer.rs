@@ -0,0 +1,517 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Chunked, CRC-checked, resumable file transfer over a
+//! [`Router`](super::router::Router).
+//!
+//! The link that mirrors topics to an MCU is usually also the one used
+//! to push firmware to it.
+//! [`Router::send_file`](super::router::Router::send_file) offers a file
+//! to the peer, which must have enabled
+//! [`Router::receive_files`](super::router::Router::receive_files). The
+//! receiver answers with how much of the file it already holds from an
+//! earlier, interrupted attempt (`<name>.part` in its directory). The
+//! sender then sends the rest one chunk at a time, waiting for each
+//! acknowledgment and retransmitting on timeouts and CRC errors. Once
+//! the whole file checks out against the offered CRC, it is renamed to
+//! its final name.
+//!
+//! Chunks and the whole file are protected by [`frame::crc32`].
+
+#[cfg(feature = "callbacks")]
+use super::{
+    frame::{self, FileFrame},
+    link::Link,
+};
+use std::time::Duration;
+#[cfg(feature = "callbacks")]
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc, Arc, Mutex,
+    },
+};
+
+/// Ack status: chunk stored, `next` is the offset expected next.
+pub const ACK_OK: u8 = 0;
+/// Ack status: chunk failed its CRC; resend from `next`.
+pub const ACK_BAD_CHUNK: u8 = 1;
+/// Ack status: the file is complete and verified.
+pub const ACK_DONE: u8 = 2;
+/// Ack status: the file is complete but failed its CRC and was discarded.
+pub const ACK_FAILED: u8 = 3;
+
+/// Smallest chunk picked automatically; links with a tinier MTU
+/// fragment each chunk rather than waiting for an ack per few bytes.
+pub const MIN_AUTO_CHUNK: usize = 64;
+/// Largest chunk picked automatically.
+pub const MAX_AUTO_CHUNK: usize = 4096;
+
+/// Bytes of a chunk frame in front of the data.
+#[cfg(feature = "callbacks")]
+const CHUNK_HEADER: usize = 17;
+
+/// Settings for [`Router::send_file`](super::router::Router::send_file).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferOptions {
+    chunk_size: Option<usize>,
+    timeout: Duration,
+    retries: u32,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        TransferOptions {
+            chunk_size: None,
+            timeout: Duration::from_millis(500),
+            retries: 5,
+        }
+    }
+}
+
+impl TransferOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes of file data per chunk frame. By default chunk frames fill
+    /// the link's MTU, within [`MIN_AUTO_CHUNK`]..=[`MAX_AUTO_CHUNK`].
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        assert!(bytes > 0, "chunk size must be non-zero");
+        self.chunk_size = Some(bytes);
+        self
+    }
+
+    /// How long to wait for each answer before resending.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Resends of one frame before giving up.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+}
+
+/// Outcome of a completed [`Router::send_file`](super::router::Router::send_file).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransferReport {
+    /// File size.
+    pub bytes: u64,
+    /// Bytes the receiver already had, so were not sent again.
+    pub resumed_from: u64,
+    /// Chunk frames sent, retransmissions included.
+    pub chunks: u64,
+    /// Frames sent again after a timeout or CRC error.
+    pub retransmits: u64,
+}
+
+#[cfg(feature = "callbacks")]
+type Received = Arc<dyn Fn(&Path) + Send + Sync + 'static>;
+
+#[cfg(feature = "callbacks")]
+struct Incoming {
+    name: String,
+    size: u64,
+    crc: u32,
+    file: File,
+    len: u64,
+    running_crc: u32,
+}
+
+/// File transfer bookkeeping of one router: replies awaited by our
+/// senders and partial files being received.
+#[cfg(feature = "callbacks")]
+#[derive(Default)]
+pub(super) struct Transfers {
+    next_id: AtomicU32,
+    waiting: Mutex<HashMap<u32, mpsc::Sender<FileFrame>>>,
+    receiver: Mutex<Option<(PathBuf, Received)>>,
+    incoming: Mutex<HashMap<u32, Incoming>>,
+}
+
+#[cfg(feature = "callbacks")]
+impl Transfers {
+    pub(super) fn receive_into(&self, dir: PathBuf, on_received: Received) {
+        *self.receiver.lock().unwrap() = Some((dir, on_received));
+    }
+
+    pub(super) fn on_frame(&self, link: &dyn Link, bytes: &[u8]) {
+        let Some(f) = frame::decode_file(bytes) else {
+            return;
+        };
+        let reply = match f {
+            FileFrame::Offer {
+                id,
+                size,
+                crc,
+                restart,
+                name,
+            } => self.offer(id, size, crc, restart, &name),
+            FileFrame::Chunk {
+                id,
+                offset,
+                crc,
+                data,
+            } => self.chunk(id, offset, crc, &data),
+            FileFrame::Accept { id, .. } | FileFrame::Ack { id, .. } => {
+                if let Some(tx) = self.waiting.lock().unwrap().get(&id) {
+                    let _ = tx.send(f);
+                }
+                None
+            }
+        };
+        if let Some(reply) = reply {
+            let _ = link.send(&frame::encode_file(&reply));
+        }
+    }
+
+    fn offer(&self, id: u32, size: u64, crc: u32, restart: bool, name: &str) -> Option<FileFrame> {
+        let refused = FileFrame::Accept {
+            id,
+            refused: true,
+            offset: 0,
+            prefix_crc: 0,
+        };
+        let Some(dir) = self
+            .receiver
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(d, _)| d.clone())
+        else {
+            return Some(refused);
+        };
+        if !valid_name(name) {
+            return Some(refused);
+        }
+        match open_part(&dir, name, size, restart) {
+            Ok((file, len, running_crc)) => {
+                self.incoming.lock().unwrap().insert(
+                    id,
+                    Incoming {
+                        name: name.to_string(),
+                        size,
+                        crc,
+                        file,
+                        len,
+                        running_crc,
+                    },
+                );
+                Some(FileFrame::Accept {
+                    id,
+                    refused: false,
+                    offset: len,
+                    prefix_crc: running_crc,
+                })
+            }
+            Err(_) => Some(refused),
+        }
+    }
+
+    fn chunk(&self, id: u32, offset: u64, crc: u32, data: &[u8]) -> Option<FileFrame> {
+        let mut incoming = self.incoming.lock().unwrap();
+        let inc = incoming.get_mut(&id)?;
+        let ack = |status, next| Some(FileFrame::Ack { id, status, next });
+
+        if frame::crc32(data) != crc {
+            return ack(ACK_BAD_CHUNK, inc.len);
+        }
+        // Duplicates and gaps just tell the sender where we are.
+        if offset != inc.len || inc.len + data.len() as u64 > inc.size {
+            return ack(ACK_OK, inc.len);
+        }
+        if inc.file.write_all(data).is_err() {
+            return ack(ACK_BAD_CHUNK, inc.len);
+        }
+        inc.len += data.len() as u64;
+        inc.running_crc = frame::crc32_update(inc.running_crc, data);
+        if inc.len < inc.size {
+            return ack(ACK_OK, inc.len);
+        }
+
+        let inc = incoming.remove(&id)?;
+        drop(incoming);
+        let (dir, on_received) = self.receiver.lock().unwrap().clone()?;
+        let part = part_path(&dir, &inc.name);
+        if inc.running_crc != inc.crc || inc.file.sync_all().is_err() {
+            let _ = fs::remove_file(&part);
+            return ack(ACK_FAILED, 0);
+        }
+        let dest = dir.join(&inc.name);
+        if fs::rename(&part, &dest).is_err() {
+            return ack(ACK_FAILED, 0);
+        }
+        on_received(&dest);
+        ack(ACK_DONE, inc.size)
+    }
+
+    pub(super) fn send(
+        &self,
+        link: &dyn Link,
+        data: &[u8],
+        name: &str,
+        opts: &TransferOptions,
+    ) -> io::Result<TransferReport> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+        self.waiting.lock().unwrap().insert(id, tx);
+        let res = run_send(link, &rx, id, data, name, opts);
+        self.waiting.lock().unwrap().remove(&id);
+        res
+    }
+}
+
+#[cfg(feature = "callbacks")]
+fn run_send(
+    link: &dyn Link,
+    rx: &mpsc::Receiver<FileFrame>,
+    id: u32,
+    data: &[u8],
+    name: &str,
+    opts: &TransferOptions,
+) -> io::Result<TransferReport> {
+    let size = data.len() as u64;
+    let chunk_size = opts.chunk_size.unwrap_or_else(|| {
+        link.mtu()
+            .saturating_sub(CHUNK_HEADER)
+            .clamp(MIN_AUTO_CHUNK, MAX_AUTO_CHUNK)
+    });
+    let mut report = TransferReport {
+        bytes: size,
+        ..TransferReport::default()
+    };
+
+    let mut restart = false;
+    let mut offset = loop {
+        let offer = FileFrame::Offer {
+            id,
+            size,
+            crc: frame::crc32(data),
+            restart,
+            name: name.to_string(),
+        };
+        let accept = exchange(link, rx, &offer, opts, &mut report, |f| {
+            matches!(f, FileFrame::Accept { .. })
+        })?;
+        let FileFrame::Accept {
+            refused,
+            offset,
+            prefix_crc,
+            ..
+        } = accept
+        else {
+            unreachable!();
+        };
+        if refused {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("peer refused file '{name}'"),
+            ));
+        }
+        // A partial copy that is not a prefix of this file is restarted.
+        if offset <= size && frame::crc32(&data[..offset as usize]) == prefix_crc {
+            break offset;
+        }
+        restart = true;
+    };
+    report.resumed_from = offset;
+
+    loop {
+        let end = (offset as usize + chunk_size).min(data.len());
+        let piece = &data[offset as usize..end];
+        let chunk = FileFrame::Chunk {
+            id,
+            offset,
+            crc: frame::crc32(piece),
+            data: piece.to_vec(),
+        };
+        report.chunks += 1;
+        let ack = exchange(link, rx, &chunk, opts, &mut report, |f| {
+            matches!(f, FileFrame::Ack { .. })
+        })?;
+        let FileFrame::Ack { status, next, .. } = ack else {
+            unreachable!();
+        };
+        match status {
+            ACK_DONE => return Ok(report),
+            ACK_FAILED => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("'{name}' failed its CRC check on the peer"),
+                ))
+            }
+            ACK_BAD_CHUNK => report.retransmits += 1,
+            _ => {}
+        }
+        offset = next.min(size);
+    }
+}
+
+/// Send `f` and wait for an answer matching `want`, resending on timeout.
+#[cfg(feature = "callbacks")]
+fn exchange(
+    link: &dyn Link,
+    rx: &mpsc::Receiver<FileFrame>,
+    f: &FileFrame,
+    opts: &TransferOptions,
+    report: &mut TransferReport,
+    want: impl Fn(&FileFrame) -> bool,
+) -> io::Result<FileFrame> {
+    let bytes = frame::encode_file(f);
+    for attempt in 0..=opts.retries {
+        if attempt > 0 {
+            report.retransmits += 1;
+        }
+        link.send(&bytes)?;
+        loop {
+            match rx.recv_timeout(opts.timeout) {
+                Ok(reply) if want(&reply) => return Ok(reply),
+                // Late answers to earlier frames.
+                Ok(_) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "file transfer peer stopped answering",
+    ))
+}
+
+/// Names are plain file names inside the receive directory.
+#[cfg(feature = "callbacks")]
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
+
+#[cfg(feature = "callbacks")]
+fn part_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.part"))
+}
+
+/// Open `<name>.part`, returning it with its length and CRC.
+#[cfg(feature = "callbacks")]
+fn open_part(dir: &Path, name: &str, size: u64, restart: bool) -> io::Result<(File, u64, u32)> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(part_path(dir, name))?;
+    let mut held = Vec::new();
+    file.read_to_end(&mut held)?;
+    if restart || held.len() as u64 > size {
+        file.set_len(0)?;
+        held.clear();
+    }
+    Ok((file, held.len() as u64, frame::crc32(&held)))
+}
+
+#[cfg(all(test, feature = "callbacks"))]
+mod tests {
+    use super::*;
+
+    /// Delivers frames straight into the peer's `Transfers`, dropping
+    /// the first chunk once to exercise retransmission.
+    struct Direct {
+        peer: Mutex<Option<Arc<Transfers>>>,
+        back: Mutex<Option<Arc<Direct>>>,
+        drop_next_chunk: std::sync::atomic::AtomicBool,
+    }
+
+    impl Link for Direct {
+        fn send(&self, bytes: &[u8]) -> io::Result<()> {
+            if frame::kind(bytes) == Some(frame::KIND_FILE_CHUNK)
+                && self.drop_next_chunk.swap(false, Ordering::Relaxed)
+            {
+                return Ok(());
+            }
+            let peer = self.peer.lock().unwrap().clone().unwrap();
+            let back = self.back.lock().unwrap().clone().unwrap();
+            peer.on_frame(&*back, bytes);
+            Ok(())
+        }
+
+        fn recv(&self, _: Duration) -> io::Result<Option<Vec<u8>>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn resumes_partial_file() {
+        assert_eq!(frame::crc32(b"123456789"), 0xCBF4_3926);
+
+        let dir = std::env::temp_dir().join(format!("mq_ipc_test_transfer_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let data: Vec<u8> = (0..2000u32).map(|i| (i * 7) as u8).collect();
+        // An earlier attempt got the first 700 bytes across.
+        fs::write(dir.join("fw.bin.part"), &data[..700]).unwrap();
+
+        let sender = Arc::new(Transfers::default());
+        let receiver = Arc::new(Transfers::default());
+        let got = Arc::new(Mutex::new(Vec::new()));
+        let got_cb = Arc::clone(&got);
+        receiver.receive_into(
+            dir.clone(),
+            Arc::new(move |p: &Path| got_cb.lock().unwrap().push(p.to_path_buf())),
+        );
+
+        let to_rx = Arc::new(Direct {
+            peer: Mutex::new(Some(Arc::clone(&receiver))),
+            back: Mutex::new(None),
+            drop_next_chunk: true.into(),
+        });
+        let to_tx = Arc::new(Direct {
+            peer: Mutex::new(Some(Arc::clone(&sender))),
+            back: Mutex::new(Some(Arc::clone(&to_rx))),
+            drop_next_chunk: false.into(),
+        });
+        *to_rx.back.lock().unwrap() = Some(Arc::clone(&to_tx));
+
+        let opts = TransferOptions::new()
+            .chunk_size(256)
+            .timeout(Duration::from_millis(20));
+        let report = sender.send(&*to_rx, &data, "fw.bin", &opts).unwrap();
+        assert_eq!(report.resumed_from, 700);
+        assert_eq!(report.chunks, 6);
+        assert_eq!(report.retransmits, 1);
+        assert_eq!(fs::read(dir.join("fw.bin")).unwrap(), data);
+        assert!(!dir.join("fw.bin.part").exists());
+        assert_eq!(*got.lock().unwrap(), vec![dir.join("fw.bin")]);
+
+        let err = sender.send(&*to_rx, &data, "../evil", &opts).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        // Direct links form a cycle; break it.
+        *to_rx.back.lock().unwrap() = None;
+        *to_tx.back.lock().unwrap() = None;
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
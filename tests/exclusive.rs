@@ -0,0 +1,83 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Exclusive ownership only bites across processes, so the intruder runs
+//! as a testkit child.
+
+use mq_ipc::{registry::ExclusiveOwner, testkit, MqTopic, Msg, TopicOptions};
+use std::{io, time::Duration};
+
+#[test]
+fn other_process_cannot_publish_on_owned_topic() {
+    if let Some(env) = testkit::child() {
+        let name = env.topic_name("cmd");
+        let topic = MqTopic::new(&name, 4).unwrap();
+        let err = topic.publish(&Msg::new(1, &[]), 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        let owner = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<ExclusiveOwner>())
+            .expect("ExclusiveOwner error");
+        assert_eq!(owner.pid, std::os::unix::process::parent_id());
+
+        let opts = TopicOptions::new(4).exclusive(true);
+        assert!(MqTopic::with_options(&name, &opts).is_err());
+        env.ready().unwrap();
+        return;
+    }
+
+    let mut harness = testkit::Harness::new("other_process_cannot_publish_on_owned_topic").unwrap();
+    let opts = TopicOptions::new(4).exclusive(true);
+    let topic = MqTopic::with_options(&harness.topic_name("cmd"), &opts).unwrap();
+    topic.publish(&Msg::new(1, &[]), 0).unwrap();
+
+    harness.spawn("intruder").unwrap();
+    harness.wait_ready(1, Duration::from_secs(10)).unwrap();
+    let statuses = harness.wait_all(Duration::from_secs(10)).unwrap();
+    assert!(statuses.iter().all(|s| s.success()));
+}
+
+#[test]
+fn ownership_outlives_the_first_of_two_handles() {
+    if let Some(env) = testkit::child() {
+        let opts = TopicOptions::new(4).exclusive(true);
+        let err = MqTopic::with_options(&env.topic_name("cmd"), &opts)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        env.ready().unwrap();
+        return;
+    }
+
+    let mut harness = testkit::Harness::new("ownership_outlives_the_first_of_two_handles").unwrap();
+    let opts = TopicOptions::new(4).exclusive(true);
+    let first = MqTopic::with_options(&harness.topic_name("cmd"), &opts).unwrap();
+    let second = MqTopic::with_options(&harness.topic_name("cmd"), &opts).unwrap();
+    drop(first);
+
+    harness.spawn("intruder").unwrap();
+    harness.wait_ready(1, Duration::from_secs(10)).unwrap();
+    let statuses = harness.wait_all(Duration::from_secs(10)).unwrap();
+    assert!(statuses.iter().all(|s| s.success()));
+    second.publish(&Msg::new(1, &[]), 0).unwrap();
+}
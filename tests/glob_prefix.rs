@@ -0,0 +1,75 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! `MultiSubscriber::from_glob` under `MQ_IPC_PREFIX`. Setting the
+//! variable is process-wide, so this test has a binary of its own.
+
+#![cfg(feature = "callbacks")]
+
+use mq_ipc::{
+    cleanup::TempTopic,
+    defaults,
+    multi::{MultiSubscriber, MQUEUE_FS},
+    MqTopic, Msg,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[test]
+fn glob_patterns_are_written_without_the_prefix() {
+    // Globbing lists queues through the mqueue filesystem.
+    if !std::path::Path::new(MQUEUE_FS).is_dir() {
+        eprintln!("{MQUEUE_FS} is not mounted; skipping");
+        return;
+    }
+    let prefix = format!("mqipc_glob{}.", std::process::id());
+    // SAFETY: the only test in this binary, set before any other thread
+    // reads the environment.
+    unsafe { std::env::set_var(defaults::PREFIX_VAR, &prefix) };
+
+    let tmp = TempTopic::new("/mq_ipc_test_glob_");
+    let topic = MqTopic::new(tmp.name(), 4).unwrap();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&received);
+    let multi = MultiSubscriber::from_glob("/mq_ipc_test_glob_*", move |name: &str, msg: Msg| {
+        sink.lock()
+            .unwrap()
+            .push((name.to_string(), msg.hdr.msg_type));
+    })
+    .unwrap();
+
+    topic.publish(&Msg::new(7, &[]), 0).unwrap();
+    for _ in 0..50 {
+        if !received.lock().unwrap().is_empty() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    drop(multi);
+
+    let resolved = format!("/{prefix}{}", &tmp.name()[1..]);
+    assert_eq!(*received.lock().unwrap(), vec![(resolved, 7)]);
+}
@@ -0,0 +1,59 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! A handle that only publishes must not receive: in another process its
+//! worker would take the messages meant for the real subscribers.
+
+use mq_ipc::{MqTopic, Msg};
+use std::{ffi::CString, thread, time::Duration};
+
+fn queued(name: &str) -> i64 {
+    let cname = CString::new(name).unwrap();
+    let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
+    unsafe {
+        let mqd = libc::mq_open(cname.as_ptr(), libc::O_RDONLY);
+        assert!(mqd != -1);
+        libc::mq_getattr(mqd, &mut attr);
+        libc::mq_close(mqd);
+    }
+    attr.mq_curmsgs as i64
+}
+
+#[test]
+fn publishing_handles_leave_messages_queued() {
+    let name = format!("/mq_ipc_test_lazy_worker_{}", std::process::id());
+    {
+        let publisher = MqTopic::new(&name, 4).expect("failed to create topic");
+        let other = MqTopic::new(&name, 4).expect("failed to open topic");
+        publisher.publish(&Msg::new(1, b"a"), 0).unwrap();
+        other.publish(&Msg::new(1, b"b"), 0).unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(queued(&name), 2);
+    }
+
+    let cname = CString::new(name).unwrap();
+    unsafe {
+        libc::mq_unlink(cname.as_ptr());
+    }
+}
@@ -0,0 +1,123 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! The `pid_loop` example run end to end: topics, keyed state with a
+//! deadline, the tune service and the plant's heartbeat watchdog.
+
+#[allow(dead_code)]
+#[path = "../examples/pid_loop/nodes.rs"]
+mod nodes;
+
+use nodes::{Gains, Names};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn loop_settles_and_recovers_from_silent_nodes() {
+    let names = Names::new(&format!("/mq_ipc_test_pid_{}", std::process::id()));
+    let queues = nodes::queue_names(&names);
+    let _guards: Vec<_> = queues
+        .iter()
+        .map(|q| mq_ipc::cleanup::TempTopic::with_name(q))
+        .collect();
+
+    let spawn = |f: fn(&Names, &AtomicBool)| {
+        let (names, stop) = (names.clone(), Arc::new(AtomicBool::new(false)));
+        let stop_clone = Arc::clone(&stop);
+        (stop, thread::spawn(move || f(&names, &stop_clone)))
+    };
+    let (stop_controller, controller) = spawn(|n, stop| {
+        nodes::run_controller(n, Gains::DEFAULT, stop).unwrap();
+    });
+    let (stop_setpoint, setpoint) = spawn(|n, stop| {
+        nodes::run_setpoint(n, &[1.0], Duration::from_secs(60), stop).unwrap();
+    });
+    // Let the controller subscribe before the watchdog starts its clock.
+    thread::sleep(Duration::from_millis(100));
+
+    let stop_plant = Arc::new(AtomicBool::new(false));
+    let plant = {
+        let (names, stop) = (names.clone(), Arc::clone(&stop_plant));
+        thread::spawn(move || nodes::run_plant(&names, &stop).unwrap())
+    };
+
+    let stop_monitor = Arc::new(AtomicBool::new(false));
+    let monitor = {
+        let (names, stop) = (names.clone(), Arc::clone(&stop_monitor));
+        thread::spawn(move || {
+            nodes::run_monitor(&names, Duration::from_millis(200), &stop).unwrap()
+        })
+    };
+
+    thread::sleep(Duration::from_millis(2500));
+
+    // Retune over the service and read the gains back.
+    let soft = Gains {
+        kp: 8.0,
+        ki: 0.5,
+        kd: 4.0,
+    };
+    let status = nodes::tune(&names, soft).unwrap();
+    assert_eq!(status.gains, soft);
+    assert!((status.setpoint - 1.0).abs() < f32::EPSILON);
+    assert!(status.error.abs() < 0.1, "{status:?}");
+
+    stop_monitor.store(true, Ordering::Relaxed);
+    let history = monitor.join().unwrap();
+    assert!(history.len() >= 5, "{history:?}");
+    assert!(history.windows(2).all(|w| w[0].cycles <= w[1].cycles));
+
+    // A silent plant is a missed deadline for the controller.
+    stop_plant.store(true, Ordering::Relaxed);
+    let report = plant.join().unwrap();
+    assert!((report.state.position - 1.0).abs() < 0.1, "{report:?}");
+    assert_eq!(report.estops, 0);
+    thread::sleep(nodes::STATE_DEADLINE * 3);
+    let status = nodes::tune(&names, soft).unwrap();
+    assert_eq!(status.deadline_misses, 1, "{status:?}");
+    assert_eq!(status.effort, 0.0);
+
+    // A silent controller trips the plant's watchdog, which zeroes the effort.
+    stop_plant.store(false, Ordering::Relaxed);
+    let plant = {
+        let (names, stop) = (names.clone(), Arc::clone(&stop_plant));
+        thread::spawn(move || nodes::run_plant(&names, &stop).unwrap())
+    };
+    thread::sleep(Duration::from_millis(300));
+    stop_controller.store(true, Ordering::Relaxed);
+    controller.join().unwrap();
+    thread::sleep(nodes::HEARTBEAT_TIMEOUT * 3);
+    stop_plant.store(true, Ordering::Relaxed);
+    let report = plant.join().unwrap();
+    assert_eq!(report.estops, 1, "{report:?}");
+    assert_eq!(report.effort, 0.0);
+
+    stop_setpoint.store(true, Ordering::Relaxed);
+    setpoint.join().unwrap();
+}
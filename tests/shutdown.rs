@@ -0,0 +1,92 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Shutdown flips process-wide state, so it lives in its own test binary.
+
+use mq_ipc::{shutdown, MqTopic, Msg};
+use std::{
+    ffi::CString,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn signal_stops_spin_and_workers() {
+    let topic_name = format!("/mq_ipc_test_shutdown_{}", std::process::id());
+
+    {
+        let topic = MqTopic::new(&topic_name, 4).expect("failed to create topic");
+        topic.subscribe(|_| {});
+
+        shutdown::install().expect("failed to install handlers");
+        assert!(!shutdown::requested());
+        assert_eq!(
+            shutdown::wait_timeout(Duration::from_millis(10)).unwrap(),
+            None
+        );
+
+        // The signal lands on another thread while this one is blocked.
+        let raiser = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(100));
+            unsafe { libc::raise(libc::SIGTERM) };
+        });
+        let start = Instant::now();
+        let err = topic.recv_timeout(Duration::from_secs(10)).unwrap_err();
+        assert!(shutdown::is_shutdown(&err), "{err}");
+        assert!(start.elapsed() < Duration::from_secs(2));
+        raiser.join().unwrap();
+        assert!(shutdown::requested());
+
+        // The worker stops on the signal alone, before anyone spins: what
+        // is published now stays queued.
+        thread::sleep(Duration::from_millis(50));
+        topic.publish(&Msg::new(1, &[]), 0).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(topic.stats().depth.current, 1);
+        assert_eq!(topic.try_recv().unwrap().unwrap().hdr.msg_type, 1);
+        assert!(shutdown::is_shutdown(&topic.try_recv().unwrap_err()));
+
+        let reason = shutdown::spin().expect("spin failed");
+        assert_eq!(reason.signal, Some(libc::SIGTERM));
+        assert!(shutdown::requested());
+
+        // Give the worker a moment to consume its wake-up.
+        thread::sleep(Duration::from_millis(50));
+        drop(topic);
+
+        let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
+        let cname = CString::new(topic_name.as_str()).unwrap();
+        unsafe {
+            let mqd = libc::mq_open(cname.as_ptr(), libc::O_RDONLY);
+            assert!(mqd != -1);
+            libc::mq_getattr(mqd, &mut attr);
+            libc::mq_close(mqd);
+        }
+        assert_eq!(attr.mq_curmsgs, 0, "no stray wake-up left in the queue");
+    }
+
+    let cname = CString::new(topic_name).unwrap();
+    unsafe {
+        libc::mq_unlink(cname.as_ptr());
+    }
+}
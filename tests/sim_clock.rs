@@ -0,0 +1,94 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Components driven by a simulated clock. Installing a default clock is
+//! process-wide, so this lives in its own test binary.
+
+use mq_ipc::{
+    cleanup::TempTopic,
+    clock::{self, SimClock},
+    testkit::{MockTransport, ScriptedPublisher},
+    MqTopic, Msg, TopicOptions,
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+// Each test installs its own clock; run them one at a time.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn ttl_and_send_stamps_follow_the_default_clock() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let sim = Arc::new(SimClock::new(Duration::from_secs(100)));
+    clock::set_default_clock(sim.clone());
+
+    let tmp = TempTopic::new("/mq_ipc_test_sim_ttl_");
+    let opts = TopicOptions::new(4).ttl(Duration::from_secs(1));
+    let topic = MqTopic::with_options(tmp.name(), &opts).unwrap();
+
+    topic.publish(&Msg::new(1, &[]), 0).unwrap();
+    let msg = topic.try_recv().unwrap().unwrap();
+    assert_eq!(msg.sent_at(), Some(Duration::from_secs(100)));
+
+    // Wall-clock time barely moves; only simulated time expires it.
+    topic.publish(&Msg::new(2, &[]), 0).unwrap();
+    sim.advance(Duration::from_secs(2));
+    assert!(topic.try_recv().unwrap().is_none());
+    assert_eq!(topic.stats().expired, 1);
+}
+
+#[test]
+fn scripted_publisher_follows_simulated_time() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let sim = Arc::new(SimClock::new(Duration::ZERO));
+    clock::set_default_clock(sim.clone());
+
+    let mock = Arc::new(MockTransport::new());
+    let player = ScriptedPublisher::new()
+        .at(Duration::ZERO, Msg::new(1, &[]), 0)
+        .at(Duration::from_secs(3600), Msg::new(2, &[]), 0)
+        .spawn(mock.clone());
+
+    let sent = || mock.published().len();
+    wait_for(|| sent() == 1);
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(sent(), 1);
+
+    // An hour of script passes without waiting for it.
+    sim.advance(Duration::from_secs(3600));
+    assert_eq!(player.join().unwrap().unwrap(), 2);
+    assert_eq!(sent(), 2);
+}
+
+fn wait_for(cond: impl Fn() -> bool) {
+    for _ in 0..100 {
+        if cond() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    panic!("condition not met");
+}
@@ -0,0 +1,67 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! Cross-process delivery through the testkit harness.
+
+use mq_ipc::{testkit, MqTopic, Msg};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn publisher_child_delivers_messages() {
+    if let Some(env) = testkit::child() {
+        assert_eq!(env.role(), "publisher");
+        let topic = MqTopic::new(&env.topic_name("data"), 8).unwrap();
+        env.ready().unwrap();
+        for i in 0..5u8 {
+            topic.publish(&Msg::new(1, &[i]), 0).unwrap();
+        }
+        return;
+    }
+
+    let mut harness = testkit::Harness::new("publisher_child_delivers_messages").unwrap();
+    let topic = MqTopic::new(&harness.topic_name("data"), 8).unwrap();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_clone = Arc::clone(&received);
+    topic.subscribe(move |msg| {
+        received_clone.lock().unwrap().push(msg.data()[0]);
+    });
+
+    harness.spawn("publisher").unwrap();
+    harness.wait_ready(1, Duration::from_secs(10)).unwrap();
+
+    let statuses = harness.wait_all(Duration::from_secs(10)).unwrap();
+    assert!(statuses.iter().all(|s| s.success()));
+
+    for _ in 0..100 {
+        if received.lock().unwrap().len() == 5 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(*received.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+}
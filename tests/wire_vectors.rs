@@ -0,0 +1,52 @@
+/*
+MIT License
+Copyright (c) 2025 Felipe Neves
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in
+all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+THE SOFTWARE.
+*/
+
+//! The checked-in wire vectors match what the library encodes.
+//!
+//! After an intentional change to the wire format, regenerate them with
+//! `MQ_IPC_BLESS=1 cargo test --test wire_vectors`.
+
+use mq_ipc::wire::conformance;
+use std::path::Path;
+
+#[test]
+fn golden_frames_match() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors/wire.txt");
+    let vectors = conformance::vectors();
+    if std::env::var_os("MQ_IPC_BLESS").is_some() {
+        std::fs::write(&path, conformance::fixture(&vectors)).unwrap();
+    }
+
+    for v in &vectors {
+        conformance::check(v).unwrap_or_else(|e| panic!("{}: {e}", v.name));
+    }
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        conformance::check_fixture(&text).unwrap(),
+        Vec::<String>::new()
+    );
+
+    let mut torn = vectors[0].frame.clone();
+    torn.pop();
+    assert!(conformance::validate(&torn).is_err());
+}